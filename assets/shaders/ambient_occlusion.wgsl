@@ -0,0 +1,48 @@
+#import bevy_volumetric::types::{CHUNK_SZ, VoxelBuffer, AmbientOcclusionParams}
+
+@group(0) @binding(0) var<uniform> params: AmbientOcclusionParams;
+@group(0) @binding(1) var<storage, read> in_voxels: VoxelBuffer;
+@group(0) @binding(2) var occlusion: texture_storage_3d<rgba8unorm, write>;
+
+// Function to get a flat index for a given position in the 3D grid.
+fn get_flat_index(pos: vec3<i32>) -> u32 {
+    return u32(pos.x + pos.y * CHUNK_SZ + pos.z * CHUNK_SZ * CHUNK_SZ);
+}
+
+// Function to get the density of a voxel, treating everything outside the chunk as open sky.
+fn density(pos: vec3<i32>) -> f32 {
+    if (any(pos < vec3<i32>(0)) || any(pos >= vec3<i32>(CHUNK_SZ))) {
+        return 0.0;
+    }
+    return clamp(in_voxels.data[get_flat_index(pos)].density, 0.0, 1.0);
+}
+
+// Each invocation averages the densities around the centre of one occlusion texel.
+@compute @workgroup_size(4, 4, 4)
+fn main(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
+    let texel = vec3<i32>(invocation_id);
+
+    // Skip invocations outside of the occlusion texture.
+    if (any(texel >= vec3<i32>(textureDimensions(occlusion)))) {
+        return;
+    }
+
+    let cell_size = i32(params.cell_size);
+    let radius = i32(params.radius);
+    let centre = texel * cell_size + cell_size / 2;
+
+    var sum = 0.0;
+    var count = 0.0;
+    for (var z = -radius; z <= radius; z++) {
+        for (var y = -radius; y <= radius; y++) {
+            for (var x = -radius; x <= radius; x++) {
+                sum += density(centre + vec3<i32>(x, y, z));
+                count += 1.0;
+            }
+        }
+    }
+
+    // Store the visibility, so that materials can multiply their ambient light by it.
+    let visibility = 1.0 - sum / count;
+    textureStore(occlusion, texel, vec4<f32>(visibility, visibility, visibility, 1.0));
+}
@@ -0,0 +1,65 @@
+#import bevy_volumetric::types::{MAX_MATERIALS, VoxelBuffer, ChunkAnalysisCounters, ChunkAnalysisParams}
+
+@group(0) @binding(0) var<uniform> params: ChunkAnalysisParams;
+@group(0) @binding(1) var<storage, read> in_voxels: VoxelBuffer;
+@group(0) @binding(2) var<storage, read_write> counters: ChunkAnalysisCounters;
+
+var<workgroup> local_materials: array<atomic<u32>, MAX_MATERIALS>;
+var<workgroup> local_min_key: atomic<u32>;
+var<workgroup> local_max_key: atomic<u32>;
+var<workgroup> local_voxel_count: atomic<u32>;
+
+// Function to map a density to a key whose unsigned order is the order of the densities.
+fn order_key(density: f32) -> u32 {
+    let bits = bitcast<u32>(density);
+    if ((bits & 0x80000000u) != 0u) {
+        return ~bits;
+    }
+    return bits | 0x80000000u;
+}
+
+// Accumulates the counters of the workgroup's voxels in workgroup memory first, then adds them to
+// the chunk's counters at once.
+@compute @workgroup_size(256)
+fn main(
+    @builtin(global_invocation_id) invocation_id: vec3<u32>,
+    @builtin(local_invocation_index) local_index: u32,
+) {
+    if (local_index < MAX_MATERIALS) {
+        atomicStore(&local_materials[local_index], 0u);
+    }
+    if (local_index == 0u) {
+        atomicStore(&local_min_key, 0u);
+        atomicStore(&local_max_key, 0u);
+        atomicStore(&local_voxel_count, 0u);
+    }
+    workgroupBarrier();
+
+    let index = invocation_id.x;
+    if (index < params.voxel_count) {
+        let voxel = in_voxels.data[index];
+        let key = order_key(voxel.density);
+        atomicMax(&local_min_key, ~key);
+        atomicMax(&local_max_key, key);
+        atomicAdd(&local_voxel_count, 1u);
+        if (voxel.density >= params.iso_level) {
+            atomicAdd(&local_materials[min(voxel.flags, MAX_MATERIALS - 1u)], 1u);
+        }
+    }
+    workgroupBarrier();
+
+    if (local_index < MAX_MATERIALS) {
+        let count = atomicLoad(&local_materials[local_index]);
+        if (count > 0u) {
+            atomicAdd(&counters.material_counts[local_index], count);
+        }
+    }
+    if (local_index == 0u) {
+        let voxel_count = atomicLoad(&local_voxel_count);
+        if (voxel_count > 0u) {
+            atomicMax(&counters.min_key, atomicLoad(&local_min_key));
+            atomicMax(&counters.max_key, atomicLoad(&local_max_key));
+            atomicAdd(&counters.voxel_count, voxel_count);
+        }
+    }
+}
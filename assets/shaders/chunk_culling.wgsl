@@ -0,0 +1,138 @@
+#import bevy_volumetric::types::{ChunkCullingView, ChunkCullingParams, ChunkDrawArgs, ChunkDrawArgsBuffer, MAX_MATERIALS}
+#ifdef CLUSTERS
+#import bevy_volumetric::types::{ClusterBoundBuffer, CLUSTER_TRIANGLES}
+#endif
+
+// The atomics buffer of the meshed chunk, read once the meshing is done.
+struct MeshCounters {
+    vertices_head: u32,
+    indices_head: u32,
+    material_index_counts: array<u32, MAX_MATERIALS>,
+};
+
+@group(0) @binding(0) var<uniform> view: ChunkCullingView;
+@group(0) @binding(1) var depth_pyramid: texture_2d<f32>;
+
+@group(1) @binding(0) var<uniform> params: ChunkCullingParams;
+@group(1) @binding(1) var<storage, read> counters: MeshCounters;
+@group(1) @binding(2) var<storage, read_write> args: ChunkDrawArgsBuffer;
+#ifdef CLUSTERS
+@group(1) @binding(3) var<storage, read> bounds: ClusterBoundBuffer;
+#endif
+
+// Function to test whether the box from `lo` to `hi`, in world space, is at least partly within
+// the half spaces of the frustum.
+fn in_frustum(lo: vec3<f32>, hi: vec3<f32>) -> bool {
+    for (var i = 0u; i < 6u; i++) {
+        let half_space = view.frustum[i];
+        // The corner of the box farthest along the normal of the half space.
+        let corner = select(lo, hi, half_space.xyz > vec3<f32>(0.0));
+        if (dot(half_space.xyz, corner) + half_space.w < 0.0) {
+            return false;
+        }
+    }
+    return true;
+}
+
+// Function to test whether the box from `lo` to `hi`, in world space, is hidden behind the depth
+// of the main pass: its nearest depth is farther than the farthest depth of the texels of
+// the depth pyramid covering it, read from the mip where it covers at most two by two texels.
+// Depths are reversed, so the farthest is the smallest.
+fn is_occluded(lo: vec3<f32>, hi: vec3<f32>) -> bool {
+    var uv_min = vec2<f32>(1.0);
+    var uv_max = vec2<f32>(0.0);
+    var nearest = 0.0;
+    for (var corner = 0u; corner < 8u; corner++) {
+        let pos = select(lo, hi, vec3<bool>((corner & 1u) != 0u, (corner & 2u) != 0u, (corner & 4u) != 0u));
+        let clip = view.clip_from_world * vec4<f32>(pos, 1.0);
+        // Boxes reaching behind the camera are never occluded.
+        if (clip.w <= 0.0) {
+            return false;
+        }
+        let ndc = clip.xyz / clip.w;
+        let uv = ndc.xy * vec2<f32>(0.5, -0.5) + 0.5;
+        uv_min = min(uv_min, uv);
+        uv_max = max(uv_max, uv);
+        nearest = max(nearest, ndc.z);
+    }
+    uv_min = clamp(uv_min, vec2<f32>(0.0), vec2<f32>(1.0));
+    uv_max = clamp(uv_max, vec2<f32>(0.0), vec2<f32>(1.0));
+
+    let extent = (uv_max - uv_min) * vec2<f32>(view.pyramid_size);
+    let mip = min(u32(ceil(log2(max(max(extent.x, extent.y), 1.0)))), view.pyramid_mips - 1u);
+    let mip_size = vec2<i32>(textureDimensions(depth_pyramid, mip));
+    let first = clamp(vec2<i32>(uv_min * vec2<f32>(mip_size)), vec2<i32>(0), mip_size - 1);
+    let last = clamp(vec2<i32>(uv_max * vec2<f32>(mip_size)), first, min(first + 1, mip_size - 1));
+
+    var farthest = 1.0;
+    for (var y = first.y; y <= last.y; y++) {
+        for (var x = first.x; x <= last.x; x++) {
+            farthest = min(farthest, textureLoad(depth_pyramid, vec2<i32>(x, y), i32(mip)).r);
+        }
+    }
+    return nearest < farthest;
+}
+
+// Function to test the two phases of the culling against the box from `lo` to `hi`, in world
+// space: the frustum, then the depth pyramid.
+fn is_visible(lo: vec3<f32>, hi: vec3<f32>) -> bool {
+    if (!in_frustum(lo, hi)) {
+        return false;
+    }
+    return view.occlusion == 0u || !is_occluded(lo, hi);
+}
+
+// Function to get the number of indices meshed in `segment` of the index buffer.
+fn segment_index_count(segment: u32) -> u32 {
+    var index_count = counters.indices_head;
+    if (params.segment_count > 1u) {
+        index_count = counters.material_index_counts[min(segment, MAX_MATERIALS - 1u)];
+    }
+    return min(index_count, params.segment_size);
+}
+
+// Writes the args of one draw of the chunk, a cluster or a segment of its index buffer, with an
+// instance if it survives the culling.
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
+    let draw = invocation_id.x;
+    if (draw >= params.draw_count) {
+        return;
+    }
+
+    var out = ChunkDrawArgs(0u, 0u, 0u, 0, 0u);
+#ifdef CLUSTERS
+    let bound = bounds.data[draw];
+    let segment = draw / params.clusters_per_segment;
+    out.first_index = segment * params.segment_size
+        + (draw % params.clusters_per_segment) * CLUSTER_TRIANGLES * 3u;
+    out.index_count = bound.triangle_count * 3u;
+
+    // Phase one: the chunk, then the sphere of the cluster, within the frustum. The normal cone
+    // drops the clusters facing away from the camera.
+    let center = (params.world_from_local * vec4<f32>(bound.center, 1.0)).xyz;
+    let radius = bound.radius * params.max_scale;
+    var visible = out.index_count > 0u && in_frustum(params.aabb_min, params.aabb_max);
+    for (var i = 0u; visible && i < 6u; i++) {
+        let half_space = view.frustum[i];
+        visible = dot(half_space.xyz, center) + half_space.w >= -radius;
+    }
+    if (visible) {
+        let to_apex = bound.cone_apex - params.camera;
+        visible = dot(to_apex, to_apex) == 0.0
+            || dot(normalize(to_apex), bound.cone_axis) < bound.cone_cutoff;
+    }
+
+    // Phase two: the box around the sphere of the cluster, in front of the depth pyramid.
+    if (visible && view.occlusion != 0u) {
+        visible = !is_occluded(center - radius, center + radius);
+    }
+#else
+    out.first_index = draw * params.segment_size;
+    out.index_count = segment_index_count(draw);
+    let visible = out.index_count > 0u && is_visible(params.aabb_min, params.aabb_max);
+#endif
+
+    out.instance_count = u32(visible);
+    args.data[draw] = out;
+}
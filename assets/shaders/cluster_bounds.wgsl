@@ -0,0 +1,107 @@
+#import bevy_volumetric::types::{VertexBuffer, IndexBuffer, ClusterBoundsParams, ClusterBound, ClusterBoundBuffer, MAX_MATERIALS, CLUSTER_TRIANGLES}
+
+// The atomics buffer of the meshed entity, read once the meshing is done.
+struct MeshCounters {
+    vertices_head: u32,
+    indices_head: u32,
+    material_index_counts: array<u32, MAX_MATERIALS>,
+};
+
+@group(0) @binding(0) var<uniform> params: ClusterBoundsParams;
+@group(0) @binding(1) var<storage, read> vertices: VertexBuffer;
+@group(0) @binding(2) var<storage, read> indices: IndexBuffer;
+@group(0) @binding(3) var<storage, read> counters: MeshCounters;
+@group(0) @binding(4) var<storage, read_write> bounds: ClusterBoundBuffer;
+
+// Function to load the position of the vertex at `index` in the index buffer.
+fn position(index: u32) -> vec3<f32> {
+    return vertices.data[min(indices.data[index], arrayLength(&vertices.data) - 1u)];
+}
+
+// Function to compute the unnormalized normal of the triangle starting at `index`.
+fn triangle_normal(index: u32) -> vec3<f32> {
+    let a = position(index);
+    return cross(position(index + 1u) - a, position(index + 2u) - a);
+}
+
+// Computes the bounding sphere and normal cone of one cluster of triangles.
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
+    let cluster = invocation_id.x;
+    if (cluster >= arrayLength(&bounds.data)) {
+        return;
+    }
+
+    // The triangles of the cluster, within the segment of the index buffer it belongs to.
+    let segment = cluster / params.clusters_per_segment;
+    var index_count = counters.indices_head;
+    if (params.segment_count > 1u) {
+        index_count = counters.material_index_counts[min(segment, MAX_MATERIALS - 1u)];
+    }
+    index_count = min(index_count, params.segment_size);
+    let first = (cluster % params.clusters_per_segment) * CLUSTER_TRIANGLES * 3u;
+    let triangle_count = min(index_count - min(first, index_count), CLUSTER_TRIANGLES * 3u) / 3u;
+    let start = segment * params.segment_size + first;
+    let end = start + triangle_count * 3u;
+
+    var bound = ClusterBound(vec3<f32>(0.0), 0.0, vec3<f32>(0.0), 2.0, vec3<f32>(0.0, 0.0, 1.0), triangle_count);
+    if (triangle_count == 0u) {
+        bounds.data[cluster] = bound;
+        return;
+    }
+
+    // The sphere around the center of the bounding box of the cluster.
+    var lo = vec3<f32>(3.4e38);
+    var hi = vec3<f32>(-3.4e38);
+    for (var i = start; i < end; i++) {
+        let p = position(i);
+        lo = min(lo, p);
+        hi = max(hi, p);
+    }
+    let center = (lo + hi) * 0.5;
+    var radius = 0.0;
+    for (var i = start; i < end; i++) {
+        radius = max(radius, distance(position(i), center));
+    }
+    bound.center = center;
+    bound.radius = radius;
+
+    // The cone around the average normal of the triangles.
+    var normal_sum = vec3<f32>(0.0);
+    for (var i = start; i < end; i += 3u) {
+        let normal = triangle_normal(i);
+        if (dot(normal, normal) > 0.0) {
+            normal_sum += normalize(normal);
+        }
+    }
+    if (dot(normal_sum, normal_sum) == 0.0) {
+        bounds.data[cluster] = bound;
+        return;
+    }
+    let axis = normalize(normal_sum);
+
+    var min_dot = 1.0;
+    var max_t = 0.0;
+    for (var i = start; i < end; i += 3u) {
+        var normal = triangle_normal(i);
+        if (dot(normal, normal) == 0.0) {
+            continue;
+        }
+        normal = normalize(normal);
+        let dn = dot(axis, normal);
+        min_dot = min(min_dot, dn);
+        // Move the apex back along the axis until it is behind the plane of every triangle.
+        if (dn > 0.0) {
+            max_t = max(max_t, dot(center - position(i), normal) / dn);
+        }
+    }
+
+    // The cone of triangles facing too many directions would hardly cull the cluster anyway.
+    if (min_dot > 0.1) {
+        bound.cone_axis = axis;
+        bound.cone_apex = center - axis * max_t;
+        bound.cone_cutoff = sqrt(1.0 - min_dot * min_dot);
+    }
+
+    bounds.data[cluster] = bound;
+}
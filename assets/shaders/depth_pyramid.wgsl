@@ -0,0 +1,57 @@
+#ifdef FROM_DEPTH
+#ifdef MULTISAMPLED
+@group(0) @binding(0) var source: texture_depth_multisampled_2d;
+#else
+@group(0) @binding(0) var source: texture_depth_2d;
+#endif
+#else
+@group(0) @binding(0) var source: texture_2d<f32>;
+#endif
+@group(0) @binding(1) var mip: texture_storage_2d<r32float, write>;
+
+// Function to load the depth of the source at `coords`, the farthest of its samples if it is
+// multisampled. Depths are reversed, so the farthest is the smallest.
+fn load_depth(coords: vec2<i32>) -> f32 {
+#ifdef FROM_DEPTH
+#ifdef MULTISAMPLED
+    var depth = 1.0;
+    for (var i = 0u; i < textureNumSamples(source); i++) {
+        depth = min(depth, textureLoad(source, coords, i32(i)));
+    }
+    return depth;
+#else
+    return textureLoad(source, coords, 0);
+#endif
+#else
+    return textureLoad(source, coords, 0).r;
+#endif
+}
+
+// Writes a texel of the mip, the depth texture's own for the first mip or the farthest depth of
+// the texels it covers in the previous mip.
+@compute @workgroup_size(8, 8, 1)
+fn main(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
+    let size = textureDimensions(mip);
+    if (any(invocation_id.xy >= size)) {
+        return;
+    }
+    let coords = vec2<i32>(invocation_id.xy);
+
+#ifdef FROM_DEPTH
+    let depth = load_depth(coords);
+#else
+    let source_size = vec2<i32>(textureDimensions(source));
+    // The last row and column of a source of odd size are covered by the last texels.
+    let odd = (source_size % 2) == vec2<i32>(1) & coords == vec2<i32>(size) - 1;
+    let extent = select(vec2<i32>(2), vec2<i32>(3), odd);
+
+    var depth = 1.0;
+    for (var y = 0; y < extent.y; y++) {
+        for (var x = 0; x < extent.x; x++) {
+            depth = min(depth, load_depth(min(coords * 2 + vec2<i32>(x, y), source_size - 1)));
+        }
+    }
+#endif
+
+    textureStore(mip, coords, vec4<f32>(depth, 0.0, 0.0, 0.0));
+}
@@ -0,0 +1,106 @@
+#import bevy_volumetric::types::{CHUNK_SZ, VoxelBuffer, SeedBuffer, DistanceFieldParams}
+
+@group(0) @binding(0) var<uniform> params: DistanceFieldParams;
+@group(0) @binding(1) var<storage, read> in_voxels: VoxelBuffer;
+@group(0) @binding(2) var<storage, read> in_seeds: SeedBuffer;
+@group(0) @binding(3) var<storage, read_write> out_seeds: SeedBuffer;
+@group(0) @binding(4) var distances: texture_storage_3d<r32float, write>;
+
+// Marks the voxels no seed was found for yet.
+const NO_SEED: u32 = 0xffffffffu;
+
+// Function to get a flat index for a given position in the 3D grid.
+fn get_flat_index(pos: vec3<i32>) -> u32 {
+    return u32(pos.x + pos.y * CHUNK_SZ + pos.z * CHUNK_SZ * CHUNK_SZ);
+}
+
+// Function to get the position of the voxel at a flat index, the inverse of `get_flat_index`.
+fn get_position(index: u32) -> vec3<i32> {
+    let i = i32(index);
+    return vec3<i32>(i % CHUNK_SZ, (i / CHUNK_SZ) % CHUNK_SZ, i / (CHUNK_SZ * CHUNK_SZ));
+}
+
+fn in_chunk(pos: vec3<i32>) -> bool {
+    return all(pos >= vec3<i32>(0)) && all(pos < vec3<i32>(CHUNK_SZ));
+}
+
+fn is_inside(pos: vec3<i32>) -> bool {
+    return in_voxels.data[get_flat_index(pos)].density >= params.iso_level;
+}
+
+// Seeds the voxels with a face neighbour on the other side of the surface with themselves.
+@compute @workgroup_size(4, 4, 4)
+fn seed(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
+    let pos = vec3<i32>(invocation_id);
+    if (!in_chunk(pos)) {
+        return;
+    }
+
+    let inside = is_inside(pos);
+    var found = NO_SEED;
+    for (var axis = 0; axis < 3; axis++) {
+        for (var side = -1; side <= 1; side += 2) {
+            var neighbour = pos;
+            neighbour[axis] += side;
+            if (in_chunk(neighbour) && is_inside(neighbour) != inside) {
+                found = get_flat_index(pos);
+            }
+        }
+    }
+    out_seeds.data[get_flat_index(pos)] = found;
+}
+
+// Keeps the closest of the seeds of the 27 voxels `params.step` voxels apart around each voxel.
+@compute @workgroup_size(4, 4, 4)
+fn jump(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
+    let pos = vec3<i32>(invocation_id);
+    if (!in_chunk(pos)) {
+        return;
+    }
+
+    let jump_step = i32(params.step);
+    var closest = NO_SEED;
+    var closest_distance = 0x7fffffff;
+    for (var z = -1; z <= 1; z++) {
+        for (var y = -1; y <= 1; y++) {
+            for (var x = -1; x <= 1; x++) {
+                let neighbour = pos + vec3<i32>(x, y, z) * jump_step;
+                if (!in_chunk(neighbour)) {
+                    continue;
+                }
+                let candidate = in_seeds.data[get_flat_index(neighbour)];
+                if (candidate == NO_SEED) {
+                    continue;
+                }
+                let offset = get_position(candidate) - pos;
+                let candidate_distance = dot(offset, offset);
+                if (candidate_distance < closest_distance) {
+                    closest = candidate;
+                    closest_distance = candidate_distance;
+                }
+            }
+        }
+    }
+    out_seeds.data[get_flat_index(pos)] = closest;
+}
+
+// Writes the distance to the closest seed, half a voxel further as the surface lies between the
+// seeds on either side of it, negative inside.
+@compute @workgroup_size(4, 4, 4)
+fn resolve(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
+    let pos = vec3<i32>(invocation_id);
+    if (!in_chunk(pos)) {
+        return;
+    }
+
+    var signed_distance = params.max_distance;
+    let closest = in_seeds.data[get_flat_index(pos)];
+    if (closest != NO_SEED) {
+        let offset = vec3<f32>(get_position(closest) - pos);
+        signed_distance = min(length(offset) + 0.5, params.max_distance);
+    }
+    if (is_inside(pos)) {
+        signed_distance = -signed_distance;
+    }
+    textureStore(distances, pos, vec4<f32>(signed_distance, 0.0, 0.0, 0.0));
+}
@@ -0,0 +1,128 @@
+#import bevy_volumetric::types::{CHUNK_SZ, Voxel, VoxelBuffer, ErosionParams}
+
+@group(0) @binding(0) var<uniform> params: ErosionParams;
+@group(0) @binding(1) var<storage, read> in_voxels: VoxelBuffer;
+@group(0) @binding(2) var<storage, read_write> out_voxels: VoxelBuffer;
+
+const EROSION_THERMAL: u32 = 0u;
+const EROSION_HYDRAULIC: u32 = 1u;
+
+// The five neighbours thermal erosion moves material down to.
+const THERMAL_OFFSETS = array<vec3<i32>, 5>(
+    vec3<i32>( 0, -1,  0),
+    vec3<i32>( 1, -1,  0),
+    vec3<i32>(-1, -1,  0),
+    vec3<i32>( 0, -1,  1),
+    vec3<i32>( 0, -1, -1),
+);
+
+// The four downhill neighbours hydraulic erosion carries surface material to.
+const HYDRAULIC_OFFSETS = array<vec3<i32>, 4>(
+    vec3<i32>( 1, -1,  0),
+    vec3<i32>(-1, -1,  0),
+    vec3<i32>( 0, -1,  1),
+    vec3<i32>( 0, -1, -1),
+);
+
+// Function to get a flat index for a given position in the 3D grid.
+fn get_flat_index(pos: vec3<i32>) -> u32 {
+    return u32(pos.x + pos.y * CHUNK_SZ + pos.z * CHUNK_SZ * CHUNK_SZ);
+}
+
+// Function to check whether a position is inside the simulated region.
+fn in_region(pos: vec3<i32>) -> bool {
+    return all(pos >= vec3<i32>(params.region_min)) && all(pos < vec3<i32>(params.region_max));
+}
+
+// Function to get the density of a voxel, treating everything outside the chunk as empty.
+fn density(pos: vec3<i32>) -> f32 {
+    if (any(pos < vec3<i32>(0)) || any(pos >= vec3<i32>(CHUNK_SZ))) {
+        return 0.0;
+    }
+    return in_voxels.data[get_flat_index(pos)].density;
+}
+
+// Function to get the material thermal erosion moves from `source` to `destination` this step.
+fn thermal_transfer(source: vec3<i32>, destination: vec3<i32>) -> f32 {
+    if (!in_region(source) || !in_region(destination)) {
+        return 0.0;
+    }
+    return params.rate * max(density(source) - density(destination) - params.talus, 0.0) / 5.0;
+}
+
+// Function to get the density change of the voxel at `pos` from thermal erosion. Every transfer is
+// evaluated identically by its source and its destination, so material is conserved.
+fn thermal(pos: vec3<i32>) -> f32 {
+    var offsets = THERMAL_OFFSETS;
+    var delta = 0.0;
+    for (var i = 0u; i < 5u; i++) {
+        delta = delta - thermal_transfer(pos, pos + offsets[i]);
+        delta = delta + thermal_transfer(pos - offsets[i], pos);
+    }
+    return delta;
+}
+
+// Function to get the downhill neighbour surface material at `pos` is carried to, or `pos` itself
+// if there is none.
+fn hydraulic_target(pos: vec3<i32>) -> vec3<i32> {
+    var offsets = HYDRAULIC_OFFSETS;
+    var downhill = pos;
+    var lowest = density(pos);
+    for (var i = 0u; i < 4u; i++) {
+        let neighbour = pos + offsets[i];
+        if (in_region(neighbour) && density(neighbour) < lowest) {
+            downhill = neighbour;
+            lowest = density(neighbour);
+        }
+    }
+    return downhill;
+}
+
+// Function to get the material hydraulic erosion removes from the voxel at `pos` this step.
+fn hydraulic_erosion(pos: vec3<i32>) -> f32 {
+    let exposed = density(pos) >= 0.5 && density(pos + vec3<i32>(0, 1, 0)) < 0.5;
+    if (!in_region(pos) || !exposed) {
+        return 0.0;
+    }
+
+    let downhill = hydraulic_target(pos);
+    if (all(downhill == pos)) {
+        return 0.0;
+    }
+    return params.rate * max(density(pos) - density(downhill) - params.talus, 0.0) * 0.5;
+}
+
+// Function to get the density change of the voxel at `pos` from hydraulic erosion.
+fn hydraulic(pos: vec3<i32>) -> f32 {
+    var offsets = HYDRAULIC_OFFSETS;
+    var delta = -hydraulic_erosion(pos);
+    for (var i = 0u; i < 4u; i++) {
+        let source = pos - offsets[i];
+        if (all(hydraulic_target(source) == pos)) {
+            delta = delta + hydraulic_erosion(source);
+        }
+    }
+    return delta;
+}
+
+// Simulates one erosion step, reading `in_voxels` and writing every voxel to `out_voxels`.
+@compute @workgroup_size(8, 8, 8)
+fn main(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
+    let pos = vec3<i32>(invocation_id);
+    if (any(pos >= vec3<i32>(CHUNK_SZ))) {
+        return;
+    }
+
+    var voxel = in_voxels.data[get_flat_index(pos)];
+
+    if (in_region(pos)) {
+        if (params.kind == EROSION_HYDRAULIC) {
+            voxel.density = voxel.density + hydraulic(pos);
+        } else {
+            voxel.density = voxel.density + thermal(pos);
+        }
+        voxel.density = clamp(voxel.density, 0.0, 1.0);
+    }
+
+    out_voxels.data[get_flat_index(pos)] = voxel;
+}
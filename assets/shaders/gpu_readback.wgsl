@@ -1,295 +1,938 @@
-// Define a structure representing a voxel with flags and density.
-struct Voxel {
-    flags: u32, // Stores flags related to the voxel (e.g., active or not).
-    density: f32, // Stores the density value of the voxel.
-};
-
-// Define a structure representing a buffer containing an array of voxels.
-struct VoxelBuffer {
-    data: array<Voxel>, // Array of voxels.
-};
-
-// Define a structure representing a buffer containing an array of vertex positions.
-struct VertexBuffer {
-    data: array<vec3<f32>>, // Array of vertex positions.
-};
-
-// Define a structure representing a buffer containing an array of vertex normals.
-struct NormalBuffer {
-    data: array<vec3<f32>>, // Array of vertex normals.
-};
-
-// Define a structure representing a buffer containing an array of indices.
-struct IndexBuffer {
-    data: array<u32>, // Array of indices.
-};
-
-// Define a structure representing a buffer containing an array of texture coordinates.
-struct UvBuffer {
-    data: array<vec2<f32>>, // Array of UV coordinates.
-};
-
-// Define a structure containing atomic counters for vertices and indices.
-struct Atomics {
-    vertices_head: atomic<u32>, // Atomic counter for vertex indices.
-    indices_head: atomic<u32>, // Atomic counter for indices.
-};
-
-// Define a structure representing a lookup table for edge cases.
-struct EdgeTable {
-    data: array<u32, 256>, // Array of edge data for 256 configurations.
-};
-
-// Define a structure representing a lookup table for triangle cases.
-struct TriangleTable {
-    data: array<array<i32, 16>, 256>, // Array of triangle data for 256 configurations, each with up to 16 entries.
-};
-
-// Bindings for various buffers and tables used in the shader.
-@group(0) @binding(0) var<storage, read_write> uniform_edge_table: EdgeTable;
-@group(0) @binding(1) var<storage, read_write> uniform_tri_table: TriangleTable;
-@group(0) @binding(2) var<storage, read_write> in_voxels: VoxelBuffer;
-@group(0) @binding(3) var<storage, read_write> global_atomics: Atomics;
-@group(0) @binding(4) var<storage, read_write> out_vertices: VertexBuffer;
-@group(0) @binding(5) var<storage, read_write> out_normals: NormalBuffer;
-@group(0) @binding(6) var<storage, read_write> out_indices: IndexBuffer;
-@group(0) @binding(7) var<storage, read_write> out_uvs: UvBuffer;
-
-const chunk_sz = 32; // Define the size of a chunk.
-
-// Function to get a flat index for a given position in the 3D grid.
-fn get_flat_index(pos: vec3<i32>) -> u32 {
-    return u32(pos.x + pos.y * chunk_sz + pos.z * chunk_sz * chunk_sz);
+#import bevy_volumetric::types::{CHUNK_SZ, MAX_MATERIALS, MAX_DETAIL_REGIONS}
+#import bevy_volumetric::bindings::{uniform_edge_table, uniform_tri_table, in_voxels, out_vertices, out_normals, out_indices, out_uvs, out_tangents, out_emissives, dual_contouring, iso_surface, adaptive_resolution}
+#ifdef CELL_SLOTS
+#import bevy_volumetric::bindings::cell_slots
+#else
+#import bevy_volumetric::bindings::global_atomics
+#endif
+#import bevy_volumetric::voxel::{get_volume_size, get_voxel, get_voxel_density, interp_vertex, vertex_emissive, pack_emissive}
+#import bevy_volumetric::normals::encode_normal
+#ifdef SEAMLESS_NORMALS
+#import bevy_volumetric::voxel::gradient_normal
+#endif
+
+// Function to get the tangent of a triangle along increasing u of its UVs, orthogonalised against
+// `normal`, with the handedness of the bitangent in w.
+fn triangle_tangent(
+    v0: vec3<f32>, v1: vec3<f32>, v2: vec3<f32>,
+    uv0: vec2<f32>, uv1: vec2<f32>, uv2: vec2<f32>,
+    normal: vec3<f32>,
+) -> vec4<f32> {
+    let e1 = v1 - v0;
+    let e2 = v2 - v0;
+    let d1 = uv1 - uv0;
+    let d2 = uv2 - uv0;
+
+    let r = d1.x * d2.y - d2.x * d1.y;
+    let n = normal / max(length(normal), 1e-8);
+    let t = (e1 * d2.y - e2 * d1.y) / r;
+    let b = (e2 * d1.x - e1 * d2.x) / r;
+
+    // Gram-Schmidt orthogonalise the tangent, falling back to any axis for degenerate triangles.
+    let tangent = t - n * dot(n, t);
+    if (abs(r) < 1e-8 || length(tangent) < 1e-8) {
+        return vec4<f32>(1.0, 0.0, 0.0, 1.0);
+    }
+
+    let handedness = select(1.0, -1.0, dot(cross(n, tangent), b) < 0.0);
+    return vec4<f32>(normalize(tangent), handedness);
 }
 
-// Function to get the density of a voxel at a given position.
-fn get_voxel_density(pos: vec3<i32>) -> f32 {
-    var density: f32 = 0.0;
-    if (pos.x >= 0 && pos.x < chunk_sz
-     && pos.y >= 0 && pos.y < chunk_sz
-     && pos.z >= 0 && pos.z < chunk_sz) {
-        density = in_voxels.data[get_flat_index(pos)].density;
+#ifdef CELL_SLOTS
+// The slot of the cell meshed by this invocation, and the vertices and indices emitted into it.
+var<private> cell_slot: u32;
+var<private> cell_vertices: u32;
+var<private> cell_indices: u32;
+#endif
+
+// Function to check whether a primitive of `vertices` vertices and `indices` indices fits in the
+// slot of the cell. Primitives allocated from the atomics are bounded by `allocate_indices`.
+fn fits_cell_slot(vertices: u32, indices: u32) -> bool {
+#ifdef FIXED_OUTPUT_SLOTS
+    return cell_vertices + vertices <= #{CELL_SLOT_VERTICES}u && cell_indices + indices <= #{CELL_SLOT_INDICES}u;
+#else
+#ifdef PREFIX_SUM_COUNT
+    // The count pass only counts the primitives of the cell, the scatter pass emits them.
+    cell_vertices += vertices;
+    cell_indices += indices;
+    return false;
+#else
+#ifdef PREFIX_SUM_SCATTER
+    // Cells past the end of the output buffers are left no room by the prefix sum.
+    let slot = cell_slots.data[cell_slot];
+    return cell_vertices + vertices <= slot.vertices && cell_indices + indices <= slot.indices;
+#else
+    return true;
+#endif
+#endif
+#endif
+}
+
+// Function to allocate `count` indices for a primitive of `material`, or return `NO_INDICES` if
+// there is no space left for them.
+const NO_INDICES: u32 = 0xffffffffu;
+fn allocate_indices(count: u32, material: u32) -> u32 {
+#ifdef FIXED_OUTPUT_SLOTS
+    let start = cell_slot * #{CELL_SLOT_INDICES}u + cell_indices;
+    cell_indices += count;
+    return start;
+#else
+#ifdef CELL_SLOTS
+    // The prefix sum resolved where the primitives of each cell start in the output buffers.
+    let start = cell_slots.data[cell_slot].index_offset + cell_indices;
+    cell_indices += count;
+    return start;
+#else
+    let start = atomicAdd(&global_atomics.indices_head, count);
+#ifdef MATERIAL_SPLIT
+    // The index buffer is split into one segment per material, filled from its start.
+    let segment = arrayLength(&out_indices.data) / MAX_MATERIALS;
+    let m = min(material, MAX_MATERIALS - 1u);
+    let offset = atomicAdd(&global_atomics.material_index_counts[m], count);
+    if (offset + count > segment) {
+        return NO_INDICES;
     }
-    return density;
+    return m * segment + offset;
+#else
+    return start;
+#endif
+#endif
+#endif
 }
 
-// Function to interpolate between two vertices based on their densities.
-fn interp_vertex(p1: vec3<f32>, p2: vec3<f32>, v1: f32, v2: f32) -> vec3<f32> {
-    let mu = (0.5 - v1) / (v2 - v1);
-    return p1 + mu * (p2 - p1);
+// Function to allocate `count` vertices, returning the first one.
+fn allocate_vertices(count: u32) -> u32 {
+#ifdef FIXED_OUTPUT_SLOTS
+    let start = cell_slot * #{CELL_SLOT_VERTICES}u + cell_vertices;
+    cell_vertices += count;
+    return start;
+#else
+#ifdef CELL_SLOTS
+    let start = cell_slots.data[cell_slot].vertex_offset + cell_vertices;
+    cell_vertices += count;
+    return start;
+#else
+    return atomicAdd(&global_atomics.vertices_head, count);
+#endif
+#endif
 }
 
-// Main compute shader entry point with a workgroup size of 8x8x8.
-@compute @workgroup_size(8, 8, 8)
-fn main(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
+// Function to get the index referring to the vertex at `vertex_idx`. Indices in fixed slots refer
+// to the vertices of their own slot, and are rebased once the slots are compacted.
+fn vertex_index(vertex_idx: u32) -> u32 {
+#ifdef FIXED_OUTPUT_SLOTS
+    return vertex_idx - cell_slot * #{CELL_SLOT_VERTICES}u;
+#else
+    return vertex_idx;
+#endif
+}
 
-    let pos = vec3<i32>(invocation_id); // Convert invocation ID to integer position.
-    let voxel = in_voxels.data[get_flat_index(pos)]; // Get the voxel data for the current position.
+// Function to get the material of a voxel, from its flags. Materials are only looked up when the
+// index buffer is split by material.
+fn voxel_material(pos: vec3<i32>) -> u32 {
+#ifdef MATERIAL_SPLIT
+    return min(get_voxel(pos).flags, MAX_MATERIALS - 1u);
+#else
+    return 0u;
+#endif
+}
 
-    // If the voxel is active (flags == 0).
-    if (voxel.flags == 0u) {
+// Function to get the material of the cell at `cell`: the one of its densest corner.
+fn cell_material(cell: vec3<i32>) -> u32 {
+#ifndef MATERIAL_SPLIT
+    return 0u;
+#else
+    var densest = cell;
+    var density = get_voxel_density(cell);
+    for (var corner = 1u; corner < 8u; corner++) {
+        let p = cell + vec3<i32>(i32(corner & 1u), i32((corner >> 1u) & 1u), i32((corner >> 2u) & 1u));
+        let d = get_voxel_density(p);
+        if (d > density) {
+            densest = p;
+            density = d;
+        }
+    }
+    return voxel_material(densest);
+#endif
+}
+
+// Function to get the normal of the vertex at `v` of a face with the flat normal `normal`, which
+// follows the density gradient across the faces of the chunk with `SeamlessNormals`.
+fn vertex_normal(v: vec3<f32>, normal: vec3<f32>) -> vec3<f32> {
+#ifdef SEAMLESS_NORMALS
+    return gradient_normal(v, normal);
+#else
+    return normal;
+#endif
+}
+
+// Function to get the triplanar UV of the vertex at `v` of a face with the flat normal `normal`:
+// its position in voxels projected along the axis the face mostly faces, mirrored on the faces
+// looking down the axis so that textures are never seen flipped.
+fn triplanar_uv(v: vec3<f32>, normal: vec3<f32>) -> vec2<f32> {
+    let n = abs(normal);
+    let s = sign(normal);
+    if (n.x >= n.y && n.x >= n.z) {
+        return vec2<f32>(-v.z * select(1.0, s.x, s.x != 0.0), -v.y);
+    }
+    if (n.y >= n.z) {
+        return vec2<f32>(v.x, v.z * select(1.0, s.y, s.y != 0.0));
+    }
+    return vec2<f32>(v.x * select(1.0, s.z, s.z != 0.0), -v.y);
+}
+
+// Function to store a triangle in the output buffers.
+fn emit_triangle(v0: vec3<f32>, v1: vec3<f32>, v2: vec3<f32>, material: u32) {
+    if (!fits_cell_slot(3u, 3u)) {
+        return;
+    }
+    let start_indices_idx = allocate_indices(3u, material); // Allocate space for 3 indices.
+    if (start_indices_idx == NO_INDICES) {
+        return;
+    }
+    let start_vert_idx = allocate_vertices(3u); // Allocate space for 3 vertices.
+    let start_index = vertex_index(start_vert_idx);
+
+    out_vertices.data[start_vert_idx + 0u] = v0; // Store the first vertex.
+    out_vertices.data[start_vert_idx + 1u] = v1; // Store the second vertex.
+    out_vertices.data[start_vert_idx + 2u] = v2; // Store the third vertex.
+
+    out_indices.data[start_indices_idx + 0u] = start_index + 0u; // Store the first index.
+    out_indices.data[start_indices_idx + 1u] = start_index + 1u; // Store the second index.
+    out_indices.data[start_indices_idx + 2u] = start_index + 2u; // Store the third index.
+
+    let normal = cross(v0 - v1, v0 - v2); // Calculate the normal for the triangle.
+    out_normals.data[start_vert_idx + 0u] = encode_normal(vertex_normal(v0, normal)); // Store the normal for the first vertex.
+    out_normals.data[start_vert_idx + 1u] = encode_normal(vertex_normal(v1, normal)); // Store the normal for the second vertex.
+    out_normals.data[start_vert_idx + 2u] = encode_normal(vertex_normal(v2, normal)); // Store the normal for the third vertex.
+
+    // Store the triplanar UV coordinates of the triangle vertices.
+    let uv0 = triplanar_uv(v0, normal);
+    let uv1 = triplanar_uv(v1, normal);
+    let uv2 = triplanar_uv(v2, normal);
+    out_uvs.data[start_vert_idx + 0u] = uv0;
+    out_uvs.data[start_vert_idx + 1u] = uv1;
+    out_uvs.data[start_vert_idx + 2u] = uv2;
+
+    // Store the tangent of the UV parameterisation for the triangle vertices.
+    let tangent = triangle_tangent(v0, v1, v2, uv0, uv1, uv2, normal);
+    out_tangents.data[start_vert_idx + 0u] = tangent;
+    out_tangents.data[start_vert_idx + 1u] = tangent;
+    out_tangents.data[start_vert_idx + 2u] = tangent;
+
+    // Store the emissive colors of the triangle vertices, interpolated from the voxels.
+    out_emissives.data[start_vert_idx + 0u] = pack_emissive(vertex_emissive(v0));
+    out_emissives.data[start_vert_idx + 1u] = pack_emissive(vertex_emissive(v1));
+    out_emissives.data[start_vert_idx + 2u] = pack_emissive(vertex_emissive(v2));
+}
+
+// Function to store a counter-clockwise quad in the output buffers.
+fn emit_quad(v0: vec3<f32>, v1: vec3<f32>, v2: vec3<f32>, v3: vec3<f32>, material: u32) {
+    if (!fits_cell_slot(4u, 6u)) {
+        return;
+    }
+    let start_indices_idx = allocate_indices(6u, material); // Allocate space for 6 indices.
+    if (start_indices_idx == NO_INDICES) {
+        return;
+    }
+    let start_vert_idx = allocate_vertices(4u); // Allocate space for 4 vertices.
+    let start_index = vertex_index(start_vert_idx);
+
+    out_vertices.data[start_vert_idx + 0u] = v0; // Store the first vertex.
+    out_vertices.data[start_vert_idx + 1u] = v1; // Store the second vertex.
+    out_vertices.data[start_vert_idx + 2u] = v2; // Store the third vertex.
+    out_vertices.data[start_vert_idx + 3u] = v3; // Store the fourth vertex.
+
+    let normal = cross(v0 - v1, v0 - v2); // Calculate the normal for the face.
+#ifdef MESHING_MARCHING_CUBES
+    // Marching cubes only emits quads for the blocks of flagged voxels, which stay flat.
+    let encoded_normal = encode_normal(normal);
+    out_normals.data[start_vert_idx + 0u] = encoded_normal; // Store the normal for the first vertex.
+    out_normals.data[start_vert_idx + 1u] = encoded_normal; // Store the normal for the second vertex.
+    out_normals.data[start_vert_idx + 2u] = encoded_normal; // Store the normal for the third vertex.
+    out_normals.data[start_vert_idx + 3u] = encoded_normal; // Store the normal for the fourth vertex.
+#else
+    out_normals.data[start_vert_idx + 0u] = encode_normal(vertex_normal(v0, normal)); // Store the normal for the first vertex.
+    out_normals.data[start_vert_idx + 1u] = encode_normal(vertex_normal(v1, normal)); // Store the normal for the second vertex.
+    out_normals.data[start_vert_idx + 2u] = encode_normal(vertex_normal(v2, normal)); // Store the normal for the third vertex.
+    out_normals.data[start_vert_idx + 3u] = encode_normal(vertex_normal(v3, normal)); // Store the normal for the fourth vertex.
+#endif
+
+    // Store the triplanar UV coordinates of the face vertices, projected the same for the whole
+    // face.
+    let uv0 = triplanar_uv(v0, normal);
+    let uv1 = triplanar_uv(v1, normal);
+    let uv2 = triplanar_uv(v2, normal);
+    out_uvs.data[start_vert_idx + 0u] = uv0;
+    out_uvs.data[start_vert_idx + 1u] = uv1;
+    out_uvs.data[start_vert_idx + 2u] = uv2;
+    out_uvs.data[start_vert_idx + 3u] = triplanar_uv(v3, normal);
+
+    // Store the tangent of the UV parameterisation for the face vertices.
+    let tangent = triangle_tangent(v0, v1, v2, uv0, uv1, uv2, normal);
+    out_tangents.data[start_vert_idx + 0u] = tangent;
+    out_tangents.data[start_vert_idx + 1u] = tangent;
+    out_tangents.data[start_vert_idx + 2u] = tangent;
+    out_tangents.data[start_vert_idx + 3u] = tangent;
+
+    // Store the emissive colors of the face vertices, interpolated from the voxels.
+    out_emissives.data[start_vert_idx + 0u] = pack_emissive(vertex_emissive(v0));
+    out_emissives.data[start_vert_idx + 1u] = pack_emissive(vertex_emissive(v1));
+    out_emissives.data[start_vert_idx + 2u] = pack_emissive(vertex_emissive(v2));
+    out_emissives.data[start_vert_idx + 3u] = pack_emissive(vertex_emissive(v3));
+
+    // Store indices for two triangles forming the face.
+    out_indices.data[start_indices_idx + 0u] = start_index + 0u;
+    out_indices.data[start_indices_idx + 1u] = start_index + 1u;
+    out_indices.data[start_indices_idx + 2u] = start_index + 2u;
+    out_indices.data[start_indices_idx + 3u] = start_index + 0u;
+    out_indices.data[start_indices_idx + 4u] = start_index + 2u;
+    out_indices.data[start_indices_idx + 5u] = start_index + 3u;
+}
+
+// Function to get the unit offset along an axis (0 = x, 1 = y, 2 = z).
+fn axis_offset(axis: u32) -> vec3<i32> {
+    var offset = vec3<i32>(0);
+    offset[axis] = 1;
+    return offset;
+}
+
+#ifdef DENSITY_TILE
+// Voxels along each axis of the tile of densities shared by the workgroup, see `load_density_tile`.
+const DENSITY_TILE_SIZE: u32 = #{DENSITY_TILE_SIZE}u;
+const DENSITY_TILE_LEN: u32 = DENSITY_TILE_SIZE * DENSITY_TILE_SIZE * DENSITY_TILE_SIZE;
+
+// The densities of the corners of the workgroup's cells, x first, then y, then z.
+var<workgroup> density_tile: array<f32, DENSITY_TILE_LEN>;
+
+// The position of the first corner of the tile.
+var<private> density_tile_origin: vec3<i32>;
+
+// Function to load the densities of the corners of the cells of the workgroup whose first cell is
+// at `origin` into its tile, spreading the loads over its invocations. Every invocation of the
+// workgroup must call it, before any of them returns.
+fn load_density_tile(origin: vec3<i32>, local_index: u32) {
+    density_tile_origin = origin;
+    let invocations = u32(#{WORKGROUP_SIZE} * #{WORKGROUP_SIZE} * #{WORKGROUP_SIZE});
+    for (var i = local_index; i < DENSITY_TILE_LEN; i += invocations) {
+        let offset = vec3<u32>(i % DENSITY_TILE_SIZE, i / DENSITY_TILE_SIZE % DENSITY_TILE_SIZE, i / (DENSITY_TILE_SIZE * DENSITY_TILE_SIZE));
+        density_tile[i] = get_voxel_density(origin + vec3<i32>(offset));
+    }
+    workgroupBarrier();
+}
+#endif
+
+// Function to get the density of a corner of a cell of the workgroup, from its tile if it was
+// loaded.
+fn corner_density(pos: vec3<i32>) -> f32 {
+#ifdef DENSITY_TILE
+    let offset = vec3<u32>(pos - density_tile_origin);
+    return density_tile[offset.x + (offset.y + offset.z * DENSITY_TILE_SIZE) * DENSITY_TILE_SIZE];
+#else
+    return get_voxel_density(pos);
+#endif
+}
+
+// Function to polygonise the cell at `pos` with marching cubes.
+fn marching_cubes(pos: vec3<i32>) {
+    // Define the offsets for the 8 corners of the voxel cube.
+    let smooth_adj_offsets = array<vec3<i32>, 8>(
+        vec3<i32>(0, 0, 1),
+        vec3<i32>(1, 0, 1),
+        vec3<i32>(1, 0, 0),
+        vec3<i32>(0, 0, 0),
+        vec3<i32>(0, 1, 1),
+        vec3<i32>(1, 1, 1),
+        vec3<i32>(1, 1, 0),
+        vec3<i32>(0, 1, 0)
+    );
+
+    var orient: u32 = 0u; // Initialize the orientation.
+    // Define the positions of the 8 corners of the voxel cube.
+    let positions = array<vec3<f32>, 8>(
+        vec3<f32>(pos + smooth_adj_offsets[0u]),
+        vec3<f32>(pos + smooth_adj_offsets[1u]),
+        vec3<f32>(pos + smooth_adj_offsets[2u]),
+        vec3<f32>(pos + smooth_adj_offsets[3u]),
+        vec3<f32>(pos + smooth_adj_offsets[4u]),
+        vec3<f32>(pos + smooth_adj_offsets[5u]),
+        vec3<f32>(pos + smooth_adj_offsets[6u]),
+        vec3<f32>(pos + smooth_adj_offsets[7u]),
+    );
+    // Get the densities of the 8 corners of the voxel cube.
+    let densities = array<f32, 8>(
+        corner_density(pos + smooth_adj_offsets[0u]),
+        corner_density(pos + smooth_adj_offsets[1u]),
+        corner_density(pos + smooth_adj_offsets[2u]),
+        corner_density(pos + smooth_adj_offsets[3u]),
+        corner_density(pos + smooth_adj_offsets[4u]),
+        corner_density(pos + smooth_adj_offsets[5u]),
+        corner_density(pos + smooth_adj_offsets[6u]),
+        corner_density(pos + smooth_adj_offsets[7u]),
+    );
+    // All the triangles of the cell share its material.
+    polygonise(positions, densities, cell_material(pos), pos, false);
+}
+
+// Function to polygonise a cube with marching cubes, from the positions and densities of its
+// corners in the order of the tables. The vertices of a `refined` cube are moved onto the contours
+// of the base resolution cells around its `cell`, see `snap_to_base_cells`.
+fn polygonise(positions: array<vec3<f32>, 8>, densities: array<f32, 8>, material: u32, cell: vec3<i32>, refined: bool) {
+    var cube_idx: u32 = 0u; // Initialize the cube index.
+    // Calculate the cube index based on the densities.
+    cube_idx = cube_idx | (u32(densities[0u] < iso_surface.iso_level) * (1u << 0u));
+    cube_idx = cube_idx | (u32(densities[1u] < iso_surface.iso_level) * (1u << 1u));
+    cube_idx = cube_idx | (u32(densities[2u] < iso_surface.iso_level) * (1u << 2u));
+    cube_idx = cube_idx | (u32(densities[3u] < iso_surface.iso_level) * (1u << 3u));
+    cube_idx = cube_idx | (u32(densities[4u] < iso_surface.iso_level) * (1u << 4u));
+    cube_idx = cube_idx | (u32(densities[5u] < iso_surface.iso_level) * (1u << 5u));
+    cube_idx = cube_idx | (u32(densities[6u] < iso_surface.iso_level) * (1u << 6u));
+    cube_idx = cube_idx | (u32(densities[7u] < iso_surface.iso_level) * (1u << 7u));
+
+    // If the cube is fully inside or outside the surface, skip it.
+    if (cube_idx == 0x00u || cube_idx == 0xffu) {
+        return;
+    }
+
+    // Interpolate the vertices along the edges of the cube.
+    var vertices = array<vec3<f32>, 12>(
+        f32((uniform_edge_table.data[cube_idx] & (1u <<  0u)) != 0u) * interp_vertex(positions[0u], positions[1u], densities[0u], densities[1u]),
+        f32((uniform_edge_table.data[cube_idx] & (1u <<  1u)) != 0u) * interp_vertex(positions[1u], positions[2u], densities[1u], densities[2u]),
+        f32((uniform_edge_table.data[cube_idx] & (1u <<  2u)) != 0u) * interp_vertex(positions[2u], positions[3u], densities[2u], densities[3u]),
+        f32((uniform_edge_table.data[cube_idx] & (1u <<  3u)) != 0u) * interp_vertex(positions[3u], positions[0u], densities[3u], densities[0u]),
+        f32((uniform_edge_table.data[cube_idx] & (1u <<  4u)) != 0u) * interp_vertex(positions[4u], positions[5u], densities[4u], densities[5u]),
+        f32((uniform_edge_table.data[cube_idx] & (1u <<  5u)) != 0u) * interp_vertex(positions[5u], positions[6u], densities[5u], densities[6u]),
+        f32((uniform_edge_table.data[cube_idx] & (1u <<  6u)) != 0u) * interp_vertex(positions[6u], positions[7u], densities[6u], densities[7u]),
+        f32((uniform_edge_table.data[cube_idx] & (1u <<  7u)) != 0u) * interp_vertex(positions[7u], positions[4u], densities[7u], densities[4u]),
+        f32((uniform_edge_table.data[cube_idx] & (1u <<  8u)) != 0u) * interp_vertex(positions[0u], positions[4u], densities[0u], densities[4u]),
+        f32((uniform_edge_table.data[cube_idx] & (1u <<  9u)) != 0u) * interp_vertex(positions[1u], positions[5u], densities[1u], densities[5u]),
+        f32((uniform_edge_table.data[cube_idx] & (1u << 10u)) != 0u) * interp_vertex(positions[2u], positions[6u], densities[2u], densities[6u]),
+        f32((uniform_edge_table.data[cube_idx] & (1u << 11u)) != 0u) * interp_vertex(positions[3u], positions[7u], densities[3u], densities[7u]),
+    );
+
+#ifdef ADAPTIVE_RESOLUTION
+    if (refined) {
+        for (var edge = 0u; edge < 12u; edge++) {
+            if ((uniform_edge_table.data[cube_idx] & (1u << edge)) != 0u) {
+                vertices[edge] = snap_to_base_cells(vertices[edge], cell);
+            }
+        }
+    }
+#endif
+
+    var tri_idx: u32 = 0u; // Initialize the triangle index.
+    // Loop to generate triangles for the current voxel.
+    loop {
+        let v0 = vertices[ uniform_tri_table.data[cube_idx][tri_idx + 0u] ]; // Get the first vertex of the triangle.
+        let v1 = vertices[ uniform_tri_table.data[cube_idx][tri_idx + 1u] ]; // Get the second vertex of the triangle.
+        let v2 = vertices[ uniform_tri_table.data[cube_idx][tri_idx + 2u] ]; // Get the third vertex of the triangle.
+
+        emit_triangle(v0, v1, v2, material); // Store the triangle.
+
+        tri_idx = tri_idx + 3u; // Move to the next triangle index.
+        // Break the loop if there are no more triangles to process.
+        if (uniform_tri_table.data[cube_idx][tri_idx] == -1) {
+            break;
+        }
+    }
+}
+
+#ifdef ADAPTIVE_RESOLUTION
+// Function to check whether the cell at `cell` is in a detail region. Cells outside of the volume,
+// meshed by the neighbouring chunks, are never refined.
+fn is_refined(cell: vec3<i32>) -> bool {
+    if (any(cell < vec3<i32>(0)) || any(cell >= get_volume_size())) {
+        return false;
+    }
+
+    let c = vec3<u32>(cell);
+    for (var i = 0u; i < min(adaptive_resolution.count, MAX_DETAIL_REGIONS); i++) {
+        if (all(c >= adaptive_resolution.regions_min[i].xyz) && all(c < adaptive_resolution.regions_max[i].xyz)) {
+            return true;
+        }
+    }
+    return false;
+}
+
+// Function to interpolate from `a` at 0 to `b` at 1, giving exactly `a` and `b` at the ends so that
+// two refined cells sample the same densities on the face they share.
+fn lerp_exact(a: f32, b: f32, t: f32) -> f32 {
+    return a * (1.0 - t) + b * t;
+}
+
+// Function to sample the trilinear interpolation of the corner densities of a cell, indexed by
+// their x, y and z bits, at `t` from 0 to 1 along each axis.
+fn trilinear(corners: array<f32, 8>, t: vec3<f32>) -> f32 {
+    let x00 = lerp_exact(corners[0u], corners[1u], t.x);
+    let x10 = lerp_exact(corners[2u], corners[3u], t.x);
+    let x01 = lerp_exact(corners[4u], corners[5u], t.x);
+    let x11 = lerp_exact(corners[6u], corners[7u], t.x);
+    let y0 = lerp_exact(x00, x10, t.y);
+    let y1 = lerp_exact(x01, x11, t.y);
+    return lerp_exact(y0, y1, t.z);
+}
+
+// Function to project `v` onto the marching squares contour of the face of a base resolution cell
+// starting at `origin` and normal to `axis`, which the triangles of that cell end on. Faces
+// crossed four times are ambiguous, `v` is projected onto the nearest of their possible segments.
+fn project_onto_face_contour(v: vec3<f32>, origin: vec3<i32>, axis: u32) -> vec3<f32> {
+    let b = axis_offset((axis + 1u) % 3u);
+    let c = axis_offset((axis + 2u) % 3u);
+    var corners = array<vec3<i32>, 4>(origin, origin + b, origin + b + c, origin + c);
+
+    // Find where the surface crosses the edges of the face, in order around it.
+    var crossings: array<vec3<f32>, 4>;
+    var count = 0u;
+    for (var i = 0u; i < 4u; i++) {
+        let p1 = corners[i];
+        let p2 = corners[(i + 1u) % 4u];
+        let d1 = get_voxel_density(p1);
+        let d2 = get_voxel_density(p2);
+        if ((d1 < iso_surface.iso_level) != (d2 < iso_surface.iso_level)) {
+            crossings[count] = interp_vertex(vec3<f32>(p1), vec3<f32>(p2), d1, d2);
+            count++;
+        }
+    }
+
+    if (count < 2u) {
+        return v;
+    }
+
+    var nearest = v;
+    var nearest_distance = 1e30;
+    let segments = select(1u, 4u, count == 4u);
+    for (var i = 0u; i < segments; i++) {
+        let a = crossings[i];
+        let e = crossings[(i + 1u) % count] - a;
+        let t = clamp(dot(v - a, e) / max(dot(e, e), 1e-12), 0.0, 1.0);
+        let p = a + t * e;
+        if (distance(v, p) < nearest_distance) {
+            nearest = p;
+            nearest_distance = distance(v, p);
+        }
+    }
+    return nearest;
+}
+
+// Function to move a vertex of the refined cell at `cell` that lies on a face shared with a base
+// resolution cell onto the contour the base resolution cell meshes on that face, so that the two
+// resolutions meet without cracks.
+fn snap_to_base_cells(v: vec3<f32>, cell: vec3<i32>) -> vec3<f32> {
+    for (var axis = 0u; axis < 3u; axis++) {
+        for (var side = 0; side < 2; side++) {
+            if (v[axis] != f32(cell[axis] + side)) {
+                continue;
+            }
+            if (is_refined(cell + axis_offset(axis) * (2 * side - 1))) {
+                continue;
+            }
+            return project_onto_face_contour(v, cell + axis_offset(axis) * side, axis);
+        }
+    }
+    return v;
+}
+
+// Function to polygonise the cell at `cell` with marching cubes on a lattice `subdivisions` times
+// denser than the voxels, supersampling the trilinear interpolation of its corner densities.
+fn marching_cubes_refined(cell: vec3<i32>) {
+    var corners: array<f32, 8>;
+    var inside = 0u;
+    for (var corner = 0u; corner < 8u; corner++) {
+        let p = cell + vec3<i32>(i32(corner & 1u), i32((corner >> 1u) & 1u), i32((corner >> 2u) & 1u));
+        corners[corner] = get_voxel_density(p);
+        inside += u32(corners[corner] < iso_surface.iso_level);
+    }
+
+    // The interpolated densities never cross the iso-level if the corners don't.
+    if (inside == 0u || inside == 8u) {
+        return;
+    }
+
+    // The corners of a sub-cell in the order of the marching cubes tables.
+    var offsets = array<vec3<u32>, 8>(
+        vec3<u32>(0u, 0u, 1u),
+        vec3<u32>(1u, 0u, 1u),
+        vec3<u32>(1u, 0u, 0u),
+        vec3<u32>(0u, 0u, 0u),
+        vec3<u32>(0u, 1u, 1u),
+        vec3<u32>(1u, 1u, 1u),
+        vec3<u32>(1u, 1u, 0u),
+        vec3<u32>(0u, 1u, 0u),
+    );
+
+    let subdivisions = adaptive_resolution.subdivisions;
+    let step = 1.0 / f32(subdivisions);
+    let material = cell_material(cell); // All the triangles of the cell share its material.
+
+    for (var z = 0u; z < subdivisions; z++) {
+        for (var y = 0u; y < subdivisions; y++) {
+            for (var x = 0u; x < subdivisions; x++) {
+                var positions: array<vec3<f32>, 8>;
+                var densities: array<f32, 8>;
+                for (var i = 0u; i < 8u; i++) {
+                    let t = vec3<f32>(vec3<u32>(x, y, z) + offsets[i]) * step;
+                    positions[i] = vec3<f32>(cell) + t;
+                    densities[i] = trilinear(corners, t);
+                }
+                polygonise(positions, densities, material, cell, true);
+            }
+        }
+    }
+}
+#endif
+
+// Function to emit the faces of the block at `pos` that border an empty voxel.
+fn emit_block_faces(pos: vec3<i32>) {
+    // Define the faces and adjacent offsets for a block.
+    var block_faces = array<array<vec3<f32>, 4>, 6>(
+        array<vec3<f32>, 4>(
+            vec3<f32>(0.5, -0.5, -0.5),
+            vec3<f32>(0.5,  0.5, -0.5),
+            vec3<f32>(0.5,  0.5,  0.5),
+            vec3<f32>(0.5, -0.5,  0.5),
+        ),
+        array<vec3<f32>, 4>(
+            vec3<f32>(-0.5, -0.5,  0.5),
+            vec3<f32>(-0.5,  0.5,  0.5),
+            vec3<f32>(-0.5,  0.5, -0.5),
+            vec3<f32>(-0.5, -0.5, -0.5)
+        ),
+        array<vec3<f32>, 4>(
+            vec3<f32>(-0.5, 0.5,  0.5),
+            vec3<f32>( 0.5, 0.5,  0.5),
+            vec3<f32>( 0.5, 0.5, -0.5),
+            vec3<f32>(-0.5, 0.5, -0.5)
+        ),
+        array<vec3<f32>, 4>(
+            vec3<f32>(-0.5, -0.5, -0.5),
+            vec3<f32>( 0.5, -0.5, -0.5),
+            vec3<f32>( 0.5, -0.5,  0.5),
+            vec3<f32>(-0.5, -0.5,  0.5)
+        ),
+        array<vec3<f32>, 4>(
+            vec3<f32>( 0.5, -0.5, 0.5),
+            vec3<f32>( 0.5,  0.5, 0.5),
+            vec3<f32>(-0.5,  0.5, 0.5),
+            vec3<f32>(-0.5, -0.5, 0.5)
+        ),
+        array<vec3<f32>, 4>(
+            vec3<f32>(-0.5, -0.5, -0.5),
+            vec3<f32>(-0.5,  0.5, -0.5),
+            vec3<f32>( 0.5,  0.5, -0.5),
+            vec3<f32>( 0.5, -0.5, -0.5)
+        ),
+    );
+    // Define the adjacent offsets for the block faces.
+    var block_adj_offsets = array<vec3<i32>, 6>(
+        vec3<i32>( 1,  0,  0),
+        vec3<i32>(-1,  0,  0),
+        vec3<i32>( 0,  1,  0),
+        vec3<i32>( 0, -1,  0),
+        vec3<i32>( 0,  0,  1),
+        vec3<i32>( 0,  0, -1),
+    );
+
+    let material = voxel_material(pos); // All the faces of the block share its material.
+    var dir: u32 = 0u; // Initialize the direction index.
+    // Loop to process each face of the block.
+    loop {
+        let adj_pos = pos + block_adj_offsets[dir]; // Get the adjacent position.
+        let adj_density = get_voxel_density(adj_pos); // Get the density of the adjacent voxel.
+
+        // If the adjacent voxel is below the surface threshold.
+        if (adj_density < iso_surface.iso_level) {
+            let center = vec3<f32>(pos); // Convert the position to float.
+
+            // Store the face as a quad around the voxel center.
+            emit_quad(
+                center + block_faces[dir][0u],
+                center + block_faces[dir][1u],
+                center + block_faces[dir][2u],
+                center + block_faces[dir][3u],
+                material,
+            );
+        }
 
-        // Define the offsets for the 8 corners of the voxel cube.
-        let smooth_adj_offsets = array<vec3<i32>, 8>(
-            vec3<i32>(0, 0, 1),
-            vec3<i32>(1, 0, 1),
-            vec3<i32>(1, 0, 0),
-            vec3<i32>(0, 0, 0),
-            vec3<i32>(0, 1, 1),
-            vec3<i32>(1, 1, 1),
-            vec3<i32>(1, 1, 0),
-            vec3<i32>(0, 1, 0)
-        );
-
-        var cube_idx: u32 = 0u; // Initialize the cube index.
-        var orient: u32 = 0u; // Initialize the orientation.
-        // Define the positions of the 8 corners of the voxel cube.
-        let positions = array<vec3<f32>, 8>(
-            vec3<f32>(pos + smooth_adj_offsets[0u]),
-            vec3<f32>(pos + smooth_adj_offsets[1u]),
-            vec3<f32>(pos + smooth_adj_offsets[2u]),
-            vec3<f32>(pos + smooth_adj_offsets[3u]),
-            vec3<f32>(pos + smooth_adj_offsets[4u]),
-            vec3<f32>(pos + smooth_adj_offsets[5u]),
-            vec3<f32>(pos + smooth_adj_offsets[6u]),
-            vec3<f32>(pos + smooth_adj_offsets[7u]),
-        );
-        // Get the densities of the 8 corners of the voxel cube.
-        let densities = array<f32, 8>(
-            get_voxel_density(pos + smooth_adj_offsets[0u]),
-            get_voxel_density(pos + smooth_adj_offsets[1u]),
-            get_voxel_density(pos + smooth_adj_offsets[2u]),
-            get_voxel_density(pos + smooth_adj_offsets[3u]),
-            get_voxel_density(pos + smooth_adj_offsets[4u]),
-            get_voxel_density(pos + smooth_adj_offsets[5u]),
-            get_voxel_density(pos + smooth_adj_offsets[6u]),
-            get_voxel_density(pos + smooth_adj_offsets[7u]),
-        );
-        // Calculate the cube index based on the densities.
-        cube_idx = cube_idx | (u32(densities[0u] < 0.5) * (1u << 0u));
-        cube_idx = cube_idx | (u32(densities[1u] < 0.5) * (1u << 1u));
-        cube_idx = cube_idx | (u32(densities[2u] < 0.5) * (1u << 2u));
-        cube_idx = cube_idx | (u32(densities[3u] < 0.5) * (1u << 3u));
-        cube_idx = cube_idx | (u32(densities[4u] < 0.5) * (1u << 4u));
-        cube_idx = cube_idx | (u32(densities[5u] < 0.5) * (1u << 5u));
-        cube_idx = cube_idx | (u32(densities[6u] < 0.5) * (1u << 6u));
-        cube_idx = cube_idx | (u32(densities[7u] < 0.5) * (1u << 7u));
-
-        // If the cube is fully inside or outside the surface, skip it.
-        if (cube_idx == 0x00u || cube_idx == 0xffu) {
-            return;
+        dir = dir + 1u; // Move to the next direction.
+        // Break the loop if all directions have been processed.
+        if (dir >= 6u) {
+            break;
         }
+    }
+}
+
+// Function to get the surface nets vertex of the cell at `cell`: the average of its edge crossings.
+fn surface_nets_vertex(cell: vec3<i32>) -> vec3<f32> {
+    var sum = vec3<f32>(0.0);
+    var crossings = 0.0;
+
+    // Visit each of the 12 cell edges once, from the corner with the lower coordinate.
+    for (var corner = 0u; corner < 8u; corner++) {
+        let p1 = cell + vec3<i32>(i32(corner & 1u), i32((corner >> 1u) & 1u), i32((corner >> 2u) & 1u));
+        let d1 = get_voxel_density(p1);
+
+        for (var axis = 0u; axis < 3u; axis++) {
+            if ((corner & (1u << axis)) != 0u) {
+                continue;
+            }
+
+            let p2 = p1 + axis_offset(axis);
+            let d2 = get_voxel_density(p2);
 
-        // Interpolate the vertices along the edges of the cube.
-        var vertices = array<vec3<f32>, 12>(
-            f32((uniform_edge_table.data[cube_idx] & (1u <<  0u)) != 0u) * interp_vertex(positions[0u], positions[1u], densities[0u], densities[1u]),
-            f32((uniform_edge_table.data[cube_idx] & (1u <<  1u)) != 0u) * interp_vertex(positions[1u], positions[2u], densities[1u], densities[2u]),
-            f32((uniform_edge_table.data[cube_idx] & (1u <<  2u)) != 0u) * interp_vertex(positions[2u], positions[3u], densities[2u], densities[3u]),
-            f32((uniform_edge_table.data[cube_idx] & (1u <<  3u)) != 0u) * interp_vertex(positions[3u], positions[0u], densities[3u], densities[0u]),
-            f32((uniform_edge_table.data[cube_idx] & (1u <<  4u)) != 0u) * interp_vertex(positions[4u], positions[5u], densities[4u], densities[5u]),
-            f32((uniform_edge_table.data[cube_idx] & (1u <<  5u)) != 0u) * interp_vertex(positions[5u], positions[6u], densities[5u], densities[6u]),
-            f32((uniform_edge_table.data[cube_idx] & (1u <<  6u)) != 0u) * interp_vertex(positions[6u], positions[7u], densities[6u], densities[7u]),
-            f32((uniform_edge_table.data[cube_idx] & (1u <<  7u)) != 0u) * interp_vertex(positions[7u], positions[4u], densities[7u], densities[4u]),
-            f32((uniform_edge_table.data[cube_idx] & (1u <<  8u)) != 0u) * interp_vertex(positions[0u], positions[4u], densities[0u], densities[4u]),
-            f32((uniform_edge_table.data[cube_idx] & (1u <<  9u)) != 0u) * interp_vertex(positions[1u], positions[5u], densities[1u], densities[5u]),
-            f32((uniform_edge_table.data[cube_idx] & (1u << 10u)) != 0u) * interp_vertex(positions[2u], positions[6u], densities[2u], densities[6u]),
-            f32((uniform_edge_table.data[cube_idx] & (1u << 11u)) != 0u) * interp_vertex(positions[3u], positions[7u], densities[3u], densities[7u]),
-        );
-
-        var tri_idx: u32 = 0u; // Initialize the triangle index.
-        // Loop to generate triangles for the current voxel.
-        loop {
-            var start_vert_idx = atomicAdd(&global_atomics.vertices_head, 3u); // Allocate space for 3 vertices.
-            var start_indices_idx = atomicAdd(&global_atomics.indices_head, 3u); // Allocate space for 3 indices.
-
-            let v0 = vertices[ uniform_tri_table.data[cube_idx][tri_idx + 0u] ]; // Get the first vertex of the triangle.
-            let v1 = vertices[ uniform_tri_table.data[cube_idx][tri_idx + 1u] ]; // Get the second vertex of the triangle.
-            let v2 = vertices[ uniform_tri_table.data[cube_idx][tri_idx + 2u] ]; // Get the third vertex of the triangle.
-
-            out_vertices.data[start_vert_idx + 0u] = v0; // Store the first vertex.
-            out_vertices.data[start_vert_idx + 1u] = v1; // Store the second vertex.
-            out_vertices.data[start_vert_idx + 2u] = v2; // Store the third vertex.
-
-            out_indices.data[start_indices_idx + 0u] = start_vert_idx + 0u; // Store the first index.
-            out_indices.data[start_indices_idx + 1u] = start_vert_idx + 1u; // Store the second index.
-            out_indices.data[start_indices_idx + 2u] = start_vert_idx + 2u; // Store the third index.
-
-            let normal = cross(v0 - v1, v0 - v2); // Calculate the normal for the triangle.
-            out_normals.data[start_vert_idx + 0u] = normal; // Store the normal for the first vertex.
-            out_normals.data[start_vert_idx + 1u] = normal; // Store the normal for the second vertex.
-            out_normals.data[start_vert_idx + 2u] = normal; // Store the normal for the third vertex.
-
-            // Store default UV coordinates for the triangle vertices.
-            out_uvs.data[start_vert_idx + 0u] = vec2<f32>(0.0, 0.0);
-            out_uvs.data[start_vert_idx + 1u] = vec2<f32>(1.0, 0.0);
-            out_uvs.data[start_vert_idx + 2u] = vec2<f32>(0.0, 1.0);
-
-            tri_idx = tri_idx + 3u; // Move to the next triangle index.
-            // Break the loop if there are no more triangles to process.
-            if (uniform_tri_table.data[cube_idx][tri_idx] == -1) {
-                break;
+            if ((d1 < iso_surface.iso_level) != (d2 < iso_surface.iso_level)) {
+                sum = sum + interp_vertex(vec3<f32>(p1), vec3<f32>(p2), d1, d2);
+                crossings = crossings + 1.0;
             }
         }
-    } else { // If the voxel is inactive (flags != 0).
-
-        // Define the faces and adjacent offsets for a block.
-        var block_faces = array<array<vec3<f32>, 4>, 6>(
-            array<vec3<f32>, 4>(
-                vec3<f32>(0.5, -0.5, -0.5),
-                vec3<f32>(0.5,  0.5, -0.5),
-                vec3<f32>(0.5,  0.5,  0.5),
-                vec3<f32>(0.5, -0.5,  0.5),
-            ),
-            array<vec3<f32>, 4>(
-                vec3<f32>(-0.5, -0.5,  0.5),
-                vec3<f32>(-0.5,  0.5,  0.5),
-                vec3<f32>(-0.5,  0.5, -0.5),
-                vec3<f32>(-0.5, -0.5, -0.5)
-            ),
-            array<vec3<f32>, 4>(
-                vec3<f32>(-0.5, 0.5,  0.5),
-                vec3<f32>( 0.5, 0.5,  0.5),
-                vec3<f32>( 0.5, 0.5, -0.5),
-                vec3<f32>(-0.5, 0.5, -0.5)
-            ),
-            array<vec3<f32>, 4>(
-                vec3<f32>(-0.5, -0.5, -0.5),
-                vec3<f32>( 0.5, -0.5, -0.5),
-                vec3<f32>( 0.5, -0.5,  0.5),
-                vec3<f32>(-0.5, -0.5,  0.5)
-            ),
-            array<vec3<f32>, 4>(
-                vec3<f32>( 0.5, -0.5, 0.5),
-                vec3<f32>( 0.5,  0.5, 0.5),
-                vec3<f32>(-0.5,  0.5, 0.5),
-                vec3<f32>(-0.5, -0.5, 0.5)
-            ),
-            array<vec3<f32>, 4>(
-                vec3<f32>(-0.5, -0.5, -0.5),
-                vec3<f32>(-0.5,  0.5, -0.5),
-                vec3<f32>( 0.5,  0.5, -0.5),
-                vec3<f32>( 0.5, -0.5, -0.5)
-            ),
-        );
-        // Define the adjacent offsets for the block faces.
-        var block_adj_offsets = array<vec3<i32>, 6>(
-            vec3<i32>( 1,  0,  0),
-            vec3<i32>(-1,  0,  0),
-            vec3<i32>( 0,  1,  0),
-            vec3<i32>( 0, -1,  0),
-            vec3<i32>( 0,  0,  1),
-            vec3<i32>( 0,  0, -1),
-        );
-
-        var dir: u32 = 0u; // Initialize the direction index.
-        // Loop to process each face of the block.
-        loop {
-            let adj_pos = pos + block_adj_offsets[dir]; // Get the adjacent position.
-            let adj_density = get_voxel_density(pos); // Get the density of the adjacent voxel.
-
-            // If the adjacent voxel is below the surface threshold.
-            if (adj_density < 0.5) {
-                var pos = vec3<f32>(invocation_id); // Convert the position to float.
-
-                let start_vert_idx = atomicAdd(&global_atomics.vertices_head, 4u); // Allocate space for 4 vertices.
-                let start_indices_idx = atomicAdd(&global_atomics.indices_head, 6u); // Allocate space for 6 indices.
-
-                let v0 = block_faces[dir][0u]; // Get the first vertex of the face.
-                let v1 = block_faces[dir][1u]; // Get the second vertex of the face.
-                let v2 = block_faces[dir][2u]; // Get the third vertex of the face.
-                let v3 = block_faces[dir][3u]; // Get the fourth vertex of the face.
-
-                out_vertices.data[start_vert_idx + 0u] = pos + v0; // Store the first vertex.
-                out_vertices.data[start_vert_idx + 1u] = pos + v1; // Store the second vertex.
-                out_vertices.data[start_vert_idx + 2u] = pos + v2; // Store the third vertex.
-                out_vertices.data[start_vert_idx + 3u] = pos + v3; // Store the fourth vertex.
-
-                let normal = cross(v0 - v1, v0 - v2); // Calculate the normal for the face.
-                out_normals.data[start_vert_idx + 0u] = normal; // Store the normal for the first vertex.
-                out_normals.data[start_vert_idx + 1u] = normal; // Store the normal for the second vertex.
-                out_normals.data[start_vert_idx + 2u] = normal; // Store the normal for the third vertex.
-                out_normals.data[start_vert_idx + 3u] = normal; // Store the normal for the fourth vertex.
-
-                // Store default UV coordinates for the face vertices.
-                out_uvs.data[start_vert_idx + 0u] = vec2<f32>(0.0, 0.0);
-                out_uvs.data[start_vert_idx + 1u] = vec2<f32>(1.0, 0.0);
-                out_uvs.data[start_vert_idx + 2u] = vec2<f32>(1.0, 1.0);
-                out_uvs.data[start_vert_idx + 3u] = vec2<f32>(0.0, 1.0);
-
-                // Store indices for two triangles forming the face.
-                out_indices.data[start_indices_idx + 0u] = start_vert_idx + 0u;
-                out_indices.data[start_indices_idx + 1u] = start_vert_idx + 1u;
-                out_indices.data[start_indices_idx + 2u] = start_vert_idx + 2u;
-                out_indices.data[start_indices_idx + 3u] = start_vert_idx + 0u;
-                out_indices.data[start_indices_idx + 4u] = start_vert_idx + 2u;
-                out_indices.data[start_indices_idx + 5u] = start_vert_idx + 3u;
+    }
+
+    return sum / max(crossings, 1.0);
+}
+
+// Determinant below which the QEF of a cell is solved with the pseudo-inverse instead.
+const QEF_DETERMINANT_TOLERANCE: f32 = 1e-6;
+// Eigenvalues below this fraction of the largest are dropped from the QEF pseudo-inverse.
+const QEF_EIGENVALUE_TOLERANCE: f32 = 0.1;
+
+// Function to get the density gradient at a lattice point by central differences.
+fn density_gradient(p: vec3<i32>) -> vec3<f32> {
+    return 0.5 * vec3<f32>(
+        get_voxel_density(p + vec3<i32>(1, 0, 0)) - get_voxel_density(p - vec3<i32>(1, 0, 0)),
+        get_voxel_density(p + vec3<i32>(0, 1, 0)) - get_voxel_density(p - vec3<i32>(0, 1, 0)),
+        get_voxel_density(p + vec3<i32>(0, 0, 1)) - get_voxel_density(p - vec3<i32>(0, 0, 1)),
+    );
+}
+
+// Function to solve `a * x = b` for the symmetric matrix `a` with its pseudo-inverse, from the
+// eigendecomposition found by Jacobi rotations. Dropping the small eigenvalues leaves `x` at zero
+// along the directions the tangent planes don't constrain, e.g. along a sharp edge.
+fn solve_pseudo_inverse(a_in: mat3x3<f32>, b: vec3<f32>) -> vec3<f32> {
+    var a = a_in;
+    var v = mat3x3<f32>(vec3<f32>(1.0, 0.0, 0.0), vec3<f32>(0.0, 1.0, 0.0), vec3<f32>(0.0, 0.0, 1.0));
+    var rows = array<u32, 3>(0u, 0u, 1u);
+    var cols = array<u32, 3>(1u, 2u, 2u);
+
+    for (var sweep = 0u; sweep < 5u; sweep++) {
+        for (var pair = 0u; pair < 3u; pair++) {
+            let p = rows[pair];
+            let q = cols[pair];
+            if (abs(a[q][p]) < 1e-12) {
+                continue;
             }
 
-            dir = dir + 1u; // Move to the next direction.
-            // Break the loop if all directions have been processed.
-            if (dir >= 6u) {
-                break;
+            // Rotate in the (p, q) plane to zero the off-diagonal element.
+            let theta = (a[q][q] - a[p][p]) / (2.0 * a[q][p]);
+            let t = select(-1.0, 1.0, theta >= 0.0) / (abs(theta) + sqrt(theta * theta + 1.0));
+            let c = 1.0 / sqrt(t * t + 1.0);
+            let s = t * c;
+
+            var rotation = mat3x3<f32>(vec3<f32>(1.0, 0.0, 0.0), vec3<f32>(0.0, 1.0, 0.0), vec3<f32>(0.0, 0.0, 1.0));
+            rotation[p][p] = c;
+            rotation[q][q] = c;
+            rotation[q][p] = s;
+            rotation[p][q] = -s;
+
+            a = transpose(rotation) * a * rotation;
+            v = v * rotation;
+        }
+    }
+
+    let largest = max(abs(a[0][0]), max(abs(a[1][1]), abs(a[2][2])));
+    var x = vec3<f32>(0.0);
+    for (var i = 0u; i < 3u; i++) {
+        let eigenvalue = a[i][i];
+        if (abs(eigenvalue) > QEF_EIGENVALUE_TOLERANCE * largest) {
+            x = x + v[i] * (dot(v[i], b) / eigenvalue);
+        }
+    }
+    return x;
+}
+
+// Function to solve `a * x = b` directly, falling back to the pseudo-inverse if `a` is close to
+// singular.
+fn solve_qef(a: mat3x3<f32>, b: vec3<f32>) -> vec3<f32> {
+    let r0 = cross(a[1], a[2]);
+    let r1 = cross(a[2], a[0]);
+    let r2 = cross(a[0], a[1]);
+    let det = dot(a[0], r0);
+
+    if (abs(det) < QEF_DETERMINANT_TOLERANCE) {
+        return solve_pseudo_inverse(a, b);
+    }
+    return vec3<f32>(dot(r0, b), dot(r1, b), dot(r2, b)) / det;
+}
+
+// Function to get the dual contouring vertex of the cell at `cell`: the point minimising the
+// squared distances to the tangent planes of its edge crossings, regularised towards their average.
+fn dual_contouring_vertex(cell: vec3<i32>) -> vec3<f32> {
+    var ata = mat3x3<f32>(vec3<f32>(0.0), vec3<f32>(0.0), vec3<f32>(0.0));
+    var atb = vec3<f32>(0.0);
+    var sum = vec3<f32>(0.0);
+    var crossings = 0.0;
+
+    // Visit each of the 12 cell edges once, from the corner with the lower coordinate.
+    for (var corner = 0u; corner < 8u; corner++) {
+        let p1 = cell + vec3<i32>(i32(corner & 1u), i32((corner >> 1u) & 1u), i32((corner >> 2u) & 1u));
+        let d1 = get_voxel_density(p1);
+
+        for (var axis = 0u; axis < 3u; axis++) {
+            if ((corner & (1u << axis)) != 0u) {
+                continue;
             }
+
+            let p2 = p1 + axis_offset(axis);
+            let d2 = get_voxel_density(p2);
+
+            if ((d1 < iso_surface.iso_level) != (d2 < iso_surface.iso_level)) {
+                let point = interp_vertex(vec3<f32>(p1), vec3<f32>(p2), d1, d2);
+                let mu = (iso_surface.iso_level - d1) / (d2 - d1);
+                let gradient = mix(density_gradient(p1), density_gradient(p2), mu);
+                let normal = gradient / max(length(gradient), 1e-8);
+
+                ata = ata + mat3x3<f32>(normal * normal.x, normal * normal.y, normal * normal.z);
+                atb = atb + normal * dot(normal, point);
+                sum = sum + point;
+                crossings = crossings + 1.0;
+            }
+        }
+    }
+
+    let mass_point = sum / max(crossings, 1.0);
+    let regularization = dual_contouring.regularization;
+    let a = ata + mat3x3<f32>(
+        vec3<f32>(regularization, 0.0, 0.0),
+        vec3<f32>(0.0, regularization, 0.0),
+        vec3<f32>(0.0, 0.0, regularization),
+    );
+
+    // Solve relative to the mass point, which the regularisation pulls towards.
+    let offset = solve_qef(a, atb - ata * mass_point);
+
+    // Keep the vertex inside its cell so that the surface cannot fold over.
+    return clamp(mass_point + offset, vec3<f32>(cell), vec3<f32>(cell + 1));
+}
+
+// Function to get the vertex of the cell at `cell` for the selected dual meshing algorithm.
+fn cell_vertex(cell: vec3<i32>) -> vec3<f32> {
+#ifdef MESHING_DUAL_CONTOURING
+    return dual_contouring_vertex(cell);
+#else
+    return surface_nets_vertex(cell);
+#endif
+}
+
+// Function to emit a surface nets quad for each edge leaving `pos` in the +x, +y and +z directions
+// that crosses the surface, joining the vertices of the four cells sharing that edge.
+fn surface_nets(pos: vec3<i32>) {
+    let d0 = get_voxel_density(pos);
+
+    for (var axis = 0u; axis < 3u; axis++) {
+        let a = axis_offset(axis);
+        let b = axis_offset((axis + 1u) % 3u);
+        let c = axis_offset((axis + 2u) % 3u);
+
+#ifdef MESH_CAPPING
+        // Skip edges whose surrounding cells are not all inside the volume or the capping layer
+        // around it.
+        if (any(pos - b - c < vec3<i32>(-1)) || any(pos + a > get_volume_size())) {
+            continue;
+        }
+#else
+        // Skip edges whose surrounding cells are not all inside the volume.
+        if (any(pos - b - c < vec3<i32>(0)) || any(pos + a >= get_volume_size())) {
+            continue;
+        }
+#endif
+
+        let d1 = get_voxel_density(pos + a);
+        if ((d0 < iso_surface.iso_level) == (d1 < iso_surface.iso_level)) {
+            continue;
+        }
+
+        let q0 = cell_vertex(pos - b - c);
+        let q1 = cell_vertex(pos - c);
+        let q2 = cell_vertex(pos);
+        let q3 = cell_vertex(pos - b);
+
+        // Face away from the solid side of the edge, taking the material of that side.
+        if (d0 >= iso_surface.iso_level) {
+            emit_quad(q0, q1, q2, q3, voxel_material(pos));
+        } else {
+            emit_quad(q0, q3, q2, q1, voxel_material(pos + a));
+        }
+    }
+}
+
+// Main compute shader entry point, with cubic workgroups of the side of the `ShaderPlatform`.
+@compute @workgroup_size(#{WORKGROUP_SIZE}, #{WORKGROUP_SIZE}, #{WORKGROUP_SIZE})
+fn main(
+    @builtin(global_invocation_id) invocation_id: vec3<u32>,
+    @builtin(local_invocation_id) local_id: vec3<u32>,
+    @builtin(local_invocation_index) local_index: u32,
+) {
+
+#ifdef MESH_CAPPING
+    // Start one cell below the volume so that the cells straddling its lower edges are meshed too.
+    let pos = vec3<i32>(invocation_id) - 1;
+#else
+    let pos = vec3<i32>(invocation_id); // Convert invocation ID to integer position.
+#endif
+
+#ifdef DENSITY_TILE
+    // Every invocation helps load the tile, including those outside of the volume.
+    load_density_tile(pos - vec3<i32>(local_id), local_index);
+#endif
+
+    // Skip invocations outside of the volume.
+    if (any(pos >= get_volume_size())) {
+        return;
+    }
+
+#ifdef CELL_SLOTS
+    // Slots are laid out over the meshed cells, including the capping layer.
+    let cells = vec3<u32>(get_volume_size() + vec3<i32>(invocation_id) - pos);
+    cell_slot = invocation_id.x + cells.x * (invocation_id.y + cells.y * invocation_id.z);
+#endif
+
+#ifdef MESHING_SURFACE_NETS
+    surface_nets(pos);
+#endif
+
+#ifdef MESHING_DUAL_CONTOURING
+    // Dual contouring shares the surface nets topology, only its vertices are placed differently.
+    surface_nets(pos);
+#endif
+
+#ifdef MESHING_CUBIC
+    // Every voxel inside the surface is meshed as a block.
+    if (get_voxel_density(pos) >= iso_surface.iso_level) {
+        emit_block_faces(pos);
+    }
+#endif
+
+#ifdef MESHING_MARCHING_CUBES
+    let voxel = get_voxel(pos); // Get the voxel data for the current position.
+
+    // If the voxel is active (flags == 0) polygonise it, otherwise mesh it as a block.
+    if (voxel.flags == 0u) {
+#ifdef ADAPTIVE_RESOLUTION
+        if (is_refined(pos)) {
+            marching_cubes_refined(pos);
+        } else {
+            marching_cubes(pos);
         }
+#else
+        marching_cubes(pos);
+#endif
+    } else {
+        emit_block_faces(pos);
     }
+#endif
+
+#ifdef CELL_SLOTS
+#ifndef PREFIX_SUM_SCATTER
+    cell_slots.data[cell_slot].vertices = cell_vertices;
+    cell_slots.data[cell_slot].indices = cell_indices;
+#endif
+#endif
 }
@@ -0,0 +1,83 @@
+#import bevy_volumetric::types::{CHUNK_SZ, VoxelBuffer, IsoSurfaceParams, LightProbeParams}
+
+@group(0) @binding(0) var<uniform> params: LightProbeParams;
+@group(0) @binding(1) var<storage, read> in_voxels: VoxelBuffer;
+@group(0) @binding(2) var<uniform> iso_surface: IsoSurfaceParams;
+@group(0) @binding(3) var probes: texture_storage_3d<rgba16float, write>;
+
+// The angle between consecutive rays of a side, spreading them evenly over its hemisphere.
+const GOLDEN_ANGLE: f32 = 2.39996323;
+
+// Distance in voxels between two samples of a ray.
+const STEP: f32 = 0.5;
+
+// Function to get a flat index for a given position in the 3D grid.
+fn get_flat_index(pos: vec3<i32>) -> u32 {
+    return u32(pos.x + pos.y * CHUNK_SZ + pos.z * CHUNK_SZ * CHUNK_SZ);
+}
+
+// Function to check whether the voxel at a position is solid, treating everything outside the
+// chunk as open sky.
+fn is_solid(pos: vec3<f32>) -> bool {
+    let voxel = vec3<i32>(floor(pos + 0.5));
+    if (any(voxel < vec3<i32>(0)) || any(voxel >= vec3<i32>(CHUNK_SZ))) {
+        return false;
+    }
+    return in_voxels.data[get_flat_index(voxel)].density >= iso_surface.iso_level;
+}
+
+// Function to get the light gathered by a ray, from the sky if it escapes the volume or bounced
+// off the surface if it hits it.
+fn trace(origin: vec3<f32>, direction: vec3<f32>) -> vec3<f32> {
+    for (var t = STEP; t < params.max_distance; t += STEP) {
+        if (is_solid(origin + direction * t)) {
+            return params.sky_color.rgb * params.bounce_color.rgb;
+        }
+    }
+    return params.sky_color.rgb;
+}
+
+// Function to get the average light reaching the side of a probe facing along `normal`, from
+// cosine-weighted rays over its hemisphere.
+fn side_irradiance(origin: vec3<f32>, normal: vec3<f32>, tangent: vec3<f32>, bitangent: vec3<f32>) -> vec3<f32> {
+    var sum = vec3<f32>(0.0);
+    for (var i = 0u; i < params.ray_count; i++) {
+        let u = (f32(i) + 0.5) / f32(params.ray_count);
+        let phi = f32(i) * GOLDEN_ANGLE;
+        let r = sqrt(u);
+        let direction = tangent * (r * cos(phi)) + bitangent * (r * sin(phi)) + normal * sqrt(1.0 - u);
+        sum += trace(origin, direction);
+    }
+    return sum / f32(params.ray_count);
+}
+
+// Each invocation bakes the six sides of the ambient cube of one probe.
+@compute @workgroup_size(4, 4, 4)
+fn main(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
+    let size = textureDimensions(probes);
+    let resolution = vec3<u32>(size.x, size.y / 2u, size.z / 3u);
+    let probe = invocation_id;
+
+    // Skip invocations outside of the probe grid.
+    if (any(probe >= resolution)) {
+        return;
+    }
+
+    // The probe sits in the centre of its cell of the grid.
+    let origin = (vec3<f32>(probe) + 0.5) * f32(params.probe_spacing) - 0.5;
+
+    for (var axis = 0u; axis < 3u; axis++) {
+        var normal = vec3<f32>(0.0);
+        normal[axis] = 1.0;
+        var tangent = vec3<f32>(0.0);
+        tangent[(axis + 1u) % 3u] = 1.0;
+        let bitangent = cross(normal, tangent);
+
+        // The sides of each axis are stacked along z, the positive ones above the negative ones.
+        let texel = vec3<u32>(probe.x, probe.y, probe.z + axis * resolution.z);
+        let negative = side_irradiance(origin, -normal, tangent, -bitangent);
+        let positive = side_irradiance(origin, normal, tangent, bitangent);
+        textureStore(probes, texel, vec4<f32>(negative, 1.0));
+        textureStore(probes, texel + vec3<u32>(0u, resolution.y, 0u), vec4<f32>(positive, 1.0));
+    }
+}
@@ -0,0 +1,171 @@
+#import bevy_volumetric::types::{CHUNK_SZ, Voxel, VoxelBuffer, ProceduralParams, GraphNode, GraphNodeBuffer, GraphLayerBuffer, GRAPH_NOISE, GRAPH_DOMAIN_WARP, GRAPH_TERRACE, GRAPH_CAVE_CARVE, GRAPH_MATERIAL_ASSIGN}
+
+@group(0) @binding(0) var<uniform> params: ProceduralParams;
+@group(0) @binding(1) var<storage, read_write> voxels: VoxelBuffer;
+// Only bound for the graph entry point.
+@group(0) @binding(2) var<storage, read> nodes: GraphNodeBuffer;
+@group(0) @binding(3) var<storage, read> layers: GraphLayerBuffer;
+
+// Function to hash a lattice cell, the same as the CPU terrain so that both generate the same hills.
+fn hash(cell: vec3<i32>, seed: u32) -> u32 {
+    var h = seed
+        ^ (bitcast<u32>(cell.x) * 0x8da6b343u)
+        ^ (bitcast<u32>(cell.y) * 0xd8163841u)
+        ^ (bitcast<u32>(cell.z) * 0xcb1ab31fu);
+    h ^= h >> 15u;
+    h *= 0x2c1b3c6du;
+    h ^= h >> 12u;
+    h *= 0x297a2d39u;
+    return h ^ (h >> 15u);
+}
+
+var<private> GRADIENTS: array<vec3<f32>, 12> = array<vec3<f32>, 12>(
+    vec3<f32>(1.0, 1.0, 0.0),
+    vec3<f32>(-1.0, 1.0, 0.0),
+    vec3<f32>(1.0, -1.0, 0.0),
+    vec3<f32>(-1.0, -1.0, 0.0),
+    vec3<f32>(1.0, 0.0, 1.0),
+    vec3<f32>(-1.0, 0.0, 1.0),
+    vec3<f32>(1.0, 0.0, -1.0),
+    vec3<f32>(-1.0, 0.0, -1.0),
+    vec3<f32>(0.0, 1.0, 1.0),
+    vec3<f32>(0.0, -1.0, 1.0),
+    vec3<f32>(0.0, 1.0, -1.0),
+    vec3<f32>(0.0, -1.0, -1.0),
+);
+
+// Function to compute Perlin gradient noise at `p`, roughly from -1 to 1.
+fn gradient_noise(p: vec3<f32>, seed: u32) -> f32 {
+    let cell = floor(p);
+    let f = p - cell;
+    let fade = f * f * f * (f * (f * 6.0 - 15.0) + 10.0);
+
+    var noise = 0.0;
+    for (var corner = 0; corner < 8; corner++) {
+        let offset = vec3<i32>(corner & 1, (corner >> 1u) & 1, (corner >> 2u) & 1);
+        let gradient = GRADIENTS[hash(vec3<i32>(cell) + offset, seed) % 12u];
+        let weight = select(vec3<f32>(1.0) - fade, fade, offset == vec3<i32>(1));
+        noise += weight.x * weight.y * weight.z * dot(gradient, f - vec3<f32>(offset));
+    }
+    return noise;
+}
+
+// Function to sum `octaves` of gradient noise, each of twice the frequency and half the amplitude.
+fn fbm(p: vec3<f32>, seed: u32, octaves: u32) -> f32 {
+    var noise = 0.0;
+    var amplitude = 1.0;
+    var frequency = 1.0;
+    var total = 0.0;
+    for (var octave = 0u; octave < octaves; octave++) {
+        noise += gradient_noise(p * frequency, seed + octave) * amplitude;
+        total += amplitude;
+        amplitude *= 0.5;
+        frequency *= 2.0;
+    }
+    return noise / max(total, 1.1920929e-7);
+}
+
+// Generates the voxel at the invocation's index from the heightmap of its column.
+@compute @workgroup_size(256)
+fn main(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
+    let index = invocation_id.x;
+    if (index >= params.voxel_count) {
+        return;
+    }
+
+    let size = u32(CHUNK_SZ);
+    let local = vec3<u32>(index % size, (index / size) % size, index / (size * size));
+    let pos = vec3<f32>(params.origin + vec3<i32>(local));
+
+    let column = vec3<f32>(pos.x, 0.0, pos.z) * params.height_frequency;
+    let height = params.base_height + fbm(column, params.seed, params.octaves) * params.height_amplitude;
+
+    var flags = params.material;
+    if (height - pos.y < params.surface_depth) {
+        flags = params.surface_material;
+    }
+    voxels.data[index] = Voxel(flags, clamp(height - pos.y, 0.0, 1.0), 0u);
+}
+
+// Function to ease `x` from 0 at `edge0` to 1 at `edge1`, the same as the CPU terrain.
+fn smooth_step(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = clamp((x - edge0) / max(edge1 - edge0, 1.1920929e-7), 0.0, 1.0);
+    return t * t * (3.0 - 2.0 * t);
+}
+
+// Function to compute how much of the voxel at `pos` the caves of a cave carving node carve out.
+fn carve(node: GraphNode, pos: vec3<f32>) -> f32 {
+    let values = node.values;
+    let ridge = 1.0 - abs(gradient_noise(pos * values[0], node.seed));
+    let cavern = smooth_step(values[1], 1.0, ridge);
+
+    var worm = 0.0;
+    if (values[3] > 0.0) {
+        let p = pos * values[2];
+        let a = gradient_noise(p, node.seed + 1u);
+        let b = gradient_noise(p, node.seed + 2u);
+        worm = 1.0 - smooth_step(0.0, values[3], length(vec2<f32>(a, b)));
+    }
+    return max(cavern, worm);
+}
+
+// Function to pick the material of the first layer of a material node reaching below `depth`, or
+// else of its last layer.
+fn layer_material(node: GraphNode, depth: f32) -> u32 {
+    let last = node.first_layer + node.layer_count - 1u;
+    for (var layer = node.first_layer; layer < last; layer++) {
+        if (depth < layers.data[layer].depth) {
+            return layers.data[layer].material;
+        }
+    }
+    return layers.data[last].material;
+}
+
+// Generates the voxel at the invocation's index by evaluating the nodes of the graph in order.
+@compute @workgroup_size(256)
+fn graph(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
+    let index = invocation_id.x;
+    if (index >= params.voxel_count) {
+        return;
+    }
+
+    let size = u32(CHUNK_SZ);
+    let local = vec3<u32>(index % size, (index / size) % size, index / (size * size));
+    var pos = vec3<f32>(params.origin + vec3<i32>(local));
+
+    var height = 0.0;
+    var carved = 0.0;
+    var flags = 0u;
+    for (var i = 0u; i < arrayLength(&nodes.data); i++) {
+        let node = nodes.data[i];
+        let values = node.values;
+        if (node.kind == GRAPH_NOISE) {
+            let column = vec3<f32>(pos.x, 0.0, pos.z) * values[2];
+            height += values[0] + fbm(column, node.seed, node.octaves) * values[1];
+        } else if (node.kind == GRAPH_DOMAIN_WARP) {
+            let p = pos * values[1];
+            let warp = vec3<f32>(
+                gradient_noise(p, node.seed),
+                gradient_noise(p, node.seed + 1u),
+                gradient_noise(p, node.seed + 2u),
+            );
+            pos += warp * values[0];
+        } else if (node.kind == GRAPH_TERRACE) {
+            if (values[0] > 0.0) {
+                let t = height / values[0];
+                let base = floor(t);
+                height = (base + pow(t - base, values[1])) * values[0];
+            }
+        } else if (node.kind == GRAPH_CAVE_CARVE) {
+            if (pos.y <= height - values[4]) {
+                carved = max(carved, carve(node, pos));
+            }
+        } else if (node.kind == GRAPH_MATERIAL_ASSIGN) {
+            if (node.layer_count > 0u) {
+                flags = layer_material(node, height - pos.y);
+            }
+        }
+    }
+
+    voxels.data[index] = Voxel(flags, clamp(height - pos.y, 0.0, 1.0) * (1.0 - carved), 0u);
+}
@@ -0,0 +1,79 @@
+#import bevy_volumetric::types::{CHUNK_SZ, VoxelBuffer, PropCandidateParams, PropCandidatePoint, PropCandidateBuffer}
+
+@group(0) @binding(0) var<uniform> params: PropCandidateParams;
+@group(0) @binding(1) var<storage, read> in_voxels: VoxelBuffer;
+@group(0) @binding(2) var<storage, read_write> points: PropCandidateBuffer;
+
+// Function to hash a world voxel, the same as the CPU terrain's lattice hash.
+fn hash(cell: vec3<i32>, seed: u32) -> u32 {
+    var h = seed
+        ^ (bitcast<u32>(cell.x) * 0x8da6b343u)
+        ^ (bitcast<u32>(cell.y) * 0xd8163841u)
+        ^ (bitcast<u32>(cell.z) * 0xcb1ab31fu);
+    h ^= h >> 15u;
+    h *= 0x2c1b3c6du;
+    h ^= h >> 12u;
+    h *= 0x297a2d39u;
+    return h ^ (h >> 15u);
+}
+
+// Function to read the density of the voxel at `pos`, clamped to the chunk.
+fn density_at(pos: vec3<i32>) -> f32 {
+    let p = clamp(pos, vec3<i32>(0), vec3<i32>(CHUNK_SZ - 1));
+    let index = u32(p.x + p.y * CHUNK_SZ + p.z * CHUNK_SZ * CHUNK_SZ);
+    if (index >= arrayLength(&in_voxels.data)) {
+        return 0.0;
+    }
+    return in_voxels.data[index].density;
+}
+
+// Emits a point on top of the invocation's voxel if it is solid under an empty voxel, not too
+// steep and picked by its hash.
+@compute @workgroup_size(256)
+fn main(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
+    let index = invocation_id.x;
+    let size = u32(CHUNK_SZ);
+    if (index >= size * size * size || index >= arrayLength(&in_voxels.data)) {
+        return;
+    }
+
+    let local = vec3<i32>(vec3<u32>(index % size, (index / size) % size, index / (size * size)));
+    // The voxel above is in the chunk above.
+    if (local.y + 1 >= CHUNK_SZ) {
+        return;
+    }
+
+    let voxel = in_voxels.data[index];
+    let above = density_at(local + vec3<i32>(0, 1, 0));
+    if (voxel.density < params.iso_level || above >= params.iso_level) {
+        return;
+    }
+
+    let random = f32(hash(params.origin + local, params.seed) >> 8u) / 16777216.0;
+    if (random >= params.density) {
+        return;
+    }
+
+    // Densities rise into the terrain, so the surface faces against their gradient.
+    let gradient = vec3<f32>(
+        density_at(local + vec3<i32>(1, 0, 0)) - density_at(local - vec3<i32>(1, 0, 0)),
+        density_at(local + vec3<i32>(0, 1, 0)) - density_at(local - vec3<i32>(0, 1, 0)),
+        density_at(local + vec3<i32>(0, 0, 1)) - density_at(local - vec3<i32>(0, 0, 1)),
+    );
+    var normal = vec3<f32>(0.0, 1.0, 0.0);
+    if (dot(gradient, gradient) > 0.0) {
+        normal = -normalize(gradient);
+    }
+    if (normal.y < params.min_normal_y) {
+        return;
+    }
+
+    // The surface crosses the iso-level between the voxel and the one above.
+    let t = (params.iso_level - voxel.density) / (above - voxel.density);
+    let position = vec3<f32>(local) + vec3<f32>(0.0, t, 0.0);
+
+    let slot = atomicAdd(&points.count, 1u);
+    if (slot < params.capacity) {
+        points.data[slot] = PropCandidatePoint(position, acos(clamp(normal.y, -1.0, 1.0)), normal, voxel.flags);
+    }
+}
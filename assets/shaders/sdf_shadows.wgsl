@@ -0,0 +1,98 @@
+#import bevy_pbr::{
+    pbr_fragment::pbr_input_from_standard_material,
+    pbr_functions::alpha_discard,
+}
+
+#ifdef PREPASS_PIPELINE
+#import bevy_pbr::{
+    prepass_io::{VertexOutput, FragmentOutput},
+    pbr_deferred_functions::deferred_output,
+}
+#else
+#import bevy_pbr::{
+    forward_io::{VertexOutput, FragmentOutput},
+    pbr_functions::{apply_pbr_lighting, main_pass_post_lighting_processing},
+}
+#endif
+
+struct SdfShadowParams {
+    volume_from_world: mat4x4<f32>,
+    light_direction: vec3<f32>,
+    cone_angle: f32,
+    strength: f32,
+    max_distance: f32,
+    steps: u32,
+};
+
+@group(2) @binding(100) var<uniform> sdf_shadows: SdfShadowParams;
+@group(2) @binding(101) var distances: texture_3d<f32>;
+
+// Function to get the trilinearly interpolated distance at `pos`, in voxels, as the texture of
+// `R32Float` distances can't be filtered.
+fn sample_distance(pos: vec3<f32>) -> f32 {
+    let max_texel = vec3<i32>(textureDimensions(distances)) - 1;
+    let base = clamp(vec3<i32>(floor(pos)), vec3<i32>(0), max_texel);
+    let t = clamp(pos - vec3<f32>(base), vec3<f32>(0.0), vec3<f32>(1.0));
+
+    var sum = 0.0;
+    for (var corner = 0; corner < 8; corner++) {
+        let offset = vec3<i32>(corner & 1, (corner >> 1u) & 1, corner >> 2u);
+        let texel = min(base + offset, max_texel);
+        let weights = select(1.0 - t, t, offset == vec3<i32>(1));
+        sum += textureLoad(distances, texel, 0).r * weights.x * weights.y * weights.z;
+    }
+    return sum;
+}
+
+// Function to get the visibility of the light from `origin`, from 0 in full shadow to 1, by
+// tracing a cone towards it: the light is occluded by the fraction of the cone covered by the
+// closest surface along the way.
+fn cone_trace(origin: vec3<f32>) -> f32 {
+    let size = vec3<f32>(textureDimensions(distances));
+    let tan_angle = tan(sdf_shadows.cone_angle);
+
+    var visibility = 1.0;
+    var t = 1.0;
+    for (var i = 0u; i < sdf_shadows.steps; i++) {
+        let pos = origin + sdf_shadows.light_direction * t;
+        // Cones leaving the chunk reach the light.
+        if (any(pos < vec3<f32>(0.0)) || any(pos > size - 1.0) || t > sdf_shadows.max_distance) {
+            break;
+        }
+
+        let surface_distance = sample_distance(pos);
+        visibility = min(visibility, surface_distance / (t * tan_angle));
+        if (visibility <= 0.0) {
+            break;
+        }
+        t += max(surface_distance, 0.5);
+    }
+    return smoothstep(0.0, 1.0, clamp(visibility, 0.0, 1.0));
+}
+
+@fragment
+fn fragment(
+    in: VertexOutput,
+    @builtin(front_facing) is_front: bool,
+) -> FragmentOutput {
+    var pbr_input = pbr_input_from_standard_material(in, is_front);
+    pbr_input.material.base_color = alpha_discard(pbr_input.material, pbr_input.material.base_color);
+
+#ifdef PREPASS_PIPELINE
+    let out = deferred_output(in, pbr_input);
+#else
+    var out: FragmentOutput;
+    out.color = apply_pbr_lighting(pbr_input);
+
+    // Start the cones half a voxel off the surface, so that it doesn't shadow itself.
+    let pos = (sdf_shadows.volume_from_world * in.world_position).xyz;
+    let normal = normalize((sdf_shadows.volume_from_world * vec4<f32>(pbr_input.N, 0.0)).xyz);
+    let visibility = cone_trace(pos + normal * 0.5);
+    let shadow = mix(1.0 - sdf_shadows.strength, 1.0, visibility);
+    out.color = vec4<f32>(out.color.rgb * shadow, out.color.a);
+
+    out.color = main_pass_post_lighting_processing(pbr_input, out.color);
+#endif
+
+    return out;
+}
@@ -0,0 +1,140 @@
+#import bevy_volumetric::types::{CellSlotBuffer, SlotCompactionParams, SlotStreamParams, MAX_MATERIALS, SLOT_BLOCK_CELLS}
+
+// The atomics buffer of the meshed entity, written without atomics once its slots are compacted.
+struct MeshCounters {
+    vertices_head: u32,
+    indices_head: u32,
+    material_index_counts: array<u32, MAX_MATERIALS>,
+};
+
+struct BlockOffsets {
+    data: array<vec2<u32>>,
+};
+
+struct Words {
+    data: array<u32>,
+};
+
+@group(0) @binding(0) var<uniform> params: SlotCompactionParams;
+@group(0) @binding(1) var<storage, read_write> cell_slots: CellSlotBuffer;
+@group(0) @binding(2) var<storage, read_write> blocks: BlockOffsets;
+@group(0) @binding(3) var<storage, read_write> counters: MeshCounters;
+
+@group(1) @binding(0) var<uniform> stream: SlotStreamParams;
+@group(1) @binding(1) var<storage, read> slots: Words;
+@group(1) @binding(2) var<storage, read_write> compacted: Words;
+
+// Sums the vertices and indices of each block of cells, storing where each cell starts relative
+// to its block.
+@compute @workgroup_size(64)
+fn count_blocks(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
+    let block = invocation_id.x;
+    let first = block * SLOT_BLOCK_CELLS;
+    if (first >= params.cells) {
+        return;
+    }
+
+    var total = vec2<u32>(0u);
+    for (var cell = first; cell < min(first + SLOT_BLOCK_CELLS, params.cells); cell++) {
+        cell_slots.data[cell].vertex_offset = total.x;
+        cell_slots.data[cell].index_offset = total.y;
+        total += vec2<u32>(cell_slots.data[cell].vertices, cell_slots.data[cell].indices);
+    }
+    blocks.data[block] = total;
+}
+
+// Turns the sums of the blocks into where each block starts, and counts the vertices and indices
+// of the leading cells fitting in the output buffers. The cells past them are dropped.
+@compute @workgroup_size(1)
+fn scan_blocks() {
+    let capacity = vec2<u32>(params.vertex_capacity, params.index_capacity);
+    let block_count = (params.cells + SLOT_BLOCK_CELLS - 1u) / SLOT_BLOCK_CELLS;
+
+    var offset = vec2<u32>(0u);
+    var fitting = vec2<u32>(0u);
+    var full = false;
+    for (var block = 0u; block < block_count; block++) {
+        let sum = blocks.data[block];
+        blocks.data[block] = offset;
+
+        if (!full && all(offset + sum <= capacity)) {
+            fitting = offset + sum;
+        } else if (!full) {
+            // Find the last cell of the block that still fits.
+            full = true;
+            let first = block * SLOT_BLOCK_CELLS;
+            for (var cell = first; cell < min(first + SLOT_BLOCK_CELLS, params.cells); cell++) {
+                let slot = cell_slots.data[cell];
+                let end = offset + vec2<u32>(slot.vertex_offset + slot.vertices, slot.index_offset + slot.indices);
+                if (any(end > capacity)) {
+                    break;
+                }
+                fitting = end;
+            }
+        }
+        offset += sum;
+    }
+
+    counters.vertices_head = fitting.x;
+    counters.indices_head = fitting.y;
+    for (var m = 0u; m < MAX_MATERIALS; m++) {
+        counters.material_index_counts[m] = 0u;
+    }
+}
+
+// Copies the slot of a cell in one of the output buffers to where the cell starts once compacted,
+// rebasing indices onto the compacted vertices of the cell.
+@compute @workgroup_size(64)
+fn scatter(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
+    let cell = invocation_id.x;
+    if (cell >= params.cells) {
+        return;
+    }
+
+    let slot = cell_slots.data[cell];
+    let block = blocks.data[cell / SLOT_BLOCK_CELLS];
+    let vertex_offset = block.x + slot.vertex_offset;
+    let index_offset = block.y + slot.index_offset;
+    if (vertex_offset + slot.vertices > counters.vertices_head || index_offset + slot.indices > counters.indices_head) {
+        return;
+    }
+
+    var count = slot.vertices;
+    var src = cell * params.slot_vertices;
+    var dst = vertex_offset;
+    var rebase = 0u;
+    if (stream.indices != 0u) {
+        count = slot.indices;
+        src = cell * params.slot_indices;
+        dst = index_offset;
+        rebase = vertex_offset;
+    }
+
+    for (var element = 0u; element < count; element++) {
+        for (var word = 0u; word < stream.words; word++) {
+            compacted.data[(dst + element) * stream.words + word] = slots.data[(src + element) * stream.words + word] + rebase;
+        }
+    }
+}
+
+// Turns where each cell starts relative to its block into where it starts in the output buffers,
+// for the scatter pass of a prefix sum allocation. Cells past the end of the output buffers are
+// left no room.
+@compute @workgroup_size(64)
+fn resolve_offsets(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
+    let cell = invocation_id.x;
+    if (cell >= params.cells) {
+        return;
+    }
+
+    let slot = cell_slots.data[cell];
+    let block = blocks.data[cell / SLOT_BLOCK_CELLS];
+    let vertex_offset = block.x + slot.vertex_offset;
+    let index_offset = block.y + slot.index_offset;
+    cell_slots.data[cell].vertex_offset = vertex_offset;
+    cell_slots.data[cell].index_offset = index_offset;
+    if (vertex_offset + slot.vertices > counters.vertices_head || index_offset + slot.indices > counters.indices_head) {
+        cell_slots.data[cell].vertices = 0u;
+        cell_slots.data[cell].indices = 0u;
+    }
+}
@@ -0,0 +1,47 @@
+#import bevy_volumetric::types::{CHUNK_SZ, VoxelBuffer, SplatMapParams}
+
+@group(0) @binding(0) var<uniform> params: SplatMapParams;
+@group(0) @binding(1) var<storage, read> in_voxels: VoxelBuffer;
+@group(0) @binding(2) var splat_map: texture_storage_3d<rgba8unorm, write>;
+
+// Function to get a flat index for a given position in the 3D grid.
+fn get_flat_index(pos: vec3<i32>) -> u32 {
+    return u32(pos.x + pos.y * CHUNK_SZ + pos.z * CHUNK_SZ * CHUNK_SZ);
+}
+
+// Each invocation weights the materials of the solid voxels of one splat texel, along with those
+// of the voxels a voxel past its faces so that the weights blend across texels.
+@compute @workgroup_size(4, 4, 4)
+fn main(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
+    let texel = vec3<i32>(invocation_id);
+
+    // Skip invocations outside of the splat texture.
+    if (any(texel >= vec3<i32>(textureDimensions(splat_map)))) {
+        return;
+    }
+
+    let cell_size = i32(params.cell_size);
+    let min_voxel = max(texel * cell_size - 1, vec3<i32>(0));
+    let max_voxel = min((texel + 1) * cell_size + 1, vec3<i32>(CHUNK_SZ));
+
+    var counts = vec4<f32>(0.0);
+    for (var z = min_voxel.z; z < max_voxel.z; z++) {
+        for (var y = min_voxel.y; y < max_voxel.y; y++) {
+            for (var x = min_voxel.x; x < max_voxel.x; x++) {
+                let voxel = in_voxels.data[get_flat_index(vec3<i32>(x, y, z))];
+                if (voxel.density < params.iso_level) {
+                    continue;
+                }
+                counts += select(vec4<f32>(0.0), vec4<f32>(1.0), params.materials == vec4<u32>(voxel.flags));
+            }
+        }
+    }
+
+    // Texels without any solid voxel of the materials keep no weight.
+    let total = counts.x + counts.y + counts.z + counts.w;
+    var weights = vec4<f32>(0.0);
+    if (total > 0.0) {
+        weights = counts / total;
+    }
+    textureStore(splat_map, texel, weights);
+}
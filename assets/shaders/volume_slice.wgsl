@@ -0,0 +1,57 @@
+#import bevy_pbr::forward_io::VertexOutput
+
+struct VolumeSliceParams {
+    volume_from_world: mat4x4<f32>,
+    density_range: vec2<f32>,
+    color_map: u32,
+};
+
+@group(2) @binding(0) var<uniform> params: VolumeSliceParams;
+@group(2) @binding(1) var densities: texture_3d<f32>;
+
+const COLOR_MAP_VIRIDIS: u32 = 1u;
+const COLOR_MAP_HOT: u32 = 2u;
+
+// Function to approximate the viridis colour map with a polynomial fit.
+fn viridis(t: f32) -> vec3<f32> {
+    let c0 = vec3<f32>(0.2777273, 0.0054073, 0.3340998);
+    let c1 = vec3<f32>(0.1050930, 1.4046134, 1.3845901);
+    let c2 = vec3<f32>(-0.3308618, 0.2148475, 0.0950952);
+    let c3 = vec3<f32>(-4.6342305, -5.7991009, -19.3324409);
+    let c4 = vec3<f32>(6.2282699, 14.1799333, 56.6905526);
+    let c5 = vec3<f32>(4.7763845, -13.7451460, -65.3530326);
+    let c6 = vec3<f32>(-5.4354559, 4.6458523, 26.3124352);
+    return c0 + t * (c1 + t * (c2 + t * (c3 + t * (c4 + t * (c5 + t * c6)))));
+}
+
+// Function to map `t` in [0, 1] to a colour of the selected colour map.
+fn color_map(t: f32) -> vec3<f32> {
+    switch (params.color_map) {
+        case COLOR_MAP_VIRIDIS: {
+            return viridis(t);
+        }
+        case COLOR_MAP_HOT: {
+            return clamp(vec3<f32>(t * 3.0, t * 3.0 - 1.0, t * 3.0 - 2.0), vec3<f32>(0.0), vec3<f32>(1.0));
+        }
+        default: {
+            return vec3<f32>(t);
+        }
+    }
+}
+
+// Colours each fragment of the slice by the density of the nearest voxel, discarding fragments
+// outside of the volume.
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    let pos = (params.volume_from_world * in.world_position).xyz;
+    let voxel = vec3<i32>(round(pos));
+
+    if (any(voxel < vec3<i32>(0)) || any(voxel >= vec3<i32>(textureDimensions(densities)))) {
+        discard;
+    }
+
+    let density = textureLoad(densities, voxel, 0).r;
+    let range = params.density_range;
+    let t = clamp((density - range.x) / max(range.y - range.x, 1e-6), 0.0, 1.0);
+    return vec4<f32>(color_map(t), 1.0);
+}
@@ -0,0 +1,68 @@
+#import bevy_volumetric::types::{HISTOGRAM_BINS, VoxelBuffer, VolumeHistogram, VolumeStatisticsParams}
+
+@group(0) @binding(0) var<uniform> params: VolumeStatisticsParams;
+@group(0) @binding(1) var<storage, read> in_voxels: VoxelBuffer;
+@group(0) @binding(2) var<storage, read_write> statistics: VolumeHistogram;
+
+var<workgroup> local_bins: array<atomic<u32>, HISTOGRAM_BINS>;
+
+// Function to map a density to a key whose unsigned order is the order of the densities.
+fn order_key(density: f32) -> u32 {
+    let bits = bitcast<u32>(density);
+    if ((bits & 0x80000000u) != 0u) {
+        return ~bits;
+    }
+    return bits | 0x80000000u;
+}
+
+// Function to map an order key back to its density.
+fn from_order_key(key: u32) -> f32 {
+    if ((key & 0x80000000u) != 0u) {
+        return bitcast<f32>(key & 0x7fffffffu);
+    }
+    return bitcast<f32>(~key);
+}
+
+// Finds the density range of the voxels.
+@compute @workgroup_size(HISTOGRAM_BINS)
+fn range(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
+    let index = invocation_id.x;
+    if (index >= params.voxel_count) {
+        return;
+    }
+
+    let key = order_key(in_voxels.data[index].density);
+    atomicMax(&statistics.min_key, ~key);
+    atomicMax(&statistics.max_key, key);
+}
+
+// Counts the voxels of each bin in workgroup memory first, then adds every bin of the workgroup to
+// the histogram at once.
+@compute @workgroup_size(HISTOGRAM_BINS)
+fn histogram(
+    @builtin(global_invocation_id) invocation_id: vec3<u32>,
+    @builtin(local_invocation_index) local_index: u32,
+) {
+    atomicStore(&local_bins[local_index], 0u);
+    workgroupBarrier();
+
+    let index = invocation_id.x;
+    if (index < params.voxel_count) {
+        let low = from_order_key(~atomicLoad(&statistics.min_key));
+        let high = from_order_key(atomicLoad(&statistics.max_key));
+        let density = in_voxels.data[index].density;
+
+        var bin = 0u;
+        if (high > low) {
+            let position = (density - low) / (high - low) * f32(HISTOGRAM_BINS);
+            bin = min(u32(position), HISTOGRAM_BINS - 1u);
+        }
+        atomicAdd(&local_bins[bin], 1u);
+    }
+    workgroupBarrier();
+
+    let count = atomicLoad(&local_bins[local_index]);
+    if (count > 0u) {
+        atomicAdd(&statistics.bins[local_index], count);
+    }
+}
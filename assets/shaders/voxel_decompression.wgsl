@@ -0,0 +1,32 @@
+#import bevy_volumetric::types::{Voxel, VoxelBuffer, VoxelRun, VoxelRunBuffer, DecompressionParams}
+
+@group(0) @binding(0) var<uniform> params: DecompressionParams;
+@group(0) @binding(1) var<storage, read> palette: VoxelBuffer;
+@group(0) @binding(2) var<storage, read> runs: VoxelRunBuffer;
+@group(0) @binding(3) var<storage, read_write> voxels: VoxelBuffer;
+
+// Function to find the run holding the voxel at `index`: the first run ending after it.
+fn find_run(index: u32) -> u32 {
+    var low = 0u;
+    var high = params.run_count - 1u;
+    while (low < high) {
+        let mid = (low + high) / 2u;
+        if (runs.data[mid].end > index) {
+            high = mid;
+        } else {
+            low = mid + 1u;
+        }
+    }
+    return low;
+}
+
+// Expands the voxel at the invocation's index from its run and the palette.
+@compute @workgroup_size(256)
+fn main(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
+    let index = invocation_id.x;
+    if (index >= params.voxel_count) {
+        return;
+    }
+
+    voxels.data[index] = palette.data[runs.data[find_run(index)].palette_index];
+}
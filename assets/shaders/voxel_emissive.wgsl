@@ -0,0 +1,122 @@
+#import bevy_pbr::{
+    mesh_functions,
+    view_transformations::position_world_to_clip,
+    pbr_fragment::pbr_input_from_standard_material,
+    pbr_functions::{alpha_discard, apply_pbr_lighting, main_pass_post_lighting_processing},
+    forward_io::{VertexOutput, FragmentOutput},
+}
+
+@group(2) @binding(100) var<uniform> strength: f32;
+
+// The vertex of a volumetric mesh. Voxel meshes are neither skinned nor morphed.
+struct Vertex {
+    @builtin(instance_index) instance_index: u32,
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+#ifdef VERTEX_UVS_A
+    @location(2) uv: vec2<f32>,
+#endif
+#ifdef VERTEX_TANGENTS
+    @location(4) tangent: vec4<f32>,
+#endif
+#ifdef VOXEL_EMISSIVE
+    @location(8) emissive: vec3<f32>,
+#endif
+};
+
+// The `VertexOutput` of bevy with the emissive color of the vertex, as structs can't be nested in
+// the inputs of a fragment shader.
+struct EmissiveVertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) world_position: vec4<f32>,
+    @location(1) world_normal: vec3<f32>,
+#ifdef VERTEX_UVS_A
+    @location(2) uv: vec2<f32>,
+#endif
+#ifdef VERTEX_TANGENTS
+    @location(4) world_tangent: vec4<f32>,
+#endif
+#ifdef VERTEX_OUTPUT_INSTANCE_INDEX
+    @location(6) @interpolate(flat) instance_index: u32,
+#endif
+#ifdef VISIBILITY_RANGE_DITHER
+    @location(7) @interpolate(flat) visibility_range_dither: i32,
+#endif
+#ifdef VOXEL_EMISSIVE
+    @location(8) emissive: vec3<f32>,
+#endif
+};
+
+@vertex
+fn vertex(vertex: Vertex) -> EmissiveVertexOutput {
+    var out: EmissiveVertexOutput;
+
+    let world_from_local = mesh_functions::get_world_from_local(vertex.instance_index);
+    out.world_normal = mesh_functions::mesh_normal_local_to_world(vertex.normal, vertex.instance_index);
+    out.world_position = mesh_functions::mesh_position_local_to_world(world_from_local, vec4<f32>(vertex.position, 1.0));
+    out.position = position_world_to_clip(out.world_position.xyz);
+
+#ifdef VERTEX_UVS_A
+    out.uv = vertex.uv;
+#endif
+#ifdef VERTEX_TANGENTS
+    out.world_tangent = mesh_functions::mesh_tangent_local_to_world(
+        world_from_local,
+        vertex.tangent,
+        vertex.instance_index
+    );
+#endif
+#ifdef VERTEX_OUTPUT_INSTANCE_INDEX
+    out.instance_index = vertex.instance_index;
+#endif
+#ifdef VISIBILITY_RANGE_DITHER
+    out.visibility_range_dither = mesh_functions::get_visibility_range_dither_level(
+        vertex.instance_index, world_from_local[3]);
+#endif
+#ifdef VOXEL_EMISSIVE
+    out.emissive = vertex.emissive;
+#endif
+
+    return out;
+}
+
+// Function to get the `VertexOutput` the standard material is shaded from.
+fn mesh_output(voxel_in: EmissiveVertexOutput) -> VertexOutput {
+    var out: VertexOutput;
+    out.position = voxel_in.position;
+    out.world_position = voxel_in.world_position;
+    out.world_normal = voxel_in.world_normal;
+#ifdef VERTEX_UVS_A
+    out.uv = voxel_in.uv;
+#endif
+#ifdef VERTEX_TANGENTS
+    out.world_tangent = voxel_in.world_tangent;
+#endif
+#ifdef VERTEX_OUTPUT_INSTANCE_INDEX
+    out.instance_index = voxel_in.instance_index;
+#endif
+#ifdef VISIBILITY_RANGE_DITHER
+    out.visibility_range_dither = voxel_in.visibility_range_dither;
+#endif
+    return out;
+}
+
+@fragment
+fn fragment(
+    voxel_in: EmissiveVertexOutput,
+    @builtin(front_facing) is_front: bool,
+) -> FragmentOutput {
+    let in = mesh_output(voxel_in);
+    var pbr_input = pbr_input_from_standard_material(in, is_front);
+    pbr_input.material.base_color = alpha_discard(pbr_input.material, pbr_input.material.base_color);
+
+#ifdef VOXEL_EMISSIVE
+    // Leave the alpha alone, as it weighs the exposure applied to the emissive light.
+    pbr_input.material.emissive += vec4<f32>(voxel_in.emissive * strength, 0.0);
+#endif
+
+    var out: FragmentOutput;
+    out.color = apply_pbr_lighting(pbr_input);
+    out.color = main_pass_post_lighting_processing(pbr_input, out.color);
+    return out;
+}
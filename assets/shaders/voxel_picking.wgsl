@@ -0,0 +1,96 @@
+#import bevy_volumetric::types::{IndexBuffer, VertexBuffer, VoxelPickParams, VoxelPickResult, MAX_MATERIALS}
+
+// The atomics buffer of the picked entity, only read once it is meshed.
+struct MeshCounters {
+    vertices_head: u32,
+    indices_head: u32,
+    material_index_counts: array<u32, MAX_MATERIALS>,
+};
+
+const NONE: u32 = 0xffffffffu;
+
+@group(0) @binding(0) var<uniform> params: VoxelPickParams;
+@group(0) @binding(1) var<storage, read> vertices: VertexBuffer;
+@group(0) @binding(2) var<storage, read> indices: IndexBuffer;
+@group(0) @binding(3) var<storage, read> counters: MeshCounters;
+@group(0) @binding(4) var<storage, read_write> result: VoxelPickResult;
+
+// Where the indices of the `triangle`-th triangle start, or NONE if the meshing pass did not write
+// it. A split index buffer is filled from the start of the segment of each material.
+fn first_index(triangle: u32) -> u32 {
+    if (params.material_split == 0u) {
+        let first = triangle * 3u;
+        return select(NONE, first, first + 3u <= counters.indices_head);
+    }
+
+    let segment = arrayLength(&indices.data) / MAX_MATERIALS;
+    let segment_triangles = segment / 3u;
+    if (segment_triangles == 0u) {
+        return NONE;
+    }
+    let m = triangle / segment_triangles;
+    if (m >= MAX_MATERIALS) {
+        return NONE;
+    }
+    let offset = (triangle % segment_triangles) * 3u;
+    return select(NONE, m * segment + offset, offset + 3u <= min(counters.material_index_counts[m], segment));
+}
+
+// The distance along the ray to the triangle whose indices start at `first`, negative if the ray
+// misses it. Both faces are hit.
+fn intersect(first: u32) -> f32 {
+    let a = vertices.data[indices.data[first]];
+    let e1 = vertices.data[indices.data[first + 1u]] - a;
+    let e2 = vertices.data[indices.data[first + 2u]] - a;
+
+    let p = cross(params.direction, e2);
+    let det = dot(e1, p);
+    if (det == 0.0) {
+        return -1.0;
+    }
+    let inv_det = 1.0 / det;
+
+    let s = params.origin - a;
+    let u = dot(s, p) * inv_det;
+    if (u < 0.0 || u > 1.0) {
+        return -1.0;
+    }
+    let q = cross(s, e1);
+    let v = dot(params.direction, q) * inv_det;
+    if (v < 0.0 || u + v > 1.0) {
+        return -1.0;
+    }
+    return dot(e2, q) * inv_det;
+}
+
+// Keeps the nearest distance hit. Positive floats order as their bits.
+@compute @workgroup_size(64)
+fn nearest(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
+    if (invocation_id.x >= params.triangles) {
+        return;
+    }
+    let first = first_index(invocation_id.x);
+    if (first == NONE) {
+        return;
+    }
+    let distance = intersect(first);
+    if (distance >= 0.0) {
+        atomicMin(&result.distance_key, bitcast<u32>(distance));
+    }
+}
+
+// Keeps the first triangle hit at the nearest distance.
+@compute @workgroup_size(64)
+fn identify(@builtin(global_invocation_id) invocation_id: vec3<u32>) {
+    if (invocation_id.x >= params.triangles) {
+        return;
+    }
+    let first = first_index(invocation_id.x);
+    if (first == NONE) {
+        return;
+    }
+    let distance = intersect(first);
+    if (distance >= 0.0 && bitcast<u32>(distance) == atomicLoad(&result.distance_key)) {
+        atomicMin(&result.first_index, first);
+    }
+}
@@ -0,0 +1,154 @@
+use glam::{UVec3, Vec3};
+
+use crate::{grid::VoxelGrid, voxel::Voxel};
+
+/// A solid defined by its signed distance, negative inside, in voxels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Shape {
+    Sphere {
+        center: Vec3,
+        radius: f32,
+    },
+    /// A box spanning `half_extents` on each side of `center`.
+    Cuboid {
+        center: Vec3,
+        half_extents: Vec3,
+    },
+    /// A capsule of `radius` around the segment from `start` to `end`.
+    Capsule {
+        start: Vec3,
+        end: Vec3,
+        radius: f32,
+    },
+}
+
+impl Shape {
+    /// Signed distance from `pos` to the surface of the shape.
+    pub fn distance(&self, pos: Vec3) -> f32 {
+        match *self {
+            Shape::Sphere { center, radius } => pos.distance(center) - radius,
+            Shape::Cuboid {
+                center,
+                half_extents,
+            } => {
+                let q = (pos - center).abs() - half_extents;
+                q.max(Vec3::ZERO).length() + q.max_element().min(0.0)
+            }
+            Shape::Capsule { start, end, radius } => {
+                let segment = end - start;
+                let length_squared = segment.length_squared();
+                let t = match length_squared > 0.0 {
+                    true => ((pos - start).dot(segment) / length_squared).clamp(0.0, 1.0),
+                    false => 0.0,
+                };
+                pos.distance(start + segment * t) - radius
+            }
+        }
+    }
+
+    /// The first voxel the shape may cover and the voxel past the last one, clamped to `size`.
+    pub fn bounds(&self, size: UVec3) -> (UVec3, UVec3) {
+        let (min, max) = match *self {
+            Shape::Sphere { center, radius } => (center - radius, center + radius),
+            Shape::Cuboid {
+                center,
+                half_extents,
+            } => (center - half_extents, center + half_extents),
+            Shape::Capsule { start, end, radius } => {
+                (start.min(end) - radius, start.max(end) + radius)
+            }
+        };
+        // Densities ramp over a voxel on each side of the surface.
+        let min = (min - 1.0).floor().max(Vec3::ZERO).as_uvec3().min(size);
+        let max = (max + 1.0).ceil().max(Vec3::ZERO).as_uvec3().min(size);
+        (min, max)
+    }
+}
+
+/// How a [`Csg`] shape is combined with the voxels.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum CsgOp {
+    /// Adds the shape, e.g. building.
+    #[default]
+    Union,
+    /// Removes the shape, e.g. digging.
+    Subtract,
+    /// Keeps only the voxels inside the shape.
+    Intersect,
+}
+
+/// A constructive solid geometry operation on a grid of voxels, solid where their density is at
+/// least `iso_level`. The density of the shape ramps linearly over a voxel across its surface, so
+/// that the meshers place the surface between voxels rather than on them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Csg {
+    pub shape: Shape,
+    pub op: CsgOp,
+    pub iso_level: f32,
+    /// Flags given to the voxels made solid by a union, the others keep theirs.
+    pub flags: Option<u32>,
+}
+
+impl Csg {
+    pub fn new(shape: Shape, op: CsgOp) -> Self {
+        Self {
+            shape,
+            op,
+            iso_level: 0.5,
+            flags: None,
+        }
+    }
+
+    /// Applies the operation to `voxels`, laid out in `grid`, returning the first voxel changed and
+    /// the voxel past the last one, if any voxel changed.
+    pub fn apply(&self, grid: VoxelGrid, voxels: &mut [Voxel]) -> Option<(UVec3, UVec3)> {
+        if voxels.len() < grid.len() {
+            return None;
+        }
+
+        let (min, max) = match self.op {
+            CsgOp::Intersect => (UVec3::ZERO, grid.size),
+            CsgOp::Union | CsgOp::Subtract => self.shape.bounds(grid.size),
+        };
+
+        let mut changed: Option<(UVec3, UVec3)> = None;
+        for z in min.z..max.z {
+            for y in min.y..max.y {
+                for x in min.x..max.x {
+                    let pos = UVec3::new(x, y, z);
+                    let index = grid.index(pos);
+                    let voxel = voxels[index];
+                    let distance = self.shape.distance(pos.as_vec3());
+                    let inside = (self.iso_level - distance).clamp(0.0, 1.0);
+                    let outside = (self.iso_level + distance).clamp(0.0, 1.0);
+
+                    let density = match self.op {
+                        CsgOp::Union => voxel.density().max(inside),
+                        CsgOp::Subtract => voxel.density().min(outside),
+                        CsgOp::Intersect => voxel.density().min(inside),
+                    };
+                    let flags = match (self.op, self.flags) {
+                        (CsgOp::Union, Some(flags))
+                            if voxel.density() < self.iso_level && density >= self.iso_level =>
+                        {
+                            flags
+                        }
+                        _ => voxel.flags(),
+                    };
+
+                    if density == voxel.density() && flags == voxel.flags() {
+                        continue;
+                    }
+                    voxels[index] = Voxel::new(flags, density);
+                    changed = Some(match changed {
+                        Some((changed_min, changed_max)) => {
+                            (changed_min.min(pos), changed_max.max(pos + UVec3::ONE))
+                        }
+                        None => (pos, pos + UVec3::ONE),
+                    });
+                }
+            }
+        }
+        changed
+    }
+}
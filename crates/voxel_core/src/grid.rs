@@ -0,0 +1,150 @@
+use glam::{IVec3, UVec3, Vec3Swizzles};
+use serde::{Deserialize, Serialize};
+
+/// An axis of a voxel grid.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum VoxelAxis {
+    X,
+    #[default]
+    Y,
+    Z,
+}
+
+impl VoxelAxis {
+    pub fn index(self) -> usize {
+        match self {
+            VoxelAxis::X => 0,
+            VoxelAxis::Y => 1,
+            VoxelAxis::Z => 2,
+        }
+    }
+}
+
+/// Transforms of a grid of `size` voxels laid out x first, then y, then z, like the voxels of a
+/// chunk's [`Voxel`](crate::voxel::Voxel)s. They are generic over the voxel type so
+/// that any auxiliary per-voxel layer stored alongside the voxels, e.g. material ids, can be given
+/// the exact same transform and stay aligned with them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VoxelGrid {
+    pub size: UVec3,
+}
+
+impl VoxelGrid {
+    pub fn new(size: UVec3) -> Self {
+        Self { size }
+    }
+
+    pub fn len(&self) -> usize {
+        self.size.x as usize * self.size.y as usize * self.size.z as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn index(&self, pos: UVec3) -> usize {
+        pos.x as usize
+            + pos.y as usize * self.size.x as usize
+            + pos.z as usize * self.size.x as usize * self.size.y as usize
+    }
+
+    pub fn position(&self, index: usize) -> UVec3 {
+        let x = self.size.x as usize;
+        let xy = x * self.size.y as usize;
+        UVec3::new(
+            (index % x) as u32,
+            ((index % xy) / x) as u32,
+            (index / xy) as u32,
+        )
+    }
+
+    /// The size of the grid after [`VoxelGrid::rotate_90`].
+    pub fn rotated_size(&self, axis: VoxelAxis, turns: u32) -> UVec3 {
+        match (axis, turns % 2) {
+            (_, 0) => self.size,
+            (VoxelAxis::X, _) => self.size.xzy(),
+            (VoxelAxis::Y, _) => self.size.zyx(),
+            (VoxelAxis::Z, _) => self.size.yxz(),
+        }
+    }
+
+    /// Where the voxel at `pos` ends up after a single counterclockwise quarter turn around
+    /// `axis`, looking down the axis.
+    fn quarter_turn(size: UVec3, axis: VoxelAxis, pos: UVec3) -> UVec3 {
+        match axis {
+            VoxelAxis::X => UVec3::new(pos.x, size.z - 1 - pos.z, pos.y),
+            VoxelAxis::Y => UVec3::new(pos.z, pos.y, size.x - 1 - pos.x),
+            VoxelAxis::Z => UVec3::new(size.y - 1 - pos.y, pos.x, pos.z),
+        }
+    }
+
+    /// Where the voxel at `pos` ends up after [`VoxelGrid::rotate_90`].
+    pub fn rotate_position(&self, pos: UVec3, axis: VoxelAxis, turns: u32) -> UVec3 {
+        let mut size = self.size;
+        let mut pos = pos;
+        for _ in 0..turns % 4 {
+            pos = Self::quarter_turn(size, axis, pos);
+            size = VoxelGrid::new(size).rotated_size(axis, 1);
+        }
+        pos
+    }
+
+    /// Rotates `voxels` by `turns` counterclockwise quarter turns around `axis`, returning the
+    /// rotated voxels, whose grid has the [`VoxelGrid::rotated_size`].
+    pub fn rotate_90<T: Copy>(&self, voxels: &[T], axis: VoxelAxis, turns: u32) -> Vec<T> {
+        let mut size = self.size;
+        let mut voxels = voxels.to_vec();
+        if voxels.len() < self.len() {
+            return voxels;
+        }
+
+        for _ in 0..turns % 4 {
+            let rotated_size = VoxelGrid::new(size).rotated_size(axis, 1);
+            let (grid, rotated_grid) = (VoxelGrid::new(size), VoxelGrid::new(rotated_size));
+
+            let mut rotated = voxels.clone();
+            for (index, voxel) in voxels.iter().enumerate().take(grid.len()) {
+                let pos = Self::quarter_turn(size, axis, grid.position(index));
+                rotated[rotated_grid.index(pos)] = *voxel;
+            }
+
+            voxels = rotated;
+            size = rotated_size;
+        }
+
+        voxels
+    }
+
+    /// Mirrors `voxels` across the middle of the grid along `axis`.
+    pub fn mirror_axis<T>(&self, voxels: &mut [T], axis: VoxelAxis) {
+        if voxels.len() < self.len() {
+            return;
+        }
+
+        let extent = self.size[axis.index()];
+        for index in 0..self.len() {
+            let pos = self.position(index);
+            if pos[axis.index()] >= extent / 2 {
+                continue;
+            }
+
+            let mut mirrored = pos;
+            mirrored[axis.index()] = extent - 1 - pos[axis.index()];
+            voxels.swap(index, self.index(mirrored));
+        }
+    }
+
+    /// Moves `voxels` by `offset`, wrapping the voxels leaving the grid around to its other side.
+    pub fn translate_wrap<T: Copy>(&self, voxels: &mut [T], offset: IVec3) {
+        if self.is_empty() || voxels.len() < self.len() {
+            return;
+        }
+
+        let size = self.size.as_ivec3();
+        let source = voxels[..self.len()].to_vec();
+        for (index, voxel) in source.into_iter().enumerate() {
+            let pos = (self.position(index).as_ivec3() + offset).rem_euclid(size);
+            voxels[self.index(pos.as_uvec3())] = voxel;
+        }
+    }
+}
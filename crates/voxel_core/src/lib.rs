@@ -0,0 +1,13 @@
+//! The voxel data structures, CPU meshers, CSG and serialization of `compute_mesh`, without any
+//! dependency on Bevy, so that servers and command line tools can link just the voxel logic.
+//! The Bevy plugin re-exports this crate as `compute_mesh::core` and builds on its types.
+
+pub mod csg;
+pub mod grid;
+pub mod mesher;
+pub mod serialization;
+pub mod tables;
+pub mod voxel;
+
+pub use glam;
+pub use voxel::{voxel_index, Voxel, CHUNK_SZ, CHUNK_SZ_2, CHUNK_SZ_3};
@@ -0,0 +1,358 @@
+use glam::{IVec3, Vec3, Vec4};
+
+use crate::{
+    tables::TRI_TABLE,
+    voxel::{voxel_index, Voxel, CHUNK_SZ},
+};
+
+use super::{BoundaryMode, EdgeInterpolation, MeshingAlgorithm, BISECTION_STEPS};
+
+/// Corners of a marching cubes cell, in the order of the edge and triangle tables.
+const CORNER_OFFSETS: [IVec3; 8] = [
+    IVec3::new(0, 0, 1),
+    IVec3::new(1, 0, 1),
+    IVec3::new(1, 0, 0),
+    IVec3::new(0, 0, 0),
+    IVec3::new(0, 1, 1),
+    IVec3::new(1, 1, 1),
+    IVec3::new(1, 1, 0),
+    IVec3::new(0, 1, 0),
+];
+
+/// The two corners of each of the 12 edges of a marching cubes cell.
+const EDGE_CORNERS: [(usize, usize); 12] = [
+    (0, 1),
+    (1, 2),
+    (2, 3),
+    (3, 0),
+    (4, 5),
+    (5, 6),
+    (6, 7),
+    (7, 4),
+    (0, 4),
+    (1, 5),
+    (2, 6),
+    (3, 7),
+];
+
+/// The outward direction and the four corners, around the voxel center, of each block face.
+const BLOCK_FACES: [(IVec3, [Vec3; 4]); 6] = [
+    (
+        IVec3::X,
+        [
+            Vec3::new(0.5, -0.5, -0.5),
+            Vec3::new(0.5, 0.5, -0.5),
+            Vec3::new(0.5, 0.5, 0.5),
+            Vec3::new(0.5, -0.5, 0.5),
+        ],
+    ),
+    (
+        IVec3::NEG_X,
+        [
+            Vec3::new(-0.5, -0.5, 0.5),
+            Vec3::new(-0.5, 0.5, 0.5),
+            Vec3::new(-0.5, 0.5, -0.5),
+            Vec3::new(-0.5, -0.5, -0.5),
+        ],
+    ),
+    (
+        IVec3::Y,
+        [
+            Vec3::new(-0.5, 0.5, 0.5),
+            Vec3::new(0.5, 0.5, 0.5),
+            Vec3::new(0.5, 0.5, -0.5),
+            Vec3::new(-0.5, 0.5, -0.5),
+        ],
+    ),
+    (
+        IVec3::NEG_Y,
+        [
+            Vec3::new(-0.5, -0.5, -0.5),
+            Vec3::new(0.5, -0.5, -0.5),
+            Vec3::new(0.5, -0.5, 0.5),
+            Vec3::new(-0.5, -0.5, 0.5),
+        ],
+    ),
+    (
+        IVec3::Z,
+        [
+            Vec3::new(0.5, -0.5, 0.5),
+            Vec3::new(0.5, 0.5, 0.5),
+            Vec3::new(-0.5, 0.5, 0.5),
+            Vec3::new(-0.5, -0.5, 0.5),
+        ],
+    ),
+    (
+        IVec3::NEG_Z,
+        [
+            Vec3::new(-0.5, -0.5, -0.5),
+            Vec3::new(-0.5, 0.5, -0.5),
+            Vec3::new(0.5, 0.5, -0.5),
+            Vec3::new(0.5, -0.5, -0.5),
+        ],
+    ),
+];
+
+/// The vertices and index count generated by the [`CpuMesher`], laid out like the output buffers
+/// of the meshing compute shader: three vertices per triangle and four per quad.
+#[derive(Clone, Debug, Default)]
+pub struct CpuMesh {
+    pub positions: Vec<Vec3>,
+    pub index_count: usize,
+}
+
+impl CpuMesh {
+    fn emit_triangle(&mut self, v0: Vec3, v1: Vec3, v2: Vec3) {
+        self.positions.extend([v0, v1, v2]);
+        self.index_count += 3;
+    }
+
+    fn emit_quad(&mut self, v0: Vec3, v1: Vec3, v2: Vec3, v3: Vec3) {
+        self.positions.extend([v0, v1, v2, v3]);
+        self.index_count += 6;
+    }
+}
+
+/// Meshes the voxels of a single chunk on the CPU, a reference implementation of the meshing
+/// compute shader also used to validate its output. Dual contouring vertices are placed like
+/// surface nets ones, so only the topology of its meshes matches the GPU.
+pub struct CpuMesher<'a> {
+    pub voxels: &'a [Voxel],
+    pub meshing_algorithm: MeshingAlgorithm,
+    pub boundary_mode: BoundaryMode,
+    /// Whether the cells straddling the edges of the volume are meshed, closing the surface with
+    /// caps where it meets them.
+    pub capped: bool,
+    /// Planes clipping the volume, with the normal of each in `xyz` and its distance in `w`.
+    pub clip_planes: &'a [Vec4],
+    pub iso_level: f32,
+    pub edge_interpolation: EdgeInterpolation,
+}
+
+impl CpuMesher<'_> {
+    fn volume_size(&self) -> IVec3 {
+        IVec3::splat(CHUNK_SZ as i32)
+    }
+
+    /// Same as `clip_density` in `voxel.wgsl`.
+    fn clip_density(&self, pos: IVec3, density: f32) -> f32 {
+        let planes = self.clip_planes;
+        if planes.is_empty() {
+            return density;
+        }
+
+        let pos = pos.as_vec3();
+        let distance = planes
+            .iter()
+            .map(|plane| plane.truncate().dot(pos) - plane.w)
+            .fold(-1e30, f32::max);
+        density.min(self.iso_level - distance)
+    }
+
+    /// Same as `get_voxel` in `voxel.wgsl`.
+    fn voxel(&self, pos: IVec3) -> Voxel {
+        let size = self.volume_size();
+        // Caps are only closed if everything outside the volume is empty.
+        let boundary_mode = match self.capped {
+            true => BoundaryMode::Empty,
+            false => self.boundary_mode,
+        };
+
+        let p = match boundary_mode {
+            BoundaryMode::Clamp => pos.clamp(IVec3::ZERO, size - 1),
+            BoundaryMode::Wrap => pos.rem_euclid(size),
+            BoundaryMode::Empty | BoundaryMode::Solid => pos,
+        };
+
+        let inside = p.cmpge(IVec3::ZERO).all() && p.cmplt(size).all();
+        if boundary_mode == BoundaryMode::Solid && !inside {
+            return Voxel::new(0, self.clip_density(pos, 1.0));
+        }
+
+        let voxel = match inside {
+            true => self
+                .voxels
+                .get(voxel_index(p.as_uvec3()))
+                .copied()
+                .unwrap_or(Voxel::new(0, 0.0)),
+            false => Voxel::new(0, 0.0),
+        };
+        voxel.with_density(self.clip_density(pos, voxel.density()))
+    }
+
+    fn density(&self, pos: IVec3) -> f32 {
+        self.voxel(pos).density()
+    }
+
+    /// Same as `interp_vertex` in `voxel.wgsl`, for the lattice points `p1` and `p2`.
+    fn interp_vertex(&self, p1: Vec3, p2: Vec3, v1: f32, v2: f32) -> Vec3 {
+        let mu = (self.iso_level - v1) / (v2 - v1);
+        let mu = match self.edge_interpolation {
+            EdgeInterpolation::Linear => mu,
+            EdgeInterpolation::Smoothstep => {
+                0.5 - ((1.0 - 2.0 * mu.clamp(0.0, 1.0)).asin() / 3.0).sin()
+            }
+            EdgeInterpolation::Bisection => {
+                let edge = (p2 - p1).as_ivec3();
+                let v0 = self.density(p1.as_ivec3() - edge);
+                let v3 = self.density(p2.as_ivec3() + edge);
+                let (mut lo, mut hi) = (0.0, 1.0);
+                for _ in 0..BISECTION_STEPS {
+                    let mid = (lo + hi) * 0.5;
+                    match (catmull_rom(v0, v1, v2, v3, mid) < self.iso_level)
+                        == (v1 < self.iso_level)
+                    {
+                        true => lo = mid,
+                        false => hi = mid,
+                    }
+                }
+                (lo + hi) * 0.5
+            }
+        };
+        p1 + mu * (p2 - p1)
+    }
+
+    /// Meshes every cell of the chunk with the selected [`MeshingAlgorithm`].
+    pub fn mesh(&self) -> CpuMesh {
+        let mut mesh = CpuMesh::default();
+        let start = match self.capped {
+            true => -1,
+            false => 0,
+        };
+        let end = CHUNK_SZ as i32;
+
+        for z in start..end {
+            for y in start..end {
+                for x in start..end {
+                    let pos = IVec3::new(x, y, z);
+                    match self.meshing_algorithm {
+                        MeshingAlgorithm::SurfaceNets | MeshingAlgorithm::DualContouring => {
+                            self.surface_nets(pos, &mut mesh);
+                        }
+                        MeshingAlgorithm::Cubic => {
+                            if self.density(pos) >= self.iso_level {
+                                self.block_faces(pos, &mut mesh);
+                            }
+                        }
+                        MeshingAlgorithm::MarchingCubes => match self.voxel(pos).flags() {
+                            0 => self.marching_cubes(pos, &mut mesh),
+                            _ => self.block_faces(pos, &mut mesh),
+                        },
+                    }
+                }
+            }
+        }
+
+        mesh
+    }
+
+    fn marching_cubes(&self, pos: IVec3, mesh: &mut CpuMesh) {
+        let positions = CORNER_OFFSETS.map(|offset| (pos + offset).as_vec3());
+        let densities = CORNER_OFFSETS.map(|offset| self.density(pos + offset));
+
+        let cube_idx = densities
+            .iter()
+            .enumerate()
+            .filter(|(_, density)| **density < self.iso_level)
+            .fold(0, |cube_idx, (corner, _)| cube_idx | (1 << corner));
+
+        if cube_idx == 0x00 || cube_idx == 0xff {
+            return;
+        }
+
+        let vertices = EDGE_CORNERS.map(|(a, b)| {
+            self.interp_vertex(positions[a], positions[b], densities[a], densities[b])
+        });
+
+        for triangle in TRI_TABLE[cube_idx].chunks_exact(3) {
+            if triangle[0] == -1 {
+                break;
+            }
+            let [v0, v1, v2] = [0, 1, 2].map(|i| vertices[triangle[i] as usize]);
+            mesh.emit_triangle(v0, v1, v2);
+        }
+    }
+
+    fn block_faces(&self, pos: IVec3, mesh: &mut CpuMesh) {
+        let center = pos.as_vec3();
+        for (direction, corners) in BLOCK_FACES {
+            if self.density(pos + direction) < self.iso_level {
+                let [v0, v1, v2, v3] = corners.map(|corner| center + corner);
+                mesh.emit_quad(v0, v1, v2, v3);
+            }
+        }
+    }
+
+    fn cell_vertex(&self, cell: IVec3) -> Vec3 {
+        let mut sum = Vec3::ZERO;
+        let mut crossings = 0.0;
+
+        // Visit each of the 12 cell edges once, from the corner with the lower coordinate.
+        for corner in 0..8 {
+            let p1 = cell + IVec3::new(corner & 1, (corner >> 1) & 1, (corner >> 2) & 1);
+            let d1 = self.density(p1);
+
+            for axis in 0..3 {
+                if corner & (1 << axis) != 0 {
+                    continue;
+                }
+
+                let p2 = p1 + IVec3::AXES[axis];
+                let d2 = self.density(p2);
+
+                if (d1 < self.iso_level) != (d2 < self.iso_level) {
+                    sum += self.interp_vertex(p1.as_vec3(), p2.as_vec3(), d1, d2);
+                    crossings += 1.0;
+                }
+            }
+        }
+
+        sum / f32::max(crossings, 1.0)
+    }
+
+    fn surface_nets(&self, pos: IVec3, mesh: &mut CpuMesh) {
+        let size = self.volume_size();
+        let d0 = self.density(pos);
+
+        for axis in 0..3 {
+            let a = IVec3::AXES[axis];
+            let b = IVec3::AXES[(axis + 1) % 3];
+            let c = IVec3::AXES[(axis + 2) % 3];
+
+            // Skip edges whose surrounding cells are not all inside the volume, or the capping
+            // layer around it.
+            let skip = match self.capped {
+                true => (pos - b - c).cmplt(IVec3::NEG_ONE).any() || (pos + a).cmpgt(size).any(),
+                false => (pos - b - c).cmplt(IVec3::ZERO).any() || (pos + a).cmpge(size).any(),
+            };
+            if skip {
+                continue;
+            }
+
+            let d1 = self.density(pos + a);
+            if (d0 < self.iso_level) == (d1 < self.iso_level) {
+                continue;
+            }
+
+            let q0 = self.cell_vertex(pos - b - c);
+            let q1 = self.cell_vertex(pos - c);
+            let q2 = self.cell_vertex(pos);
+            let q3 = self.cell_vertex(pos - b);
+
+            // Face away from the solid side of the edge.
+            match d0 >= self.iso_level {
+                true => mesh.emit_quad(q0, q1, q2, q3),
+                false => mesh.emit_quad(q0, q3, q2, q1),
+            }
+        }
+    }
+}
+
+/// Same as `catmull_rom` in `voxel.wgsl`: the density at `t` between `v1` and `v2` of the spline
+/// through the densities `v0` to `v3` of four consecutive voxels.
+fn catmull_rom(v0: f32, v1: f32, v2: f32, v3: f32, t: f32) -> f32 {
+    let a = -0.5 * v0 + 1.5 * v1 - 1.5 * v2 + 0.5 * v3;
+    let b = v0 - 2.5 * v1 + 2.0 * v2 - 0.5 * v3;
+    let c = 0.5 * (v2 - v0);
+    ((a * t + b) * t + c) * t + v1
+}
@@ -0,0 +1,56 @@
+//! The meshing settings shared by the GPU and CPU meshers, and the `CpuMesher` reference
+//! implementation of the meshing compute shader with the `cpu-mesher` feature.
+
+#[cfg(feature = "cpu-mesher")]
+mod cpu;
+
+#[cfg(feature = "cpu-mesher")]
+pub use cpu::{CpuMesh, CpuMesher};
+
+/// How densities outside of a volume are treated when meshing the cells at its edges.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum BoundaryMode {
+    /// Outside voxels repeat the nearest voxel on the edge of the volume.
+    Clamp,
+    /// Outside voxels are empty, closing the surface where it meets the edge of the volume.
+    #[default]
+    Empty,
+    /// Outside voxels are solid.
+    Solid,
+    /// Outside voxels wrap around to the opposite edge, for toroidal worlds.
+    Wrap,
+}
+
+/// The algorithm used to mesh a volume.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum MeshingAlgorithm {
+    /// Smooth marching cubes surface, with voxels that have flags set meshed as blocks.
+    #[default]
+    MarchingCubes,
+    /// Smooth surface nets, placing one vertex per cell at the average of its edge crossings.
+    SurfaceNets,
+    /// Blocky meshing of every voxel inside the surface.
+    Cubic,
+    /// Surface nets topology, the vertices of the GPU mesher being placed by minimising the
+    /// quadratic error to the tangent planes of their cell's edge crossings.
+    DualContouring,
+}
+
+/// How the vertices on the edges crossing the surface are placed between the densities of their
+/// two voxels.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum EdgeInterpolation {
+    /// Where the line between the two densities crosses the iso-level.
+    #[default]
+    Linear,
+    /// Where a smoothstep between the two densities crosses the iso-level, pulling the vertices
+    /// towards the middle of the edges.
+    Smoothstep,
+    /// Where the Catmull-Rom spline through the densities before, along and after the edge
+    /// crosses the iso-level, found by bisection. Follows the curvature of signed distance
+    /// fields that linear interpolation flattens into facets.
+    Bisection,
+}
+
+/// Halvings of the edge searching the crossing of [`EdgeInterpolation::Bisection`].
+pub const BISECTION_STEPS: u32 = 8;
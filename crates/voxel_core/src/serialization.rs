@@ -0,0 +1,62 @@
+use crate::voxel::Voxel;
+
+/// Bytes of a serialized voxel, its flags, density and RGB9E5 emissive color, all little endian.
+pub const VOXEL_BYTES: usize = 12;
+
+/// Serializes `voxels` into [`VOXEL_BYTES`] bytes each, the same on every platform.
+pub fn encode_voxels(voxels: &[Voxel]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(voxels.len() * VOXEL_BYTES);
+    for voxel in voxels {
+        data.extend_from_slice(&voxel.flags().to_le_bytes());
+        data.extend_from_slice(&voxel.density().to_le_bytes());
+        data.extend_from_slice(&voxel.emissive_bits().to_le_bytes());
+    }
+    data
+}
+
+/// Reads the voxels written by [`encode_voxels`], ignoring a trailing partial voxel.
+pub fn decode_voxels(data: &[u8]) -> Vec<Voxel> {
+    data.chunks_exact(VOXEL_BYTES)
+        .map(|voxel| {
+            let flags = u32::from_le_bytes(voxel[0..4].try_into().expect("should be a u32"));
+            let density = f32::from_le_bytes(voxel[4..8].try_into().expect("should be a f32"));
+            let emissive = u32::from_le_bytes(voxel[8..12].try_into().expect("should be a u32"));
+            Voxel::new(flags, density).with_emissive_bits(emissive)
+        })
+        .collect()
+}
+
+/// A run of `len` identical voxels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VoxelRun {
+    pub voxel: Voxel,
+    pub len: u32,
+}
+
+/// Encodes `voxels` as runs of identical voxels, compact for mostly uniform chunks.
+pub fn run_length_encode(voxels: &[Voxel]) -> Vec<VoxelRun> {
+    let mut runs = Vec::<VoxelRun>::new();
+    for voxel in voxels {
+        match runs.last_mut() {
+            Some(run)
+                if run.voxel.flags() == voxel.flags()
+                    && run.voxel.density().to_bits() == voxel.density().to_bits()
+                    && run.voxel.emissive_bits() == voxel.emissive_bits() =>
+            {
+                run.len += 1
+            }
+            _ => runs.push(VoxelRun {
+                voxel: *voxel,
+                len: 1,
+            }),
+        }
+    }
+    runs
+}
+
+/// Expands the runs written by [`run_length_encode`].
+pub fn run_length_decode(runs: &[VoxelRun]) -> Vec<Voxel> {
+    runs.iter()
+        .flat_map(|run| std::iter::repeat_n(run.voxel, run.len as usize))
+        .collect()
+}
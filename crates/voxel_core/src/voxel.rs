@@ -0,0 +1,115 @@
+use glam::{UVec3, Vec3};
+use serde::{Deserialize, Serialize};
+
+/// Voxels along each axis of a chunk.
+pub const CHUNK_SZ: usize = 32;
+pub const CHUNK_SZ_2: usize = CHUNK_SZ * CHUNK_SZ;
+pub const CHUNK_SZ_3: usize = CHUNK_SZ * CHUNK_SZ * CHUNK_SZ;
+
+/// Index of the voxel at `pos` in the voxels of a chunk, laid out x first, then y, then z.
+pub fn voxel_index(pos: UVec3) -> usize {
+    pos.x as usize + pos.y as usize * CHUNK_SZ + pos.z as usize * CHUNK_SZ_2
+}
+
+/// Brightest emissive color component a [`Voxel`] holds, the largest value of the RGB9E5 format.
+pub const MAX_EMISSIVE: f32 = 65408.0;
+
+/// The density of a point of a volume along with its flags, e.g. a material id, and the light it
+/// emits. Laid out like the voxels of the meshing shaders, three 32 bit words.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "shader-type", derive(encase::ShaderType))]
+#[repr(C)]
+pub struct Voxel {
+    flags: u32,
+    density: f32,
+    /// The emissive color, linear and premultiplied by its intensity, packed as RGB9E5.
+    #[serde(default)]
+    emissive: u32,
+}
+
+impl Default for Voxel {
+    fn default() -> Self {
+        Self {
+            flags: 0,
+            density: 1.,
+            emissive: 0,
+        }
+    }
+}
+
+impl Voxel {
+    /// A voxel emitting no light.
+    pub fn new(flags: u32, density: f32) -> Self {
+        Self {
+            flags,
+            density,
+            emissive: 0,
+        }
+    }
+
+    /// The voxel with the linear `emissive` color, premultiplied by its intensity. Components are
+    /// clamped from 0 to [`MAX_EMISSIVE`] and keep 9 bits of precision relative to the largest.
+    pub fn with_emissive(self, emissive: Vec3) -> Self {
+        self.with_emissive_bits(pack_rgb9e5(emissive))
+    }
+
+    /// The voxel with the emissive color packed as RGB9E5 by [`Voxel::emissive_bits`].
+    pub fn with_emissive_bits(mut self, emissive: u32) -> Self {
+        self.emissive = emissive;
+        self
+    }
+
+    /// The voxel with `density`, keeping its flags and emissive color.
+    pub fn with_density(mut self, density: f32) -> Self {
+        self.density = density;
+        self
+    }
+
+    pub fn flags(&self) -> u32 {
+        self.flags
+    }
+
+    pub fn density(&self) -> f32 {
+        self.density
+    }
+
+    /// The linear emissive color, premultiplied by its intensity.
+    pub fn emissive(&self) -> Vec3 {
+        unpack_rgb9e5(self.emissive)
+    }
+
+    /// The emissive color packed as RGB9E5: a 9 bit mantissa per component from the lowest bits,
+    /// then a shared 5 bit exponent.
+    pub fn emissive_bits(&self) -> u32 {
+        self.emissive
+    }
+}
+
+const RGB9E5_MANTISSA_BITS: i32 = 9;
+const RGB9E5_EXPONENT_BIAS: i32 = 15;
+
+/// Packs a color with non-negative components into the RGB9E5 shared exponent format, the same
+/// as `Rgb9e5Ufloat` textures.
+pub fn pack_rgb9e5(color: Vec3) -> u32 {
+    // `max` and `min` map NaNs to 0.
+    let color = color.max(Vec3::ZERO).min(Vec3::splat(MAX_EMISSIVE));
+    let max = color.max_element();
+
+    let mut exponent =
+        (max.log2().floor() as i32).max(-RGB9E5_EXPONENT_BIAS - 1) + 1 + RGB9E5_EXPONENT_BIAS;
+    let scale = |exponent: i32| 2f32.powi(exponent - RGB9E5_EXPONENT_BIAS - RGB9E5_MANTISSA_BITS);
+    // Rounding the largest component up may overflow its mantissa.
+    if (max / scale(exponent) + 0.5).floor() as u32 == 1 << RGB9E5_MANTISSA_BITS {
+        exponent += 1;
+    }
+
+    let mantissas = (color / scale(exponent) + 0.5).floor().as_uvec3();
+    mantissas.x | mantissas.y << 9 | mantissas.z << 18 | (exponent as u32) << 27
+}
+
+/// Unpacks a color packed by [`pack_rgb9e5`].
+pub fn unpack_rgb9e5(packed: u32) -> Vec3 {
+    let mantissas = UVec3::new(packed, packed >> 9, packed >> 18) & UVec3::splat(0x1ff);
+    let exponent = (packed >> 27) as i32;
+    mantissas.as_vec3() * 2f32.powi(exponent - RGB9E5_EXPONENT_BIAS - RGB9E5_MANTISSA_BITS)
+}
@@ -0,0 +1,152 @@
+//! A metaball "lava lamp": blobs rising and sinking in a chunk, splatted into its voxels on the
+//! CPU every frame and meshed on the GPU into an iso-surface, which is read back into a mesh to
+//! draw it. The iso-level of the surface breathes slowly, so that the blobs merge sooner or later.
+
+use bevy::prelude::*;
+use compute_mesh::{
+    bundles::volumetric_bundle::VolumetricBundle,
+    core::{CHUNK_SZ, CHUNK_SZ_2, CHUNK_SZ_3},
+    data::{
+        iso_surface::{IsoSurface, IsoSurfaces},
+        voxel::Voxel,
+        voxel_material::VoxelMaterial,
+    },
+    GpuReadbackPlugin,
+};
+
+const BLOB_COUNT: usize = 6;
+
+const ISO_LEVEL: f32 = 0.5;
+
+/// How far the iso-level breathes above and below [`ISO_LEVEL`].
+const ISO_LEVEL_SWING: f32 = 0.15;
+
+/// A metaball moving up and down the lamp.
+struct Blob {
+    radius: f32,
+    speed: f32,
+    phase: f32,
+    column: Vec2,
+}
+
+impl Blob {
+    fn center(&self, time: f32) -> Vec3 {
+        let size = CHUNK_SZ as f32;
+        let height = (time * self.speed + self.phase).sin() * 0.35 + 0.5;
+        let sway = Vec2::new((time * 0.3 + self.phase).cos(), (time * 0.2).sin()) * 1.5;
+        let column = self.column + sway;
+        Vec3::new(column.x, height * size, column.y)
+    }
+}
+
+#[derive(Component)]
+struct LavaLamp {
+    blobs: Vec<Blob>,
+}
+
+fn main() {
+    App::new()
+        .insert_resource(ClearColor(Color::srgb(0.05, 0.02, 0.08)))
+        .add_plugins((DefaultPlugins, GpuReadbackPlugin))
+        .add_systems(Startup, setup)
+        .add_systems(Update, (splat_blobs, breathe_iso_level))
+        .run();
+}
+
+fn setup(mut commands: Commands, mut materials: ResMut<Assets<StandardMaterial>>) {
+    let center = CHUNK_SZ as f32 / 2.0;
+
+    let blobs = (0..BLOB_COUNT)
+        .map(|index| {
+            let angle = index as f32 / BLOB_COUNT as f32 * std::f32::consts::TAU;
+            Blob {
+                radius: 3.0 + (index % 3) as f32,
+                speed: 0.3 + index as f32 * 0.07,
+                phase: angle,
+                column: Vec2::splat(center) + Vec2::from_angle(angle) * center * 0.3,
+            }
+        })
+        .collect();
+
+    let wax = materials.add(StandardMaterial {
+        base_color: Color::srgb(1.0, 0.3, 0.05),
+        emissive: LinearRgba::rgb(0.8, 0.15, 0.0),
+        perceptual_roughness: 0.3,
+        ..default()
+    });
+
+    commands.spawn((
+        VolumetricBundle::new(VoxelMaterial::new(
+            vec![Voxel::default(); CHUNK_SZ_3],
+            CHUNK_SZ_3 as u32,
+        )),
+        IsoSurfaces(vec![IsoSurface {
+            iso_level: ISO_LEVEL,
+            material: wax,
+        }]),
+        LavaLamp { blobs },
+        SpatialBundle::default(),
+    ));
+
+    commands.spawn(PointLightBundle {
+        point_light: PointLight {
+            intensity: 2_000_000.0,
+            range: 200.0,
+            ..default()
+        },
+        transform: Transform::from_xyz(center, CHUNK_SZ as f32 * 1.5, center * 3.0),
+        ..default()
+    });
+
+    commands.spawn(Camera3dBundle {
+        transform: Transform::from_xyz(center, center, CHUNK_SZ as f32 * 2.2)
+            .looking_at(Vec3::splat(center), Vec3::Y),
+        ..default()
+    });
+}
+
+/// Splats the blobs into the voxels of the lamp, each adding `(r / d)²` to the density of the
+/// voxels around its center.
+fn splat_blobs(time: Res<Time>, mut lamp_query: Query<(&LavaLamp, &mut VoxelMaterial)>) {
+    let t = time.elapsed_seconds();
+
+    for (lamp, mut voxel_material) in lamp_query.iter_mut() {
+        let centers = lamp
+            .blobs
+            .iter()
+            .map(|blob| (blob.center(t), blob.radius * blob.radius))
+            .collect::<Vec<_>>();
+
+        for z in 0..CHUNK_SZ {
+            for y in 0..CHUNK_SZ {
+                for x in 0..CHUNK_SZ {
+                    let position = Vec3::new(x as f32, y as f32, z as f32);
+                    let field = centers
+                        .iter()
+                        .map(|(center, radius_squared)| {
+                            radius_squared / position.distance_squared(*center).max(1e-3)
+                        })
+                        .sum::<f32>();
+                    let density = (field * ISO_LEVEL).clamp(0.0, 1.0);
+
+                    voxel_material.voxels_mut()[x + y * CHUNK_SZ + z * CHUNK_SZ_2] =
+                        Voxel::new(0, density);
+                }
+            }
+        }
+    }
+}
+
+/// Moves the iso-level of the lamp's surface up and down, remeshing it at the new level.
+fn breathe_iso_level(
+    time: Res<Time>,
+    mut iso_surfaces_query: Query<&mut IsoSurfaces, With<LavaLamp>>,
+) {
+    let iso_level = ISO_LEVEL + (time.elapsed_seconds() * 0.25).sin() * ISO_LEVEL_SWING;
+
+    for mut iso_surfaces in iso_surfaces_query.iter_mut() {
+        for iso_surface in iso_surfaces.0.iter_mut() {
+            iso_surface.iso_level = iso_level;
+        }
+    }
+}
@@ -0,0 +1,304 @@
+//! A destructible wall shooting gallery: projectiles carve spheres out of a voxel wall with
+//! [`ChunkEdit`]s, and collide with a trimesh collider rebuilt from the [`RawMeshData`] read back
+//! whenever the wall is remeshed. Click to shoot where the cursor points, the gallery also shoots
+//! on its own so that the whole edit → remesh → collider → render loop runs unattended.
+
+use bevy::{prelude::*, window::PrimaryWindow};
+use compute_mesh::{
+    bundles::volumetric_bundle::VolumetricBundle,
+    core::{CHUNK_SZ, CHUNK_SZ_2, CHUNK_SZ_3},
+    data::{
+        iso_surface::{IsoSurface, IsoSurfaces, DEFAULT_ISO_LEVEL},
+        raw_mesh_data::RawMeshData,
+        voxel::Voxel,
+        voxel_edit::{ChunkEdit, ChunkEdited, VoxelEdit},
+        voxel_material::VoxelMaterial,
+    },
+    GpuReadbackPlugin,
+};
+
+const WALL_DEPTH: std::ops::Range<usize> = 12..20;
+
+const PROJECTILE_SPEED: f32 = 60.0;
+
+const PROJECTILE_RADIUS: f32 = 0.5;
+
+const CRATER_RADIUS: f32 = 3.0;
+
+/// Triangles of the wall's last mesh read back, in the local space of the wall.
+#[derive(Component, Default)]
+struct WallCollider {
+    triangles: Vec<[Vec3; 3]>,
+}
+
+impl WallCollider {
+    /// Rebuilds the collider of every wall whose mesh was read back.
+    fn rebuild(
+        mut stats: ResMut<GalleryStats>,
+        mut wall_query: Query<(&RawMeshData, &mut WallCollider), Changed<RawMeshData>>,
+    ) {
+        for (raw_mesh_data, mut collider) in wall_query.iter_mut() {
+            collider.triangles = raw_mesh_data
+                .indices
+                .chunks_exact(3)
+                .filter_map(|triangle| {
+                    let vertex = |index: u32| raw_mesh_data.vertices.get(index as usize).copied();
+                    Some([
+                        vertex(triangle[0])?,
+                        vertex(triangle[1])?,
+                        vertex(triangle[2])?,
+                    ])
+                })
+                .collect();
+            stats.collider_rebuilds += 1;
+        }
+    }
+
+    /// The distance along `direction` at which the ray from `origin` first hits the collider, if it
+    /// does within `max_distance`.
+    fn cast_ray(&self, origin: Vec3, direction: Vec3, max_distance: f32) -> Option<f32> {
+        self.triangles
+            .iter()
+            .filter_map(|triangle| ray_triangle(origin, direction, triangle))
+            .filter(|distance| *distance <= max_distance)
+            .min_by(f32::total_cmp)
+    }
+}
+
+/// Möller–Trumbore intersection of a ray with a double-sided triangle.
+fn ray_triangle(origin: Vec3, direction: Vec3, [a, b, c]: &[Vec3; 3]) -> Option<f32> {
+    let edge_1 = *b - *a;
+    let edge_2 = *c - *a;
+    let p = direction.cross(edge_2);
+    let determinant = edge_1.dot(p);
+    if determinant.abs() < 1e-6 {
+        return None;
+    }
+    let inverse = 1.0 / determinant;
+    let t_vec = origin - *a;
+    let u = t_vec.dot(p) * inverse;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = t_vec.cross(edge_1);
+    let v = direction.dot(q) * inverse;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    let distance = edge_2.dot(q) * inverse;
+    (distance >= 0.0).then_some(distance)
+}
+
+#[derive(Component)]
+struct Projectile {
+    velocity: Vec3,
+}
+
+#[derive(Resource)]
+struct Gallery {
+    auto_fire: Timer,
+    shots: u32,
+    projectile_mesh: Handle<Mesh>,
+    projectile_material: Handle<StandardMaterial>,
+}
+
+/// Counts each step of the loop, logged every few seconds.
+#[derive(Resource, Default)]
+struct GalleryStats {
+    hits: u32,
+    edits: u32,
+    collider_rebuilds: u32,
+}
+
+fn main() {
+    App::new()
+        .insert_resource(ClearColor(Color::srgb(0.1, 0.1, 0.12)))
+        .add_plugins((DefaultPlugins, GpuReadbackPlugin))
+        .init_resource::<GalleryStats>()
+        .add_systems(Startup, setup)
+        .add_systems(
+            Update,
+            (
+                shoot,
+                move_projectiles.after(shoot),
+                WallCollider::rebuild.before(move_projectiles),
+                count_edits,
+                log_stats,
+            ),
+        )
+        .run();
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let mut voxels = vec![Voxel::default(); CHUNK_SZ_3];
+    for z in WALL_DEPTH {
+        for y in 1..CHUNK_SZ - 1 {
+            for x in 1..CHUNK_SZ - 1 {
+                voxels[x + y * CHUNK_SZ + z * CHUNK_SZ_2] = Voxel::new(0, 1.0);
+            }
+        }
+    }
+
+    let brick = materials.add(StandardMaterial {
+        base_color: Color::srgb(0.7, 0.35, 0.25),
+        perceptual_roughness: 0.9,
+        ..default()
+    });
+
+    commands.spawn((
+        VolumetricBundle::new(VoxelMaterial::new(voxels, CHUNK_SZ_3 as u32)),
+        IsoSurfaces(vec![IsoSurface {
+            iso_level: DEFAULT_ISO_LEVEL,
+            material: brick,
+        }]),
+        RawMeshData::default(),
+        WallCollider::default(),
+        SpatialBundle::default(),
+    ));
+
+    let center = CHUNK_SZ as f32 / 2.0;
+
+    commands.spawn(DirectionalLightBundle {
+        directional_light: DirectionalLight {
+            illuminance: 8_000.0,
+            shadows_enabled: true,
+            ..default()
+        },
+        transform: Transform::from_xyz(center * 0.5, center * 3.0, center * 4.0)
+            .looking_at(Vec3::splat(center), Vec3::Y),
+        ..default()
+    });
+
+    commands.spawn(Camera3dBundle {
+        transform: Transform::from_xyz(center, center, CHUNK_SZ as f32 * 2.5)
+            .looking_at(Vec3::splat(center), Vec3::Y),
+        ..default()
+    });
+
+    commands.insert_resource(Gallery {
+        auto_fire: Timer::from_seconds(0.5, TimerMode::Repeating),
+        shots: 0,
+        projectile_mesh: meshes.add(Sphere::new(PROJECTILE_RADIUS)),
+        projectile_material: materials.add(Color::srgb(1.0, 0.9, 0.3)),
+    });
+}
+
+/// Shoots from the camera at the cursor on click, and at a point spiralling over the wall on each
+/// tick of the auto fire.
+fn shoot(
+    mut commands: Commands,
+    time: Res<Time>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    mut gallery: ResMut<Gallery>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+) {
+    let Ok((camera, camera_transform)) = camera_query.get_single() else {
+        return;
+    };
+
+    let mut directions = Vec::new();
+
+    if buttons.just_pressed(MouseButton::Left) {
+        let ray = window_query
+            .get_single()
+            .ok()
+            .and_then(Window::cursor_position)
+            .and_then(|cursor| camera.viewport_to_world(camera_transform, cursor));
+        if let Some(ray) = ray {
+            directions.push(*ray.direction);
+        }
+    }
+
+    if gallery.auto_fire.tick(time.delta()).just_finished() {
+        let center = CHUNK_SZ as f32 / 2.0;
+        let angle = gallery.shots as f32 * 2.4;
+        let spread = (gallery.shots % 16) as f32 / 16.0 * center * 0.8;
+        let target = Vec3::new(
+            center + angle.cos() * spread,
+            center + angle.sin() * spread,
+            WALL_DEPTH.end as f32,
+        );
+        directions.push((target - camera_transform.translation()).normalize());
+    }
+
+    for direction in directions {
+        gallery.shots += 1;
+        commands.spawn((
+            PbrBundle {
+                mesh: gallery.projectile_mesh.clone(),
+                material: gallery.projectile_material.clone(),
+                transform: Transform::from_translation(camera_transform.translation()),
+                ..default()
+            },
+            Projectile {
+                velocity: direction * PROJECTILE_SPEED,
+            },
+        ));
+    }
+}
+
+/// Moves the projectiles, carving a crater wherever one hits a wall.
+fn move_projectiles(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut stats: ResMut<GalleryStats>,
+    mut chunk_edits: EventWriter<ChunkEdit>,
+    mut projectile_query: Query<(Entity, &Projectile, &mut Transform)>,
+    wall_query: Query<(Entity, &WallCollider, &GlobalTransform)>,
+) {
+    for (entity, projectile, mut transform) in projectile_query.iter_mut() {
+        let step = projectile.velocity * time.delta_seconds();
+        let distance = step.length();
+        let direction = step / distance.max(f32::EPSILON);
+
+        let hit = wall_query
+            .iter()
+            .find_map(|(wall, collider, wall_transform)| {
+                let to_local = wall_transform.affine().inverse();
+                let origin = to_local.transform_point3(transform.translation);
+                let local_direction = to_local.transform_vector3(direction).normalize();
+                collider
+                    .cast_ray(origin, local_direction, distance + PROJECTILE_RADIUS)
+                    .map(|hit_distance| (wall, origin + local_direction * hit_distance))
+            });
+
+        if let Some((wall, point)) = hit {
+            chunk_edits.send(ChunkEdit {
+                entity: wall,
+                edit: VoxelEdit::Sphere {
+                    center: point,
+                    radius: CRATER_RADIUS,
+                    density: 0.0,
+                },
+            });
+            stats.hits += 1;
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        transform.translation += step;
+        if transform.translation.z < -(CHUNK_SZ as f32) {
+            commands.entity(entity).despawn(); // went through a hole
+        }
+    }
+}
+
+fn count_edits(mut stats: ResMut<GalleryStats>, mut edited: EventReader<ChunkEdited>) {
+    stats.edits += edited.read().count() as u32;
+}
+
+fn log_stats(time: Res<Time>, stats: Res<GalleryStats>, mut next_log: Local<f32>) {
+    if time.elapsed_seconds() < *next_log {
+        return;
+    }
+    *next_log = time.elapsed_seconds() + 5.0;
+    info!(
+        "{} hits, {} edits applied, {} collider rebuilds",
+        stats.hits, stats.edits, stats.collider_rebuilds
+    );
+}
@@ -0,0 +1,65 @@
+use bevy::{
+    prelude::*,
+    render::{
+        extract_component::ExtractComponentPlugin, graph::CameraDriverLabel,
+        render_graph::RenderGraph, Render, RenderApp, RenderSet,
+    },
+};
+
+use crate::{
+    data::{
+        ambient_occlusion::AmbientOcclusionVolume, chunk_retirement::ChunkRetirementExt,
+        gpu_ambient_occlusion::GpuAmbientOcclusion, voxel_material::VoxelMaterialComponents,
+    },
+    render::{
+        ambient_occlusion_compute_pipeline::{
+            AmbientOcclusionComputeNode, AmbientOcclusionComputeNodeLabel,
+            AmbientOcclusionComputePipeline,
+        },
+        voxel_mesh_compute_pipeline::VoxelMeshComputeNodeLabel,
+    },
+};
+
+/// Generates the occlusion texture of every [`AmbientOcclusionVolume`] on the GPU after the volumes
+/// are meshed and before the cameras render, so materials can sample it in the same frame.
+///
+/// Must be added after the [`GpuReadbackPlugin`](crate::GpuReadbackPlugin).
+pub struct AmbientOcclusionPlugin;
+
+impl Plugin for AmbientOcclusionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(ExtractComponentPlugin::<AmbientOcclusionVolume>::default());
+    }
+
+    fn finish(&self, app: &mut App) {
+        let render_app = app.sub_app_mut(RenderApp);
+
+        render_app
+            .init_resource::<AmbientOcclusionComputePipeline>()
+            .init_resource::<VoxelMaterialComponents<GpuAmbientOcclusion>>()
+            .free_on_retire::<GpuAmbientOcclusion>()
+            .add_systems(
+                ExtractSchedule,
+                (
+                    GpuAmbientOcclusion::initialize,
+                    GpuAmbientOcclusion::extract.after(GpuAmbientOcclusion::initialize),
+                ),
+            )
+            .add_systems(
+                Render,
+                GpuAmbientOcclusion::prepare.in_set(RenderSet::PrepareBindGroups),
+            );
+
+        let ambient_occlusion_compute_node =
+            AmbientOcclusionComputeNode::from_world(render_app.world_mut());
+
+        let mut render_graph = render_app.world_mut().resource_mut::<RenderGraph>();
+
+        render_graph.add_node(
+            AmbientOcclusionComputeNodeLabel,
+            ambient_occlusion_compute_node,
+        );
+        render_graph.add_node_edge(VoxelMeshComputeNodeLabel, AmbientOcclusionComputeNodeLabel);
+        render_graph.add_node_edge(AmbientOcclusionComputeNodeLabel, CameraDriverLabel);
+    }
+}
@@ -0,0 +1,308 @@
+use bevy::{
+    core::FrameCount,
+    prelude::*,
+    render::{
+        mesh::{Indices, MeshVertexAttribute, PrimitiveTopology, VertexAttributeValues},
+        render_asset::RenderAssetUsages,
+        render_resource::VertexFormat,
+        view::VisibilitySystems,
+    },
+    utils::{HashMap, HashSet},
+};
+
+use crate::data::chunk_coord::ChunkCoord;
+
+/// Offset of the chunk a vertex of a [`RegionMesh`] comes from, relative to the region mesh.
+pub const ATTRIBUTE_CHUNK_OFFSET: MeshVertexAttribute =
+    MeshVertexAttribute::new("Vertex_ChunkOffset", 0x7a3c_5e21, VertexFormat::Float32x3);
+
+/// Marks a chunk whose mesh changed recently. Dirty chunks are drawn on their own rather than in
+/// their region mesh, so that editing them doesn't rebuild the whole region every frame.
+#[derive(Clone, Copy, Component, Debug)]
+pub struct DirtyChunk {
+    pub changed_at: u32,
+}
+
+/// A mesh merging the static chunks of a region that share a material.
+#[derive(Clone, Component, Debug)]
+pub struct RegionMesh {
+    pub region: IVec3,
+    pub chunks: Vec<Entity>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct RegionKey {
+    region: IVec3,
+    material: AssetId<StandardMaterial>,
+}
+
+struct RegionBatch {
+    entity: Entity,
+    mesh: Handle<Mesh>,
+    chunks: Vec<Entity>,
+}
+
+/// A static chunk waiting to be merged into its region.
+struct BatchedChunk {
+    entity: Entity,
+    translation: Vec3,
+    mesh: AssetId<Mesh>,
+}
+
+/// Merges the [`Mesh`]es of adjacent chunks sharing a [`StandardMaterial`] into one mesh per
+/// region, reducing the draw calls of large static areas.
+#[derive(Resource)]
+pub struct RegionBatching {
+    /// Chunks per region along each axis.
+    pub region_size: IVec3,
+    /// Frames the mesh of a chunk must stay unchanged before it is merged back into its region.
+    pub settle_frames: u32,
+    regions: HashMap<RegionKey, RegionBatch>,
+}
+
+impl Default for RegionBatching {
+    fn default() -> Self {
+        Self {
+            region_size: IVec3::splat(4),
+            settle_frames: 30,
+            regions: HashMap::default(),
+        }
+    }
+}
+
+impl RegionBatching {
+    /// Marks the chunks whose mesh changed as dirty, rebuilds the region meshes whose static chunks
+    /// changed and hides the chunks drawn by a region mesh.
+    #[allow(clippy::type_complexity, clippy::too_many_arguments)]
+    pub fn update(
+        mut commands: Commands,
+        mut batching: ResMut<Self>,
+        frame_count: Res<FrameCount>,
+        mut meshes: ResMut<Assets<Mesh>>,
+        mut mesh_events: EventReader<AssetEvent<Mesh>>,
+        mut chunk_query: Query<
+            (
+                Entity,
+                &ChunkCoord,
+                &Transform,
+                Ref<Handle<Mesh>>,
+                &Handle<StandardMaterial>,
+                Option<&DirtyChunk>,
+                &mut Visibility,
+            ),
+            Without<RegionMesh>,
+        >,
+    ) {
+        let modified_meshes = mesh_events
+            .read()
+            .filter_map(|event| match event {
+                AssetEvent::Modified { id } => Some(*id),
+                _ => None,
+            })
+            .collect::<HashSet<_>>();
+
+        let mut groups =
+            HashMap::<RegionKey, (Handle<StandardMaterial>, Vec<BatchedChunk>)>::default();
+
+        for (entity, coord, transform, mesh, material, dirty, _) in chunk_query.iter() {
+            let is_dirty = if mesh.is_changed() || modified_meshes.contains(&mesh.id()) {
+                commands.entity(entity).insert(DirtyChunk {
+                    changed_at: frame_count.0,
+                });
+                true
+            } else if let Some(dirty) = dirty {
+                let settled =
+                    frame_count.0.wrapping_sub(dirty.changed_at) >= batching.settle_frames;
+                if settled {
+                    commands.entity(entity).remove::<DirtyChunk>();
+                }
+                !settled
+            } else {
+                false
+            };
+
+            if is_dirty {
+                continue;
+            }
+
+            let key = RegionKey {
+                region: coord.0.div_euclid(batching.region_size.max(IVec3::ONE)),
+                material: material.id(),
+            };
+            groups
+                .entry(key)
+                .or_insert_with(|| (material.clone(), Vec::new()))
+                .1
+                .push(BatchedChunk {
+                    entity,
+                    translation: transform.translation,
+                    mesh: mesh.id(),
+                });
+        }
+
+        // A region of a single chunk saves no draw calls.
+        groups.retain(|_, (_, chunks)| chunks.len() > 1);
+
+        batching.regions.retain(|key, batch| {
+            let keep = groups.contains_key(key);
+            if !keep {
+                commands.entity(batch.entity).despawn_recursive();
+                meshes.remove(&batch.mesh);
+            }
+            keep
+        });
+
+        let mut batched = HashSet::<Entity>::default();
+
+        for (key, (material, mut chunks)) in groups {
+            chunks.sort_by_key(|chunk| chunk.entity);
+            let entities = chunks.iter().map(|chunk| chunk.entity).collect::<Vec<_>>();
+            batched.extend(entities.iter().copied());
+
+            if batching
+                .regions
+                .get(&key)
+                .is_some_and(|batch| batch.chunks == entities)
+            {
+                continue;
+            }
+
+            let origin = chunks
+                .iter()
+                .map(|chunk| chunk.translation)
+                .reduce(Vec3::min)
+                .unwrap_or_default();
+            let region_mesh = merge_chunk_meshes(&meshes, &chunks, origin);
+            let region = RegionMesh {
+                region: key.region,
+                chunks: entities.clone(),
+            };
+
+            match batching.regions.get_mut(&key) {
+                Some(batch) => {
+                    meshes.insert(&batch.mesh, region_mesh);
+                    commands
+                        .entity(batch.entity)
+                        .insert((Transform::from_translation(origin), region));
+                    batch.chunks = entities;
+                }
+                None => {
+                    let mesh = meshes.add(region_mesh);
+                    let entity = commands
+                        .spawn((
+                            PbrBundle {
+                                mesh: mesh.clone(),
+                                material,
+                                transform: Transform::from_translation(origin),
+                                ..default()
+                            },
+                            region,
+                        ))
+                        .id();
+                    batching.regions.insert(
+                        key,
+                        RegionBatch {
+                            entity,
+                            mesh,
+                            chunks: entities,
+                        },
+                    );
+                }
+            }
+        }
+
+        for (entity, .., mut visibility) in chunk_query.iter_mut() {
+            let target = match batched.contains(&entity) {
+                true => Visibility::Hidden,
+                false => Visibility::Inherited,
+            };
+            if *visibility != target {
+                *visibility = target;
+            }
+        }
+    }
+}
+
+/// Merges the triangle list meshes of `chunks` into one mesh positioned at `origin`, tagging each
+/// vertex with the [`ATTRIBUTE_CHUNK_OFFSET`] of its chunk. Normals and UVs are kept if every
+/// chunk has them.
+fn merge_chunk_meshes(meshes: &Assets<Mesh>, chunks: &[BatchedChunk], origin: Vec3) -> Mesh {
+    let mut positions = Vec::<[f32; 3]>::new();
+    let mut offsets = Vec::<[f32; 3]>::new();
+    let mut normals = Vec::<[f32; 3]>::new();
+    let mut uvs = Vec::<[f32; 2]>::new();
+    let mut indices = Vec::<u32>::new();
+
+    for chunk in chunks {
+        let Some(mesh) = meshes.get(chunk.mesh) else {
+            continue;
+        };
+        if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+            continue;
+        }
+        let Some(VertexAttributeValues::Float32x3(chunk_positions)) =
+            mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+        else {
+            continue;
+        };
+
+        let base = positions.len() as u32;
+        let offset = chunk.translation - origin;
+
+        positions.extend(
+            chunk_positions
+                .iter()
+                .map(|position| (Vec3::from_array(*position) + offset).to_array()),
+        );
+        offsets.extend(std::iter::repeat_n(
+            offset.to_array(),
+            chunk_positions.len(),
+        ));
+
+        if let Some(VertexAttributeValues::Float32x3(chunk_normals)) =
+            mesh.attribute(Mesh::ATTRIBUTE_NORMAL)
+        {
+            normals.extend_from_slice(chunk_normals);
+        }
+        if let Some(VertexAttributeValues::Float32x2(chunk_uvs)) =
+            mesh.attribute(Mesh::ATTRIBUTE_UV_0)
+        {
+            uvs.extend_from_slice(chunk_uvs);
+        }
+
+        match mesh.indices() {
+            Some(chunk_indices) => {
+                indices.extend(chunk_indices.iter().map(|index| base + index as u32))
+            }
+            None => indices.extend(base..base + chunk_positions.len() as u32),
+        }
+    }
+
+    let vertex_count = positions.len();
+    let mut mesh = Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(ATTRIBUTE_CHUNK_OFFSET, offsets);
+    if normals.len() == vertex_count {
+        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    }
+    if uvs.len() == vertex_count {
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    }
+    mesh.insert_indices(Indices::U32(indices));
+    mesh
+}
+
+/// Batches the static chunk meshes of each region with [`RegionBatching`].
+pub struct RegionBatchingPlugin;
+
+impl Plugin for RegionBatchingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RegionBatching>().add_systems(
+            PostUpdate,
+            RegionBatching::update.before(VisibilitySystems::VisibilityPropagate),
+        );
+    }
+}
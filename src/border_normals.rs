@@ -0,0 +1,220 @@
+use bevy::{
+    prelude::*,
+    render::mesh::VertexAttributeValues,
+    utils::{HashMap, HashSet},
+};
+
+use crate::data::{chunk_coord::ChunkCoord, voxel_world::VoxelWorldConfig};
+
+/// Distance in voxels from a face of its chunk under which a vertex lies on the border.
+const BORDER_EPSILON: f32 = 1e-4;
+
+/// Subdivisions of a voxel that coincident border vertices are snapped to.
+const BORDER_GRID: f32 = 256.0;
+
+/// A vertex of a chunk mesh lying on a face of the chunk.
+#[derive(Clone, Copy, Debug)]
+struct BorderVertex {
+    index: usize,
+    /// The vertex position in world voxel coordinates, snapped to the [`BORDER_GRID`].
+    key: IVec3,
+    /// The normal the mesh was generated with.
+    normal: Vec3,
+}
+
+/// Averages the normals of the vertices of a chunk's [`Mesh`] lying on a face of the chunk with
+/// those of the coincident vertices of the adjacent chunks, so that the shading doesn't show a
+/// seam where the normals were computed without the voxels of the neighbour. Only chunks that
+/// both opted in are smoothed together.
+///
+/// The chunk meshes must be in the voxel coordinates of their [`ChunkCoord`], as placed by
+/// [`VoxelWorldConfig::chunk_transform`]. The smoothing is undone when a neighbour is removed.
+#[derive(Clone, Component, Debug, Default)]
+pub struct SmoothBorderNormals {
+    border: Vec<BorderVertex>,
+    /// The normals last written to the border vertices, in the order of `border`.
+    smoothed: Vec<Vec3>,
+    vertex_count: usize,
+}
+
+impl SmoothBorderNormals {
+    /// Smooths the border normals of the chunks whose mesh changed and of their neighbours.
+    #[allow(clippy::type_complexity)]
+    pub fn smooth(
+        config: Res<VoxelWorldConfig>,
+        mut meshes: ResMut<Assets<Mesh>>,
+        mut mesh_events: EventReader<AssetEvent<Mesh>>,
+        mut chunk_query: Query<(
+            Entity,
+            &ChunkCoord,
+            Ref<Handle<Mesh>>,
+            &mut SmoothBorderNormals,
+        )>,
+        mut previous_chunks: Local<HashMap<Entity, ChunkCoord>>,
+    ) {
+        let modified_meshes = mesh_events
+            .read()
+            .filter_map(|event| match event {
+                AssetEvent::Added { id }
+                | AssetEvent::Modified { id }
+                | AssetEvent::LoadedWithDependencies { id } => Some(*id),
+                _ => None,
+            })
+            .collect::<HashSet<_>>();
+
+        let chunks = chunk_query
+            .iter()
+            .map(|(entity, coord, ..)| (*coord, entity))
+            .collect::<HashMap<_, _>>();
+
+        // The neighbours of removed or moved chunks lose the normals they were averaged with.
+        let mut changed = previous_chunks
+            .iter()
+            .filter(|(entity, coord)| chunks.get(*coord) != Some(*entity))
+            .map(|(_, coord)| *coord)
+            .collect::<HashSet<_>>();
+        *previous_chunks = chunk_query
+            .iter()
+            .map(|(entity, coord, ..)| (entity, *coord))
+            .collect();
+
+        for (_, coord, mesh, mut smooth_border_normals) in chunk_query.iter_mut() {
+            if !(mesh.is_changed()
+                || smooth_border_normals.is_added()
+                || modified_meshes.contains(&mesh.id()))
+            {
+                continue;
+            }
+            let Some(mesh) = meshes.get(mesh.id()) else {
+                continue;
+            };
+            if smooth_border_normals.is_current(mesh) {
+                continue;
+            }
+
+            let border = border_vertices(mesh, &config, *coord);
+            smooth_border_normals.vertex_count = mesh.count_vertices();
+            smooth_border_normals.smoothed = border.iter().map(|vertex| vertex.normal).collect();
+            smooth_border_normals.border = border;
+            changed.insert(*coord);
+        }
+
+        if changed.is_empty() {
+            return;
+        }
+
+        let affected = changed
+            .iter()
+            .flat_map(|coord| neighbourhood(*coord))
+            .filter(|coord| chunks.contains_key(coord))
+            .collect::<HashSet<_>>();
+        let involved = affected
+            .iter()
+            .flat_map(|coord| neighbourhood(*coord))
+            .filter_map(|coord| chunks.get(&coord))
+            .collect::<HashSet<_>>();
+
+        let mut sums = HashMap::<IVec3, Vec3>::default();
+        for entity in involved {
+            let Ok((.., smooth_border_normals)) = chunk_query.get(*entity) else {
+                continue;
+            };
+            for vertex in &smooth_border_normals.border {
+                *sums.entry(vertex.key).or_default() += vertex.normal;
+            }
+        }
+
+        for coord in affected {
+            let Ok((_, _, mesh, mut smooth_border_normals)) = chunk_query.get_mut(chunks[&coord])
+            else {
+                continue;
+            };
+            let smoothed = smooth_border_normals
+                .border
+                .iter()
+                .map(|vertex| sums[&vertex.key].normalize_or(vertex.normal))
+                .collect::<Vec<_>>();
+            if smoothed == smooth_border_normals.smoothed {
+                continue;
+            }
+
+            let Some(mesh) = meshes.get_mut(mesh.id()) else {
+                continue;
+            };
+            if let Some(VertexAttributeValues::Float32x3(normals)) =
+                mesh.attribute_mut(Mesh::ATTRIBUTE_NORMAL)
+            {
+                for (vertex, normal) in smooth_border_normals.border.iter().zip(&smoothed) {
+                    normals[vertex.index] = normal.to_array();
+                }
+            }
+            smooth_border_normals.smoothed = smoothed;
+        }
+    }
+
+    /// Whether the border normals of `mesh` are the ones last written, i.e. the mesh wasn't
+    /// replaced since it was smoothed.
+    fn is_current(&self, mesh: &Mesh) -> bool {
+        let Some(VertexAttributeValues::Float32x3(normals)) =
+            mesh.attribute(Mesh::ATTRIBUTE_NORMAL)
+        else {
+            return false;
+        };
+        !self.border.is_empty()
+            && self.vertex_count == mesh.count_vertices()
+            && self
+                .border
+                .iter()
+                .zip(&self.smoothed)
+                .all(|(vertex, smoothed)| normals[vertex.index] == smoothed.to_array())
+    }
+}
+
+/// The vertices of `mesh` lying on a face of the chunk at `coord`.
+fn border_vertices(mesh: &Mesh, config: &VoxelWorldConfig, coord: ChunkCoord) -> Vec<BorderVertex> {
+    let (
+        Some(VertexAttributeValues::Float32x3(positions)),
+        Some(VertexAttributeValues::Float32x3(normals)),
+    ) = (
+        mesh.attribute(Mesh::ATTRIBUTE_POSITION),
+        mesh.attribute(Mesh::ATTRIBUTE_NORMAL),
+    )
+    else {
+        return Vec::new();
+    };
+
+    let chunk_size = VoxelWorldConfig::CHUNK_SIZE as f32;
+    let chunk_origin = config.chunk_to_voxel(coord).as_vec3();
+    positions
+        .iter()
+        .zip(normals)
+        .enumerate()
+        .filter_map(|(index, (position, normal))| {
+            let position = Vec3::from_array(*position);
+            let on_border = position.abs().min_element() < BORDER_EPSILON
+                || (position - chunk_size).abs().min_element() < BORDER_EPSILON;
+            on_border.then(|| BorderVertex {
+                index,
+                key: ((chunk_origin + position) * BORDER_GRID).round().as_ivec3(),
+                normal: Vec3::from_array(*normal),
+            })
+        })
+        .collect()
+}
+
+/// The chunk at `coord` and the 26 chunks around it.
+fn neighbourhood(coord: ChunkCoord) -> impl Iterator<Item = ChunkCoord> {
+    (-1..=1).flat_map(move |z| {
+        (-1..=1).flat_map(move |y| (-1..=1).map(move |x| ChunkCoord(coord.0 + IVec3::new(x, y, z))))
+    })
+}
+
+/// Smooths the normals across the borders of the chunks with [`SmoothBorderNormals`].
+pub struct BorderNormalsPlugin;
+
+impl Plugin for BorderNormalsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<VoxelWorldConfig>()
+            .add_systems(PostUpdate, SmoothBorderNormals::smooth);
+    }
+}
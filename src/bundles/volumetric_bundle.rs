@@ -1,14 +1,46 @@
 use bevy::{prelude::*, render::extract_component::ExtractComponent};
 
-use crate::data::{voxel::Voxel, voxel_material::VoxelMaterial};
+use crate::data::{
+    boundary_mode::BoundaryMode, chunk_priority::ChunkPriority, generation_graph::GraphVolume,
+    meshing_algorithm::MeshingAlgorithm, procedural_volume::ProceduralVolume,
+    virtual_volume::VirtualVolume, voxel_material::VoxelMaterial, voxel_world::DirtyRegion,
+};
 
 #[derive(Clone, Copy, Component, ExtractComponent)]
 pub struct Volumetric;
 
+/// Closes the mesh of a volumetric entity with caps where its surface meets the edge of the volume,
+/// treating everything outside the volume as empty regardless of its [`BoundaryMode`].
+#[derive(Clone, Copy, Component, ExtractComponent)]
+pub struct MeshCapping;
+
+/// Splits the index buffer of a volumetric entity into one range per material, the flags of the
+/// solid voxel each triangle comes from, see
+/// [`MaterialIndexRanges`](crate::data::material_split::MaterialIndexRanges).
+#[derive(Clone, Copy, Component, ExtractComponent)]
+pub struct MaterialSplit;
+
+/// Shades the mesh of a volumetric entity with smooth normals from the gradient of its densities
+/// instead of flat ones, reading the voxels past its faces from the chunks next to it by
+/// [`ChunkCoord`](crate::data::chunk_coord::ChunkCoord), so that the shading shows no seam between
+/// chunks. An alternative to [`SmoothBorderNormals`](crate::border_normals::SmoothBorderNormals)
+/// fully on the GPU; faces without a neighbour, and virtual volumes, fall back to the
+/// [`BoundaryMode`].
+#[derive(Clone, Copy, Component, ExtractComponent)]
+pub struct SeamlessNormals;
+
+/// Freezes the meshing of a volumetric entity while keeping its data and bind groups.
+#[derive(Clone, Copy, Component, ExtractComponent)]
+pub struct VoxelComputeSuspended;
+
 #[derive(Bundle)]
 pub struct VolumetricBundle {
     pub volumetric: Volumetric,
     pub material: VoxelMaterial,
+    pub dirty_region: DirtyRegion,
+    pub meshing_algorithm: MeshingAlgorithm,
+    pub boundary_mode: BoundaryMode,
+    pub priority: ChunkPriority,
 }
 
 impl VolumetricBundle {
@@ -17,6 +49,124 @@ impl VolumetricBundle {
         Self {
             volumetric: Volumetric,
             material: voxel_material,
+            dirty_region: DirtyRegion::default(),
+            meshing_algorithm: MeshingAlgorithm::default(),
+            boundary_mode: BoundaryMode::default(),
+            priority: ChunkPriority::default(),
+        }
+    }
+
+    /// Meshes the volume with `meshing_algorithm` instead of the default.
+    pub fn with_meshing_algorithm(mut self, meshing_algorithm: MeshingAlgorithm) -> Self {
+        self.meshing_algorithm = meshing_algorithm;
+        self
+    }
+
+    /// Treats densities outside of the volume according to `boundary_mode` instead of the default.
+    pub fn with_boundary_mode(mut self, boundary_mode: BoundaryMode) -> Self {
+        self.boundary_mode = boundary_mode;
+        self
+    }
+}
+
+#[derive(Bundle)]
+pub struct VirtualVolumeBundle {
+    pub volumetric: Volumetric,
+    pub virtual_volume: VirtualVolume,
+    pub meshing_algorithm: MeshingAlgorithm,
+    pub boundary_mode: BoundaryMode,
+    pub priority: ChunkPriority,
+}
+
+impl VirtualVolumeBundle {
+    /// Creates a new bundle meshing the resident bricks of `virtual_volume`.
+    pub fn new(virtual_volume: VirtualVolume) -> Self {
+        Self {
+            volumetric: Volumetric,
+            virtual_volume,
+            meshing_algorithm: MeshingAlgorithm::default(),
+            boundary_mode: BoundaryMode::default(),
+            priority: ChunkPriority::default(),
+        }
+    }
+
+    /// Meshes the volume with `meshing_algorithm` instead of the default.
+    pub fn with_meshing_algorithm(mut self, meshing_algorithm: MeshingAlgorithm) -> Self {
+        self.meshing_algorithm = meshing_algorithm;
+        self
+    }
+
+    /// Treats densities outside of the volume according to `boundary_mode` instead of the default.
+    pub fn with_boundary_mode(mut self, boundary_mode: BoundaryMode) -> Self {
+        self.boundary_mode = boundary_mode;
+        self
+    }
+}
+
+/// A chunk whose voxels are generated on the GPU from a [`ProceduralVolume`].
+#[derive(Bundle)]
+pub struct ProceduralVolumeBundle {
+    pub volumetric: Volumetric,
+    pub procedural_volume: ProceduralVolume,
+    pub meshing_algorithm: MeshingAlgorithm,
+    pub boundary_mode: BoundaryMode,
+    pub priority: ChunkPriority,
+}
+
+impl ProceduralVolumeBundle {
+    pub fn new(procedural_volume: ProceduralVolume) -> Self {
+        Self {
+            volumetric: Volumetric,
+            procedural_volume,
+            meshing_algorithm: MeshingAlgorithm::default(),
+            boundary_mode: BoundaryMode::default(),
+            priority: ChunkPriority::default(),
         }
     }
+
+    /// Meshes the volume with `meshing_algorithm` instead of the default.
+    pub fn with_meshing_algorithm(mut self, meshing_algorithm: MeshingAlgorithm) -> Self {
+        self.meshing_algorithm = meshing_algorithm;
+        self
+    }
+
+    /// Treats densities outside of the volume according to `boundary_mode` instead of the default.
+    pub fn with_boundary_mode(mut self, boundary_mode: BoundaryMode) -> Self {
+        self.boundary_mode = boundary_mode;
+        self
+    }
+}
+
+/// A chunk whose voxels are generated on the GPU from the graph of a [`GraphVolume`].
+#[derive(Bundle)]
+pub struct GraphVolumeBundle {
+    pub volumetric: Volumetric,
+    pub graph_volume: GraphVolume,
+    pub meshing_algorithm: MeshingAlgorithm,
+    pub boundary_mode: BoundaryMode,
+    pub priority: ChunkPriority,
+}
+
+impl GraphVolumeBundle {
+    pub fn new(graph_volume: GraphVolume) -> Self {
+        Self {
+            volumetric: Volumetric,
+            graph_volume,
+            meshing_algorithm: MeshingAlgorithm::default(),
+            boundary_mode: BoundaryMode::default(),
+            priority: ChunkPriority::default(),
+        }
+    }
+
+    /// Meshes the volume with `meshing_algorithm` instead of the default.
+    pub fn with_meshing_algorithm(mut self, meshing_algorithm: MeshingAlgorithm) -> Self {
+        self.meshing_algorithm = meshing_algorithm;
+        self
+    }
+
+    /// Treats densities outside of the volume according to `boundary_mode` instead of the default.
+    pub fn with_boundary_mode(mut self, boundary_mode: BoundaryMode) -> Self {
+        self.boundary_mode = boundary_mode;
+        self
+    }
 }
@@ -1,70 +1,1240 @@
 use bevy::{
-    ecs::{
-        query::ROQueryItem,
-        system::{lifetimeless::SRes, SystemParamItem},
-        world::Command,
-    },
+    core::FrameCount,
+    ecs::{component::ComponentId, world::DeferredWorld},
     prelude::*,
     render::{
         extract_component::{ExtractComponent, ExtractComponentPlugin},
-        render_graph::{self, NodeRunError, RenderGraph, RenderLabel},
-        render_phase::{PhaseItem, RenderCommand, RenderCommandResult, TrackedRenderPass},
-        render_resource::{binding_types::storage_buffer, *},
-        renderer::{RenderContext, RenderDevice, RenderQueue},
-        Extract, Render, RenderApp, RenderSet,
+        extract_resource::ExtractResource,
+        render_resource::*,
+        renderer::{RenderDevice, RenderQueue},
+        Render, RenderApp, RenderSet,
     },
-    utils::{info, HashMap},
+    utils::{HashMap, HashSet},
 };
+use bytemuck::Pod;
 use crossbeam_channel::{Receiver, Sender};
+use std::{collections::VecDeque, ops::Range};
+
+use crate::{
+    bundles::volumetric_bundle::Volumetric,
+    core::voxel::unpack_rgb9e5,
+    data::{
+        atomics::Atomics,
+        chunk_priority::ChunkPriority,
+        gpu_chunk_state::{GpuChunkState, GpuChunkStates},
+        gpu_iso_surface::GpuIsoSurfaces,
+        gpu_voxel_material::{GpuVoxelMaterial, MeshCounts},
+        iso_surface::{IsoSurfaceMesh, IsoSurfaceMeshData},
+        material_split::MaterialIndexRanges,
+        meshing_algorithm::MeshingAlgorithm,
+        normal_encoding::read_normals,
+        raw_mesh_data::{GpuRawMeshData, RawMeshData, RawMeshRegion, RawMeshUpdate},
+        readback_header::MeshReadbackHeader,
+        voxel_material::{VoxelMaterial, VoxelMaterialComponents},
+    },
+    render::voxel_mesh_compute_pipeline::DirtyMeshes,
+};
+
+#[cfg(feature = "validate-gpu")]
+use crate::validation::GpuValidation;
+
+/// Counts the times the voxels of a volumetric entity were replaced by inserting a new
+/// [`VoxelMaterial`](crate::data::voxel_material::VoxelMaterial) or
+/// [`VirtualVolume`](crate::data::virtual_volume::VirtualVolume), so that GPU results computed
+/// for the previous voxels can be told apart. Entities without one are at version 0.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, ExtractComponent)]
+pub struct VoxelDataVersion(pub u32);
+
+impl VoxelDataVersion {
+    /// Component hook bumping the version of an entity whenever its voxels are inserted.
+    pub(crate) fn on_insert(mut world: DeferredWorld, entity: Entity, _: ComponentId) {
+        match world.get_mut::<VoxelDataVersion>(entity) {
+            Some(mut version) => version.0 = version.0.wrapping_add(1),
+            None => {
+                world
+                    .commands()
+                    .entity(entity)
+                    .insert(VoxelDataVersion::default());
+            }
+        }
+    }
+}
+
+/// Identifies the voxels a readback was started for: the entity, whose generation changes once it
+/// is despawned, and the [`VoxelDataVersion`] of its voxels. Results whose tag is no longer
+/// current are dropped instead of being applied.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ReadbackTag {
+    pub entity: Entity,
+    pub version: u32,
+}
+
+impl ReadbackTag {
+    /// The tag of the current voxels of `entity`, or `None` if it is no longer volumetric.
+    pub fn current(
+        entity: Entity,
+        version_query: &Query<Option<&VoxelDataVersion>, With<Volumetric>>,
+    ) -> Option<Self> {
+        let version = version_query.get(entity).ok()?;
+        Some(Self {
+            entity,
+            version: version.map_or(0, |version| version.0),
+        })
+    }
 
-use crate::data::{gpu_voxel_material::GpuVoxelMaterial, voxel_material::VoxelMaterialComponents};
+    /// Whether the entity still holds the voxels the readback was started for.
+    pub fn is_current(
+        &self,
+        version_query: &Query<Option<&VoxelDataVersion>, With<Volumetric>>,
+    ) -> bool {
+        Self::current(self.entity, version_query) == Some(*self)
+    }
+}
 
+/// Receives the vertices read back for each mesh, as the words of a [`MeshReadbackHeader`]
+/// followed by those of the vertices.
 #[derive(Resource, Deref)]
-pub struct MainWorldReceiver(pub Receiver<Vec<u32>>);
+pub struct MainWorldReceiver(pub Receiver<(ReadbackTag, Vec<u32>)>);
 
 impl MainWorldReceiver {
-    pub fn receive(receiver: Res<Self>) {
-        if let Ok(data) = receiver.try_recv() {
-            println!("Received data from render world: {data:?}");
+    pub fn receive(
+        receiver: Res<Self>,
+        version_query: Query<Option<&VoxelDataVersion>, With<Volumetric>>,
+    ) {
+        if let Ok((tag, data)) = receiver.try_recv() {
+            if !tag.is_current(&version_query) {
+                return;
+            }
+            match MeshReadbackHeader::split(&data) {
+                Ok((header, _)) => trace!("Received mesh readback of {:?}: {header:?}", tag.entity),
+                Err(err) => warn!("Dropped mesh readback of {:?}: {err}", tag.entity),
+            }
+        }
+    }
+}
+
+/// Limits how much data is read back from the GPU each frame. Readbacks over the limits are
+/// queued for later frames, without being copied into their staging buffers until then.
+#[derive(Resource, Clone, Copy, Debug, ExtractResource)]
+pub struct ReadbackPolicy {
+    /// Maximum number of bytes read back per frame. At least one chunk is always read back.
+    pub max_bytes_per_frame: u64,
+    pub max_chunks_per_frame: usize,
+}
+
+impl Default for ReadbackPolicy {
+    fn default() -> Self {
+        Self {
+            max_bytes_per_frame: u64::MAX,
+            max_chunks_per_frame: usize::MAX,
+        }
+    }
+}
+
+/// Entities waiting to be read back, by descending [`ChunkPriority`] and then oldest first, and
+/// those selected to be read back this frame within the [`ReadbackPolicy`].
+#[derive(Resource, Default)]
+pub struct PendingReadbacks {
+    queue: VecDeque<Entity>,
+    /// The entities of `queue` and `selected`, for the checks made for every meshed entity each
+    /// frame.
+    entities: HashSet<Entity>,
+    /// The entities copied into their staging buffers and read back this frame, in order.
+    selected: Vec<Entity>,
+}
+
+impl PendingReadbacks {
+    pub fn contains(&self, entity: &Entity) -> bool {
+        self.entities.contains(entity)
+    }
+
+    /// Whether `entity` is copied into its staging buffers and read back this frame.
+    pub fn is_selected(&self, entity: &Entity) -> bool {
+        self.contains(entity) && self.selected.contains(entity)
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len() + self.selected.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty() && self.selected.is_empty()
+    }
+
+    /// The entities in the order they are read back.
+    pub fn iter(&self) -> impl Iterator<Item = &Entity> {
+        self.selected.iter().chain(self.queue.iter())
+    }
+
+    /// The entities read back this frame.
+    pub fn selected(&self) -> impl Iterator<Item = &Entity> {
+        self.selected.iter()
+    }
+
+    /// Queues `entity` last, unless it is already queued.
+    pub fn push_back(&mut self, entity: Entity) {
+        if self.entities.insert(entity) {
+            self.queue.push_back(entity);
+        }
+    }
+
+    pub fn pop_front(&mut self) -> Option<Entity> {
+        let entity = self.queue.pop_front()?;
+        self.entities.remove(&entity);
+        Some(entity)
+    }
+
+    /// Takes the entities selected this frame out of the queue, to be queued again if they have
+    /// more pages to read or failed to map.
+    fn take_selected(&mut self) -> Vec<Entity> {
+        let selected = std::mem::take(&mut self.selected);
+        for entity in &selected {
+            self.entities.remove(entity);
+        }
+        selected
+    }
+
+    /// Queues the entities remeshed this frame, then selects the pending entities read back this
+    /// frame by priority, as far as the [`ReadbackPolicy`] allows. Only those are copied into
+    /// their staging buffers by the
+    /// [`VoxelMeshComputeNode`](crate::render::voxel_mesh_compute_pipeline::VoxelMeshComputeNode),
+    /// the others keep their place in the queue without any GPU traffic.
+    #[allow(clippy::too_many_arguments)]
+    pub fn select(
+        mut pending_readbacks: ResMut<Self>,
+        gpu_voxel_materials: Res<VoxelMaterialComponents<GpuVoxelMaterial>>,
+        mut vertex_readbacks: ResMut<VoxelMaterialComponents<VertexReadback>>,
+        readback_policy: Res<ReadbackPolicy>,
+        mut readback_retries: ResMut<ReadbackRetries>,
+        frame_count: Res<FrameCount>,
+        priority_query: Query<&ChunkPriority>,
+        dirty_meshes: Res<DirtyMeshes>,
+        gpu_chunk_states: Res<GpuChunkStates>,
+        version_query: Query<Option<&VoxelDataVersion>, With<Volumetric>>,
+    ) {
+        for entity in dirty_meshes.meshed() {
+            vertex_readbacks.0.entry(*entity).or_default().tag =
+                ReadbackTag::current(*entity, &version_query);
+            pending_readbacks.push_back(*entity);
+        }
+
+        let priority = |entity: &Entity| priority_query.get(*entity).map_or(0.0, |p| p.0);
+        pending_readbacks
+            .queue
+            .make_contiguous()
+            .sort_by(|a, b| priority(b).total_cmp(&priority(a)));
+
+        let mut bytes_read = 0;
+
+        // Visit every pending entity at most once, moving those waiting to retry to the back.
+        for _ in 0..pending_readbacks.queue.len() {
+            let Some(entity) = pending_readbacks.queue.front().copied() else {
+                break;
+            };
+            // Once the entity is despawned or its voxels replaced, its staging buffers no longer
+            // hold the mesh being read back. Replaced voxels are remeshed anyway.
+            let tag = ReadbackTag::current(entity, &version_query);
+            let cancelled = tag.is_none()
+                || vertex_readbacks
+                    .get(&entity)
+                    .map(|vertex_readback| vertex_readback.tag)
+                    != Some(tag);
+            let gpu_voxel_material = match cancelled {
+                true => None,
+                false => gpu_voxel_materials.get(&entity),
+            };
+            let Some(gpu_voxel_material) = gpu_voxel_material else {
+                // The entity's material was removed or its readback cancelled while it was queued.
+                pending_readbacks.pop_front();
+                readback_retries.remove(&entity);
+                vertex_readbacks.0.remove(&entity);
+                continue;
+            };
+
+            // Entities in error are not copied into their staging buffers, see `GpuChunkStates`.
+            if readback_retries.is_waiting(&entity, frame_count.0)
+                || !gpu_chunk_states.is_ready(&entity)
+            {
+                pending_readbacks.queue.rotate_left(1);
+                continue;
+            }
+
+            let chunks_read = pending_readbacks.selected.len();
+            let page = vertex_readbacks
+                .0
+                .entry(entity)
+                .or_default()
+                .page(gpu_voxel_material, &readback_policy);
+            let size = page.end - page.start;
+            if chunks_read >= readback_policy.max_chunks_per_frame
+                || (chunks_read > 0 && bytes_read + size > readback_policy.max_bytes_per_frame)
+            {
+                break;
+            }
+
+            pending_readbacks.queue.pop_front();
+            pending_readbacks.selected.push(entity);
+            bytes_read += size;
         }
     }
 }
 
+/// Sent when mapping the staging buffers of an entity for readback fails.
+#[derive(Event, Clone, Debug)]
+pub struct ReadbackFailed {
+    pub entity: Entity,
+    /// Consecutive failed attempts, including this one.
+    pub attempts: u32,
+    pub error: String,
+}
+
+/// Failed attempts after which the staging buffer of an entity is assumed to be lost and is
+/// recreated.
+const RECREATE_STAGING_BUFFER_ATTEMPTS: u32 = 2;
+
+/// Longest delay in frames between two readback attempts of an entity.
+const MAX_RETRY_DELAY: u32 = 64;
+
+/// The failed readback of an entity, retried once the frame count reaches `retry_at`.
+#[derive(Clone, Copy, Debug)]
+pub struct ReadbackRetry {
+    pub attempts: u32,
+    pub retry_at: u32,
+}
+
+/// Entities whose last readback failed.
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct ReadbackRetries(pub HashMap<Entity, ReadbackRetry>);
+
+impl ReadbackRetries {
+    /// Records a failed readback of `entity`, doubling its retry delay, and returns the number of
+    /// consecutive failed attempts.
+    fn fail(&mut self, entity: Entity, frame: u32) -> u32 {
+        let retry = self.0.entry(entity).or_insert(ReadbackRetry {
+            attempts: 0,
+            retry_at: frame,
+        });
+        retry.attempts += 1;
+        retry.retry_at = frame.wrapping_add((1 << retry.attempts.min(6)).min(MAX_RETRY_DELAY));
+        retry.attempts
+    }
+
+    fn is_waiting(&self, entity: &Entity, frame: u32) -> bool {
+        self.0
+            .get(entity)
+            .is_some_and(|retry| (retry.retry_at.wrapping_sub(frame) as i32) > 0)
+    }
+}
+
+#[derive(Resource, Deref)]
+pub struct ReadbackFailedReceiver(pub Receiver<ReadbackFailed>);
+
+impl ReadbackFailedReceiver {
+    /// Forwards the failures reported by the render world as [`ReadbackFailed`] events.
+    pub fn receive(receiver: Res<Self>, mut readback_failed: EventWriter<ReadbackFailed>) {
+        readback_failed.send_batch(receiver.try_iter());
+    }
+}
+
+#[derive(Resource, Deref)]
+pub struct ReadbackFailedSender(pub Sender<ReadbackFailed>);
+
+#[derive(Resource, Deref)]
+pub struct GpuChunkStateReceiver(pub Receiver<(Entity, GpuChunkState)>);
+
+impl GpuChunkStateReceiver {
+    /// Mirrors the [`GpuChunkState`] changes of the render world onto their entities.
+    pub fn receive(receiver: Res<Self>, mut commands: Commands) {
+        for (entity, state) in receiver.try_iter() {
+            if let Some(mut entity_commands) = commands.get_entity(entity) {
+                entity_commands.insert(state);
+            }
+        }
+    }
+}
+
+#[derive(Resource, Deref)]
+pub struct GpuChunkStateSender(pub Sender<(Entity, GpuChunkState)>);
+
+/// Maps `buffers` for reading, blocking until the GPU is done with them. If any of them fails to
+/// map, the others are unmapped again.
+pub(crate) fn map_buffers(
+    render_device: &RenderDevice,
+    buffers: &[&Buffer],
+) -> Result<(), BufferAsyncError> {
+    let (s, r) = crossbeam_channel::unbounded();
+
+    for (index, buffer) in buffers.iter().enumerate() {
+        let s = s.clone();
+        buffer.slice(..).map_async(MapMode::Read, move |result| {
+            s.send((index, result)).expect("Failed to send map update")
+        });
+    }
+
+    render_device.poll(Maintain::Wait);
+
+    let results = r.try_iter().take(buffers.len()).collect::<Vec<_>>();
+    let error = results
+        .iter()
+        .find_map(|(_, result)| result.clone().err())
+        .or((results.len() < buffers.len()).then_some(BufferAsyncError));
+
+    match error {
+        Some(error) => {
+            for (index, result) in results {
+                if result.is_ok() {
+                    buffers[index].unmap();
+                }
+            }
+            Err(error)
+        }
+        None => Ok(()),
+    }
+}
+
+/// Pages are split on vertex boundaries, vertices being stored as vec3<f32> with a 16 byte stride.
+const VERTEX_STRIDE: u64 = std::mem::size_of::<Vec4>() as u64;
+
+/// Progress of the paged readback of an entity's vertices. Vertices that don't fit in the staging
+/// buffer or in the [`ReadbackPolicy`] byte limit are read back one page per frame and reassembled,
+/// with the mesh left as is until its last page is read so that every page comes from the same mesh.
+#[derive(Default)]
+pub struct VertexReadback {
+    /// Byte offset of the next page in the vertices buffer.
+    pub offset: u64,
+    /// Bytes of vertices generated, known once the first page is read.
+    pub total: Option<u64>,
+    /// The voxels the mesh being read back was generated from.
+    pub tag: Option<ReadbackTag>,
+    /// The header read along with the first page.
+    header: MeshReadbackHeader,
+    data: Vec<u8>,
+}
+
+impl VertexReadback {
+    /// Whether some pages of the mesh have been read but not all of them.
+    pub fn in_progress(&self) -> bool {
+        self.offset > 0
+    }
+
+    /// The bytes of the vertices buffer copied into the staging buffer for the next page, after
+    /// its [`MeshReadbackHeader`].
+    pub fn page(
+        &self,
+        gpu_voxel_material: &GpuVoxelMaterial,
+        policy: &ReadbackPolicy,
+    ) -> Range<u64> {
+        let buffer_size = gpu_voxel_material
+            .vertices_buffer
+            .buffer()
+            .map_or(0, |buffer| buffer.size());
+        let end = self.total.unwrap_or(buffer_size).min(buffer_size);
+        let start = self.offset.min(end);
+
+        let page_size = (gpu_voxel_material.vertices_staging_buffer.size()
+            - MeshReadbackHeader::SIZE)
+            .min(policy.max_bytes_per_frame)
+            / VERTEX_STRIDE
+            * VERTEX_STRIDE;
+
+        start..(start + page_size.max(VERTEX_STRIDE)).min(end)
+    }
+}
+
 #[derive(Resource, Deref)]
-pub struct RenderWorldSender(pub Sender<Vec<u32>>);
+pub struct RenderWorldSender(pub Sender<(ReadbackTag, Vec<u32>)>);
 
 impl RenderWorldSender {
+    /// Maps the staging buffers of the entities selected by [`PendingReadbacks::select`] and
+    /// sends their meshes to the main world once all of their pages are read.
+    #[allow(clippy::too_many_arguments)]
     pub fn map_and_read_buffer(
         render_device: Res<RenderDevice>,
-        gpu_voxel_materials: ResMut<VoxelMaterialComponents<GpuVoxelMaterial>>,
+        mut gpu_voxel_materials: ResMut<VoxelMaterialComponents<GpuVoxelMaterial>>,
+        mut vertex_readbacks: ResMut<VoxelMaterialComponents<VertexReadback>>,
+        readback_policy: Res<ReadbackPolicy>,
+        mut pending_readbacks: ResMut<PendingReadbacks>,
+        mut readback_retries: ResMut<ReadbackRetries>,
+        frame_count: Res<FrameCount>,
+        mut material_index_ranges: ResMut<VoxelMaterialComponents<MaterialIndexRanges>>,
         sender: Res<Self>,
+        failed_sender: Res<ReadbackFailedSender>,
+        #[cfg(feature = "validate-gpu")] mut gpu_validation: ResMut<GpuValidation>,
     ) {
-        for (_, gpu_voxel_material) in &gpu_voxel_materials.0 {
-            let buffer_slice = gpu_voxel_material.vertices_staging_buffer.slice(..);
+        for entity in pending_readbacks.take_selected() {
+            let (Some(gpu_voxel_material), Some(vertex_readback)) = (
+                gpu_voxel_materials.get_mut(&entity),
+                vertex_readbacks.0.get_mut(&entity),
+            ) else {
+                continue;
+            };
+            let page = vertex_readback.page(gpu_voxel_material, &readback_policy);
+            let size = page.end - page.start;
+
+            // The vertex count is read along with the first page.
+            let first_page = page.start == 0;
+            let buffers = match first_page {
+                true => vec![
+                    &gpu_voxel_material.vertices_staging_buffer,
+                    &gpu_voxel_material.atomics_staging_buffer,
+                ],
+                false => vec![&gpu_voxel_material.vertices_staging_buffer],
+            };
+
+            if let Err(err) = map_buffers(&render_device, &buffers) {
+                let attempts = readback_retries.fail(entity, frame_count.0);
+                if attempts >= RECREATE_STAGING_BUFFER_ATTEMPTS {
+                    gpu_voxel_material.recreate_vertices_staging_buffer(&render_device);
+                }
+
+                let _ = failed_sender.send(ReadbackFailed {
+                    entity,
+                    attempts,
+                    error: err.to_string(),
+                });
+                pending_readbacks.push_back(entity);
+                continue;
+            }
+            readback_retries.remove(&entity);
+
+            if first_page {
+                let atomics = gpu_voxel_material
+                    .atomics_staging_buffer
+                    .slice(..)
+                    .get_mapped_range();
+                let header = MeshReadbackHeader::from_bytes(
+                    &gpu_voxel_material
+                        .vertices_staging_buffer
+                        .slice(..MeshReadbackHeader::SIZE)
+                        .get_mapped_range(),
+                )
+                .expect("the header should be written along with the first page");
+                vertex_readback.header = header;
+                vertex_readback.total = Some(header.vertex_count as u64 * VERTEX_STRIDE);
+                vertex_readback.data.clear();
+                gpu_voxel_material.mesh_counts = Some(MeshCounts {
+                    vertices: header.vertex_count,
+                    indices: header.index_count,
+                });
+
+                if let Some(ranges) = material_index_ranges.get_mut(&entity) {
+                    let heads = read_u32s(&atomics, 0..Atomics::LEN).collect::<Vec<_>>();
+                    *ranges = MaterialIndexRanges::from_atomics(
+                        &heads,
+                        gpu_voxel_material.index_capacity(),
+                    );
+                }
+
+                #[cfg(feature = "validate-gpu")]
+                gpu_validation.record_index_count(entity, header.index_count);
+            }
+
+            if size > 0 {
+                let buffer_view = gpu_voxel_material
+                    .vertices_staging_buffer
+                    .slice(MeshReadbackHeader::SIZE..MeshReadbackHeader::SIZE + size)
+                    .get_mapped_range();
+                vertex_readback.data.extend_from_slice(&buffer_view);
+            }
+
+            for buffer in buffers {
+                buffer.unmap();
+            }
+
+            vertex_readback.offset = page.end;
+            let total = vertex_readback.total.unwrap_or(0);
+            if vertex_readback.offset < total && size > 0 {
+                // Read the next page on a later frame.
+                pending_readbacks.push_back(entity);
+                continue;
+            }
+
+            vertex_readback.data.truncate(total as usize);
+            let mut header = vertex_readback.header;
+            if (vertex_readback.data.len() as u64) < total {
+                header.flags |= MeshReadbackHeader::TRUNCATED;
+            }
+            let data = header
+                .to_words()
+                .into_iter()
+                .chain(
+                    vertex_readback
+                        .data
+                        .chunks_exact(std::mem::size_of::<u32>())
+                        .map(|chunk| {
+                            u32::from_le_bytes(chunk.try_into().expect("should be a u32"))
+                        }),
+                )
+                .collect::<Vec<u32>>();
 
-            let (s, r) = crossbeam_channel::unbounded::<()>();
+            #[cfg(feature = "validate-gpu")]
+            gpu_validation.compare(entity, &data[MeshReadbackHeader::LEN..]);
 
-            buffer_slice.map_async(MapMode::Read, move |result| match result {
-                Ok(_) => s.send(()).expect("Failed to send map update"),
-                Err(err) => panic!("Failed to map buffer {err}"),
+            let tag = vertex_readback
+                .tag
+                .expect("cancelled readbacks were skipped");
+            sender
+                .send((tag, data))
+                .expect("Failed to send data to main world");
+
+            vertex_readback.offset = 0;
+            vertex_readback.total = None;
+        }
+    }
+}
+
+#[derive(Resource, Deref)]
+pub struct RawMeshReceiver(pub Receiver<(ReadbackTag, RawMeshUpdate)>);
+
+impl RawMeshReceiver {
+    /// Stores the latest mesh read back for each entity in its [`RawMeshData`], patching the
+    /// regions read back into it. Entities whose mesh is not the one a region was read back for
+    /// are uploaded and read back in full again.
+    pub fn receive(
+        receiver: Res<Self>,
+        mut raw_mesh_query: Query<(&mut RawMeshData, Option<&mut VoxelMaterial>)>,
+        version_query: Query<Option<&VoxelDataVersion>, With<Volumetric>>,
+    ) {
+        for (tag, update) in receiver.try_iter() {
+            if !tag.is_current(&version_query) {
+                continue;
+            }
+            let Ok((mut raw_mesh_data, voxel_material)) = raw_mesh_query.get_mut(tag.entity) else {
+                continue;
+            };
+            match update {
+                RawMeshUpdate::Full(data) => *raw_mesh_data = data,
+                RawMeshUpdate::Region(region) => {
+                    if !raw_mesh_data.patch(&region) {
+                        if let Some(mut voxel_material) = voxel_material {
+                            voxel_material.voxels_mut();
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Resource, Deref)]
+pub struct RawMeshSender(pub Sender<(ReadbackTag, RawMeshUpdate)>);
+
+impl RawMeshSender {
+    /// Reads back the generated vertices, UVs, tangents, emissives and indices of every
+    /// [`GpuRawMeshData`] remeshed this frame, trimmed to the counts written by the compute
+    /// shader. Only the output of the [`changed_cells`](GpuRawMeshData::changed_cells) is read back
+    /// for the entities that have some, unless the mesh may not have fit in its buffers.
+    #[allow(clippy::too_many_arguments)]
+    pub fn map_and_read_buffers(
+        render_device: Res<RenderDevice>,
+        render_queue: Res<RenderQueue>,
+        mut gpu_raw_meshes: ResMut<VoxelMaterialComponents<GpuRawMeshData>>,
+        gpu_voxel_materials: Res<VoxelMaterialComponents<GpuVoxelMaterial>>,
+        material_index_ranges: Res<VoxelMaterialComponents<MaterialIndexRanges>>,
+        mut dirty_meshes: ResMut<DirtyMeshes>,
+        version_query: Query<Option<&VoxelDataVersion>, With<Volumetric>>,
+        algorithm_query: Query<&MeshingAlgorithm>,
+        sender: Res<Self>,
+        failed_sender: Res<ReadbackFailedSender>,
+    ) {
+        for (entity, gpu_raw_mesh) in gpu_raw_meshes.0.iter_mut() {
+            if !dirty_meshes.is_meshed(entity) {
+                continue;
+            }
+            let Some(tag) = ReadbackTag::current(*entity, &version_query) else {
+                continue;
+            };
+
+            let read = match (&gpu_raw_mesh.changed_cells, gpu_voxel_materials.get(entity)) {
+                (Some(_), Some(gpu_voxel_material)) => Self::read_changed_cells(
+                    &render_device,
+                    &render_queue,
+                    gpu_raw_mesh,
+                    gpu_voxel_material,
+                    algorithm_query.get(*entity).copied().unwrap_or_default(),
+                ),
+                (Some(_), None) => Err(BufferAsyncError),
+                (None, _) => Self::read_mesh(
+                    &render_device,
+                    gpu_raw_mesh,
+                    material_index_ranges.get(entity).is_some(),
+                ),
+            };
+
+            match read {
+                Ok((counts, update)) => {
+                    gpu_raw_mesh.read_back = Some(counts);
+                    sender
+                        .send((tag, update))
+                        .expect("Failed to send raw mesh data to main world");
+                }
+                Err(err) => {
+                    // Remesh the entity to retry in full on the next frame.
+                    gpu_raw_mesh.read_back = None;
+                    dirty_meshes.mark(*entity);
+                    let _ = failed_sender.send(ReadbackFailed {
+                        entity: *entity,
+                        attempts: 1,
+                        error: err.to_string(),
+                    });
+                }
+            }
+        }
+    }
+
+    /// Reads the whole mesh copied into the staging buffers of `gpu_raw_mesh`.
+    fn read_mesh(
+        render_device: &RenderDevice,
+        gpu_raw_mesh: &GpuRawMeshData,
+        material_split: bool,
+    ) -> Result<(MeshCounts, RawMeshUpdate), BufferAsyncError> {
+        map_buffers(render_device, &[&gpu_raw_mesh.atomics_staging_buffer])?;
+        let heads = read_u32s(
+            &gpu_raw_mesh
+                .atomics_staging_buffer
+                .slice(..)
+                .get_mapped_range(),
+            0..Atomics::LEN,
+        )
+        .collect::<Vec<_>>();
+        gpu_raw_mesh.atomics_staging_buffer.unmap();
+
+        let counts = MeshCounts {
+            vertices: heads.first().copied().unwrap_or(0),
+            indices: heads.get(1).copied().unwrap_or(0),
+        };
+        let data = read_raw_mesh(render_device, gpu_raw_mesh, counts, &heads, material_split)?;
+        Ok((counts, RawMeshUpdate::Full(data)))
+    }
+
+    /// Copies the output of the changed cells of `gpu_raw_mesh` into its staging buffers once
+    /// their offsets are read, and reads it as a [`RawMeshRegion`] of the mesh read back before.
+    /// The whole mesh is copied and read instead if either mesh may have been cut short by the
+    /// capacity of the output buffers, which changes where the output of the later cells is.
+    fn read_changed_cells(
+        render_device: &RenderDevice,
+        render_queue: &RenderQueue,
+        gpu_raw_mesh: &GpuRawMeshData,
+        gpu_voxel_material: &GpuVoxelMaterial,
+        algorithm: MeshingAlgorithm,
+    ) -> Result<(MeshCounts, RawMeshUpdate), BufferAsyncError> {
+        let buffers = [
+            &gpu_raw_mesh.atomics_staging_buffer,
+            &gpu_raw_mesh.cell_slots_staging_buffer,
+        ];
+        map_buffers(render_device, &buffers)?;
+        let heads = read_u32s(&buffers[0].slice(..).get_mapped_range(), 0..Atomics::LEN)
+            .collect::<Vec<_>>();
+        let [first, last] = {
+            let slots =
+                read_u32s(&buffers[1].slice(..).get_mapped_range(), 0..8).collect::<Vec<_>>();
+            // The vertices, indices, vertex offset and index offset of each slot.
+            [0, 4].map(|slot| {
+                let word = |word: usize| slots.get(slot + word).copied().unwrap_or(0);
+                (word(0), word(1), word(2), word(3))
+            })
+        };
+        for buffer in buffers {
+            buffer.unmap();
+        }
+
+        let counts = MeshCounts {
+            vertices: heads.first().copied().unwrap_or(0),
+            indices: heads.get(1).copied().unwrap_or(0),
+        };
+        let vertex_capacity = gpu_voxel_material
+            .vertices_buffer
+            .buffer()
+            .map_or(0, |buffer| buffer.size() / VERTEX_STRIDE);
+        let index_capacity = gpu_voxel_material
+            .indices_buffer
+            .buffer()
+            .map_or(0, |buffer| buffer.size() / U32_SIZE);
+        let (cell_vertices, cell_indices) = algorithm.max_cell_output();
+        // Meshes leaving room for the output of another cell were not cut short.
+        let fits = |counts: &MeshCounts| {
+            counts.vertices as u64 + cell_vertices as u64 <= vertex_capacity
+                && counts.indices as u64 + cell_indices as u64 <= index_capacity
+        };
+
+        // The output after the changed cells is where it was, shifted by the elements they added
+        // or removed.
+        let replaced = |new: &Range<u32>, count: u32, base: u32| {
+            let end = new.end as i64 - count as i64 + base as i64;
+            (new.start <= new.end
+                && new.end <= count
+                && (new.start as i64..=base as i64).contains(&end))
+            .then_some(new.start as usize..end as usize)
+        };
+        let new_vertices = first.2..last.2 + last.0;
+        let new_indices = first.3..last.3 + last.1;
+        let region = gpu_raw_mesh
+            .read_back
+            .filter(|base| fits(base) && fits(&counts))
+            .and_then(|base| {
+                Some((
+                    base,
+                    replaced(&new_vertices, counts.vertices, base.vertices)?,
+                    replaced(&new_indices, counts.indices, base.indices)?,
+                ))
             });
+        let (vertices, indices) = match region {
+            Some(_) => (new_vertices, new_indices),
+            None => (0..counts.vertices, 0..counts.indices),
+        };
 
-            render_device.poll(Maintain::Wait);
+        let mut command_encoder = render_device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("raw_mesh_changed_cells_readback"),
+        });
+        for (buffer, staging_buffer, range, stride) in [
+            (
+                gpu_voxel_material.vertices_buffer.buffer(),
+                &gpu_raw_mesh.vertices_staging_buffer,
+                &vertices,
+                VERTEX_STRIDE,
+            ),
+            (
+                gpu_voxel_material.uvs_buffer.buffer(),
+                &gpu_raw_mesh.uvs_staging_buffer,
+                &vertices,
+                std::mem::size_of::<Vec2>() as u64,
+            ),
+            (
+                gpu_voxel_material.tangents_buffer.buffer(),
+                &gpu_raw_mesh.tangents_staging_buffer,
+                &vertices,
+                std::mem::size_of::<Vec4>() as u64,
+            ),
+            (
+                gpu_voxel_material.emissives_buffer.buffer(),
+                &gpu_raw_mesh.emissives_staging_buffer,
+                &vertices,
+                U32_SIZE,
+            ),
+            (
+                gpu_voxel_material.indices_buffer.buffer(),
+                &gpu_raw_mesh.indices_staging_buffer,
+                &indices,
+                U32_SIZE,
+            ),
+        ] {
+            let size = (range.end - range.start) as u64 * stride;
+            if let Some(buffer) = buffer.filter(|_| size > 0) {
+                command_encoder.copy_buffer_to_buffer(
+                    buffer,
+                    range.start as u64 * stride,
+                    staging_buffer,
+                    0,
+                    size.min(staging_buffer.size()),
+                );
+            }
+        }
+        render_queue.submit([command_encoder.finish()]);
+
+        let copied = MeshCounts {
+            vertices: vertices.end - vertices.start,
+            indices: indices.end - indices.start,
+        };
+        let data = read_raw_mesh(render_device, gpu_raw_mesh, copied, &heads, false)?;
+        let update = match region {
+            Some((base, replaced_vertices, replaced_indices)) => {
+                RawMeshUpdate::Region(RawMeshRegion {
+                    base,
+                    replaced_vertices,
+                    replaced_indices,
+                    data,
+                })
+            }
+            None => RawMeshUpdate::Full(data),
+        };
+        Ok((counts, update))
+    }
+}
 
-            r.recv().expect("Failed to receive the map_async message");
+const U32_SIZE: u64 = std::mem::size_of::<u32>() as u64;
 
-            {
-                let buffer_view = buffer_slice.get_mapped_range();
-                let data = buffer_view
-                    .chunks(std::mem::size_of::<u32>())
-                    .map(|chunk| u32::from_ne_bytes(chunk.try_into().expect("should be a u32")))
-                    .collect::<Vec<u32>>();
-                sender
-                    .send(data)
-                    .expect("Failed to send data to main world");
+/// Reads the first `counts` vertices, attributes and indices copied into the staging buffers of
+/// `gpu_raw_mesh`, packing the indices of each material if it is `material_split`.
+fn read_raw_mesh(
+    render_device: &RenderDevice,
+    gpu_raw_mesh: &GpuRawMeshData,
+    counts: MeshCounts,
+    heads: &[u32],
+    material_split: bool,
+) -> Result<RawMeshData, BufferAsyncError> {
+    let buffers = [
+        &gpu_raw_mesh.vertices_staging_buffer,
+        &gpu_raw_mesh.uvs_staging_buffer,
+        &gpu_raw_mesh.tangents_staging_buffer,
+        &gpu_raw_mesh.emissives_staging_buffer,
+        &gpu_raw_mesh.indices_staging_buffer,
+    ];
+    map_buffers(render_device, &buffers)?;
+
+    let data = {
+        let [vertices, uvs, tangents, emissives, indices] =
+            buffers.map(|buffer| buffer.slice(..).get_mapped_range());
+        let vertex_count = counts.vertices as usize;
+
+        let (indices, material_ranges) =
+            read_indices(&indices, heads, counts.indices as usize, material_split);
+        RawMeshData {
+            vertices: read_vec3s(&vertices, vertex_count)
+                .into_iter()
+                .map(Vec3::from_array)
+                .collect(),
+            uvs: read_f32s(&uvs, vertex_count)
+                .into_iter()
+                .map(Vec2::from_array)
+                .collect(),
+            tangents: read_f32s(&tangents, vertex_count)
+                .into_iter()
+                .map(Vec4::from_array)
+                .collect(),
+            emissives: read_emissives(&emissives, vertex_count)
+                .into_iter()
+                .map(Vec3::from_array)
+                .collect(),
+            indices: indices.into(),
+            material_ranges,
+        }
+    };
+
+    for buffer in buffers {
+        buffer.unmap();
+    }
+    Ok(data)
+}
+
+/// Reads the `u32`s of a readback buffer in `range`.
+fn read_u32s(bytes: &[u8], range: Range<usize>) -> impl Iterator<Item = u32> + '_ {
+    let size = std::mem::size_of::<u32>();
+    let end = (range.end * size).min(bytes.len());
+    bytes[(range.start * size).min(end)..end]
+        .chunks_exact(size)
+        .map(|chunk| u32::from_ne_bytes(chunk.try_into().expect("should be a u32")))
+}
+
+/// Reads the `index_count` indices written by the meshing shader from a readback of its index
+/// buffer, packing the range of each material one after the other if it is `material_split`.
+fn read_indices(
+    indices: &[u8],
+    heads: &[u32],
+    index_count: usize,
+    material_split: bool,
+) -> (Vec<u32>, Option<MaterialIndexRanges>) {
+    if !material_split {
+        return (read_u32s(indices, 0..index_count).collect(), None);
+    }
+
+    let index_capacity = (indices.len() / std::mem::size_of::<u32>()) as u32;
+    let ranges = MaterialIndexRanges::from_atomics(heads, index_capacity);
+    let packed = ranges
+        .0
+        .iter()
+        .flat_map(|range| read_u32s(indices, range.start as usize..range.end as usize))
+        .collect();
+    (packed, Some(ranges.packed()))
+}
+
+/// Reads the first `count` `vecN<f32>`s of a readback buffer, for vectors whose stride is their
+/// size, e.g. the UVs and tangents.
+fn read_f32s<const N: usize>(bytes: &[u8], count: usize) -> Vec<[f32; N]> {
+    bytes
+        .chunks_exact(N * std::mem::size_of::<f32>())
+        .take(count)
+        .map(|chunk| {
+            std::array::from_fn(|component| {
+                let offset = component * std::mem::size_of::<f32>();
+                f32::from_ne_bytes(
+                    chunk[offset..offset + 4]
+                        .try_into()
+                        .expect("should be a f32"),
+                )
+            })
+        })
+        .collect()
+}
+
+/// Unpacks the `count` RGB9E5 emissive colors of a readback of an emissives buffer.
+fn read_emissives(bytes: &[u8], count: usize) -> Vec<[f32; 3]> {
+    read_u32s(bytes, 0..count)
+        .map(|emissive| unpack_rgb9e5(emissive).to_array())
+        .collect()
+}
+
+/// Reads the first three components of each `vec4<f32>` of a readback buffer.
+fn read_vec3s(bytes: &[u8], count: usize) -> Vec<[f32; 3]> {
+    bytes
+        .chunks_exact(std::mem::size_of::<Vec4>())
+        .take(count)
+        .map(|chunk| {
+            [0, 4, 8].map(|offset| {
+                f32::from_ne_bytes(
+                    chunk[offset..offset + 4]
+                        .try_into()
+                        .expect("should be a f32"),
+                )
+            })
+        })
+        .collect()
+}
+
+#[derive(Resource, Deref)]
+pub struct IsoSurfaceReceiver(pub Receiver<(ReadbackTag, usize, IsoSurfaceMeshData)>);
+
+impl IsoSurfaceReceiver {
+    /// Replaces the mesh of each [`IsoSurfaceMesh`] with the surface read back for it.
+    pub fn receive(
+        receiver: Res<Self>,
+        mut meshes: ResMut<Assets<Mesh>>,
+        children_query: Query<&Children>,
+        iso_surface_mesh_query: Query<(&IsoSurfaceMesh, &Handle<Mesh>)>,
+        version_query: Query<Option<&VoxelDataVersion>, With<Volumetric>>,
+    ) {
+        for (tag, index, data) in receiver.try_iter() {
+            if !tag.is_current(&version_query) {
+                continue;
             }
+            let Ok(children) = children_query.get(tag.entity) else {
+                continue;
+            };
+            let mesh = iso_surface_mesh_query
+                .iter_many(children)
+                .find(|(iso_surface_mesh, _)| iso_surface_mesh.index == index)
+                .and_then(|(_, mesh)| meshes.get_mut(mesh));
 
-            gpu_voxel_material.vertices_staging_buffer.unmap();
+            if let Some(mesh) = mesh {
+                *mesh = data.into_mesh();
+            }
         }
     }
 }
+
+#[derive(Resource, Deref)]
+pub struct IsoSurfaceSender(pub Sender<(ReadbackTag, usize, IsoSurfaceMeshData)>);
+
+impl IsoSurfaceSender {
+    /// Reads back the vertices, normals, UVs, tangents and indices of every [`GpuIsoSurface`](crate::data::gpu_iso_surface::GpuIsoSurface)
+    /// remeshed this frame, trimmed to the counts written by the compute shader.
+    pub fn map_and_read_buffers(
+        render_device: Res<RenderDevice>,
+        gpu_iso_surfaces: Res<VoxelMaterialComponents<GpuIsoSurfaces>>,
+        material_index_ranges: Res<VoxelMaterialComponents<MaterialIndexRanges>>,
+        mut dirty_meshes: ResMut<DirtyMeshes>,
+        version_query: Query<Option<&VoxelDataVersion>, With<Volumetric>>,
+        sender: Res<Self>,
+        failed_sender: Res<ReadbackFailedSender>,
+    ) {
+        for (entity, gpu_iso_surfaces) in &gpu_iso_surfaces.0 {
+            if !dirty_meshes.is_meshed(entity) {
+                continue;
+            }
+            let Some(tag) = ReadbackTag::current(*entity, &version_query) else {
+                continue;
+            };
+
+            for (index, gpu_iso_surface) in gpu_iso_surfaces.surfaces.iter().enumerate() {
+                if gpu_iso_surface.bind_group.is_none() {
+                    continue;
+                }
+
+                let buffers = gpu_iso_surface
+                    .readback_buffers()
+                    .map(|(_, staging_buffer)| staging_buffer);
+
+                if let Err(err) = map_buffers(&render_device, &buffers) {
+                    // Remesh the entity to retry on the next frame.
+                    dirty_meshes.mark(*entity);
+                    let _ = failed_sender.send(ReadbackFailed {
+                        entity: *entity,
+                        attempts: 1,
+                        error: err.to_string(),
+                    });
+                    continue;
+                }
+
+                {
+                    let [atomics, vertices, normals, uvs, tangents, emissives, indices] =
+                        buffers.map(|buffer| buffer.slice(..).get_mapped_range());
+
+                    let heads = read_u32s(&atomics, 0..Atomics::LEN).collect::<Vec<_>>();
+                    let vertex_count = heads.first().copied().unwrap_or(0) as usize;
+                    let index_count = heads.get(1).copied().unwrap_or(0) as usize;
+
+                    let data = IsoSurfaceMeshData {
+                        positions: read_vec3s(&vertices, vertex_count),
+                        normals: read_normals(&normals, vertex_count),
+                        uvs: read_f32s(&uvs, vertex_count),
+                        tangents: read_f32s(&tangents, vertex_count),
+                        emissives: read_emissives(&emissives, vertex_count),
+                        // Iso-surfaces are meshed into a single mesh, whatever their materials.
+                        indices: read_indices(
+                            &indices,
+                            &heads,
+                            index_count,
+                            material_index_ranges.get(entity).is_some(),
+                        )
+                        .0,
+                    };
+
+                    sender
+                        .send((tag, index, data))
+                        .expect("Failed to send iso-surface to main world");
+                }
+
+                for buffer in buffers {
+                    buffer.unmap();
+                }
+            }
+        }
+    }
+}
+
+/// A typed channel carrying the [`Pod`] payloads read back by a render world producer to the main
+/// world, e.g. the bins of a histogram or the counters of an analytics pass. The same channel is
+/// inserted into both worlds by the [`ReadbackPlugin`]: producers send from the render world, the
+/// plugin receives in the main world, keeps the latest payload in the [`ReadbackSubscription`] of
+/// the entity and sends a [`ReadbackReceived`] event per payload.
+#[derive(Resource)]
+pub struct ReadbackChannel<T> {
+    sender: Sender<(ReadbackTag, Vec<T>)>,
+    receiver: Receiver<(ReadbackTag, Vec<T>)>,
+}
+
+impl<T> Clone for ReadbackChannel<T> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            receiver: self.receiver.clone(),
+        }
+    }
+}
+
+impl<T> Default for ReadbackChannel<T> {
+    fn default() -> Self {
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        Self { sender, receiver }
+    }
+}
+
+impl<T: Pod + Send + Sync + 'static> ReadbackChannel<T> {
+    /// Sends `data` read back for the voxels identified by `tag`.
+    pub fn send(&self, tag: ReadbackTag, data: Vec<T>) {
+        let _ = self.sender.send((tag, data));
+    }
+
+    /// Maps the staging `buffer`, blocking until the GPU is done with it, and sends its contents
+    /// as `T`s. Trailing bytes not filling a whole `T` are dropped.
+    pub fn read_buffer(
+        &self,
+        render_device: &RenderDevice,
+        tag: ReadbackTag,
+        buffer: &Buffer,
+    ) -> Result<(), BufferAsyncError> {
+        map_buffers(render_device, &[buffer])?;
+        let data = {
+            let bytes = buffer.slice(..).get_mapped_range();
+            let len = bytes.len() / std::mem::size_of::<T>().max(1) * std::mem::size_of::<T>();
+            bytemuck::pod_collect_to_vec(&bytes[..len])
+        };
+        buffer.unmap();
+        self.send(tag, data);
+        Ok(())
+    }
+
+    /// Stores every payload whose voxels are still current in the [`ReadbackSubscription`] of its
+    /// entity and sends a [`ReadbackReceived`] for it.
+    pub fn receive(
+        channel: Res<Self>,
+        mut subscription_query: Query<&mut ReadbackSubscription<T>>,
+        version_query: Query<Option<&VoxelDataVersion>, With<Volumetric>>,
+        mut readback_received: EventWriter<ReadbackReceived<T>>,
+    ) {
+        for (tag, data) in channel.receiver.try_iter() {
+            if !tag.is_current(&version_query) {
+                continue;
+            }
+            if let Ok(mut subscription) = subscription_query.get_mut(tag.entity) {
+                subscription.latest = Some(data.clone());
+            }
+            readback_received.send(ReadbackReceived {
+                entity: tag.entity,
+                data,
+            });
+        }
+    }
+}
+
+/// Subscribes a volumetric entity to the payloads of `T`. Producers only read back the subscribed
+/// entities, found in the render world with `With<ReadbackSubscription<T>>`, and the latest
+/// payload received for the entity is kept in `latest`.
+#[derive(Component)]
+pub struct ReadbackSubscription<T: Send + Sync + 'static> {
+    pub latest: Option<Vec<T>>,
+}
+
+impl<T: Send + Sync + 'static> Default for ReadbackSubscription<T> {
+    fn default() -> Self {
+        Self { latest: None }
+    }
+}
+
+impl<T: Send + Sync + 'static> ExtractComponent for ReadbackSubscription<T> {
+    type QueryData = ();
+    type QueryFilter = (With<Self>, With<Volumetric>);
+    type Out = Self;
+
+    /// Only the subscription is extracted, not its payload.
+    fn extract_component(_: ()) -> Option<Self> {
+        Some(Self::default())
+    }
+}
+
+/// Sent when a payload of a [`ReadbackChannel`] is received for a volumetric entity.
+#[derive(Event, Clone, Debug)]
+pub struct ReadbackReceived<T> {
+    pub entity: Entity,
+    pub data: Vec<T>,
+}
+
+/// Creates the [`ReadbackChannel`] of `T` and receives its payloads as [`ReadbackReceived`]
+/// events. Producers are added with [`ReadbackAppExt::add_readback_producer`].
+///
+/// Must be added after the [`GpuReadbackPlugin`](crate::GpuReadbackPlugin).
+pub struct ReadbackPlugin<T>(std::marker::PhantomData<fn() -> T>);
+
+impl<T> Default for ReadbackPlugin<T> {
+    fn default() -> Self {
+        Self(std::marker::PhantomData)
+    }
+}
+
+impl<T: Pod + Send + Sync + 'static> Plugin for ReadbackPlugin<T> {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(ExtractComponentPlugin::<ReadbackSubscription<T>>::default())
+            .add_event::<ReadbackReceived<T>>()
+            .add_systems(Update, ReadbackChannel::<T>::receive);
+    }
+
+    fn finish(&self, app: &mut App) {
+        let channel = ReadbackChannel::<T>::default();
+        app.insert_resource(channel.clone());
+        app.sub_app_mut(RenderApp).insert_resource(channel);
+    }
+}
+
+pub trait ReadbackAppExt {
+    /// Runs `producer` in the render world once the frame is rendered, when the buffers it reads
+    /// back hold the results of the frame. It sends its payloads through `Res<ReadbackChannel<T>>`
+    /// of the [`ReadbackPlugin`] of `T`.
+    fn add_readback_producer<M>(&mut self, producer: impl IntoSystemConfigs<M>) -> &mut Self;
+}
+
+impl ReadbackAppExt for App {
+    fn add_readback_producer<M>(&mut self, producer: impl IntoSystemConfigs<M>) -> &mut Self {
+        self.sub_app_mut(RenderApp)
+            .add_systems(Render, producer.after(RenderSet::Render));
+        self
+    }
+}
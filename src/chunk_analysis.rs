@@ -0,0 +1,84 @@
+use bevy::{
+    prelude::*,
+    render::{
+        extract_component::ExtractComponentPlugin, render_graph::RenderGraph, Render, RenderApp,
+        RenderSet,
+    },
+};
+
+use crate::{
+    channels::{ReadbackAppExt, ReadbackChannel, ReadbackPlugin, ReadbackReceived},
+    data::{
+        chunk_analysis::{ChunkAnalysis, ChunkAnalysisData},
+        chunk_retirement::ChunkRetirementExt,
+        gpu_chunk_analysis::GpuChunkAnalysis,
+        voxel_material::VoxelMaterialComponents,
+    },
+    render::{
+        chunk_analysis_compute_pipeline::{
+            ChunkAnalysisComputeNode, ChunkAnalysisComputeNodeLabel, ChunkAnalysisComputePipeline,
+        },
+        voxel_mesh_compute_pipeline::{DirtyMeshes, VoxelMeshComputeNodeLabel},
+    },
+};
+
+/// Computes the [`ChunkAnalysis`] of volumetric entities on the GPU whenever they are remeshed,
+/// reading it back through a [`ReadbackChannel`] of [`ChunkAnalysisData`].
+///
+/// Must be added after the [`GpuReadbackPlugin`](crate::GpuReadbackPlugin).
+pub struct ChunkAnalysisPlugin;
+
+impl ChunkAnalysisPlugin {
+    /// Updates the [`ChunkAnalysis`] of the entities whose counters were read back.
+    pub fn receive(
+        mut readback_received: EventReader<ReadbackReceived<ChunkAnalysisData>>,
+        mut analysis_query: Query<&mut ChunkAnalysis>,
+    ) {
+        for received in readback_received.read() {
+            let (Some(data), Ok(mut chunk_analysis)) = (
+                received.data.first(),
+                analysis_query.get_mut(received.entity),
+            ) else {
+                continue;
+            };
+            chunk_analysis.apply(data);
+        }
+    }
+}
+
+impl Plugin for ChunkAnalysisPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            ExtractComponentPlugin::<ChunkAnalysis>::default(),
+            ReadbackPlugin::<ChunkAnalysisData>::default(),
+        ))
+        .add_readback_producer(GpuChunkAnalysis::map_and_read_buffers)
+        .add_systems(
+            Update,
+            Self::receive.after(ReadbackChannel::<ChunkAnalysisData>::receive),
+        );
+    }
+
+    fn finish(&self, app: &mut App) {
+        let render_app = app.sub_app_mut(RenderApp);
+
+        render_app
+            .init_resource::<ChunkAnalysisComputePipeline>()
+            .init_resource::<VoxelMaterialComponents<GpuChunkAnalysis>>()
+            .free_on_retire::<GpuChunkAnalysis>()
+            .add_systems(ExtractSchedule, GpuChunkAnalysis::extract)
+            .add_systems(
+                Render,
+                GpuChunkAnalysis::prepare
+                    .in_set(RenderSet::PrepareBindGroups)
+                    .after(DirtyMeshes::select),
+            );
+
+        let analysis_compute_node = ChunkAnalysisComputeNode::from_world(render_app.world_mut());
+
+        let mut render_graph = render_app.world_mut().resource_mut::<RenderGraph>();
+
+        render_graph.add_node(ChunkAnalysisComputeNodeLabel, analysis_compute_node);
+        render_graph.add_node_edge(VoxelMeshComputeNodeLabel, ChunkAnalysisComputeNodeLabel);
+    }
+}
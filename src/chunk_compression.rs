@@ -0,0 +1,125 @@
+use std::{
+    borrow::Cow,
+    io::{self, Read, Write},
+    num::NonZeroUsize,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
+use serde::{Deserialize, Serialize};
+
+use crate::invalid_data;
+
+/// The codec compressing the voxels of the chunks of a
+/// [`WorldSnapshot`](crate::snapshot::WorldSnapshot) in its archive.
+///
+/// Only deflate is offered, its levels trading speed for size. LZ4 and zstd are not: the crate
+/// doesn't depend on an LZ4 or zstd encoder. Codecs may be added as variants without breaking
+/// archives, which record the codec of their chunks.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum ChunkCodec {
+    /// Stores the voxels uncompressed, as the snapshots saved before chunks were compressed.
+    #[default]
+    None,
+    /// Deflate, at a `level` from 0 for the fastest saves to 9 for the smallest archives.
+    Deflate { level: u32 },
+}
+
+impl ChunkCodec {
+    /// Deflate at the level trading the least size for speed.
+    pub const FAST: Self = Self::Deflate { level: 1 };
+    /// Deflate at the level trading the most speed for size.
+    pub const SMALLEST: Self = Self::Deflate { level: 9 };
+
+    /// Compresses the encoded voxels of a chunk.
+    pub fn compress(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+        match *self {
+            ChunkCodec::None => Ok(data.to_vec()),
+            ChunkCodec::Deflate { level } => {
+                let mut encoder = DeflateEncoder::new(
+                    Vec::with_capacity(data.len() / 2),
+                    Compression::new(level.min(9)),
+                );
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+        }
+    }
+
+    /// Decompresses the encoded voxels of a chunk written by [`ChunkCodec::compress`], borrowing
+    /// them if they are uncompressed.
+    pub fn decompress<'a>(&self, data: &'a [u8]) -> io::Result<Cow<'a, [u8]>> {
+        match self {
+            ChunkCodec::None => Ok(Cow::Borrowed(data)),
+            ChunkCodec::Deflate { .. } => {
+                let mut voxels = Vec::with_capacity(data.len() * 2);
+                DeflateDecoder::new(data)
+                    .read_to_end(&mut voxels)
+                    .map_err(invalid_data)?;
+                Ok(Cow::Owned(voxels))
+            }
+        }
+    }
+}
+
+/// How the chunks of a [`WorldSnapshot`](crate::snapshot::WorldSnapshot) are compressed when it
+/// is saved.
+#[derive(Clone, Copy, Debug)]
+pub struct CompressionOptions {
+    pub codec: ChunkCodec,
+    /// Threads encoding and compressing chunks while the archive is written.
+    pub workers: usize,
+    /// Compressed chunks waiting to be written before the workers block, bounding the memory of a
+    /// save to about `workers + max_in_flight` chunks however large the world.
+    pub max_in_flight: usize,
+}
+
+impl Default for CompressionOptions {
+    fn default() -> Self {
+        let workers = std::thread::available_parallelism().map_or(1, NonZeroUsize::get);
+        Self {
+            codec: ChunkCodec::FAST,
+            workers,
+            max_in_flight: workers * 2,
+        }
+    }
+}
+
+/// Encodes and compresses `count` chunks on the worker threads of `options`, streaming each
+/// compressed chunk to `write` on the calling thread in the order they complete. Stops at the
+/// first error of `encode`, the codec or `write`.
+pub fn compress_chunks(
+    count: usize,
+    options: &CompressionOptions,
+    encode: impl Fn(usize) -> Vec<u8> + Sync,
+    mut write: impl FnMut(usize, Vec<u8>) -> io::Result<()>,
+) -> io::Result<()> {
+    let (sender, receiver) = crossbeam_channel::bounded(options.max_in_flight.max(1));
+    let next = AtomicUsize::new(0);
+
+    std::thread::scope(|scope| {
+        for _ in 0..options.workers.clamp(1, count.max(1)) {
+            let sender = sender.clone();
+            let (next, encode) = (&next, &encode);
+            scope.spawn(move || loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                if index >= count {
+                    break;
+                }
+                let compressed = options.codec.compress(&encode(index));
+                // The writer hung up after an error.
+                if sender.send((index, compressed)).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(sender);
+
+        // Dropping the receiver on an error stops the workers at their next chunk.
+        for (index, compressed) in receiver {
+            write(index, compressed?)?;
+        }
+        Ok(())
+    })
+}
@@ -0,0 +1,73 @@
+use bevy::{
+    core_pipeline::core_3d::graph::{Core3d, Node3d},
+    prelude::*,
+    render::{
+        extract_component::ExtractComponentPlugin,
+        render_graph::{RenderGraphApp, ViewNodeRunner},
+        Render, RenderApp, RenderSet,
+    },
+};
+
+use crate::{
+    data::{
+        chunk_culling::ChunkCulling,
+        chunk_retirement::ChunkRetirementExt,
+        gpu_chunk_culling::{CulledChunkTransform, GpuChunkCulling},
+        gpu_cluster_bounds::GpuClusterBounds,
+        gpu_depth_pyramid::GpuDepthPyramid,
+        voxel_material::VoxelMaterialComponents,
+    },
+    render::{
+        chunk_culling_compute_pipeline::{
+            ChunkCullingComputePipeline, ChunkCullingNode, ChunkCullingNodeLabel,
+        },
+        depth_pyramid_compute_pipeline::DepthPyramidNodeLabel,
+    },
+};
+
+/// Culls the chunks for every [`ChunkCulling`] camera once its depth pyramid is built, into the
+/// render world's [`VoxelMaterialComponents<GpuChunkCulling>`] by view entity. The results are
+/// statistics only, chunks are still drawn as regular meshes.
+///
+/// Needs the [`DepthPyramidPlugin`](crate::depth_pyramid::DepthPyramidPlugin), added before it.
+/// Chunks are culled by cluster with the
+/// [`ClusterBoundsPlugin`](crate::cluster_bounds::ClusterBoundsPlugin).
+pub struct ChunkCullingPlugin;
+
+impl Plugin for ChunkCullingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(ExtractComponentPlugin::<ChunkCulling>::default())
+            .add_systems(PostUpdate, ChunkCulling::add_depth_pyramids);
+    }
+
+    fn finish(&self, app: &mut App) {
+        let render_app = app.sub_app_mut(RenderApp);
+
+        render_app
+            .init_resource::<ChunkCullingComputePipeline>()
+            .init_resource::<VoxelMaterialComponents<GpuChunkCulling>>()
+            .init_resource::<VoxelMaterialComponents<CulledChunkTransform>>()
+            .init_resource::<VoxelMaterialComponents<GpuClusterBounds>>()
+            .free_on_retire::<CulledChunkTransform>()
+            .add_systems(ExtractSchedule, GpuChunkCulling::extract)
+            .add_systems(
+                Render,
+                GpuChunkCulling::prepare
+                    .in_set(RenderSet::PrepareBindGroups)
+                    .after(GpuDepthPyramid::prepare)
+                    .after(GpuClusterBounds::prepare),
+            )
+            .add_render_graph_node::<ViewNodeRunner<ChunkCullingNode>>(
+                Core3d,
+                ChunkCullingNodeLabel,
+            )
+            .add_render_graph_edges(
+                Core3d,
+                (
+                    DepthPyramidNodeLabel,
+                    ChunkCullingNodeLabel,
+                    Node3d::EndMainPassPostProcessing,
+                ),
+            );
+    }
+}
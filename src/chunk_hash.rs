@@ -0,0 +1,106 @@
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+
+use crate::{
+    bundles::volumetric_bundle::Volumetric,
+    data::{chunk_coord::ChunkCoord, voxel_material::VoxelMaterial},
+};
+
+pub(crate) const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+pub(crate) fn fnv1a(hash: u64, bytes: impl IntoIterator<Item = u8>) -> u64 {
+    bytes.into_iter().fold(hash, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+    })
+}
+
+/// A hash of the voxels of a chunk that is the same on every platform and build, so that peers or
+/// replays can compare their worlds by exchanging a few bytes per chunk. Densities that compare
+/// equal hash the same, i.e. `-0.0` and `0.0`, and all NaNs. Emissive colors are only hashed when
+/// set, so that unlit chunks hash as they did before voxels had them.
+pub fn chunk_hash(voxel_material: &VoxelMaterial) -> u64 {
+    let hash = fnv1a(FNV_OFFSET_BASIS, voxel_material.chunk_size.to_le_bytes());
+    voxel_material.voxels().iter().fold(hash, |hash, voxel| {
+        let density = voxel.density();
+        let density = if density.is_nan() {
+            f32::NAN
+        } else if density == 0.0 {
+            0.0
+        } else {
+            density
+        };
+        let hash = fnv1a(hash, voxel.flags().to_le_bytes());
+        let hash = fnv1a(hash, density.to_bits().to_le_bytes());
+        match voxel.emissive_bits() {
+            0 => hash,
+            emissive => fnv1a(hash, emissive.to_le_bytes()),
+        }
+    })
+}
+
+/// Sent whenever the hash of a chunk changes.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct ChunkHashed {
+    pub entity: Entity,
+    pub coord: ChunkCoord,
+    pub hash: u64,
+}
+
+/// The [`chunk_hash`] of every volumetric chunk with a [`ChunkCoord`].
+#[derive(Resource, Default, Debug)]
+pub struct ChunkHashes(pub HashMap<ChunkCoord, u64>);
+
+impl ChunkHashes {
+    pub fn get(&self, coord: ChunkCoord) -> Option<u64> {
+        self.0.get(&coord).copied()
+    }
+
+    /// Rehashes the chunks whose voxels changed, sending a [`ChunkHashed`] for each one, and forgets
+    /// the coordinates no chunk is at anymore.
+    #[allow(clippy::type_complexity)]
+    pub fn update(
+        mut hashes: ResMut<Self>,
+        chunk_query: Query<
+            (Entity, &ChunkCoord, &VoxelMaterial),
+            (
+                With<Volumetric>,
+                Or<(Changed<VoxelMaterial>, Changed<ChunkCoord>)>,
+            ),
+        >,
+        coord_query: Query<&ChunkCoord, With<Volumetric>>,
+        mut removed: RemovedComponents<ChunkCoord>,
+        mut hashed: EventWriter<ChunkHashed>,
+    ) {
+        if removed.read().count() > 0 || !chunk_query.is_empty() {
+            let coords = coord_query.iter().collect::<HashSet<_>>();
+            hashes.0.retain(|coord, _| coords.contains(coord));
+        }
+
+        for (entity, coord, voxel_material) in chunk_query.iter() {
+            let hash = chunk_hash(voxel_material);
+            if hashes.0.insert(*coord, hash) == Some(hash) {
+                continue;
+            }
+            hashed.send(ChunkHashed {
+                entity,
+                coord: *coord,
+                hash,
+            });
+        }
+    }
+}
+
+/// Publishes the [`ChunkHashes`] of the world and a [`ChunkHashed`] event whenever one changes,
+/// e.g. to send them to the other peers of a networked session.
+pub struct ChunkHashPlugin;
+
+impl Plugin for ChunkHashPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ChunkHashes>()
+            .add_event::<ChunkHashed>()
+            .add_systems(Last, ChunkHashes::update);
+    }
+}
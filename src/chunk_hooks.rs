@@ -0,0 +1,151 @@
+use bevy::{
+    prelude::*,
+    render::{Render, RenderApp, RenderSet},
+};
+use crossbeam_channel::{Receiver, Sender};
+
+use crate::{
+    bundles::volumetric_bundle::Volumetric, data::chunk_coord::ChunkCoord,
+    render::voxel_mesh_compute_pipeline::DirtyMeshes,
+};
+
+#[cfg(feature = "editing")]
+use crate::data::voxel_edit::{ChunkEdit, ChunkEdited};
+
+/// Triggered on a chunk once it is spawned with its voxels, by streaming, a
+/// [`WorldGenJob`](crate::world_gen::WorldGenJob) or any other system.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct ChunkGenerated {
+    pub entity: Entity,
+    pub coord: ChunkCoord,
+}
+
+/// Triggered on a chunk in the frame its mesh is regenerated on the GPU, after it was spawned or
+/// edited.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct ChunkMeshReady {
+    pub entity: Entity,
+    pub coord: ChunkCoord,
+}
+
+/// Triggered globally when a chunk is despawned or loses its [`Volumetric`] component. The entity
+/// is already gone when the observers run, so observers of the entity itself never see it.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct ChunkUnloaded {
+    pub entity: Entity,
+    pub coord: ChunkCoord,
+}
+
+/// Triggers observer events at the lifecycle points of the chunks, the entities with both a
+/// [`Volumetric`] and a [`ChunkCoord`] component, so that gameplay systems react to them instead
+/// of polling: [`ChunkGenerated`], [`ChunkEdited`], [`ChunkMeshReady`] and [`ChunkUnloaded`].
+///
+/// Observers are registered globally with [`App::observe`], or on a single chunk with
+/// [`EntityCommands::observe`]:
+///
+/// ```ignore
+/// app.observe(|trigger: Trigger<ChunkGenerated>, mut commands: Commands| {
+///     commands.spawn(Ore::at(trigger.event().coord));
+/// });
+/// ```
+///
+/// Must be added after the [`GpuReadbackPlugin`](crate::GpuReadbackPlugin).
+pub struct ChunkHooksPlugin;
+
+impl ChunkHooksPlugin {
+    /// Triggers [`ChunkGenerated`] on the chunks spawned since the last run.
+    pub fn trigger_generated(
+        mut commands: Commands,
+        chunk_query: Query<(Entity, &ChunkCoord), Added<Volumetric>>,
+    ) {
+        for (entity, coord) in chunk_query.iter() {
+            commands.trigger_targets(
+                ChunkGenerated {
+                    entity,
+                    coord: *coord,
+                },
+                entity,
+            );
+        }
+    }
+
+    /// Triggers the [`ChunkEdited`] events sent by [`ChunkEdit::apply`] on their chunk.
+    #[cfg(feature = "editing")]
+    pub fn trigger_edited(mut commands: Commands, mut chunk_edited: EventReader<ChunkEdited>) {
+        for edited in chunk_edited.read() {
+            commands.trigger_targets(*edited, edited.entity);
+        }
+    }
+
+    /// Triggers [`ChunkUnloaded`] for a chunk losing its [`Volumetric`] component.
+    pub fn trigger_unloaded(
+        trigger: Trigger<OnRemove, Volumetric>,
+        mut commands: Commands,
+        coord_query: Query<&ChunkCoord>,
+    ) {
+        let entity = trigger.entity();
+        if let Ok(coord) = coord_query.get(entity) {
+            commands.trigger(ChunkUnloaded {
+                entity,
+                coord: *coord,
+            });
+        }
+    }
+}
+
+impl Plugin for ChunkHooksPlugin {
+    fn build(&self, app: &mut App) {
+        app.observe(Self::trigger_unloaded)
+            .add_systems(PostUpdate, Self::trigger_generated)
+            .add_systems(Update, MeshReadyReceiver::receive);
+
+        #[cfg(feature = "editing")]
+        app.add_systems(PostUpdate, Self::trigger_edited.after(ChunkEdit::apply));
+    }
+
+    fn finish(&self, app: &mut App) {
+        let (s, r) = crossbeam_channel::unbounded();
+        app.insert_resource(MeshReadyReceiver(r));
+
+        app.sub_app_mut(RenderApp)
+            .insert_resource(MeshReadySender(s))
+            .add_systems(Render, MeshReadySender::send.after(RenderSet::Render));
+    }
+}
+
+#[derive(Resource, Deref)]
+pub struct MeshReadyReceiver(pub Receiver<Vec<Entity>>);
+
+impl MeshReadyReceiver {
+    /// Triggers [`ChunkMeshReady`] on the chunks remeshed in the render world that still exist.
+    pub fn receive(
+        mut commands: Commands,
+        receiver: Res<Self>,
+        chunk_query: Query<&ChunkCoord, With<Volumetric>>,
+    ) {
+        for entity in receiver.try_iter().flatten() {
+            if let Ok(coord) = chunk_query.get(entity) {
+                commands.trigger_targets(
+                    ChunkMeshReady {
+                        entity,
+                        coord: *coord,
+                    },
+                    entity,
+                );
+            }
+        }
+    }
+}
+
+#[derive(Resource, Deref)]
+pub struct MeshReadySender(pub Sender<Vec<Entity>>);
+
+impl MeshReadySender {
+    /// Sends the entities remeshed this frame to the main world.
+    pub fn send(sender: Res<Self>, dirty_meshes: Res<DirtyMeshes>) {
+        let meshed = dirty_meshes.meshed().copied().collect::<Vec<_>>();
+        if !meshed.is_empty() {
+            let _ = sender.send(meshed);
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use std::{borrow::Cow, collections::BTreeMap, io};
+
+use bevy::prelude::*;
+
+use crate::invalid_data;
+
+/// Version of the schema of the voxels of persisted chunks, as encoded by
+/// [`encode_voxels`](crate::core::serialization::encode_voxels). Bump it whenever the encoding of
+/// a [`Voxel`](crate::data::voxel::Voxel) changes, and register a [`ChunkMigrations`] migration
+/// from the previous version so that the worlds saved with it still load.
+pub const CHUNK_SCHEMA_VERSION: u32 = 2;
+
+/// Bytes of a voxel encoded with the first schema version, its flags then its density.
+const VOXEL_BYTES_V1: usize = 8;
+
+/// Rewrites the encoded voxels of a chunk from one schema version to a later one.
+pub type ChunkMigration = Box<dyn Fn(&[u8]) -> io::Result<Vec<u8>> + Send + Sync>;
+
+/// The migrations applied to the voxels of the chunks of a
+/// [`WorldSnapshot`](crate::snapshot::WorldSnapshot) saved with an older
+/// [`CHUNK_SCHEMA_VERSION`] when it is loaded, chained from its version up to the current one.
+/// The migrations between the schema versions of this crate are registered by default.
+#[derive(Resource)]
+pub struct ChunkMigrations {
+    /// The version each migration migrates to and the migration, by the version it migrates from.
+    migrations: BTreeMap<u32, (u32, ChunkMigration)>,
+}
+
+impl Default for ChunkMigrations {
+    fn default() -> Self {
+        let mut migrations = Self {
+            migrations: BTreeMap::new(),
+        };
+        migrations.register_migration(1, 2, add_emissive);
+        migrations
+    }
+}
+
+/// Migrates voxels from the first schema version by appending an unlit emissive color to each.
+fn add_emissive(data: &[u8]) -> io::Result<Vec<u8>> {
+    if !data.len().is_multiple_of(VOXEL_BYTES_V1) {
+        return Err(invalid_data(format!(
+            "{} bytes of voxels are not a whole number of {VOXEL_BYTES_V1} byte voxels",
+            data.len()
+        )));
+    }
+    Ok(data
+        .chunks_exact(VOXEL_BYTES_V1)
+        .flat_map(|voxel| voxel.iter().copied().chain(0u32.to_le_bytes()))
+        .collect())
+}
+
+impl ChunkMigrations {
+    /// Registers the migration of chunks from the schema version `from` to the later version `to`,
+    /// replacing any migration already registered from `from`.
+    pub fn register_migration(
+        &mut self,
+        from: u32,
+        to: u32,
+        migration: impl Fn(&[u8]) -> io::Result<Vec<u8>> + Send + Sync + 'static,
+    ) -> &mut Self {
+        assert!(
+            from < to,
+            "chunk migrations must migrate to a later version, not from {from} to {to}"
+        );
+        self.migrations.insert(from, (to, Box::new(migration)));
+        self
+    }
+
+    /// Migrates the encoded voxels of a chunk saved with the schema `version` to the
+    /// [`CHUNK_SCHEMA_VERSION`], borrowing them if they are already up to date.
+    pub fn migrate<'a>(&self, data: &'a [u8], version: u32) -> io::Result<Cow<'a, [u8]>> {
+        let mut data = Cow::Borrowed(data);
+        let mut version = version;
+        while version < CHUNK_SCHEMA_VERSION {
+            let Some((to, migration)) = self.migrations.get(&version) else {
+                return Err(invalid_data(format!(
+                    "no migration of chunks from schema version {version} to \
+                     {CHUNK_SCHEMA_VERSION}"
+                )));
+            };
+            data = Cow::Owned(migration(&data)?);
+            version = *to;
+        }
+
+        if version > CHUNK_SCHEMA_VERSION {
+            return Err(invalid_data(format!(
+                "unsupported chunk schema version {version}, newer than {CHUNK_SCHEMA_VERSION}"
+            )));
+        }
+        Ok(data)
+    }
+}
+
+pub trait ChunkMigrationAppExt {
+    /// Registers a migration of the [`ChunkMigrations`] of the app, see
+    /// [`ChunkMigrations::register_migration`].
+    fn register_chunk_migration(
+        &mut self,
+        from: u32,
+        to: u32,
+        migration: impl Fn(&[u8]) -> io::Result<Vec<u8>> + Send + Sync + 'static,
+    ) -> &mut Self;
+}
+
+impl ChunkMigrationAppExt for App {
+    fn register_chunk_migration(
+        &mut self,
+        from: u32,
+        to: u32,
+        migration: impl Fn(&[u8]) -> io::Result<Vec<u8>> + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.world_mut()
+            .get_resource_or_insert_with(ChunkMigrations::default)
+            .register_migration(from, to, migration);
+        self
+    }
+}
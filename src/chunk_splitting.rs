@@ -0,0 +1,390 @@
+use std::time::Duration;
+
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{
+    bundles::volumetric_bundle::VolumetricBundle,
+    data::{
+        boundary_mode::BoundaryMode,
+        chunk_coord::ChunkCoord,
+        meshing_algorithm::MeshingAlgorithm,
+        voxel::Voxel,
+        voxel_edit::{ChunkEdit, ChunkEdited},
+        voxel_material::VoxelMaterial,
+        voxel_world::{voxel_index, VoxelWorldConfig},
+    },
+    CHUNK_SZ, CHUNK_SZ_3,
+};
+
+/// Voxels along each axis of a logical chunk, split into 2³ chunks of [`CHUNK_SZ`] voxels.
+pub const LOGICAL_CHUNK_SZ: usize = CHUNK_SZ * 2;
+
+/// Index of the voxel at `pos` in the voxels of a logical chunk.
+fn logical_index(pos: UVec3) -> usize {
+    pos.x as usize
+        + pos.y as usize * LOGICAL_CHUNK_SZ
+        + pos.z as usize * LOGICAL_CHUNK_SZ * LOGICAL_CHUNK_SZ
+}
+
+/// The offsets of the sub-chunks of a logical chunk, in chunks.
+fn sub_chunk_offsets() -> impl Iterator<Item = UVec3> {
+    (0..8).map(|index| UVec3::new(index & 1, (index >> 1) & 1, index >> 2))
+}
+
+/// Splits the logical chunks of 64³ voxels edited often into their 2³ sub-chunks, so that an edit
+/// only remeshes the 32³ voxels around it, and merges idle sub-chunks back into a single chunk
+/// meshed at half resolution, so that the untouched parts of the world hold one GPU volume and
+/// one mesh per logical chunk.
+///
+/// Chunks opt in with a [`SubChunk`] or as a [`MergedChunk`]. The [`VoxelWorld`](crate::data::voxel_world::VoxelWorld)
+/// only sees split logical chunks, as merged chunks have no [`ChunkCoord`].
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct ChunkSplitting {
+    /// Edits of a merged chunk within `edit_window` after which it is split.
+    pub split_edits: usize,
+    pub edit_window: Duration,
+    /// How long every sub-chunk of a logical chunk must go unedited before they are merged.
+    pub merge_after: Duration,
+}
+
+impl Default for ChunkSplitting {
+    fn default() -> Self {
+        Self {
+            split_edits: 8,
+            edit_window: Duration::from_secs(2),
+            merge_after: Duration::from_secs(30),
+        }
+    }
+}
+
+/// A logical chunk meshed as a single chunk at half resolution. The full resolution voxels are
+/// kept to split it again, with the voxels changed by edits of the merged chunk resampled from it.
+#[derive(Component)]
+pub struct MergedChunk {
+    /// Position of the logical chunk, in logical chunks.
+    pub coord: IVec3,
+    voxels: Vec<Voxel>,
+    recent_edits: Vec<Duration>,
+}
+
+impl MergedChunk {
+    /// A logical chunk from its [`LOGICAL_CHUNK_SZ`]³ voxels.
+    pub fn new(coord: IVec3, voxels: Vec<Voxel>) -> Self {
+        assert_eq!(
+            voxels.len(),
+            LOGICAL_CHUNK_SZ.pow(3),
+            "a logical chunk should have 64³ voxels"
+        );
+        Self {
+            coord,
+            voxels,
+            recent_edits: Vec::new(),
+        }
+    }
+
+    /// The full resolution voxels of the logical chunk.
+    pub fn voxels(&self) -> &[Voxel] {
+        &self.voxels
+    }
+
+    /// The voxels of the half resolution chunk, averaging the densities of each 2³ block and
+    /// keeping the flags of its densest voxel.
+    pub fn downsample(&self) -> Vec<Voxel> {
+        let mut voxels = vec![Voxel::default(); CHUNK_SZ_3];
+        for z in 0..CHUNK_SZ as u32 {
+            for y in 0..CHUNK_SZ as u32 {
+                for x in 0..CHUNK_SZ as u32 {
+                    let pos = UVec3::new(x, y, z);
+                    voxels[voxel_index(pos)] = self.block(pos);
+                }
+            }
+        }
+        voxels
+    }
+
+    /// The half resolution voxel at `pos` of the 2³ full resolution voxels it covers.
+    fn block(&self, pos: UVec3) -> Voxel {
+        let mut density = 0.0;
+        let mut densest = self.voxels[logical_index(pos * 2)];
+        for offset in sub_chunk_offsets() {
+            let voxel = self.voxels[logical_index(pos * 2 + offset)];
+            density += voxel.density();
+            if voxel.density() > densest.density() {
+                densest = voxel;
+            }
+        }
+        densest.with_density(density / 8.0)
+    }
+
+    /// Copies the half resolution voxels changed from `min` up to, but excluding, `max` into the
+    /// 2³ full resolution voxels they cover.
+    fn resample(&mut self, voxel_material: &VoxelMaterial, min: UVec3, max: UVec3) {
+        for z in min.z..max.z {
+            for y in min.y..max.y {
+                for x in min.x..max.x {
+                    let pos = UVec3::new(x, y, z);
+                    let Some(voxel) = voxel_material.voxels().get(voxel_index(pos)) else {
+                        continue;
+                    };
+                    let block = self.block(pos);
+                    if voxel.flags() == block.flags() && voxel.density() == block.density() {
+                        continue;
+                    }
+                    for offset in sub_chunk_offsets() {
+                        self.voxels[logical_index(pos * 2 + offset)] = *voxel;
+                    }
+                }
+            }
+        }
+    }
+
+    /// The chunk at `offset` in the logical chunk, at full resolution.
+    fn sub_chunk(&self, offset: UVec3) -> Vec<Voxel> {
+        let mut voxels = vec![Voxel::default(); CHUNK_SZ_3];
+        let origin = offset * CHUNK_SZ as u32;
+        for z in 0..CHUNK_SZ as u32 {
+            for y in 0..CHUNK_SZ as u32 {
+                for x in 0..CHUNK_SZ as u32 {
+                    let pos = UVec3::new(x, y, z);
+                    voxels[voxel_index(pos)] = self.voxels[logical_index(origin + pos)];
+                }
+            }
+        }
+        voxels
+    }
+
+    /// The transform placing the merged chunk of the logical chunk at `coord`, whose meshes are in
+    /// half resolution voxel coordinates.
+    pub fn transform(config: &VoxelWorldConfig, coord: IVec3) -> Transform {
+        config
+            .chunk_transform(ChunkCoord(coord * 2))
+            .with_scale(Vec3::splat(config.voxel_size * 2.0))
+    }
+
+    /// Records the edits of merged chunks and of sub-chunks, resampling the voxels changed by the
+    /// edits of merged chunks into their full resolution voxels.
+    pub fn track_edits(
+        time: Res<Time>,
+        mut edited: EventReader<ChunkEdited>,
+        mut merged_query: Query<(&mut MergedChunk, &VoxelMaterial)>,
+        mut sub_chunk_query: Query<&mut SubChunk>,
+    ) {
+        let now = time.elapsed();
+        for ChunkEdited { entity, edit, .. } in edited.read() {
+            if let Ok((mut merged_chunk, voxel_material)) = merged_query.get_mut(*entity) {
+                let (min, max) = edit.bounds();
+                merged_chunk.resample(voxel_material, min, max);
+                merged_chunk.recent_edits.push(now);
+            } else if let Ok(mut sub_chunk) = sub_chunk_query.get_mut(*entity) {
+                sub_chunk.last_edit = Some(now);
+            }
+        }
+    }
+}
+
+/// A chunk meshing an eighth of the logical chunk containing its [`ChunkCoord`] at full
+/// resolution, merged with the other seven once they all go unedited for a while.
+#[derive(Clone, Copy, Component, Debug, Default)]
+pub struct SubChunk {
+    last_edit: Option<Duration>,
+}
+
+/// Requests splitting the [`MergedChunk`] `entity` into its sub-chunks.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct SplitChunk {
+    pub entity: Entity,
+}
+
+/// Requests merging the sub-chunks of the logical chunk at `coord`, once all of them are spawned.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct MergeChunks {
+    pub coord: IVec3,
+}
+
+/// Sent when the [`MergedChunk`] `merged` is split into `sub_chunks`, which replace it.
+#[derive(Event, Clone, Debug)]
+pub struct ChunkSplit {
+    pub coord: IVec3,
+    pub merged: Entity,
+    /// The sub-chunks spawned, in the order of their offsets along x, then y, then z.
+    pub sub_chunks: Vec<Entity>,
+}
+
+/// Sent when the `sub_chunks` of a logical chunk are merged into `merged`, which replaces them.
+#[derive(Event, Clone, Debug)]
+pub struct ChunksMerged {
+    pub coord: IVec3,
+    pub merged: Entity,
+    pub sub_chunks: Vec<Entity>,
+}
+
+impl ChunkSplitting {
+    /// Splits the merged chunks edited often or requested to split, and merges the sub-chunks of
+    /// the logical chunks left idle or requested to merge. The chunks replaced are despawned and
+    /// their replacements keep their [`MeshingAlgorithm`] and [`BoundaryMode`].
+    #[allow(clippy::too_many_arguments, clippy::type_complexity)]
+    pub fn update(
+        mut commands: Commands,
+        settings: Res<Self>,
+        config: Res<VoxelWorldConfig>,
+        time: Res<Time>,
+        mut split_requests: EventReader<SplitChunk>,
+        mut merge_requests: EventReader<MergeChunks>,
+        mut chunk_split: EventWriter<ChunkSplit>,
+        mut chunks_merged: EventWriter<ChunksMerged>,
+        mut merged_query: Query<(
+            Entity,
+            &mut MergedChunk,
+            Option<&MeshingAlgorithm>,
+            Option<&BoundaryMode>,
+        )>,
+        mut sub_chunk_query: Query<(
+            Entity,
+            &ChunkCoord,
+            &VoxelMaterial,
+            &mut SubChunk,
+            Option<&MeshingAlgorithm>,
+            Option<&BoundaryMode>,
+        )>,
+    ) {
+        let now = time.elapsed();
+        let split_requests = split_requests
+            .read()
+            .map(|request| request.entity)
+            .collect::<Vec<_>>();
+        let merge_requests = merge_requests
+            .read()
+            .map(|request| request.coord)
+            .collect::<Vec<_>>();
+
+        for (entity, mut merged_chunk, meshing_algorithm, boundary_mode) in merged_query.iter_mut()
+        {
+            merged_chunk
+                .recent_edits
+                .retain(|edited_at| now.saturating_sub(*edited_at) < settings.edit_window);
+            if merged_chunk.recent_edits.len() < settings.split_edits.max(1)
+                && !split_requests.contains(&entity)
+            {
+                continue;
+            }
+
+            let sub_chunks = sub_chunk_offsets()
+                .map(|offset| {
+                    let coord = ChunkCoord(merged_chunk.coord * 2 + offset.as_ivec3());
+                    let voxel_material =
+                        VoxelMaterial::new(merged_chunk.sub_chunk(offset), CHUNK_SZ_3 as u32);
+                    commands
+                        .spawn((
+                            VolumetricBundle::new(voxel_material)
+                                .with_meshing_algorithm(
+                                    meshing_algorithm.copied().unwrap_or_default(),
+                                )
+                                .with_boundary_mode(boundary_mode.copied().unwrap_or_default()),
+                            coord,
+                            config.chunk_transform(coord),
+                            SubChunk {
+                                last_edit: Some(now),
+                            },
+                        ))
+                        .id()
+                })
+                .collect();
+
+            commands.entity(entity).despawn_recursive();
+            chunk_split.send(ChunkSplit {
+                coord: merged_chunk.coord,
+                merged: entity,
+                sub_chunks,
+            });
+        }
+
+        let mut logical_chunks = HashMap::<IVec3, Vec<Entity>>::default();
+        for (entity, coord, _, mut sub_chunk, ..) in sub_chunk_query.iter_mut() {
+            if sub_chunk.is_added() && sub_chunk.last_edit.is_none() {
+                sub_chunk.last_edit = Some(now);
+            }
+            logical_chunks
+                .entry(coord.0.div_euclid(IVec3::splat(2)))
+                .or_default()
+                .push(entity);
+        }
+
+        for (logical_coord, entities) in logical_chunks {
+            if entities.len() != 8 {
+                continue;
+            }
+            let idle = entities.iter().all(|entity| {
+                sub_chunk_query
+                    .get(*entity)
+                    .ok()
+                    .and_then(|(.., sub_chunk, _, _)| sub_chunk.last_edit)
+                    .is_none_or(|edited_at| now.saturating_sub(edited_at) >= settings.merge_after)
+            });
+            if !idle && !merge_requests.contains(&logical_coord) {
+                continue;
+            }
+
+            let mut voxels = vec![Voxel::default(); LOGICAL_CHUNK_SZ.pow(3)];
+            let mut meshing = None;
+            for entity in &entities {
+                let Ok((_, coord, voxel_material, _, meshing_algorithm, boundary_mode)) =
+                    sub_chunk_query.get(*entity)
+                else {
+                    continue;
+                };
+                meshing.get_or_insert((meshing_algorithm.copied(), boundary_mode.copied()));
+                let origin = (coord.0 - logical_coord * 2).as_uvec3() * CHUNK_SZ as u32;
+                for z in 0..CHUNK_SZ as u32 {
+                    for y in 0..CHUNK_SZ as u32 {
+                        for x in 0..CHUNK_SZ as u32 {
+                            let pos = UVec3::new(x, y, z);
+                            if let Some(voxel) = voxel_material.voxels().get(voxel_index(pos)) {
+                                voxels[logical_index(origin + pos)] = *voxel;
+                            }
+                        }
+                    }
+                }
+                commands.entity(*entity).despawn_recursive();
+            }
+
+            let (meshing_algorithm, boundary_mode) = meshing.unwrap_or_default();
+            let merged_chunk = MergedChunk::new(logical_coord, voxels);
+            let voxel_material = VoxelMaterial::new(merged_chunk.downsample(), CHUNK_SZ_3 as u32);
+            let merged = commands
+                .spawn((
+                    VolumetricBundle::new(voxel_material)
+                        .with_meshing_algorithm(meshing_algorithm.unwrap_or_default())
+                        .with_boundary_mode(boundary_mode.unwrap_or_default()),
+                    MergedChunk::transform(&config, logical_coord),
+                    merged_chunk,
+                ))
+                .id();
+            chunks_merged.send(ChunksMerged {
+                coord: logical_coord,
+                merged,
+                sub_chunks: entities,
+            });
+        }
+    }
+}
+
+/// Splits and merges logical chunks at runtime with the [`ChunkSplitting`] settings.
+pub struct ChunkSplittingPlugin;
+
+impl Plugin for ChunkSplittingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ChunkSplitting>()
+            .init_resource::<VoxelWorldConfig>()
+            .add_event::<SplitChunk>()
+            .add_event::<MergeChunks>()
+            .add_event::<ChunkSplit>()
+            .add_event::<ChunksMerged>()
+            .add_systems(
+                PostUpdate,
+                (
+                    MergedChunk::track_edits.after(ChunkEdit::apply),
+                    ChunkSplitting::update.after(MergedChunk::track_edits),
+                ),
+            );
+    }
+}
@@ -0,0 +1,43 @@
+use bevy::{
+    prelude::*,
+    render::{extract_component::ExtractComponentPlugin, Render, RenderApp, RenderSet},
+};
+
+use crate::{
+    data::{
+        chunk_retirement::ChunkRetirementExt, cluster_bounds::ClusterBounds,
+        gpu_cluster_bounds::GpuClusterBounds, voxel_material::VoxelMaterialComponents,
+    },
+    render::{
+        cluster_bounds_compute_pipeline::{ClusterBoundsComputePass, ClusterBoundsComputePipeline},
+        post_mesh_compute_pass::PostMeshComputePassAppExt,
+        voxel_mesh_compute_pipeline::DirtyMeshes,
+    },
+};
+
+/// Computes the [`ClusterBounds`] of volumetric entities on the GPU whenever they are remeshed,
+/// into the render world's [`VoxelMaterialComponents<GpuClusterBounds>`].
+pub struct ClusterBoundsPlugin;
+
+impl Plugin for ClusterBoundsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(ExtractComponentPlugin::<ClusterBounds>::default())
+            .add_post_mesh_compute_pass(ClusterBoundsComputePass);
+    }
+
+    fn finish(&self, app: &mut App) {
+        let render_app = app.sub_app_mut(RenderApp);
+
+        render_app
+            .init_resource::<ClusterBoundsComputePipeline>()
+            .init_resource::<VoxelMaterialComponents<GpuClusterBounds>>()
+            .free_on_retire::<GpuClusterBounds>()
+            .add_systems(ExtractSchedule, GpuClusterBounds::extract)
+            .add_systems(
+                Render,
+                GpuClusterBounds::prepare
+                    .in_set(RenderSet::PrepareBindGroups)
+                    .after(DirtyMeshes::select),
+            );
+    }
+}
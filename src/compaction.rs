@@ -0,0 +1,219 @@
+use std::time::{Duration, Instant};
+
+use bevy::{
+    core::FrameCount,
+    prelude::*,
+    render::{
+        extract_resource::{ExtractResource, ExtractResourcePlugin},
+        renderer::{RenderDevice, RenderQueue},
+        Render, RenderApp, RenderSet,
+    },
+    utils::HashMap,
+};
+use crossbeam_channel::{Receiver, Sender};
+
+use crate::{
+    channels::{PendingReadbacks, VertexReadback},
+    data::{
+        gpu_iso_surface::GpuIsoSurfaces, gpu_voxel_material::GpuVoxelMaterial,
+        gpu_voxel_material_bind_group::GpuVoxelMaterialBindGroups,
+        material_split::MaterialIndexRanges, raw_mesh_data::GpuRawMeshData,
+        voxel_material::VoxelMaterialComponents,
+    },
+    render::voxel_mesh_compute_pipeline::DirtyMeshes,
+};
+
+/// Shrinks the output buffers of chunks left unedited for a while down to their mesh, so that a
+/// long editing session doesn't keep every chunk at the size of its most complex mesh. Compacted
+/// chunks are grown back to full size before they are remeshed.
+///
+/// Chunks with a [`MaterialSplit`](crate::bundles::volumetric_bundle::MaterialSplit), whose index
+/// buffer is split by size, or with [`RawMeshData`](crate::data::raw_mesh_data::RawMeshData), whose
+/// staging buffers follow the output buffers, are never compacted.
+#[derive(Resource, Clone, Copy, Debug, ExtractResource)]
+pub struct BufferCompaction {
+    pub enabled: bool,
+    /// Time spent compacting chunks each frame, at least one chunk is compacted per frame.
+    pub budget: Duration,
+    /// Capacity kept above the mesh of a compacted chunk, as a multiple of its size.
+    pub headroom: f32,
+    /// Frames a chunk must go without being remeshed or read back before it is compacted.
+    pub idle_frames: u32,
+}
+
+impl Default for BufferCompaction {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            budget: Duration::from_micros(500),
+            headroom: 1.25,
+            idle_frames: 600,
+        }
+    }
+}
+
+/// Sent when the output buffers of an entity are compacted.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct BuffersCompacted {
+    pub entity: Entity,
+    /// Bytes of VRAM freed by the compaction.
+    pub reclaimed_bytes: u64,
+}
+
+/// VRAM currently reclaimed by [`BufferCompaction`], updated once per frame from the render world.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BufferCompactionStats {
+    pub reclaimed_bytes: u64,
+    pub compacted_chunks: usize,
+}
+
+/// The compactions of a frame along with the stats after them.
+pub struct CompactionReport {
+    pub compacted: Vec<BuffersCompacted>,
+    pub stats: BufferCompactionStats,
+}
+
+/// The last frame each entity was remeshed or read back, and the stats last sent.
+#[derive(Resource, Default)]
+pub struct BufferCompactionState {
+    last_active: HashMap<Entity, u32>,
+    stats: BufferCompactionStats,
+}
+
+impl BufferCompaction {
+    /// Grows the compacted chunks about to be remeshed back to full size, then compacts the idle
+    /// chunks until the frame budget is spent.
+    #[allow(clippy::too_many_arguments)]
+    pub fn compact(
+        settings: Res<Self>,
+        render_device: Res<RenderDevice>,
+        render_queue: Res<RenderQueue>,
+        frame_count: Res<FrameCount>,
+        dirty_meshes: Res<DirtyMeshes>,
+        pending_readbacks: Res<PendingReadbacks>,
+        vertex_readbacks: Res<VoxelMaterialComponents<VertexReadback>>,
+        material_index_ranges: Res<VoxelMaterialComponents<MaterialIndexRanges>>,
+        gpu_raw_meshes: Res<VoxelMaterialComponents<GpuRawMeshData>>,
+        mut gpu_voxel_materials: ResMut<VoxelMaterialComponents<GpuVoxelMaterial>>,
+        mut state: ResMut<BufferCompactionState>,
+        sender: Res<BufferCompactionSender>,
+    ) {
+        let frame = frame_count.0;
+        let start = Instant::now();
+        let mut compacted = Vec::new();
+
+        state
+            .last_active
+            .retain(|entity, _| gpu_voxel_materials.0.contains_key(entity));
+
+        for (entity, gpu_voxel_material) in gpu_voxel_materials.0.iter_mut() {
+            let full = gpu_voxel_material.full_output_capacities();
+            let current = gpu_voxel_material.output_capacities();
+
+            let active = dirty_meshes.is_dirty(entity)
+                || dirty_meshes.is_meshed(entity)
+                || pending_readbacks.contains(entity)
+                || vertex_readbacks
+                    .get(entity)
+                    .is_some_and(VertexReadback::in_progress);
+            if active {
+                state.last_active.insert(*entity, frame);
+                // The compute shader writes meshes of any size up to the full capacities.
+                if dirty_meshes.is_dirty(entity) && current != full {
+                    gpu_voxel_material.resize_output_buffers(&render_device, &render_queue, full);
+                }
+                continue;
+            }
+
+            let last_active = *state.last_active.entry(*entity).or_insert(frame);
+            if !settings.enabled
+                || settings.budget.is_zero()
+                || (!compacted.is_empty() && start.elapsed() >= settings.budget)
+                || frame.wrapping_sub(last_active) < settings.idle_frames
+                || material_index_ranges.0.contains_key(entity)
+                || gpu_raw_meshes.0.contains_key(entity)
+            {
+                continue;
+            }
+
+            let Some(mesh_counts) = gpu_voxel_material.mesh_counts else {
+                continue;
+            };
+            let snug = full.snug(mesh_counts, settings.headroom);
+            if snug.size() >= current.size() {
+                continue;
+            }
+
+            gpu_voxel_material.resize_output_buffers(&render_device, &render_queue, snug);
+            compacted.push(BuffersCompacted {
+                entity: *entity,
+                reclaimed_bytes: current.size() - snug.size(),
+            });
+        }
+
+        let mut stats = BufferCompactionStats::default();
+        for gpu_voxel_material in gpu_voxel_materials.0.values() {
+            let full = gpu_voxel_material.full_output_capacities().size();
+            let current = gpu_voxel_material.output_capacities().size();
+            if current < full {
+                stats.reclaimed_bytes += full - current;
+                stats.compacted_chunks += 1;
+            }
+        }
+
+        if !compacted.is_empty() || stats != state.stats {
+            state.stats = stats;
+            let _ = sender.send(CompactionReport { compacted, stats });
+        }
+    }
+}
+
+#[derive(Resource, Deref)]
+pub struct BufferCompactionReceiver(pub Receiver<CompactionReport>);
+
+impl BufferCompactionReceiver {
+    /// Forwards the compactions reported by the render world as [`BuffersCompacted`] events.
+    pub fn receive(
+        receiver: Res<Self>,
+        mut stats: ResMut<BufferCompactionStats>,
+        mut buffers_compacted: EventWriter<BuffersCompacted>,
+    ) {
+        for report in receiver.try_iter() {
+            buffers_compacted.send_batch(report.compacted);
+            *stats = report.stats;
+        }
+    }
+}
+
+#[derive(Resource, Deref)]
+pub struct BufferCompactionSender(pub Sender<CompactionReport>);
+
+/// Compacts the output buffers of idle chunks with the [`BufferCompaction`] settings. Must be
+/// added after the [`GpuReadbackPlugin`](crate::GpuReadbackPlugin).
+pub struct BufferCompactionPlugin;
+
+impl Plugin for BufferCompactionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(ExtractResourcePlugin::<BufferCompaction>::default())
+            .init_resource::<BufferCompaction>()
+            .init_resource::<BufferCompactionStats>()
+            .add_event::<BuffersCompacted>()
+            .add_systems(Update, BufferCompactionReceiver::receive);
+    }
+
+    fn finish(&self, app: &mut App) {
+        let (s, r) = crossbeam_channel::unbounded();
+        app.insert_resource(BufferCompactionReceiver(r));
+
+        app.sub_app_mut(RenderApp)
+            .init_resource::<BufferCompactionState>()
+            .insert_resource(BufferCompactionSender(s))
+            .add_systems(
+                Render,
+                BufferCompaction::compact
+                    .in_set(RenderSet::PrepareBindGroups)
+                    .after(GpuIsoSurfaces::prepare)
+                    .before(GpuVoxelMaterialBindGroups::prepare),
+            );
+    }
+}
@@ -0,0 +1,242 @@
+use std::time::Duration;
+
+use bevy::{
+    prelude::*,
+    render::{
+        extract_component::ExtractComponent,
+        renderer::{RenderDevice, RenderQueue},
+    },
+};
+
+use crate::{
+    render::{shaders::shader_struct, voxel_mesh_compute_pipeline::DirtyMeshes},
+    CHUNK_SZ,
+};
+
+use super::{gpu_voxel_material::GpuVoxelMaterial, voxel_material::VoxelMaterialComponents};
+
+#[cfg(feature = "editing")]
+use super::voxel_edit::ChunkEdited;
+
+/// Most [`DetailRegion`]s refined in a volume, further regions are meshed at base resolution.
+pub const MAX_DETAIL_REGIONS: usize = 8;
+
+/// A box of cells meshed at the refined resolution of an [`AdaptiveResolution`], in the voxel
+/// coordinates of the volume.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DetailRegion {
+    pub min: UVec3,
+    /// The cell past the last refined one.
+    pub max: UVec3,
+}
+
+impl DetailRegion {
+    /// The cells within `radius` of `center`, clamped to the volume, or `None` if the sphere
+    /// doesn't overlap it.
+    pub fn around(center: Vec3, radius: f32) -> Option<Self> {
+        let size = Vec3::splat(CHUNK_SZ as f32);
+        let min = (center - radius).floor().max(Vec3::ZERO);
+        let max = (center + radius).ceil().min(size);
+        (min.cmplt(max).all()).then(|| Self {
+            min: min.as_uvec3(),
+            max: max.as_uvec3(),
+        })
+    }
+
+    /// The cells whose corners include any of the voxels from `min` to `max`, e.g. the
+    /// [`VoxelEdit::bounds`](super::voxel_edit::VoxelEdit::bounds) of an edit.
+    pub fn touching(min: UVec3, max: UVec3) -> Self {
+        Self {
+            min: min.saturating_sub(UVec3::ONE),
+            max,
+        }
+    }
+}
+
+/// Meshes the smooth cells of [`MeshingAlgorithm::MarchingCubes`](super::meshing_algorithm::MeshingAlgorithm::MarchingCubes)
+/// inside its detail regions at `subdivisions` times the voxel density, by subdividing each cell
+/// like an octree and supersampling the density field trilinearly, while the rest of the volume
+/// stays at base resolution. The vertices refined cells place on a face shared with a base
+/// resolution cell are moved onto the contour of that cell, so the two resolutions meet without
+/// cracks, also across chunks.
+///
+/// The detail regions are the user's `regions`, the cells touched by the edits of the last
+/// `edit_lifetime` and the cells around every [`DetailFocus`]. Only the first
+/// [`MAX_DETAIL_REGIONS`] are refined. Refined cells emit many more triangles into the same output
+/// buffers as the rest of the volume, so the regions should stay small.
+#[derive(Clone, Component, ExtractComponent, Debug)]
+pub struct AdaptiveResolution {
+    /// Subdivisions of each refined cell along each axis, 2 or 4.
+    pub subdivisions: u32,
+    pub regions: Vec<DetailRegion>,
+    /// How long the cells touched by an edit stay refined, zero to not refine edits.
+    pub edit_lifetime: Duration,
+    recent_edits: Vec<(DetailRegion, Duration)>,
+    focus_regions: Vec<DetailRegion>,
+}
+
+impl Default for AdaptiveResolution {
+    fn default() -> Self {
+        Self {
+            subdivisions: 2,
+            regions: Vec::new(),
+            edit_lifetime: Duration::from_secs(5),
+            recent_edits: Vec::new(),
+            focus_regions: Vec::new(),
+        }
+    }
+}
+
+/// Refines the cells of every [`AdaptiveResolution`] volume within `radius` voxels of the entity,
+/// e.g. the player or the camera.
+#[derive(Clone, Copy, Component, Debug)]
+pub struct DetailFocus {
+    pub radius: f32,
+}
+
+impl Default for DetailFocus {
+    fn default() -> Self {
+        Self { radius: 4.0 }
+    }
+}
+
+shader_struct! {
+    #[derive(Clone, Copy, Default, PartialEq)]
+    pub struct AdaptiveResolutionParams {
+        /// The first refined cell of each region.
+        regions_min: [UVec4; MAX_DETAIL_REGIONS],
+        /// The cell past the last refined one of each region.
+        regions_max: [UVec4; MAX_DETAIL_REGIONS],
+        count: u32,
+        subdivisions: u32,
+    }
+}
+
+impl AdaptiveResolutionParams {
+    /// Whether any cell is refined.
+    pub fn is_refined(&self) -> bool {
+        self.count > 0
+    }
+}
+
+impl From<&AdaptiveResolution> for AdaptiveResolutionParams {
+    fn from(adaptive_resolution: &AdaptiveResolution) -> Self {
+        let mut params = Self {
+            subdivisions: match adaptive_resolution.subdivisions {
+                0..=2 => 2,
+                _ => 4,
+            },
+            ..default()
+        };
+        let regions = adaptive_resolution
+            .detail_regions()
+            .take(MAX_DETAIL_REGIONS);
+        for (index, region) in regions.enumerate() {
+            params.regions_min[index] = region.min.extend(0);
+            params.regions_max[index] = region.max.extend(0);
+            params.count += 1;
+        }
+        params
+    }
+}
+
+impl AdaptiveResolution {
+    /// The regions refined this frame.
+    pub fn detail_regions(&self) -> impl Iterator<Item = DetailRegion> + '_ {
+        self.regions
+            .iter()
+            .chain(self.recent_edits.iter().map(|(region, _)| region))
+            .chain(&self.focus_regions)
+            .copied()
+    }
+
+    /// Refines the cells touched by each [`ChunkEdited`] for the `edit_lifetime` of its volume,
+    /// dropping the edits that expired.
+    #[cfg(feature = "editing")]
+    pub fn track_edits(
+        time: Res<Time>,
+        mut edited: EventReader<ChunkEdited>,
+        mut adaptive_query: Query<&mut AdaptiveResolution>,
+    ) {
+        let now = time.elapsed();
+        for ChunkEdited { entity, edit, .. } in edited.read() {
+            let Ok(mut adaptive_resolution) = adaptive_query.get_mut(*entity) else {
+                continue;
+            };
+            if adaptive_resolution.edit_lifetime.is_zero() {
+                continue;
+            }
+
+            let (min, max) = edit.bounds();
+            let expires = now + adaptive_resolution.edit_lifetime;
+            let region = DetailRegion::touching(min, max);
+            // Repeated edits of the same cells, e.g. while painting, only extend the lifetime.
+            match adaptive_resolution
+                .recent_edits
+                .iter_mut()
+                .find(|(recent, _)| *recent == region)
+            {
+                Some((_, recent_expires)) => *recent_expires = expires,
+                None => adaptive_resolution.recent_edits.push((region, expires)),
+            }
+        }
+
+        for mut adaptive_resolution in adaptive_query.iter_mut() {
+            if adaptive_resolution
+                .recent_edits
+                .iter()
+                .any(|(_, expires)| *expires <= now)
+            {
+                adaptive_resolution
+                    .recent_edits
+                    .retain(|(_, expires)| *expires > now);
+            }
+        }
+    }
+
+    /// Refines the cells around every [`DetailFocus`], in the voxel coordinates of each volume.
+    pub fn track_focus(
+        focus_query: Query<(&GlobalTransform, &DetailFocus)>,
+        mut adaptive_query: Query<(&mut AdaptiveResolution, &GlobalTransform)>,
+    ) {
+        for (mut adaptive_resolution, transform) in adaptive_query.iter_mut() {
+            let volume_from_world = transform.compute_matrix().inverse();
+            let focus_regions = focus_query
+                .iter()
+                .filter_map(|(focus_transform, focus)| {
+                    let center = volume_from_world.transform_point3(focus_transform.translation());
+                    DetailRegion::around(center, focus.radius)
+                })
+                .collect::<Vec<_>>();
+
+            if adaptive_resolution.focus_regions != focus_regions {
+                adaptive_resolution.focus_regions = focus_regions;
+            }
+        }
+    }
+
+    /// Uploads the detail regions of each entity into the adaptive resolution buffer of its
+    /// [`GpuVoxelMaterial`], clearing those of entities whose [`AdaptiveResolution`] was removed.
+    pub fn prepare(
+        render_queue: Res<RenderQueue>,
+        render_device: Res<RenderDevice>,
+        mut gpu_voxel_materials: ResMut<VoxelMaterialComponents<GpuVoxelMaterial>>,
+        mut dirty_meshes: ResMut<DirtyMeshes>,
+        adaptive_query: Query<&AdaptiveResolution>,
+    ) {
+        for (entity, gpu_voxel_material) in gpu_voxel_materials.0.iter_mut() {
+            let params = adaptive_query.get(*entity).map_or_else(
+                |_| AdaptiveResolutionParams::default(),
+                AdaptiveResolutionParams::from,
+            );
+
+            if *gpu_voxel_material.adaptive_resolution_buffer.get() != params {
+                gpu_voxel_material.adaptive_resolution_buffer.set(params);
+                gpu_voxel_material
+                    .adaptive_resolution_buffer
+                    .write_buffer(render_device.as_ref(), render_queue.as_ref());
+                dirty_meshes.mark(*entity);
+            }
+        }
+    }
+}
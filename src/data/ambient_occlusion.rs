@@ -0,0 +1,68 @@
+use bevy::{
+    prelude::*,
+    render::{
+        extract_component::ExtractComponent,
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages},
+    },
+};
+
+use crate::{render::shaders::shader_struct, CHUNK_SZ};
+
+/// Generates a low resolution 3D ambient occlusion texture from the density grid of a volumetric
+/// entity on the GPU, for other materials in the scene to sample near the volume.
+#[derive(Clone, Component, ExtractComponent, Debug)]
+pub struct AmbientOcclusionVolume {
+    /// The occlusion texture, with one texel per `cell_size`³ voxels holding the ambient visibility
+    /// around it, from 0 (fully occluded) to 1 (unoccluded).
+    pub image: Handle<Image>,
+    /// Voxels covered by a texel along each axis.
+    pub cell_size: u32,
+    /// Distance in voxels around the centre of a texel over which densities are averaged.
+    pub radius: u32,
+}
+
+impl AmbientOcclusionVolume {
+    /// Creates the occlusion texture of a chunk with one texel per `cell_size`³ voxels.
+    pub fn new(images: &mut Assets<Image>, cell_size: u32) -> Self {
+        let resolution = (CHUNK_SZ as u32).div_ceil(cell_size);
+
+        let mut image = Image::new_fill(
+            Extent3d {
+                width: resolution,
+                height: resolution,
+                depth_or_array_layers: resolution,
+            },
+            TextureDimension::D3,
+            &[255, 255, 255, 255],
+            TextureFormat::Rgba8Unorm,
+            RenderAssetUsages::RENDER_WORLD,
+        );
+        image.texture_descriptor.usage = TextureUsages::COPY_DST
+            | TextureUsages::STORAGE_BINDING
+            | TextureUsages::TEXTURE_BINDING;
+
+        Self {
+            image: images.add(image),
+            cell_size,
+            radius: cell_size,
+        }
+    }
+}
+
+shader_struct! {
+    #[derive(Clone, Copy, Default)]
+    pub struct AmbientOcclusionParams {
+        cell_size: u32,
+        radius: u32,
+    }
+}
+
+impl From<&AmbientOcclusionVolume> for AmbientOcclusionParams {
+    fn from(ambient_occlusion: &AmbientOcclusionVolume) -> Self {
+        Self {
+            cell_size: ambient_occlusion.cell_size.max(1),
+            radius: ambient_occlusion.radius,
+        }
+    }
+}
@@ -1,9 +1,20 @@
 use std::sync::atomic::AtomicU32;
 
-use bevy::render::render_resource::ShaderType;
+use crate::render::shaders::shader_struct;
 
-#[derive(ShaderType)]
-pub struct Atomics {
-    vertices_head: AtomicU32,
-    indices_head: AtomicU32,
+use super::material_split::MAX_MATERIALS;
+
+shader_struct! {
+    pub struct Atomics {
+        vertices_head: AtomicU32,
+        indices_head: AtomicU32,
+        /// Number of indices of each material, only counted with a
+        /// [`MaterialSplit`](crate::bundles::volumetric_bundle::MaterialSplit).
+        material_index_counts: [AtomicU32; MAX_MATERIALS],
+    }
+}
+
+impl Atomics {
+    /// Number of `u32`s of the atomics buffer.
+    pub const LEN: usize = 2 + MAX_MATERIALS;
 }
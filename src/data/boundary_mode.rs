@@ -0,0 +1,44 @@
+use bevy::{
+    prelude::*,
+    render::{extract_component::ExtractComponent, render_resource::ShaderDefVal},
+};
+
+use crate::core;
+
+/// How densities outside of a volume are treated when meshing the cells at its edges. Entities
+/// without one use [`BoundaryMode::Empty`].
+#[derive(Clone, Copy, Component, ExtractComponent, Debug, Default, PartialEq, Eq, Hash)]
+pub enum BoundaryMode {
+    /// Outside voxels repeat the nearest voxel on the edge of the volume.
+    Clamp,
+    /// Outside voxels are empty, closing the surface where it meets the edge of the volume.
+    #[default]
+    Empty,
+    /// Outside voxels are solid.
+    Solid,
+    /// Outside voxels wrap around to the opposite edge, for toroidal worlds.
+    Wrap,
+}
+
+impl From<BoundaryMode> for core::mesher::BoundaryMode {
+    fn from(boundary_mode: BoundaryMode) -> Self {
+        match boundary_mode {
+            BoundaryMode::Clamp => Self::Clamp,
+            BoundaryMode::Empty => Self::Empty,
+            BoundaryMode::Solid => Self::Solid,
+            BoundaryMode::Wrap => Self::Wrap,
+        }
+    }
+}
+
+impl BoundaryMode {
+    /// The shader def selecting this mode in the meshing compute shader.
+    pub fn shader_def(&self) -> ShaderDefVal {
+        match self {
+            Self::Clamp => "BOUNDARY_CLAMP".into(),
+            Self::Empty => "BOUNDARY_EMPTY".into(),
+            Self::Solid => "BOUNDARY_SOLID".into(),
+            Self::Wrap => "BOUNDARY_WRAP".into(),
+        }
+    }
+}
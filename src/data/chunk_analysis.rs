@@ -0,0 +1,130 @@
+use std::sync::atomic::AtomicU32;
+
+use bevy::{ecs::query::QueryItem, prelude::*, render::extract_component::ExtractComponent};
+use bytemuck::{Pod, Zeroable};
+
+use crate::render::shaders::shader_struct;
+
+use super::{
+    iso_surface::DEFAULT_ISO_LEVEL, material_split::MAX_MATERIALS,
+    volume_statistics::from_order_key,
+};
+
+/// Statistics of the voxels of a chunk computed on the GPU whenever it is remeshed, to decide
+/// what to stream, at which level of detail, or to debug generation. Insert a default one to opt
+/// an entity in; the statistics stay at zero until the first analysis is read back.
+#[derive(Component, Clone, Debug, PartialEq)]
+pub struct ChunkAnalysis {
+    /// Density from which a voxel counts towards `occupancy` and `material_counts`.
+    pub iso_level: f32,
+    pub min_density: f32,
+    pub max_density: f32,
+    /// Fraction of the voxels that are solid, from 0 to 1.
+    pub occupancy: f32,
+    pub voxel_count: u32,
+    /// Solid voxels of each material, the voxels whose flags are past the last material counting
+    /// as the last one.
+    pub material_counts: [u32; MAX_MATERIALS],
+}
+
+impl Default for ChunkAnalysis {
+    fn default() -> Self {
+        Self {
+            iso_level: DEFAULT_ISO_LEVEL,
+            min_density: 0.0,
+            max_density: 0.0,
+            occupancy: 0.0,
+            voxel_count: 0,
+            material_counts: [0; MAX_MATERIALS],
+        }
+    }
+}
+
+impl ChunkAnalysis {
+    /// Whether the chunk has no solid voxel, so that it can be skipped or unloaded.
+    pub fn is_empty(&self) -> bool {
+        self.voxel_count > 0 && self.material_counts.iter().all(|count| *count == 0)
+    }
+
+    /// Whether every voxel of the chunk is solid, so that it has no surface of its own.
+    pub fn is_full(&self) -> bool {
+        self.voxel_count > 0 && self.occupancy >= 1.0
+    }
+
+    /// The material with the most solid voxels, if any voxel is solid.
+    pub fn dominant_material(&self) -> Option<usize> {
+        self.material_counts
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count > 0)
+            .max_by_key(|(_, count)| **count)
+            .map(|(material, _)| material)
+    }
+
+    /// Updates the statistics from the counters read back for the chunk.
+    pub fn apply(&mut self, data: &ChunkAnalysisData) {
+        let solid = data
+            .material_counts
+            .iter()
+            .map(|count| *count as u64)
+            .sum::<u64>();
+        self.voxel_count = data.voxel_count;
+        self.material_counts = data.material_counts;
+        self.occupancy = solid as f32 / data.voxel_count.max(1) as f32;
+        match data.voxel_count {
+            0 => (self.min_density, self.max_density) = (0.0, 0.0),
+            _ => {
+                self.min_density = from_order_key(!data.min_key);
+                self.max_density = from_order_key(data.max_key);
+            }
+        }
+    }
+}
+
+/// Marks the render world entities whose [`ChunkAnalysis`] is computed, with its iso-level.
+#[derive(Component, Clone, Copy)]
+pub struct ChunkAnalysisReadback {
+    pub iso_level: f32,
+}
+
+impl ExtractComponent for ChunkAnalysis {
+    type QueryData = &'static ChunkAnalysis;
+    type QueryFilter = ();
+    type Out = ChunkAnalysisReadback;
+
+    fn extract_component(chunk_analysis: QueryItem<'_, Self::QueryData>) -> Option<Self::Out> {
+        Some(ChunkAnalysisReadback {
+            iso_level: chunk_analysis.iso_level,
+        })
+    }
+}
+
+shader_struct! {
+    #[derive(Clone, Copy, Default)]
+    pub struct ChunkAnalysisParams {
+        pub voxel_count: u32,
+        pub iso_level: f32,
+    }
+}
+
+shader_struct! {
+    /// The counters accumulated by the analysis shader, laid out as a [`ChunkAnalysisData`]. The
+    /// density range is stored as order keys, with the complement of the smallest one in `min_key`
+    /// so that both start at zero.
+    pub struct ChunkAnalysisCounters {
+        min_key: AtomicU32,
+        max_key: AtomicU32,
+        voxel_count: AtomicU32,
+        material_counts: [AtomicU32; MAX_MATERIALS],
+    }
+}
+
+/// The [`ChunkAnalysisCounters`] read back from the GPU.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Pod, Zeroable)]
+pub struct ChunkAnalysisData {
+    pub min_key: u32,
+    pub max_key: u32,
+    pub voxel_count: u32,
+    pub material_counts: [u32; MAX_MATERIALS],
+}
@@ -0,0 +1,6 @@
+use bevy::prelude::*;
+
+/// Position of a chunk in the world, in chunks. Identifies the chunk across saves.
+#[derive(Clone, Copy, Component, Reflect, Debug, Default, PartialEq, Eq, Hash)]
+#[reflect(Component)]
+pub struct ChunkCoord(pub IVec3);
@@ -0,0 +1,122 @@
+use bevy::{
+    prelude::*,
+    render::{extract_component::ExtractComponent, primitives::Frustum},
+};
+
+use crate::render::shaders::shader_struct;
+
+use super::depth_pyramid::DepthPyramid;
+
+/// Culls the chunks of volumetric entities against the view of a 3d camera on the GPU every
+/// frame, in two phases: a frustum test of the bounding box of each chunk, then a Hi-Z occlusion
+/// test of the survivors against the [`DepthPyramid`] of the camera. Whether each draw survived
+/// is written into the [`GpuChunkCulling`](super::gpu_chunk_culling::GpuChunkCulling) of the view
+/// in the render world, for statistics and debugging tools.
+///
+/// The culling doesn't feed any draw: chunks are drawn as regular meshes read back from the GPU,
+/// and the crate has no GPU-driven renderer drawing from the output buffers of the chunks.
+///
+/// A chunk with [`ClusterBounds`](super::cluster_bounds::ClusterBounds) is drawn cluster by
+/// cluster, each surviving cluster also tested against its own bounding sphere and normal cone
+/// within the frustum and depth pyramid. Otherwise it is drawn one material segment of its index
+/// buffer at a time, a single draw without a
+/// [`MaterialSplit`](crate::bundles::volumetric_bundle::MaterialSplit).
+///
+/// The culling runs after the main pass, as soon as the pyramid is built from its depth, so the
+/// results are those of the draws visible in the frame just rendered. A [`DepthPyramid`] is added
+/// to the camera along with it.
+#[derive(Component, Clone, Copy, Debug, ExtractComponent)]
+pub struct ChunkCulling {
+    /// Whether the chunks within the frustum are tested against the depth pyramid too.
+    pub occlusion: bool,
+}
+
+impl Default for ChunkCulling {
+    fn default() -> Self {
+        Self { occlusion: true }
+    }
+}
+
+impl ChunkCulling {
+    /// Adds a [`DepthPyramid`] to the [`ChunkCulling`] cameras without one.
+    pub fn add_depth_pyramids(
+        mut commands: Commands,
+        camera_query: Query<Entity, (With<ChunkCulling>, Without<DepthPyramid>)>,
+    ) {
+        for entity in camera_query.iter() {
+            commands.entity(entity).insert(DepthPyramid);
+        }
+    }
+}
+
+shader_struct! {
+    #[derive(Clone, Copy, Default, PartialEq)]
+    pub struct ChunkCullingView {
+        pub clip_from_world: Mat4,
+        /// The half spaces of the frustum of the view, as in [`Frustum`].
+        pub frustum: [Vec4; 6],
+        /// The size of the first mip of the depth pyramid.
+        pub pyramid_size: UVec2,
+        pub pyramid_mips: u32,
+        /// Whether the draws are tested against the depth pyramid.
+        pub occlusion: u32,
+    }
+}
+
+impl ChunkCullingView {
+    pub fn new(
+        clip_from_world: Mat4,
+        frustum: &Frustum,
+        pyramid_size: UVec2,
+        pyramid_mips: u32,
+        occlusion: bool,
+    ) -> Self {
+        Self {
+            clip_from_world,
+            frustum: frustum.half_spaces.map(|half_space| half_space.normal_d()),
+            pyramid_size,
+            pyramid_mips,
+            occlusion: occlusion as u32,
+        }
+    }
+}
+
+shader_struct! {
+    #[derive(Clone, Copy, Default, PartialEq)]
+    pub struct ChunkCullingParams {
+        pub world_from_local: Mat4,
+        /// The bounding box of the chunk in world space.
+        pub aabb_min: Vec3,
+        /// Largest scale of `world_from_local`, scaling the radius of the cluster spheres.
+        pub max_scale: f32,
+        pub aabb_max: Vec3,
+        /// Draws written to the args buffer, one per cluster or per segment.
+        pub draw_count: u32,
+        /// The camera of the view in the local space of the chunk, for the normal cones.
+        pub camera: Vec3,
+        /// Indices of each segment of the index buffer, the whole buffer without a material split.
+        pub segment_size: u32,
+        pub segment_count: u32,
+        pub clusters_per_segment: u32,
+    }
+}
+
+shader_struct! {
+    /// The args of an indexed indirect draw, laid out as `DrawIndexedIndirectArgs`.
+    #[derive(Clone, Copy, Default)]
+    pub struct ChunkDrawArgs {
+        pub index_count: u32,
+        /// 0 for culled draws, else 1.
+        pub instance_count: u32,
+        pub first_index: u32,
+        pub base_vertex: i32,
+        pub first_instance: u32,
+    }
+}
+
+shader_struct! {
+    pub struct ChunkDrawArgsBuffer {
+        #[size(runtime)]
+        data: Vec<ChunkDrawArgs>,
+    }
+}
@@ -0,0 +1,74 @@
+use std::sync::Arc;
+
+use bevy::{prelude::*, render::extract_component::ExtractComponent};
+
+use crate::{bundles::volumetric_bundle::Volumetric, CHUNK_SZ};
+
+/// How urgently a volumetric entity should be processed, higher first. Updated every frame from
+/// the active camera by the [`ChunkPriorityFn`].
+#[derive(Clone, Copy, Component, ExtractComponent, Debug, Default, PartialEq)]
+pub struct ChunkPriority(pub f32);
+
+/// What a [`ChunkPriorityFn`] scores a chunk from.
+#[derive(Clone, Copy, Debug)]
+pub struct ChunkPriorityContext {
+    pub entity: Entity,
+    /// Centre of the chunk in world space.
+    pub chunk_center: Vec3,
+    pub camera_position: Vec3,
+    pub camera_forward: Vec3,
+}
+
+/// Scores the [`ChunkPriority`] of every volumetric entity. Replace it to customise the order
+/// chunks are processed in, e.g. around the player's build cursor.
+#[derive(Resource, Clone)]
+pub struct ChunkPriorityFn(pub Arc<dyn Fn(&ChunkPriorityContext) -> f32 + Send + Sync>);
+
+impl Default for ChunkPriorityFn {
+    fn default() -> Self {
+        Self(Arc::new(view_direction_priority))
+    }
+}
+
+/// Favours chunks in front of the camera over those behind it, and near chunks over far ones.
+pub fn view_direction_priority(context: &ChunkPriorityContext) -> f32 {
+    let to_chunk = context.chunk_center - context.camera_position;
+    let distance = to_chunk.length();
+    let facing = context.camera_forward.dot(to_chunk.normalize_or_zero());
+
+    (2.0 + facing) / (1.0 + distance)
+}
+
+impl ChunkPriority {
+    /// Scores every volumetric entity from the first active camera.
+    pub fn update(
+        priority_fn: Res<ChunkPriorityFn>,
+        camera_query: Query<(&Camera, &GlobalTransform)>,
+        mut chunk_query: Query<
+            (Entity, Option<&GlobalTransform>, &mut ChunkPriority),
+            With<Volumetric>,
+        >,
+    ) {
+        let Some((_, camera_transform)) = camera_query.iter().find(|(camera, _)| camera.is_active)
+        else {
+            return;
+        };
+
+        for (entity, transform, mut priority) in chunk_query.iter_mut() {
+            let local_center = Vec3::splat(CHUNK_SZ as f32 / 2.0);
+            let context = ChunkPriorityContext {
+                entity,
+                chunk_center: transform.map_or(local_center, |transform| {
+                    transform.transform_point(local_center)
+                }),
+                camera_position: camera_transform.translation(),
+                camera_forward: camera_transform.forward().into(),
+            };
+
+            let score = (priority_fn.0)(&context);
+            if priority.0 != score {
+                priority.0 = score;
+            }
+        }
+    }
+}
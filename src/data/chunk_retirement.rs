@@ -0,0 +1,148 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use bevy::{
+    app::SubApp,
+    prelude::*,
+    render::{extract_component::ExtractComponent, renderer::RenderQueue, Extract},
+    utils::HashMap,
+};
+use crossbeam_channel::{Receiver, Sender};
+
+use super::voxel_material::VoxelMaterialComponents;
+
+/// Unloads a volumetric entity without freeing GPU resources still in use by frames in flight.
+/// Along with [`VoxelComputeSuspended`](crate::bundles::volumetric_bundle::VoxelComputeSuspended),
+/// which stops its dispatches the frame it is inserted, the render world keeps its resources until
+/// the GPU is done with every submission recorded before it, then frees them and despawns the
+/// entity. Despawning a chunk right away while its buffers are bound by work still in flight risks
+/// validation errors or a lost device under aggressive streaming.
+#[derive(Clone, Copy, Component, ExtractComponent, Debug)]
+pub struct ChunkRetiring;
+
+struct RetiringChunk {
+    /// Set once the GPU is done with the work submitted until the chunk was suspended.
+    work_done: Arc<AtomicBool>,
+    /// Whether `work_done` waits on a submission yet.
+    tracked: bool,
+}
+
+/// The [`ChunkRetiring`] entities of the render world, along with how to free the
+/// [`VoxelMaterialComponents`] registered with [`ChunkRetirementExt::free_on_retire`].
+#[derive(Resource, Default)]
+pub struct RetiringChunks {
+    chunks: HashMap<Entity, RetiringChunk>,
+    free_fns: Vec<fn(&mut World, Entity)>,
+}
+
+impl RetiringChunks {
+    /// Whether `entity` is waiting for its GPU resources to be freed.
+    pub fn contains(&self, entity: &Entity) -> bool {
+        self.chunks.contains_key(entity)
+    }
+
+    /// Tracks the newly retiring entities and forgets those no longer retiring.
+    pub fn extract(
+        mut retiring_chunks: ResMut<Self>,
+        retiring_query: Extract<Query<Entity, With<ChunkRetiring>>>,
+    ) {
+        retiring_chunks
+            .chunks
+            .retain(|entity, _| retiring_query.contains(*entity));
+
+        for entity in retiring_query.iter() {
+            retiring_chunks
+                .chunks
+                .entry(entity)
+                .or_insert_with(|| RetiringChunk {
+                    work_done: Arc::new(AtomicBool::new(false)),
+                    tracked: false,
+                });
+        }
+    }
+
+    /// Waits on the work submitted this frame for the newly retiring entities. They were suspended
+    /// when extracted, so no later submission uses their resources.
+    pub fn track_submissions(mut retiring_chunks: ResMut<Self>, render_queue: Res<RenderQueue>) {
+        for chunk in retiring_chunks.chunks.values_mut() {
+            if chunk.tracked {
+                continue;
+            }
+            let work_done = chunk.work_done.clone();
+            render_queue.on_submitted_work_done(move || work_done.store(true, Ordering::Release));
+            chunk.tracked = true;
+        }
+    }
+
+    /// Frees the resources of the entities whose work is done, then has the main world despawn
+    /// them.
+    pub fn free_retired(world: &mut World) {
+        let retiring_chunks = world.resource::<Self>();
+        let retired: Vec<Entity> = retiring_chunks
+            .chunks
+            .iter()
+            .filter(|(_, chunk)| chunk.work_done.load(Ordering::Acquire))
+            .map(|(entity, _)| *entity)
+            .collect();
+        if retired.is_empty() {
+            return;
+        }
+
+        let free_fns = retiring_chunks.free_fns.clone();
+        for entity in retired.iter() {
+            for free in free_fns.iter() {
+                free(world, *entity);
+            }
+        }
+
+        let mut retiring_chunks = world.resource_mut::<Self>();
+        for entity in retired.iter() {
+            retiring_chunks.chunks.remove(entity);
+        }
+        let sender = world.resource::<RetiredChunkSender>();
+        for entity in retired {
+            let _ = sender.send(entity);
+        }
+    }
+}
+
+fn free_component<C: Send + Sync + 'static>(world: &mut World, entity: Entity) {
+    if let Some(mut components) = world.get_resource_mut::<VoxelMaterialComponents<C>>() {
+        components.0.remove(&entity);
+    }
+}
+
+pub trait ChunkRetirementExt {
+    /// Frees the [`VoxelMaterialComponents<C>`] of the render world of [`ChunkRetiring`] entities
+    /// once their work is done.
+    fn free_on_retire<C: Send + Sync + 'static>(&mut self) -> &mut Self;
+}
+
+impl ChunkRetirementExt for SubApp {
+    fn free_on_retire<C: Send + Sync + 'static>(&mut self) -> &mut Self {
+        self.world_mut()
+            .get_resource_or_insert_with(RetiringChunks::default)
+            .free_fns
+            .push(free_component::<C>);
+        self
+    }
+}
+
+#[derive(Resource, Deref)]
+pub struct RetiredChunkReceiver(pub Receiver<Entity>);
+
+impl RetiredChunkReceiver {
+    /// Despawns the [`ChunkRetiring`] entities whose GPU resources were freed.
+    pub fn receive(receiver: Res<Self>, mut commands: Commands) {
+        for entity in receiver.try_iter() {
+            if let Some(entity_commands) = commands.get_entity(entity) {
+                entity_commands.despawn_recursive();
+            }
+        }
+    }
+}
+
+#[derive(Resource, Deref)]
+pub struct RetiredChunkSender(pub Sender<Entity>);
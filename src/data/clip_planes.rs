@@ -0,0 +1,91 @@
+use bevy::{
+    prelude::*,
+    render::{
+        extract_component::ExtractComponent,
+        renderer::{RenderDevice, RenderQueue},
+    },
+};
+
+use crate::render::{shaders::shader_struct, voxel_mesh_compute_pipeline::DirtyMeshes};
+
+use super::{gpu_voxel_material::GpuVoxelMaterial, voxel_material::VoxelMaterialComponents};
+
+/// Most [`ClipPlane`]s applied to a volume, further planes are ignored.
+pub const MAX_CLIP_PLANES: usize = 8;
+
+/// A plane cutting away the voxels in front of it, in the voxel coordinates of the volume.
+#[derive(Clone, Copy, Debug)]
+pub struct ClipPlane {
+    /// Points towards the voxels that are cut away.
+    pub normal: Dir3,
+    /// Distance of the plane from the origin of the volume along `normal`.
+    pub distance: f32,
+}
+
+impl ClipPlane {
+    /// The plane through `point` cutting away the voxels along `normal`.
+    pub fn new(point: Vec3, normal: Dir3) -> Self {
+        Self {
+            normal,
+            distance: normal.dot(point),
+        }
+    }
+}
+
+/// Cuts away the voxels in front of any of the planes when the volume is meshed, closing the
+/// surface along the planes, to expose its interior without editing the voxels.
+#[derive(Clone, Component, ExtractComponent, Debug, Default)]
+pub struct ClipPlanes(pub Vec<ClipPlane>);
+
+shader_struct! {
+    #[derive(Clone, Copy, Default, PartialEq)]
+    pub struct ClipPlanesParams {
+        /// The normal of each plane in `xyz` and its distance in `w`.
+        planes: [Vec4; MAX_CLIP_PLANES],
+        count: u32,
+    }
+}
+
+impl ClipPlanesParams {
+    /// The planes in use, with the normal of each in `xyz` and its distance in `w`.
+    pub fn planes(&self) -> &[Vec4] {
+        &self.planes[..(self.count as usize).min(MAX_CLIP_PLANES)]
+    }
+}
+
+impl From<&ClipPlanes> for ClipPlanesParams {
+    fn from(clip_planes: &ClipPlanes) -> Self {
+        let mut params = Self::default();
+        for (plane, clip_plane) in params.planes.iter_mut().zip(&clip_planes.0) {
+            *plane = clip_plane.normal.extend(clip_plane.distance);
+        }
+        params.count = clip_planes.0.len().min(MAX_CLIP_PLANES) as u32;
+        params
+    }
+}
+
+impl ClipPlanes {
+    /// Uploads the planes of each entity into the clip planes buffer of its [`GpuVoxelMaterial`],
+    /// clearing those of entities whose [`ClipPlanes`] were removed.
+    pub fn prepare(
+        render_queue: Res<RenderQueue>,
+        render_device: Res<RenderDevice>,
+        mut gpu_voxel_materials: ResMut<VoxelMaterialComponents<GpuVoxelMaterial>>,
+        mut dirty_meshes: ResMut<DirtyMeshes>,
+        clip_planes_query: Query<&ClipPlanes>,
+    ) {
+        for (entity, gpu_voxel_material) in gpu_voxel_materials.0.iter_mut() {
+            let params = clip_planes_query
+                .get(*entity)
+                .map_or_else(|_| ClipPlanesParams::default(), ClipPlanesParams::from);
+
+            if *gpu_voxel_material.clip_planes_buffer.get() != params {
+                gpu_voxel_material.clip_planes_buffer.set(params);
+                gpu_voxel_material
+                    .clip_planes_buffer
+                    .write_buffer(render_device.as_ref(), render_queue.as_ref());
+                dirty_meshes.mark(*entity);
+            }
+        }
+    }
+}
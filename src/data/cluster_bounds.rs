@@ -0,0 +1,50 @@
+use bevy::{prelude::*, render::extract_component::ExtractComponent};
+
+use crate::render::shaders::shader_struct;
+
+/// Number of triangles of each cluster of a [`ClusterBounds`] entity.
+pub const CLUSTER_TRIANGLES: u32 = 64;
+
+/// Computes the bounds of each cluster of [`CLUSTER_TRIANGLES`] consecutive triangles of the index
+/// buffer of a volumetric entity whenever it is remeshed, for a cluster culling pass to drop those
+/// that are offscreen or face away from the camera, meshlet style. The bounds stay on the GPU, in
+/// the [`GpuClusterBounds`](super::gpu_cluster_bounds::GpuClusterBounds) of the entity, one
+/// [`ClusterBound`] per cluster the index buffer may hold. With a
+/// [`MaterialSplit`](crate::bundles::volumetric_bundle::MaterialSplit), the clusters of each
+/// material segment of the index buffer follow those of the previous one.
+#[derive(Component, Clone, Copy, Debug, Default, ExtractComponent)]
+pub struct ClusterBounds;
+
+shader_struct! {
+    #[derive(Clone, Copy, Default, PartialEq)]
+    pub struct ClusterBoundsParams {
+        /// Indices of each segment of the index buffer, the whole buffer without a material split.
+        pub segment_size: u32,
+        pub segment_count: u32,
+        pub clusters_per_segment: u32,
+    }
+}
+
+shader_struct! {
+    /// The bounds of a cluster in the local space of the entity's mesh, with the normal cone of
+    /// [meshoptimizer](https://github.com/zeux/meshoptimizer): the cluster faces away from a camera
+    /// at `camera` if `dot(normalize(cone_apex - camera), cone_axis) >= cone_cutoff`.
+    #[derive(Clone, Copy, Default)]
+    pub struct ClusterBound {
+        pub center: Vec3,
+        pub radius: f32,
+        pub cone_apex: Vec3,
+        /// Above 1 if the triangles face too many directions for the cone to cull the cluster.
+        pub cone_cutoff: f32,
+        pub cone_axis: Vec3,
+        /// 0 for the clusters past the generated triangles.
+        pub triangle_count: u32,
+    }
+}
+
+shader_struct! {
+    pub struct ClusterBoundBuffer {
+        #[size(runtime)]
+        data: Vec<ClusterBound>,
+    }
+}
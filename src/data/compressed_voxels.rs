@@ -0,0 +1,86 @@
+use bevy::{
+    prelude::*,
+    render::{extract_component::ExtractComponent, render_resource::ShaderType},
+    utils::HashMap,
+};
+
+use crate::render::shaders::shader_struct;
+
+use super::voxel::Voxel;
+
+/// Uploads the voxels of a volumetric entity palette and run-length encoded, expanding them into
+/// the voxels buffer on the GPU. Cuts the upload of mostly uniform chunks by orders of magnitude.
+#[derive(Clone, Copy, Component, ExtractComponent)]
+pub struct CompressedUpload;
+
+shader_struct! {
+    /// A run of identical voxels, ending before voxel `end`.
+    #[derive(Clone, Copy, Default)]
+    pub struct VoxelRun {
+        pub end: u32,
+        pub palette_index: u32,
+    }
+}
+
+shader_struct! {
+    #[derive(Clone)]
+    pub struct VoxelRunBuffer {
+        #[size(runtime)]
+        data: Vec<VoxelRun>,
+    }
+}
+
+shader_struct! {
+    #[derive(Clone, Copy, Default)]
+    pub struct DecompressionParams {
+        pub voxel_count: u32,
+        pub run_count: u32,
+    }
+}
+
+/// Voxels encoded as runs of indices into a palette of the distinct voxels.
+pub struct CompressedVoxels {
+    pub palette: Vec<Voxel>,
+    pub runs: Vec<VoxelRun>,
+}
+
+impl CompressedVoxels {
+    pub fn compress(voxels: &[Voxel]) -> Self {
+        let mut palette = Vec::new();
+        let mut palette_indices = HashMap::<(u32, u32, u32), u32>::default();
+        let mut runs = Vec::<VoxelRun>::new();
+
+        for (index, voxel) in voxels.iter().enumerate() {
+            let palette_index = *palette_indices
+                .entry((
+                    voxel.flags(),
+                    voxel.density().to_bits(),
+                    voxel.emissive_bits(),
+                ))
+                .or_insert_with(|| {
+                    palette.push(*voxel);
+                    palette.len() as u32 - 1
+                });
+
+            match runs.last_mut() {
+                Some(run) if run.palette_index == palette_index => run.end = index as u32 + 1,
+                _ => runs.push(VoxelRun {
+                    end: index as u32 + 1,
+                    palette_index,
+                }),
+            }
+        }
+
+        Self { palette, runs }
+    }
+
+    pub fn voxel_count(&self) -> u32 {
+        self.runs.last().map_or(0, |run| run.end)
+    }
+
+    /// The number of bytes uploaded for the compressed voxels.
+    pub fn size(&self) -> u64 {
+        self.palette.len() as u64 * Voxel::min_size().get()
+            + self.runs.len() as u64 * VoxelRun::min_size().get()
+    }
+}
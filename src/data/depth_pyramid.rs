@@ -0,0 +1,36 @@
+use bevy::{
+    prelude::*,
+    render::{extract_component::ExtractComponent, render_resource::TextureUsages},
+};
+
+/// Builds a hierarchical depth pyramid of a 3d camera's depth buffer every frame, after its main
+/// pass, for occlusion culling of chunks and screen-space effects on the terrain. The pyramid is
+/// a [`GpuDepthPyramid`](super::gpu_depth_pyramid::GpuDepthPyramid) of the view in the render
+/// world, whose first mip holds the depth buffer and every further mip the farthest depth of the
+/// texels it covers in the previous one, the smallest with Bevy's reversed z.
+///
+/// The depth texture of the camera is made bindable so that the pyramid can be built from it.
+#[derive(Component, Clone, Copy, Debug, Default, ExtractComponent)]
+pub struct DepthPyramid;
+
+impl DepthPyramid {
+    /// Adds [`TextureUsages::TEXTURE_BINDING`] to the depth texture usages of [`DepthPyramid`]
+    /// cameras, whenever they are added or their [`Camera3d`] changes.
+    #[allow(clippy::type_complexity)]
+    pub fn bind_depth_texture(
+        mut camera_query: Query<
+            &mut Camera3d,
+            (
+                With<DepthPyramid>,
+                Or<(Added<DepthPyramid>, Changed<Camera3d>)>,
+            ),
+        >,
+    ) {
+        for mut camera in camera_query.iter_mut() {
+            let usages = TextureUsages::from_bits_truncate(camera.depth_texture_usages.0);
+            if !usages.contains(TextureUsages::TEXTURE_BINDING) {
+                camera.depth_texture_usages = (usages | TextureUsages::TEXTURE_BINDING).into();
+            }
+        }
+    }
+}
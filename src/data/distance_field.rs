@@ -0,0 +1,103 @@
+use bevy::{
+    prelude::*,
+    render::{
+        extract_component::ExtractComponent,
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages},
+    },
+};
+
+use crate::{render::shaders::shader_struct, CHUNK_SZ};
+
+use super::iso_surface::DEFAULT_ISO_LEVEL;
+
+/// The format of the distance textures generated for [`DistanceFieldVolume`]s.
+pub const DISTANCE_FIELD_FORMAT: TextureFormat = TextureFormat::R32Float;
+
+/// Generates a signed distance field from the densities of a volumetric entity on the GPU, with a
+/// jump flood over the voxels on either side of its surface, e.g. for ray-marched shadows,
+/// particle collisions or steering. The field is generated again whenever the entity is remeshed.
+///
+/// Distances only reach surfaces within the chunk: voxels with no surface in the chunk are at
+/// `max_distance`, outside or inside.
+#[derive(Clone, Component, ExtractComponent, Debug)]
+pub struct DistanceFieldVolume {
+    /// The distance texture, with one `R32Float` texel per voxel holding the distance in voxels
+    /// from its centre to the surface, negative inside.
+    pub image: Handle<Image>,
+    /// Density from which a voxel is inside.
+    pub iso_level: f32,
+    /// Distance in voxels the field is clamped to.
+    pub max_distance: f32,
+}
+
+impl DistanceFieldVolume {
+    /// Creates the distance texture of a chunk.
+    pub fn new(images: &mut Assets<Image>) -> Self {
+        let size = CHUNK_SZ as u32;
+        let max_distance = (3.0f32).sqrt() * size as f32;
+
+        let mut image = Image::new_fill(
+            Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: size,
+            },
+            TextureDimension::D3,
+            &max_distance.to_le_bytes(),
+            DISTANCE_FIELD_FORMAT,
+            RenderAssetUsages::RENDER_WORLD,
+        );
+        image.texture_descriptor.usage = TextureUsages::COPY_DST
+            | TextureUsages::STORAGE_BINDING
+            | TextureUsages::TEXTURE_BINDING;
+
+        Self {
+            image: images.add(image),
+            iso_level: DEFAULT_ISO_LEVEL,
+            max_distance,
+        }
+    }
+
+    /// The sizes of the jumps of the flood, halving from half a chunk down to a voxel, with a
+    /// last jump of a voxel again fixing most of the seeds the flood misses.
+    pub fn jump_steps() -> Vec<u32> {
+        let mut steps = Vec::new();
+        let mut step = CHUNK_SZ as u32 / 2;
+        while step > 0 {
+            steps.push(step);
+            step /= 2;
+        }
+        steps.push(1);
+        steps
+    }
+}
+
+shader_struct! {
+    #[derive(Clone, Copy, Default)]
+    pub struct DistanceFieldParams {
+        iso_level: f32,
+        max_distance: f32,
+        /// Voxels between the seeds read by a jump.
+        step: u32,
+    }
+}
+
+impl DistanceFieldParams {
+    pub fn new(distance_field: &DistanceFieldVolume, step: u32) -> Self {
+        Self {
+            iso_level: distance_field.iso_level,
+            max_distance: distance_field.max_distance.max(0.0),
+            step,
+        }
+    }
+}
+
+shader_struct! {
+    /// The voxel index of the closest seed found so far for each voxel of a chunk.
+    #[derive(Clone)]
+    pub struct SeedBuffer {
+        #[size(runtime)]
+        data: Vec<u32>,
+    }
+}
@@ -0,0 +1,76 @@
+use bevy::{
+    prelude::*,
+    render::{
+        extract_component::ExtractComponent,
+        renderer::{RenderDevice, RenderQueue},
+    },
+};
+
+use crate::render::{shaders::shader_struct, voxel_mesh_compute_pipeline::DirtyMeshes};
+
+use super::{gpu_voxel_material::GpuVoxelMaterial, voxel_material::VoxelMaterialComponents};
+
+/// Tunes the vertex placement of [`MeshingAlgorithm::DualContouring`](super::meshing_algorithm::MeshingAlgorithm::DualContouring).
+/// Entities without one use the default settings.
+#[derive(Clone, Copy, Component, ExtractComponent, Debug)]
+pub struct DualContouringSettings {
+    /// Weight pulling each vertex towards the average of its cell's edge crossings. Low values
+    /// keep the sharp edges and corners of the surface, high values give smoother, more robust
+    /// vertices on noisy densities.
+    pub regularization: f32,
+}
+
+impl Default for DualContouringSettings {
+    fn default() -> Self {
+        Self {
+            regularization: 0.05,
+        }
+    }
+}
+
+shader_struct! {
+    #[derive(Clone, Copy, PartialEq)]
+    pub struct DualContouringParams {
+        regularization: f32,
+    }
+}
+
+impl Default for DualContouringParams {
+    fn default() -> Self {
+        (&DualContouringSettings::default()).into()
+    }
+}
+
+impl From<&DualContouringSettings> for DualContouringParams {
+    fn from(settings: &DualContouringSettings) -> Self {
+        Self {
+            regularization: settings.regularization.max(0.0),
+        }
+    }
+}
+
+impl DualContouringSettings {
+    /// Uploads the settings of each entity into the params buffer of its [`GpuVoxelMaterial`].
+    pub fn prepare(
+        render_queue: Res<RenderQueue>,
+        render_device: Res<RenderDevice>,
+        mut gpu_voxel_materials: ResMut<VoxelMaterialComponents<GpuVoxelMaterial>>,
+        mut dirty_meshes: ResMut<DirtyMeshes>,
+        settings_query: Query<(Entity, &DualContouringSettings)>,
+    ) {
+        for (entity, settings) in settings_query.iter() {
+            let Some(gpu_voxel_material) = gpu_voxel_materials.get_mut(&entity) else {
+                continue;
+            };
+
+            let params = settings.into();
+            if *gpu_voxel_material.dual_contouring_params_buffer.get() != params {
+                gpu_voxel_material.dual_contouring_params_buffer.set(params);
+                gpu_voxel_material
+                    .dual_contouring_params_buffer
+                    .write_buffer(render_device.as_ref(), render_queue.as_ref());
+                dirty_meshes.mark(entity);
+            }
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use bevy::{
+    prelude::*,
+    render::{extract_component::ExtractComponent, render_resource::ShaderDefVal},
+};
+
+use crate::core;
+
+/// How the vertices on the edges crossing the surface are placed between the densities of their
+/// two voxels. Entities without one use [`EdgeInterpolation::Linear`].
+#[derive(Clone, Copy, Component, ExtractComponent, Debug, Default, PartialEq, Eq, Hash)]
+pub enum EdgeInterpolation {
+    /// Where the line between the two densities crosses the iso-level.
+    #[default]
+    Linear,
+    /// Where a smoothstep between the two densities crosses the iso-level, pulling the vertices
+    /// towards the middle of the edges.
+    Smoothstep,
+    /// Where the Catmull-Rom spline through the densities before, along and after the edge
+    /// crosses the iso-level, found by bisection. Follows the curvature of volumes sampled from
+    /// signed distance fields, which linear interpolation flattens into facets. The edges of
+    /// cells refined by [`AdaptiveResolution`](super::adaptive_resolution::AdaptiveResolution)
+    /// are still interpolated linearly.
+    Bisection,
+}
+
+impl From<EdgeInterpolation> for core::mesher::EdgeInterpolation {
+    fn from(edge_interpolation: EdgeInterpolation) -> Self {
+        match edge_interpolation {
+            EdgeInterpolation::Linear => Self::Linear,
+            EdgeInterpolation::Smoothstep => Self::Smoothstep,
+            EdgeInterpolation::Bisection => Self::Bisection,
+        }
+    }
+}
+
+impl EdgeInterpolation {
+    /// The shader def selecting this interpolation in the meshing compute shader.
+    pub fn shader_def(&self) -> ShaderDefVal {
+        match self {
+            Self::Linear => "EDGE_INTERPOLATION_LINEAR".into(),
+            Self::Smoothstep => "EDGE_INTERPOLATION_SMOOTHSTEP".into(),
+            Self::Bisection => "EDGE_INTERPOLATION_BISECTION".into(),
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use bevy::{prelude::*, render::extract_component::ExtractComponent};
+
+use crate::{render::shaders::shader_struct, CHUNK_SZ};
+
+/// The erosion model simulated by an [`Erosion`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ErosionKind {
+    /// Material slides down to lower neighbours wherever the density drop exceeds the talus.
+    #[default]
+    Thermal,
+    /// Surface material is carried downhill towards the lowest neighbour, as if by rain.
+    Hydraulic,
+}
+
+/// Runs an erosion simulation over the density grid of a volumetric entity on the GPU,
+/// before the volume is meshed.
+#[derive(Clone, Copy, Component, ExtractComponent, Debug)]
+pub struct Erosion {
+    pub kind: ErosionKind,
+    /// First voxel of the simulated region.
+    pub region_min: UVec3,
+    /// Voxel past the end of the simulated region.
+    pub region_max: UVec3,
+    /// Fraction of the movable material that is moved each step.
+    pub rate: f32,
+    /// Density difference below which no material is moved.
+    pub talus: f32,
+    /// Steps simulated every frame.
+    pub steps_per_frame: u32,
+    /// Steps simulated once on the next frame, see [`Erosion::run`].
+    pub pending_steps: u32,
+}
+
+impl Default for Erosion {
+    fn default() -> Self {
+        Self {
+            kind: ErosionKind::default(),
+            region_min: UVec3::ZERO,
+            region_max: UVec3::splat(CHUNK_SZ as u32),
+            rate: 0.5,
+            talus: 0.05,
+            steps_per_frame: 0,
+            pending_steps: 0,
+        }
+    }
+}
+
+impl Erosion {
+    /// Simulates `steps` additional steps on the next frame.
+    pub fn run(&mut self, steps: u32) {
+        self.pending_steps += steps;
+    }
+
+    /// The number of steps the render world simulates this frame.
+    pub fn steps(&self) -> u32 {
+        self.steps_per_frame + self.pending_steps
+    }
+
+    /// Clears the steps requested with [`Erosion::run`] once they have been extracted.
+    pub fn clear_pending(mut erosion_query: Query<&mut Erosion>) {
+        for mut erosion in erosion_query.iter_mut() {
+            if erosion.pending_steps > 0 {
+                erosion.pending_steps = 0;
+            }
+        }
+    }
+}
+
+shader_struct! {
+    #[derive(Clone, Copy, Default)]
+    pub struct ErosionParams {
+        region_min: UVec3,
+        kind: u32,
+        region_max: UVec3,
+        rate: f32,
+        talus: f32,
+    }
+}
+
+impl From<&Erosion> for ErosionParams {
+    fn from(erosion: &Erosion) -> Self {
+        Self {
+            region_min: erosion.region_min,
+            kind: erosion.kind as u32,
+            region_max: erosion.region_max.min(UVec3::splat(CHUNK_SZ as u32)),
+            rate: erosion.rate.clamp(0.0, 1.0),
+            talus: erosion.talus,
+        }
+    }
+}
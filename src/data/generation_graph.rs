@@ -0,0 +1,297 @@
+use bevy::prelude::*;
+use ron::extensions::Extensions;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    render::shaders::shader_struct,
+    terrain::{carve, fbm, gradient_noise, layer_material, CaveSettings, TerrainStage},
+    CHUNK_SZ, CHUNK_SZ_3,
+};
+
+use super::{
+    chunk_coord::ChunkCoord,
+    voxel::Voxel,
+    voxel_world::{voxel_index, VoxelWorldConfig},
+};
+
+/// Kinds of [`GraphNode`], as matched by the graph entry point of the generation shader.
+pub const GRAPH_NOISE: u32 = 1;
+pub const GRAPH_DOMAIN_WARP: u32 = 2;
+pub const GRAPH_TERRACE: u32 = 3;
+pub const GRAPH_CAVE_CARVE: u32 = 4;
+pub const GRAPH_MATERIAL_ASSIGN: u32 = 5;
+
+/// A step of a [`GenerationGraph`]. Every voxel is evaluated on its own: the nodes move the
+/// position it is sampled at, raise or reshape the height of the surface above its column, carve
+/// it out or pick its material, and the voxel ends up solid below the surface, with a gradient of
+/// one voxel at the surface, minus what was carved. Frequencies are in cycles per voxel, heights
+/// and depths in voxels.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum GenerationNode {
+    /// Adds `offset` and fractal noise of `amplitude` to the height of the surface.
+    Noise {
+        offset: f32,
+        amplitude: f32,
+        frequency: f32,
+        octaves: u32,
+    },
+    /// Displaces the position the nodes after it sample by noise of `amplitude` in each axis,
+    /// bending their hills and caves into overhangs.
+    DomainWarp { amplitude: f32, frequency: f32 },
+    /// Flattens the height into steps of `step`, whose slopes steepen with `sharpness`; 1 leaves
+    /// the slopes as they are.
+    Terrace { step: f32, sharpness: f32 },
+    /// Carves the caves of a [`CaveCarving`](crate::terrain::CaveCarving) out of the voxels at
+    /// least `min_depth` below the height so far.
+    CaveCarve(CaveSettings),
+    /// Gives the voxel the material of its layer, each reaching down to its depth below the
+    /// surface so far. Voxels below the last layer are of its material.
+    MaterialAssign { layers: Vec<(f32, u32)> },
+}
+
+/// A chunk generator configured from data: a chain of [`GenerationNode`]s such as noise, then
+/// domain warp, terrace, cave carving and material assignment, evaluated in order for every voxel.
+/// Graphs are loaded from `.graph.ron` assets by the
+/// [`GenerationGraphPlugin`](crate::generation_graph::GenerationGraphPlugin) and regenerate their
+/// chunks when the asset is modified, so that worldgen can be iterated on without recompiling.
+///
+/// On the CPU, the graph is a [`TerrainStage`] generating the chunks of a
+/// [`TerrainPipeline`](crate::terrain::TerrainPipeline) or a
+/// [`GraphChunk`](crate::generation_graph::GraphChunk) on the task pool. On the GPU, a
+/// [`GraphVolume`] evaluates it in the generation pass. Both sample the same noise in world voxel
+/// coordinates, so neighbouring chunks line up without seams whichever generated them.
+#[derive(Asset, TypePath, Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct GenerationGraph {
+    pub seed: u32,
+    pub nodes: Vec<GenerationNode>,
+}
+
+impl GenerationGraph {
+    /// Parses a graph written in RON, where the [`CaveSettings`] of a
+    /// [`GenerationNode::CaveCarve`] may be written without their own parentheses.
+    pub fn from_ron(ron: &[u8]) -> ron::error::SpannedResult<Self> {
+        ron::Options::default()
+            .with_default_extension(Extensions::UNWRAP_VARIANT_NEWTYPES)
+            .from_bytes(ron)
+    }
+
+    /// The seed of the node at `index`, so that two nodes of the same kind differ.
+    fn node_seed(&self, index: usize) -> u32 {
+        self.seed
+            .wrapping_add((index as u32).wrapping_mul(0x9e37_79b9))
+    }
+
+    /// The voxel at `pos`, in world voxel coordinates.
+    pub fn sample(&self, pos: IVec3) -> Voxel {
+        let mut pos = pos.as_vec3();
+        let (mut height, mut carved, mut material) = (0.0, 0.0_f32, 0);
+
+        for (index, node) in self.nodes.iter().enumerate() {
+            let seed = self.node_seed(index);
+            match node {
+                GenerationNode::Noise {
+                    offset,
+                    amplitude,
+                    frequency,
+                    octaves,
+                } => {
+                    let column = Vec3::new(pos.x, 0.0, pos.z) * *frequency;
+                    height += offset + fbm(column, seed, *octaves) * amplitude;
+                }
+                GenerationNode::DomainWarp {
+                    amplitude,
+                    frequency,
+                } => {
+                    let p = pos * *frequency;
+                    let warp = Vec3::new(
+                        gradient_noise(p, seed),
+                        gradient_noise(p, seed.wrapping_add(1)),
+                        gradient_noise(p, seed.wrapping_add(2)),
+                    );
+                    pos += warp * *amplitude;
+                }
+                GenerationNode::Terrace { step, sharpness } => {
+                    if *step > 0.0 {
+                        let t = height / step;
+                        let base = t.floor();
+                        height = (base + (t - base).powf(sharpness.max(f32::EPSILON))) * step;
+                    }
+                }
+                GenerationNode::CaveCarve(caves) => {
+                    if pos.y <= height - caves.min_depth {
+                        carved = carved.max(carve(caves, pos, seed));
+                    }
+                }
+                GenerationNode::MaterialAssign { layers } => {
+                    if !layers.is_empty() {
+                        material = layer_material(layers, height - pos.y);
+                    }
+                }
+            }
+        }
+
+        Voxel::new(material, (height - pos.y).clamp(0.0, 1.0) * (1.0 - carved))
+    }
+
+    /// The voxels of the chunk whose first voxel is at `origin` in world voxel coordinates.
+    pub fn generate_chunk(&self, origin: IVec3) -> Vec<Voxel> {
+        let mut voxels = vec![Voxel::new(0, 0.0); CHUNK_SZ_3];
+        self.generate(ChunkCoord::default(), origin, &mut voxels);
+        voxels
+    }
+
+    /// The nodes and material layers of the graph as read by the generation shader. Neither is
+    /// ever empty, so that both can be bound.
+    pub fn gpu_nodes(&self) -> (Vec<GraphNode>, Vec<GraphLayer>) {
+        let mut nodes = Vec::with_capacity(self.nodes.len().max(1));
+        let mut layers = Vec::new();
+
+        for (index, node) in self.nodes.iter().enumerate() {
+            let seed = self.node_seed(index);
+            nodes.push(match node {
+                GenerationNode::Noise {
+                    offset,
+                    amplitude,
+                    frequency,
+                    octaves,
+                } => GraphNode {
+                    kind: GRAPH_NOISE,
+                    seed,
+                    octaves: *octaves,
+                    values: [*offset, *amplitude, *frequency, 0.0, 0.0],
+                    ..default()
+                },
+                GenerationNode::DomainWarp {
+                    amplitude,
+                    frequency,
+                } => GraphNode {
+                    kind: GRAPH_DOMAIN_WARP,
+                    seed,
+                    values: [*amplitude, *frequency, 0.0, 0.0, 0.0],
+                    ..default()
+                },
+                GenerationNode::Terrace { step, sharpness } => GraphNode {
+                    kind: GRAPH_TERRACE,
+                    seed,
+                    values: [*step, sharpness.max(f32::EPSILON), 0.0, 0.0, 0.0],
+                    ..default()
+                },
+                GenerationNode::CaveCarve(caves) => GraphNode {
+                    kind: GRAPH_CAVE_CARVE,
+                    seed,
+                    values: [
+                        caves.ridge_frequency,
+                        caves.ridge_threshold,
+                        caves.worm_frequency,
+                        caves.worm_radius,
+                        caves.min_depth,
+                    ],
+                    ..default()
+                },
+                GenerationNode::MaterialAssign {
+                    layers: node_layers,
+                } => {
+                    let first_layer = layers.len() as u32;
+                    layers.extend(node_layers.iter().map(|(depth, material)| GraphLayer {
+                        depth: *depth,
+                        material: *material,
+                    }));
+                    GraphNode {
+                        kind: GRAPH_MATERIAL_ASSIGN,
+                        seed,
+                        first_layer,
+                        layer_count: node_layers.len() as u32,
+                        ..default()
+                    }
+                }
+            });
+        }
+
+        if nodes.is_empty() {
+            nodes.push(GraphNode::default());
+        }
+        if layers.is_empty() {
+            layers.push(GraphLayer::default());
+        }
+        (nodes, layers)
+    }
+}
+
+impl TerrainStage for GenerationGraph {
+    /// Overwrites the voxels of the previous stages.
+    fn generate(&self, _coord: ChunkCoord, origin: IVec3, voxels: &mut [Voxel]) {
+        for z in 0..CHUNK_SZ as u32 {
+            for y in 0..CHUNK_SZ as u32 {
+                for x in 0..CHUNK_SZ as u32 {
+                    let local = UVec3::new(x, y, z);
+                    voxels[voxel_index(local)] = self.sample(origin + local.as_ivec3());
+                }
+            }
+        }
+    }
+}
+
+shader_struct! {
+    /// A [`GenerationNode`] as read by the generation shader. `values` holds the fields of the
+    /// node in the order they are declared, the [`CaveSettings`] of a cave carving node in theirs.
+    #[derive(Clone, Copy, Default)]
+    pub struct GraphNode {
+        /// One of the `GRAPH_*` kinds, nodes of kind 0 are skipped.
+        pub kind: u32,
+        pub seed: u32,
+        pub octaves: u32,
+        /// The layers of a material assignment node in the [`GraphLayerBuffer`].
+        pub first_layer: u32,
+        pub layer_count: u32,
+        pub values: [f32; 5],
+    }
+}
+
+shader_struct! {
+    #[derive(Clone)]
+    pub struct GraphNodeBuffer {
+        #[size(runtime)]
+        pub data: Vec<GraphNode>,
+    }
+}
+
+shader_struct! {
+    /// A layer of a [`GenerationNode::MaterialAssign`] as read by the generation shader.
+    #[derive(Clone, Copy, Default)]
+    pub struct GraphLayer {
+        pub depth: f32,
+        pub material: u32,
+    }
+}
+
+shader_struct! {
+    #[derive(Clone)]
+    pub struct GraphLayerBuffer {
+        #[size(runtime)]
+        pub data: Vec<GraphLayer>,
+    }
+}
+
+/// Voxels generated on the GPU from a [`GenerationGraph`] straight into the voxels buffer of a
+/// chunk, like a [`ProceduralVolume`](super::procedural_volume::ProceduralVolume): the voxels
+/// never exist on the CPU. Changing the component or modifying the graph asset generates the chunk
+/// again, nothing is generated until the asset is loaded.
+#[derive(Component, Clone, Debug, PartialEq)]
+pub struct GraphVolume {
+    pub graph: Handle<GenerationGraph>,
+    /// World voxel coordinates of the first voxel of the chunk.
+    pub origin: IVec3,
+}
+impl GraphVolume {
+    /// The volume of the chunk at `coord` generated by `graph`, placed by `config`.
+    pub fn for_chunk(
+        graph: Handle<GenerationGraph>,
+        config: &VoxelWorldConfig,
+        coord: ChunkCoord,
+    ) -> Self {
+        Self {
+            graph,
+            origin: config.chunk_to_voxel(coord),
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use bevy::{
+    prelude::*,
+    render::{
+        render_asset::RenderAssets,
+        render_resource::{BindGroup, BindGroupEntries, UniformBuffer},
+        renderer::{RenderDevice, RenderQueue},
+        texture::GpuImage,
+        Extract,
+    },
+};
+
+use crate::render::ambient_occlusion_compute_pipeline::AmbientOcclusionComputePipeline;
+
+use super::{
+    ambient_occlusion::{AmbientOcclusionParams, AmbientOcclusionVolume},
+    gpu_voxel_material::GpuVoxelMaterial,
+    voxel_material::VoxelMaterialComponents,
+};
+
+pub struct GpuAmbientOcclusion {
+    pub params_buffer: UniformBuffer<AmbientOcclusionParams>,
+    pub image: Handle<Image>,
+    pub bind_group: Option<BindGroup>,
+}
+
+impl GpuAmbientOcclusion {
+    /// Initializes the [`GpuAmbientOcclusion`] of newly added [`AmbientOcclusionVolume`]s.
+    pub fn initialize(
+        mut gpu_ambient_occlusions: ResMut<VoxelMaterialComponents<GpuAmbientOcclusion>>,
+        ambient_occlusion_query: Extract<
+            Query<(Entity, &AmbientOcclusionVolume), Added<AmbientOcclusionVolume>>,
+        >,
+    ) {
+        for (entity, ambient_occlusion) in ambient_occlusion_query.iter() {
+            gpu_ambient_occlusions.insert(
+                entity,
+                GpuAmbientOcclusion {
+                    params_buffer: UniformBuffer::default(),
+                    image: ambient_occlusion.image.clone(),
+                    bind_group: None,
+                },
+            );
+        }
+    }
+
+    /// Uploads the current [`AmbientOcclusionVolume`] settings.
+    pub fn extract(
+        render_queue: Res<RenderQueue>,
+        render_device: Res<RenderDevice>,
+        mut gpu_ambient_occlusions: ResMut<VoxelMaterialComponents<GpuAmbientOcclusion>>,
+        ambient_occlusion_query: Extract<Query<(Entity, &AmbientOcclusionVolume)>>,
+    ) {
+        for (entity, ambient_occlusion) in ambient_occlusion_query.iter() {
+            if let Some(gpu_ambient_occlusion) = gpu_ambient_occlusions.get_mut(&entity) {
+                gpu_ambient_occlusion.image = ambient_occlusion.image.clone();
+                gpu_ambient_occlusion
+                    .params_buffer
+                    .set(ambient_occlusion.into());
+                gpu_ambient_occlusion
+                    .params_buffer
+                    .write_buffer(render_device.as_ref(), render_queue.as_ref());
+            }
+        }
+    }
+
+    /// Binds the occlusion texture of each [`GpuAmbientOcclusion`] and the voxels buffer of the
+    /// entity's [`GpuVoxelMaterial`].
+    pub fn prepare(
+        render_device: Res<RenderDevice>,
+        ambient_occlusion_pipeline: Res<AmbientOcclusionComputePipeline>,
+        gpu_images: Res<RenderAssets<GpuImage>>,
+        gpu_voxel_materials: Res<VoxelMaterialComponents<GpuVoxelMaterial>>,
+        mut gpu_ambient_occlusions: ResMut<VoxelMaterialComponents<GpuAmbientOcclusion>>,
+    ) {
+        for (entity, gpu_ambient_occlusion) in gpu_ambient_occlusions.0.iter_mut() {
+            let (Some(gpu_voxel_material), Some(gpu_image)) = (
+                gpu_voxel_materials.get(entity),
+                gpu_images.get(&gpu_ambient_occlusion.image),
+            ) else {
+                continue;
+            };
+
+            gpu_ambient_occlusion.bind_group = Some(
+                render_device.create_bind_group(
+                    "GpuAmbientOcclusion::bind_group",
+                    &ambient_occlusion_pipeline.bind_group_layout,
+                    &BindGroupEntries::sequential((
+                        gpu_ambient_occlusion.params_buffer.binding().expect(
+                            "Ambient Occlusion Params Buffer should have already been uploaded to the gpu",
+                        ),
+                        gpu_voxel_material
+                            .voxels_buffer
+                            .binding()
+                            .expect("Voxels Buffer should have already been uploaded to the gpu"),
+                        &gpu_image.texture_view,
+                    )),
+                ),
+            );
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use bevy::{
+    prelude::*,
+    render::{
+        render_resource::{
+            BindGroup, BindGroupEntries, Buffer, BufferDescriptor, BufferUsages, PipelineCache,
+            ShaderType, UniformBuffer,
+        },
+        renderer::{RenderDevice, RenderQueue},
+        Extract,
+    },
+};
+
+use crate::{
+    bundles::volumetric_bundle::Volumetric,
+    channels::{ReadbackChannel, ReadbackTag, VoxelDataVersion},
+    render::{
+        chunk_analysis_compute_pipeline::ChunkAnalysisComputePipeline,
+        voxel_mesh_compute_pipeline::DirtyMeshes,
+    },
+};
+
+use super::{
+    chunk_analysis::{
+        ChunkAnalysis, ChunkAnalysisCounters, ChunkAnalysisData, ChunkAnalysisParams,
+    },
+    gpu_voxel_material::GpuVoxelMaterial,
+    voxel_material::VoxelMaterialComponents,
+};
+
+pub struct GpuChunkAnalysis {
+    pub params_buffer: UniformBuffer<ChunkAnalysisParams>,
+    pub counters_buffer: Buffer,
+    pub counters_staging_buffer: Buffer,
+    pub bind_group: Option<BindGroup>,
+    pub iso_level: f32,
+    /// Whether the voxels changed since the counters were last read back.
+    pub changed: bool,
+}
+
+impl GpuChunkAnalysis {
+    pub fn new(render_device: &RenderDevice, iso_level: f32) -> Self {
+        let size = ChunkAnalysisCounters::min_size().get();
+
+        GpuChunkAnalysis {
+            params_buffer: UniformBuffer::default(),
+            counters_buffer: render_device.create_buffer(&BufferDescriptor {
+                label: Some("chunk_analysis_counters_buffer"),
+                size,
+                usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            counters_staging_buffer: render_device.create_buffer(&BufferDescriptor {
+                label: Some("chunk_analysis_counters_staging_buffer"),
+                size,
+                usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            bind_group: None,
+            iso_level,
+            changed: true,
+        }
+    }
+
+    /// Initializes the [`GpuChunkAnalysis`] of newly added [`ChunkAnalysis`], flags those whose
+    /// iso-level changed and drops those of removed ones.
+    pub fn extract(
+        render_device: Res<RenderDevice>,
+        mut gpu_chunk_analyses: ResMut<VoxelMaterialComponents<GpuChunkAnalysis>>,
+        analysis_query: Extract<Query<(Entity, &ChunkAnalysis)>>,
+    ) {
+        gpu_chunk_analyses
+            .0
+            .retain(|entity, _| analysis_query.contains(*entity));
+
+        for (entity, chunk_analysis) in analysis_query.iter() {
+            let gpu_analysis = gpu_chunk_analyses.0.entry(entity).or_insert_with(|| {
+                GpuChunkAnalysis::new(render_device.as_ref(), chunk_analysis.iso_level)
+            });
+
+            if gpu_analysis.iso_level != chunk_analysis.iso_level {
+                gpu_analysis.iso_level = chunk_analysis.iso_level;
+                gpu_analysis.changed = true;
+            }
+        }
+    }
+
+    /// Flags the analyses of the entities remeshed this frame, whatever changed their voxels, and
+    /// binds the counters of each changed one to the voxels buffer of its [`GpuVoxelMaterial`].
+    pub fn prepare(
+        render_device: Res<RenderDevice>,
+        render_queue: Res<RenderQueue>,
+        analysis_pipeline: Res<ChunkAnalysisComputePipeline>,
+        dirty_meshes: Res<DirtyMeshes>,
+        gpu_voxel_materials: Res<VoxelMaterialComponents<GpuVoxelMaterial>>,
+        mut gpu_chunk_analyses: ResMut<VoxelMaterialComponents<GpuChunkAnalysis>>,
+    ) {
+        for (entity, gpu_analysis) in gpu_chunk_analyses.0.iter_mut() {
+            gpu_analysis.changed |= dirty_meshes.is_meshed(entity);
+            if !gpu_analysis.changed {
+                continue;
+            }
+            let Some(gpu_voxel_material) = gpu_voxel_materials.get(entity) else {
+                gpu_analysis.bind_group = None;
+                continue;
+            };
+            let Some(voxels_binding) = gpu_voxel_material.voxels_buffer.binding() else {
+                gpu_analysis.bind_group = None;
+                continue;
+            };
+
+            gpu_analysis.params_buffer.set(ChunkAnalysisParams {
+                voxel_count: gpu_voxel_material.voxels_buffer.capacity() as u32,
+                iso_level: gpu_analysis.iso_level,
+            });
+            gpu_analysis
+                .params_buffer
+                .write_buffer(render_device.as_ref(), render_queue.as_ref());
+
+            gpu_analysis.bind_group = Some(render_device.create_bind_group(
+                "GpuChunkAnalysis::bind_group",
+                &analysis_pipeline.bind_group_layout,
+                &BindGroupEntries::sequential((
+                    gpu_analysis.params_buffer.binding().expect(
+                        "Chunk Analysis Params Buffer should have already been uploaded to the gpu",
+                    ),
+                    voxels_binding,
+                    gpu_analysis.counters_buffer.as_entire_binding(),
+                )),
+            ));
+        }
+    }
+}
+
+impl GpuChunkAnalysis {
+    /// Reads back the counters computed this frame through the [`ReadbackChannel`]. Counters that
+    /// fail to map are computed again on the next frame.
+    pub fn map_and_read_buffers(
+        render_device: Res<RenderDevice>,
+        pipeline_cache: Res<PipelineCache>,
+        analysis_pipeline: Res<ChunkAnalysisComputePipeline>,
+        mut gpu_chunk_analyses: ResMut<VoxelMaterialComponents<GpuChunkAnalysis>>,
+        version_query: Query<Option<&VoxelDataVersion>, With<Volumetric>>,
+        channel: Res<ReadbackChannel<ChunkAnalysisData>>,
+    ) {
+        if pipeline_cache
+            .get_compute_pipeline(analysis_pipeline.pipeline)
+            .is_none()
+        {
+            return;
+        }
+
+        for (entity, gpu_analysis) in gpu_chunk_analyses.0.iter_mut() {
+            if !gpu_analysis.changed || gpu_analysis.bind_group.is_none() {
+                continue;
+            }
+            let Some(tag) = ReadbackTag::current(*entity, &version_query) else {
+                continue;
+            };
+
+            if channel
+                .read_buffer(&render_device, tag, &gpu_analysis.counters_staging_buffer)
+                .is_ok()
+            {
+                gpu_analysis.changed = false;
+            }
+        }
+    }
+}
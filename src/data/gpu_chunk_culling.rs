@@ -0,0 +1,257 @@
+use bevy::{
+    prelude::*,
+    render::{
+        primitives::Frustum,
+        render_resource::{
+            BindGroup, BindGroupEntries, Buffer, BufferDescriptor, BufferUsages, ShaderType,
+            UniformBuffer,
+        },
+        renderer::{RenderDevice, RenderQueue},
+        view::ExtractedView,
+        Extract,
+    },
+    utils::HashMap,
+};
+
+use crate::{
+    bundles::volumetric_bundle::Volumetric,
+    render::{
+        chunk_culling_compute_pipeline::ChunkCullingComputePipeline,
+        voxel_mesh_compute_pipeline::meshed_cells,
+    },
+};
+
+use super::{
+    chunk_culling::{ChunkCulling, ChunkCullingParams, ChunkCullingView, ChunkDrawArgs},
+    gpu_cluster_bounds::GpuClusterBounds,
+    gpu_depth_pyramid::GpuDepthPyramid,
+    gpu_virtual_volume::GpuVirtualVolume,
+    gpu_voxel_material::GpuVoxelMaterial,
+    material_split::{MaterialIndexRanges, MAX_MATERIALS},
+    voxel_material::VoxelMaterialComponents,
+};
+
+/// The transform of a volumetric entity, extracted for the [`GpuChunkCulling`] of the views.
+#[derive(Clone, Copy)]
+pub struct CulledChunkTransform(pub GlobalTransform);
+
+/// The culled draws of every meshed chunk for a [`ChunkCulling`] view, written every frame after
+/// its main pass. Nothing draws them, see [`ChunkCulling`].
+#[derive(Default)]
+pub struct GpuChunkCulling {
+    pub view: ChunkCullingView,
+    pub view_buffer: UniformBuffer<ChunkCullingView>,
+    /// Binds `view_buffer` and the [`GpuDepthPyramid`] of the view.
+    pub view_bind_group: Option<BindGroup>,
+    pub chunks: HashMap<Entity, GpuCulledChunk>,
+}
+
+/// The culled draws of a chunk for a view.
+pub struct GpuCulledChunk {
+    pub params: ChunkCullingParams,
+    pub params_buffer: UniformBuffer<ChunkCullingParams>,
+    /// A [`ChunkDrawArgs`] per draw of the index buffer of the [`GpuVoxelMaterial`] of the chunk,
+    /// laid out as indexed indirect args. Culled draws have no instance.
+    pub args_buffer: Buffer,
+    /// Whether the draws are the clusters of the
+    /// [`ClusterBounds`](super::cluster_bounds::ClusterBounds) of the chunk, rather than the
+    /// segments of its index buffer.
+    pub clusters: bool,
+    pub bind_group: Option<BindGroup>,
+}
+
+impl GpuCulledChunk {
+    pub fn new(render_device: &RenderDevice, draw_count: u32, clusters: bool) -> Self {
+        GpuCulledChunk {
+            params: ChunkCullingParams {
+                draw_count,
+                ..default()
+            },
+            params_buffer: UniformBuffer::default(),
+            args_buffer: render_device.create_buffer(&BufferDescriptor {
+                label: Some("chunk_culling_args_buffer"),
+                size: draw_count.max(1) as u64 * ChunkDrawArgs::min_size().get(),
+                usage: BufferUsages::STORAGE | BufferUsages::INDIRECT | BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            }),
+            clusters,
+            bind_group: None,
+        }
+    }
+
+    pub fn draw_count(&self) -> u32 {
+        self.params.draw_count
+    }
+}
+
+impl GpuChunkCulling {
+    /// Tracks the transforms of the volumetric entities in the render world.
+    pub fn extract(
+        mut transforms: ResMut<VoxelMaterialComponents<CulledChunkTransform>>,
+        volumetric_query: Extract<Query<(Entity, &GlobalTransform), With<Volumetric>>>,
+    ) {
+        transforms
+            .0
+            .retain(|entity, _| volumetric_query.contains(*entity));
+        for (entity, transform) in volumetric_query.iter() {
+            transforms.insert(entity, CulledChunkTransform(*transform));
+        }
+    }
+
+    /// Binds the depth pyramid of each [`ChunkCulling`] view and the output buffers of every
+    /// meshed chunk, recreating the args buffer of the chunks whose draws changed. Views whose
+    /// pyramid is not built yet cull nothing.
+    #[allow(clippy::too_many_arguments)]
+    pub fn prepare(
+        render_device: Res<RenderDevice>,
+        render_queue: Res<RenderQueue>,
+        culling_pipeline: Res<ChunkCullingComputePipeline>,
+        gpu_depth_pyramids: Res<VoxelMaterialComponents<GpuDepthPyramid>>,
+        gpu_voxel_materials: Res<VoxelMaterialComponents<GpuVoxelMaterial>>,
+        gpu_cluster_bounds: Res<VoxelMaterialComponents<GpuClusterBounds>>,
+        gpu_virtual_volumes: Res<VoxelMaterialComponents<GpuVirtualVolume>>,
+        material_index_ranges: Res<VoxelMaterialComponents<MaterialIndexRanges>>,
+        transforms: Res<VoxelMaterialComponents<CulledChunkTransform>>,
+        mut gpu_chunk_cullings: ResMut<VoxelMaterialComponents<GpuChunkCulling>>,
+        view_query: Query<(Entity, &ExtractedView, &Frustum, &ChunkCulling)>,
+    ) {
+        gpu_chunk_cullings
+            .0
+            .retain(|entity, _| view_query.contains(*entity));
+
+        for (view_entity, view, frustum, culling) in view_query.iter() {
+            let gpu_culling = gpu_chunk_cullings.0.entry(view_entity).or_default();
+            let Some(gpu_depth_pyramid) = gpu_depth_pyramids.get(&view_entity) else {
+                gpu_culling.view_bind_group = None;
+                continue;
+            };
+
+            let world_from_view = view.world_from_view.compute_matrix();
+            let clip_from_world = view
+                .clip_from_world
+                .unwrap_or_else(|| view.clip_from_view * world_from_view.inverse());
+            gpu_culling.view = ChunkCullingView::new(
+                clip_from_world,
+                frustum,
+                gpu_depth_pyramid.mip_size(0),
+                gpu_depth_pyramid.mip_count(),
+                culling.occlusion,
+            );
+            gpu_culling.view_buffer.set(gpu_culling.view);
+            gpu_culling
+                .view_buffer
+                .write_buffer(render_device.as_ref(), render_queue.as_ref());
+            gpu_culling.view_bind_group = Some(render_device.create_bind_group(
+                "GpuChunkCulling::view_bind_group",
+                &culling_pipeline.view_bind_group_layout,
+                &BindGroupEntries::sequential((
+                    gpu_culling.view_buffer.binding().expect(
+                        "Chunk Culling View Buffer should have already been uploaded to the gpu",
+                    ),
+                    &gpu_depth_pyramid.texture.default_view,
+                )),
+            ));
+
+            gpu_culling.chunks.retain(|entity, _| {
+                gpu_voxel_materials.get(entity).is_some() && transforms.get(entity).is_some()
+            });
+
+            for (entity, gpu_voxel_material) in gpu_voxel_materials.0.iter() {
+                let (Some(atomics), Some(transform)) = (
+                    gpu_voxel_material.atomics_buffer.binding(),
+                    transforms.get(entity),
+                ) else {
+                    continue;
+                };
+
+                // The clusters are drawn as their bounds were computed, the segments as the index
+                // buffer is split.
+                let cluster_bounds = gpu_cluster_bounds
+                    .get(entity)
+                    .and_then(|gpu_bounds| Some((gpu_bounds, gpu_bounds.bounds_buffer.as_ref()?)));
+                let (segment_size, segment_count, clusters_per_segment, draw_count) =
+                    match cluster_bounds {
+                        Some((gpu_bounds, _)) => (
+                            gpu_bounds.params.segment_size,
+                            gpu_bounds.params.segment_count,
+                            gpu_bounds.params.clusters_per_segment,
+                            gpu_bounds.cluster_count(),
+                        ),
+                        None => {
+                            let segment_count = match material_index_ranges.get(entity) {
+                                Some(_) => MAX_MATERIALS as u32,
+                                None => 1,
+                            };
+                            let segment_size = gpu_voxel_material.index_capacity() / segment_count;
+                            (segment_size, segment_count, 0, segment_count)
+                        }
+                    };
+
+                // The meshed cells, with a voxel of margin for the capping layer and the vertices
+                // pushed past the cells.
+                let world_from_local = transform.0.compute_matrix();
+                let size = meshed_cells(gpu_virtual_volumes.get(entity), false).as_vec3();
+                let (aabb_min, aabb_max) = (0..8).fold(
+                    (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)),
+                    |(aabb_min, aabb_max), corner| {
+                        let local = Vec3::select(
+                            BVec3::new(corner & 1 != 0, corner & 2 != 0, corner & 4 != 0),
+                            size + 1.0,
+                            Vec3::splat(-1.0),
+                        );
+                        let world = world_from_local.transform_point3(local);
+                        (aabb_min.min(world), aabb_max.max(world))
+                    },
+                );
+                let params = ChunkCullingParams {
+                    world_from_local,
+                    aabb_min,
+                    max_scale: transform.0.compute_transform().scale.abs().max_element(),
+                    aabb_max,
+                    draw_count,
+                    camera: world_from_local
+                        .inverse()
+                        .transform_point3(world_from_view.w_axis.truncate()),
+                    segment_size,
+                    segment_count,
+                    clusters_per_segment,
+                };
+
+                let clusters = cluster_bounds.is_some();
+                let gpu_chunk = gpu_culling.chunks.entry(*entity).or_insert_with(|| {
+                    GpuCulledChunk::new(render_device.as_ref(), draw_count, clusters)
+                });
+                if gpu_chunk.params.draw_count != draw_count || gpu_chunk.clusters != clusters {
+                    *gpu_chunk = GpuCulledChunk::new(render_device.as_ref(), draw_count, clusters);
+                }
+                gpu_chunk.params = params;
+                gpu_chunk.params_buffer.set(params);
+                gpu_chunk
+                    .params_buffer
+                    .write_buffer(render_device.as_ref(), render_queue.as_ref());
+
+                let params_binding = gpu_chunk.params_buffer.binding().expect(
+                    "Chunk Culling Params Buffer should have already been uploaded to the gpu",
+                );
+                let args = gpu_chunk.args_buffer.as_entire_binding();
+                gpu_chunk.bind_group = Some(match cluster_bounds {
+                    Some((_, bounds_buffer)) => render_device.create_bind_group(
+                        "GpuCulledChunk::cluster_bind_group",
+                        &culling_pipeline.cluster_bind_group_layout,
+                        &BindGroupEntries::sequential((
+                            params_binding,
+                            atomics,
+                            args,
+                            bounds_buffer.as_entire_binding(),
+                        )),
+                    ),
+                    None => render_device.create_bind_group(
+                        "GpuCulledChunk::segment_bind_group",
+                        &culling_pipeline.segment_bind_group_layout,
+                        &BindGroupEntries::sequential((params_binding, atomics, args)),
+                    ),
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use bevy::{
+    prelude::*,
+    render::{
+        render_resource::{CachedPipelineState, PipelineCache},
+        Extract,
+    },
+    utils::HashMap,
+};
+
+use crate::{
+    bundles::volumetric_bundle::Volumetric,
+    channels::GpuChunkStateSender,
+    render::{
+        slot_compaction_compute_pipeline::SlotCompactionComputePipeline,
+        voxel_mesh_compute_pipeline::{VoxelMeshPipelineId, VoxelMeshPrefixSumPipelineIds},
+    },
+};
+
+use super::{
+    chunk_retirement::ChunkRetiring,
+    gpu_compressed_voxels::GpuCompressedVoxels,
+    gpu_fixed_output_slots::GpuSlotCompaction,
+    gpu_procedural_volume::GpuProceduralVolume,
+    gpu_voxel_material::{ExtractedVoxelMaterial, GpuVoxelMaterial},
+    gpu_voxel_material_bind_group::GpuVoxelMaterialBindGroups,
+    shader_platform::ShaderPlatform,
+    voxel_material::VoxelMaterialComponents,
+};
+
+/// How far the GPU resources of a volumetric entity are from being meshed. Kept in the render
+/// world by [`GpuChunkStates`] and mirrored onto the main world entity, where it shows up in the
+/// inspector.
+#[derive(Component, Reflect, Clone, Debug, Default, PartialEq, Eq)]
+#[reflect(Component)]
+pub enum GpuChunkState {
+    /// No voxels were extracted for the entity yet.
+    #[default]
+    Uninitialized,
+    /// The voxels are extracted, waiting for their buffers, bind groups or pipeline.
+    Uploading,
+    /// The entity's buffers are bound and its pipeline is loaded, so that it can be meshed.
+    Ready,
+    /// The entity cannot be meshed until the reason is fixed.
+    Error(String),
+}
+
+impl GpuChunkState {
+    pub fn is_ready(&self) -> bool {
+        matches!(self, GpuChunkState::Ready)
+    }
+}
+
+/// The [`GpuChunkState`] of every volumetric entity in the render world. Entering an error warns
+/// once, rather than every frame the entity stays in it.
+#[derive(Resource, Default)]
+pub struct GpuChunkStates(pub HashMap<Entity, GpuChunkState>);
+
+impl GpuChunkStates {
+    pub fn get(&self, entity: &Entity) -> &GpuChunkState {
+        self.0.get(entity).unwrap_or(&GpuChunkState::Uninitialized)
+    }
+
+    pub fn is_ready(&self, entity: &Entity) -> bool {
+        self.get(entity).is_ready()
+    }
+
+    /// Moves `entity` to `state`, returning whether it changed.
+    pub fn set(&mut self, entity: Entity, state: GpuChunkState) -> bool {
+        if self.get(&entity) == &state {
+            return false;
+        }
+        if let GpuChunkState::Error(reason) = &state {
+            warn!("Volumetric entity {entity} cannot be meshed: {reason}");
+        }
+        self.0.insert(entity, state);
+        true
+    }
+
+    /// Tracks the newly extracted volumetric entities and forgets the removed ones.
+    pub fn extract(
+        mut gpu_chunk_states: ResMut<Self>,
+        sender: Res<GpuChunkStateSender>,
+        volumetric_query: Extract<Query<Entity, With<Volumetric>>>,
+    ) {
+        gpu_chunk_states
+            .0
+            .retain(|entity, _| volumetric_query.contains(*entity));
+
+        for entity in volumetric_query.iter() {
+            if !gpu_chunk_states.0.contains_key(&entity) {
+                gpu_chunk_states
+                    .0
+                    .insert(entity, GpuChunkState::Uninitialized);
+                let _ = sender.send((entity, GpuChunkState::Uninitialized));
+            }
+        }
+    }
+
+    /// Updates the state of each volumetric entity from its prepared resources, sending those that
+    /// changed to the main world. [`ChunkRetiring`] entities keep their last state while their resources
+    /// are freed.
+    #[allow(clippy::too_many_arguments)]
+    pub fn prepare(
+        mut gpu_chunk_states: ResMut<Self>,
+        sender: Res<GpuChunkStateSender>,
+        pipeline_cache: Res<PipelineCache>,
+        pipeline_ids: Res<VoxelMaterialComponents<VoxelMeshPipelineId>>,
+        prefix_sum_pipeline_ids: Res<VoxelMaterialComponents<VoxelMeshPrefixSumPipelineIds>>,
+        extracted_voxel_materials: Res<VoxelMaterialComponents<ExtractedVoxelMaterial>>,
+        gpu_compressed_voxels: Res<VoxelMaterialComponents<GpuCompressedVoxels>>,
+        gpu_procedural_volumes: Res<VoxelMaterialComponents<GpuProceduralVolume>>,
+        gpu_voxel_materials: Res<VoxelMaterialComponents<GpuVoxelMaterial>>,
+        voxel_bind_groups: Res<VoxelMaterialComponents<GpuVoxelMaterialBindGroups>>,
+        platform: Res<ShaderPlatform>,
+        slot_compaction_pipeline: Res<SlotCompactionComputePipeline>,
+        gpu_slot_compactions: Res<VoxelMaterialComponents<GpuSlotCompaction>>,
+        volumetric_query: Query<Entity, (With<Volumetric>, Without<ChunkRetiring>)>,
+    ) {
+        let compaction_loaded = slot_compaction_pipeline.is_loaded(&pipeline_cache);
+
+        for entity in volumetric_query.iter() {
+            let pipeline_state = pipeline_ids
+                .get(&entity)
+                .map(|pipeline_id| pipeline_cache.get_compute_pipeline_state(pipeline_id.0));
+            let has_voxels = gpu_voxel_materials.get(&entity).is_some();
+            // Voxels deferred by the `UploadBudget` are still waiting to be uploaded.
+            let upload_pending = extracted_voxel_materials
+                .get(&entity)
+                .is_some_and(|extracted| extracted.changed);
+            let has_bind_group = voxel_bind_groups.get(&entity).is_some();
+            // Fixed slots are meshed only once they can be compacted, and prefix sum allocations
+            // once their count and scatter passes can run too.
+            let prefix_sum_pipelines = prefix_sum_pipeline_ids.get(&entity);
+            let has_compaction = !(platform.fixed_output_slots || prefix_sum_pipelines.is_some())
+                || (compaction_loaded
+                    && gpu_slot_compactions.get(&entity).is_some()
+                    && prefix_sum_pipelines.is_none_or(|ids| ids.is_loaded(&pipeline_cache)));
+
+            let state = match pipeline_state {
+                Some(CachedPipelineState::Err(err)) => {
+                    GpuChunkState::Error(format!("its meshing pipeline failed: {err}"))
+                }
+                Some(CachedPipelineState::Ok(_))
+                    if has_voxels && has_bind_group && has_compaction && !upload_pending =>
+                {
+                    GpuChunkState::Ready
+                }
+                _ if has_voxels && !has_bind_group => {
+                    GpuChunkState::Error("its voxel buffers are not bound".to_string())
+                }
+                _ if has_voxels
+                    || extracted_voxel_materials.get(&entity).is_some()
+                    || gpu_compressed_voxels.get(&entity).is_some()
+                    || gpu_procedural_volumes.get(&entity).is_some() =>
+                {
+                    GpuChunkState::Uploading
+                }
+                // The voxels were there before, the entity stays in error until they are back.
+                _ if matches!(
+                    gpu_chunk_states.get(&entity),
+                    GpuChunkState::Ready | GpuChunkState::Error(_)
+                ) =>
+                {
+                    GpuChunkState::Error("its voxel buffers were dropped".to_string())
+                }
+                _ => GpuChunkState::Uninitialized,
+            };
+
+            if gpu_chunk_states.set(entity, state.clone()) {
+                let _ = sender.send((entity, state));
+            }
+        }
+    }
+}
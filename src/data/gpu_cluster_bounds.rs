@@ -0,0 +1,130 @@
+use bevy::{
+    prelude::*,
+    render::{
+        render_resource::{
+            BindGroup, BindGroupEntries, Buffer, BufferDescriptor, BufferUsages, ShaderType,
+            UniformBuffer,
+        },
+        renderer::{RenderDevice, RenderQueue},
+        Extract,
+    },
+};
+
+use crate::{
+    bundles::volumetric_bundle::Volumetric,
+    render::{
+        cluster_bounds_compute_pipeline::ClusterBoundsComputePipeline,
+        voxel_mesh_compute_pipeline::DirtyMeshes,
+    },
+};
+
+use super::{
+    cluster_bounds::{ClusterBound, ClusterBounds, ClusterBoundsParams, CLUSTER_TRIANGLES},
+    gpu_voxel_material::GpuVoxelMaterial,
+    material_split::{MaterialIndexRanges, MAX_MATERIALS},
+    voxel_material::VoxelMaterialComponents,
+};
+
+#[derive(Default)]
+pub struct GpuClusterBounds {
+    pub params: ClusterBoundsParams,
+    pub params_buffer: UniformBuffer<ClusterBoundsParams>,
+    /// The [`ClusterBound`] of each cluster, in the order of the index buffer.
+    pub bounds_buffer: Option<Buffer>,
+    pub bind_group: Option<BindGroup>,
+}
+
+impl GpuClusterBounds {
+    /// Number of clusters the index buffer may hold, and of bounds in `bounds_buffer`.
+    pub fn cluster_count(&self) -> u32 {
+        self.params.segment_count * self.params.clusters_per_segment
+    }
+
+    /// Tracks the volumetric entities with [`ClusterBounds`] in the render world.
+    #[allow(clippy::type_complexity)]
+    pub fn extract(
+        mut gpu_cluster_bounds: ResMut<VoxelMaterialComponents<GpuClusterBounds>>,
+        bounds_query: Extract<Query<Entity, (With<Volumetric>, With<ClusterBounds>)>>,
+    ) {
+        gpu_cluster_bounds
+            .0
+            .retain(|entity, _| bounds_query.contains(*entity));
+        for entity in bounds_query.iter() {
+            gpu_cluster_bounds.0.entry(entity).or_default();
+        }
+    }
+
+    /// Binds the output buffers of the entities remeshed this frame, recreating their bounds
+    /// buffer whenever their index buffer was resized or split.
+    pub fn prepare(
+        render_device: Res<RenderDevice>,
+        render_queue: Res<RenderQueue>,
+        bounds_pipeline: Res<ClusterBoundsComputePipeline>,
+        dirty_meshes: Res<DirtyMeshes>,
+        gpu_voxel_materials: Res<VoxelMaterialComponents<GpuVoxelMaterial>>,
+        material_index_ranges: Res<VoxelMaterialComponents<MaterialIndexRanges>>,
+        mut gpu_cluster_bounds: ResMut<VoxelMaterialComponents<GpuClusterBounds>>,
+    ) {
+        for (entity, gpu_bounds) in gpu_cluster_bounds.0.iter_mut() {
+            if !dirty_meshes.is_meshed(entity) {
+                continue;
+            }
+            let Some(gpu_voxel_material) = gpu_voxel_materials.get(entity) else {
+                gpu_bounds.bind_group = None;
+                continue;
+            };
+            let (Some(vertices), Some(indices), Some(atomics)) = (
+                gpu_voxel_material.vertices_buffer.binding(),
+                gpu_voxel_material.indices_buffer.binding(),
+                gpu_voxel_material.atomics_buffer.binding(),
+            ) else {
+                gpu_bounds.bind_group = None;
+                continue;
+            };
+
+            let segment_count = match material_index_ranges.get(entity) {
+                Some(_) => MAX_MATERIALS as u32,
+                None => 1,
+            };
+            let segment_size = gpu_voxel_material.index_capacity() / segment_count;
+            let params = ClusterBoundsParams {
+                segment_size,
+                segment_count,
+                clusters_per_segment: segment_size.div_ceil(CLUSTER_TRIANGLES * 3).max(1),
+            };
+
+            if gpu_bounds.bounds_buffer.is_none() || gpu_bounds.params != params {
+                gpu_bounds.params = params;
+                gpu_bounds.bounds_buffer = Some(render_device.create_buffer(&BufferDescriptor {
+                    label: Some("cluster_bounds_buffer"),
+                    size: gpu_bounds.cluster_count() as u64 * ClusterBound::min_size().get(),
+                    usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC,
+                    mapped_at_creation: false,
+                }));
+            }
+            let bounds_buffer = gpu_bounds
+                .bounds_buffer
+                .as_ref()
+                .expect("the bounds buffer was just created");
+
+            gpu_bounds.params_buffer.set(params);
+            gpu_bounds
+                .params_buffer
+                .write_buffer(render_device.as_ref(), render_queue.as_ref());
+
+            gpu_bounds.bind_group = Some(render_device.create_bind_group(
+                "GpuClusterBounds::bind_group",
+                &bounds_pipeline.bind_group_layout,
+                &BindGroupEntries::sequential((
+                    gpu_bounds.params_buffer.binding().expect(
+                        "Cluster Bounds Params Buffer should have already been uploaded to the gpu",
+                    ),
+                    vertices,
+                    indices,
+                    atomics,
+                    bounds_buffer.as_entire_binding(),
+                )),
+            ));
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use bevy::{
+    prelude::*,
+    render::{
+        render_resource::{
+            BindGroup, BindGroupEntries, BufferUsages, BufferVec, PipelineCache, UniformBuffer,
+        },
+        renderer::{RenderDevice, RenderQueue},
+    },
+};
+
+use crate::render::{
+    voxel_decompression_compute_pipeline::VoxelDecompressionComputePipeline,
+    voxel_mesh_compute_pipeline::DirtyMeshes,
+};
+
+use super::{
+    compressed_voxels::{CompressedUpload, CompressedVoxels, DecompressionParams, VoxelRun},
+    gpu_voxel_material::{ExtractedVoxelMaterial, GpuVoxelMaterial},
+    mesh_buffer_sizing::MeshBufferSizing,
+    meshing_algorithm::MeshingAlgorithm,
+    voxel::Voxel,
+    voxel_material::VoxelMaterialComponents,
+};
+
+/// The compressed voxels of a [`CompressedUpload`] entity, expanded into the voxels buffer of its
+/// [`GpuVoxelMaterial`] by the decompression pass.
+pub struct GpuCompressedVoxels {
+    pub palette_buffer: BufferVec<Voxel>,
+    pub runs_buffer: BufferVec<VoxelRun>,
+    pub params_buffer: UniformBuffer<DecompressionParams>,
+    pub bind_group: Option<BindGroup>,
+    /// Voxels to expand this frame, 0 if nothing was uploaded.
+    pub voxel_count: u32,
+}
+
+impl Default for GpuCompressedVoxels {
+    fn default() -> Self {
+        Self {
+            palette_buffer: BufferVec::new(BufferUsages::STORAGE),
+            runs_buffer: BufferVec::new(BufferUsages::STORAGE),
+            params_buffer: UniformBuffer::default(),
+            bind_group: None,
+            voxel_count: 0,
+        }
+    }
+}
+
+impl GpuCompressedVoxels {
+    /// Compresses and uploads the changed [`ExtractedVoxelMaterial`]s of [`CompressedUpload`]
+    /// entities in place of [`GpuVoxelMaterial::prepare`], creating their [`GpuVoxelMaterial`]
+    /// without uploading any voxels. Until the decompression pipeline is compiled, which the
+    /// voxels of the first frames would otherwise wait on forever, they are uploaded as is.
+    #[allow(clippy::too_many_arguments)]
+    pub fn prepare(
+        render_queue: Res<RenderQueue>,
+        render_device: Res<RenderDevice>,
+        decompression_pipeline: Res<VoxelDecompressionComputePipeline>,
+        pipeline_cache: Res<PipelineCache>,
+        compressed_query: Query<Entity, With<CompressedUpload>>,
+        mut extracted_voxel_materials: ResMut<VoxelMaterialComponents<ExtractedVoxelMaterial>>,
+        mut gpu_voxel_materials: ResMut<VoxelMaterialComponents<GpuVoxelMaterial>>,
+        mut gpu_compressed_voxels: ResMut<VoxelMaterialComponents<GpuCompressedVoxels>>,
+        mut dirty_meshes: ResMut<DirtyMeshes>,
+        sizing: Res<MeshBufferSizing>,
+        algorithm_query: Query<&MeshingAlgorithm>,
+    ) {
+        for gpu_compressed in gpu_compressed_voxels.0.values_mut() {
+            gpu_compressed.voxel_count = 0;
+        }
+        let decompression_ready = pipeline_cache
+            .get_compute_pipeline(decompression_pipeline.pipeline)
+            .is_some();
+
+        for entity in compressed_query.iter() {
+            let Some(extracted) = extracted_voxel_materials.get_mut(&entity) else {
+                continue;
+            };
+            if !extracted.changed {
+                continue;
+            }
+            extracted.changed = false;
+            extracted.dirty = None;
+            dirty_meshes.mark(entity);
+
+            let voxel_capacity = extracted.chunk_size as usize;
+            if gpu_voxel_materials
+                .get(&entity)
+                .is_none_or(|gpu| gpu.voxels_buffer.capacity() != voxel_capacity)
+            {
+                gpu_voxel_materials.insert(
+                    entity,
+                    GpuVoxelMaterial::from_voxels(
+                        render_device.as_ref(),
+                        render_queue.as_ref(),
+                        &[],
+                        voxel_capacity,
+                        sizing.capacities(
+                            algorithm_query.get(entity).copied().unwrap_or_default(),
+                            voxel_capacity,
+                        ),
+                    ),
+                );
+            }
+            if !decompression_ready {
+                let Some(gpu_voxel_material) = gpu_voxel_materials.get_mut(&entity) else {
+                    continue;
+                };
+                gpu_voxel_material.voxels_buffer.clear();
+                for voxel in &extracted.voxels {
+                    gpu_voxel_material.voxels_buffer.push(*voxel);
+                }
+                gpu_voxel_material
+                    .voxels_buffer
+                    .write_buffer(render_device.as_ref(), render_queue.as_ref());
+                continue;
+            }
+
+            let Some(voxels_buffer) = gpu_voxel_materials
+                .get(&entity)
+                .and_then(|gpu| gpu.voxels_buffer.binding())
+            else {
+                continue;
+            };
+
+            let compressed = CompressedVoxels::compress(&extracted.voxels);
+            if compressed.runs.is_empty() {
+                continue;
+            }
+
+            let gpu_compressed = gpu_compressed_voxels.0.entry(entity).or_default();
+
+            gpu_compressed.palette_buffer.clear();
+            for voxel in &compressed.palette {
+                gpu_compressed.palette_buffer.push(*voxel);
+            }
+            gpu_compressed
+                .palette_buffer
+                .write_buffer(render_device.as_ref(), render_queue.as_ref());
+
+            gpu_compressed.runs_buffer.clear();
+            for run in &compressed.runs {
+                gpu_compressed.runs_buffer.push(*run);
+            }
+            gpu_compressed
+                .runs_buffer
+                .write_buffer(render_device.as_ref(), render_queue.as_ref());
+
+            gpu_compressed.voxel_count = compressed.voxel_count().min(voxel_capacity as u32);
+            gpu_compressed.params_buffer.set(DecompressionParams {
+                voxel_count: gpu_compressed.voxel_count,
+                run_count: compressed.runs.len() as u32,
+            });
+            gpu_compressed
+                .params_buffer
+                .write_buffer(render_device.as_ref(), render_queue.as_ref());
+
+            gpu_compressed.bind_group = Some(
+                render_device.create_bind_group(
+                    "GpuCompressedVoxels::bind_group",
+                    &decompression_pipeline.bind_group_layout,
+                    &BindGroupEntries::sequential((
+                        gpu_compressed
+                            .params_buffer
+                            .binding()
+                            .expect("Decompression Params Buffer should have been uploaded"),
+                        gpu_compressed
+                            .palette_buffer
+                            .binding()
+                            .expect("Palette Buffer should have been uploaded"),
+                        gpu_compressed
+                            .runs_buffer
+                            .binding()
+                            .expect("Runs Buffer should have been uploaded"),
+                        voxels_buffer,
+                    )),
+                ),
+            );
+        }
+    }
+}
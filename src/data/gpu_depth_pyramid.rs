@@ -0,0 +1,117 @@
+use bevy::{
+    prelude::*,
+    render::{
+        render_resource::{
+            BindGroup, BindGroupEntries, Extent3d, TextureDescriptor, TextureDimension,
+            TextureUsages, TextureView, TextureViewDescriptor,
+        },
+        renderer::RenderDevice,
+        texture::{CachedTexture, TextureCache},
+        view::ViewDepthTexture,
+    },
+};
+
+use crate::render::depth_pyramid_compute_pipeline::{
+    DepthPyramidComputePipeline, DEPTH_PYRAMID_FORMAT,
+};
+
+use super::{depth_pyramid::DepthPyramid, voxel_material::VoxelMaterialComponents};
+
+/// The hierarchical depth pyramid of a [`DepthPyramid`] view, built every frame after its main
+/// pass. Bind `texture.default_view` to read every mip, e.g. with `textureLoad` for occlusion
+/// culling, or one of `mip_views`.
+pub struct GpuDepthPyramid {
+    /// A [`DEPTH_PYRAMID_FORMAT`] texture the size of the depth texture, with a full mip chain.
+    pub texture: CachedTexture,
+    pub mip_views: Vec<TextureView>,
+    /// Whether the depth texture of the view is multisampled, the first mip then holding the
+    /// farthest depth of its samples.
+    pub multisampled: bool,
+    /// Bind the source and destination of each mip, the depth texture for the first one.
+    pub bind_groups: Vec<BindGroup>,
+}
+
+impl GpuDepthPyramid {
+    pub fn mip_count(&self) -> u32 {
+        self.texture.texture.mip_level_count()
+    }
+
+    /// The size of `mip`, half the size of the previous one rounded down.
+    pub fn mip_size(&self, mip: u32) -> UVec2 {
+        let size = self.texture.texture.size();
+        UVec2::new(size.width >> mip, size.height >> mip).max(UVec2::ONE)
+    }
+
+    /// Creates the pyramid of each [`DepthPyramid`] view for its depth texture of this frame,
+    /// dropping those of removed views.
+    pub fn prepare(
+        render_device: Res<RenderDevice>,
+        mut texture_cache: ResMut<TextureCache>,
+        pyramid_pipeline: Res<DepthPyramidComputePipeline>,
+        mut gpu_depth_pyramids: ResMut<VoxelMaterialComponents<GpuDepthPyramid>>,
+        view_query: Query<(Entity, &ViewDepthTexture), With<DepthPyramid>>,
+    ) {
+        gpu_depth_pyramids
+            .0
+            .retain(|entity, _| view_query.contains(*entity));
+
+        for (entity, view_depth_texture) in view_query.iter() {
+            let size = view_depth_texture.texture.size();
+            let texture = texture_cache.get(
+                &render_device,
+                TextureDescriptor {
+                    label: Some("depth_pyramid_texture"),
+                    size: Extent3d {
+                        depth_or_array_layers: 1,
+                        ..size
+                    },
+                    mip_level_count: size.width.max(size.height).max(1).ilog2() + 1,
+                    sample_count: 1,
+                    dimension: TextureDimension::D2,
+                    format: DEPTH_PYRAMID_FORMAT,
+                    usage: TextureUsages::STORAGE_BINDING | TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[],
+                },
+            );
+
+            let mip_views = (0..texture.texture.mip_level_count())
+                .map(|mip| {
+                    texture.texture.create_view(&TextureViewDescriptor {
+                        label: Some("depth_pyramid_mip_view"),
+                        base_mip_level: mip,
+                        mip_level_count: Some(1),
+                        ..default()
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            let multisampled = view_depth_texture.texture.sample_count() > 1;
+            let bind_groups = mip_views
+                .iter()
+                .enumerate()
+                .map(|(mip, mip_view)| match mip {
+                    0 => render_device.create_bind_group(
+                        "GpuDepthPyramid::depth_bind_group",
+                        pyramid_pipeline.depth_layout(multisampled),
+                        &BindGroupEntries::sequential((view_depth_texture.view(), mip_view)),
+                    ),
+                    _ => render_device.create_bind_group(
+                        "GpuDepthPyramid::downsample_bind_group",
+                        &pyramid_pipeline.downsample_bind_group_layout,
+                        &BindGroupEntries::sequential((&mip_views[mip - 1], mip_view)),
+                    ),
+                })
+                .collect();
+
+            gpu_depth_pyramids.insert(
+                entity,
+                GpuDepthPyramid {
+                    texture,
+                    mip_views,
+                    multisampled,
+                    bind_groups,
+                },
+            );
+        }
+    }
+}
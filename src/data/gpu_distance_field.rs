@@ -0,0 +1,170 @@
+use bevy::{
+    prelude::*,
+    render::{
+        render_asset::RenderAssets,
+        render_resource::{
+            BindGroup, BindGroupEntries, Buffer, BufferDescriptor, BufferId, BufferUsages,
+            ShaderType, UniformBuffer,
+        },
+        renderer::{RenderDevice, RenderQueue},
+        texture::GpuImage,
+        Extract,
+    },
+};
+
+use crate::{render::distance_field_compute_pipeline::DistanceFieldComputePipeline, CHUNK_SZ_3};
+
+use super::{
+    distance_field::{DistanceFieldParams, DistanceFieldVolume},
+    gpu_voxel_material::GpuVoxelMaterial,
+    voxel_material::VoxelMaterialComponents,
+};
+
+pub struct GpuDistanceField {
+    /// The params of each pass: seeding, then one per jump step, then resolving the distances.
+    pub params_buffers: Vec<UniformBuffer<DistanceFieldParams>>,
+    /// The seeds of the flood, read from one and written to the other by each pass.
+    pub seed_buffers: [Buffer; 2],
+    pub image: Handle<Image>,
+    /// The bind group of each pass, in the order of `params_buffers`.
+    pub bind_groups: Vec<BindGroup>,
+    /// Generates the field this frame even if the entity is not remeshed, e.g. once its settings
+    /// changed or its bind groups were created.
+    pub regenerate: bool,
+    /// The voxels buffer the bind groups bind, to bind them again once it is reallocated.
+    voxels_buffer: Option<BufferId>,
+}
+
+impl GpuDistanceField {
+    pub fn new(render_device: &RenderDevice, distance_field: &DistanceFieldVolume) -> Self {
+        let seed_buffer = |label| {
+            render_device.create_buffer(&BufferDescriptor {
+                label: Some(label),
+                size: u32::min_size().get() * CHUNK_SZ_3 as u64,
+                usage: BufferUsages::STORAGE,
+                mapped_at_creation: false,
+            })
+        };
+
+        GpuDistanceField {
+            params_buffers: Vec::new(),
+            seed_buffers: [
+                seed_buffer("distance_field_seeds_a"),
+                seed_buffer("distance_field_seeds_b"),
+            ],
+            image: distance_field.image.clone(),
+            bind_groups: Vec::new(),
+            regenerate: true,
+            voxels_buffer: None,
+        }
+    }
+
+    /// Initializes the [`GpuDistanceField`] of newly added [`DistanceFieldVolume`]s.
+    pub fn initialize(
+        render_device: Res<RenderDevice>,
+        mut gpu_distance_fields: ResMut<VoxelMaterialComponents<GpuDistanceField>>,
+        distance_field_query: Extract<
+            Query<(Entity, &DistanceFieldVolume), Added<DistanceFieldVolume>>,
+        >,
+    ) {
+        for (entity, distance_field) in distance_field_query.iter() {
+            gpu_distance_fields.insert(
+                entity,
+                GpuDistanceField::new(render_device.as_ref(), distance_field),
+            );
+        }
+    }
+
+    /// Uploads the params of each pass of the changed [`DistanceFieldVolume`]s, generating them
+    /// again.
+    pub fn extract(
+        render_queue: Res<RenderQueue>,
+        render_device: Res<RenderDevice>,
+        mut gpu_distance_fields: ResMut<VoxelMaterialComponents<GpuDistanceField>>,
+        distance_field_query: Extract<Query<(Entity, Ref<DistanceFieldVolume>)>>,
+    ) {
+        for (entity, distance_field) in distance_field_query.iter() {
+            let Some(gpu_distance_field) = gpu_distance_fields.get_mut(&entity) else {
+                continue;
+            };
+            gpu_distance_field.regenerate = false;
+            if !distance_field.is_changed() {
+                continue;
+            }
+
+            if gpu_distance_field.image != distance_field.image {
+                gpu_distance_field.image = distance_field.image.clone();
+                gpu_distance_field.bind_groups.clear();
+            }
+            let steps = DistanceFieldVolume::jump_steps();
+            let passes = std::iter::once(0).chain(steps).chain(std::iter::once(0));
+            gpu_distance_field.params_buffers = passes
+                .map(|step| {
+                    let mut params_buffer =
+                        UniformBuffer::from(DistanceFieldParams::new(&distance_field, step));
+                    params_buffer.write_buffer(render_device.as_ref(), render_queue.as_ref());
+                    params_buffer
+                })
+                .collect();
+            gpu_distance_field.bind_groups.clear();
+            gpu_distance_field.regenerate = true;
+        }
+    }
+
+    /// Binds the voxels buffer of the entity's [`GpuVoxelMaterial`], the seed buffers and the
+    /// distance texture of each pass, ping-ponging between the seed buffers, again whenever the
+    /// voxels buffer is reallocated.
+    pub fn prepare(
+        render_device: Res<RenderDevice>,
+        distance_field_pipeline: Res<DistanceFieldComputePipeline>,
+        gpu_images: Res<RenderAssets<GpuImage>>,
+        gpu_voxel_materials: Res<VoxelMaterialComponents<GpuVoxelMaterial>>,
+        mut gpu_distance_fields: ResMut<VoxelMaterialComponents<GpuDistanceField>>,
+    ) {
+        for (entity, gpu_distance_field) in gpu_distance_fields.0.iter_mut() {
+            let (Some(voxels_buffer), Some(gpu_image)) = (
+                gpu_voxel_materials
+                    .get(entity)
+                    .and_then(|gpu_voxel_material| gpu_voxel_material.voxels_buffer.buffer()),
+                gpu_images.get(&gpu_distance_field.image),
+            ) else {
+                continue;
+            };
+            if !gpu_distance_field.bind_groups.is_empty()
+                && gpu_distance_field.voxels_buffer == Some(voxels_buffer.id())
+            {
+                continue;
+            }
+
+            let [seeds_a, seeds_b] = &gpu_distance_field.seed_buffers;
+            gpu_distance_field.bind_groups = gpu_distance_field
+                .params_buffers
+                .iter()
+                .enumerate()
+                .map(|(pass, params_buffer)| {
+                    // The seeding pass writes to the first buffer, each later pass reads the
+                    // buffer written by the pass before it.
+                    let (in_seeds, out_seeds) = match pass % 2 {
+                        0 => (seeds_b, seeds_a),
+                        _ => (seeds_a, seeds_b),
+                    };
+                    render_device.create_bind_group(
+                        "GpuDistanceField::bind_group",
+                        &distance_field_pipeline.bind_group_layout,
+                        &BindGroupEntries::sequential((
+                            params_buffer.binding().expect(
+                                "Distance Field Params Buffer should have already been uploaded to the gpu",
+                            ),
+                            voxels_buffer.as_entire_binding(),
+                            in_seeds.as_entire_binding(),
+                            out_seeds.as_entire_binding(),
+                            &gpu_image.texture_view,
+                        )),
+                    )
+                })
+                .collect();
+            gpu_distance_field.voxels_buffer = Some(voxels_buffer.id());
+            gpu_distance_field.regenerate = true;
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use bevy::{
+    prelude::*,
+    render::{
+        render_resource::{
+            BindGroup, BindGroupEntries, Buffer, BufferDescriptor, BufferUsages, ShaderType,
+            UniformBuffer,
+        },
+        renderer::{RenderDevice, RenderQueue},
+        Extract,
+    },
+};
+
+use crate::render::{
+    erosion_compute_pipeline::ErosionComputePipeline, voxel_mesh_compute_pipeline::DirtyMeshes,
+};
+
+use super::{
+    erosion::{Erosion, ErosionParams},
+    gpu_voxel_material::GpuVoxelMaterial,
+    voxel::Voxel,
+    voxel_material::{VoxelMaterial, VoxelMaterialComponents},
+};
+
+pub struct GpuErosion {
+    pub params_buffer: UniformBuffer<ErosionParams>,
+    /// Receives the eroded voxels of a step before they are copied back into the voxels buffer.
+    pub eroded_voxels_buffer: Buffer,
+    pub steps: u32,
+    pub bind_group: Option<BindGroup>,
+}
+
+impl GpuErosion {
+    pub fn new(render_device: &RenderDevice, voxel_material: &VoxelMaterial) -> Self {
+        let eroded_voxels_buffer = render_device.create_buffer(&BufferDescriptor {
+            label: Some("eroded_voxels_buffer"),
+            size: Voxel::min_size().get() * voxel_material.voxels().len() as u64,
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        GpuErosion {
+            params_buffer: UniformBuffer::default(),
+            eroded_voxels_buffer,
+            steps: 0,
+            bind_group: None,
+        }
+    }
+
+    /// Initializes the [`GpuErosion`] of newly added [`Erosion`]s.
+    pub fn initialize(
+        render_device: Res<RenderDevice>,
+        mut gpu_erosions: ResMut<VoxelMaterialComponents<GpuErosion>>,
+        erosion_query: Extract<Query<(Entity, &VoxelMaterial), Added<Erosion>>>,
+    ) {
+        for (entity, voxel_material) in erosion_query.iter() {
+            gpu_erosions.insert(
+                entity,
+                GpuErosion::new(render_device.as_ref(), voxel_material),
+            );
+        }
+    }
+
+    /// Uploads the current [`Erosion`] settings and the number of steps to simulate this frame.
+    pub fn extract(
+        render_queue: Res<RenderQueue>,
+        render_device: Res<RenderDevice>,
+        mut gpu_erosions: ResMut<VoxelMaterialComponents<GpuErosion>>,
+        mut dirty_meshes: ResMut<DirtyMeshes>,
+        erosion_query: Extract<Query<(Entity, &Erosion)>>,
+    ) {
+        for (entity, erosion) in erosion_query.iter() {
+            if let Some(gpu_erosion) = gpu_erosions.get_mut(&entity) {
+                gpu_erosion.steps = erosion.steps();
+                if gpu_erosion.steps > 0 {
+                    dirty_meshes.mark(entity);
+                }
+                gpu_erosion.params_buffer.set(erosion.into());
+                gpu_erosion
+                    .params_buffer
+                    .write_buffer(render_device.as_ref(), render_queue.as_ref());
+            }
+        }
+    }
+
+    /// Binds the [`GpuErosion`] buffers to the voxels buffer of the entity's [`GpuVoxelMaterial`].
+    pub fn prepare(
+        render_device: Res<RenderDevice>,
+        erosion_pipeline: Res<ErosionComputePipeline>,
+        gpu_voxel_materials: Res<VoxelMaterialComponents<GpuVoxelMaterial>>,
+        mut gpu_erosions: ResMut<VoxelMaterialComponents<GpuErosion>>,
+    ) {
+        for (entity, gpu_erosion) in gpu_erosions.0.iter_mut() {
+            let Some(gpu_voxel_material) = gpu_voxel_materials.get(entity) else {
+                continue;
+            };
+
+            gpu_erosion.bind_group = Some(
+                render_device.create_bind_group(
+                    "GpuErosion::bind_group",
+                    &erosion_pipeline.bind_group_layout,
+                    &BindGroupEntries::sequential((
+                        gpu_erosion.params_buffer.binding().expect(
+                            "Erosion Params Buffer should have already been uploaded to the gpu",
+                        ),
+                        gpu_voxel_material
+                            .voxels_buffer
+                            .binding()
+                            .expect("Voxels Buffer should have already been uploaded to the gpu"),
+                        gpu_erosion.eroded_voxels_buffer.as_entire_binding(),
+                    )),
+                ),
+            );
+        }
+    }
+}
@@ -0,0 +1,308 @@
+use bevy::{
+    prelude::*,
+    render::{
+        render_resource::{
+            BindGroup, BindGroupEntries, Buffer, BufferDescriptor, BufferUsages, ShaderType,
+            UniformBuffer,
+        },
+        renderer::{RenderDevice, RenderQueue},
+    },
+};
+
+use crate::{
+    bundles::volumetric_bundle::{MeshCapping, Volumetric},
+    render::{
+        slot_compaction_compute_pipeline::SlotCompactionComputePipeline,
+        voxel_mesh_compute_pipeline::{meshed_cells, VertexBuffer, VoxelMeshPrefixSumPipelineIds},
+    },
+};
+
+use super::{
+    gpu_virtual_volume::GpuVirtualVolume,
+    gpu_voxel_material::GpuVoxelMaterial,
+    meshing_algorithm::MeshingAlgorithm,
+    normal_encoding::EncodedNormal,
+    shader_platform::{CellSlot, ShaderPlatform, SlotCompactionParams, SLOT_BLOCK_CELLS},
+    voxel_material::VoxelMaterialComponents,
+};
+
+/// The buffers of the [`GpuFixedOutputSlots`], sized for `cells` cells of `vertices` vertices and
+/// `indices` indices in all.
+pub struct FixedSlotBuffers {
+    pub cells: usize,
+    pub vertices: usize,
+    pub indices: usize,
+    pub cell_slots_buffer: Buffer,
+    /// Where each block of [`SLOT_BLOCK_CELLS`] cells starts once compacted.
+    pub blocks_buffer: Buffer,
+    pub vertices_buffer: Buffer,
+    pub normals_buffer: Buffer,
+    pub uvs_buffer: Buffer,
+    pub tangents_buffer: Buffer,
+    pub emissives_buffer: Buffer,
+    pub indices_buffer: Buffer,
+}
+
+impl FixedSlotBuffers {
+    fn new(render_device: &RenderDevice, cells: usize, vertices: usize, indices: usize) -> Self {
+        let vec4 = std::mem::size_of::<Vec4>() as u64;
+        let vec2 = std::mem::size_of::<Vec2>() as u64;
+        let normal = std::mem::size_of::<EncodedNormal>() as u64;
+        let u32 = std::mem::size_of::<u32>() as u64;
+        let blocks = cells.div_ceil(SLOT_BLOCK_CELLS as usize) as u64;
+
+        // The cell slots are copied for the readback of changed regions, see
+        // `GpuRawMeshData::changed_cells`.
+        let buffer = |label: &str, size: u64| {
+            render_device.create_buffer(&BufferDescriptor {
+                label: Some(label),
+                size: size.max(4),
+                usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        };
+
+        Self {
+            cells,
+            vertices,
+            indices,
+            cell_slots_buffer: buffer(
+                "fixed_slots_cell_slots_buffer",
+                cells as u64 * CellSlot::min_size().get(),
+            ),
+            blocks_buffer: buffer("fixed_slots_blocks_buffer", blocks * u32 * 2),
+            vertices_buffer: buffer(
+                "fixed_slots_vertices_buffer",
+                vertices as u64 * VertexBuffer::min_size().get(),
+            ),
+            normals_buffer: buffer("fixed_slots_normals_buffer", vertices as u64 * normal),
+            uvs_buffer: buffer("fixed_slots_uvs_buffer", vertices as u64 * vec2),
+            tangents_buffer: buffer("fixed_slots_tangents_buffer", vertices as u64 * vec4),
+            emissives_buffer: buffer("fixed_slots_emissives_buffer", vertices as u64 * u32),
+            indices_buffer: buffer("fixed_slots_indices_buffer", indices as u64 * u32),
+        }
+    }
+}
+
+/// The fixed slots the cells of a volumetric entity are meshed into on a [`ShaderPlatform`] with
+/// `fixed_output_slots`, before being compacted into its output buffers. Entities and their
+/// iso-surfaces are meshed one after the other, so the slots are shared and sized for the largest
+/// one. With `prefix_sum_allocation`, only the counts of the cells are stored in them.
+#[derive(Resource, Default)]
+pub struct GpuFixedOutputSlots {
+    pub buffers: Option<FixedSlotBuffers>,
+}
+
+/// The meshing algorithm of a volumetric entity and whether its mesh is capped.
+type MeshedVolumeItem = (Entity, Option<&'static MeshingAlgorithm>, Has<MeshCapping>);
+
+impl GpuFixedOutputSlots {
+    /// Grows the slots to fit the cells of every volumetric entity.
+    pub fn prepare(
+        render_device: Res<RenderDevice>,
+        platform: Res<ShaderPlatform>,
+        mut fixed_output_slots: ResMut<GpuFixedOutputSlots>,
+        gpu_virtual_volumes: Res<VoxelMaterialComponents<GpuVirtualVolume>>,
+        prefix_sum_pipeline_ids: Res<VoxelMaterialComponents<VoxelMeshPrefixSumPipelineIds>>,
+        volumetric_query: Query<MeshedVolumeItem, With<Volumetric>>,
+    ) {
+        if !platform.fixed_output_slots && prefix_sum_pipeline_ids.0.is_empty() {
+            return;
+        }
+
+        let (mut cells, mut vertices, mut indices) = (0, 0, 0);
+        for (entity, meshing_algorithm, capped) in volumetric_query.iter() {
+            if !platform.fixed_output_slots && prefix_sum_pipeline_ids.get(&entity).is_none() {
+                continue;
+            }
+            let entity_cells =
+                meshed_cells(gpu_virtual_volumes.get(&entity), capped).element_product() as usize;
+            cells = cells.max(entity_cells);
+            // The primitives of a prefix sum allocation are emitted straight into the output
+            // buffers of the entity.
+            if platform.fixed_output_slots {
+                let (slot_vertices, slot_indices) = meshing_algorithm
+                    .copied()
+                    .unwrap_or_default()
+                    .max_cell_output();
+                vertices = vertices.max(entity_cells * slot_vertices);
+                indices = indices.max(entity_cells * slot_indices);
+            }
+        }
+
+        let fits = fixed_output_slots.buffers.as_ref().is_some_and(|buffers| {
+            buffers.cells >= cells && buffers.vertices >= vertices && buffers.indices >= indices
+        });
+        if !fits {
+            fixed_output_slots.buffers = Some(FixedSlotBuffers::new(
+                &render_device,
+                cells,
+                vertices,
+                indices,
+            ));
+        }
+    }
+}
+
+/// The compaction of the fixed slots of a volumetric entity into its output buffers, or the prefix
+/// sum of the primitives counted in them.
+pub struct GpuSlotCompaction {
+    pub params_buffer: UniformBuffer<SlotCompactionParams>,
+    pub bind_group: BindGroup,
+    /// The bind groups of the vertices, normals, uvs, tangents, emissives and indices, in the order of the
+    /// streams of the [`SlotCompactionComputePipeline`]. Empty for a prefix sum allocation.
+    pub stream_bind_groups: Vec<BindGroup>,
+}
+
+impl GpuSlotCompaction {
+    /// Binds the fixed slots to the output buffers of each entity, with the cells and slots of its
+    /// meshing algorithm.
+    #[allow(clippy::too_many_arguments)]
+    pub fn prepare(
+        render_device: Res<RenderDevice>,
+        render_queue: Res<RenderQueue>,
+        platform: Res<ShaderPlatform>,
+        compaction_pipeline: Res<SlotCompactionComputePipeline>,
+        fixed_output_slots: Res<GpuFixedOutputSlots>,
+        gpu_voxel_materials: Res<VoxelMaterialComponents<GpuVoxelMaterial>>,
+        gpu_virtual_volumes: Res<VoxelMaterialComponents<GpuVirtualVolume>>,
+        mut gpu_slot_compactions: ResMut<VoxelMaterialComponents<GpuSlotCompaction>>,
+        prefix_sum_pipeline_ids: Res<VoxelMaterialComponents<VoxelMeshPrefixSumPipelineIds>>,
+        volumetric_query: Query<MeshedVolumeItem, With<Volumetric>>,
+    ) {
+        gpu_slot_compactions.0.clear();
+        let Some(slots) = &fixed_output_slots.buffers else {
+            return;
+        };
+
+        for (entity, meshing_algorithm, capped) in volumetric_query.iter() {
+            if !platform.fixed_output_slots && prefix_sum_pipeline_ids.get(&entity).is_none() {
+                continue;
+            }
+            let Some(gpu_voxel_material) = gpu_voxel_materials.get(&entity) else {
+                continue;
+            };
+            let (
+                Some(vertices_buffer),
+                Some(normals_buffer),
+                Some(uvs_buffer),
+                Some(tangents_buffer),
+                Some(emissives_buffer),
+                Some(indices_buffer),
+                Some(atomics_buffer),
+            ) = (
+                gpu_voxel_material.vertices_buffer.buffer(),
+                gpu_voxel_material.normals_buffer.buffer(),
+                gpu_voxel_material.uvs_buffer.buffer(),
+                gpu_voxel_material.tangents_buffer.buffer(),
+                gpu_voxel_material.emissives_buffer.buffer(),
+                gpu_voxel_material.indices_buffer.buffer(),
+                gpu_voxel_material.atomics_buffer.buffer(),
+            )
+            else {
+                continue;
+            };
+
+            let (slot_vertices, slot_indices) = meshing_algorithm
+                .copied()
+                .unwrap_or_default()
+                .max_cell_output();
+            let params = SlotCompactionParams {
+                cells: meshed_cells(gpu_virtual_volumes.get(&entity), capped).element_product(),
+                slot_vertices: slot_vertices as u32,
+                slot_indices: slot_indices as u32,
+                vertex_capacity: gpu_voxel_material.vertices_buffer.capacity() as u32,
+                index_capacity: gpu_voxel_material.indices_buffer.capacity() as u32,
+            };
+
+            gpu_slot_compactions.insert(
+                entity,
+                GpuSlotCompaction::new(
+                    &render_device,
+                    &render_queue,
+                    &compaction_pipeline,
+                    slots,
+                    params,
+                    [
+                        vertices_buffer,
+                        normals_buffer,
+                        uvs_buffer,
+                        tangents_buffer,
+                        emissives_buffer,
+                        indices_buffer,
+                    ],
+                    atomics_buffer,
+                    platform.fixed_output_slots,
+                ),
+            );
+        }
+    }
+
+    /// Binds the fixed `slots` to the vertices, normals, uvs, tangents, emissives and indices
+    /// `outputs` they are compacted into, in that order, and to the counters of `atomics_buffer`.
+    /// Without `streams`, only the primitives counted in the slots are scanned, for a prefix sum
+    /// allocation.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        render_device: &RenderDevice,
+        render_queue: &RenderQueue,
+        compaction_pipeline: &SlotCompactionComputePipeline,
+        slots: &FixedSlotBuffers,
+        params: SlotCompactionParams,
+        outputs: [&Buffer; 6],
+        atomics_buffer: &Buffer,
+        streams: bool,
+    ) -> Self {
+        let mut params_buffer = UniformBuffer::from(params);
+        params_buffer.write_buffer(render_device, render_queue);
+
+        let bind_group = render_device.create_bind_group(
+            "GpuSlotCompaction::bind_group",
+            &compaction_pipeline.bind_group_layout,
+            &BindGroupEntries::sequential((
+                params_buffer.binding().expect(
+                    "Slot Compaction Params Buffer should have already been uploaded to the gpu",
+                ),
+                slots.cell_slots_buffer.as_entire_binding(),
+                slots.blocks_buffer.as_entire_binding(),
+                atomics_buffer.as_entire_binding(),
+            )),
+        );
+
+        let streams = match streams {
+            true => &compaction_pipeline.stream_params[..],
+            false => &[],
+        };
+        let stream_bind_groups = [
+            &slots.vertices_buffer,
+            &slots.normals_buffer,
+            &slots.uvs_buffer,
+            &slots.tangents_buffer,
+            &slots.emissives_buffer,
+            &slots.indices_buffer,
+        ]
+        .into_iter()
+        .zip(outputs)
+        .zip(streams)
+        .map(|((slots_buffer, output_buffer), stream_params)| {
+            render_device.create_bind_group(
+                "GpuSlotCompaction::stream_bind_group",
+                &compaction_pipeline.stream_layout,
+                &BindGroupEntries::sequential((
+                    stream_params.binding().expect(
+                        "Slot Stream Params Buffer should have already been uploaded to the gpu",
+                    ),
+                    slots_buffer.as_entire_binding(),
+                    output_buffer.as_entire_binding(),
+                )),
+            )
+        })
+        .collect();
+
+        GpuSlotCompaction {
+            params_buffer,
+            bind_group,
+            stream_bind_groups,
+        }
+    }
+}
@@ -0,0 +1,330 @@
+use bevy::{
+    prelude::*,
+    render::{
+        render_resource::{
+            BindGroup, BindGroupEntries, Buffer, BufferDescriptor, BufferUsages, BufferVec,
+            UniformBuffer,
+        },
+        renderer::{RenderDevice, RenderQueue},
+    },
+};
+
+use crate::render::{
+    slot_compaction_compute_pipeline::SlotCompactionComputePipeline,
+    voxel_mesh_compute_pipeline::{DirtyMeshes, VoxelMeshComputePipeline},
+};
+
+use super::{
+    atomics::Atomics,
+    gpu_fixed_output_slots::{FixedSlotBuffers, GpuFixedOutputSlots, GpuSlotCompaction},
+    gpu_voxel_material::{GpuVoxelMaterial, OutputCapacities},
+    iso_surface::{IsoLevels, IsoSurfaceParams},
+    normal_encoding::EncodedNormal,
+    voxel_material::VoxelMaterialComponents,
+};
+
+/// The output buffers of one of the [`IsoSurfaces`](super::iso_surface::IsoSurfaces) of an
+/// entity, meshed from the voxels buffer of its [`GpuVoxelMaterial`], and the staging buffers
+/// receiving a full copy of them for readback.
+pub struct GpuIsoSurface {
+    pub params_buffer: UniformBuffer<IsoSurfaceParams>,
+    pub vertices_buffer: BufferVec<Vec4>,
+    pub normals_buffer: BufferVec<EncodedNormal>,
+    pub uvs_buffer: BufferVec<Vec2>,
+    pub tangents_buffer: BufferVec<Vec4>,
+    pub emissives_buffer: BufferVec<u32>,
+    pub indices_buffer: BufferVec<u32>,
+    pub atomics_buffer: BufferVec<u32>,
+
+    pub atomics_staging_buffer: Buffer,
+    pub vertices_staging_buffer: Buffer,
+    pub normals_staging_buffer: Buffer,
+    pub uvs_staging_buffer: Buffer,
+    pub tangents_staging_buffer: Buffer,
+    pub emissives_staging_buffer: Buffer,
+    pub indices_staging_buffer: Buffer,
+
+    pub bind_group: Option<BindGroup>,
+    /// The compaction of the fixed slots the surface is meshed into on a
+    /// [`ShaderPlatform`](super::shader_platform::ShaderPlatform) with `fixed_output_slots`.
+    pub slot_compaction: Option<GpuSlotCompaction>,
+}
+
+impl GpuIsoSurface {
+    /// Creates output buffers of `capacities` for meshing the surface at `iso_level`, the full
+    /// capacities of the entity's [`GpuVoxelMaterial`].
+    pub fn new(
+        render_device: &RenderDevice,
+        render_queue: &RenderQueue,
+        capacities: OutputCapacities,
+        iso_level: f32,
+    ) -> Self {
+        let usage = BufferUsages::STORAGE | BufferUsages::COPY_SRC | BufferUsages::COPY_DST;
+
+        let mut vertices_buffer = BufferVec::<Vec4>::new(usage);
+        vertices_buffer.reserve(capacities.vertices, render_device);
+
+        let mut normals_buffer = BufferVec::<EncodedNormal>::new(usage);
+        normals_buffer.reserve(capacities.attributes, render_device);
+
+        let mut uvs_buffer = BufferVec::<Vec2>::new(usage);
+        uvs_buffer.reserve(capacities.attributes, render_device);
+
+        let mut tangents_buffer = BufferVec::<Vec4>::new(usage);
+        tangents_buffer.reserve(capacities.attributes, render_device);
+
+        let mut emissives_buffer = BufferVec::<u32>::new(usage);
+        emissives_buffer.reserve(capacities.attributes, render_device);
+
+        let mut indices_buffer = BufferVec::<u32>::new(usage);
+        indices_buffer.reserve(capacities.indices, render_device);
+
+        let mut atomics_buffer = BufferVec::<u32>::new(usage);
+        atomics_buffer.reserve(Atomics::LEN, render_device);
+
+        let staging_buffer = |label, buffer: Option<&Buffer>| {
+            render_device.create_buffer(&BufferDescriptor {
+                label: Some(label),
+                size: buffer.map_or(0, |buffer| buffer.size()),
+                usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        };
+
+        let mut params_buffer = UniformBuffer::from(IsoSurfaceParams { iso_level });
+        params_buffer.write_buffer(render_device, render_queue);
+
+        GpuIsoSurface {
+            atomics_staging_buffer: staging_buffer(
+                "iso_surface_atomics_staging_buffer",
+                atomics_buffer.buffer(),
+            ),
+            vertices_staging_buffer: staging_buffer(
+                "iso_surface_vertices_staging_buffer",
+                vertices_buffer.buffer(),
+            ),
+            normals_staging_buffer: staging_buffer(
+                "iso_surface_normals_staging_buffer",
+                normals_buffer.buffer(),
+            ),
+            uvs_staging_buffer: staging_buffer(
+                "iso_surface_uvs_staging_buffer",
+                uvs_buffer.buffer(),
+            ),
+            tangents_staging_buffer: staging_buffer(
+                "iso_surface_tangents_staging_buffer",
+                tangents_buffer.buffer(),
+            ),
+            emissives_staging_buffer: staging_buffer(
+                "iso_surface_emissives_staging_buffer",
+                emissives_buffer.buffer(),
+            ),
+            indices_staging_buffer: staging_buffer(
+                "iso_surface_indices_staging_buffer",
+                indices_buffer.buffer(),
+            ),
+            params_buffer,
+            vertices_buffer,
+            normals_buffer,
+            uvs_buffer,
+            tangents_buffer,
+            emissives_buffer,
+            indices_buffer,
+            atomics_buffer,
+            bind_group: None,
+            slot_compaction: None,
+        }
+    }
+
+    /// The output buffers paired with the staging buffers they are read back through.
+    pub fn readback_buffers(&self) -> [(Option<&Buffer>, &Buffer); 7] {
+        [
+            (self.atomics_buffer.buffer(), &self.atomics_staging_buffer),
+            (self.vertices_buffer.buffer(), &self.vertices_staging_buffer),
+            (self.normals_buffer.buffer(), &self.normals_staging_buffer),
+            (self.uvs_buffer.buffer(), &self.uvs_staging_buffer),
+            (self.tangents_buffer.buffer(), &self.tangents_staging_buffer),
+            (
+                self.emissives_buffer.buffer(),
+                &self.emissives_staging_buffer,
+            ),
+            (self.indices_buffer.buffer(), &self.indices_staging_buffer),
+        ]
+    }
+
+    /// Binds the voxels and tables of `gpu_voxel_material` with the output buffers of the surface,
+    /// in the layout of [`VoxelMeshComputePipeline::bind_group_1_layout`], or with the
+    /// `fixed_slots` compacted into them.
+    fn create_bind_group(
+        &self,
+        render_device: &RenderDevice,
+        voxel_pipeline: &VoxelMeshComputePipeline,
+        gpu_voxel_material: &GpuVoxelMaterial,
+        fixed_slots: Option<&FixedSlotBuffers>,
+    ) -> Option<BindGroup> {
+        let [atomics, vertices, normals, indices, uvs, tangents, emissives] = match fixed_slots {
+            Some(slots) => [
+                slots.cell_slots_buffer.as_entire_binding(),
+                slots.vertices_buffer.as_entire_binding(),
+                slots.normals_buffer.as_entire_binding(),
+                slots.indices_buffer.as_entire_binding(),
+                slots.uvs_buffer.as_entire_binding(),
+                slots.tangents_buffer.as_entire_binding(),
+                slots.emissives_buffer.as_entire_binding(),
+            ],
+            None => [
+                self.atomics_buffer.binding()?,
+                self.vertices_buffer.binding()?,
+                self.normals_buffer.binding()?,
+                self.indices_buffer.binding()?,
+                self.uvs_buffer.binding()?,
+                self.tangents_buffer.binding()?,
+                self.emissives_buffer.binding()?,
+            ],
+        };
+
+        Some(render_device.create_bind_group(
+            "GpuIsoSurface::bind_group",
+            &voxel_pipeline.bind_group_1_layout,
+            &BindGroupEntries::with_indices((
+                (0, gpu_voxel_material.edge_table_buffer.binding()?),
+                (1, gpu_voxel_material.tri_table_buffer.binding()?),
+                (2, gpu_voxel_material.voxels_buffer.binding()?),
+                (3, atomics),
+                (4, vertices),
+                (5, normals),
+                (6, indices),
+                (7, uvs),
+                (8, tangents),
+                (
+                    9,
+                    gpu_voxel_material.dual_contouring_params_buffer.binding()?,
+                ),
+                (10, self.params_buffer.binding()?),
+                (11, gpu_voxel_material.clip_planes_buffer.binding()?),
+                (12, gpu_voxel_material.adaptive_resolution_buffer.binding()?),
+                (13, emissives),
+            )),
+        ))
+    }
+
+    /// Binds the fixed `slots` to the output buffers of the surface, with the cells and slots of
+    /// the compaction of its entity, `entity_compaction`.
+    fn create_slot_compaction(
+        &self,
+        render_device: &RenderDevice,
+        render_queue: &RenderQueue,
+        compaction_pipeline: &SlotCompactionComputePipeline,
+        slots: &FixedSlotBuffers,
+        entity_compaction: &GpuSlotCompaction,
+    ) -> Option<GpuSlotCompaction> {
+        let mut params = *entity_compaction.params_buffer.get();
+        params.vertex_capacity = self.vertices_buffer.capacity() as u32;
+        params.index_capacity = self.indices_buffer.capacity() as u32;
+
+        Some(GpuSlotCompaction::new(
+            render_device,
+            render_queue,
+            compaction_pipeline,
+            slots,
+            params,
+            [
+                self.vertices_buffer.buffer()?,
+                self.normals_buffer.buffer()?,
+                self.uvs_buffer.buffer()?,
+                self.tangents_buffer.buffer()?,
+                self.emissives_buffer.buffer()?,
+                self.indices_buffer.buffer()?,
+            ],
+            self.atomics_buffer.buffer()?,
+            true,
+        ))
+    }
+}
+
+/// The [`GpuIsoSurface`]s of an entity, in the order of its iso-levels.
+#[derive(Default)]
+pub struct GpuIsoSurfaces {
+    pub surfaces: Vec<GpuIsoSurface>,
+    capacities: Option<OutputCapacities>,
+}
+
+impl GpuIsoSurfaces {
+    /// Creates the [`GpuIsoSurface`]s of entities whose iso-levels or buffer capacities changed and
+    /// binds them to the entity's current [`GpuVoxelMaterial`], through the fixed slots compacted
+    /// like the entity's own where the platform has them.
+    #[allow(clippy::too_many_arguments)]
+    pub fn prepare(
+        render_device: Res<RenderDevice>,
+        render_queue: Res<RenderQueue>,
+        voxel_pipeline: Res<VoxelMeshComputePipeline>,
+        compaction_pipeline: Res<SlotCompactionComputePipeline>,
+        fixed_output_slots: Res<GpuFixedOutputSlots>,
+        gpu_slot_compactions: Res<VoxelMaterialComponents<GpuSlotCompaction>>,
+        gpu_voxel_materials: Res<VoxelMaterialComponents<GpuVoxelMaterial>>,
+        mut gpu_iso_surfaces: ResMut<VoxelMaterialComponents<GpuIsoSurfaces>>,
+        mut dirty_meshes: ResMut<DirtyMeshes>,
+        iso_levels_query: Query<(Entity, &IsoLevels)>,
+    ) {
+        gpu_iso_surfaces
+            .0
+            .retain(|entity, _| iso_levels_query.contains(*entity));
+
+        let fixed_slots = fixed_output_slots
+            .buffers
+            .as_ref()
+            .filter(|_| voxel_pipeline.platform.fixed_output_slots);
+
+        for (entity, iso_levels) in iso_levels_query.iter() {
+            let Some(gpu_voxel_material) = gpu_voxel_materials.get(&entity) else {
+                continue;
+            };
+            let capacities = gpu_voxel_material.full_output_capacities();
+            let gpu_surfaces = gpu_iso_surfaces.0.entry(entity).or_default();
+
+            if gpu_surfaces.capacities != Some(capacities)
+                || gpu_surfaces.surfaces.len() != iso_levels.0.len()
+            {
+                gpu_surfaces.capacities = Some(capacities);
+                gpu_surfaces.surfaces = iso_levels
+                    .0
+                    .iter()
+                    .map(|iso_level| {
+                        GpuIsoSurface::new(&render_device, &render_queue, capacities, *iso_level)
+                    })
+                    .collect();
+                dirty_meshes.mark(entity);
+            }
+
+            for (gpu_surface, iso_level) in gpu_surfaces.surfaces.iter_mut().zip(&iso_levels.0) {
+                if gpu_surface.params_buffer.get().iso_level != *iso_level {
+                    gpu_surface.params_buffer.set(IsoSurfaceParams {
+                        iso_level: *iso_level,
+                    });
+                    gpu_surface
+                        .params_buffer
+                        .write_buffer(&render_device, &render_queue);
+                    dirty_meshes.mark(entity);
+                }
+
+                gpu_surface.bind_group = gpu_surface.create_bind_group(
+                    &render_device,
+                    &voxel_pipeline,
+                    gpu_voxel_material,
+                    fixed_slots,
+                );
+                gpu_surface.slot_compaction = fixed_slots
+                    .zip(gpu_slot_compactions.get(&entity))
+                    .and_then(|(slots, entity_compaction)| {
+                        gpu_surface.create_slot_compaction(
+                            &render_device,
+                            &render_queue,
+                            &compaction_pipeline,
+                            slots,
+                            entity_compaction,
+                        )
+                    });
+            }
+        }
+    }
+}
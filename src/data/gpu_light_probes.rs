@@ -0,0 +1,130 @@
+use bevy::{
+    prelude::*,
+    render::{
+        render_asset::RenderAssets,
+        render_resource::{BindGroup, BindGroupEntries, PipelineCache, UniformBuffer},
+        renderer::{RenderDevice, RenderQueue},
+        texture::GpuImage,
+        Extract,
+    },
+};
+
+use crate::render::{
+    light_probes_compute_pipeline::LightProbesComputePipeline,
+    voxel_mesh_compute_pipeline::DirtyMeshes,
+};
+
+use super::{
+    gpu_voxel_material::GpuVoxelMaterial,
+    light_probes::{LightProbeParams, VoxelLightProbes},
+    voxel_material::VoxelMaterialComponents,
+};
+
+pub struct GpuLightProbes {
+    pub params_buffer: UniformBuffer<LightProbeParams>,
+    pub image: Handle<Image>,
+    /// Whether the probes have been baked since the texture was last replaced.
+    pub baked: bool,
+    /// Only bound on the frames the probes are baked.
+    pub bind_group: Option<BindGroup>,
+}
+
+impl GpuLightProbes {
+    /// Initializes the [`GpuLightProbes`] of newly added [`VoxelLightProbes`].
+    pub fn initialize(
+        mut gpu_light_probes: ResMut<VoxelMaterialComponents<GpuLightProbes>>,
+        light_probes_query: Extract<Query<(Entity, &VoxelLightProbes), Added<VoxelLightProbes>>>,
+    ) {
+        for (entity, light_probes) in light_probes_query.iter() {
+            gpu_light_probes.insert(
+                entity,
+                GpuLightProbes {
+                    params_buffer: UniformBuffer::default(),
+                    image: light_probes.image.clone(),
+                    baked: false,
+                    bind_group: None,
+                },
+            );
+        }
+    }
+
+    /// Uploads the changed [`VoxelLightProbes`] settings, baking the probes again.
+    pub fn extract(
+        render_queue: Res<RenderQueue>,
+        render_device: Res<RenderDevice>,
+        mut gpu_light_probes: ResMut<VoxelMaterialComponents<GpuLightProbes>>,
+        light_probes_query: Extract<Query<(Entity, Ref<VoxelLightProbes>)>>,
+    ) {
+        gpu_light_probes
+            .0
+            .retain(|entity, _| light_probes_query.contains(*entity));
+
+        for (entity, light_probes) in light_probes_query.iter() {
+            let Some(gpu_probes) = gpu_light_probes.get_mut(&entity) else {
+                continue;
+            };
+            if !light_probes.is_changed() && gpu_probes.params_buffer.buffer().is_some() {
+                continue;
+            }
+            gpu_probes.image = light_probes.image.clone();
+            gpu_probes.baked = false;
+            gpu_probes.params_buffer.set(light_probes.as_ref().into());
+            gpu_probes
+                .params_buffer
+                .write_buffer(render_device.as_ref(), render_queue.as_ref());
+        }
+    }
+
+    /// Binds the voxels of the entities remeshed this frame, or whose probes were never baked, to
+    /// their irradiance volume texture.
+    pub fn prepare(
+        render_device: Res<RenderDevice>,
+        pipeline_cache: Res<PipelineCache>,
+        light_probes_pipeline: Res<LightProbesComputePipeline>,
+        gpu_images: Res<RenderAssets<GpuImage>>,
+        dirty_meshes: Res<DirtyMeshes>,
+        gpu_voxel_materials: Res<VoxelMaterialComponents<GpuVoxelMaterial>>,
+        mut gpu_light_probes: ResMut<VoxelMaterialComponents<GpuLightProbes>>,
+    ) {
+        // Keep the probes waiting to be baked until the pipeline is loaded.
+        if pipeline_cache
+            .get_compute_pipeline(light_probes_pipeline.pipeline)
+            .is_none()
+        {
+            return;
+        }
+
+        for (entity, gpu_probes) in gpu_light_probes.0.iter_mut() {
+            gpu_probes.bind_group = None;
+            if gpu_probes.baked && !dirty_meshes.is_meshed(entity) {
+                continue;
+            }
+            let (Some(gpu_voxel_material), Some(gpu_image)) = (
+                gpu_voxel_materials.get(entity),
+                gpu_images.get(&gpu_probes.image),
+            ) else {
+                continue;
+            };
+
+            gpu_probes.bind_group = Some(render_device.create_bind_group(
+                "GpuLightProbes::bind_group",
+                &light_probes_pipeline.bind_group_layout,
+                &BindGroupEntries::sequential((
+                    gpu_probes.params_buffer.binding().expect(
+                        "Light Probe Params Buffer should have already been uploaded to the gpu",
+                    ),
+                    gpu_voxel_material
+                        .voxels_buffer
+                        .binding()
+                        .expect("Voxels Buffer should have already been uploaded to the gpu"),
+                    gpu_voxel_material
+                        .iso_surface_params_buffer
+                        .binding()
+                        .expect("Iso Surface Params Buffer should have already been uploaded to the gpu"),
+                    &gpu_image.texture_view,
+                )),
+            ));
+            gpu_probes.baked = true;
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use bevy::{
+    prelude::*,
+    render::{
+        render_resource::{BindGroup, BindGroupEntries, BufferId},
+        renderer::RenderDevice,
+        Extract,
+    },
+    utils::HashMap,
+};
+
+use crate::{
+    bundles::volumetric_bundle::{SeamlessNormals, Volumetric},
+    render::voxel_mesh_compute_pipeline::{DirtyMeshes, VoxelMeshComputePipeline},
+};
+
+use super::{
+    chunk_coord::ChunkCoord,
+    gpu_virtual_volume::GpuVirtualVolume,
+    gpu_voxel_material::GpuVoxelMaterial,
+    virtual_volume::VirtualVolume,
+    voxel_material::{VoxelMaterial, VoxelMaterialComponents},
+};
+
+/// Offsets of the chunks next to the faces of a chunk, in the order of the neighbour bindings of
+/// [`VoxelMeshComputePipeline::neighbor_voxels_layout`]: -x, +x, -y, +y, -z, +z.
+pub const NEIGHBOR_OFFSETS: [IVec3; 6] = [
+    IVec3::NEG_X,
+    IVec3::X,
+    IVec3::NEG_Y,
+    IVec3::Y,
+    IVec3::NEG_Z,
+    IVec3::Z,
+];
+
+/// The voxels buffers of the chunks next to the faces of a [`SeamlessNormals`] entity, bound
+/// read-only for its gradient normals. Faces without a neighbour are bound to an empty buffer.
+#[derive(Default)]
+pub struct GpuNeighborVoxels {
+    pub neighbors: [Option<Entity>; 6],
+    pub bind_group: Option<BindGroup>,
+    /// The buffers `bind_group` binds, to bind them again once a neighbour's is reallocated.
+    buffers: [Option<BufferId>; 6],
+}
+
+impl GpuNeighborVoxels {
+    /// Finds the neighbours of the [`SeamlessNormals`] entities, remeshing those whose neighbours
+    /// changed or whose neighbours' voxels changed, as the normals along their faces did too.
+    #[allow(clippy::type_complexity)]
+    pub fn extract(
+        mut gpu_neighbor_voxels: ResMut<VoxelMaterialComponents<Self>>,
+        mut dirty_meshes: ResMut<DirtyMeshes>,
+        chunk_query: Extract<
+            Query<
+                (Entity, &ChunkCoord, Ref<VoxelMaterial>),
+                (With<Volumetric>, Without<VirtualVolume>),
+            >,
+        >,
+        seamless_query: Extract<Query<(Entity, Option<&ChunkCoord>), With<SeamlessNormals>>>,
+    ) {
+        gpu_neighbor_voxels
+            .0
+            .retain(|entity, _| seamless_query.contains(*entity));
+        if seamless_query.is_empty() {
+            return;
+        }
+
+        let chunks = chunk_query
+            .iter()
+            .map(|(entity, coord, voxel_material)| (*coord, (entity, voxel_material.is_changed())))
+            .collect::<HashMap<_, _>>();
+
+        for (entity, coord) in seamless_query.iter() {
+            // Entities outside of the grid of chunks have no neighbours.
+            let neighbor_chunks = NEIGHBOR_OFFSETS.map(|offset| {
+                coord.and_then(|coord| chunks.get(&ChunkCoord(coord.0 + offset)).copied())
+            });
+            let neighbors = neighbor_chunks.map(|chunk| chunk.map(|(neighbor, _)| neighbor));
+            let changed = neighbor_chunks
+                .iter()
+                .flatten()
+                .any(|(_, changed)| *changed);
+
+            let gpu_neighbors = gpu_neighbor_voxels.0.entry(entity).or_default();
+            if gpu_neighbors.neighbors != neighbors || changed {
+                gpu_neighbors.neighbors = neighbors;
+                dirty_meshes.mark(entity);
+            }
+        }
+    }
+
+    /// Binds the voxels buffers of the neighbours, again whenever one of them changed.
+    pub fn prepare(
+        render_device: Res<RenderDevice>,
+        voxel_pipeline: Res<VoxelMeshComputePipeline>,
+        gpu_voxel_materials: Res<VoxelMaterialComponents<GpuVoxelMaterial>>,
+        gpu_virtual_volumes: Res<VoxelMaterialComponents<GpuVirtualVolume>>,
+        mut gpu_neighbor_voxels: ResMut<VoxelMaterialComponents<Self>>,
+    ) {
+        for (entity, gpu_neighbors) in gpu_neighbor_voxels.0.iter_mut() {
+            // Virtual volumes span more than a chunk, their voxels are paged instead.
+            if gpu_virtual_volumes.get(entity).is_some() {
+                gpu_neighbors.bind_group = None;
+                continue;
+            }
+
+            let buffers = gpu_neighbors.neighbors.map(|neighbor| {
+                neighbor
+                    .and_then(|neighbor| gpu_voxel_materials.get(&neighbor))
+                    .and_then(|gpu_voxel_material| gpu_voxel_material.voxels_buffer.buffer())
+            });
+            let buffer_ids = buffers.map(|buffer| buffer.map(|buffer| buffer.id()));
+            if gpu_neighbors.bind_group.is_some() && gpu_neighbors.buffers == buffer_ids {
+                continue;
+            }
+
+            let [neg_x, pos_x, neg_y, pos_y, neg_z, pos_z] = buffers.map(|buffer| {
+                buffer
+                    .unwrap_or(&voxel_pipeline.empty_neighbor_buffer)
+                    .as_entire_binding()
+            });
+            gpu_neighbors.bind_group = Some(render_device.create_bind_group(
+                "GpuNeighborVoxels::bind_group",
+                &voxel_pipeline.neighbor_voxels_layout,
+                &BindGroupEntries::sequential((neg_x, pos_x, neg_y, pos_y, neg_z, pos_z)),
+            ));
+            gpu_neighbors.buffers = buffer_ids;
+        }
+    }
+}
@@ -0,0 +1,254 @@
+use bevy::{
+    prelude::*,
+    render::{
+        render_resource::{
+            BindGroup, BindGroupEntries, BufferUsages, BufferVec, PipelineCache, UniformBuffer,
+        },
+        renderer::{RenderDevice, RenderQueue},
+        Extract,
+    },
+};
+
+use crate::{
+    bundles::volumetric_bundle::Volumetric,
+    render::{
+        procedural_generation_compute_pipeline::ProceduralGenerationComputePipeline,
+        voxel_mesh_compute_pipeline::DirtyMeshes,
+    },
+    CHUNK_SZ_3,
+};
+
+use super::{
+    chunk_priority::ChunkPriority,
+    generation_graph::{GenerationGraph, GraphLayer, GraphNode, GraphVolume},
+    gpu_voxel_material::GpuVoxelMaterial,
+    mesh_buffer_sizing::MeshBufferSizing,
+    meshing_algorithm::MeshingAlgorithm,
+    procedural_volume::{GenerationBudget, ProceduralParams, ProceduralVolume},
+    voxel_material::VoxelMaterialComponents,
+};
+
+/// The generation job of a [`ProceduralVolume`] or a [`GraphVolume`], writing the voxels buffer
+/// of the entity's [`GpuVoxelMaterial`] in the generation pass.
+pub struct GpuProceduralVolume {
+    /// The params of a [`ProceduralVolume`], of which a [`GraphVolume`] only sets the origin and
+    /// voxel count.
+    pub params: ProceduralParams,
+    pub params_buffer: UniformBuffer<ProceduralParams>,
+    /// The nodes of the graph of a [`GraphVolume`], generating the voxels in place of the
+    /// heightmap of the params.
+    pub graph: Option<GpuGenerationGraph>,
+    pub bind_group: Option<BindGroup>,
+    /// Whether the voxels are generated this frame.
+    pub generate: bool,
+}
+
+/// The nodes and material layers of a [`GenerationGraph`], uploaded for the graph entry point of
+/// the generation shader.
+pub struct GpuGenerationGraph {
+    pub nodes: Vec<GraphNode>,
+    pub layers: Vec<GraphLayer>,
+    pub nodes_buffer: BufferVec<GraphNode>,
+    pub layers_buffer: BufferVec<GraphLayer>,
+}
+
+impl GpuGenerationGraph {
+    pub fn new(graph: &GenerationGraph) -> Self {
+        let (nodes, layers) = graph.gpu_nodes();
+        Self {
+            nodes,
+            layers,
+            nodes_buffer: BufferVec::new(BufferUsages::STORAGE),
+            layers_buffer: BufferVec::new(BufferUsages::STORAGE),
+        }
+    }
+}
+
+/// The [`ProceduralVolume`]s waiting to be generated, by descending [`ChunkPriority`] and then
+/// oldest first.
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct GenerationQueue(pub Vec<Entity>);
+
+impl GpuProceduralVolume {
+    /// Creates the voxels buffers of new [`ProceduralVolume`]s and [`GraphVolume`]s, queues the new
+    /// and changed ones for generation and drops the jobs of removed ones. Graph volumes wait for
+    /// their graph to be loaded.
+    #[allow(clippy::too_many_arguments, clippy::type_complexity)]
+    pub fn extract(
+        render_queue: Res<RenderQueue>,
+        render_device: Res<RenderDevice>,
+        mut gpu_voxel_materials: ResMut<VoxelMaterialComponents<GpuVoxelMaterial>>,
+        mut gpu_procedural_volumes: ResMut<VoxelMaterialComponents<GpuProceduralVolume>>,
+        mut generation_queue: ResMut<GenerationQueue>,
+        sizing: Extract<Res<MeshBufferSizing>>,
+        procedural_query: Extract<
+            Query<(Entity, Ref<ProceduralVolume>, Option<&MeshingAlgorithm>), With<Volumetric>>,
+        >,
+        graph_query: Extract<
+            Query<(Entity, Ref<GraphVolume>, Option<&MeshingAlgorithm>), With<Volumetric>>,
+        >,
+        graphs: Extract<Option<Res<Assets<GenerationGraph>>>>,
+    ) {
+        let mut jobs = Vec::new();
+        for (entity, procedural_volume, meshing_algorithm) in procedural_query.iter() {
+            if !procedural_volume.is_changed() && gpu_procedural_volumes.0.contains_key(&entity) {
+                continue;
+            }
+            jobs.push((entity, meshing_algorithm, procedural_volume.params(), None));
+        }
+
+        for (entity, graph_volume, meshing_algorithm) in graph_query.iter() {
+            if !graph_volume.is_changed() && gpu_procedural_volumes.0.contains_key(&entity) {
+                continue;
+            }
+            let Some(graph) = graphs
+                .as_ref()
+                .and_then(|graphs| graphs.get(&graph_volume.graph))
+            else {
+                continue;
+            };
+            let params = ProceduralParams {
+                origin: graph_volume.origin,
+                seed: graph.seed,
+                voxel_count: CHUNK_SZ_3 as u32,
+                ..default()
+            };
+            jobs.push((
+                entity,
+                meshing_algorithm,
+                params,
+                Some(GpuGenerationGraph::new(graph)),
+            ));
+        }
+
+        for (entity, meshing_algorithm, params, graph) in jobs {
+            if !gpu_voxel_materials.0.contains_key(&entity) {
+                gpu_voxel_materials.insert(
+                    entity,
+                    GpuVoxelMaterial::from_voxels(
+                        render_device.as_ref(),
+                        render_queue.as_ref(),
+                        &[],
+                        CHUNK_SZ_3,
+                        sizing
+                            .capacities(meshing_algorithm.copied().unwrap_or_default(), CHUNK_SZ_3),
+                    ),
+                );
+            }
+            gpu_procedural_volumes.insert(
+                entity,
+                GpuProceduralVolume {
+                    params,
+                    params_buffer: UniformBuffer::default(),
+                    graph,
+                    bind_group: None,
+                    generate: false,
+                },
+            );
+            if !generation_queue.contains(&entity) {
+                generation_queue.push(entity);
+            }
+        }
+
+        gpu_procedural_volumes.0.retain(|entity, _| {
+            procedural_query.contains(*entity) || graph_query.contains(*entity)
+        });
+    }
+
+    /// Picks the queued volumes generated this frame within the [`GenerationBudget`], uploading
+    /// their params and graph and binding their voxels buffer, and marks them for remeshing.
+    /// Nothing is picked until the generation pipelines are loaded.
+    #[allow(clippy::too_many_arguments)]
+    pub fn prepare(
+        render_queue: Res<RenderQueue>,
+        render_device: Res<RenderDevice>,
+        pipeline_cache: Res<PipelineCache>,
+        generation_pipeline: Res<ProceduralGenerationComputePipeline>,
+        budget: Res<GenerationBudget>,
+        priority_query: Query<&ChunkPriority>,
+        gpu_voxel_materials: Res<VoxelMaterialComponents<GpuVoxelMaterial>>,
+        mut gpu_procedural_volumes: ResMut<VoxelMaterialComponents<GpuProceduralVolume>>,
+        mut generation_queue: ResMut<GenerationQueue>,
+        mut dirty_meshes: ResMut<DirtyMeshes>,
+    ) {
+        for gpu_procedural_volume in gpu_procedural_volumes.0.values_mut() {
+            gpu_procedural_volume.generate = false;
+        }
+
+        generation_queue.retain(|entity| gpu_procedural_volumes.0.contains_key(entity));
+        if generation_queue.is_empty() || !generation_pipeline.is_loaded(&pipeline_cache) {
+            return;
+        }
+
+        let priority = |entity: &Entity| priority_query.get(*entity).map_or(0.0, |p| p.0);
+        generation_queue.sort_by(|a, b| priority(b).total_cmp(&priority(a)));
+
+        let count = budget.max_chunks_per_frame.min(generation_queue.len());
+        for entity in generation_queue.drain(..count) {
+            let (Some(gpu_procedural_volume), Some(voxels_buffer)) = (
+                gpu_procedural_volumes.get_mut(&entity),
+                gpu_voxel_materials
+                    .get(&entity)
+                    .and_then(|gpu| gpu.voxels_buffer.binding()),
+            ) else {
+                continue;
+            };
+
+            gpu_procedural_volume
+                .params_buffer
+                .set(gpu_procedural_volume.params);
+            gpu_procedural_volume
+                .params_buffer
+                .write_buffer(render_device.as_ref(), render_queue.as_ref());
+
+            let params_binding = gpu_procedural_volume
+                .params_buffer
+                .binding()
+                .expect("Procedural Params Buffer should have been uploaded");
+
+            gpu_procedural_volume.bind_group = Some(match &mut gpu_procedural_volume.graph {
+                Some(graph) => {
+                    graph.nodes_buffer.clear();
+                    for node in &graph.nodes {
+                        graph.nodes_buffer.push(*node);
+                    }
+                    graph
+                        .nodes_buffer
+                        .write_buffer(render_device.as_ref(), render_queue.as_ref());
+
+                    graph.layers_buffer.clear();
+                    for layer in &graph.layers {
+                        graph.layers_buffer.push(*layer);
+                    }
+                    graph
+                        .layers_buffer
+                        .write_buffer(render_device.as_ref(), render_queue.as_ref());
+
+                    render_device.create_bind_group(
+                        "GpuProceduralVolume::graph_bind_group",
+                        &generation_pipeline.graph_bind_group_layout,
+                        &BindGroupEntries::sequential((
+                            params_binding,
+                            voxels_buffer,
+                            graph
+                                .nodes_buffer
+                                .binding()
+                                .expect("Graph Nodes Buffer should have been uploaded"),
+                            graph
+                                .layers_buffer
+                                .binding()
+                                .expect("Graph Layers Buffer should have been uploaded"),
+                        )),
+                    )
+                }
+                None => render_device.create_bind_group(
+                    "GpuProceduralVolume::bind_group",
+                    &generation_pipeline.bind_group_layout,
+                    &BindGroupEntries::sequential((params_binding, voxels_buffer)),
+                ),
+            });
+            gpu_procedural_volume.generate = true;
+            dirty_meshes.mark(entity);
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use bevy::{
+    prelude::*,
+    render::{
+        render_resource::{
+            BindGroup, BindGroupEntries, Buffer, BufferDescriptor, BufferUsages, PipelineCache,
+            UniformBuffer,
+        },
+        renderer::{RenderDevice, RenderQueue},
+        Extract,
+    },
+};
+
+use crate::{
+    bundles::volumetric_bundle::Volumetric,
+    channels::{ReadbackChannel, ReadbackTag, VoxelDataVersion},
+    render::{
+        prop_candidates_compute_pipeline::PropCandidatesComputePipeline,
+        voxel_mesh_compute_pipeline::DirtyMeshes,
+    },
+};
+
+use super::{
+    chunk_coord::ChunkCoord,
+    gpu_voxel_material::GpuVoxelMaterial,
+    prop_candidates::{PropCandidateData, PropCandidateParams, PropCandidates},
+    voxel_material::VoxelMaterialComponents,
+    voxel_world::VoxelWorldConfig,
+};
+
+pub struct GpuPropCandidates {
+    pub params: PropCandidateParams,
+    pub params_buffer: UniformBuffer<PropCandidateParams>,
+    pub points_buffer: Buffer,
+    pub points_staging_buffer: Buffer,
+    pub bind_group: Option<BindGroup>,
+    /// Whether the voxels or settings changed since the points were last read back.
+    pub changed: bool,
+}
+
+impl GpuPropCandidates {
+    pub fn new(render_device: &RenderDevice, params: PropCandidateParams) -> Self {
+        // The header takes the room of one point.
+        let size = (params.capacity as u64 + 1) * std::mem::size_of::<PropCandidateData>() as u64;
+
+        GpuPropCandidates {
+            params,
+            params_buffer: UniformBuffer::default(),
+            points_buffer: render_device.create_buffer(&BufferDescriptor {
+                label: Some("prop_candidates_points_buffer"),
+                size,
+                usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            points_staging_buffer: render_device.create_buffer(&BufferDescriptor {
+                label: Some("prop_candidates_points_staging_buffer"),
+                size,
+                usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            bind_group: None,
+            changed: true,
+        }
+    }
+
+    /// Initializes the [`GpuPropCandidates`] of newly added [`PropCandidates`], flags those whose
+    /// settings or chunk changed, recreating their buffers for a new capacity, and drops those of
+    /// removed ones.
+    pub fn extract(
+        render_device: Res<RenderDevice>,
+        mut gpu_prop_candidates: ResMut<VoxelMaterialComponents<GpuPropCandidates>>,
+        config: Extract<Res<VoxelWorldConfig>>,
+        candidates_query: Extract<Query<(Entity, &PropCandidates, Option<&ChunkCoord>)>>,
+    ) {
+        gpu_prop_candidates
+            .0
+            .retain(|entity, _| candidates_query.contains(*entity));
+
+        for (entity, prop_candidates, coord) in candidates_query.iter() {
+            let params = prop_candidates.params(coord, &config);
+            let gpu_candidates = gpu_prop_candidates
+                .0
+                .entry(entity)
+                .or_insert_with(|| GpuPropCandidates::new(render_device.as_ref(), params));
+
+            if gpu_candidates.params.capacity != params.capacity {
+                *gpu_candidates = GpuPropCandidates::new(render_device.as_ref(), params);
+            } else if gpu_candidates.params != params {
+                gpu_candidates.params = params;
+                gpu_candidates.changed = true;
+            }
+        }
+    }
+
+    /// Flags the candidates of the entities remeshed this frame, whatever changed their voxels,
+    /// and binds the points of each changed one to the voxels buffer of its [`GpuVoxelMaterial`].
+    pub fn prepare(
+        render_device: Res<RenderDevice>,
+        render_queue: Res<RenderQueue>,
+        candidates_pipeline: Res<PropCandidatesComputePipeline>,
+        dirty_meshes: Res<DirtyMeshes>,
+        gpu_voxel_materials: Res<VoxelMaterialComponents<GpuVoxelMaterial>>,
+        mut gpu_prop_candidates: ResMut<VoxelMaterialComponents<GpuPropCandidates>>,
+    ) {
+        for (entity, gpu_candidates) in gpu_prop_candidates.0.iter_mut() {
+            gpu_candidates.changed |= dirty_meshes.is_meshed(entity);
+            if !gpu_candidates.changed {
+                continue;
+            }
+            let Some(voxels_binding) = gpu_voxel_materials
+                .get(entity)
+                .and_then(|gpu_voxel_material| gpu_voxel_material.voxels_buffer.binding())
+            else {
+                gpu_candidates.bind_group = None;
+                continue;
+            };
+
+            gpu_candidates.params_buffer.set(gpu_candidates.params);
+            gpu_candidates
+                .params_buffer
+                .write_buffer(render_device.as_ref(), render_queue.as_ref());
+
+            gpu_candidates.bind_group = Some(render_device.create_bind_group(
+                "GpuPropCandidates::bind_group",
+                &candidates_pipeline.bind_group_layout,
+                &BindGroupEntries::sequential((
+                    gpu_candidates.params_buffer.binding().expect(
+                        "Prop Candidate Params Buffer should have already been uploaded to the gpu",
+                    ),
+                    voxels_binding,
+                    gpu_candidates.points_buffer.as_entire_binding(),
+                )),
+            ));
+        }
+    }
+
+    /// Reads back the points emitted this frame through the [`ReadbackChannel`]. Points that fail
+    /// to map are emitted again on the next frame.
+    pub fn map_and_read_buffers(
+        render_device: Res<RenderDevice>,
+        pipeline_cache: Res<PipelineCache>,
+        candidates_pipeline: Res<PropCandidatesComputePipeline>,
+        mut gpu_prop_candidates: ResMut<VoxelMaterialComponents<GpuPropCandidates>>,
+        version_query: Query<Option<&VoxelDataVersion>, With<Volumetric>>,
+        channel: Res<ReadbackChannel<PropCandidateData>>,
+    ) {
+        if pipeline_cache
+            .get_compute_pipeline(candidates_pipeline.pipeline)
+            .is_none()
+        {
+            return;
+        }
+
+        for (entity, gpu_candidates) in gpu_prop_candidates.0.iter_mut() {
+            if !gpu_candidates.changed || gpu_candidates.bind_group.is_none() {
+                continue;
+            }
+            let Some(tag) = ReadbackTag::current(*entity, &version_query) else {
+                continue;
+            };
+
+            if channel
+                .read_buffer(&render_device, tag, &gpu_candidates.points_staging_buffer)
+                .is_ok()
+            {
+                gpu_candidates.changed = false;
+            }
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use bevy::{
+    prelude::*,
+    render::{
+        render_asset::RenderAssets,
+        render_resource::{BindGroup, BindGroupEntries, UniformBuffer},
+        renderer::{RenderDevice, RenderQueue},
+        texture::GpuImage,
+        Extract,
+    },
+};
+
+use crate::render::{
+    splat_map_compute_pipeline::SplatMapComputePipeline, voxel_mesh_compute_pipeline::DirtyMeshes,
+};
+
+use super::{
+    gpu_voxel_material::GpuVoxelMaterial,
+    splat_map::{SplatMap, SplatMapParams},
+    voxel_material::VoxelMaterialComponents,
+};
+
+pub struct GpuSplatMap {
+    pub params_buffer: UniformBuffer<SplatMapParams>,
+    pub image: Handle<Image>,
+    pub bind_group: Option<BindGroup>,
+}
+
+impl GpuSplatMap {
+    /// Initializes the [`GpuSplatMap`] of newly added [`SplatMap`]s.
+    pub fn initialize(
+        mut gpu_splat_maps: ResMut<VoxelMaterialComponents<GpuSplatMap>>,
+        splat_map_query: Extract<Query<(Entity, &SplatMap), Added<SplatMap>>>,
+    ) {
+        for (entity, splat_map) in splat_map_query.iter() {
+            gpu_splat_maps.insert(
+                entity,
+                GpuSplatMap {
+                    params_buffer: UniformBuffer::default(),
+                    image: splat_map.image.clone(),
+                    bind_group: None,
+                },
+            );
+        }
+    }
+
+    /// Uploads the settings of the changed [`SplatMap`]s, remeshing their entities so that their
+    /// splat texture is generated again.
+    pub fn extract(
+        render_queue: Res<RenderQueue>,
+        render_device: Res<RenderDevice>,
+        mut gpu_splat_maps: ResMut<VoxelMaterialComponents<GpuSplatMap>>,
+        mut dirty_meshes: ResMut<DirtyMeshes>,
+        splat_map_query: Extract<Query<(Entity, Ref<SplatMap>)>>,
+    ) {
+        for (entity, splat_map) in splat_map_query.iter() {
+            if !splat_map.is_changed() {
+                continue;
+            }
+            if let Some(gpu_splat_map) = gpu_splat_maps.get_mut(&entity) {
+                gpu_splat_map.image = splat_map.image.clone();
+                gpu_splat_map
+                    .params_buffer
+                    .set(SplatMapParams::from(&*splat_map));
+                gpu_splat_map
+                    .params_buffer
+                    .write_buffer(render_device.as_ref(), render_queue.as_ref());
+                dirty_meshes.mark(entity);
+            }
+        }
+    }
+
+    /// Binds the splat texture of each [`GpuSplatMap`] and the voxels buffer of the entity's
+    /// [`GpuVoxelMaterial`].
+    pub fn prepare(
+        render_device: Res<RenderDevice>,
+        splat_map_pipeline: Res<SplatMapComputePipeline>,
+        gpu_images: Res<RenderAssets<GpuImage>>,
+        gpu_voxel_materials: Res<VoxelMaterialComponents<GpuVoxelMaterial>>,
+        mut gpu_splat_maps: ResMut<VoxelMaterialComponents<GpuSplatMap>>,
+    ) {
+        for (entity, gpu_splat_map) in gpu_splat_maps.0.iter_mut() {
+            let (Some(gpu_voxel_material), Some(gpu_image)) = (
+                gpu_voxel_materials.get(entity),
+                gpu_images.get(&gpu_splat_map.image),
+            ) else {
+                continue;
+            };
+
+            gpu_splat_map.bind_group = Some(
+                render_device.create_bind_group(
+                    "GpuSplatMap::bind_group",
+                    &splat_map_pipeline.bind_group_layout,
+                    &BindGroupEntries::sequential((
+                        gpu_splat_map.params_buffer.binding().expect(
+                            "Splat Map Params Buffer should have already been uploaded to the gpu",
+                        ),
+                        gpu_voxel_material
+                            .voxels_buffer
+                            .binding()
+                            .expect("Voxels Buffer should have already been uploaded to the gpu"),
+                        &gpu_image.texture_view,
+                    )),
+                ),
+            );
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use bevy::{
+    prelude::*,
+    render::{
+        render_resource::{
+            encase::StorageBuffer, BindGroup, BindGroupEntries, Extent3d, ImageCopyTexture,
+            ImageDataLayout, Origin3d, ShaderType, Texture, TextureAspect, TextureDescriptor,
+            TextureDimension, TextureFormat, TextureUsages, TextureView, TextureViewDescriptor,
+        },
+        renderer::{RenderDevice, RenderQueue},
+        Extract,
+    },
+    utils::HashMap,
+};
+
+use crate::{
+    bundles::volumetric_bundle::Volumetric,
+    render::voxel_mesh_compute_pipeline::{DirtyMeshes, VoxelMeshComputePipeline},
+    CHUNK_SZ_3,
+};
+
+use super::{
+    gpu_voxel_material::GpuVoxelMaterial, mesh_buffer_sizing::MeshBufferSizing,
+    meshing_algorithm::MeshingAlgorithm, virtual_volume::VirtualVolume, voxel::Voxel,
+    voxel_material::VoxelMaterialComponents,
+};
+
+/// The page table of a [`VirtualVolume`]. Its bricks are stored in the voxels buffer of the
+/// entity's [`GpuVoxelMaterial`], which is sized to hold `pool_capacity` bricks.
+pub struct GpuVirtualVolume {
+    pub extent: UVec3,
+    /// One texel per brick, holding the pool slot + 1 of resident bricks and 0 otherwise.
+    pub page_table: Texture,
+    pub page_table_view: TextureView,
+    pub bind_group: BindGroup,
+    slots: HashMap<UVec3, u32>,
+    free_slots: Vec<u32>,
+}
+
+/// A virtual volume along with the meshing algorithm of its entity.
+type VirtualVolumeItem = (
+    Entity,
+    &'static VirtualVolume,
+    Option<&'static MeshingAlgorithm>,
+);
+
+impl GpuVirtualVolume {
+    pub fn new(
+        render_device: &RenderDevice,
+        voxel_pipeline: &VoxelMeshComputePipeline,
+        virtual_volume: &VirtualVolume,
+    ) -> Self {
+        let page_table = render_device.create_texture(&TextureDescriptor {
+            label: Some("page_table"),
+            size: Extent3d {
+                width: virtual_volume.extent.x,
+                height: virtual_volume.extent.y,
+                depth_or_array_layers: virtual_volume.extent.z,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D3,
+            format: TextureFormat::R32Uint,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let page_table_view = page_table.create_view(&TextureViewDescriptor::default());
+
+        let bind_group = render_device.create_bind_group(
+            "GpuVirtualVolume::bind_group",
+            &voxel_pipeline.page_table_layout,
+            &BindGroupEntries::single(&page_table_view),
+        );
+
+        GpuVirtualVolume {
+            extent: virtual_volume.extent,
+            page_table,
+            page_table_view,
+            bind_group,
+            slots: HashMap::default(),
+            free_slots: (0..virtual_volume.pool_capacity).rev().collect(),
+        }
+    }
+
+    /// Initializes the [`GpuVirtualVolume`] and the brick pool of newly created [`VirtualVolume`]s.
+    #[allow(clippy::too_many_arguments)]
+    pub fn initialize(
+        render_queue: Res<RenderQueue>,
+        render_device: Res<RenderDevice>,
+        voxel_pipeline: Res<VoxelMeshComputePipeline>,
+        mut gpu_voxel_materials: ResMut<VoxelMaterialComponents<GpuVoxelMaterial>>,
+        mut gpu_virtual_volumes: ResMut<VoxelMaterialComponents<GpuVirtualVolume>>,
+        mut dirty_meshes: ResMut<DirtyMeshes>,
+        sizing: Extract<Res<MeshBufferSizing>>,
+        virtual_volume_query: Extract<Query<VirtualVolumeItem, Added<Volumetric>>>,
+    ) {
+        for (entity, virtual_volume, meshing_algorithm) in virtual_volume_query.iter() {
+            let voxel_capacity = virtual_volume.pool_capacity as usize * CHUNK_SZ_3;
+            let gpu_voxel_material = GpuVoxelMaterial::from_voxels(
+                render_device.as_ref(),
+                render_queue.as_ref(),
+                &[],
+                voxel_capacity,
+                sizing.capacities(
+                    meshing_algorithm.copied().unwrap_or_default(),
+                    voxel_capacity,
+                ),
+            );
+
+            gpu_voxel_materials.insert(entity, gpu_voxel_material);
+            gpu_virtual_volumes.insert(
+                entity,
+                GpuVirtualVolume::new(render_device.as_ref(), &voxel_pipeline, virtual_volume),
+            );
+            dirty_meshes.mark(entity);
+        }
+    }
+
+    /// Uploads the bricks inserted into each [`VirtualVolume`] since the last frame and clears the
+    /// page table entries of the evicted ones.
+    pub fn extract(
+        render_queue: Res<RenderQueue>,
+        gpu_voxel_materials: Res<VoxelMaterialComponents<GpuVoxelMaterial>>,
+        mut gpu_virtual_volumes: ResMut<VoxelMaterialComponents<GpuVirtualVolume>>,
+        mut dirty_meshes: ResMut<DirtyMeshes>,
+        virtual_volume_query: Extract<Query<(Entity, &VirtualVolume)>>,
+    ) {
+        for (entity, virtual_volume) in virtual_volume_query.iter() {
+            let (Some(gpu_virtual_volume), Some(gpu_voxel_material)) = (
+                gpu_virtual_volumes.get_mut(&entity),
+                gpu_voxel_materials.get(&entity),
+            ) else {
+                continue;
+            };
+            let Some(pool) = gpu_voxel_material.voxels_buffer.buffer() else {
+                continue;
+            };
+
+            if virtual_volume.changed_bricks().next().is_some() {
+                dirty_meshes.mark(entity);
+            }
+
+            // Free the slots of evicted bricks first so that they can be reused this frame.
+            for brick in virtual_volume.changed_bricks() {
+                if virtual_volume.is_resident(brick) {
+                    continue;
+                }
+                if let Some(slot) = gpu_virtual_volume.slots.remove(&brick) {
+                    gpu_virtual_volume.free_slots.push(slot);
+                    gpu_virtual_volume.write_page_table_entry(&render_queue, brick, 0);
+                }
+            }
+
+            for brick in virtual_volume.changed_bricks() {
+                let Some(voxels) = virtual_volume.brick(brick) else {
+                    continue;
+                };
+                let slot = match gpu_virtual_volume.slots.get(&brick) {
+                    Some(slot) => *slot,
+                    None => {
+                        let Some(slot) = gpu_virtual_volume.free_slots.pop() else {
+                            warn!("No free brick slot for {brick} of {entity}");
+                            continue;
+                        };
+                        gpu_virtual_volume.slots.insert(brick, slot);
+                        gpu_virtual_volume.write_page_table_entry(&render_queue, brick, slot + 1);
+                        slot
+                    }
+                };
+
+                let mut bytes = StorageBuffer::new(Vec::<u8>::new());
+                bytes
+                    .write(voxels)
+                    .expect("Bricks should be writable to a byte buffer");
+
+                let offset = slot as u64 * CHUNK_SZ_3 as u64 * Voxel::min_size().get();
+                render_queue.write_buffer(pool, offset, bytes.as_ref());
+            }
+        }
+    }
+
+    fn write_page_table_entry(&self, render_queue: &RenderQueue, brick: UVec3, entry: u32) {
+        render_queue.write_texture(
+            ImageCopyTexture {
+                texture: &self.page_table,
+                mip_level: 0,
+                origin: Origin3d {
+                    x: brick.x,
+                    y: brick.y,
+                    z: brick.z,
+                },
+                aspect: TextureAspect::All,
+            },
+            &entry.to_le_bytes(),
+            ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(4),
+                rows_per_image: Some(1),
+            },
+            Extent3d {
+                width: 1,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+}
@@ -0,0 +1,163 @@
+use bevy::{
+    prelude::*,
+    render::{
+        render_resource::{
+            BindGroup, BindGroupEntries, Buffer, BufferDescriptor, BufferUsages, PipelineCache,
+            ShaderType, UniformBuffer,
+        },
+        renderer::{RenderDevice, RenderQueue},
+        Extract,
+    },
+};
+
+use crate::{
+    bundles::volumetric_bundle::Volumetric,
+    channels::{ReadbackChannel, ReadbackTag, VoxelDataVersion},
+    render::volume_statistics_compute_pipeline::VolumeStatisticsComputePipeline,
+};
+
+use super::{
+    gpu_voxel_material::GpuVoxelMaterial,
+    volume_statistics::{
+        VolumeHistogram, VolumeHistogramData, VolumeStatistics, VolumeStatisticsParams,
+    },
+    voxel_material::{VoxelMaterial, VoxelMaterialComponents},
+};
+
+pub struct GpuVolumeStatistics {
+    pub params_buffer: UniformBuffer<VolumeStatisticsParams>,
+    pub histogram_buffer: Buffer,
+    pub histogram_staging_buffer: Buffer,
+    pub bind_group: Option<BindGroup>,
+    /// Whether the voxels changed since the histogram was last read back.
+    pub changed: bool,
+}
+
+impl GpuVolumeStatistics {
+    pub fn new(render_device: &RenderDevice) -> Self {
+        let size = VolumeHistogram::min_size().get();
+
+        GpuVolumeStatistics {
+            params_buffer: UniformBuffer::default(),
+            histogram_buffer: render_device.create_buffer(&BufferDescriptor {
+                label: Some("volume_histogram_buffer"),
+                size,
+                usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            histogram_staging_buffer: render_device.create_buffer(&BufferDescriptor {
+                label: Some("volume_histogram_staging_buffer"),
+                size,
+                usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            bind_group: None,
+            changed: true,
+        }
+    }
+
+    /// Initializes the [`GpuVolumeStatistics`] of newly added [`VolumeStatistics`] and flags those
+    /// whose voxels changed.
+    #[allow(clippy::type_complexity)]
+    pub fn extract(
+        render_queue: Res<RenderQueue>,
+        render_device: Res<RenderDevice>,
+        mut gpu_volume_statistics: ResMut<VoxelMaterialComponents<GpuVolumeStatistics>>,
+        statistics_query: Extract<Query<(Entity, Ref<VoxelMaterial>), With<VolumeStatistics>>>,
+    ) {
+        for (entity, voxel_material) in statistics_query.iter() {
+            let gpu_statistics = gpu_volume_statistics
+                .0
+                .entry(entity)
+                .or_insert_with(|| GpuVolumeStatistics::new(render_device.as_ref()));
+
+            if !gpu_statistics.changed && !voxel_material.is_changed() {
+                continue;
+            }
+
+            gpu_statistics.changed = true;
+            gpu_statistics.params_buffer.set(VolumeStatisticsParams {
+                voxel_count: voxel_material.voxels().len() as u32,
+            });
+            gpu_statistics
+                .params_buffer
+                .write_buffer(render_device.as_ref(), render_queue.as_ref());
+        }
+    }
+
+    /// Binds the histogram of each changed [`GpuVolumeStatistics`] and the voxels buffer of the
+    /// entity's [`GpuVoxelMaterial`].
+    pub fn prepare(
+        render_device: Res<RenderDevice>,
+        statistics_pipeline: Res<VolumeStatisticsComputePipeline>,
+        gpu_voxel_materials: Res<VoxelMaterialComponents<GpuVoxelMaterial>>,
+        mut gpu_volume_statistics: ResMut<VoxelMaterialComponents<GpuVolumeStatistics>>,
+    ) {
+        for (entity, gpu_statistics) in gpu_volume_statistics.0.iter_mut() {
+            if !gpu_statistics.changed {
+                continue;
+            }
+            let Some(voxels_binding) = gpu_voxel_materials
+                .get(entity)
+                .and_then(|gpu_voxel_material| gpu_voxel_material.voxels_buffer.binding())
+            else {
+                gpu_statistics.bind_group = None;
+                continue;
+            };
+
+            gpu_statistics.bind_group = Some(
+                render_device.create_bind_group(
+                    "GpuVolumeStatistics::bind_group",
+                    &statistics_pipeline.bind_group_layout,
+                    &BindGroupEntries::sequential((
+                        gpu_statistics.params_buffer.binding().expect(
+                            "Volume Statistics Params Buffer should have already been uploaded to the gpu",
+                        ),
+                        voxels_binding,
+                        gpu_statistics.histogram_buffer.as_entire_binding(),
+                    )),
+                ),
+            );
+        }
+    }
+
+    /// Reads back the histograms computed this frame through the [`ReadbackChannel`]. Histograms
+    /// that fail to map are computed again on the next frame, those of despawned entities are
+    /// dropped without being mapped.
+    pub fn map_and_read_buffers(
+        render_device: Res<RenderDevice>,
+        pipeline_cache: Res<PipelineCache>,
+        statistics_pipeline: Res<VolumeStatisticsComputePipeline>,
+        mut gpu_volume_statistics: ResMut<VoxelMaterialComponents<GpuVolumeStatistics>>,
+        version_query: Query<Option<&VoxelDataVersion>, With<Volumetric>>,
+        channel: Res<ReadbackChannel<VolumeHistogramData>>,
+    ) {
+        gpu_volume_statistics
+            .0
+            .retain(|entity, _| version_query.contains(*entity));
+
+        if !statistics_pipeline.is_ready(&pipeline_cache) {
+            return;
+        }
+
+        for (entity, gpu_statistics) in gpu_volume_statistics.0.iter_mut() {
+            if !gpu_statistics.changed || gpu_statistics.bind_group.is_none() {
+                continue;
+            }
+            let Some(tag) = ReadbackTag::current(*entity, &version_query) else {
+                continue;
+            };
+
+            if channel
+                .read_buffer(
+                    &render_device,
+                    tag,
+                    &gpu_statistics.histogram_staging_buffer,
+                )
+                .is_ok()
+            {
+                gpu_statistics.changed = false;
+            }
+        }
+    }
+}
@@ -1,23 +1,39 @@
-use std::num::NonZeroU64;
+use std::ops::Range;
 
 use bevy::{
     prelude::*,
     render::{
-        render_resource::{Buffer, BufferDescriptor, BufferUsages, BufferVec, ShaderType},
+        render_resource::{
+            encase::{internal::WriteInto, StorageBuffer},
+            Buffer, BufferDescriptor, BufferUsages, BufferVec, CommandEncoder,
+            CommandEncoderDescriptor, ShaderType, UniformBuffer,
+        },
         renderer::{RenderDevice, RenderQueue},
         Extract,
     },
 };
 
 use crate::{
-    bundles::volumetric_bundle::Volumetric, render::voxel_mesh_compute_pipeline::VertexBuffer,
+    bundles::volumetric_bundle::Volumetric,
+    core::tables::{EDGE_TABLE, TRI_TABLE},
+    render::voxel_mesh_compute_pipeline::{DirtyMeshes, VertexBuffer},
 };
 
 use super::{
-    edge_table::EDGE_TABLE,
-    triangle_table::TRI_TABLE,
+    adaptive_resolution::AdaptiveResolutionParams,
+    atomics::Atomics,
+    chunk_priority::ChunkPriority,
+    clip_planes::ClipPlanesParams,
+    dual_contouring::DualContouringParams,
+    iso_surface::IsoSurfaceParams,
+    mesh_buffer_sizing::MeshBufferSizing,
+    meshing_algorithm::MeshingAlgorithm,
+    normal_encoding::EncodedNormal,
+    readback_header::MeshReadbackHeader,
+    upload_budget::{UploadBudget, UploadQueue},
     voxel::Voxel,
     voxel_material::{VoxelMaterial, VoxelMaterialComponents},
+    voxel_world::DirtyRegion,
 };
 
 #[derive(Component)]
@@ -26,12 +42,72 @@ pub struct GpuVoxelMaterial {
     pub edge_table_buffer: BufferVec<u32>,
     pub tri_table_buffer: BufferVec<[i32; 16]>,
     pub vertices_buffer: BufferVec<Vec4>,
-    pub normals_buffer: BufferVec<Vec4>,
+    pub normals_buffer: BufferVec<EncodedNormal>,
     pub uvs_buffer: BufferVec<Vec2>,
+    pub tangents_buffer: BufferVec<Vec4>,
+    /// The emissive color of each vertex, packed as RGB9E5.
+    pub emissives_buffer: BufferVec<u32>,
     pub indices_buffer: BufferVec<u32>,
     pub atomics_buffer: BufferVec<u32>,
+    pub dual_contouring_params_buffer: UniformBuffer<DualContouringParams>,
+    /// The iso-level of the surface meshed into these buffers, further surfaces are meshed into
+    /// their [`GpuIsoSurface`](super::gpu_iso_surface::GpuIsoSurface).
+    pub iso_surface_params_buffer: UniformBuffer<IsoSurfaceParams>,
+    pub clip_planes_buffer: UniformBuffer<ClipPlanesParams>,
+    pub adaptive_resolution_buffer: UniformBuffer<AdaptiveResolutionParams>,
 
+    /// Receives a [`MeshReadbackHeader`] and a page of vertices for readback.
     pub vertices_staging_buffer: Buffer,
+    /// Receives the vertex and index counts along with the first page of vertices read back.
+    pub atomics_staging_buffer: Buffer,
+    /// The vertices and indices of the last mesh read back, known once its first page is read.
+    pub mesh_counts: Option<MeshCounts>,
+    /// The capacities the output buffers were sized for by the [`MeshBufferSizing`].
+    full_capacities: OutputCapacities,
+}
+
+/// The vertices and indices generated into the output buffers of a [`GpuVoxelMaterial`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MeshCounts {
+    pub vertices: u32,
+    pub indices: u32,
+}
+
+/// Elements each output buffer of a [`GpuVoxelMaterial`] can hold, see [`MeshBufferSizing`]. The
+/// vertices staging buffer holds as many vertices as the vertices buffer, after a
+/// [`MeshReadbackHeader`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OutputCapacities {
+    pub vertices: usize,
+    /// Elements of the normals, uvs, tangents and emissives buffers.
+    pub attributes: usize,
+    pub indices: usize,
+}
+
+impl OutputCapacities {
+    /// The capacities just holding `mesh_counts` with some `headroom`, at most `self`.
+    pub fn snug(&self, mesh_counts: MeshCounts, headroom: f32) -> Self {
+        let snug = |count: u32, capacity: usize| {
+            ((count as f32 * headroom.max(1.0)).ceil() as usize).clamp(1, capacity.max(1))
+        };
+        let vertices = snug(mesh_counts.vertices, self.vertices);
+        Self {
+            vertices,
+            attributes: snug(mesh_counts.vertices, self.attributes),
+            indices: snug(mesh_counts.indices, self.indices),
+        }
+    }
+
+    /// Bytes of the output buffers and of the vertices staging buffer.
+    pub fn size(&self) -> u64 {
+        let vec4 = std::mem::size_of::<Vec4>() as u64;
+        let vec2 = std::mem::size_of::<Vec2>() as u64;
+        let u32 = std::mem::size_of::<u32>() as u64;
+        let vertices = self.vertices as u64 * (vec4 + VertexBuffer::min_size().get());
+        let normal = std::mem::size_of::<EncodedNormal>() as u64;
+        let attributes = self.attributes as u64 * (vec4 + normal + vec2 + u32);
+        vertices + attributes + self.indices as u64 * u32
+    }
 }
 
 impl GpuVoxelMaterial {
@@ -39,13 +115,32 @@ impl GpuVoxelMaterial {
         render_device: &RenderDevice,
         render_queue: &RenderQueue,
         voxel_material: &VoxelMaterial,
+        capacities: OutputCapacities,
+    ) -> Self {
+        Self::from_voxels(
+            render_device,
+            render_queue,
+            voxel_material.voxels(),
+            voxel_material.chunk_size as usize,
+            capacities,
+        )
+    }
+
+    /// Creates the buffers for meshing up to `voxel_capacity` voxels into output buffers of
+    /// `capacities`, uploading `voxels` to the start of the voxels buffer.
+    pub fn from_voxels(
+        render_device: &RenderDevice,
+        render_queue: &RenderQueue,
+        voxels: &[Voxel],
+        voxel_capacity: usize,
+        capacities: OutputCapacities,
     ) -> Self {
         let mut voxels_buffer =
             BufferVec::<Voxel>::new(BufferUsages::STORAGE | BufferUsages::COPY_SRC);
 
-        voxels_buffer.reserve(voxel_material.chunk_size as usize, render_device);
+        voxels_buffer.reserve(voxel_capacity, render_device);
 
-        for voxel in &voxel_material.voxels {
+        for voxel in voxels {
             voxels_buffer.push(*voxel);
         }
         voxels_buffer.write_buffer(render_device, render_queue);
@@ -71,12 +166,16 @@ impl GpuVoxelMaterial {
         let mut vertices_buffer = BufferVec::<Vec4>::new(
             BufferUsages::STORAGE | BufferUsages::COPY_SRC | BufferUsages::COPY_DST,
         );
-        vertices_buffer.reserve((voxel_material.chunk_size as usize), render_device);
+        vertices_buffer.reserve(capacities.vertices, render_device);
 
-        let vertices_staging_buffer = render_device.create_buffer(&BufferDescriptor {
-            label: Some("vertices_staging_buffer"),
-            size: (VertexBuffer::min_size()
-                .checked_mul(NonZeroU64::new_unchecked(voxel_material.chunk_size))),
+        let vertices_staging_buffer = Self::create_vertices_staging_buffer(
+            render_device,
+            Self::vertices_staging_size(capacities.vertices),
+        );
+
+        let atomics_staging_buffer = render_device.create_buffer(&BufferDescriptor {
+            label: Some("atomics_staging_buffer"),
+            size: Atomics::min_size().get(),
             usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
@@ -84,22 +183,44 @@ impl GpuVoxelMaterial {
         let mut uvs_buffer = BufferVec::<Vec2>::new(
             BufferUsages::STORAGE | BufferUsages::COPY_SRC | BufferUsages::COPY_DST,
         );
-        uvs_buffer.reserve((voxel_material.chunk_size as usize) * 4 * 6, render_device);
+        uvs_buffer.reserve(capacities.attributes, render_device);
+
+        let mut normals_buffer = BufferVec::<EncodedNormal>::new(
+            BufferUsages::STORAGE | BufferUsages::COPY_SRC | BufferUsages::COPY_DST,
+        );
+        normals_buffer.reserve(capacities.attributes, render_device);
+
+        let mut tangents_buffer = BufferVec::<Vec4>::new(
+            BufferUsages::STORAGE | BufferUsages::COPY_SRC | BufferUsages::COPY_DST,
+        );
+        tangents_buffer.reserve(capacities.attributes, render_device);
 
-        let mut normals_buffer = BufferVec::<Vec4>::new(
+        let mut emissives_buffer = BufferVec::<u32>::new(
             BufferUsages::STORAGE | BufferUsages::COPY_SRC | BufferUsages::COPY_DST,
         );
-        normals_buffer.reserve((voxel_material.chunk_size as usize) * 4 * 6, render_device);
+        emissives_buffer.reserve(capacities.attributes, render_device);
 
         let mut indices_buffer = BufferVec::<u32>::new(
             BufferUsages::STORAGE | BufferUsages::COPY_SRC | BufferUsages::COPY_DST,
         );
-        indices_buffer.reserve((voxel_material.chunk_size as usize) * 6 * 6, render_device);
+        indices_buffer.reserve(capacities.indices, render_device);
 
         let mut atomics_buffer = BufferVec::<u32>::new(
             BufferUsages::STORAGE | BufferUsages::COPY_SRC | BufferUsages::COPY_DST,
         );
-        atomics_buffer.reserve(2, render_device);
+        atomics_buffer.reserve(Atomics::LEN, render_device);
+
+        let mut dual_contouring_params_buffer = UniformBuffer::<DualContouringParams>::default();
+        dual_contouring_params_buffer.write_buffer(render_device, render_queue);
+
+        let mut iso_surface_params_buffer = UniformBuffer::<IsoSurfaceParams>::default();
+        iso_surface_params_buffer.write_buffer(render_device, render_queue);
+
+        let mut clip_planes_buffer = UniformBuffer::<ClipPlanesParams>::default();
+        clip_planes_buffer.write_buffer(render_device, render_queue);
+
+        let mut adaptive_resolution_buffer = UniformBuffer::<AdaptiveResolutionParams>::default();
+        adaptive_resolution_buffer.write_buffer(render_device, render_queue);
 
         GpuVoxelMaterial {
             voxels_buffer,
@@ -107,57 +228,370 @@ impl GpuVoxelMaterial {
             tri_table_buffer,
             vertices_buffer,
             vertices_staging_buffer,
+            atomics_staging_buffer,
             normals_buffer,
             uvs_buffer,
+            tangents_buffer,
+            emissives_buffer,
             indices_buffer,
             atomics_buffer,
+            dual_contouring_params_buffer,
+            iso_surface_params_buffer,
+            clip_planes_buffer,
+            adaptive_resolution_buffer,
+            mesh_counts: None,
+            full_capacities: capacities,
         }
     }
 
-    /// Initializes the [`GpuVoxelMaterial`] of newly created [`VoxelMaterial`].
-    pub fn initialize(
-        mut commands: Commands,
-        render_queue: Res<RenderQueue>,
-        render_device: Res<RenderDevice>,
-        mut gpu_voxel_materials: ResMut<VoxelMaterialComponents<GpuVoxelMaterial>>,
-        voxel_material_query: Extract<Query<(Entity, &VoxelMaterial), Added<Volumetric>>>,
-    ) -> () {
-        for (entity, voxel_material) in voxel_material_query.iter() {
-            let mut gpu_voxel_material = GpuVoxelMaterial::new(
-                render_device.as_ref(),
-                render_queue.as_ref(),
-                voxel_material,
-            );
-
-            gpu_voxel_material
-                .vertices_buffer
-                .write_buffer(render_device.as_ref(), render_queue.as_ref());
-
-            gpu_voxel_materials.insert(entity, gpu_voxel_material);
-
-            commands.get_or_spawn(entity).insert(Volumetric);
+    /// Bytes of a vertices staging buffer holding a [`MeshReadbackHeader`] followed by `vertices`
+    /// vertices, so that a full vertices buffer is read back in a single page.
+    fn vertices_staging_size(vertices: usize) -> u64 {
+        MeshReadbackHeader::SIZE + VertexBuffer::min_size().get() * vertices as u64
+    }
+
+    fn create_vertices_staging_buffer(render_device: &RenderDevice, size: u64) -> Buffer {
+        render_device.create_buffer(&BufferDescriptor {
+            label: Some("vertices_staging_buffer"),
+            size,
+            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    /// Bytes of GPU memory held by the buffers.
+    pub fn gpu_bytes(&self) -> u64 {
+        let size = |buffer: Option<&Buffer>| buffer.map_or(0, |buffer| buffer.size());
+        [
+            size(self.voxels_buffer.buffer()),
+            size(self.edge_table_buffer.buffer()),
+            size(self.tri_table_buffer.buffer()),
+            size(self.vertices_buffer.buffer()),
+            size(self.normals_buffer.buffer()),
+            size(self.uvs_buffer.buffer()),
+            size(self.tangents_buffer.buffer()),
+            size(self.emissives_buffer.buffer()),
+            size(self.indices_buffer.buffer()),
+            size(self.atomics_buffer.buffer()),
+            size(self.dual_contouring_params_buffer.buffer()),
+            size(self.iso_surface_params_buffer.buffer()),
+            size(self.clip_planes_buffer.buffer()),
+            size(self.adaptive_resolution_buffer.buffer()),
+            self.vertices_staging_buffer.size(),
+            self.atomics_staging_buffer.size(),
+        ]
+        .into_iter()
+        .sum()
+    }
+
+    /// Number of indices the index buffer can hold.
+    pub fn index_capacity(&self) -> u32 {
+        self.indices_buffer.buffer().map_or(0, |buffer| {
+            buffer.size() / std::mem::size_of::<u32>() as u64
+        }) as u32
+    }
+
+    /// The capacities of the output buffers, smaller than those of the voxel capacity once
+    /// compacted.
+    pub fn output_capacities(&self) -> OutputCapacities {
+        OutputCapacities {
+            vertices: self.vertices_buffer.capacity(),
+            attributes: self.normals_buffer.capacity(),
+            indices: self.indices_buffer.capacity(),
         }
     }
 
-    /// Extracts the current data from all [`VoxelMaterial`]s into the corresponding [`GpuVoxelMaterial`]s.
+    /// The capacities of the output buffers sized by the [`MeshBufferSizing`], larger than those
+    /// of compacted buffers.
+    pub fn full_output_capacities(&self) -> OutputCapacities {
+        self.full_capacities
+    }
+
+    /// Resizes the output buffers to new full `capacities`, e.g. after the [`MeshBufferSizing`] or
+    /// the meshing algorithm changed.
+    pub fn set_full_output_capacities(
+        &mut self,
+        render_device: &RenderDevice,
+        render_queue: &RenderQueue,
+        capacities: OutputCapacities,
+    ) {
+        self.full_capacities = capacities;
+        self.resize_output_buffers(render_device, render_queue, capacities);
+    }
+
+    /// Reallocates the output buffers to `capacities`, copying as much of their contents as fits
+    /// so that the current mesh can still be read back. The vertices staging buffer, which is only
+    /// mapped while a readback is in progress, is recreated empty.
+    pub fn resize_output_buffers(
+        &mut self,
+        render_device: &RenderDevice,
+        render_queue: &RenderQueue,
+        capacities: OutputCapacities,
+    ) {
+        let mut command_encoder = render_device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("resize_output_buffers"),
+        });
+
+        let encoder = &mut command_encoder;
+        resize_buffer(
+            &mut self.vertices_buffer,
+            capacities.vertices,
+            render_device,
+            encoder,
+        );
+        resize_buffer(
+            &mut self.normals_buffer,
+            capacities.attributes,
+            render_device,
+            encoder,
+        );
+        resize_buffer(
+            &mut self.uvs_buffer,
+            capacities.attributes,
+            render_device,
+            encoder,
+        );
+        resize_buffer(
+            &mut self.tangents_buffer,
+            capacities.attributes,
+            render_device,
+            encoder,
+        );
+        resize_buffer(
+            &mut self.emissives_buffer,
+            capacities.attributes,
+            render_device,
+            encoder,
+        );
+        resize_buffer(
+            &mut self.indices_buffer,
+            capacities.indices,
+            render_device,
+            encoder,
+        );
+
+        render_queue.submit([command_encoder.finish()]);
+
+        self.vertices_staging_buffer = Self::create_vertices_staging_buffer(
+            render_device,
+            Self::vertices_staging_size(capacities.vertices),
+        );
+    }
+
+    /// Replaces the vertices staging buffer with a new one of the same size, e.g. after it could
+    /// not be mapped.
+    pub fn recreate_vertices_staging_buffer(&mut self, render_device: &RenderDevice) {
+        self.vertices_staging_buffer = Self::create_vertices_staging_buffer(
+            render_device,
+            self.vertices_staging_buffer.size(),
+        );
+    }
+
+    /// Copies the voxels of changed [`VoxelMaterial`]s into their [`ExtractedVoxelMaterial`], for
+    /// [`GpuVoxelMaterial::prepare`] to upload. Only the [`DirtyRegion`] is copied if there is one
+    /// and the voxels were not written outside of it, see [`VoxelMaterial::has_unrecorded_writes`].
+    /// The copies of entities no longer extracted are dropped.
+    #[allow(clippy::type_complexity)]
     pub fn extract(
+        mut extracted_voxel_materials: ResMut<VoxelMaterialComponents<ExtractedVoxelMaterial>>,
+        voxel_material_query: Extract<
+            Query<(Entity, Ref<VoxelMaterial>, Option<&DirtyRegion>), With<Volumetric>>,
+        >,
+    ) {
+        for (entity, voxel_material, dirty_region) in voxel_material_query.iter() {
+            if !voxel_material.is_changed() {
+                continue;
+            }
+
+            let dirty = dirty_region
+                .filter(|_| !voxel_material.has_unrecorded_writes())
+                .and_then(DirtyRegion::flat_range)
+                .filter(|range| range.end <= voxel_material.voxels().len());
+
+            match extracted_voxel_materials.get_mut(&entity) {
+                Some(extracted)
+                    if dirty.is_some()
+                        && extracted.chunk_size == voxel_material.chunk_size
+                        && extracted.voxels.len() == voxel_material.voxels().len() =>
+                {
+                    let range = dirty.expect("the dirty range was checked");
+                    extracted.voxels[range.clone()]
+                        .copy_from_slice(&voxel_material.voxels()[range.clone()]);
+
+                    // Merge with the voxels still waiting to be uploaded.
+                    extracted.dirty = match (extracted.changed, extracted.dirty.take()) {
+                        (false, _) => Some(range),
+                        (true, Some(pending)) => {
+                            Some(pending.start.min(range.start)..pending.end.max(range.end))
+                        }
+                        (true, None) => None,
+                    };
+                    extracted.changed = true;
+                }
+                Some(extracted) => {
+                    extracted.voxels.clear();
+                    extracted.voxels.extend_from_slice(voxel_material.voxels());
+                    extracted.chunk_size = voxel_material.chunk_size;
+                    extracted.dirty = None;
+                    extracted.changed = true;
+                }
+                None => extracted_voxel_materials.insert(
+                    entity,
+                    ExtractedVoxelMaterial {
+                        voxels: voxel_material.voxels().to_vec(),
+                        chunk_size: voxel_material.chunk_size,
+                        dirty: None,
+                        changed: true,
+                    },
+                ),
+            }
+        }
+
+        // Entities despawned without retiring would otherwise keep a copy of their voxels.
+        extracted_voxel_materials
+            .0
+            .retain(|entity, _| voxel_material_query.contains(*entity));
+    }
+
+    /// Uploads the changed [`ExtractedVoxelMaterial`]s into the persistent buffers of their
+    /// [`GpuVoxelMaterial`], creating the buffers of new materials and of materials that changed size.
+    /// Materials are uploaded by descending [`ChunkPriority`] until the [`UploadBudget`] is spent,
+    /// the others stay changed and wait in the [`UploadQueue`]. They are still marked dirty so that
+    /// the batches they were edited in are remeshed together once all of them are uploaded.
+    #[allow(clippy::too_many_arguments)]
+    pub fn prepare(
         render_queue: Res<RenderQueue>,
         render_device: Res<RenderDevice>,
+        mut extracted_voxel_materials: ResMut<VoxelMaterialComponents<ExtractedVoxelMaterial>>,
         mut gpu_voxel_materials: ResMut<VoxelMaterialComponents<GpuVoxelMaterial>>,
-        voxel_material_query: Extract<Query<(Entity, &VoxelMaterial), With<Volumetric>>>,
+        mut dirty_meshes: ResMut<DirtyMeshes>,
+        sizing: Res<MeshBufferSizing>,
+        budget: Res<UploadBudget>,
+        mut upload_queue: ResMut<UploadQueue>,
+        algorithm_query: Query<&MeshingAlgorithm>,
+        priority_query: Query<&ChunkPriority>,
     ) {
-        for (entity, voxel_material) in voxel_material_query.iter() {
-            let mut gpu_voxel_material = GpuVoxelMaterial::new(
-                render_device.as_ref(),
-                render_queue.as_ref(),
-                voxel_material,
-            );
-
-            gpu_voxel_material
-                .vertices_buffer
-                .write_buffer(render_device.as_ref(), render_queue.as_ref());
-
-            gpu_voxel_materials.insert(entity, gpu_voxel_material);
+        let UploadQueue {
+            uploaded,
+            pending,
+            bytes: uploaded_bytes,
+        } = upload_queue.as_mut();
+        uploaded.clear();
+        pending.clear();
+        *uploaded_bytes = 0;
+
+        let priority = |entity: &Entity| priority_query.get(*entity).map_or(0.0, |p| p.0);
+        let mut changed = extracted_voxel_materials
+            .0
+            .iter()
+            .filter(|(_, extracted)| extracted.changed)
+            .map(|(entity, _)| *entity)
+            .collect::<Vec<_>>();
+        changed.sort_by(|a, b| priority(b).total_cmp(&priority(a)));
+
+        for entity in &changed {
+            let Some(extracted) = extracted_voxel_materials.get_mut(entity) else {
+                continue;
+            };
+            let reused = gpu_voxel_materials
+                .get(entity)
+                .is_some_and(|gpu_voxel_material| {
+                    gpu_voxel_material.voxels_buffer.capacity() == extracted.chunk_size as usize
+                });
+            match (reused, &extracted.dirty) {
+                (true, Some(range)) => dirty_meshes.mark_region(*entity, range.clone()),
+                _ => dirty_meshes.mark(*entity),
+            }
+            let upload_len = match (reused, &extracted.dirty) {
+                (true, Some(range)) => range.len(),
+                _ => extracted.voxels.len(),
+            };
+            let upload_bytes = upload_len as u64 * Voxel::min_size().get();
+            let over_budget =
+                uploaded_bytes.saturating_add(upload_bytes) > budget.max_bytes_per_frame;
+            if over_budget && !uploaded.is_empty() {
+                pending.push(*entity);
+                continue;
+            }
+            uploaded.push(*entity);
+            *uploaded_bytes += upload_bytes;
+
+            extracted.changed = false;
+            let dirty = extracted.dirty.take();
+
+            let voxel_capacity = extracted.chunk_size as usize;
+
+            match gpu_voxel_materials.get_mut(entity) {
+                Some(gpu_voxel_material)
+                    if gpu_voxel_material.voxels_buffer.capacity() == voxel_capacity =>
+                {
+                    if let (Some(range), Some(buffer)) =
+                        (dirty, gpu_voxel_material.voxels_buffer.buffer())
+                    {
+                        let mut bytes = StorageBuffer::new(Vec::<u8>::new());
+                        bytes
+                            .write(&extracted.voxels[range.clone()])
+                            .expect("Voxels should be writable to a byte buffer");
+
+                        let offset = range.start as u64 * Voxel::min_size().get();
+                        render_queue.write_buffer(buffer, offset, bytes.as_ref());
+                        continue;
+                    }
+
+                    gpu_voxel_material.voxels_buffer.clear();
+                    for voxel in &extracted.voxels {
+                        gpu_voxel_material.voxels_buffer.push(*voxel);
+                    }
+                    gpu_voxel_material
+                        .voxels_buffer
+                        .write_buffer(render_device.as_ref(), render_queue.as_ref());
+                }
+                _ => {
+                    let gpu_voxel_material = GpuVoxelMaterial::from_voxels(
+                        render_device.as_ref(),
+                        render_queue.as_ref(),
+                        &extracted.voxels,
+                        voxel_capacity,
+                        sizing.capacities(
+                            algorithm_query.get(*entity).copied().unwrap_or_default(),
+                            voxel_capacity,
+                        ),
+                    );
+
+                    gpu_voxel_materials.insert(*entity, gpu_voxel_material);
+                }
+            }
         }
     }
 }
+
+/// Replaces `buffer_vec` with a buffer of `capacity` elements holding the start of its contents.
+fn resize_buffer<T: ShaderType + WriteInto>(
+    buffer_vec: &mut BufferVec<T>,
+    capacity: usize,
+    render_device: &RenderDevice,
+    command_encoder: &mut CommandEncoder,
+) {
+    let mut resized = BufferVec::<T>::new(
+        BufferUsages::STORAGE | BufferUsages::COPY_SRC | BufferUsages::COPY_DST,
+    );
+    resized.reserve(capacity, render_device);
+
+    if let (Some(source), Some(destination)) = (buffer_vec.buffer(), resized.buffer()) {
+        let size = source.size().min(destination.size());
+        if size > 0 {
+            command_encoder.copy_buffer_to_buffer(source, 0, destination, 0, size);
+        }
+    }
+    *buffer_vec = resized;
+}
+
+/// The voxels of a [`VoxelMaterial`] copied into the render world, waiting to be uploaded.
+pub struct ExtractedVoxelMaterial {
+    pub voxels: Vec<Voxel>,
+    pub chunk_size: u32,
+    /// The voxels changed since they were last uploaded, or `None` if all of them may have.
+    pub dirty: Option<Range<usize>>,
+    /// Whether the voxels changed since they were last uploaded.
+    pub changed: bool,
+}
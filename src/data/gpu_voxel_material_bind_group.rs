@@ -1,28 +1,19 @@
 use bevy::{
-    ecs::{
-        query::ROQueryItem,
-        system::{lifetimeless::SRes, SystemParamItem},
-    },
     prelude::*,
-    render::{
-        render_graph::{self, RenderLabel},
-        render_phase::{PhaseItem, RenderCommand, RenderCommandResult, TrackedRenderPass},
-        render_resource::{binding_types::storage_buffer, *},
-        renderer::{RenderContext, RenderDevice},
-        Extract, Render, RenderApp, RenderSet,
-    },
-    utils::{info, HashMap},
+    render::{render_resource::*, renderer::RenderDevice},
 };
 
 use crate::{
     bundles::volumetric_bundle::Volumetric,
-    render::voxel_mesh_compute_pipeline::VoxelMeshComputePipeline,
+    render::voxel_mesh_compute_pipeline::{
+        VoxelMeshComputePipeline, VoxelMeshPrefixSumPipelineIds,
+    },
 };
 
 use super::{
+    gpu_fixed_output_slots::{FixedSlotBuffers, GpuFixedOutputSlots},
     gpu_voxel_material::GpuVoxelMaterial,
-    voxel::Voxel,
-    voxel_material::{VoxelMaterial, VoxelMaterialComponents},
+    voxel_material::VoxelMaterialComponents,
 };
 
 pub struct GpuVoxelMaterialBindGroups(pub [BindGroup; 1]);
@@ -38,11 +29,61 @@ impl GpuVoxelMaterialBindGroups {
             vertices_buffer,
             normals_buffer,
             uvs_buffer,
+            tangents_buffer,
+            emissives_buffer,
             indices_buffer,
             atomics_buffer,
+            dual_contouring_params_buffer,
+            iso_surface_params_buffer,
+            clip_planes_buffer,
+            adaptive_resolution_buffer,
             ..
         }: &GpuVoxelMaterial,
+        fixed_slots: Option<&FixedSlotBuffers>,
+        prefix_sum_slots: Option<&FixedSlotBuffers>,
     ) -> Self {
+        // Without storage atomics, cells are meshed into the fixed slots and compacted after.
+        let [atomics, vertices, normals, indices, uvs, tangents, emissives] = match fixed_slots {
+            Some(slots) => [
+                slots.cell_slots_buffer.as_entire_binding(),
+                slots.vertices_buffer.as_entire_binding(),
+                slots.normals_buffer.as_entire_binding(),
+                slots.indices_buffer.as_entire_binding(),
+                slots.uvs_buffer.as_entire_binding(),
+                slots.tangents_buffer.as_entire_binding(),
+                slots.emissives_buffer.as_entire_binding(),
+            ],
+            None => [
+                atomics_buffer
+                    .binding()
+                    .expect("Atomics Buffer should have already been uploaded to the gpu"),
+                vertices_buffer
+                    .binding()
+                    .expect("Vertices Buffer should have already been uploaded to the gpu"),
+                normals_buffer
+                    .binding()
+                    .expect("Normals Buffer should have already been uploaded to the gpu"),
+                indices_buffer
+                    .binding()
+                    .expect("Indices Buffer should have already been uploaded to the gpu"),
+                uvs_buffer
+                    .binding()
+                    .expect("UVs Buffer should have already been uploaded to the gpu"),
+                tangents_buffer
+                    .binding()
+                    .expect("Tangents Buffer should have already been uploaded to the gpu"),
+                emissives_buffer
+                    .binding()
+                    .expect("Emissives Buffer should have already been uploaded to the gpu"),
+            ],
+        };
+        // A prefix sum allocation counts the primitives of the cells in the slots instead, then
+        // emits them straight into the output buffers.
+        let atomics = match prefix_sum_slots {
+            Some(slots) => slots.cell_slots_buffer.as_entire_binding(),
+            None => atomics,
+        };
+
         let bind_group_1 = render_device.create_bind_group(
             None,
             &voxel_pipeline.bind_group_1_layout,
@@ -65,78 +106,69 @@ impl GpuVoxelMaterialBindGroups {
                         .binding()
                         .expect("Voxels Buffer should have already been uploaded to the gpu"),
                 ),
+                (3, atomics),
+                (4, vertices),
+                (5, normals),
+                (6, indices),
+                (7, uvs),
+                (8, tangents),
                 (
-                    3,
-                    atomics_buffer
-                        .binding()
-                        .expect("Atomics Buffer should have already been uploaded to the gpu"),
+                    9,
+                    dual_contouring_params_buffer.binding().expect(
+                        "Dual Contouring Params Buffer should have already been uploaded to the gpu",
+                    ),
                 ),
                 (
-                    4,
-                    vertices_buffer
-                        .binding()
-                        .expect("Vertices Buffer should have already been uploaded to the gpu"),
+                    10,
+                    iso_surface_params_buffer.binding().expect(
+                        "Iso Surface Params Buffer should have already been uploaded to the gpu",
+                    ),
                 ),
                 (
-                    5,
-                    normals_buffer
-                        .binding()
-                        .expect("Normals Buffer should have already been uploaded to the gpu"),
+                    11,
+                    clip_planes_buffer.binding().expect(
+                        "Clip Planes Buffer should have already been uploaded to the gpu",
+                    ),
                 ),
                 (
-                    6,
-                    indices_buffer
-                        .binding()
-                        .expect("Indices Buffer should have already been uploaded to the gpu"),
-                ),
-                (
-                    7,
-                    uvs_buffer
-                        .binding()
-                        .expect("UVs Buffer should have already been uploaded to the gpu"),
+                    12,
+                    adaptive_resolution_buffer.binding().expect(
+                        "Adaptive Resolution Buffer should have already been uploaded to the gpu",
+                    ),
                 ),
+                (13, emissives),
             )),
         );
 
         GpuVoxelMaterialBindGroups([bind_group_1])
     }
-    /// Initializes the [`GpuVoxelMaterialBindGroup`] of newly created VoxelMaterial.
-    pub fn initialise(
-        render_device: Res<RenderDevice>,
-        mut voxel_material_bind_groups: ResMut<VoxelMaterialComponents<GpuVoxelMaterialBindGroups>>,
-        gpu_voxel_materials: ResMut<VoxelMaterialComponents<GpuVoxelMaterial>>,
-        voxel_pipeline: Res<VoxelMeshComputePipeline>,
-        volumetric_query: Extract<Query<Entity, (With<VoxelMaterial>, Added<Volumetric>)>>,
-    ) -> () {
-        let pipeline = voxel_pipeline.as_ref();
 
-        for (entity) in volumetric_query.iter() {
-            if let Some(gpu_voxel_material) = gpu_voxel_materials.get(&entity) {
-                let voxel_bind_groups = GpuVoxelMaterialBindGroups::new(
-                    render_device.as_ref(),
-                    pipeline,
-                    &gpu_voxel_material,
-                );
-
-                voxel_material_bind_groups.insert(entity, voxel_bind_groups);
-            }
-        }
-    }
     pub fn prepare(
         render_device: Res<RenderDevice>,
         mut voxel_material_bind_groups: ResMut<VoxelMaterialComponents<GpuVoxelMaterialBindGroups>>,
         gpu_voxel_materials: ResMut<VoxelMaterialComponents<GpuVoxelMaterial>>,
         voxel_pipeline: Res<VoxelMeshComputePipeline>,
-        volumetric_query: Query<Entity, (With<VoxelMaterial>, With<Volumetric>)>,
+        fixed_output_slots: Res<GpuFixedOutputSlots>,
+        prefix_sum_pipeline_ids: Res<VoxelMaterialComponents<VoxelMeshPrefixSumPipelineIds>>,
+        volumetric_query: Query<Entity, With<Volumetric>>,
     ) {
         let pipeline = voxel_pipeline.as_ref();
+        let fixed_slots = fixed_output_slots
+            .buffers
+            .as_ref()
+            .filter(|_| pipeline.platform.fixed_output_slots);
 
-        for (entity) in volumetric_query.iter() {
+        for entity in volumetric_query.iter() {
             if let Some(gpu_voxel_material) = gpu_voxel_materials.get(&entity) {
                 let voxel_bind_groups = GpuVoxelMaterialBindGroups::new(
                     render_device.as_ref(),
                     pipeline,
-                    &gpu_voxel_material,
+                    gpu_voxel_material,
+                    fixed_slots,
+                    fixed_output_slots
+                        .buffers
+                        .as_ref()
+                        .filter(|_| prefix_sum_pipeline_ids.get(&entity).is_some()),
                 );
 
                 voxel_material_bind_groups.insert(entity, voxel_bind_groups);
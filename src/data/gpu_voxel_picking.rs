@@ -0,0 +1,191 @@
+use bevy::{
+    prelude::*,
+    render::{
+        render_resource::{
+            BindGroup, BindGroupEntries, Buffer, BufferDescriptor, BufferUsages, PipelineCache,
+            ShaderType, UniformBuffer,
+        },
+        renderer::{RenderDevice, RenderQueue},
+        Extract,
+    },
+};
+
+use crate::{
+    bundles::volumetric_bundle::{MaterialSplit, Volumetric},
+    channels::{ReadbackChannel, ReadbackTag, VoxelDataVersion},
+    render::voxel_picking_compute_pipeline::VoxelPickingComputePipeline,
+};
+
+use super::{
+    gpu_voxel_material::GpuVoxelMaterial,
+    shader_platform::ShaderPlatform,
+    voxel_material::VoxelMaterialComponents,
+    voxel_picking::{VoxelPickData, VoxelPickParams, VoxelPickResult, VoxelPicking},
+};
+
+/// The latest cast of the [`VoxelPicking`] against the triangles of a volumetric entity.
+pub struct GpuVoxelPick {
+    pub request: u32,
+    /// The ray of the cast in the local space of the entity.
+    pub origin: Vec3,
+    pub direction: Vec3,
+    pub params_buffer: UniformBuffer<VoxelPickParams>,
+    pub result_buffer: Buffer,
+    pub result_staging_buffer: Buffer,
+    pub bind_group: Option<BindGroup>,
+    /// Whether the cast is still to be intersected and read back.
+    pub pending: bool,
+}
+
+impl GpuVoxelPick {
+    pub fn new(render_device: &RenderDevice) -> Self {
+        let size = VoxelPickResult::min_size().get();
+
+        GpuVoxelPick {
+            request: 0,
+            origin: Vec3::ZERO,
+            direction: Vec3::ZERO,
+            params_buffer: UniformBuffer::default(),
+            result_buffer: render_device.create_buffer(&BufferDescriptor {
+                label: Some("voxel_pick_result_buffer"),
+                size,
+                usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            result_staging_buffer: render_device.create_buffer(&BufferDescriptor {
+                label: Some("voxel_pick_result_staging_buffer"),
+                size,
+                usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            bind_group: None,
+            pending: false,
+        }
+    }
+
+    /// Hands a new cast of the [`VoxelPicking`] to every volumetric entity, in its local space,
+    /// and drops the picks of removed entities.
+    pub fn extract(
+        render_device: Res<RenderDevice>,
+        picking: Extract<Res<VoxelPicking>>,
+        mut gpu_voxel_picks: ResMut<VoxelMaterialComponents<GpuVoxelPick>>,
+        volumetric_query: Extract<Query<(Entity, &GlobalTransform), With<Volumetric>>>,
+        mut extracted_request: Local<u32>,
+    ) {
+        gpu_voxel_picks
+            .0
+            .retain(|entity, _| volumetric_query.contains(*entity));
+
+        let Some(ray) = picking.ray() else {
+            return;
+        };
+        if picking.request() == *extracted_request {
+            return;
+        }
+        *extracted_request = picking.request();
+
+        for (entity, transform) in volumetric_query.iter() {
+            let volume_from_world = transform.compute_matrix().inverse();
+            let gpu_pick = gpu_voxel_picks
+                .0
+                .entry(entity)
+                .or_insert_with(|| GpuVoxelPick::new(render_device.as_ref()));
+
+            gpu_pick.request = picking.request();
+            gpu_pick.origin = volume_from_world.transform_point3(ray.origin);
+            gpu_pick.direction = volume_from_world.transform_vector3(*ray.direction);
+            gpu_pick.pending = true;
+        }
+    }
+
+    /// Binds each pending pick to the output buffers of its [`GpuVoxelMaterial`], resetting its
+    /// result to a miss. Picks of entities not meshed yet wait for their buffers.
+    pub fn prepare(
+        render_device: Res<RenderDevice>,
+        render_queue: Res<RenderQueue>,
+        platform: Res<ShaderPlatform>,
+        picking_pipeline: Res<VoxelPickingComputePipeline>,
+        gpu_voxel_materials: Res<VoxelMaterialComponents<GpuVoxelMaterial>>,
+        mut gpu_voxel_picks: ResMut<VoxelMaterialComponents<GpuVoxelPick>>,
+        split_query: Query<(), (With<Volumetric>, With<MaterialSplit>)>,
+    ) {
+        for (entity, gpu_pick) in gpu_voxel_picks.0.iter_mut() {
+            gpu_pick.bind_group = None;
+            if !gpu_pick.pending {
+                continue;
+            }
+            let Some(gpu_voxel_material) = gpu_voxel_materials.get(entity) else {
+                continue;
+            };
+            let (Some(vertices_buffer), Some(indices_buffer), Some(atomics_buffer)) = (
+                gpu_voxel_material.vertices_buffer.buffer(),
+                gpu_voxel_material.indices_buffer.buffer(),
+                gpu_voxel_material.atomics_buffer.buffer(),
+            ) else {
+                continue;
+            };
+
+            gpu_pick.params_buffer.set(VoxelPickParams {
+                origin: gpu_pick.origin,
+                material_split: (split_query.contains(*entity) && !platform.fixed_output_slots)
+                    as u32,
+                direction: gpu_pick.direction,
+                triangles: gpu_voxel_material.indices_buffer.capacity() as u32 / 3,
+            });
+            gpu_pick
+                .params_buffer
+                .write_buffer(render_device.as_ref(), render_queue.as_ref());
+
+            render_queue.write_buffer(
+                &gpu_pick.result_buffer,
+                0,
+                bytemuck::bytes_of(&VoxelPickData::miss(gpu_pick.request)),
+            );
+
+            gpu_pick.bind_group = Some(render_device.create_bind_group(
+                "GpuVoxelPick::bind_group",
+                &picking_pipeline.bind_group_layout,
+                &BindGroupEntries::sequential((
+                    gpu_pick.params_buffer.binding().expect(
+                        "Voxel Pick Params Buffer should have already been uploaded to the gpu",
+                    ),
+                    vertices_buffer.as_entire_binding(),
+                    indices_buffer.as_entire_binding(),
+                    atomics_buffer.as_entire_binding(),
+                    gpu_pick.result_buffer.as_entire_binding(),
+                )),
+            ));
+        }
+    }
+
+    /// Reads back the hits intersected this frame through the [`ReadbackChannel`]. Picks that
+    /// fail to map are intersected again on the next frame.
+    pub fn map_and_read_buffers(
+        render_device: Res<RenderDevice>,
+        pipeline_cache: Res<PipelineCache>,
+        picking_pipeline: Res<VoxelPickingComputePipeline>,
+        mut gpu_voxel_picks: ResMut<VoxelMaterialComponents<GpuVoxelPick>>,
+        version_query: Query<Option<&VoxelDataVersion>, With<Volumetric>>,
+        channel: Res<ReadbackChannel<VoxelPickData>>,
+    ) {
+        if !picking_pipeline.is_loaded(&pipeline_cache) {
+            return;
+        }
+
+        for (entity, gpu_pick) in gpu_voxel_picks.0.iter_mut() {
+            if !gpu_pick.pending || gpu_pick.bind_group.is_none() {
+                continue;
+            }
+            let Some(tag) = ReadbackTag::current(*entity, &version_query) else {
+                continue;
+            };
+
+            if channel
+                .read_buffer(&render_device, tag, &gpu_pick.result_staging_buffer)
+                .is_ok()
+            {
+                gpu_pick.pending = false;
+            }
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use bevy::{
+    ecs::query::QueryItem,
+    prelude::*,
+    render::{
+        extract_component::ExtractComponent,
+        mesh::{Indices, MeshVertexAttribute, PrimitiveTopology},
+        render_asset::RenderAssetUsages,
+        render_resource::VertexFormat,
+    },
+};
+
+use crate::render::shaders::shader_struct;
+
+/// Iso-level of the surface meshed into the buffers of the entity's own
+/// [`GpuVoxelMaterial`](super::gpu_voxel_material::GpuVoxelMaterial).
+pub const DEFAULT_ISO_LEVEL: f32 = 0.5;
+
+/// The linear emissive color of a vertex of a volumetric mesh, premultiplied by its intensity and
+/// interpolated from the [`Voxel::emissive`](super::voxel::Voxel::emissive) colors of the voxels
+/// around it. Drawn by an [`EmissiveVoxelMaterial`](crate::emissive::EmissiveVoxelMaterial).
+pub const ATTRIBUTE_EMISSIVE: MeshVertexAttribute =
+    MeshVertexAttribute::new("Vertex_Emissive", 0x5e4d_1f07, VertexFormat::Float32x3);
+
+/// A surface of a volume where its density crosses `iso_level`, drawn with `material`.
+#[derive(Clone, Debug)]
+pub struct IsoSurface {
+    pub iso_level: f32,
+    pub material: Handle<StandardMaterial>,
+}
+
+/// Meshes further surfaces of a volumetric entity at other iso-levels, e.g. skin at 0.2 and bone
+/// at 0.6 of a CT scan. Each surface is meshed by its own dispatch from the uploaded voxels into
+/// its own output buffers, and read back into the mesh of an [`IsoSurfaceMesh`] child entity.
+#[derive(Clone, Component, Debug, Default)]
+pub struct IsoSurfaces(pub Vec<IsoSurface>);
+
+/// The iso-levels of the [`IsoSurfaces`] of a render world entity.
+#[derive(Clone, Component, Debug)]
+pub struct IsoLevels(pub Vec<f32>);
+
+impl ExtractComponent for IsoSurfaces {
+    type QueryData = &'static IsoSurfaces;
+    type QueryFilter = ();
+    type Out = IsoLevels;
+
+    fn extract_component(iso_surfaces: QueryItem<'_, Self::QueryData>) -> Option<Self::Out> {
+        Some(IsoLevels(
+            iso_surfaces
+                .0
+                .iter()
+                .map(|iso_surface| iso_surface.iso_level)
+                .collect(),
+        ))
+    }
+}
+
+/// The child entity drawing the surface at `index` of the [`IsoSurfaces`] of its parent.
+#[derive(Clone, Copy, Component, Debug)]
+pub struct IsoSurfaceMesh {
+    pub index: usize,
+}
+
+/// The vertices and indices of an [`IsoSurface`] read back from the GPU.
+#[derive(Clone, Debug, Default)]
+pub struct IsoSurfaceMeshData {
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    /// The triplanar UV of each vertex, in voxels.
+    pub uvs: Vec<[f32; 2]>,
+    /// The tangent of each vertex along its UVs, for normal mapped materials.
+    pub tangents: Vec<[f32; 4]>,
+    /// The emissive color of each vertex, see [`ATTRIBUTE_EMISSIVE`].
+    pub emissives: Vec<[f32; 3]>,
+    pub indices: Vec<u32>,
+}
+
+impl IsoSurfaceMeshData {
+    pub fn into_mesh(self) -> Mesh {
+        let mut mesh = Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::RENDER_WORLD,
+        );
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, self.positions);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, self.normals);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, self.uvs);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_TANGENT, self.tangents);
+        mesh.insert_attribute(ATTRIBUTE_EMISSIVE, self.emissives);
+        mesh.insert_indices(Indices::U32(self.indices));
+        mesh
+    }
+}
+
+impl IsoSurfaces {
+    /// Respawns the [`IsoSurfaceMesh`] children of entities whose [`IsoSurfaces`] changed. Children
+    /// are kept while the number of surfaces is the same, e.g. when animating their iso-levels,
+    /// only their materials being updated.
+    pub fn spawn_meshes(
+        mut commands: Commands,
+        mut meshes: ResMut<Assets<Mesh>>,
+        iso_surfaces_query: Query<(Entity, &IsoSurfaces, Option<&Children>), Changed<IsoSurfaces>>,
+        mut iso_surface_mesh_query: Query<(&IsoSurfaceMesh, &mut Handle<StandardMaterial>)>,
+    ) {
+        for (entity, iso_surfaces, children) in iso_surfaces_query.iter() {
+            let surface_meshes = children
+                .into_iter()
+                .flatten()
+                .filter(|child| iso_surface_mesh_query.contains(**child))
+                .collect::<Vec<_>>();
+
+            if surface_meshes.len() == iso_surfaces.0.len() {
+                for child in surface_meshes {
+                    let Ok((surface_mesh, mut material)) = iso_surface_mesh_query.get_mut(*child)
+                    else {
+                        continue;
+                    };
+                    let Some(iso_surface) = iso_surfaces.0.get(surface_mesh.index) else {
+                        continue;
+                    };
+                    if *material != iso_surface.material {
+                        *material = iso_surface.material.clone();
+                    }
+                }
+                continue;
+            }
+
+            for child in surface_meshes {
+                commands.entity(*child).despawn_recursive();
+            }
+
+            commands.entity(entity).with_children(|parent| {
+                for (index, iso_surface) in iso_surfaces.0.iter().enumerate() {
+                    parent.spawn((
+                        PbrBundle {
+                            mesh: meshes.add(IsoSurfaceMeshData::default().into_mesh()),
+                            material: iso_surface.material.clone(),
+                            ..default()
+                        },
+                        IsoSurfaceMesh { index },
+                    ));
+                }
+            });
+        }
+    }
+}
+
+shader_struct! {
+    #[derive(Clone, Copy)]
+    pub struct IsoSurfaceParams {
+        pub iso_level: f32,
+    }
+}
+
+impl Default for IsoSurfaceParams {
+    fn default() -> Self {
+        Self {
+            iso_level: DEFAULT_ISO_LEVEL,
+        }
+    }
+}
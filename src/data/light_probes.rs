@@ -0,0 +1,110 @@
+use bevy::{
+    prelude::*,
+    render::{
+        extract_component::ExtractComponent,
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages},
+    },
+};
+
+use crate::{render::shaders::shader_struct, CHUNK_SZ};
+
+/// The format of the irradiance volume textures baked for [`VoxelLightProbes`].
+pub const LIGHT_PROBES_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+/// Bakes a grid of light probes over the voxels of a volumetric entity on the GPU, into an
+/// [`IrradianceVolume`](bevy::pbr::irradiance_volume::IrradianceVolume) texture lighting the
+/// meshes around it with the ambient light bouncing off the volume. Each probe ray-marches the
+/// voxels in the six directions of its ambient cube: rays escaping the volume gather `sky_color`,
+/// rays hitting the surface gather `sky_color` reflected by `bounce_color`.
+///
+/// The probes are baked again whenever the entity is remeshed, and lit by a child light probe
+/// spawned by the [`LightProbesPlugin`](crate::light_probes::LightProbesPlugin).
+#[derive(Clone, Component, ExtractComponent, Debug)]
+pub struct VoxelLightProbes {
+    /// The irradiance volume texture, laid out as Bevy's irradiance volumes expect: the probes of
+    /// the `-X`, `-Y` and `-Z` sides of the ambient cubes stacked along z, then those of the `+X`,
+    /// `+Y` and `+Z` sides above them along y.
+    pub image: Handle<Image>,
+    /// Voxels between two probes along each axis.
+    pub probe_spacing: u32,
+    /// Rays traced by each side of a probe.
+    pub ray_count: u32,
+    /// Distance in voxels after which a ray escapes.
+    pub max_distance: f32,
+    /// The light of the rays escaping the volume.
+    pub sky_color: LinearRgba,
+    /// The fraction of the sky light reflected by the surface of the volume.
+    pub bounce_color: LinearRgba,
+    /// The brightness of the irradiance volume, in the units of Bevy's irradiance volumes.
+    pub intensity: f32,
+}
+
+impl VoxelLightProbes {
+    /// Creates the irradiance volume texture of a chunk with one probe every `probe_spacing`
+    /// voxels along each axis.
+    pub fn new(images: &mut Assets<Image>, probe_spacing: u32) -> Self {
+        let resolution = Self::resolution(probe_spacing);
+
+        let mut image = Image::new_fill(
+            Extent3d {
+                width: resolution,
+                height: resolution * 2,
+                depth_or_array_layers: resolution * 3,
+            },
+            TextureDimension::D3,
+            &[0; 8],
+            LIGHT_PROBES_FORMAT,
+            RenderAssetUsages::RENDER_WORLD,
+        );
+        image.texture_descriptor.usage = TextureUsages::COPY_DST
+            | TextureUsages::STORAGE_BINDING
+            | TextureUsages::TEXTURE_BINDING;
+
+        Self {
+            image: images.add(image),
+            probe_spacing,
+            ray_count: 32,
+            max_distance: CHUNK_SZ as f32,
+            sky_color: LinearRgba::WHITE,
+            bounce_color: LinearRgba::gray(0.5),
+            intensity: 1000.0,
+        }
+    }
+
+    /// Probes along each axis of a chunk with one probe every `probe_spacing` voxels.
+    pub fn resolution(probe_spacing: u32) -> u32 {
+        (CHUNK_SZ as u32).div_ceil(probe_spacing.max(1))
+    }
+
+    /// The transform of the light probe lighting the probes of a chunk, relative to the chunk.
+    /// Its unit cube spans the cells of every probe from the first voxel, whose center is at the
+    /// origin, so that the probes are in the centers of its texels.
+    pub fn light_probe_transform(&self) -> Transform {
+        let extent = (Self::resolution(self.probe_spacing) * self.probe_spacing.max(1)) as f32;
+        Transform::from_translation(Vec3::splat(extent * 0.5 - 0.5)).with_scale(Vec3::splat(extent))
+    }
+}
+
+shader_struct! {
+    #[derive(Clone, Copy, Default)]
+    pub struct LightProbeParams {
+        sky_color: Vec4,
+        bounce_color: Vec4,
+        probe_spacing: u32,
+        ray_count: u32,
+        max_distance: f32,
+    }
+}
+
+impl From<&VoxelLightProbes> for LightProbeParams {
+    fn from(light_probes: &VoxelLightProbes) -> Self {
+        Self {
+            sky_color: light_probes.sky_color.to_vec4(),
+            bounce_color: light_probes.bounce_color.to_vec4(),
+            probe_spacing: light_probes.probe_spacing.max(1),
+            ray_count: light_probes.ray_count.max(1),
+            max_distance: light_probes.max_distance,
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use std::ops::Range;
+
+use bevy::{prelude::*, render::Extract};
+
+use crate::bundles::volumetric_bundle::{MaterialSplit, Volumetric};
+
+use super::voxel_material::VoxelMaterialComponents;
+
+/// Number of materials the index buffer of a [`MaterialSplit`] entity is split into. Voxels whose
+/// flags are past the last material are meshed with it.
+pub const MAX_MATERIALS: usize = 8;
+
+/// The range of the index buffer holding the triangles of each material of a [`MaterialSplit`]
+/// entity, so that each range can be drawn with its own render pipeline. The index buffer is split
+/// into [`MAX_MATERIALS`] segments of equal size; triangles that don't fit in the segment of their
+/// material are dropped.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MaterialIndexRanges(pub [Range<u32>; MAX_MATERIALS]);
+
+impl MaterialIndexRanges {
+    /// The ranges of an index buffer of `index_capacity` indices, from the `heads` of the atomics
+    /// buffer read back after meshing.
+    pub fn from_atomics(heads: &[u32], index_capacity: u32) -> Self {
+        let segment = index_capacity / MAX_MATERIALS as u32;
+        Self(std::array::from_fn(|material| {
+            let start = material as u32 * segment;
+            let count = heads.get(2 + material).copied().unwrap_or(0).min(segment);
+            start..start + count
+        }))
+    }
+
+    pub fn get(&self, material: usize) -> Option<Range<u32>> {
+        self.0.get(material).cloned()
+    }
+
+    /// The number of indices of all the materials.
+    pub fn index_count(&self) -> u32 {
+        self.0.iter().map(|range| range.end - range.start).sum()
+    }
+
+    /// The ranges of the same indices packed one material after the other.
+    pub fn packed(&self) -> Self {
+        let mut start = 0;
+        Self(self.0.clone().map(|range| {
+            let packed = start..start + range.end - range.start;
+            start = packed.end;
+            packed
+        }))
+    }
+
+    /// Tracks the entities with a [`MaterialSplit`] in the render world, whose ranges are updated
+    /// whenever the first page of their vertices is read back.
+    #[allow(clippy::type_complexity)]
+    pub fn extract(
+        mut material_index_ranges: ResMut<VoxelMaterialComponents<MaterialIndexRanges>>,
+        split_query: Extract<Query<Entity, (With<Volumetric>, With<MaterialSplit>)>>,
+    ) {
+        material_index_ranges
+            .0
+            .retain(|entity, _| split_query.contains(*entity));
+        for entity in split_query.iter() {
+            material_index_ranges.0.entry(entity).or_default();
+        }
+    }
+}
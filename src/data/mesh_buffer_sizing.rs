@@ -0,0 +1,88 @@
+use bevy::{
+    prelude::*,
+    render::{
+        extract_resource::ExtractResource,
+        renderer::{RenderDevice, RenderQueue},
+    },
+};
+
+use crate::{
+    bundles::volumetric_bundle::Volumetric, render::voxel_mesh_compute_pipeline::DirtyMeshes,
+};
+
+use super::{
+    gpu_voxel_material::{GpuVoxelMaterial, OutputCapacities},
+    meshing_algorithm::MeshingAlgorithm,
+    voxel_material::VoxelMaterialComponents,
+};
+
+/// How many vertices and indices the output buffers of volumetric entities are sized for, from
+/// the most a cell of their [`MeshingAlgorithm`] can emit. Trades GPU memory against the risk of
+/// a mesh overflowing its buffers, whose primitives past the end are lost. Changing the sizing or
+/// the algorithm of an entity reallocates its buffers.
+///
+/// Refined cells of an [`AdaptiveResolution`](super::adaptive_resolution::AdaptiveResolution)
+/// emit up to `subdivisions³` times more, and are not accounted for.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, ExtractResource)]
+pub enum MeshBufferSizing {
+    /// Enough for every cell to emit as much as it can, so that meshes never overflow.
+    #[default]
+    WorstCase,
+    /// Enough for `surface_fraction` of the cells to emit as much as they can, e.g. 0.25 for
+    /// terrain whose surface only crosses a few layers of cells.
+    Expected { surface_fraction: f32 },
+}
+
+impl MeshBufferSizing {
+    /// The capacities of the output buffers meshing up to `voxel_capacity` voxels with
+    /// `meshing_algorithm`.
+    pub fn capacities(
+        &self,
+        meshing_algorithm: MeshingAlgorithm,
+        voxel_capacity: usize,
+    ) -> OutputCapacities {
+        // The cells of the volume along with the capping layer around it.
+        let side = (voxel_capacity as f64).cbrt().ceil() as usize + 1;
+        let cells = match *self {
+            MeshBufferSizing::WorstCase => side.pow(3),
+            MeshBufferSizing::Expected { surface_fraction } => {
+                (side.pow(3) as f32 * surface_fraction.clamp(0.0, 1.0)).ceil() as usize
+            }
+        }
+        .max(1);
+
+        let (vertices, indices) = meshing_algorithm.max_cell_output();
+        OutputCapacities {
+            vertices: cells * vertices,
+            attributes: cells * vertices,
+            indices: cells * indices,
+        }
+    }
+
+    /// Reallocates the output buffers of the entities whose sizing or meshing algorithm changed,
+    /// and remeshes them.
+    pub fn prepare(
+        render_device: Res<RenderDevice>,
+        render_queue: Res<RenderQueue>,
+        sizing: Res<MeshBufferSizing>,
+        mut gpu_voxel_materials: ResMut<VoxelMaterialComponents<GpuVoxelMaterial>>,
+        mut dirty_meshes: ResMut<DirtyMeshes>,
+        algorithm_query: Query<(Entity, Option<&MeshingAlgorithm>), With<Volumetric>>,
+    ) {
+        for (entity, meshing_algorithm) in algorithm_query.iter() {
+            let Some(gpu_voxel_material) = gpu_voxel_materials.get_mut(&entity) else {
+                continue;
+            };
+            let full = sizing.capacities(
+                meshing_algorithm.copied().unwrap_or_default(),
+                gpu_voxel_material.voxels_buffer.capacity(),
+            );
+            if gpu_voxel_material.full_output_capacities() == full {
+                continue;
+            }
+
+            gpu_voxel_material.set_full_output_capacities(&render_device, &render_queue, full);
+            dirty_meshes.mark(entity);
+        }
+    }
+}
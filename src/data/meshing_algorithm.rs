@@ -0,0 +1,58 @@
+use bevy::{
+    prelude::*,
+    render::{extract_component::ExtractComponent, render_resource::ShaderDefVal},
+};
+
+use crate::core;
+
+/// The algorithm used to mesh a volumetric entity. Entities without one use [`MeshingAlgorithm::MarchingCubes`].
+#[derive(Clone, Copy, Component, ExtractComponent, Debug, Default, PartialEq, Eq, Hash)]
+pub enum MeshingAlgorithm {
+    /// Smooth marching cubes surface, with voxels that have flags set meshed as blocks.
+    #[default]
+    MarchingCubes,
+    /// Smooth surface nets, placing one vertex per cell at the average of its edge crossings.
+    SurfaceNets,
+    /// Blocky meshing of every voxel inside the surface.
+    Cubic,
+    /// Surface nets topology with each vertex placed by minimising the quadratic error to the
+    /// tangent planes of its cell's edge crossings, preserving sharp edges and corners. See
+    /// [`DualContouringSettings`](super::dual_contouring::DualContouringSettings).
+    DualContouring,
+}
+
+impl From<MeshingAlgorithm> for core::mesher::MeshingAlgorithm {
+    fn from(meshing_algorithm: MeshingAlgorithm) -> Self {
+        match meshing_algorithm {
+            MeshingAlgorithm::MarchingCubes => Self::MarchingCubes,
+            MeshingAlgorithm::SurfaceNets => Self::SurfaceNets,
+            MeshingAlgorithm::Cubic => Self::Cubic,
+            MeshingAlgorithm::DualContouring => Self::DualContouring,
+        }
+    }
+}
+
+impl MeshingAlgorithm {
+    /// The shader def selecting this algorithm in the meshing compute shader.
+    pub fn shader_def(&self) -> ShaderDefVal {
+        match self {
+            Self::MarchingCubes => "MESHING_MARCHING_CUBES".into(),
+            Self::SurfaceNets => "MESHING_SURFACE_NETS".into(),
+            Self::Cubic => "MESHING_CUBIC".into(),
+            Self::DualContouring => "MESHING_DUAL_CONTOURING".into(),
+        }
+    }
+}
+
+impl MeshingAlgorithm {
+    /// The most vertices and indices the meshing compute shader can emit for a single cell.
+    pub fn max_cell_output(&self) -> (usize, usize) {
+        match self {
+            // The 6 faces of a block, more than the 5 triangles of a marching cubes cell, which
+            // meshes voxels with flags set as blocks.
+            Self::MarchingCubes | Self::Cubic => (6 * 4, 6 * 6),
+            // A quad for each of the 3 edges leaving the cell.
+            Self::SurfaceNets | Self::DualContouring => (3 * 4, 3 * 6),
+        }
+    }
+}
@@ -1,7 +1,84 @@
+pub mod adaptive_resolution;
+pub mod ambient_occlusion;
 pub mod atomics;
-pub mod edge_table;
+pub mod boundary_mode;
+#[cfg(feature = "readback")]
+pub mod chunk_analysis;
+pub mod chunk_coord;
+#[cfg(feature = "gpu-driven")]
+pub mod chunk_culling;
+pub mod chunk_priority;
+pub mod chunk_retirement;
+pub mod clip_planes;
+#[cfg(feature = "gpu-driven")]
+pub mod cluster_bounds;
+pub mod compressed_voxels;
+#[cfg(feature = "gpu-driven")]
+pub mod depth_pyramid;
+pub mod distance_field;
+pub mod dual_contouring;
+pub mod edge_interpolation;
+pub mod erosion;
+pub mod generation_graph;
+pub mod gpu_ambient_occlusion;
+#[cfg(feature = "readback")]
+pub mod gpu_chunk_analysis;
+#[cfg(feature = "gpu-driven")]
+pub mod gpu_chunk_culling;
+pub mod gpu_chunk_state;
+#[cfg(feature = "gpu-driven")]
+pub mod gpu_cluster_bounds;
+pub mod gpu_compressed_voxels;
+#[cfg(feature = "gpu-driven")]
+pub mod gpu_depth_pyramid;
+pub mod gpu_distance_field;
+pub mod gpu_erosion;
+pub mod gpu_fixed_output_slots;
+pub mod gpu_iso_surface;
+#[cfg(feature = "raymarch")]
+pub mod gpu_light_probes;
+pub mod gpu_neighbor_voxels;
+pub mod gpu_procedural_volume;
+#[cfg(feature = "readback")]
+pub mod gpu_prop_candidates;
+pub mod gpu_splat_map;
+pub mod gpu_virtual_volume;
+pub mod gpu_volume_statistics;
 pub mod gpu_voxel_material;
 pub mod gpu_voxel_material_bind_group;
-pub mod triangle_table;
+#[cfg(feature = "readback")]
+pub mod gpu_voxel_picking;
+pub mod iso_surface;
+#[cfg(feature = "raymarch")]
+pub mod light_probes;
+pub mod material_split;
+pub mod mesh_buffer_sizing;
+pub mod meshing_algorithm;
+pub mod normal_encoding;
+pub mod particle_collision;
+pub mod procedural_volume;
+#[cfg(feature = "readback")]
+pub mod prop_candidates;
+pub mod raw_mesh_data;
+pub mod readback_header;
+pub mod shader_platform;
+pub mod splat_map;
+pub mod upload_budget;
+pub mod virtual_volume;
+pub mod volume_statistics;
 pub mod voxel;
+pub mod voxel_collision;
+#[cfg(feature = "editing")]
+pub mod voxel_decal;
+#[cfg(feature = "editing")]
+pub mod voxel_edit;
+pub mod voxel_layers;
 pub mod voxel_material;
+#[cfg(feature = "raymarch")]
+pub mod voxel_occlusion;
+#[cfg(feature = "readback")]
+pub mod voxel_picking;
+#[cfg(feature = "editing")]
+pub mod voxel_structure;
+pub mod voxel_transform;
+pub mod voxel_world;
@@ -0,0 +1,63 @@
+use bevy::prelude::*;
+
+/// A normal of the normals buffers of the meshing shader: octahedral encoded into two 16 bit
+/// snorms by [`encode_octahedral`], halving the memory and readback bandwidth of the normals, or
+/// the full normal with the `full-normals` feature, for debugging. WGSL shaders encode and decode
+/// them with `bevy_volumetric::normals::{encode_normal, decode_normal}`.
+#[cfg(not(feature = "full-normals"))]
+pub type EncodedNormal = u32;
+#[cfg(feature = "full-normals")]
+pub type EncodedNormal = Vec4;
+
+/// Number of `u32`s of an [`EncodedNormal`].
+pub const NORMAL_WORDS: u32 =
+    (std::mem::size_of::<EncodedNormal>() / std::mem::size_of::<u32>()) as u32;
+
+/// Encodes `normal`, which needs not be normalized, the same as the shaders' `encode_normal`.
+/// Zero normals decode to [`Vec3::Z`].
+pub fn encode_octahedral(normal: Vec3) -> u32 {
+    let l1 = normal.abs().element_sum();
+    if l1 == 0.0 {
+        return 0;
+    }
+    let mut p = normal.xy() / l1;
+    // The lower half of the octahedron is folded over the upper one.
+    if normal.z < 0.0 {
+        let sign = Vec2::select(p.cmpge(Vec2::ZERO), Vec2::ONE, Vec2::NEG_ONE);
+        p = (Vec2::ONE - p.yx().abs()) * sign;
+    }
+    let snorm = |v: f32| (v.clamp(-1.0, 1.0) * 32767.0).round() as i16 as u16 as u32;
+    snorm(p.x) | (snorm(p.y) << 16)
+}
+
+/// Decodes a normal encoded with [`encode_octahedral`], normalized.
+pub fn decode_octahedral(encoded: u32) -> Vec3 {
+    let snorm = |bits: u32| (bits as u16 as i16 as f32 / 32767.0).max(-1.0);
+    let p = Vec2::new(snorm(encoded), snorm(encoded >> 16));
+    let mut normal = p.extend(1.0 - p.x.abs() - p.y.abs());
+    let t = (-normal.z).max(0.0);
+    normal.x += if normal.x >= 0.0 { -t } else { t };
+    normal.y += if normal.y >= 0.0 { -t } else { t };
+    normal.normalize()
+}
+
+/// Decodes an [`EncodedNormal`], normalized unless it is a full normal.
+#[cfg(not(feature = "full-normals"))]
+pub fn decode_normal(normal: EncodedNormal) -> Vec3 {
+    decode_octahedral(normal)
+}
+
+/// Decodes an [`EncodedNormal`], normalized unless it is a full normal.
+#[cfg(feature = "full-normals")]
+pub fn decode_normal(normal: EncodedNormal) -> Vec3 {
+    normal.truncate()
+}
+
+/// Reads the first `count` normals of a readback of a normals buffer.
+pub fn read_normals(bytes: &[u8], count: usize) -> Vec<[f32; 3]> {
+    bytes
+        .chunks_exact(std::mem::size_of::<EncodedNormal>())
+        .take(count)
+        .map(|chunk| decode_normal(bytemuck::pod_read_unaligned(chunk)).to_array())
+        .collect()
+}
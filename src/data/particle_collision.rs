@@ -0,0 +1,46 @@
+use bevy::prelude::*;
+
+use crate::render::shaders::shader_struct;
+
+shader_struct! {
+    /// Places the density texture of the
+    /// [`ParticleCollisionVolume`](crate::particle_collision::ParticleCollisionVolume) in world
+    /// space, for the functions of `bevy_volumetric::particle_collision`.
+    #[derive(Clone, Copy, Default, Debug)]
+    pub struct ParticleCollisionParams {
+        /// World space position of the voxel at the first texel.
+        world_min: Vec3,
+        voxel_size: f32,
+        /// Texels along each axis of the texture.
+        size: UVec3,
+        /// Density from which particles collide.
+        iso_level: f32,
+    }
+}
+
+impl ParticleCollisionParams {
+    pub fn new(world_min: Vec3, voxel_size: f32, size: UVec3, iso_level: f32) -> Self {
+        Self {
+            world_min,
+            voxel_size,
+            size,
+            iso_level,
+        }
+    }
+
+    pub fn world_min(&self) -> Vec3 {
+        self.world_min
+    }
+
+    pub fn voxel_size(&self) -> f32 {
+        self.voxel_size
+    }
+
+    pub fn size(&self) -> UVec3 {
+        self.size
+    }
+
+    pub fn iso_level(&self) -> f32 {
+        self.iso_level
+    }
+}
@@ -0,0 +1,106 @@
+use bevy::{prelude::*, render::extract_resource::ExtractResource};
+
+use crate::{render::shaders::shader_struct, CHUNK_SZ_3};
+
+use super::{chunk_coord::ChunkCoord, voxel_world::VoxelWorldConfig};
+
+shader_struct! {
+    /// The [`ProceduralVolume`] of a chunk as read by the generation shader.
+    #[derive(Clone, Copy, Default)]
+    pub struct ProceduralParams {
+        pub origin: IVec3,
+        pub seed: u32,
+        pub base_height: f32,
+        pub height_amplitude: f32,
+        pub height_frequency: f32,
+        pub octaves: u32,
+        pub surface_depth: f32,
+        pub surface_material: u32,
+        pub material: u32,
+        pub voxel_count: u32,
+    }
+}
+
+/// Voxels generated on the GPU straight into the voxels buffer of a chunk, without a
+/// [`VoxelMaterial`](super::voxel_material::VoxelMaterial): a heightmap of fractal gradient noise,
+/// solid below the surface. The noise is the [`fbm`](crate::terrain::fbm) of a
+/// [`LayeredBiome`](crate::terrain::LayeredBiome), sampled in world voxel coordinates so that
+/// neighbouring chunks line up without seams.
+///
+/// The voxels never exist on the CPU, so the chunk can't be edited or read back as voxels. Changing
+/// the component generates the chunk again.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct ProceduralVolume {
+    /// World voxel coordinates of the first voxel of the chunk.
+    pub origin: IVec3,
+    pub seed: u32,
+    /// Height of the surface in voxels, before the noise is added.
+    pub base_height: f32,
+    /// Height of the hills above and below `base_height`, in voxels.
+    pub height_amplitude: f32,
+    /// Frequency of the hills, in cycles per voxel.
+    pub height_frequency: f32,
+    pub octaves: u32,
+    /// Voxels below the surface given `surface_material`, e.g. grass.
+    pub surface_depth: f32,
+    pub surface_material: u32,
+    /// Flags of the voxels below the surface layer.
+    pub material: u32,
+}
+
+impl Default for ProceduralVolume {
+    fn default() -> Self {
+        Self {
+            origin: IVec3::ZERO,
+            seed: 0,
+            base_height: 16.0,
+            height_amplitude: 8.0,
+            height_frequency: 0.01,
+            octaves: 4,
+            surface_depth: 1.0,
+            surface_material: 0,
+            material: 0,
+        }
+    }
+}
+
+impl ProceduralVolume {
+    /// The volume of the chunk at `coord`, placed by `config`.
+    pub fn for_chunk(config: &VoxelWorldConfig, coord: ChunkCoord) -> Self {
+        Self {
+            origin: config.chunk_to_voxel(coord),
+            ..default()
+        }
+    }
+
+    pub fn params(&self) -> ProceduralParams {
+        ProceduralParams {
+            origin: self.origin,
+            seed: self.seed,
+            base_height: self.base_height,
+            height_amplitude: self.height_amplitude,
+            height_frequency: self.height_frequency,
+            octaves: self.octaves,
+            surface_depth: self.surface_depth,
+            surface_material: self.surface_material,
+            material: self.material,
+            voxel_count: CHUNK_SZ_3 as u32,
+        }
+    }
+}
+
+/// Limits how many [`ProceduralVolume`]s are generated each frame. Chunks over the limit wait in
+/// the [`GenerationQueue`](super::gpu_procedural_volume::GenerationQueue) by descending
+/// [`ChunkPriority`](super::chunk_priority::ChunkPriority).
+#[derive(Resource, Clone, Copy, Debug, ExtractResource)]
+pub struct GenerationBudget {
+    pub max_chunks_per_frame: usize,
+}
+
+impl Default for GenerationBudget {
+    fn default() -> Self {
+        Self {
+            max_chunks_per_frame: 8,
+        }
+    }
+}
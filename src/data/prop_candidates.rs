@@ -0,0 +1,177 @@
+use std::sync::atomic::AtomicU32;
+
+use bevy::{ecs::query::QueryItem, prelude::*, render::extract_component::ExtractComponent};
+use bytemuck::{Pod, Zeroable};
+
+use crate::render::shaders::shader_struct;
+
+use super::{
+    chunk_coord::ChunkCoord, iso_surface::DEFAULT_ISO_LEVEL, voxel_world::VoxelWorldConfig,
+};
+
+/// A point of the surface of a chunk where gameplay may place a prop such as a tree, a rock or an
+/// item, in the local space of the chunk's meshes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PropCandidate {
+    pub position: Vec3,
+    /// Unit normal of the surface, pointing out of the terrain.
+    pub normal: Vec3,
+    /// Flags of the solid voxel below the point.
+    pub material: u32,
+    /// Angle between the normal and up, in radians.
+    pub slope: f32,
+}
+
+/// Candidate placement points for props on the ground of a chunk, emitted on the GPU whenever the
+/// chunk is remeshed so that props follow the terrain without rescanning its mesh. A point is
+/// emitted on top of each solid voxel under an empty one whose surface is no steeper than
+/// `max_slope`, kept with a probability of `density` from a hash of its world voxel coordinates
+/// and `seed`, so that the same points are picked whenever the chunk is remeshed and neighbouring
+/// chunks agree. World voxel coordinates come from the [`ChunkCoord`] of the chunk, if any.
+///
+/// Insert one to opt an entity in; `candidates` stays empty until the first points are read back,
+/// and is replaced by every readback, so that `Changed<PropCandidates>` tells when to respawn the
+/// props of the chunk. Surfaces between two chunks are left out.
+#[derive(Component, Clone, Debug, PartialEq)]
+pub struct PropCandidates {
+    /// Density of the ground the points are emitted on, the voxels below it being air.
+    pub iso_level: f32,
+    /// Fraction of the ground voxels emitted, from 0 to 1.
+    pub density: f32,
+    pub seed: u32,
+    /// Steepest slope of the emitted points, in radians.
+    pub max_slope: f32,
+    /// Most points emitted per chunk, those past it are dropped.
+    pub capacity: u32,
+    pub candidates: Vec<PropCandidate>,
+    /// Whether more points than `capacity` were found in the last readback.
+    pub overflowed: bool,
+}
+
+impl Default for PropCandidates {
+    fn default() -> Self {
+        Self {
+            iso_level: DEFAULT_ISO_LEVEL,
+            density: 0.05,
+            seed: 0,
+            max_slope: std::f32::consts::FRAC_PI_4,
+            capacity: 256,
+            candidates: Vec::new(),
+            overflowed: false,
+        }
+    }
+}
+
+impl PropCandidates {
+    /// The candidates whose material is `material`.
+    pub fn with_material(&self, material: u32) -> impl Iterator<Item = &PropCandidate> {
+        self.candidates
+            .iter()
+            .filter(move |candidate| candidate.material == material)
+    }
+
+    /// Replaces the candidates with those read back for the chunk.
+    pub fn apply(&mut self, data: &[PropCandidateData]) {
+        let Some((header, points)) = data.split_first() else {
+            return;
+        };
+        let count = header.count();
+        self.overflowed = count as usize > points.len();
+        self.candidates = points
+            .iter()
+            .take(count as usize)
+            .map(|point| PropCandidate {
+                position: Vec3::from_array(point.position),
+                normal: Vec3::from_array(point.normal),
+                material: point.material,
+                slope: point.slope,
+            })
+            .collect();
+    }
+}
+
+/// Marks the render world entities whose [`PropCandidates`] are emitted.
+#[derive(Component, Clone, Copy)]
+pub struct PropCandidatesReadback;
+
+impl ExtractComponent for PropCandidates {
+    type QueryData = ();
+    type QueryFilter = With<PropCandidates>;
+    type Out = PropCandidatesReadback;
+
+    fn extract_component(_: QueryItem<'_, Self::QueryData>) -> Option<Self::Out> {
+        Some(PropCandidatesReadback)
+    }
+}
+
+impl PropCandidates {
+    /// The params of the chunk at `coord` laid out by `config`, or at the origin of the world
+    /// without one.
+    pub fn params(
+        &self,
+        coord: Option<&ChunkCoord>,
+        config: &VoxelWorldConfig,
+    ) -> PropCandidateParams {
+        PropCandidateParams {
+            origin: coord.map_or(IVec3::ZERO, |coord| config.chunk_to_voxel(*coord)),
+            seed: self.seed,
+            iso_level: self.iso_level,
+            density: self.density.clamp(0.0, 1.0),
+            min_normal_y: self.max_slope.cos(),
+            capacity: self.capacity.max(1),
+        }
+    }
+}
+
+shader_struct! {
+    #[derive(Clone, Copy, Default, PartialEq)]
+    pub struct PropCandidateParams {
+        pub origin: IVec3,
+        pub seed: u32,
+        pub iso_level: f32,
+        pub density: f32,
+        /// Cosine of the steepest slope, the smallest up component of an emitted normal.
+        pub min_normal_y: f32,
+        pub capacity: u32,
+    }
+}
+
+shader_struct! {
+    /// A [`PropCandidate`] as written by the shader, laid out as a [`PropCandidateData`].
+    #[derive(Clone, Copy, Default)]
+    pub struct PropCandidatePoint {
+        pub position: Vec3,
+        pub slope: f32,
+        pub normal: Vec3,
+        pub material: u32,
+    }
+}
+
+shader_struct! {
+    /// The points emitted by the shader, after a header the size of a point holding the number of
+    /// points found, which may exceed the capacity of the buffer.
+    pub struct PropCandidateBuffer {
+        count: AtomicU32,
+        padding: [u32; 7],
+        #[size(runtime)]
+        data: Vec<PropCandidatePoint>,
+    }
+}
+
+/// A [`PropCandidatePoint`] read back from the GPU. The first one read back for a chunk is the
+/// header of the [`PropCandidateBuffer`].
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct PropCandidateData {
+    pub position: [f32; 3],
+    pub slope: f32,
+    pub normal: [f32; 3],
+    pub material: u32,
+}
+
+impl PropCandidateData {
+    /// The number of points found, when read as the header.
+    pub fn count(&self) -> u32 {
+        bytemuck::cast::<_, [u32; 8]>(*self)[0]
+    }
+}
@@ -0,0 +1,303 @@
+use std::{ops::Range, sync::Arc};
+
+use bevy::{
+    ecs::query::QueryItem,
+    prelude::*,
+    render::{
+        extract_component::ExtractComponent,
+        render_resource::{Buffer, BufferDescriptor, BufferUsages, ShaderType},
+        renderer::RenderDevice,
+        Extract,
+    },
+};
+
+use crate::{
+    bundles::volumetric_bundle::MeshCapping,
+    render::voxel_mesh_compute_pipeline::{
+        meshed_cells, DirtyMeshes, VoxelMeshPrefixSumPipelineIds,
+    },
+    CHUNK_SZ_2,
+};
+
+use super::{
+    gpu_fixed_output_slots::GpuSlotCompaction,
+    gpu_virtual_volume::GpuVirtualVolume,
+    gpu_voxel_material::{GpuVoxelMaterial, MeshCounts},
+    material_split::MaterialIndexRanges,
+    shader_platform::CellSlot,
+    voxel_material::VoxelMaterialComponents,
+};
+
+/// The vertices and indices generated for a volumetric entity, read back whenever it is remeshed
+/// without converting them into a [`Mesh`] asset. Insert a default one to opt an entity in. Once
+/// read back, voxels written through a [`ChunkGuard`](super::voxel_world::ChunkGuard) only read
+/// back the output of the cells around them, patched into it as a [`RawMeshRegion`].
+#[derive(Component, Clone, Debug)]
+pub struct RawMeshData {
+    pub vertices: Arc<[Vec3]>,
+    /// The triplanar UV of each vertex, in voxels.
+    pub uvs: Arc<[Vec2]>,
+    /// The tangent of each vertex along its UVs, laid out as [`Mesh::ATTRIBUTE_TANGENT`].
+    pub tangents: Arc<[Vec4]>,
+    /// The linear emissive color of each vertex, interpolated from the voxels around it, laid
+    /// out as [`ATTRIBUTE_EMISSIVE`](super::iso_surface::ATTRIBUTE_EMISSIVE).
+    pub emissives: Arc<[Vec3]>,
+    pub indices: Arc<[u32]>,
+    /// The range of `indices` of each material, packed one after the other, for entities with a
+    /// [`MaterialSplit`](crate::bundles::volumetric_bundle::MaterialSplit).
+    pub material_ranges: Option<MaterialIndexRanges>,
+}
+
+impl Default for RawMeshData {
+    fn default() -> Self {
+        Self {
+            vertices: Arc::new([]),
+            uvs: Arc::new([]),
+            tangents: Arc::new([]),
+            emissives: Arc::new([]),
+            indices: Arc::new([]),
+            material_ranges: None,
+        }
+    }
+}
+
+impl RawMeshData {
+    /// Replaces the vertices and indices of a mesh with those of a [`RawMeshRegion`] remeshed
+    /// from it, shifting the indices after the region by the vertices it added or removed.
+    /// Returns `false`, leaving the mesh as is, if it is not the mesh the region was read back for.
+    pub fn patch(&mut self, region: &RawMeshRegion) -> bool {
+        let base = (self.vertices.len(), self.indices.len());
+        let is_base = base == (region.base.vertices as usize, region.base.indices as usize)
+            && self.material_ranges.is_none()
+            && [&self.uvs.len(), &self.tangents.len(), &self.emissives.len()]
+                .into_iter()
+                .all(|len| *len == base.0)
+            && region.replaced_vertices.end <= base.0
+            && region.replaced_indices.end <= base.1;
+        if !is_base {
+            return false;
+        }
+
+        fn splice<T: Copy>(old: &[T], replaced: &Range<usize>, new: &[T]) -> Arc<[T]> {
+            old[..replaced.start]
+                .iter()
+                .chain(new)
+                .chain(&old[replaced.end..])
+                .copied()
+                .collect()
+        }
+
+        let vertices = &region.replaced_vertices;
+        self.vertices = splice(&self.vertices, vertices, &region.data.vertices);
+        self.uvs = splice(&self.uvs, vertices, &region.data.uvs);
+        self.tangents = splice(&self.tangents, vertices, &region.data.tangents);
+        self.emissives = splice(&self.emissives, vertices, &region.data.emissives);
+
+        // The indices after the region only join vertices after its start, which moved along
+        // with the vertices it added or removed.
+        let added_vertices = region.data.vertices.len() as i64 - vertices.len() as i64;
+        let indices = &region.replaced_indices;
+        self.indices = self.indices[..indices.start]
+            .iter()
+            .copied()
+            .chain(region.data.indices.iter().copied())
+            .chain(
+                self.indices[indices.end..]
+                    .iter()
+                    .map(|index| (*index as i64 + added_vertices) as u32),
+            )
+            .collect();
+        true
+    }
+}
+
+/// The vertices and indices generated for the cells around changed voxels, read back instead of
+/// the whole mesh of a [`RawMeshData`] entity whose voxels were only written in a
+/// [`DirtyRegion`](super::voxel_world::DirtyRegion).
+#[derive(Clone, Debug)]
+pub struct RawMeshRegion {
+    /// The vertex and index counts of the mesh the region replaces part of.
+    pub base: MeshCounts,
+    /// The vertices of the base mesh replaced by those of the region.
+    pub replaced_vertices: Range<usize>,
+    /// The indices of the base mesh replaced by those of the region.
+    pub replaced_indices: Range<usize>,
+    /// The vertices and attributes of the region, and its indices into the patched mesh.
+    pub data: RawMeshData,
+}
+
+/// A mesh read back for a [`RawMeshData`] entity, either in full or as the [`RawMeshRegion`]
+/// remeshed since the previous one.
+#[derive(Clone, Debug)]
+pub enum RawMeshUpdate {
+    Full(RawMeshData),
+    Region(RawMeshRegion),
+}
+
+/// Marks the render world entities whose [`RawMeshData`] is read back.
+#[derive(Component, Clone, Copy)]
+pub struct RawMeshReadback;
+
+impl ExtractComponent for RawMeshData {
+    type QueryData = ();
+    type QueryFilter = ();
+    type Out = RawMeshReadback;
+
+    fn extract_component(_: QueryItem<'_, Self::QueryData>) -> Option<Self::Out> {
+        Some(RawMeshReadback)
+    }
+}
+
+/// Staging buffers receiving a copy of the generated mesh of a [`RawMeshData`] entity, in full or
+/// for its [`changed_cells`](Self::changed_cells) only.
+pub struct GpuRawMeshData {
+    pub atomics_staging_buffer: Buffer,
+    pub vertices_staging_buffer: Buffer,
+    pub uvs_staging_buffer: Buffer,
+    pub tangents_staging_buffer: Buffer,
+    pub emissives_staging_buffer: Buffer,
+    pub indices_staging_buffer: Buffer,
+    /// Receives the [`CellSlot`]s of the first and last of the changed cells, whose offsets
+    /// locate their vertices and indices in the output buffers.
+    pub cell_slots_staging_buffer: Buffer,
+    /// The cells whose output alone is read back this frame, see
+    /// [`GpuRawMeshData::select_changed_cells`].
+    pub changed_cells: Option<Range<u32>>,
+    /// The counts of the mesh last read back, `None` until it has been read back in full.
+    pub read_back: Option<MeshCounts>,
+}
+
+impl GpuRawMeshData {
+    pub fn new(render_device: &RenderDevice, gpu_voxel_material: &GpuVoxelMaterial) -> Self {
+        let staging_buffer = |label, buffer: Option<&Buffer>| {
+            render_device.create_buffer(&BufferDescriptor {
+                label: Some(label),
+                size: buffer.map_or(0, |buffer| buffer.size()),
+                usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        };
+
+        GpuRawMeshData {
+            atomics_staging_buffer: staging_buffer(
+                "raw_mesh_atomics_staging_buffer",
+                gpu_voxel_material.atomics_buffer.buffer(),
+            ),
+            vertices_staging_buffer: staging_buffer(
+                "raw_mesh_vertices_staging_buffer",
+                gpu_voxel_material.vertices_buffer.buffer(),
+            ),
+            uvs_staging_buffer: staging_buffer(
+                "raw_mesh_uvs_staging_buffer",
+                gpu_voxel_material.uvs_buffer.buffer(),
+            ),
+            tangents_staging_buffer: staging_buffer(
+                "raw_mesh_tangents_staging_buffer",
+                gpu_voxel_material.tangents_buffer.buffer(),
+            ),
+            emissives_staging_buffer: staging_buffer(
+                "raw_mesh_emissives_staging_buffer",
+                gpu_voxel_material.emissives_buffer.buffer(),
+            ),
+            indices_staging_buffer: staging_buffer(
+                "raw_mesh_indices_staging_buffer",
+                gpu_voxel_material.indices_buffer.buffer(),
+            ),
+            cell_slots_staging_buffer: render_device.create_buffer(&BufferDescriptor {
+                label: Some("raw_mesh_cell_slots_staging_buffer"),
+                size: 2 * CellSlot::min_size().get(),
+                usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            changed_cells: None,
+            read_back: None,
+        }
+    }
+
+    /// The meshed cells whose output may differ once the voxels in the `voxels` range of the
+    /// voxels buffer changed, as whole layers of `cells` cells: those sampling the voxels or the
+    /// density gradient at their corners, then those whose quads join their vertices. The output
+    /// of the cells before and after them is left as is by the prefix sum allocation, only
+    /// shifted by the vertices and indices the changed cells added or removed.
+    pub fn changed_cells(voxels: &Range<usize>, cells: UVec3, capped: bool) -> Range<u32> {
+        let layer = cells.x * cells.y;
+        // Capping meshes the cells one layer below the volume first.
+        let first = (voxels.start / CHUNK_SZ_2) as u32 + capped as u32;
+        let last = ((voxels.end.max(voxels.start + 1) - 1) / CHUNK_SZ_2) as u32 + capped as u32;
+
+        // A cell samples the gradient of the voxels one past its corners, and quads join the
+        // vertices of the cells one layer below theirs.
+        let start = first.saturating_sub(2).min(cells.z);
+        let end = (last + 3).min(cells.z);
+        start * layer..end * layer
+    }
+
+    /// Selects the [`changed_cells`](Self::changed_cells) of the entities remeshed for a
+    /// [`DirtyMeshes::mark_region`] alone, when their mesh is laid out by a prefix sum allocation
+    /// in the order of its cells and was read back before. The others are read back in full.
+    #[allow(clippy::type_complexity)]
+    pub fn select_changed_cells(
+        dirty_meshes: Res<DirtyMeshes>,
+        mut gpu_raw_meshes: ResMut<VoxelMaterialComponents<GpuRawMeshData>>,
+        prefix_sum_pipeline_ids: Res<VoxelMaterialComponents<VoxelMeshPrefixSumPipelineIds>>,
+        gpu_slot_compactions: Res<VoxelMaterialComponents<GpuSlotCompaction>>,
+        gpu_virtual_volumes: Res<VoxelMaterialComponents<GpuVirtualVolume>>,
+        material_index_ranges: Res<VoxelMaterialComponents<MaterialIndexRanges>>,
+        capping_query: Query<Has<MeshCapping>>,
+    ) {
+        for (entity, gpu_raw_mesh) in gpu_raw_meshes.0.iter_mut() {
+            let is_patchable = gpu_raw_mesh.read_back.is_some()
+                && prefix_sum_pipeline_ids.get(entity).is_some()
+                && gpu_slot_compactions.get(entity).is_some()
+                && gpu_virtual_volumes.get(entity).is_none()
+                && material_index_ranges.get(entity).is_none();
+
+            gpu_raw_mesh.changed_cells = dirty_meshes
+                .meshed_region(entity)
+                .filter(|_| is_patchable)
+                .map(|voxels| {
+                    let capped = capping_query.get(*entity).unwrap_or(false);
+                    Self::changed_cells(voxels, meshed_cells(None, capped), capped)
+                })
+                .filter(|cells| !cells.is_empty());
+        }
+    }
+
+    /// Creates the staging buffers of entities that opted into [`RawMeshData`], recreating them
+    /// whenever the entity's [`GpuVoxelMaterial`] is replaced. Entities opting in after being
+    /// meshed are remeshed so that their mesh is read back without waiting for an edit.
+    pub fn initialize(
+        render_device: Res<RenderDevice>,
+        mut dirty_meshes: ResMut<DirtyMeshes>,
+        gpu_voxel_materials: Res<VoxelMaterialComponents<GpuVoxelMaterial>>,
+        mut gpu_raw_meshes: ResMut<VoxelMaterialComponents<GpuRawMeshData>>,
+        raw_mesh_query: Extract<Query<Entity, With<RawMeshData>>>,
+    ) {
+        for entity in raw_mesh_query.iter() {
+            let Some(gpu_voxel_material) = gpu_voxel_materials.get(&entity) else {
+                continue;
+            };
+
+            let is_current = gpu_raw_meshes.get(&entity).is_some_and(|gpu_raw_mesh| {
+                Some(gpu_raw_mesh.vertices_staging_buffer.size())
+                    == gpu_voxel_material
+                        .vertices_buffer
+                        .buffer()
+                        .map(|buffer| buffer.size())
+                    && Some(gpu_raw_mesh.indices_staging_buffer.size())
+                        == gpu_voxel_material
+                            .indices_buffer
+                            .buffer()
+                            .map(|buffer| buffer.size())
+            });
+
+            if !is_current {
+                gpu_raw_meshes.insert(
+                    entity,
+                    GpuRawMeshData::new(render_device.as_ref(), gpu_voxel_material),
+                );
+                dirty_meshes.mark(entity);
+            }
+        }
+    }
+}
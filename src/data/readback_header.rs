@@ -0,0 +1,120 @@
+use std::io;
+
+use bytemuck::{Pod, Zeroable};
+
+use crate::invalid_data;
+
+/// Version of the [`MeshReadbackHeader`] layout and of the payload following it, bumped whenever
+/// either changes.
+pub const MESH_READBACK_VERSION: u32 = 1;
+
+/// The header preceding the vertices of a mesh readback, both in the vertices staging buffer of a
+/// [`GpuVoxelMaterial`](super::gpu_voxel_material::GpuVoxelMaterial) and in the words sent to the
+/// [`MainWorldReceiver`](crate::channels::MainWorldReceiver), so that tools capturing either can
+/// parse them and tell when their layout changed.
+///
+/// The format is four `u32`s, `version`, `vertex_count`, `index_count` and `flags`, followed by
+/// the vertices as `vec3<f32>` with a 16 byte stride, little-endian in bytes whatever the host, as
+/// GPU buffers are. Channel messages hold the same words as numbers. The header takes the room of
+/// one vertex, so the vertices stay 16 byte aligned. In the staging buffer, `flags` never holds
+/// [`MeshReadbackHeader::TRUNCATED`] and the vertices are one page of the mesh, see
+/// [`VertexReadback`](crate::channels::VertexReadback). Messages hold every vertex read back.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Pod, Zeroable)]
+pub struct MeshReadbackHeader {
+    pub version: u32,
+    /// Vertices generated by the meshing shader, which may exceed those read back.
+    pub vertex_count: u32,
+    /// Indices generated by the meshing shader.
+    pub index_count: u32,
+    pub flags: u32,
+}
+
+impl MeshReadbackHeader {
+    /// Bytes taken by the header.
+    pub const SIZE: u64 = std::mem::size_of::<Self>() as u64;
+    /// Words taken by the header in channel messages.
+    pub const LEN: usize = std::mem::size_of::<Self>() / std::mem::size_of::<u32>();
+
+    /// The indices are grouped by material, see
+    /// [`MaterialSplit`](crate::bundles::volumetric_bundle::MaterialSplit).
+    pub const MATERIAL_SPLIT: u32 = 1 << 0;
+    /// More vertices were generated than the vertices buffer holds, so that only those that fit
+    /// were read back.
+    pub const TRUNCATED: u32 = 1 << 1;
+
+    pub fn new(vertex_count: u32, index_count: u32, flags: u32) -> Self {
+        Self {
+            version: MESH_READBACK_VERSION,
+            vertex_count,
+            index_count,
+            flags,
+        }
+    }
+
+    /// The header as the words leading a channel message.
+    pub fn to_words(self) -> [u32; Self::LEN] {
+        [
+            self.version,
+            self.vertex_count,
+            self.index_count,
+            self.flags,
+        ]
+    }
+
+    /// The header as the little-endian bytes leading a staging buffer or a file.
+    pub fn to_bytes(self) -> [u8; Self::SIZE as usize] {
+        let mut bytes = [0; Self::SIZE as usize];
+        for (chunk, word) in bytes.chunks_exact_mut(4).zip(self.to_words()) {
+            chunk.copy_from_slice(&word.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// The header of the words leading a channel message, failing if they were written with
+    /// another [`MESH_READBACK_VERSION`].
+    fn from_words(
+        [version, vertex_count, index_count, flags]: [u32; Self::LEN],
+    ) -> io::Result<Self> {
+        if version != MESH_READBACK_VERSION {
+            return Err(invalid_data(format!(
+                "unsupported mesh readback version {version}"
+            )));
+        }
+        Ok(Self {
+            version,
+            vertex_count,
+            index_count,
+            flags,
+        })
+    }
+
+    /// Splits a channel message into its header and vertex words, failing if it is too short or
+    /// was written with another [`MESH_READBACK_VERSION`].
+    pub fn split(words: &[u32]) -> io::Result<(Self, &[u32])> {
+        if words.len() < Self::LEN {
+            return Err(invalid_data(format!(
+                "mesh readback of {} words is shorter than its header",
+                words.len()
+            )));
+        }
+        let (header, vertices) = words.split_at(Self::LEN);
+        let header = Self::from_words(header.try_into().expect("should be a header"))?;
+        Ok((header, vertices))
+    }
+
+    /// Reads the header from the first little-endian bytes of a staging buffer or a file, failing
+    /// if they are too short or were written with another [`MESH_READBACK_VERSION`].
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        let Some(bytes) = bytes.get(..Self::SIZE as usize) else {
+            return Err(invalid_data(format!(
+                "mesh readback of {} bytes is shorter than its header",
+                bytes.len()
+            )));
+        };
+        Self::from_words(std::array::from_fn(|index| {
+            let word = &bytes[index * 4..index * 4 + 4];
+            u32::from_le_bytes(word.try_into().expect("should be a u32"))
+        }))
+    }
+}
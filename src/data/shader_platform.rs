@@ -0,0 +1,146 @@
+use bevy::{
+    prelude::*,
+    render::{
+        render_resource::ShaderDefVal,
+        renderer::{RenderAdapterInfo, RenderDevice},
+        settings::Backends,
+    },
+};
+
+use crate::{core::CHUNK_SZ, render::shaders::shader_struct};
+
+/// Cells whose slots are summed by a single invocation of the slot compaction.
+pub const SLOT_BLOCK_CELLS: u32 = 256;
+
+/// What the GPU the meshing shaders are specialized for supports, detected once the render device
+/// is created. Insert one before the plugins are finished to force a variant, e.g. to test the
+/// fallbacks on a desktop GPU.
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ShaderPlatform {
+    /// Whether cells write their primitives into fixed slots compacted by a second pass, rather
+    /// than allocating them from counters with storage buffer atomics. Splitting the index buffer
+    /// by material is not supported in this mode.
+    pub fixed_output_slots: bool,
+    /// Whether cells allocate their primitives from a prefix sum of the primitives counted by a
+    /// first meshing pass, and emit them in a second pass, rather than from counters with storage
+    /// buffer atomics. The output is then laid out in the order of the cells however the GPU
+    /// schedules them, at the cost of meshing twice. Ignored with `fixed_output_slots`, and by
+    /// entities splitting their index buffer by material, which keep allocating from the atomics.
+    pub prefix_sum_allocation: bool,
+    /// Side of the cubic workgroups of the meshing pass, dividing `CHUNK_SZ`.
+    pub workgroup_size: u32,
+    /// Whether each workgroup of the marching cubes pass loads the densities of the corners of its
+    /// cells into workgroup shared memory once, rather than every cell reading its eight corners
+    /// from the voxels buffer. See [`ShaderPlatform::density_tile_size`].
+    pub density_tile: bool,
+}
+
+impl Default for ShaderPlatform {
+    fn default() -> Self {
+        Self {
+            fixed_output_slots: false,
+            prefix_sum_allocation: false,
+            workgroup_size: 8,
+            density_tile: true,
+        }
+    }
+}
+
+impl ShaderPlatform {
+    /// The variants supported by `render_device`: smaller workgroups where 8³ invocations are not
+    /// allowed, and fixed slots on the GL backend, whose mobile drivers often lack or emulate
+    /// atomics in storage buffers.
+    pub fn detect(render_device: &RenderDevice, adapter_info: &RenderAdapterInfo) -> Self {
+        let limits = render_device.limits();
+        let workgroup_size = [8, 4, 2]
+            .into_iter()
+            .find(|size: &u32| {
+                size.pow(3) <= limits.max_compute_invocations_per_workgroup
+                    && *size <= limits.max_compute_workgroup_size_z
+                    && (CHUNK_SZ as u32).is_multiple_of(*size)
+            })
+            .unwrap_or(1);
+
+        let density_tile_bytes = (workgroup_size + 1).pow(3) * std::mem::size_of::<f32>() as u32;
+
+        Self {
+            fixed_output_slots: Backends::from(adapter_info.backend) == Backends::GL,
+            prefix_sum_allocation: false,
+            workgroup_size,
+            density_tile: density_tile_bytes <= limits.max_compute_workgroup_storage_size,
+        }
+    }
+
+    /// Voxels along each axis of the tile of densities shared by a workgroup: the corners of its
+    /// cells, which reach one voxel past its last cells.
+    pub fn density_tile_size(&self) -> u32 {
+        self.workgroup_size + 1
+    }
+
+    pub fn shader_defs(&self) -> Vec<ShaderDefVal> {
+        let mut shader_defs = vec![ShaderDefVal::UInt(
+            "WORKGROUP_SIZE".into(),
+            self.workgroup_size,
+        )];
+        if self.fixed_output_slots {
+            shader_defs.push("FIXED_OUTPUT_SLOTS".into());
+            shader_defs.push("CELL_SLOTS".into());
+        }
+        shader_defs
+    }
+
+    /// Whether the cells of entities not split by material are meshed by the count and scatter
+    /// passes of a prefix sum allocation.
+    pub fn prefix_sum_passes(&self, material_split: bool) -> bool {
+        self.prefix_sum_allocation && !self.fixed_output_slots && !material_split
+    }
+
+    /// Workgroups dispatched along each axis to mesh a volume of `cells` cells per axis.
+    pub fn workgroups(&self, cells: UVec3) -> UVec3 {
+        (cells + UVec3::splat(self.workgroup_size - 1)) / self.workgroup_size
+    }
+}
+
+shader_struct! {
+    /// Primitives emitted by a cell into its fixed slot, or counted for a prefix sum allocation,
+    /// and where they start once compacted, relative to the start of the block of the cell until
+    /// the offsets of a prefix sum allocation are resolved.
+    #[derive(Clone, Copy, Default)]
+    pub struct CellSlot {
+        pub vertices: u32,
+        pub indices: u32,
+        pub vertex_offset: u32,
+        pub index_offset: u32,
+    }
+}
+
+shader_struct! {
+    #[derive(Clone)]
+    pub struct CellSlotBuffer {
+        #[size(runtime)]
+        pub data: Vec<CellSlot>,
+    }
+}
+
+shader_struct! {
+    /// The cells of a volume meshed into fixed slots, the vertices and indices of each slot, and
+    /// the capacities of its output buffers.
+    #[derive(Clone, Copy, Default)]
+    pub struct SlotCompactionParams {
+        pub cells: u32,
+        pub slot_vertices: u32,
+        pub slot_indices: u32,
+        pub vertex_capacity: u32,
+        pub index_capacity: u32,
+    }
+}
+
+shader_struct! {
+    /// One of the output buffers compacted from its fixed slots, as `words` `u32`s per element.
+    /// Indices are rebased onto the compacted vertices.
+    #[derive(Clone, Copy, Default)]
+    pub struct SlotStreamParams {
+        pub words: u32,
+        pub indices: u32,
+    }
+}
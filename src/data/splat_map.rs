@@ -0,0 +1,83 @@
+use bevy::{
+    prelude::*,
+    render::{
+        extract_component::ExtractComponent,
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages},
+    },
+};
+
+use crate::{render::shaders::shader_struct, CHUNK_SZ};
+
+use super::iso_surface::DEFAULT_ISO_LEVEL;
+
+/// The format of the splat textures generated for [`SplatMap`]s.
+pub const SPLAT_MAP_FORMAT: TextureFormat = TextureFormat::Rgba8Unorm;
+
+/// Generates a low resolution 3D splat texture from the materials of the voxels of a volumetric
+/// entity on the GPU whenever it is remeshed, for its material to blend detail textures of any
+/// resolution by the weights of the materials around each fragment. The
+/// `bevy_volumetric::splat_map` shader module samples it.
+#[derive(Clone, Component, ExtractComponent, Debug)]
+pub struct SplatMap {
+    /// The splat texture, with one texel per `cell_size`³ voxels holding in each channel the
+    /// fraction of the solid voxels around it whose flags are the material of the channel.
+    pub image: Handle<Image>,
+    /// Voxels covered by a texel along each axis.
+    pub cell_size: u32,
+    /// The material weighted by each channel of the texture, the flags of the voxels.
+    pub materials: [u32; 4],
+    /// Density from which a voxel weighs on the texels around it, so that the weights follow
+    /// the meshed surface.
+    pub iso_level: f32,
+}
+
+impl SplatMap {
+    /// Creates the splat texture of a chunk with one texel per `cell_size`³ voxels, weighting
+    /// `materials`.
+    pub fn new(images: &mut Assets<Image>, cell_size: u32, materials: [u32; 4]) -> Self {
+        let cell_size = cell_size.max(1);
+        let resolution = (CHUNK_SZ as u32).div_ceil(cell_size);
+
+        let mut image = Image::new_fill(
+            Extent3d {
+                width: resolution,
+                height: resolution,
+                depth_or_array_layers: resolution,
+            },
+            TextureDimension::D3,
+            &[0, 0, 0, 0],
+            SPLAT_MAP_FORMAT,
+            RenderAssetUsages::RENDER_WORLD,
+        );
+        image.texture_descriptor.usage = TextureUsages::COPY_DST
+            | TextureUsages::STORAGE_BINDING
+            | TextureUsages::TEXTURE_BINDING;
+
+        Self {
+            image: images.add(image),
+            cell_size,
+            materials,
+            iso_level: DEFAULT_ISO_LEVEL,
+        }
+    }
+}
+
+shader_struct! {
+    #[derive(Clone, Copy, Default)]
+    pub struct SplatMapParams {
+        materials: UVec4,
+        cell_size: u32,
+        iso_level: f32,
+    }
+}
+
+impl From<&SplatMap> for SplatMapParams {
+    fn from(splat_map: &SplatMap) -> Self {
+        Self {
+            materials: UVec4::from_array(splat_map.materials),
+            cell_size: splat_map.cell_size.max(1),
+            iso_level: splat_map.iso_level,
+        }
+    }
+}
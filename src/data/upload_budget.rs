@@ -0,0 +1,60 @@
+use bevy::{prelude::*, render::extract_resource::ExtractResource};
+
+/// Limits how many bytes of voxels are written to the GPU each frame, so that bursts of chunks
+/// generated or loaded while streaming are spread over several frames instead of stalling the
+/// queue. Uploads over the limit wait in the [`UploadQueue`] by descending
+/// [`ChunkPriority`](super::chunk_priority::ChunkPriority), and their entities stay
+/// [`Uploading`](super::gpu_chunk_state::GpuChunkState::Uploading) until they are written.
+///
+/// Uploads of [`CompressedUpload`](super::compressed_voxels::CompressedUpload) entities are
+/// decompressed on the GPU and not limited.
+#[derive(Resource, Clone, Copy, Debug, ExtractResource)]
+pub struct UploadBudget {
+    /// Maximum number of bytes uploaded per frame. At least one chunk is always uploaded.
+    pub max_bytes_per_frame: u64,
+}
+
+impl Default for UploadBudget {
+    fn default() -> Self {
+        Self {
+            max_bytes_per_frame: u64::MAX,
+        }
+    }
+}
+
+impl UploadBudget {
+    /// A budget of `megabytes` per frame.
+    pub fn from_megabytes(megabytes: f32) -> Self {
+        Self {
+            max_bytes_per_frame: (megabytes.max(0.0) as f64 * 1024.0 * 1024.0) as u64,
+        }
+    }
+}
+
+/// The voxel uploads scheduled by [`GpuVoxelMaterial::prepare`] under the [`UploadBudget`] in the
+/// frame being rendered.
+///
+/// [`GpuVoxelMaterial::prepare`]: super::gpu_voxel_material::GpuVoxelMaterial::prepare
+#[derive(Resource, Default)]
+pub struct UploadQueue {
+    pub(crate) uploaded: Vec<Entity>,
+    pub(crate) pending: Vec<Entity>,
+    pub(crate) bytes: u64,
+}
+
+impl UploadQueue {
+    /// The entities whose voxels were uploaded this frame, by descending priority.
+    pub fn uploaded(&self) -> &[Entity] {
+        &self.uploaded
+    }
+
+    /// The entities whose upload was deferred to a later frame, by descending priority.
+    pub fn pending(&self) -> &[Entity] {
+        &self.pending
+    }
+
+    /// Bytes of voxels uploaded this frame.
+    pub fn bytes_uploaded(&self) -> u64 {
+        self.bytes
+    }
+}
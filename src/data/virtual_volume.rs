@@ -0,0 +1,80 @@
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+
+use crate::CHUNK_SZ_3;
+
+use super::voxel::Voxel;
+
+/// A volume of `extent` bricks of 32³ voxels of which only the resident bricks are stored. On the
+/// GPU the resident bricks live in a pool of `pool_capacity` bricks, addressed through a page
+/// table holding the pool slot of every brick.
+#[derive(Component)]
+pub struct VirtualVolume {
+    /// Size of the volume in bricks.
+    pub extent: UVec3,
+    /// Maximum number of bricks resident at once.
+    pub pool_capacity: u32,
+    bricks: HashMap<UVec3, Vec<Voxel>>,
+    changed_bricks: HashSet<UVec3>,
+}
+
+impl VirtualVolume {
+    pub fn new(extent: UVec3, pool_capacity: u32) -> Self {
+        Self {
+            extent,
+            pool_capacity,
+            bricks: default(),
+            changed_bricks: default(),
+        }
+    }
+
+    /// Makes `brick` resident with the given voxels, padded with empty voxels or truncated to 32³.
+    /// Returns `false`, leaving the volume unchanged, if the brick is outside the volume or the
+    /// pool is full.
+    pub fn insert_brick(&mut self, brick: UVec3, mut voxels: Vec<Voxel>) -> bool {
+        let is_full = self.bricks.len() >= self.pool_capacity as usize;
+        if brick.cmpge(self.extent).any() || (is_full && !self.bricks.contains_key(&brick)) {
+            return false;
+        }
+
+        voxels.resize(CHUNK_SZ_3, Voxel::new(0, 0.0));
+        self.bricks.insert(brick, voxels);
+        self.changed_bricks.insert(brick);
+        true
+    }
+
+    /// Evicts `brick`, returning its voxels if it was resident.
+    pub fn evict_brick(&mut self, brick: UVec3) -> Option<Vec<Voxel>> {
+        let voxels = self.bricks.remove(&brick)?;
+        self.changed_bricks.insert(brick);
+        Some(voxels)
+    }
+
+    pub fn brick(&self, brick: UVec3) -> Option<&[Voxel]> {
+        self.bricks.get(&brick).map(Vec::as_slice)
+    }
+
+    pub fn is_resident(&self, brick: UVec3) -> bool {
+        self.bricks.contains_key(&brick)
+    }
+
+    pub fn resident_bricks(&self) -> impl Iterator<Item = UVec3> + '_ {
+        self.bricks.keys().copied()
+    }
+
+    /// Bricks inserted or evicted since the last frame.
+    pub fn changed_bricks(&self) -> impl Iterator<Item = UVec3> + '_ {
+        self.changed_bricks.iter().copied()
+    }
+
+    /// Clears the changed bricks once they have been extracted.
+    pub fn clear_changed_bricks(mut virtual_volume_query: Query<&mut VirtualVolume>) {
+        for mut virtual_volume in virtual_volume_query.iter_mut() {
+            if !virtual_volume.changed_bricks.is_empty() {
+                virtual_volume.changed_bricks.clear();
+            }
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use std::sync::atomic::AtomicU32;
+
+use bevy::{ecs::query::QueryItem, prelude::*, render::extract_component::ExtractComponent};
+use bytemuck::{Pod, Zeroable};
+
+use crate::render::shaders::shader_struct;
+
+/// Number of bins of the density histogram of a [`VolumeStatistics`].
+pub const HISTOGRAM_BINS: usize = 256;
+
+/// Most histogram valleys suggested as iso-values after the Otsu threshold.
+const MAX_VALLEYS: usize = 4;
+
+/// The density histogram of a volumetric entity, computed on the GPU whenever its voxels change,
+/// with iso-values suggested from it to pick a surface level for volumes of unknown range. Insert a
+/// default one to opt an entity in.
+#[derive(Component, Clone, Debug, Default)]
+pub struct VolumeStatistics {
+    pub min_density: f32,
+    pub max_density: f32,
+    /// Voxel counts of [`HISTOGRAM_BINS`] bins of equal width spanning the density range.
+    pub histogram: Vec<u32>,
+    /// Iso-values likely to separate the materials of the volume, best first: the Otsu threshold
+    /// followed by the deepest valleys of the histogram.
+    pub suggested_iso_values: Vec<f32>,
+}
+
+impl VolumeStatistics {
+    pub fn from_histogram(min_density: f32, max_density: f32, histogram: Vec<u32>) -> Self {
+        let mut statistics = Self {
+            min_density,
+            max_density,
+            histogram,
+            suggested_iso_values: Vec::new(),
+        };
+
+        let otsu_threshold = statistics.otsu_threshold();
+        let bin_width = statistics.bin_width();
+        let valleys = statistics
+            .valleys()
+            .into_iter()
+            .filter(|valley| otsu_threshold.is_none_or(|otsu| (otsu - valley).abs() > bin_width))
+            .take(MAX_VALLEYS);
+        statistics.suggested_iso_values = otsu_threshold.into_iter().chain(valleys).collect();
+
+        statistics
+    }
+
+    /// The statistics of the histogram read back for the volume.
+    pub fn from_data(data: &VolumeHistogramData) -> Self {
+        let bins = data.bins.to_vec();
+        match bins.iter().any(|count| *count > 0) {
+            true => Self::from_histogram(
+                from_order_key(!data.min_key),
+                from_order_key(data.max_key),
+                bins,
+            ),
+            false => Self::from_histogram(0.0, 0.0, bins),
+        }
+    }
+
+    pub fn voxel_count(&self) -> u64 {
+        self.histogram.iter().map(|count| *count as u64).sum()
+    }
+
+    pub fn bin_width(&self) -> f32 {
+        (self.max_density - self.min_density) / self.histogram.len().max(1) as f32
+    }
+
+    /// The density at the lower edge of `bin`.
+    pub fn bin_edge(&self, bin: usize) -> f32 {
+        self.min_density + bin as f32 * self.bin_width()
+    }
+
+    /// The density maximising the variance between the voxels below and above it.
+    pub fn otsu_threshold(&self) -> Option<f32> {
+        let total = self.voxel_count();
+        let weighted_total = self
+            .histogram
+            .iter()
+            .enumerate()
+            .map(|(bin, count)| bin as f64 * *count as f64)
+            .sum::<f64>();
+
+        let mut below = 0;
+        let mut weighted_below = 0.0;
+        let mut best = None;
+        let mut best_variance = 0.0;
+
+        for (bin, count) in self.histogram.iter().enumerate() {
+            below += *count as u64;
+            weighted_below += bin as f64 * *count as f64;
+            let above = total - below;
+            if below == 0 {
+                continue;
+            }
+            if above == 0 {
+                break;
+            }
+
+            let mean_below = weighted_below / below as f64;
+            let mean_above = (weighted_total - weighted_below) / above as f64;
+            let variance = below as f64 * above as f64 * (mean_below - mean_above).powi(2);
+            if variance > best_variance {
+                best_variance = variance;
+                best = Some(bin);
+            }
+        }
+
+        best.map(|bin| self.bin_edge(bin + 1))
+    }
+
+    /// The centres of the local minima of the smoothed histogram, emptiest first.
+    fn valleys(&self) -> Vec<f32> {
+        let smoothed = (0..self.histogram.len())
+            .map(|bin| {
+                let window =
+                    &self.histogram[bin.saturating_sub(2)..(bin + 3).min(self.histogram.len())];
+                window.iter().map(|count| *count as f32).sum::<f32>() / window.len() as f32
+            })
+            .collect::<Vec<_>>();
+
+        let mut valleys = (1..smoothed.len().saturating_sub(1))
+            .filter(|bin| smoothed[bin - 1] > smoothed[*bin] && smoothed[*bin] <= smoothed[bin + 1])
+            .collect::<Vec<_>>();
+        valleys.sort_by(|a, b| smoothed[*a].total_cmp(&smoothed[*b]));
+
+        valleys
+            .into_iter()
+            .map(|bin| self.bin_edge(bin) + self.bin_width() * 0.5)
+            .collect()
+    }
+}
+
+/// Marks the render world entities whose [`VolumeStatistics`] are computed.
+#[derive(Component, Clone, Copy)]
+pub struct VolumeStatisticsReadback;
+
+impl ExtractComponent for VolumeStatistics {
+    type QueryData = ();
+    type QueryFilter = ();
+    type Out = VolumeStatisticsReadback;
+
+    fn extract_component(_: QueryItem<'_, Self::QueryData>) -> Option<Self::Out> {
+        Some(VolumeStatisticsReadback)
+    }
+}
+
+/// Maps the order key of a density written by the statistics shader back to the density.
+pub fn from_order_key(key: u32) -> f32 {
+    match key & 0x8000_0000 != 0 {
+        true => f32::from_bits(key & 0x7fff_ffff),
+        false => f32::from_bits(!key),
+    }
+}
+
+shader_struct! {
+    #[derive(Clone, Copy, Default)]
+    pub struct VolumeStatisticsParams {
+        pub voxel_count: u32,
+    }
+}
+
+shader_struct! {
+    /// The density range and histogram accumulated by the statistics shader, laid out as a
+    /// [`VolumeHistogramData`]. The range is stored as order keys, with the complement of the
+    /// smallest one in `min_key` so that both start at zero.
+    pub struct VolumeHistogram {
+        min_key: AtomicU32,
+        max_key: AtomicU32,
+        bins: [AtomicU32; HISTOGRAM_BINS],
+    }
+}
+
+/// The [`VolumeHistogram`] read back from the GPU.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Pod, Zeroable)]
+pub struct VolumeHistogramData {
+    pub min_key: u32,
+    pub max_key: u32,
+    pub bins: [u32; HISTOGRAM_BINS],
+}
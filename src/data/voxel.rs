@@ -1,22 +1,17 @@
-use bevy::render::render_resource::ShaderType;
+pub use crate::core::Voxel;
 
-#[derive(ShaderType, Clone, Copy)]
-pub struct Voxel {
-    flags: u32,
-    density: f32,
-}
+use crate::render::shaders::{WgslStruct, WgslType};
 
-impl Default for Voxel {
-    fn default() -> Self {
-        Self {
-            flags: 0,
-            density: 1.,
-        }
+impl WgslType for Voxel {
+    fn wgsl_type() -> String {
+        "Voxel".into()
     }
 }
 
-impl Voxel {
-    pub fn new(flags: u32, density: f32) -> Self {
-        Self { flags, density }
+/// Laid out like the fields of [`Voxel`], the emissive color being unpacked by `unpack_emissive`
+/// in `voxel.wgsl`.
+impl WgslStruct for Voxel {
+    fn wgsl_struct() -> String {
+        "struct Voxel {\n    flags: u32,\n    density: f32,\n    emissive: u32,\n};\n".into()
     }
 }
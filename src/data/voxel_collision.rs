@@ -0,0 +1,117 @@
+use bevy::prelude::*;
+
+use crate::CHUNK_SZ;
+
+use super::{
+    iso_surface::DEFAULT_ISO_LEVEL, voxel::Voxel, voxel_material::VoxelMaterial,
+    voxel_world::voxel_index,
+};
+
+/// A contact between a shape and the surface of a density field.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VoxelContact {
+    /// Closest point on the surface.
+    pub point: Vec3,
+    /// Surface normal at the contact, pointing out of the solid.
+    pub normal: Vec3,
+    /// Distance the shape has to move along `normal` to stop penetrating the surface.
+    pub depth: f32,
+}
+
+/// Collision tests of shapes against the density field of a [`VoxelMaterial`], so character
+/// controllers can collide with terrain without building a trimesh collider from the mesh.
+///
+/// Positions are in the local space of the generated mesh, one unit per voxel, see
+/// [`VoxelWorldConfig::world_to_local`](super::voxel_world::VoxelWorldConfig::world_to_local).
+/// Distances to the surface are estimated from the density and its gradient, and are accurate
+/// within a few voxels of the surface.
+pub struct VoxelCollision<'a> {
+    voxels: &'a [Voxel],
+}
+
+impl<'a> VoxelCollision<'a> {
+    pub fn new(voxel_material: &'a VoxelMaterial) -> Self {
+        Self {
+            voxels: voxel_material.voxels(),
+        }
+    }
+
+    fn voxel_density(&self, pos: IVec3) -> f32 {
+        if pos.cmplt(IVec3::ZERO).any() || pos.cmpge(IVec3::splat(CHUNK_SZ as i32)).any() {
+            return 0.0;
+        }
+        self.voxels
+            .get(voxel_index(pos.as_uvec3()))
+            .map_or(0.0, Voxel::density)
+    }
+
+    /// Trilinearly interpolated density at `pos`, treating everything outside the chunk as empty.
+    pub fn density(&self, pos: Vec3) -> f32 {
+        let base = pos.floor();
+        let t = pos - base;
+        let base = base.as_ivec3();
+
+        let mut density = 0.0;
+        for corner in 0..8 {
+            let offset = IVec3::new(corner & 1, (corner >> 1) & 1, (corner >> 2) & 1);
+            let weight = Vec3::select(offset.cmpeq(IVec3::ONE), t, Vec3::ONE - t);
+            density += weight.x * weight.y * weight.z * self.voxel_density(base + offset);
+        }
+        density
+    }
+
+    /// Gradient of the density at `pos` by central differences, pointing into the solid.
+    pub fn gradient(&self, pos: Vec3) -> Vec3 {
+        Vec3::new(
+            self.density(pos + Vec3::X) - self.density(pos - Vec3::X),
+            self.density(pos + Vec3::Y) - self.density(pos - Vec3::Y),
+            self.density(pos + Vec3::Z) - self.density(pos - Vec3::Z),
+        ) * 0.5
+    }
+
+    /// Estimated signed distance from `pos` to the surface, negative inside the solid, along
+    /// with the outward normal.
+    pub fn distance(&self, pos: Vec3) -> (f32, Vec3) {
+        let density = self.density(pos);
+        let gradient = self.gradient(pos);
+        let slope = gradient.length();
+
+        if slope < 1e-4 {
+            // Flat field: either far from the surface or deep inside the solid.
+            return match density >= DEFAULT_ISO_LEVEL {
+                true => (f32::NEG_INFINITY, Vec3::Y),
+                false => (f32::INFINITY, Vec3::Y),
+            };
+        }
+
+        ((DEFAULT_ISO_LEVEL - density) / slope, -gradient / slope)
+    }
+
+    /// Tests a sphere against the surface.
+    pub fn sphere(&self, center: Vec3, radius: f32) -> Option<VoxelContact> {
+        let (distance, normal) = self.distance(center);
+        if distance >= radius {
+            return None;
+        }
+
+        // Deep inside the solid there is no surface nearby to push the sphere out towards.
+        let distance = distance.max(-radius);
+
+        Some(VoxelContact {
+            point: center - normal * distance,
+            normal,
+            depth: radius - distance,
+        })
+    }
+
+    /// Tests a capsule between `a` and `b` against the surface, returning the deepest contact
+    /// along its segment.
+    pub fn capsule(&self, a: Vec3, b: Vec3, radius: f32) -> Option<VoxelContact> {
+        // Sample the segment at least every half voxel.
+        let steps = ((b - a).length() * 2.0).ceil().max(1.0) as u32;
+
+        (0..=steps)
+            .filter_map(|step| self.sphere(a.lerp(b, step as f32 / steps as f32), radius))
+            .max_by(|x, y| x.depth.total_cmp(&y.depth))
+    }
+}
@@ -0,0 +1,261 @@
+use bevy::{
+    prelude::*,
+    render::render_resource::TextureFormat,
+    utils::{HashMap, HashSet},
+};
+
+use super::{
+    chunk_coord::ChunkCoord,
+    iso_surface::DEFAULT_ISO_LEVEL,
+    voxel::Voxel,
+    voxel_edit::AppliedEditBatches,
+    voxel_material::VoxelMaterial,
+    voxel_world::{voxel_index, VoxelWorld},
+};
+
+/// A 2D mask of coverage from 0 to 1, projected onto the surface of the voxels to paint a
+/// material over them, e.g. scorch marks, roads or texture stamps.
+#[derive(Clone, Debug)]
+pub struct VoxelDecal {
+    pub width: u32,
+    pub height: u32,
+    /// Laid out row by row from the top left texel.
+    pub mask: Vec<f32>,
+}
+
+impl VoxelDecal {
+    /// The mask of the red channel of single channel images, or of the alpha of RGBA images, or
+    /// `None` for other formats.
+    pub fn from_image(image: &Image) -> Option<Self> {
+        let mask = match image.texture_descriptor.format {
+            TextureFormat::R8Unorm => image.data.iter().map(|r| *r as f32 / 255.0).collect(),
+            TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => image
+                .data
+                .chunks_exact(4)
+                .map(|rgba| rgba[3] as f32 / 255.0)
+                .collect(),
+            TextureFormat::R32Float => image
+                .data
+                .chunks_exact(4)
+                .map(|r| f32::from_le_bytes(r.try_into().expect("should be a f32")))
+                .collect(),
+            _ => return None,
+        };
+        Some(Self {
+            width: image.width(),
+            height: image.height(),
+            mask,
+        })
+    }
+
+    /// The coverage at `uv`, from the top left corner at zero to the bottom right one at one,
+    /// of the nearest texel.
+    pub fn sample(&self, uv: Vec2) -> f32 {
+        if self.width == 0
+            || self.height == 0
+            || uv.cmplt(Vec2::ZERO).any()
+            || uv.cmpgt(Vec2::ONE).any()
+        {
+            return 0.0;
+        }
+        let x = ((uv.x * self.width as f32) as u32).min(self.width - 1);
+        let y = ((uv.y * self.height as f32) as u32).min(self.height - 1);
+        self.mask
+            .get((y * self.width + x) as usize)
+            .copied()
+            .unwrap_or(0.0)
+    }
+
+    /// Projects the decal from the rectangle of `projection`, painting the material of the first
+    /// solid voxels hit along its direction where the mask covers at least its `cutoff`, and
+    /// recording the painted voxels in the dirty regions of their chunks so that they are
+    /// remeshed. Returns the touched chunks; rays over chunks that are not spawned paint nothing.
+    pub fn project(
+        &self,
+        voxel_world: &mut VoxelWorld,
+        projection: DecalProjection,
+    ) -> Vec<ChunkCoord> {
+        let painted = self.surface_voxels(voxel_world, &projection);
+
+        let mut touched = Vec::new();
+        for (coord, positions) in painted {
+            let Some(mut chunk) = voxel_world.chunk_mut(coord) else {
+                continue;
+            };
+            for position in positions {
+                if let Some(current) = chunk.get(position) {
+                    chunk.set(position, Voxel::new(projection.material, current.density()));
+                }
+            }
+            touched.push(coord);
+        }
+        touched
+    }
+
+    /// The voxels of each chunk [`VoxelDecal::project`] paints with `projection`.
+    fn surface_voxels(
+        &self,
+        voxel_world: &VoxelWorld,
+        projection: &DecalProjection,
+    ) -> HashMap<ChunkCoord, HashSet<UVec3>> {
+        let mut painted = HashMap::<ChunkCoord, HashSet<UVec3>>::new();
+        let config = voxel_world.config();
+        let (Some(direction), Some(right)) = (
+            projection.direction.try_normalize(),
+            projection.direction.cross(projection.up).try_normalize(),
+        ) else {
+            return painted;
+        };
+        let up = right.cross(direction);
+
+        // One ray per voxel across the rectangle, stepping half a voxel along the direction.
+        let rays = (projection.size / config.voxel_size)
+            .ceil()
+            .max(Vec2::ONE)
+            .as_uvec2();
+        let step = config.voxel_size * 0.5;
+        let steps = (projection.distance.max(0.0) / step).ceil() as u32;
+
+        let mut chunks = HashMap::<ChunkCoord, Option<&VoxelMaterial>>::new();
+        for ray_y in 0..rays.y {
+            for ray_x in 0..rays.x {
+                let uv = (UVec2::new(ray_x, ray_y).as_vec2() + 0.5) / rays.as_vec2();
+                if self.sample(uv) < projection.cutoff {
+                    continue;
+                }
+                let start = projection.origin
+                    + right * (uv.x - 0.5) * projection.size.x
+                    + up * (0.5 - uv.y) * projection.size.y;
+
+                let mut hits = 0;
+                let mut last = None;
+                for i in 0..=steps {
+                    let voxel = config
+                        .world_to_voxel_space(start + direction * (i as f32 * step))
+                        .round()
+                        .as_ivec3();
+                    if last == Some(voxel) {
+                        continue;
+                    }
+                    last = Some(voxel);
+
+                    let (coord, position) = config.voxel_to_chunk(voxel);
+                    let voxel_material = chunks
+                        .entry(coord)
+                        .or_insert_with(|| voxel_world.chunk(coord));
+                    let solid = voxel_material
+                        .and_then(|voxel_material| {
+                            voxel_material.voxels().get(voxel_index(position))
+                        })
+                        .is_some_and(|voxel| voxel.density() >= projection.threshold);
+                    if !solid {
+                        continue;
+                    }
+
+                    painted.entry(coord).or_default().insert(position);
+                    hits += 1;
+                    if hits >= projection.depth.max(1) {
+                        break;
+                    }
+                }
+            }
+        }
+        painted
+    }
+}
+
+/// Where and how a [`VoxelDecal`] is projected onto the voxels of the world.
+#[derive(Clone, Copy, Debug)]
+pub struct DecalProjection {
+    /// World space center of the rectangle the decal is projected from.
+    pub origin: Vec3,
+    /// World space direction the decal is projected along.
+    pub direction: Vec3,
+    /// World space direction of the top of the decal, orthogonalized against `direction`.
+    pub up: Vec3,
+    /// World space width and height of the rectangle.
+    pub size: Vec2,
+    /// World space distance from the rectangle along `direction` within which voxels are painted.
+    pub distance: f32,
+    /// Solid voxels painted along each ray from the surface inwards.
+    pub depth: u32,
+    /// The flags the painted voxels are given.
+    pub material: u32,
+    /// Coverage of the mask from which a ray paints.
+    pub cutoff: f32,
+    /// Density from which the voxels along a ray are painted and count towards `depth`.
+    pub threshold: f32,
+}
+
+impl Default for DecalProjection {
+    fn default() -> Self {
+        Self {
+            origin: Vec3::ZERO,
+            direction: Vec3::NEG_Y,
+            up: Vec3::NEG_Z,
+            size: Vec2::ONE,
+            distance: 16.0,
+            depth: 1,
+            material: 0,
+            cutoff: 0.5,
+            threshold: DEFAULT_ISO_LEVEL,
+        }
+    }
+}
+
+/// Requests projecting the mask of an image as a [`VoxelDecal`], once the image is loaded.
+#[derive(Event, Clone, Debug)]
+pub struct ProjectDecal {
+    pub mask: Handle<Image>,
+    pub projection: DecalProjection,
+}
+
+/// A [`ProjectDecal`] that has been applied, with the chunks it touched.
+#[derive(Event, Clone, Debug)]
+pub struct DecalProjected {
+    pub mask: AssetId<Image>,
+    pub projection: DecalProjection,
+    pub chunks: Vec<ChunkCoord>,
+}
+
+impl ProjectDecal {
+    /// Projects the requested decals, keeping the requests whose image is not loaded yet, and
+    /// reports each projected one as [`DecalProjected`]. Images of a format
+    /// [`VoxelDecal::from_image`] does not read are dropped with a warning. The chunks of a decal
+    /// are remeshed together, like those of a [`ChunkEditBatch`](super::voxel_edit::ChunkEditBatch).
+    pub fn apply(
+        mut requests: EventReader<ProjectDecal>,
+        mut pending: Local<Vec<ProjectDecal>>,
+        mut projected: EventWriter<DecalProjected>,
+        mut applied: ResMut<AppliedEditBatches>,
+        images: Res<Assets<Image>>,
+        mut voxel_world: VoxelWorld,
+    ) {
+        pending.extend(requests.read().cloned());
+        pending.retain(|request| {
+            let Some(image) = images.get(&request.mask) else {
+                return true;
+            };
+            let Some(decal) = VoxelDecal::from_image(image) else {
+                warn!(
+                    "Dropping a decal of unsupported format {:?}",
+                    image.texture_descriptor.format
+                );
+                return false;
+            };
+            let chunks = decal.project(&mut voxel_world, request.projection);
+            applied.0.push(
+                chunks
+                    .iter()
+                    .filter_map(|coord| voxel_world.entity(*coord))
+                    .collect(),
+            );
+            projected.send(DecalProjected {
+                mask: request.mask.id(),
+                projection: request.projection,
+                chunks,
+            });
+            false
+        });
+    }
+}
@@ -0,0 +1,284 @@
+use bevy::{
+    prelude::*,
+    render::{primitives::Aabb, Extract},
+};
+
+use crate::{edit_locks::EditLocks, render::voxel_mesh_compute_pipeline::DirtyMeshes, CHUNK_SZ};
+
+use super::{
+    chunk_coord::ChunkCoord,
+    material_split::MAX_MATERIALS,
+    voxel::Voxel,
+    voxel_material::VoxelMaterial,
+    voxel_world::{voxel_index, DirtyRegion, VoxelWorld, VoxelWorldConfig},
+};
+
+/// A modification of the voxels of a chunk.
+#[derive(Clone, Copy)]
+pub enum VoxelEdit {
+    /// Replaces a single voxel.
+    Set { position: UVec3, voxel: Voxel },
+    /// Sets the density of every voxel within `radius` of `center`.
+    Sphere {
+        center: Vec3,
+        radius: f32,
+        density: f32,
+    },
+}
+
+impl VoxelEdit {
+    /// The first voxel the edit may change and the voxel past the last one.
+    pub fn bounds(&self) -> (UVec3, UVec3) {
+        match *self {
+            VoxelEdit::Set { position, .. } => (position, position + UVec3::ONE),
+            VoxelEdit::Sphere { center, radius, .. } => {
+                let max = UVec3::splat(CHUNK_SZ as u32 - 1);
+                let min = (center - radius)
+                    .floor()
+                    .max(Vec3::ZERO)
+                    .as_uvec3()
+                    .min(max);
+                let max = (center + radius).ceil().max(Vec3::ZERO).as_uvec3().min(max);
+                (min, max + UVec3::ONE)
+            }
+        }
+    }
+
+    /// Applies the edit to the voxels of `voxel_material`, returning the materials it touched as
+    /// in [`ChunkEdited::materials_touched`].
+    pub fn apply(&self, voxel_material: &mut VoxelMaterial) -> u32 {
+        self.apply_unlocked(voxel_material, |_| false)
+    }
+
+    /// Applies the edit to the voxels of `voxel_material` at which `is_locked` is `false`,
+    /// returning the materials it touched as in [`ChunkEdited::materials_touched`]. The voxels
+    /// are then uploaded in full, see [`VoxelMaterial::voxels_mut`].
+    pub fn apply_unlocked(
+        &self,
+        voxel_material: &mut VoxelMaterial,
+        is_locked: impl Fn(UVec3) -> bool,
+    ) -> u32 {
+        self.apply_to_voxels(voxel_material.voxels_mut(), is_locked)
+    }
+
+    /// Applies the edit to the voxels of a chunk at which `is_locked` is `false`, returning the
+    /// materials it touched as in [`ChunkEdited::materials_touched`].
+    pub fn apply_to_voxels(&self, voxels: &mut [Voxel], is_locked: impl Fn(UVec3) -> bool) -> u32 {
+        let mut materials = 0;
+        match *self {
+            VoxelEdit::Set { position, voxel } => {
+                if is_locked(position) {
+                    return materials;
+                }
+                if let Some(target) = voxels.get_mut(voxel_index(position)) {
+                    // Both the material replaced and the one replacing it change.
+                    materials |= material_bit(target.flags()) | material_bit(voxel.flags());
+                    *target = voxel;
+                }
+            }
+            VoxelEdit::Sphere {
+                center,
+                radius,
+                density,
+            } => {
+                let (min, max) = self.bounds();
+
+                for z in min.z..max.z {
+                    for y in min.y..max.y {
+                        for x in min.x..max.x {
+                            let pos = UVec3::new(x, y, z);
+                            if pos.as_vec3().distance(center) > radius || is_locked(pos) {
+                                continue;
+                            }
+                            if let Some(target) = voxels.get_mut(voxel_index(pos)) {
+                                materials |= material_bit(target.flags());
+                                *target = target.with_density(density);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        materials
+    }
+}
+
+/// The bit of the material of voxels with `flags` in [`ChunkEdited::materials_touched`], clamped
+/// to the last material as when the index buffer is split by material.
+fn material_bit(flags: u32) -> u32 {
+    1 << flags.min(MAX_MATERIALS as u32 - 1)
+}
+
+/// Requests applying a [`VoxelEdit`] to the [`VoxelMaterial`] of a volumetric entity.
+#[derive(Event, Clone, Copy)]
+pub struct ChunkEdit {
+    pub entity: Entity,
+    pub edit: VoxelEdit,
+}
+
+/// A [`ChunkEdit`] that has been applied, identified by the [`ChunkCoord`] of its chunk, with the
+/// region it affected so that colliders, props and the like only update what intersects it.
+#[derive(Event, Clone, Copy)]
+pub struct ChunkEdited {
+    pub entity: Entity,
+    pub coord: ChunkCoord,
+    pub edit: VoxelEdit,
+    /// The world space bounds of the cells whose mesh the edit may have changed: those around
+    /// the voxels it may have changed, which are corners of the cells on either side of them.
+    pub aabb: Aabb,
+    /// The materials of the voxels the edit changed, bit `m` standing for the voxels whose flags
+    /// are the material `m`, or at least [`MAX_MATERIALS`]` - 1` for the last bit. Zero if every
+    /// voxel the edit covers is locked.
+    pub materials_touched: u32,
+}
+
+impl ChunkEdited {
+    /// Whether the edit changed voxels of `material`, see [`ChunkEdited::materials_touched`].
+    pub fn touches_material(&self, material: u32) -> bool {
+        self.materials_touched & material_bit(material) != 0
+    }
+
+    /// The world space bounds of the cells around the voxels from `min` up to, but excluding,
+    /// `max` of the chunk at `coord`.
+    fn cells_aabb(config: &VoxelWorldConfig, coord: ChunkCoord, min: UVec3, max: UVec3) -> Aabb {
+        let origin = config.chunk_to_voxel(coord);
+        Aabb::from_min_max(
+            config.voxel_to_world(origin + min.as_ivec3() - IVec3::ONE),
+            config.voxel_to_world(origin + max.as_ivec3()),
+        )
+    }
+}
+
+/// Requests applying [`ChunkEdit`]s to several chunks as a single transaction, e.g. a brush
+/// spanning chunk borders: either every edit is applied in the same frame or, if any of the chunks
+/// has no [`VoxelMaterial`], none of them is. The chunks are then remeshed in the same frame, so
+/// that the seams between them never show a half-applied edit.
+#[derive(Event, Clone, Default)]
+pub struct ChunkEditBatch {
+    pub edits: Vec<ChunkEdit>,
+}
+
+impl ChunkEditBatch {
+    /// Sets the density of every voxel within `radius` of the world position `center` across all
+    /// the chunks of `voxel_world` the sphere overlaps.
+    pub fn sphere(voxel_world: &VoxelWorld, center: Vec3, radius: f32, density: f32) -> Self {
+        let config = voxel_world.config();
+        let center = config.world_to_voxel_space(center);
+        let radius = radius / config.voxel_size;
+        let (min_chunk, _) = config.voxel_to_chunk((center - radius).floor().as_ivec3());
+        let (max_chunk, _) = config.voxel_to_chunk((center + radius).ceil().as_ivec3());
+
+        let mut edits = Vec::new();
+        for chunk_z in min_chunk.0.z..=max_chunk.0.z {
+            for chunk_y in min_chunk.0.y..=max_chunk.0.y {
+                for chunk_x in min_chunk.0.x..=max_chunk.0.x {
+                    let coord = ChunkCoord(IVec3::new(chunk_x, chunk_y, chunk_z));
+                    let Some(entity) = voxel_world.entity(coord) else {
+                        continue;
+                    };
+                    edits.push(ChunkEdit {
+                        entity,
+                        edit: VoxelEdit::Sphere {
+                            center: center - config.chunk_to_voxel(coord).as_vec3(),
+                            radius,
+                            density,
+                        },
+                    });
+                }
+            }
+        }
+        Self { edits }
+    }
+}
+
+/// The chunks of each transaction applied this frame, i.e. each [`ChunkEditBatch`] and placed
+/// structure, remeshed together in the render world.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct AppliedEditBatches(pub Vec<Vec<Entity>>);
+
+impl AppliedEditBatches {
+    /// Clears the batches once they have been extracted.
+    pub fn clear_extracted(mut applied: ResMut<Self>) {
+        applied.0.clear();
+    }
+
+    /// Groups the chunks of each batch in the [`DirtyMeshes`], so that none of them is remeshed
+    /// before the others.
+    pub fn extract(mut dirty_meshes: ResMut<DirtyMeshes>, applied: Extract<Res<Self>>) {
+        for batch in &applied.0 {
+            dirty_meshes.group(batch.iter().copied());
+        }
+    }
+}
+
+impl ChunkEdit {
+    /// Applies the requested [`ChunkEdit`]s, then the [`ChunkEditBatch`]es whose chunks all exist,
+    /// reporting each applied edit as [`ChunkEdited`]. Voxels in the [`EditLocks`] are left as is.
+    #[allow(clippy::too_many_arguments)]
+    pub fn apply(
+        mut edits: EventReader<ChunkEdit>,
+        mut batches: EventReader<ChunkEditBatch>,
+        mut edited: EventWriter<ChunkEdited>,
+        mut applied: ResMut<AppliedEditBatches>,
+        locks: Res<EditLocks>,
+        config: Res<VoxelWorldConfig>,
+        mut voxel_material_query: Query<(
+            &mut VoxelMaterial,
+            Option<&mut DirtyRegion>,
+            Option<&ChunkCoord>,
+        )>,
+    ) {
+        let batches = batches
+            .read()
+            .filter(|batch| {
+                let missing = batch
+                    .edits
+                    .iter()
+                    .find(|edit| !voxel_material_query.contains(edit.entity));
+                if let Some(missing) = missing {
+                    warn!(
+                        "Dropping an edit batch of {} chunks: {:?} has no voxels",
+                        batch.edits.len(),
+                        missing.entity
+                    );
+                    return false;
+                }
+                applied
+                    .0
+                    .push(batch.edits.iter().map(|edit| edit.entity).collect());
+                true
+            })
+            .collect::<Vec<_>>();
+
+        let mut apply = |ChunkEdit { entity, edit }: &ChunkEdit| {
+            let Ok((mut voxel_material, dirty_region, coord)) =
+                voxel_material_query.get_mut(*entity)
+            else {
+                return;
+            };
+
+            let chunk = coord.copied().unwrap_or_default();
+            let is_locked = |pos| locks.is_voxel_locked(&config, chunk, pos);
+            let (min, max) = edit.bounds();
+            let materials_touched = match dirty_region {
+                Some(mut dirty_region) => {
+                    dirty_region.include(min, max);
+                    edit.apply_to_voxels(voxel_material.voxels_mut_recorded(), is_locked)
+                }
+                None => edit.apply_unlocked(&mut voxel_material, is_locked),
+            };
+            edited.send(ChunkEdited {
+                entity: *entity,
+                coord: chunk,
+                edit: *edit,
+                aabb: ChunkEdited::cells_aabb(&config, chunk, min, max),
+                materials_touched,
+            });
+        };
+
+        let batch_edits = batches.iter().flat_map(|batch| &batch.edits);
+        for edit in edits.read().chain(batch_edits) {
+            apply(edit);
+        }
+    }
+}
@@ -0,0 +1,307 @@
+use bevy::prelude::*;
+
+use crate::{CHUNK_SZ, CHUNK_SZ_3};
+
+use super::{
+    iso_surface::DEFAULT_ISO_LEVEL,
+    voxel::Voxel,
+    voxel_material::VoxelMaterial,
+    voxel_world::{voxel_index, DirtyRegion},
+};
+
+#[cfg(feature = "editing")]
+use super::{chunk_coord::ChunkCoord, voxel_edit::VoxelEdit, voxel_world::VoxelWorldConfig};
+#[cfg(feature = "editing")]
+use crate::edit_locks::EditLocks;
+
+/// Identifies a [`VoxelLayer`] of the [`VoxelLayers`] of a chunk.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct VoxelLayerId(pub u32);
+
+impl VoxelLayerId {
+    /// The procedural terrain the other layers are composited over.
+    pub const TERRAIN: Self = Self(0);
+    /// Voxels built and dug by players.
+    pub const BUILT: Self = Self(1);
+    /// Destructible props, e.g. boulders and ruins.
+    pub const PROPS: Self = Self(2);
+}
+
+/// How the voxels of a [`VoxelLayer`] are combined with the composite of the layers of lower
+/// precedence. Densities are expected between `0.0` and `1.0`, as written by
+/// [`Csg`](crate::core::csg::Csg).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LayerBlend {
+    /// Keeps the denser of the two voxels, taking the flags and emissive color of the layer where
+    /// it is solid.
+    #[default]
+    Union,
+    /// Removes the voxels of the layer, the density below being at most one minus theirs, e.g.
+    /// holes dug into the terrain.
+    Subtract,
+}
+
+/// A full chunk of voxels composited with the other layers of its [`VoxelLayers`].
+#[derive(Clone)]
+pub struct VoxelLayer {
+    id: VoxelLayerId,
+    precedence: i32,
+    blend: LayerBlend,
+    visible: bool,
+    voxels: Vec<Voxel>,
+}
+
+impl VoxelLayer {
+    pub fn id(&self) -> VoxelLayerId {
+        self.id
+    }
+
+    /// Layers of higher precedence are composited over those of lower precedence.
+    pub fn precedence(&self) -> i32 {
+        self.precedence
+    }
+
+    pub fn blend(&self) -> LayerBlend {
+        self.blend
+    }
+
+    /// Whether the layer takes part in the composite.
+    pub fn visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn voxels(&self) -> &[Voxel] {
+        &self.voxels
+    }
+}
+
+/// Overlapping layers of voxels of a chunk, e.g. the procedural terrain, what players built and
+/// destructible props, composited by ascending precedence into its [`VoxelMaterial`] before it is
+/// meshed. Each layer keeps its own voxels, so that editing or clearing one never loses the
+/// voxels of another: digging through a built wall leaves the terrain behind it intact.
+///
+/// The lowest layer is taken as is and the others are combined over it with their
+/// [`LayerBlend`]. Only the voxels written since the last composite are composited again, and
+/// recorded in the [`DirtyRegion`] of the chunk so that only they are uploaded.
+#[derive(Component, Clone)]
+pub struct VoxelLayers {
+    layers: Vec<VoxelLayer>,
+    /// Density from which the voxels of a [`LayerBlend::Union`] layer are solid and give their
+    /// flags and emissive color to the composite.
+    pub iso_level: f32,
+    dirty: DirtyRegion,
+}
+
+impl VoxelLayers {
+    /// Layers holding only the [`VoxelLayerId::TERRAIN`] layer, at precedence zero, with the
+    /// voxels of `terrain`.
+    pub fn new(terrain: Vec<Voxel>) -> Self {
+        let mut layers = Self {
+            layers: Vec::new(),
+            iso_level: DEFAULT_ISO_LEVEL,
+            dirty: DirtyRegion::default(),
+        };
+        layers.insert(VoxelLayerId::TERRAIN, 0, LayerBlend::Union, terrain);
+        layers
+    }
+
+    /// Adds an empty layer, see [`VoxelLayers::insert`].
+    pub fn with_layer(mut self, id: VoxelLayerId, precedence: i32, blend: LayerBlend) -> Self {
+        self.insert(id, precedence, blend, vec![Voxel::new(0, 0.0); CHUNK_SZ_3]);
+        self
+    }
+
+    /// Adds the layer `id` with `voxels`, or replaces it if it exists, e.g. to regenerate the
+    /// terrain. Layers of equal precedence are composited in the order they were added.
+    pub fn insert(
+        &mut self,
+        id: VoxelLayerId,
+        precedence: i32,
+        blend: LayerBlend,
+        mut voxels: Vec<Voxel>,
+    ) {
+        voxels.resize(CHUNK_SZ_3, Voxel::new(0, 0.0));
+        self.layers.retain(|layer| layer.id != id);
+        let index = self
+            .layers
+            .partition_point(|layer| layer.precedence <= precedence);
+        self.layers.insert(
+            index,
+            VoxelLayer {
+                id,
+                precedence,
+                blend,
+                visible: true,
+                voxels,
+            },
+        );
+        self.mark_all_dirty();
+    }
+
+    /// Removes the layer `id`, returning it if it existed.
+    pub fn remove(&mut self, id: VoxelLayerId) -> Option<VoxelLayer> {
+        let index = self.layers.iter().position(|layer| layer.id == id)?;
+        self.mark_all_dirty();
+        Some(self.layers.remove(index))
+    }
+
+    /// The layers by ascending precedence.
+    pub fn layers(&self) -> &[VoxelLayer] {
+        &self.layers
+    }
+
+    pub fn layer(&self, id: VoxelLayerId) -> Option<&VoxelLayer> {
+        self.layers.iter().find(|layer| layer.id == id)
+    }
+
+    /// Shows or hides the layer `id` in the composite, keeping its voxels.
+    pub fn set_visible(&mut self, id: VoxelLayerId, visible: bool) {
+        let Some(layer) = self.layers.iter_mut().find(|layer| layer.id == id) else {
+            return;
+        };
+        if layer.visible != visible {
+            layer.visible = visible;
+            self.mark_all_dirty();
+        }
+    }
+
+    /// Replaces the voxel of the layer `id` at `pos`, returning whether both exist.
+    pub fn set(&mut self, id: VoxelLayerId, pos: UVec3, voxel: Voxel) -> bool {
+        self.edit(id, pos, pos + UVec3::ONE, |_, target| *target = voxel)
+    }
+
+    /// Calls `edit` on each voxel of the layer `id` from `min` up to, but excluding, `max`, with
+    /// its position in the chunk. Returns whether the layer exists.
+    pub fn edit(
+        &mut self,
+        id: VoxelLayerId,
+        min: UVec3,
+        max: UVec3,
+        mut edit: impl FnMut(UVec3, &mut Voxel),
+    ) -> bool {
+        let Some(layer) = self.layers.iter_mut().find(|layer| layer.id == id) else {
+            return false;
+        };
+        let max = max.min(UVec3::splat(CHUNK_SZ as u32));
+        for z in min.z..max.z {
+            for y in min.y..max.y {
+                for x in min.x..max.x {
+                    let pos = UVec3::new(x, y, z);
+                    edit(pos, &mut layer.voxels[voxel_index(pos)]);
+                }
+            }
+        }
+        self.dirty.include(min, max);
+        true
+    }
+
+    /// The voxel at `pos` of the composite of the visible layers.
+    pub fn composite(&self, pos: UVec3) -> Voxel {
+        let index = voxel_index(pos);
+        let mut layers = self.layers.iter().filter(|layer| layer.visible);
+        let Some(lowest) = layers.next() else {
+            return Voxel::new(0, 0.0);
+        };
+        layers.fold(lowest.voxels[index], |below, layer| {
+            let voxel = layer.voxels[index];
+            match layer.blend {
+                LayerBlend::Union => {
+                    let top = match voxel.density() >= self.iso_level {
+                        true => voxel,
+                        false => below,
+                    };
+                    top.with_density(below.density().max(voxel.density()))
+                }
+                LayerBlend::Subtract => {
+                    below.with_density(below.density().min(1.0 - voxel.density()))
+                }
+            }
+        })
+    }
+
+    fn mark_all_dirty(&mut self) {
+        self.dirty
+            .include(UVec3::ZERO, UVec3::splat(CHUNK_SZ as u32));
+    }
+
+    /// Composites the voxels of the layers written since the last composite into the
+    /// [`VoxelMaterial`] of their chunks.
+    pub fn composite_changed(
+        mut layers_query: Query<
+            (
+                &mut VoxelLayers,
+                &mut VoxelMaterial,
+                Option<&mut DirtyRegion>,
+            ),
+            Changed<VoxelLayers>,
+        >,
+    ) {
+        for (mut layers, mut voxel_material, dirty_region) in layers_query.iter_mut() {
+            let Some((min, max)) = layers.dirty.bounds() else {
+                continue;
+            };
+            layers.bypass_change_detection().dirty = DirtyRegion::default();
+
+            let voxels = voxel_material.voxels_mut_recorded();
+            voxels.resize(CHUNK_SZ_3, Voxel::new(0, 0.0));
+            for z in min.z..max.z {
+                for y in min.y..max.y {
+                    for x in min.x..max.x {
+                        let pos = UVec3::new(x, y, z);
+                        voxels[voxel_index(pos)] = layers.composite(pos);
+                    }
+                }
+            }
+            if let Some(mut dirty_region) = dirty_region {
+                dirty_region.include(min, max);
+            }
+        }
+    }
+}
+
+/// Requests applying a [`VoxelEdit`] to a single layer of the [`VoxelLayers`] of a volumetric
+/// entity, leaving its other layers untouched. Voxels in the [`EditLocks`] are left as is.
+#[cfg(feature = "editing")]
+#[derive(Event, Clone, Copy)]
+pub struct LayerEdit {
+    pub entity: Entity,
+    pub layer: VoxelLayerId,
+    pub edit: VoxelEdit,
+}
+
+#[cfg(feature = "editing")]
+impl LayerEdit {
+    /// Applies the requested [`LayerEdit`]s, which are composited by
+    /// [`VoxelLayers::composite_changed`].
+    pub fn apply(
+        mut edits: EventReader<LayerEdit>,
+        locks: Res<EditLocks>,
+        config: Res<VoxelWorldConfig>,
+        mut layers_query: Query<(&mut VoxelLayers, Option<&ChunkCoord>)>,
+    ) {
+        for LayerEdit {
+            entity,
+            layer,
+            edit,
+        } in edits.read()
+        {
+            let Ok((mut layers, coord)) = layers_query.get_mut(*entity) else {
+                continue;
+            };
+            let Some(target) = layers.layers.iter_mut().find(|target| target.id == *layer) else {
+                warn!(
+                    "Dropping an edit of {:?}: it has no layer {:?}",
+                    entity, layer
+                );
+                continue;
+            };
+
+            let chunk = coord.copied().unwrap_or_default();
+            edit.apply_to_voxels(&mut target.voxels, |pos| {
+                locks.is_voxel_locked(&config, chunk, pos)
+            });
+            let (min, max) = edit.bounds();
+            layers.dirty.include(min, max);
+        }
+    }
+}
@@ -2,15 +2,67 @@ use bevy::{prelude::*, utils::HashMap};
 
 use crate::{bundles::volumetric_bundle::VolumetricBundle, CHUNK_SZ, CHUNK_SZ_2, CHUNK_SZ_3};
 
-use super::voxel::Voxel;
+use super::{
+    voxel::Voxel,
+    voxel_transform::{VoxelAxis, VoxelGrid},
+};
 
 #[derive(Component)]
 pub struct VoxelMaterial {
-    pub voxels: Vec<Voxel>,
+    voxels: Vec<Voxel>,
     pub chunk_size: u32,
+    /// Whether the voxels were created or written since they were last extracted without
+    /// recording the written voxels in the [`DirtyRegion`](super::voxel_world::DirtyRegion) of the
+    /// chunk.
+    unrecorded_writes: bool,
 }
 
 impl VoxelMaterial {
+    pub fn new(voxels: Vec<Voxel>, chunk_size: u32) -> Self {
+        Self {
+            voxels,
+            chunk_size,
+            unrecorded_writes: true,
+        }
+    }
+
+    pub fn voxels(&self) -> &[Voxel] {
+        &self.voxels
+    }
+
+    pub fn into_voxels(self) -> Vec<Voxel> {
+        self.voxels
+    }
+
+    /// Write access to the voxels, which are then uploaded in full. Write through a
+    /// [`ChunkGuard`](super::voxel_world::ChunkGuard) to upload only the written voxels.
+    pub fn voxels_mut(&mut self) -> &mut Vec<Voxel> {
+        self.unrecorded_writes = true;
+        &mut self.voxels
+    }
+
+    /// Write access to the voxels for writers that include every written voxel in the
+    /// [`DirtyRegion`](super::voxel_world::DirtyRegion) of the chunk.
+    pub(crate) fn voxels_mut_recorded(&mut self) -> &mut Vec<Voxel> {
+        &mut self.voxels
+    }
+
+    /// Whether the voxels were created or written outside of the
+    /// [`DirtyRegion`](super::voxel_world::DirtyRegion) of the chunk since they were last
+    /// extracted, so that they must be uploaded in full.
+    pub fn has_unrecorded_writes(&self) -> bool {
+        self.unrecorded_writes
+    }
+
+    /// Forgets the unrecorded writes once the voxels have been extracted.
+    pub fn clear_unrecorded_writes(mut voxel_material_query: Query<&mut VoxelMaterial>) {
+        for mut voxel_material in voxel_material_query.iter_mut() {
+            if voxel_material.unrecorded_writes {
+                voxel_material.bypass_change_detection().unrecorded_writes = false;
+            }
+        }
+    }
+
     pub fn generate_random(mut commands: Commands) {
         let mut voxels = Vec::from_iter(0..CHUNK_SZ_3)
             .iter()
@@ -34,10 +86,30 @@ impl VoxelMaterial {
             }
         }
 
-        commands.spawn(VolumetricBundle::new(VoxelMaterial {
-            chunk_size: CHUNK_SZ_3 as u32,
+        commands.spawn(VolumetricBundle::new(VoxelMaterial::new(
             voxels,
-        }));
+            CHUNK_SZ_3 as u32,
+        )));
+    }
+
+    fn grid() -> VoxelGrid {
+        VoxelGrid::new(UVec3::splat(CHUNK_SZ as u32))
+    }
+
+    /// Rotates the chunk by `turns` counterclockwise quarter turns around `axis`.
+    pub fn rotate_90(&mut self, axis: VoxelAxis, turns: u32) {
+        let rotated = Self::grid().rotate_90(&self.voxels, axis, turns);
+        *self.voxels_mut() = rotated;
+    }
+
+    /// Mirrors the chunk across its middle along `axis`.
+    pub fn mirror_axis(&mut self, axis: VoxelAxis) {
+        Self::grid().mirror_axis(self.voxels_mut(), axis);
+    }
+
+    /// Moves the voxels of the chunk by `offset`, wrapping them around its faces.
+    pub fn translate_wrap(&mut self, offset: IVec3) {
+        Self::grid().translate_wrap(self.voxels_mut(), offset);
     }
 }
 
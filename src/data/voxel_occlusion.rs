@@ -0,0 +1,103 @@
+use bevy::{
+    prelude::*,
+    tasks::{ComputeTaskPool, ParallelSlice, TaskPool},
+    utils::HashMap,
+};
+
+use crate::CHUNK_SZ;
+
+use super::{
+    chunk_coord::ChunkCoord,
+    voxel::Voxel,
+    voxel_world::{voxel_index, VoxelWorldConfig},
+};
+
+/// Segments handed to each task by [`VoxelOcclusion::occlusion_batch`].
+const BATCH_CHUNK_SIZE: usize = 16;
+
+/// How much of a sound is absorbed by the terrain between an emitter and a listener, from a CPU
+/// ray-march of the density of the loaded chunks. Unloaded chunks are treated as empty.
+///
+/// Built once per system run with [`VoxelWorld::occlusion`](super::voxel_world::VoxelWorld::occlusion),
+/// so that the chunks are looked up once for every query of the frame.
+pub struct VoxelOcclusion<'a> {
+    config: VoxelWorldConfig,
+    chunks: HashMap<ChunkCoord, &'a [Voxel]>,
+    /// Distance between samples along the segment, in voxels.
+    pub step: f32,
+    /// Fraction of the sound absorbed by every voxel of solid crossed, so that walls of a few
+    /// voxels muffle the sound without blocking it completely.
+    pub absorption: f32,
+}
+
+impl<'a> VoxelOcclusion<'a> {
+    pub fn new(
+        config: VoxelWorldConfig,
+        chunks: impl IntoIterator<Item = (ChunkCoord, &'a [Voxel])>,
+    ) -> Self {
+        Self {
+            config,
+            chunks: chunks.into_iter().collect(),
+            step: 0.5,
+            absorption: 0.3,
+        }
+    }
+
+    /// Density of the voxel at `voxel`, in world voxel coordinates, clamped to `0..=1`.
+    fn density(&self, voxel: IVec3) -> f32 {
+        let (coord, pos) = self.config.voxel_to_chunk(voxel);
+        if pos.cmpge(UVec3::splat(CHUNK_SZ as u32)).any() {
+            return 0.0;
+        }
+        self.chunks
+            .get(&coord)
+            .and_then(|voxels| voxels.get(voxel_index(pos)))
+            .map_or(0.0, |voxel| voxel.density().clamp(0.0, 1.0))
+    }
+
+    /// Voxels of solid crossed by the segment between the world positions `a` and `b`, the
+    /// density integrated along it.
+    pub fn thickness_between(&self, a: Vec3, b: Vec3) -> f32 {
+        let a = self.config.world_to_voxel_space(a);
+        let b = self.config.world_to_voxel_space(b);
+        let length = a.distance(b);
+        if length == 0.0 {
+            return 0.0;
+        }
+
+        // Midpoint samples, each standing for an equal part of the segment.
+        let steps = (length / self.step.max(1e-3)).ceil().max(1.0) as u32;
+        let step_length = length / steps as f32;
+        (0..steps)
+            .map(|step| {
+                let t = (step as f32 + 0.5) / steps as f32;
+                self.density(a.lerp(b, t).floor().as_ivec3())
+            })
+            .sum::<f32>()
+            * step_length
+    }
+
+    /// Occlusion of a sound travelling from the world position `a` to `b`, from 0 when nothing
+    /// is in the way to 1 when it is fully blocked, to scale its volume by `1.0 - occlusion`.
+    pub fn occlusion_between(&self, a: Vec3, b: Vec3) -> f32 {
+        let transmitted =
+            (1.0 - self.absorption.clamp(0.0, 1.0)).powf(self.thickness_between(a, b));
+        1.0 - transmitted
+    }
+
+    /// [`VoxelOcclusion::occlusion_between`] for each pair of world positions, e.g. every emitter
+    /// and the listener, split across the [`ComputeTaskPool`].
+    pub fn occlusion_batch(&self, segments: &[(Vec3, Vec3)]) -> Vec<f32> {
+        let task_pool = ComputeTaskPool::get_or_init(TaskPool::default);
+        segments
+            .par_chunk_map(task_pool, BATCH_CHUNK_SIZE, |_, segments| {
+                segments
+                    .iter()
+                    .map(|(a, b)| self.occlusion_between(*a, *b))
+                    .collect::<Vec<_>>()
+            })
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+}
@@ -0,0 +1,145 @@
+use std::{collections::VecDeque, sync::atomic::AtomicU32};
+
+use bevy::prelude::*;
+use bytemuck::{Pod, Zeroable};
+
+use crate::render::shaders::shader_struct;
+
+/// Casts to keep the ray of while their hits are read back, the oldest being dropped.
+const MAX_CASTS_IN_FLIGHT: usize = 8;
+
+/// Picks the triangles generated on the GPU with a ray: each [`cast`](Self::cast) is intersected
+/// with the vertex and index buffers of every meshed volumetric entity by a compute pass, and the
+/// nearest [`VoxelPickHit`] is read back a frame or so later. Works without any mesh on the CPU,
+/// e.g. for chunks only drawn from their GPU buffers.
+#[derive(Resource, Default)]
+pub struct VoxelPicking {
+    request: u32,
+    /// The rays of the casts whose hits may still be received, oldest first.
+    in_flight: VecDeque<(u32, Ray3d)>,
+    /// The latest cast hits were received for.
+    answered: u32,
+    hit: Option<VoxelPickHit>,
+}
+
+/// The nearest triangle hit by a cast of the [`VoxelPicking`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VoxelPickHit {
+    pub entity: Entity,
+    /// Where the indices of the triangle start in the index buffer of the entity.
+    pub first_index: u32,
+    /// Where the ray hits the triangle, in world space.
+    pub position: Vec3,
+    pub distance: f32,
+}
+
+impl VoxelPicking {
+    /// Casts `ray`, in world space, against the meshes of the next frame.
+    pub fn cast(&mut self, ray: Ray3d) {
+        self.request = self.request.wrapping_add(1);
+        self.in_flight.push_back((self.request, ray));
+        if self.in_flight.len() > MAX_CASTS_IN_FLIGHT {
+            self.in_flight.pop_front();
+        }
+    }
+
+    /// The id of the latest cast.
+    pub fn request(&self) -> u32 {
+        self.request
+    }
+
+    /// The ray of the latest cast.
+    pub fn ray(&self) -> Option<Ray3d> {
+        self.in_flight.back().map(|(_, ray)| *ray)
+    }
+
+    /// The nearest hit of the latest cast whose hits were received, `None` if it missed.
+    pub fn hit(&self) -> Option<&VoxelPickHit> {
+        self.hit.as_ref()
+    }
+
+    /// Keeps the hit read back for `entity` if it is nearer than those of the other entities for
+    /// the same cast. Hits of a newer cast replace those of older ones.
+    pub fn apply(&mut self, entity: Entity, data: &VoxelPickData) {
+        let Some(&(request, ray)) = self
+            .in_flight
+            .iter()
+            .find(|(request, _)| *request == data.request)
+        else {
+            return; // too old, or not a cast of this world
+        };
+
+        if request != self.answered {
+            if request.wrapping_sub(self.answered) > u32::MAX / 2 {
+                return; // older than the hits already received
+            }
+            self.answered = request;
+            self.hit = None;
+            self.in_flight
+                .retain(|(in_flight, _)| in_flight.wrapping_sub(request) <= u32::MAX / 2);
+        }
+
+        let Some(distance) = data.distance() else {
+            return;
+        };
+        if self.hit.is_some_and(|hit| hit.distance <= distance) {
+            return;
+        }
+        self.hit = Some(VoxelPickHit {
+            entity,
+            first_index: data.first_index,
+            position: ray.origin + *ray.direction * distance,
+            distance,
+        });
+    }
+}
+
+shader_struct! {
+    /// A cast of the [`VoxelPicking`] in the local space of an entity. The direction keeps the
+    /// scale of the entity, so that distances along it are the same as in world space.
+    #[derive(Clone, Copy, Default)]
+    pub struct VoxelPickParams {
+        pub origin: Vec3,
+        /// Whether the index buffer is split into one segment per material.
+        pub material_split: u32,
+        pub direction: Vec3,
+        /// Triangles the index buffer has room for.
+        pub triangles: u32,
+    }
+}
+
+shader_struct! {
+    /// The nearest hit of a cast, laid out as a [`VoxelPickData`]. The distance is stored as the
+    /// bits of a positive float, which order as `u32`s.
+    pub struct VoxelPickResult {
+        request: u32,
+        distance_key: AtomicU32,
+        first_index: AtomicU32,
+    }
+}
+
+/// The [`VoxelPickResult`] read back from the GPU.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Pod, Zeroable)]
+pub struct VoxelPickData {
+    pub request: u32,
+    pub distance_key: u32,
+    pub first_index: u32,
+}
+
+impl VoxelPickData {
+    /// The result of `request` before any triangle is hit.
+    pub fn miss(request: u32) -> Self {
+        Self {
+            request,
+            distance_key: u32::MAX,
+            first_index: u32::MAX,
+        }
+    }
+
+    /// The distance along the ray to the hit, if a triangle was hit.
+    pub fn distance(&self) -> Option<f32> {
+        (self.distance_key != u32::MAX && self.first_index != u32::MAX)
+            .then(|| f32::from_bits(self.distance_key))
+    }
+}
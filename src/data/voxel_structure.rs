@@ -0,0 +1,161 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    chunk_coord::ChunkCoord,
+    voxel::Voxel,
+    voxel_edit::AppliedEditBatches,
+    voxel_transform::{VoxelAxis, VoxelGrid},
+    voxel_world::{VoxelWorld, VoxelWorldConfig},
+};
+
+/// How the voxels of a [`VoxelStructure`] are combined with the voxels they are placed over.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StructureBlend {
+    /// Overwrites the voxels of the world.
+    #[default]
+    Replace,
+    /// Keeps the denser of the two voxels, e.g. to add a rock without carving the terrain.
+    Max,
+    /// Keeps the less dense of the two voxels, e.g. to carve a cave.
+    Min,
+}
+
+impl StructureBlend {
+    pub fn blend(self, current: Voxel, structure: Voxel) -> Voxel {
+        match self {
+            StructureBlend::Replace => structure,
+            StructureBlend::Max if structure.density() > current.density() => structure,
+            StructureBlend::Min if structure.density() < current.density() => structure,
+            StructureBlend::Max | StructureBlend::Min => current,
+        }
+    }
+}
+
+/// Where and how a [`VoxelStructure`] is placed in the world.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StructurePlacement {
+    /// World voxel the anchor of the structure is placed at.
+    pub position: IVec3,
+    /// Axis of the counterclockwise quarter turns applied to the structure before placing it.
+    pub axis: VoxelAxis,
+    pub turns: u32,
+    pub blend: StructureBlend,
+}
+
+/// A small grid of voxels, e.g. a tree or a house, placed into the chunks of the world.
+#[derive(Asset, Clone, TypePath)]
+pub struct VoxelStructure {
+    pub size: UVec3,
+    /// Laid out x first, then y, then z.
+    pub voxels: Vec<Voxel>,
+    /// Voxel of the structure placed at [`StructurePlacement::position`].
+    pub anchor: UVec3,
+}
+
+impl VoxelStructure {
+    /// Writes the structure into every chunk of `voxel_world` it overlaps, recording the written
+    /// voxels in their dirty regions so that they are remeshed. Returns the touched chunks; parts of
+    /// the structure over chunks that are not spawned are dropped.
+    pub fn place(
+        &self,
+        voxel_world: &mut VoxelWorld,
+        placement: StructurePlacement,
+    ) -> Vec<ChunkCoord> {
+        let grid = VoxelGrid::new(self.size);
+        if grid.is_empty() || self.voxels.len() < grid.len() {
+            return Vec::new();
+        }
+
+        let (axis, turns) = (placement.axis, placement.turns);
+        let voxels = grid.rotate_90(&self.voxels, axis, turns);
+        let rotated = VoxelGrid::new(grid.rotated_size(axis, turns));
+        let anchor = grid.rotate_position(self.anchor, axis, turns);
+
+        let min = placement.position - anchor.as_ivec3();
+        let max = min + rotated.size.as_ivec3();
+        let config = *voxel_world.config();
+        let chunk_size = IVec3::splat(VoxelWorldConfig::CHUNK_SIZE as i32);
+        let (ChunkCoord(min_chunk), _) = config.voxel_to_chunk(min);
+        let (ChunkCoord(max_chunk), _) = config.voxel_to_chunk(max - 1);
+
+        let mut touched = Vec::new();
+        for chunk_z in min_chunk.z..=max_chunk.z {
+            for chunk_y in min_chunk.y..=max_chunk.y {
+                for chunk_x in min_chunk.x..=max_chunk.x {
+                    let coord = ChunkCoord(IVec3::new(chunk_x, chunk_y, chunk_z));
+                    let Some(mut chunk) = voxel_world.chunk_mut(coord) else {
+                        continue;
+                    };
+
+                    let origin = config.chunk_to_voxel(coord);
+                    let (lo, hi) = (min.max(origin), max.min(origin + chunk_size));
+                    for z in lo.z..hi.z {
+                        for y in lo.y..hi.y {
+                            for x in lo.x..hi.x {
+                                let pos = IVec3::new(x, y, z);
+                                let local = (pos - origin).as_uvec3();
+                                let Some(current) = chunk.get(local) else {
+                                    continue;
+                                };
+                                let voxel = voxels[rotated.index((pos - min).as_uvec3())];
+                                chunk.set(local, placement.blend.blend(current, voxel));
+                            }
+                        }
+                    }
+                    touched.push(coord);
+                }
+            }
+        }
+        touched
+    }
+}
+
+/// Requests placing a [`VoxelStructure`], once its asset is loaded.
+#[derive(Event, Clone, Debug)]
+pub struct PlaceStructure {
+    pub structure: Handle<VoxelStructure>,
+    pub placement: StructurePlacement,
+}
+
+/// A [`PlaceStructure`] that has been applied, with the chunks it touched.
+#[derive(Event, Clone, Debug)]
+pub struct StructurePlaced {
+    pub structure: AssetId<VoxelStructure>,
+    pub placement: StructurePlacement,
+    pub chunks: Vec<ChunkCoord>,
+}
+
+impl PlaceStructure {
+    /// Places the requested structures, keeping the requests whose structure is not loaded yet, and
+    /// reports each placed one as [`StructurePlaced`]. The chunks of a structure are remeshed
+    /// together, like those of a [`ChunkEditBatch`](super::voxel_edit::ChunkEditBatch).
+    pub fn apply(
+        mut requests: EventReader<PlaceStructure>,
+        mut pending: Local<Vec<PlaceStructure>>,
+        mut placed: EventWriter<StructurePlaced>,
+        mut applied: ResMut<AppliedEditBatches>,
+        structures: Res<Assets<VoxelStructure>>,
+        mut voxel_world: VoxelWorld,
+    ) {
+        pending.extend(requests.read().cloned());
+        pending.retain(|request| {
+            let Some(structure) = structures.get(&request.structure) else {
+                return true;
+            };
+            let chunks = structure.place(&mut voxel_world, request.placement);
+            applied.0.push(
+                chunks
+                    .iter()
+                    .filter_map(|coord| voxel_world.entity(*coord))
+                    .collect(),
+            );
+            placed.send(StructurePlaced {
+                structure: request.structure.id(),
+                placement: request.placement,
+                chunks,
+            });
+            false
+        });
+    }
+}
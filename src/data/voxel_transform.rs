@@ -0,0 +1 @@
+pub use crate::core::grid::{VoxelAxis, VoxelGrid};
@@ -0,0 +1,371 @@
+use std::ops::Range;
+
+use bevy::{
+    ecs::{component::ComponentId, system::SystemParam, world::DeferredWorld},
+    prelude::*,
+    utils::HashMap,
+};
+
+use crate::{CHUNK_SZ, CHUNK_SZ_3};
+
+use super::{chunk_coord::ChunkCoord, voxel::Voxel, voxel_material::VoxelMaterial};
+
+#[cfg(feature = "raymarch")]
+use super::voxel_occlusion::VoxelOcclusion;
+
+pub use crate::core::voxel_index;
+
+/// How voxels and chunks are laid out in world space. Voxel coordinates count voxels from the
+/// first voxel of the chunk at the origin, so that the voxel at [`IVec3::ZERO`] is placed at
+/// `origin` and each voxel is `voxel_size` apart. The conversions are used everywhere chunks are
+/// spawned or edited from world positions.
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct VoxelWorldConfig {
+    /// World space size of a voxel, the scale of every chunk.
+    pub voxel_size: f32,
+    /// World space position of the first voxel of the chunk at [`ChunkCoord`] zero.
+    pub origin: Vec3,
+}
+
+impl Default for VoxelWorldConfig {
+    fn default() -> Self {
+        Self {
+            voxel_size: 1.0,
+            origin: Vec3::ZERO,
+        }
+    }
+}
+
+impl VoxelWorldConfig {
+    /// Voxels along each axis of a chunk, the size [`voxel_index`] and the shaders lay chunks out
+    /// with.
+    pub const CHUNK_SIZE: u32 = CHUNK_SZ as u32;
+
+    /// World space size of a chunk.
+    pub fn chunk_extent(&self) -> f32 {
+        Self::CHUNK_SIZE as f32 * self.voxel_size
+    }
+
+    /// The voxel whose cell contains the world position `world`.
+    pub fn world_to_voxel(&self, world: Vec3) -> IVec3 {
+        self.world_to_voxel_space(world).floor().as_ivec3()
+    }
+
+    /// The world position `world` in voxel coordinates, keeping its fractional part.
+    pub fn world_to_voxel_space(&self, world: Vec3) -> Vec3 {
+        (world - self.origin) / self.voxel_size
+    }
+
+    /// World space position of the voxel at `voxel`.
+    pub fn voxel_to_world(&self, voxel: IVec3) -> Vec3 {
+        self.origin + voxel.as_vec3() * self.voxel_size
+    }
+
+    /// The chunk whose voxels include the world position `world`.
+    pub fn world_to_chunk(&self, world: Vec3) -> ChunkCoord {
+        self.voxel_to_chunk(self.world_to_voxel(world)).0
+    }
+
+    /// The chunk holding the voxel at `voxel`, along with its position in the chunk.
+    pub fn voxel_to_chunk(&self, voxel: IVec3) -> (ChunkCoord, UVec3) {
+        let chunk_size = IVec3::splat(Self::CHUNK_SIZE as i32);
+        (
+            ChunkCoord(voxel.div_euclid(chunk_size)),
+            voxel.rem_euclid(chunk_size).as_uvec3(),
+        )
+    }
+
+    /// The first voxel of the chunk at `coord`.
+    pub fn chunk_to_voxel(&self, coord: ChunkCoord) -> IVec3 {
+        coord.0 * Self::CHUNK_SIZE as i32
+    }
+
+    /// The world position `world` in the voxel coordinates of the chunk at `coord`, the local
+    /// space of its meshes and of [`VoxelCollision`](super::voxel_collision::VoxelCollision).
+    pub fn world_to_local(&self, coord: ChunkCoord, world: Vec3) -> Vec3 {
+        self.world_to_voxel_space(world) - self.chunk_to_voxel(coord).as_vec3()
+    }
+
+    /// The transform placing the chunk at `coord`, whose meshes are in voxel coordinates.
+    pub fn chunk_transform(&self, coord: ChunkCoord) -> Transform {
+        Transform::from_translation(self.voxel_to_world(self.chunk_to_voxel(coord)))
+            .with_scale(Vec3::splat(self.voxel_size))
+    }
+}
+
+/// The box of voxels of a chunk written since they were last extracted, so that only that part of
+/// the chunk is uploaded. Chunks whose [`VoxelMaterial`] changed without a dirty region, or whose
+/// voxels were written through [`VoxelMaterial::voxels_mut`], are uploaded in full.
+#[derive(Clone, Copy, Component, Debug, Default)]
+pub struct DirtyRegion {
+    bounds: Option<(UVec3, UVec3)>,
+}
+
+impl DirtyRegion {
+    /// The first dirty voxel and the voxel past the last one, if any voxel is dirty.
+    pub fn bounds(&self) -> Option<(UVec3, UVec3)> {
+        self.bounds
+    }
+
+    /// Grows the region to include the voxels from `min` up to, but excluding, `max`.
+    pub fn include(&mut self, min: UVec3, max: UVec3) {
+        if min.cmpge(max).any() {
+            return;
+        }
+        self.bounds = Some(match self.bounds {
+            Some((dirty_min, dirty_max)) => (dirty_min.min(min), dirty_max.max(max)),
+            None => (min, max),
+        });
+    }
+
+    /// The range of the voxels buffer holding every dirty voxel.
+    pub fn flat_range(&self) -> Option<Range<usize>> {
+        self.bounds
+            .map(|(min, max)| voxel_index(min)..voxel_index(max - UVec3::ONE) + 1)
+    }
+
+    /// Clears the dirty regions once they have been extracted.
+    pub fn clear_extracted(mut dirty_region_query: Query<&mut DirtyRegion>) {
+        for mut dirty_region in dirty_region_query.iter_mut() {
+            if dirty_region.bounds.is_some() {
+                dirty_region.bounds = None;
+            }
+        }
+    }
+}
+
+/// The entity of every chunk by [`ChunkCoord`], kept up to date by component hooks so that
+/// chunks are looked up without going through all of them.
+#[derive(Resource, Debug, Default)]
+pub struct ChunkEntities(HashMap<ChunkCoord, Entity>);
+
+impl ChunkEntities {
+    /// The entity last given the [`ChunkCoord`] `coord`, if it still has it.
+    pub fn get(&self, coord: ChunkCoord) -> Option<Entity> {
+        self.0.get(&coord).copied()
+    }
+
+    /// Component hook adding an entity under its [`ChunkCoord`] whenever one is inserted.
+    pub(crate) fn on_insert(mut world: DeferredWorld, entity: Entity, _: ComponentId) {
+        let Some(coord) = world.get::<ChunkCoord>(entity).copied() else {
+            return;
+        };
+        if let Some(mut chunk_entities) = world.get_resource_mut::<ChunkEntities>() {
+            chunk_entities.0.insert(coord, entity);
+        }
+    }
+
+    /// Component hook removing an entity once its [`ChunkCoord`] is removed or it is despawned.
+    pub(crate) fn on_remove(mut world: DeferredWorld, entity: Entity, _: ComponentId) {
+        let Some(coord) = world.get::<ChunkCoord>(entity).copied() else {
+            return;
+        };
+        if let Some(mut chunk_entities) = world.get_resource_mut::<ChunkEntities>() {
+            if chunk_entities.0.get(&coord) == Some(&entity) {
+                chunk_entities.0.remove(&coord);
+            }
+        }
+    }
+}
+
+/// Access to the voxels of the chunks of the world by [`ChunkCoord`].
+#[derive(SystemParam)]
+pub struct VoxelWorld<'w, 's> {
+    config: Res<'w, VoxelWorldConfig>,
+    chunk_entities: Res<'w, ChunkEntities>,
+    chunks: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static ChunkCoord,
+            &'static mut VoxelMaterial,
+            &'static mut DirtyRegion,
+        ),
+    >,
+}
+
+impl<'w, 's> VoxelWorld<'w, 's> {
+    pub fn config(&self) -> &VoxelWorldConfig {
+        &self.config
+    }
+
+    pub fn chunk(&self, coord: ChunkCoord) -> Option<&VoxelMaterial> {
+        let (_, _, voxel_material, _) = self.chunks.get(self.entity(coord)?).ok()?;
+        Some(voxel_material)
+    }
+
+    /// The entity of the chunk at `coord`.
+    pub fn entity(&self, coord: ChunkCoord) -> Option<Entity> {
+        // Entries of entities whose coordinate was replaced are left behind.
+        let entity = self.chunk_entities.get(coord)?;
+        let (_, chunk_coord, ..) = self.chunks.get(entity).ok()?;
+        (*chunk_coord == coord).then_some(entity)
+    }
+
+    /// The density of the voxel at `voxel`, if its chunk is loaded.
+    pub fn density(&self, voxel: IVec3) -> Option<f32> {
+        let (coord, position) = self.config.voxel_to_chunk(voxel);
+        let voxel_material = self.chunk(coord)?;
+        voxel_material
+            .voxels()
+            .get(voxel_index(position))
+            .map(Voxel::density)
+    }
+
+    /// The gradient of the density field at the world position `world_pos`, per world unit,
+    /// pointing from empty space into solid voxels, so that the surface normal is its negated
+    /// direction. The central differences of the 8 voxels around `world_pos` are trilinearly
+    /// interpolated, reading across the borders of the chunks. Differences fall back to one sided
+    /// next to unloaded chunks, and the gradient is zero where no voxel around it is loaded.
+    pub fn gradient(&self, world_pos: Vec3) -> Vec3 {
+        let position = self.config.world_to_voxel_space(world_pos);
+        let base = position.floor().as_ivec3();
+        let fraction = position - base.as_vec3();
+
+        // Look every chunk the differences read from up once.
+        let (ChunkCoord(min_chunk), _) = self.config.voxel_to_chunk(base - IVec3::ONE);
+        let (ChunkCoord(max_chunk), _) = self.config.voxel_to_chunk(base + IVec3::splat(2));
+        let mut chunks = Vec::new();
+        for z in min_chunk.z..=max_chunk.z {
+            for y in min_chunk.y..=max_chunk.y {
+                for x in min_chunk.x..=max_chunk.x {
+                    let coord = ChunkCoord(IVec3::new(x, y, z));
+                    if let Some(voxel_material) = self.chunk(coord) {
+                        chunks.push((coord, voxel_material));
+                    }
+                }
+            }
+        }
+        let density = |voxel: IVec3| {
+            let (coord, position) = self.config.voxel_to_chunk(voxel);
+            let (_, voxel_material) = chunks
+                .iter()
+                .find(|(chunk_coord, _)| *chunk_coord == coord)?;
+            voxel_material
+                .voxels()
+                .get(voxel_index(position))
+                .map(Voxel::density)
+        };
+
+        let mut gradient = Vec3::ZERO;
+        let mut weights = 0.0;
+        for corner in 0..8 {
+            let offset = IVec3::new(corner & 1, (corner >> 1) & 1, corner >> 2);
+            let voxel = base + offset;
+            let Some(center) = density(voxel) else {
+                continue;
+            };
+
+            let mut difference = Vec3::ZERO;
+            for axis in 0..3 {
+                let step = IVec3::AXES[axis];
+                difference[axis] = match (density(voxel + step), density(voxel - step)) {
+                    (Some(next), Some(previous)) => (next - previous) * 0.5,
+                    (Some(next), None) => next - center,
+                    (None, Some(previous)) => center - previous,
+                    (None, None) => 0.0,
+                };
+            }
+
+            let weight = Vec3::select(offset.cmpeq(IVec3::ONE), fraction, Vec3::ONE - fraction)
+                .element_product();
+            gradient += difference * weight;
+            weights += weight;
+        }
+
+        if weights > 0.0 {
+            gradient / (weights * self.config.voxel_size)
+        } else {
+            Vec3::ZERO
+        }
+    }
+
+    /// The outward surface normal at the world position `world_pos`, the normalized negated
+    /// [`VoxelWorld::gradient`], if the density changes around it.
+    pub fn surface_normal(&self, world_pos: Vec3) -> Option<Vec3> {
+        (-self.gradient(world_pos)).try_normalize()
+    }
+
+    /// Occlusion queries through the voxels of every chunk.
+    #[cfg(feature = "raymarch")]
+    pub fn occlusion(&self) -> VoxelOcclusion<'_> {
+        VoxelOcclusion::new(
+            *self.config,
+            self.chunks
+                .iter()
+                .map(|(_, coord, voxel_material, _)| (*coord, voxel_material.voxels())),
+        )
+    }
+
+    /// Occlusion of a sound travelling between the world positions `a` and `b`, see
+    /// [`VoxelOcclusion::occlusion_between`]. Use [`VoxelWorld::occlusion`] for several queries.
+    #[cfg(feature = "raymarch")]
+    pub fn occlusion_between(&self, a: Vec3, b: Vec3) -> f32 {
+        self.occlusion().occlusion_between(a, b)
+    }
+
+    /// Borrows the voxels of the chunk at `coord` for writing.
+    pub fn chunk_mut(&mut self, coord: ChunkCoord) -> Option<ChunkGuard<'_>> {
+        let entity = self.entity(coord)?;
+        let (_, _, voxel_material, dirty_region) = self.chunks.get_mut(entity).ok()?;
+        assert_eq!(
+            voxel_material.voxels().len(),
+            CHUNK_SZ_3,
+            "chunks must have VoxelWorldConfig::CHUNK_SIZE voxels along each axis"
+        );
+        Some(ChunkGuard {
+            voxel_material,
+            dirty_region,
+        })
+    }
+}
+
+/// Write access to the voxels of a chunk that records the written voxels in its [`DirtyRegion`].
+pub struct ChunkGuard<'a> {
+    voxel_material: Mut<'a, VoxelMaterial>,
+    dirty_region: Mut<'a, DirtyRegion>,
+}
+
+impl<'a> ChunkGuard<'a> {
+    pub fn voxels(&self) -> &[Voxel] {
+        self.voxel_material.voxels()
+    }
+
+    pub fn get(&self, pos: UVec3) -> Option<Voxel> {
+        if pos.cmpge(UVec3::splat(CHUNK_SZ as u32)).any() {
+            return None;
+        }
+        self.voxel_material.voxels().get(voxel_index(pos)).copied()
+    }
+
+    /// Replaces the voxel at `pos`, returning whether it is inside the chunk.
+    pub fn set(&mut self, pos: UVec3, voxel: Voxel) -> bool {
+        if self.get(pos).is_none() {
+            return false;
+        }
+        self.voxel_material.voxels_mut_recorded()[voxel_index(pos)] = voxel;
+        self.dirty_region.include(pos, pos + UVec3::ONE);
+        true
+    }
+
+    /// Sets the density of the voxel at `pos`, keeping its flags and emissive color.
+    pub fn set_density(&mut self, pos: UVec3, density: f32) -> bool {
+        match self.get(pos) {
+            Some(voxel) => self.set(pos, voxel.with_density(density)),
+            None => false,
+        }
+    }
+
+    /// Replaces the voxels from `min` up to, but excluding, `max`.
+    pub fn fill(&mut self, min: UVec3, max: UVec3, voxel: Voxel) {
+        let max = max.min(UVec3::splat(CHUNK_SZ as u32));
+        for z in min.z..max.z {
+            for y in min.y..max.y {
+                for x in min.x..max.x {
+                    self.set(UVec3::new(x, y, z), voxel);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use bevy::{
+    core_pipeline::core_3d::graph::{Core3d, Node3d},
+    prelude::*,
+    render::{
+        extract_component::ExtractComponentPlugin,
+        render_graph::{RenderGraphApp, ViewNodeRunner},
+        Render, RenderApp, RenderSet,
+    },
+};
+
+use crate::{
+    data::{
+        chunk_retirement::ChunkRetirementExt, depth_pyramid::DepthPyramid,
+        gpu_depth_pyramid::GpuDepthPyramid, voxel_material::VoxelMaterialComponents,
+    },
+    render::depth_pyramid_compute_pipeline::{
+        DepthPyramidComputePipeline, DepthPyramidNode, DepthPyramidNodeLabel,
+    },
+};
+
+/// Builds the [`GpuDepthPyramid`] of every [`DepthPyramid`] camera after its main pass, in the
+/// render world's [`VoxelMaterialComponents<GpuDepthPyramid>`] by view entity.
+pub struct DepthPyramidPlugin;
+
+impl Plugin for DepthPyramidPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(ExtractComponentPlugin::<DepthPyramid>::default())
+            .add_systems(PostUpdate, DepthPyramid::bind_depth_texture);
+    }
+
+    fn finish(&self, app: &mut App) {
+        let render_app = app.sub_app_mut(RenderApp);
+
+        render_app
+            .init_resource::<DepthPyramidComputePipeline>()
+            .init_resource::<VoxelMaterialComponents<GpuDepthPyramid>>()
+            .free_on_retire::<GpuDepthPyramid>()
+            .add_systems(
+                Render,
+                GpuDepthPyramid::prepare.in_set(RenderSet::PrepareBindGroups),
+            )
+            .add_render_graph_node::<ViewNodeRunner<DepthPyramidNode>>(
+                Core3d,
+                DepthPyramidNodeLabel,
+            )
+            .add_render_graph_edges(
+                Core3d,
+                (
+                    Node3d::EndMainPass,
+                    DepthPyramidNodeLabel,
+                    Node3d::EndMainPassPostProcessing,
+                ),
+            );
+    }
+}
@@ -0,0 +1,146 @@
+use std::time::Instant;
+
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic},
+    prelude::*,
+    render::{Render, RenderApp},
+    utils::HashMap,
+};
+use crossbeam_channel::{Receiver, Sender};
+
+use crate::{
+    bundles::volumetric_bundle::Volumetric,
+    channels::{PendingReadbacks, RenderWorldSender, VertexReadback},
+    data::{gpu_voxel_material::GpuVoxelMaterial, voxel_material::VoxelMaterialComponents},
+    render::voxel_mesh_compute_pipeline::DirtyMeshes,
+};
+
+/// Measurements of the render world sent to the main world once per frame.
+pub struct RenderDiagnostics {
+    pub gpu_bytes: u64,
+    pub remesh_queue_len: usize,
+    /// Milliseconds from remeshing to the end of the readback of each mesh read back this frame.
+    pub readback_latencies_ms: Vec<f64>,
+}
+
+/// The time each entity was remeshed, until its mesh is read back.
+#[derive(Resource, Default)]
+pub struct ReadbackTimings(pub HashMap<Entity, Instant>);
+
+/// Registers the voxel [`Diagnostics`], so that they are logged by the
+/// [`LogDiagnosticsPlugin`](bevy::diagnostic::LogDiagnosticsPlugin) and shown by diagnostic
+/// overlays. Must be added after the [`GpuReadbackPlugin`](crate::GpuReadbackPlugin).
+pub struct VoxelDiagnosticsPlugin;
+
+impl VoxelDiagnosticsPlugin {
+    /// Number of volumetric entities.
+    pub const CHUNKS_LOADED: DiagnosticPath = DiagnosticPath::const_new("voxel/chunks_loaded");
+    /// Bytes of the GPU buffers of every volumetric entity.
+    pub const GPU_BYTES: DiagnosticPath = DiagnosticPath::const_new("voxel/gpu_bytes");
+    /// Entities waiting to be remeshed.
+    pub const REMESH_QUEUE_LEN: DiagnosticPath =
+        DiagnosticPath::const_new("voxel/remesh_queue_len");
+    /// Time from remeshing an entity to reading back its mesh.
+    pub const READBACK_LATENCY_MS: DiagnosticPath =
+        DiagnosticPath::const_new("voxel/readback_latency_ms");
+
+    pub fn measure_chunks(
+        mut diagnostics: Diagnostics,
+        volumetric_query: Query<(), With<Volumetric>>,
+    ) {
+        diagnostics.add_measurement(&Self::CHUNKS_LOADED, || {
+            volumetric_query.iter().count() as f64
+        });
+    }
+}
+
+impl Plugin for VoxelDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_diagnostic(Diagnostic::new(Self::CHUNKS_LOADED))
+            .register_diagnostic(Diagnostic::new(Self::GPU_BYTES).with_suffix(" B"))
+            .register_diagnostic(Diagnostic::new(Self::REMESH_QUEUE_LEN))
+            .register_diagnostic(Diagnostic::new(Self::READBACK_LATENCY_MS).with_suffix("ms"))
+            .add_systems(
+                Update,
+                (Self::measure_chunks, RenderDiagnosticsReceiver::receive),
+            );
+    }
+
+    fn finish(&self, app: &mut App) {
+        let (s, r) = crossbeam_channel::unbounded();
+        app.insert_resource(RenderDiagnosticsReceiver(r));
+
+        app.sub_app_mut(RenderApp)
+            .init_resource::<ReadbackTimings>()
+            .insert_resource(RenderDiagnosticsSender(s))
+            .add_systems(
+                Render,
+                RenderDiagnosticsSender::measure.after(RenderWorldSender::map_and_read_buffer),
+            );
+    }
+}
+
+#[derive(Resource, Deref)]
+pub struct RenderDiagnosticsReceiver(pub Receiver<RenderDiagnostics>);
+
+impl RenderDiagnosticsReceiver {
+    /// Adds the measurements of the render world to the [`Diagnostics`].
+    pub fn receive(receiver: Res<Self>, mut diagnostics: Diagnostics) {
+        for measurements in receiver.try_iter() {
+            diagnostics.add_measurement(&VoxelDiagnosticsPlugin::GPU_BYTES, || {
+                measurements.gpu_bytes as f64
+            });
+            diagnostics.add_measurement(&VoxelDiagnosticsPlugin::REMESH_QUEUE_LEN, || {
+                measurements.remesh_queue_len as f64
+            });
+            for latency in &measurements.readback_latencies_ms {
+                diagnostics
+                    .add_measurement(&VoxelDiagnosticsPlugin::READBACK_LATENCY_MS, || *latency);
+            }
+        }
+    }
+}
+
+#[derive(Resource, Deref)]
+pub struct RenderDiagnosticsSender(pub Sender<RenderDiagnostics>);
+
+impl RenderDiagnosticsSender {
+    /// Measures the GPU memory and the remesh queue, and the latency of the readbacks that
+    /// completed this frame. Meshes remeshed again before being read back are timed from their
+    /// first remesh, those whose readback was cancelled are not timed.
+    pub fn measure(
+        sender: Res<Self>,
+        gpu_voxel_materials: Res<VoxelMaterialComponents<GpuVoxelMaterial>>,
+        dirty_meshes: Res<DirtyMeshes>,
+        pending_readbacks: Res<PendingReadbacks>,
+        vertex_readbacks: Res<VoxelMaterialComponents<VertexReadback>>,
+        mut timings: ResMut<ReadbackTimings>,
+    ) {
+        let now = Instant::now();
+        for entity in dirty_meshes.meshed() {
+            timings.0.entry(*entity).or_insert(now);
+        }
+
+        let mut readback_latencies_ms = Vec::new();
+        timings.0.retain(|entity, meshed_at| {
+            if pending_readbacks.contains(entity) {
+                return true;
+            }
+            if vertex_readbacks.get(entity).is_some() {
+                let latency = now.duration_since(*meshed_at);
+                readback_latencies_ms.push(latency.as_secs_f64() * 1000.0);
+            }
+            false
+        });
+
+        let _ = sender.send(RenderDiagnostics {
+            gpu_bytes: gpu_voxel_materials
+                .0
+                .values()
+                .map(GpuVoxelMaterial::gpu_bytes)
+                .sum(),
+            remesh_queue_len: dirty_meshes.dirty_len(),
+            readback_latencies_ms,
+        });
+    }
+}
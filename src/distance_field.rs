@@ -0,0 +1,61 @@
+use bevy::{
+    prelude::*,
+    render::{
+        extract_component::ExtractComponentPlugin, graph::CameraDriverLabel,
+        render_graph::RenderGraph, Render, RenderApp, RenderSet,
+    },
+};
+
+use crate::{
+    data::{
+        chunk_retirement::ChunkRetirementExt, distance_field::DistanceFieldVolume,
+        gpu_distance_field::GpuDistanceField, voxel_material::VoxelMaterialComponents,
+    },
+    render::{
+        distance_field_compute_pipeline::{
+            DistanceFieldComputeNode, DistanceFieldComputeNodeLabel, DistanceFieldComputePipeline,
+        },
+        voxel_mesh_compute_pipeline::VoxelMeshComputeNodeLabel,
+    },
+};
+
+/// Generates the distance field of every [`DistanceFieldVolume`] on the GPU after the volumes are
+/// meshed and before the cameras render, so materials can sample it in the same frame.
+///
+/// Must be added after the [`GpuReadbackPlugin`](crate::GpuReadbackPlugin).
+pub struct DistanceFieldPlugin;
+
+impl Plugin for DistanceFieldPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(ExtractComponentPlugin::<DistanceFieldVolume>::default());
+    }
+
+    fn finish(&self, app: &mut App) {
+        let render_app = app.sub_app_mut(RenderApp);
+
+        render_app
+            .init_resource::<DistanceFieldComputePipeline>()
+            .init_resource::<VoxelMaterialComponents<GpuDistanceField>>()
+            .free_on_retire::<GpuDistanceField>()
+            .add_systems(
+                ExtractSchedule,
+                (
+                    GpuDistanceField::initialize,
+                    GpuDistanceField::extract.after(GpuDistanceField::initialize),
+                ),
+            )
+            .add_systems(
+                Render,
+                GpuDistanceField::prepare.in_set(RenderSet::PrepareBindGroups),
+            );
+
+        let distance_field_compute_node =
+            DistanceFieldComputeNode::from_world(render_app.world_mut());
+
+        let mut render_graph = render_app.world_mut().resource_mut::<RenderGraph>();
+
+        render_graph.add_node(DistanceFieldComputeNodeLabel, distance_field_compute_node);
+        render_graph.add_node_edge(VoxelMeshComputeNodeLabel, DistanceFieldComputeNodeLabel);
+        render_graph.add_node_edge(DistanceFieldComputeNodeLabel, CameraDriverLabel);
+    }
+}
@@ -0,0 +1,120 @@
+use std::collections::BTreeMap;
+
+use bevy::{color::palettes::css::ORANGE, prelude::*};
+
+use crate::data::{chunk_coord::ChunkCoord, voxel_world::VoxelWorldConfig};
+
+/// Identifies a region locked by [`EditLocks`], to unlock it later.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct EditLockId(u32);
+
+/// A region of the world whose voxels [`ChunkEdit`](crate::data::voxel_edit::ChunkEdit)s leave
+/// untouched, in world space.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LockRegion {
+    Box { min: Vec3, max: Vec3 },
+    Sphere { center: Vec3, radius: f32 },
+}
+
+impl LockRegion {
+    pub fn contains(&self, world: Vec3) -> bool {
+        match *self {
+            LockRegion::Box { min, max } => world.cmpge(min).all() && world.cmple(max).all(),
+            LockRegion::Sphere { center, radius } => {
+                world.distance_squared(center) <= radius * radius
+            }
+        }
+    }
+}
+
+/// The regions of the world protected from edits, e.g. built structures or spawn areas. A voxel
+/// is locked when its world space position, see [`VoxelWorldConfig::voxel_to_world`], is in any
+/// of the regions. Edits overlapping a locked region still apply to its unlocked voxels.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct EditLocks {
+    regions: BTreeMap<EditLockId, LockRegion>,
+    next_id: u32,
+}
+
+impl EditLocks {
+    pub fn lock(&mut self, region: LockRegion) -> EditLockId {
+        let id = EditLockId(self.next_id);
+        self.next_id += 1;
+        self.regions.insert(id, region);
+        id
+    }
+
+    /// Locks the world space box from `min` to `max`, both included.
+    pub fn lock_box(&mut self, min: Vec3, max: Vec3) -> EditLockId {
+        self.lock(LockRegion::Box {
+            min: min.min(max),
+            max: min.max(max),
+        })
+    }
+
+    /// Locks the world space sphere of `radius` around `center`.
+    pub fn lock_sphere(&mut self, center: Vec3, radius: f32) -> EditLockId {
+        self.lock(LockRegion::Sphere { center, radius })
+    }
+
+    /// Unlocks the region locked as `id`, returning it if it was still locked.
+    pub fn unlock(&mut self, id: EditLockId) -> Option<LockRegion> {
+        self.regions.remove(&id)
+    }
+
+    pub fn clear(&mut self) {
+        self.regions.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty()
+    }
+
+    /// The locked regions, in the order they were locked.
+    pub fn regions(&self) -> impl Iterator<Item = (EditLockId, &LockRegion)> {
+        self.regions.iter().map(|(id, region)| (*id, region))
+    }
+
+    /// Whether the world space position `world` is in a locked region.
+    pub fn is_locked(&self, world: Vec3) -> bool {
+        self.regions.values().any(|region| region.contains(world))
+    }
+
+    /// Whether the voxel at `pos` of the chunk at `coord` is locked.
+    pub fn is_voxel_locked(
+        &self,
+        config: &VoxelWorldConfig,
+        coord: ChunkCoord,
+        pos: UVec3,
+    ) -> bool {
+        !self.is_empty()
+            && self.is_locked(config.voxel_to_world(config.chunk_to_voxel(coord) + pos.as_ivec3()))
+    }
+
+    /// Outlines the locked regions.
+    pub fn draw(locks: Res<Self>, mut gizmos: Gizmos) {
+        for region in locks.regions.values() {
+            match *region {
+                LockRegion::Box { min, max } => {
+                    gizmos.cuboid(
+                        Transform::from_translation((min + max) / 2.0).with_scale(max - min),
+                        ORANGE,
+                    );
+                }
+                LockRegion::Sphere { center, radius } => {
+                    gizmos.sphere(center, Quat::IDENTITY, radius, ORANGE);
+                }
+            }
+        }
+    }
+}
+
+/// Outlines the regions of the [`EditLocks`] with gizmos.
+pub struct EditLockGizmosPlugin;
+
+impl Plugin for EditLockGizmosPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EditLocks>()
+            .add_systems(PostUpdate, EditLocks::draw);
+    }
+}
@@ -0,0 +1,426 @@
+use bevy::{
+    color::palettes::css::{BLUE, LIME, RED, WHITE, YELLOW},
+    prelude::*,
+    window::PrimaryWindow,
+};
+
+use crate::{
+    bundles::volumetric_bundle::Volumetric,
+    data::{chunk_coord::ChunkCoord, voxel_world::VoxelWorldConfig},
+};
+
+/// Below this, the cursor ray is taken as parallel to the axis of a handle.
+const PARALLEL_EPSILON: f32 = 1e-4;
+
+/// Smallest factor a scale or the extent of a chunk can be dragged to, so that they never flip.
+const MIN_FACTOR: f32 = 1e-3;
+
+const AXIS_COLORS: [Srgba; 3] = [RED, LIME, BLUE];
+
+/// The handles drawn around the [`GizmoTarget`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GizmoMode {
+    /// Arrows along the world axes moving the entity.
+    #[default]
+    Translate,
+    /// Circles around the world axes rotating the entity about its center.
+    Rotate,
+    /// Handles along the local axes scaling the entity about its center.
+    Scale,
+    /// Handles on the positive faces of the chunk, resizing every chunk through the voxel size of
+    /// the [`VoxelWorldConfig`] while the first voxel of the target stays in place. Only chunks
+    /// with a [`ChunkCoord`] have them.
+    Bounds,
+}
+
+/// Marks the volumetric entity manipulated by the [`EditorGizmos`]. Only the first one is.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct GizmoTarget;
+
+/// Where the handles of the target are, for the frame they are drawn or dragged in.
+#[derive(Clone, Copy, Debug)]
+struct HandleFrame {
+    center: Vec3,
+    axes: [Vec3; 3],
+    half_extents: Vec3,
+    length: f32,
+}
+
+impl HandleFrame {
+    fn new(mode: GizmoMode, transform: &GlobalTransform, camera_position: Vec3, size: f32) -> Self {
+        let half_chunk = Vec3::splat(VoxelWorldConfig::CHUNK_SIZE as f32 / 2.0);
+        let (scale, rotation, _) = transform.to_scale_rotation_translation();
+        let center = transform.transform_point(half_chunk);
+        let axes = match mode {
+            GizmoMode::Translate | GizmoMode::Rotate => [Vec3::X, Vec3::Y, Vec3::Z],
+            GizmoMode::Scale | GizmoMode::Bounds => {
+                [Vec3::X, Vec3::Y, Vec3::Z].map(|axis| rotation * axis)
+            }
+        };
+
+        Self {
+            center,
+            axes,
+            half_extents: (scale * half_chunk).abs(),
+            length: size * center.distance(camera_position),
+        }
+    }
+
+    /// The segment of the linear handle along `axis`.
+    fn segment(&self, mode: GizmoMode, axis: usize) -> (Vec3, Vec3) {
+        let direction = self.axes[axis];
+        match mode {
+            GizmoMode::Bounds => {
+                let face = self.center + direction * self.half_extents[axis];
+                (face, face + direction * self.length * 0.25)
+            }
+            _ => (self.center, self.center + direction * self.length),
+        }
+    }
+
+    /// Distance from `ray` to the handle along or around `axis`.
+    fn handle_distance(&self, mode: GizmoMode, axis: usize, ray: Ray3d) -> Option<f32> {
+        if mode == GizmoMode::Rotate {
+            let hit = self.grab(mode, axis, ray)?;
+            return Some((hit.distance(self.center) - self.length).abs());
+        }
+
+        let (start, end) = self.segment(mode, axis);
+        let direction = self.axes[axis];
+        let (t, s) = closest_parameters(ray, start, direction)?;
+        if t < 0.0 {
+            return None;
+        }
+        let point = start + direction * s.clamp(0.0, start.distance(end)) - ray.origin;
+        Some((point - *ray.direction * point.dot(*ray.direction)).length())
+    }
+
+    /// The point `ray` points at while dragging the handle of `axis`: on the axis through the
+    /// center for linear handles, or on the plane of the circle for rotations.
+    fn grab(&self, mode: GizmoMode, axis: usize, ray: Ray3d) -> Option<Vec3> {
+        let direction = self.axes[axis];
+        if mode == GizmoMode::Rotate {
+            let t = ray.intersect_plane(self.center, InfinitePlane3d::new(direction))?;
+            return Some(ray.get_point(t));
+        }
+        let (_, s) = closest_parameters(ray, self.center, direction)?;
+        Some(self.center + direction * s)
+    }
+}
+
+/// The parameters along `ray` and along the line through `origin` along the unit `axis` of their
+/// closest points, or `None` when they are parallel.
+fn closest_parameters(ray: Ray3d, origin: Vec3, axis: Vec3) -> Option<(f32, f32)> {
+    let w = ray.origin - origin;
+    let b = ray.direction.dot(axis);
+    let d = ray.direction.dot(w);
+    let e = axis.dot(w);
+    let denominator = 1.0 - b * b;
+    if denominator < PARALLEL_EPSILON {
+        return None;
+    }
+    Some(((b * e - d) / denominator, (e - b * d) / denominator))
+}
+
+#[derive(Clone, Copy, Debug)]
+struct GizmoDrag {
+    mode: GizmoMode,
+    axis: usize,
+    frame: HandleFrame,
+    /// The grabbed point when the drag started.
+    start: Vec3,
+    transform: Transform,
+    config: VoxelWorldConfig,
+}
+
+/// Translate, rotate, scale and bounds handles for positioning the [`GizmoTarget`] with the mouse
+/// in tooling built on the crate. Dragging a handle with the left button writes into the
+/// [`Transform`] of the target, or into the [`VoxelWorldConfig`] for [`GizmoMode::Bounds`]. The
+/// target is expected to have no parent, its transform being taken as a world space one.
+#[derive(Resource, Clone, Debug)]
+pub struct EditorGizmos {
+    pub enabled: bool,
+    pub mode: GizmoMode,
+    /// Length of the handles as a fraction of their distance to the camera, so that they keep
+    /// their size on screen.
+    pub size: f32,
+    /// Distance from the cursor within which a handle is grabbed, as a fraction of its length.
+    pub pick_radius: f32,
+    hovered: Option<usize>,
+    drag: Option<GizmoDrag>,
+}
+
+impl Default for EditorGizmos {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            mode: GizmoMode::default(),
+            size: 0.15,
+            pick_radius: 0.08,
+            hovered: None,
+            drag: None,
+        }
+    }
+}
+
+/// The first of these entities is the one manipulated.
+type GizmoTargetFilter = (With<GizmoTarget>, With<Volumetric>);
+
+impl EditorGizmos {
+    /// Whether a handle is being dragged, e.g. to keep camera controls from reacting to the mouse.
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    /// Whether the cursor is over a handle.
+    pub fn is_hovered(&self) -> bool {
+        self.hovered.is_some()
+    }
+
+    /// The active camera of highest order, which the handles are drawn and grabbed from.
+    fn camera<'a>(
+        camera_query: &'a Query<(&Camera, &GlobalTransform)>,
+    ) -> Option<(&'a Camera, &'a GlobalTransform)> {
+        camera_query
+            .iter()
+            .filter(|(camera, _)| camera.is_active)
+            .max_by_key(|(camera, _)| camera.order)
+    }
+
+    /// Grabs the handle under the cursor when the left button is pressed, and drags it until it
+    /// is released.
+    #[allow(clippy::type_complexity)]
+    pub fn interact(
+        mut settings: ResMut<Self>,
+        mut config: ResMut<VoxelWorldConfig>,
+        mouse: Res<ButtonInput<MouseButton>>,
+        window_query: Query<&Window, With<PrimaryWindow>>,
+        camera_query: Query<(&Camera, &GlobalTransform)>,
+        target_query: Query<(Entity, &GlobalTransform, Option<&ChunkCoord>), GizmoTargetFilter>,
+        mut transform_query: Query<(&mut Transform, Option<&ChunkCoord>), With<Volumetric>>,
+    ) {
+        if !mouse.pressed(MouseButton::Left) {
+            settings.drag = None;
+        }
+        let Some((entity, global_transform, coord)) =
+            target_query.iter().next().filter(|_| settings.enabled)
+        else {
+            settings.hovered = None;
+            settings.drag = None;
+            return;
+        };
+        let Some((camera, camera_transform)) = Self::camera(&camera_query) else {
+            return;
+        };
+        let Some(ray) = window_query
+            .get_single()
+            .ok()
+            .and_then(|window| window.cursor_position())
+            .and_then(|cursor| camera.viewport_to_world(camera_transform, cursor))
+        else {
+            settings.hovered = None;
+            return;
+        };
+
+        if let Some(drag) = settings.drag {
+            let Some(current) = drag.frame.grab(drag.mode, drag.axis, ray) else {
+                return;
+            };
+            if drag.mode == GizmoMode::Bounds {
+                if let Some(coord) = coord {
+                    Self::drag_bounds(&drag, current, *coord, &mut config, &mut transform_query);
+                }
+            } else if let Ok((mut transform, _)) = transform_query.get_mut(entity) {
+                *transform = Self::drag_transform(&drag, current);
+            }
+            return;
+        }
+
+        let mode = settings.mode;
+        let frame = HandleFrame::new(
+            mode,
+            global_transform,
+            camera_transform.translation(),
+            settings.size,
+        );
+        let pick_radius = settings.pick_radius * frame.length;
+        settings.hovered = match (mode, coord) {
+            (GizmoMode::Bounds, None) => None,
+            _ => (0..3)
+                .filter_map(|axis| {
+                    let distance = frame.handle_distance(mode, axis, ray)?;
+                    (distance <= pick_radius).then_some((axis, distance))
+                })
+                .min_by(|(_, a), (_, b)| a.total_cmp(b))
+                .map(|(axis, _)| axis),
+        };
+
+        let Some(axis) = settings.hovered else {
+            return;
+        };
+        if !mouse.just_pressed(MouseButton::Left) {
+            return;
+        }
+        let (Some(start), Ok((transform, _))) =
+            (frame.grab(mode, axis, ray), transform_query.get(entity))
+        else {
+            return;
+        };
+        settings.drag = Some(GizmoDrag {
+            mode,
+            axis,
+            frame,
+            start,
+            transform: *transform,
+            config: *config,
+        });
+    }
+
+    /// The transform of the target dragged from `drag.start` to `current`.
+    fn drag_transform(drag: &GizmoDrag, current: Vec3) -> Transform {
+        let center = drag.frame.center;
+        let axis = drag.frame.axes[drag.axis];
+        let mut transform = drag.transform;
+        match drag.mode {
+            GizmoMode::Translate => {
+                transform.translation += axis * (current - drag.start).dot(axis);
+            }
+            GizmoMode::Rotate => {
+                let from = drag.start - center;
+                let to = current - center;
+                let angle = from.cross(to).dot(axis).atan2(from.dot(to));
+                let rotation = Quat::from_axis_angle(axis, angle);
+                transform.rotation = rotation * drag.transform.rotation;
+                transform.translation = center + rotation * (drag.transform.translation - center);
+            }
+            GizmoMode::Scale => {
+                let from = (drag.start - center).dot(axis);
+                if from.abs() < PARALLEL_EPSILON {
+                    return transform;
+                }
+                let factor = ((current - center).dot(axis) / from).max(MIN_FACTOR);
+                transform.scale[drag.axis] *= factor;
+                let half_chunk = Vec3::splat(VoxelWorldConfig::CHUNK_SIZE as f32 / 2.0);
+                transform.translation =
+                    center - transform.rotation * (transform.scale * half_chunk);
+            }
+            GizmoMode::Bounds => {}
+        }
+        transform
+    }
+
+    /// Resizes the chunks to the extent dragged from `drag.start` to `current` through the voxel
+    /// size, moving the origin so that the first voxel of the chunk at `coord` stays in place, and
+    /// places every chunk again.
+    fn drag_bounds(
+        drag: &GizmoDrag,
+        current: Vec3,
+        coord: ChunkCoord,
+        config: &mut VoxelWorldConfig,
+        transform_query: &mut Query<(&mut Transform, Option<&ChunkCoord>), With<Volumetric>>,
+    ) {
+        let axis = drag.frame.axes[drag.axis];
+        let start_extent = drag.config.chunk_extent();
+        let extent =
+            (start_extent + (current - drag.start).dot(axis)).max(start_extent * MIN_FACTOR);
+
+        let first_voxel = drag.config.chunk_to_voxel(coord);
+        let anchor = drag.config.voxel_to_world(first_voxel);
+        config.voxel_size = drag.config.voxel_size * extent / start_extent;
+        config.origin = anchor - first_voxel.as_vec3() * config.voxel_size;
+
+        for (mut transform, coord) in transform_query.iter_mut() {
+            let Some(coord) = coord else {
+                continue;
+            };
+            let chunk_transform = config.chunk_transform(*coord);
+            transform.translation = chunk_transform.translation;
+            transform.scale = chunk_transform.scale;
+        }
+    }
+
+    pub fn draw(
+        settings: Res<Self>,
+        mut gizmos: Gizmos,
+        camera_query: Query<(&Camera, &GlobalTransform)>,
+        target_query: Query<(&GlobalTransform, Option<&ChunkCoord>), GizmoTargetFilter>,
+    ) {
+        if !settings.enabled {
+            return;
+        }
+        let Some((transform, coord)) = target_query.iter().next() else {
+            return;
+        };
+        let Some((_, camera_transform)) = Self::camera(&camera_query) else {
+            return;
+        };
+
+        let mode = settings.drag.map_or(settings.mode, |drag| drag.mode);
+        if mode == GizmoMode::Bounds && coord.is_none() {
+            return;
+        }
+        let frame = HandleFrame::new(
+            mode,
+            transform,
+            camera_transform.translation(),
+            settings.size,
+        );
+        let highlighted = settings.drag.map(|drag| drag.axis).or(settings.hovered);
+
+        if mode == GizmoMode::Bounds {
+            let chunk_size = VoxelWorldConfig::CHUNK_SIZE as f32;
+            let bounds = Transform::from_translation(Vec3::splat(chunk_size / 2.0))
+                .with_scale(Vec3::splat(chunk_size));
+            gizmos.cuboid(transform.mul_transform(bounds), WHITE);
+        }
+
+        for (axis, direction) in frame.axes.into_iter().enumerate() {
+            let color = match highlighted == Some(axis) {
+                true => YELLOW,
+                false => AXIS_COLORS[axis],
+            };
+            let (start, end) = frame.segment(mode, axis);
+            match mode {
+                GizmoMode::Translate => {
+                    gizmos.arrow(start, end, color);
+                }
+                GizmoMode::Rotate => {
+                    gizmos.circle(
+                        frame.center,
+                        Dir3::new_unchecked(direction),
+                        frame.length,
+                        color,
+                    );
+                }
+                GizmoMode::Scale => {
+                    gizmos.line(start, end, color);
+                    let (_, rotation, _) = transform.to_scale_rotation_translation();
+                    gizmos.cuboid(
+                        Transform::from_translation(end)
+                            .with_rotation(rotation)
+                            .with_scale(Vec3::splat(frame.length * 0.1)),
+                        color,
+                    );
+                }
+                GizmoMode::Bounds => {
+                    gizmos.line(start, end, color);
+                    gizmos.sphere(end, Quat::IDENTITY, frame.length * 0.05, color);
+                }
+            }
+        }
+    }
+}
+
+/// Draws the [`EditorGizmos`] of the [`GizmoTarget`] and drags them with the mouse.
+pub struct EditorGizmosPlugin;
+
+impl Plugin for EditorGizmosPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EditorGizmos>()
+            .add_systems(Update, EditorGizmos::interact)
+            .add_systems(
+                PostUpdate,
+                EditorGizmos::draw.after(TransformSystem::TransformPropagate),
+            );
+    }
+}
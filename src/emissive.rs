@@ -0,0 +1,151 @@
+use bevy::{
+    pbr::{ExtendedMaterial, MaterialExtension, MaterialExtensionKey, MaterialExtensionPipeline},
+    prelude::*,
+    render::{
+        mesh::MeshVertexBufferLayoutRef,
+        render_resource::{
+            AsBindGroup, RenderPipelineDescriptor, ShaderRef, SpecializedMeshPipelineError,
+        },
+    },
+};
+
+use crate::data::iso_surface::ATTRIBUTE_EMISSIVE;
+
+const SHADER_ASSET_PATH: &str = "shaders/voxel_emissive.wgsl";
+
+/// Shader location of [`ATTRIBUTE_EMISSIVE`], after those of the attributes of bevy's meshes.
+const EMISSIVE_SHADER_LOCATION: u32 = 8;
+
+/// Lights the mesh of a volumetric entity with the
+/// [`Voxel::emissive`](crate::data::voxel::Voxel::emissive) colors of its voxels, interpolated
+/// onto its vertices as their [`ATTRIBUTE_EMISSIVE`] and added to the emissive light of its
+/// [`StandardMaterial`].
+///
+/// With the [`EmissiveVoxelsPlugin`], the [`StandardMaterial`] of the entity is replaced by an
+/// [`EmissiveVoxelMaterial`] extending it. The colors only light the forward pass, and an entity
+/// with `SdfShadows` as well keeps whichever material replaced its [`StandardMaterial`] first.
+#[derive(Clone, Copy, Component, Debug)]
+pub struct EmissiveVoxels {
+    /// Scale of the emissive colors of the voxels.
+    pub strength: f32,
+}
+
+impl Default for EmissiveVoxels {
+    fn default() -> Self {
+        Self { strength: 1.0 }
+    }
+}
+
+/// Adds the [`EmissiveVoxels`] of a chunk to its emissive light, see [`EmissiveVoxelMaterial`].
+#[derive(Asset, AsBindGroup, Clone, Debug, TypePath)]
+pub struct EmissiveVoxelExtension {
+    #[uniform(100)]
+    pub strength: f32,
+}
+
+impl MaterialExtension for EmissiveVoxelExtension {
+    fn vertex_shader() -> ShaderRef {
+        SHADER_ASSET_PATH.into()
+    }
+
+    fn fragment_shader() -> ShaderRef {
+        SHADER_ASSET_PATH.into()
+    }
+
+    fn specialize(
+        pipeline: &MaterialExtensionPipeline,
+        descriptor: &mut RenderPipelineDescriptor,
+        layout: &MeshVertexBufferLayoutRef,
+        _key: MaterialExtensionKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        // The prepass keeps its own vertex shader, which has no use for the colors. Meshes built
+        // without them are drawn as their base material.
+        if pipeline.vertex_shader.as_ref() != Some(&descriptor.vertex.shader)
+            || !layout.0.contains(ATTRIBUTE_EMISSIVE)
+        {
+            return Ok(());
+        }
+
+        let emissive = layout
+            .0
+            .get_layout(&[ATTRIBUTE_EMISSIVE.at_shader_location(EMISSIVE_SHADER_LOCATION)])?;
+        descriptor.vertex.buffers[0]
+            .attributes
+            .extend(emissive.attributes);
+        descriptor.vertex.shader_defs.push("VOXEL_EMISSIVE".into());
+        if let Some(fragment) = descriptor.fragment.as_mut() {
+            fragment.shader_defs.push("VOXEL_EMISSIVE".into());
+        }
+        Ok(())
+    }
+}
+
+/// A [`StandardMaterial`] lit by [`EmissiveVoxels`].
+pub type EmissiveVoxelMaterial = ExtendedMaterial<StandardMaterial, EmissiveVoxelExtension>;
+
+impl EmissiveVoxels {
+    /// Replaces the [`StandardMaterial`] of the entities with [`EmissiveVoxels`] by an
+    /// [`EmissiveVoxelMaterial`] extending it.
+    pub fn extend_materials(
+        mut commands: Commands,
+        standard_materials: Res<Assets<StandardMaterial>>,
+        mut materials: ResMut<Assets<EmissiveVoxelMaterial>>,
+        emissive_query: Query<(Entity, &EmissiveVoxels, &Handle<StandardMaterial>)>,
+    ) {
+        for (entity, emissive, standard_material) in emissive_query.iter() {
+            let Some(base) = standard_materials.get(standard_material) else {
+                continue;
+            };
+            let material = materials.add(ExtendedMaterial {
+                base: base.clone(),
+                extension: EmissiveVoxelExtension {
+                    strength: emissive.strength.max(0.0),
+                },
+            });
+            commands
+                .entity(entity)
+                .remove::<Handle<StandardMaterial>>()
+                .insert(material);
+        }
+    }
+
+    /// Keeps the strength of the materials in sync with their [`EmissiveVoxels`].
+    pub fn update_materials(
+        mut materials: ResMut<Assets<EmissiveVoxelMaterial>>,
+        emissive_query: Query<
+            (&EmissiveVoxels, &Handle<EmissiveVoxelMaterial>),
+            Changed<EmissiveVoxels>,
+        >,
+    ) {
+        for (emissive, material) in emissive_query.iter() {
+            let strength = emissive.strength.max(0.0);
+            // Only touch materials that changed, as their bind groups are prepared again.
+            let is_current = materials
+                .get(material)
+                .is_some_and(|material| material.extension.strength == strength);
+            if is_current {
+                continue;
+            }
+            if let Some(material) = materials.get_mut(material) {
+                material.extension.strength = strength;
+            }
+        }
+    }
+}
+
+/// Draws the [`EmissiveVoxels`] of volumetric entities.
+pub struct EmissiveVoxelsPlugin;
+
+impl Plugin for EmissiveVoxelsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(MaterialPlugin::<EmissiveVoxelMaterial>::default())
+            .add_systems(
+                PostUpdate,
+                (
+                    EmissiveVoxels::extend_materials,
+                    EmissiveVoxels::update_materials,
+                )
+                    .chain(),
+            );
+    }
+}
@@ -0,0 +1,61 @@
+use bevy::{
+    prelude::*,
+    render::{
+        extract_component::ExtractComponentPlugin, render_graph::RenderGraph, Render, RenderApp,
+        RenderSet,
+    },
+};
+
+use crate::{
+    data::{
+        chunk_retirement::ChunkRetirementExt, erosion::Erosion, gpu_erosion::GpuErosion,
+        voxel_material::VoxelMaterialComponents,
+    },
+    render::{
+        erosion_compute_pipeline::{
+            ErosionComputeNode, ErosionComputeNodeLabel, ErosionComputePipeline,
+        },
+        voxel_decompression_compute_pipeline::VoxelDecompressionComputeNodeLabel,
+        voxel_mesh_compute_pipeline::VoxelMeshComputeNodeLabel,
+    },
+};
+
+/// Simulates the [`Erosion`] of volumetric entities on the GPU before they are meshed.
+///
+/// Must be added after the [`GpuReadbackPlugin`](crate::GpuReadbackPlugin).
+pub struct ErosionPlugin;
+
+impl Plugin for ErosionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(ExtractComponentPlugin::<Erosion>::default())
+            .add_systems(First, Erosion::clear_pending);
+    }
+
+    fn finish(&self, app: &mut App) {
+        let render_app = app.sub_app_mut(RenderApp);
+
+        render_app
+            .init_resource::<ErosionComputePipeline>()
+            .init_resource::<VoxelMaterialComponents<GpuErosion>>()
+            .free_on_retire::<GpuErosion>()
+            .add_systems(
+                ExtractSchedule,
+                (
+                    GpuErosion::initialize,
+                    GpuErosion::extract.after(GpuErosion::initialize),
+                ),
+            )
+            .add_systems(
+                Render,
+                GpuErosion::prepare.in_set(RenderSet::PrepareBindGroups),
+            );
+
+        let erosion_compute_node = ErosionComputeNode::from_world(render_app.world_mut());
+
+        let mut render_graph = render_app.world_mut().resource_mut::<RenderGraph>();
+
+        render_graph.add_node(ErosionComputeNodeLabel, erosion_compute_node);
+        render_graph.add_node_edge(VoxelDecompressionComputeNodeLabel, ErosionComputeNodeLabel);
+        render_graph.add_node_edge(ErosionComputeNodeLabel, VoxelMeshComputeNodeLabel);
+    }
+}
@@ -0,0 +1,306 @@
+use std::sync::Arc;
+
+use bevy::prelude::*;
+
+use crate::{
+    data::{
+        chunk_coord::ChunkCoord,
+        voxel::Voxel,
+        voxel_world::{voxel_index, VoxelWorldConfig},
+    },
+    terrain::{gradient_noise, hash, BiomeMap, TerrainStage},
+};
+
+/// Identifies a [`TerrainFeature`] in a [`FeatureRegistry`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct FeatureId(pub u16);
+
+/// A small feature stamped into the voxels by [`FeatureScattering`], e.g. an ore vein or a
+/// boulder. Positions are in world voxel coordinates.
+pub trait TerrainFeature: Send + Sync + 'static {
+    /// Distance from its center beyond which the feature leaves the voxels as they are, so that
+    /// features centered in neighbouring chunks are stamped into this one too.
+    fn radius(&self) -> f32;
+
+    /// Whether the feature is placed at `center`, under the surface of the terrain at `surface`
+    /// when the [`FeatureScattering`] knows the biomes. Defaults to everywhere.
+    fn accepts(&self, center: Vec3, surface: Option<f32>) -> bool {
+        let _ = (center, surface);
+        true
+    }
+
+    /// The voxel at `pos` once the feature centered at `center` is stamped over `voxel`. `seed`
+    /// differs for each placed feature, so that no two look alike.
+    fn stamp(&self, center: Vec3, pos: Vec3, voxel: Voxel, seed: u32) -> Voxel;
+}
+
+/// Where a [`FeatureScattering`] places a [`TerrainFeature`]: at most one per cell of a grid in
+/// world space, jittered within its cell.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FeatureScatter {
+    /// Side of the cells of the grid, in voxels.
+    pub spacing: f32,
+    /// Chance of a cell holding a feature, from 0 to 1.
+    pub chance: f32,
+    /// Whether the grid covers the columns of the world, placing the features on the surface of
+    /// the terrain, rather than its whole volume.
+    pub on_surface: bool,
+}
+
+impl Default for FeatureScatter {
+    fn default() -> Self {
+        Self {
+            spacing: 16.0,
+            chance: 0.5,
+            on_surface: false,
+        }
+    }
+}
+
+/// The features a [`FeatureScattering`] places, along with where.
+#[derive(Clone, Default)]
+pub struct FeatureRegistry {
+    features: Vec<(Arc<dyn TerrainFeature>, FeatureScatter)>,
+}
+
+impl FeatureRegistry {
+    /// Adds `feature`, placed as `scatter`, returning its id. Features are stamped in the order
+    /// they were added.
+    pub fn register(&mut self, feature: impl TerrainFeature, scatter: FeatureScatter) -> FeatureId {
+        self.features.push((Arc::new(feature), scatter));
+        FeatureId(self.features.len() as u16 - 1)
+    }
+
+    pub fn get(&self, id: FeatureId) -> Option<(&dyn TerrainFeature, FeatureScatter)> {
+        self.features
+            .get(id.0 as usize)
+            .map(|(feature, scatter)| (feature.as_ref(), *scatter))
+    }
+
+    pub fn len(&self) -> usize {
+        self.features.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.features.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (FeatureId, &dyn TerrainFeature, FeatureScatter)> {
+        self.features
+            .iter()
+            .enumerate()
+            .map(|(index, (feature, scatter))| {
+                (FeatureId(index as u16), feature.as_ref(), *scatter)
+            })
+    }
+}
+
+/// Stamps the features of a [`FeatureRegistry`] into the voxels of the previous stages. Each
+/// feature is placed on its own jittered grid in world space, every cell hashed from its
+/// coordinate and the seed, so that a chunk is generated the same whatever the chunks around it
+/// and features straddling chunks are stamped whole into each.
+pub struct FeatureScattering {
+    pub seed: u32,
+    pub registry: FeatureRegistry,
+    /// The biomes whose surface the features [`FeatureScatter::on_surface`] are placed on. Without
+    /// them, those features are not placed.
+    pub biomes: Option<Arc<BiomeMap>>,
+}
+
+impl FeatureScattering {
+    pub fn new(seed: u32, registry: FeatureRegistry) -> Self {
+        Self {
+            seed,
+            registry,
+            biomes: None,
+        }
+    }
+
+    /// Places the features on the surface of `biomes`, and away from it as they accept.
+    pub fn with_biomes(mut self, biomes: Arc<BiomeMap>) -> Self {
+        self.biomes = Some(biomes);
+        self
+    }
+
+    /// The centers of the features `id` reaching into the box from `min` to `max`, along with
+    /// their seeds.
+    fn placements(
+        &self,
+        id: FeatureId,
+        feature: &dyn TerrainFeature,
+        scatter: FeatureScatter,
+        min: Vec3,
+        max: Vec3,
+    ) -> Vec<(Vec3, u32)> {
+        let seed = self
+            .seed
+            .wrapping_add((id.0 as u32).wrapping_mul(0x9e37_79b9));
+        let spacing = scatter.spacing.max(1.0);
+        let radius = feature.radius().max(0.0);
+        let mut min_cell = ((min - radius) / spacing).floor().as_ivec3();
+        let mut max_cell = ((max + radius) / spacing).floor().as_ivec3();
+        if scatter.on_surface {
+            min_cell.y = 0;
+            max_cell.y = 0;
+        }
+
+        let mut placements = Vec::new();
+        for z in min_cell.z..=max_cell.z {
+            for y in min_cell.y..=max_cell.y {
+                for x in min_cell.x..=max_cell.x {
+                    let cell = IVec3::new(x, y, z);
+                    if unit(hash(cell, seed)) >= scatter.chance {
+                        continue;
+                    }
+                    let jitter = Vec3::new(
+                        unit(hash(cell, seed.wrapping_add(1))),
+                        unit(hash(cell, seed.wrapping_add(2))),
+                        unit(hash(cell, seed.wrapping_add(3))),
+                    );
+                    let mut center = (cell.as_vec3() + jitter) * spacing;
+
+                    let surface = self
+                        .biomes
+                        .as_ref()
+                        .map(|biomes| biomes.height_at(center.xz()));
+                    if scatter.on_surface {
+                        let Some(surface) = surface else {
+                            continue;
+                        };
+                        center.y = surface;
+                    }
+                    let reaches =
+                        (center + radius).cmpge(min).all() && (center - radius).cmplt(max).all();
+                    if !reaches || !feature.accepts(center, surface) {
+                        continue;
+                    }
+                    placements.push((center, hash(cell, seed.wrapping_add(4))));
+                }
+            }
+        }
+        placements
+    }
+}
+
+impl TerrainStage for FeatureScattering {
+    fn generate(&self, _coord: ChunkCoord, origin: IVec3, voxels: &mut [Voxel]) {
+        let min = origin.as_vec3();
+        let max = min + VoxelWorldConfig::CHUNK_SIZE as f32;
+        for (id, feature, scatter) in self.registry.iter() {
+            let radius = feature.radius().max(0.0);
+            for (center, seed) in self.placements(id, feature, scatter, min, max) {
+                let first = ((center - radius).floor().as_ivec3() - origin).max(IVec3::ZERO);
+                let last = ((center + radius).ceil().as_ivec3() - origin)
+                    .min(IVec3::splat(VoxelWorldConfig::CHUNK_SIZE as i32 - 1));
+                for z in first.z..=last.z {
+                    for y in first.y..=last.y {
+                        for x in first.x..=last.x {
+                            let local = IVec3::new(x, y, z);
+                            let pos = (origin + local).as_vec3();
+                            let voxel = &mut voxels[voxel_index(local.as_uvec3())];
+                            *voxel = feature.stamp(center, pos, *voxel, seed);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A blob of `material` replacing the solid voxels of the `hosts` materials, e.g. ore in stone.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OreVein {
+    pub material: u32,
+    /// Radius of the blob, in voxels, before its edge is roughened by noise.
+    pub radius: f32,
+    /// Materials the vein replaces; empty replaces any solid voxel.
+    pub hosts: Vec<u32>,
+    /// Voxels below the surface of the terrain above which no vein is centered, when the
+    /// [`FeatureScattering`] knows the biomes.
+    pub min_depth: f32,
+}
+
+impl Default for OreVein {
+    fn default() -> Self {
+        Self {
+            material: 0,
+            radius: 3.0,
+            hosts: Vec::new(),
+            min_depth: 4.0,
+        }
+    }
+}
+
+impl TerrainFeature for OreVein {
+    fn radius(&self) -> f32 {
+        self.radius * 1.5
+    }
+
+    fn accepts(&self, center: Vec3, surface: Option<f32>) -> bool {
+        surface.is_none_or(|surface| center.y < surface - self.min_depth)
+    }
+
+    fn stamp(&self, center: Vec3, pos: Vec3, voxel: Voxel, seed: u32) -> Voxel {
+        if voxel.density() <= 0.0 {
+            return voxel;
+        }
+        if !self.hosts.is_empty() && !self.hosts.contains(&voxel.flags()) {
+            return voxel;
+        }
+        let radius = self.radius * (1.0 + gradient_noise(pos * 0.3, seed) * 0.5);
+        match pos.distance(center) < radius {
+            true => Voxel::new(self.material, voxel.density()),
+            false => voxel,
+        }
+    }
+}
+
+/// A rock of `material` resting on the terrain, blended into the voxels like a
+/// [`LayerBlend::Union`](crate::data::voxel_layers::LayerBlend::Union). Meant to be placed
+/// [`FeatureScatter::on_surface`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Boulder {
+    pub material: u32,
+    /// Radius of the rock, in voxels, before its surface is roughened by noise.
+    pub radius: f32,
+    /// Fraction of the height of the rock sunk into the ground, from 0 to 1.
+    pub embed: f32,
+    /// Relative depth of the bumps of its surface.
+    pub roughness: f32,
+}
+
+impl Default for Boulder {
+    fn default() -> Self {
+        Self {
+            material: 0,
+            radius: 2.5,
+            embed: 0.3,
+            roughness: 0.25,
+        }
+    }
+}
+
+impl TerrainFeature for Boulder {
+    fn radius(&self) -> f32 {
+        // The rock is raised up to its radius above the center it is placed at.
+        self.radius * (2.0 + self.roughness) + 1.0
+    }
+
+    fn stamp(&self, center: Vec3, pos: Vec3, voxel: Voxel, seed: u32) -> Voxel {
+        let rock = center + Vec3::Y * self.radius * (1.0 - 2.0 * self.embed.clamp(0.0, 1.0));
+        let radius = self.radius * (1.0 + gradient_noise(pos * 0.2, seed) * self.roughness);
+        let density = (radius - pos.distance(rock) + 0.5).clamp(0.0, 1.0);
+        if density <= voxel.density() {
+            return voxel;
+        }
+        match density >= 0.5 {
+            true => Voxel::new(self.material, density),
+            false => voxel.with_density(density),
+        }
+    }
+}
+
+/// Maps a hash to a number from 0 up to, but excluding, 1.
+fn unit(hash: u32) -> f32 {
+    (hash >> 8) as f32 / (1 << 24) as f32
+}
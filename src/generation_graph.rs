@@ -0,0 +1,181 @@
+use std::io;
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    tasks::{block_on, poll_once, AsyncComputeTaskPool, Task},
+    utils::HashSet,
+};
+
+use crate::{
+    bundles::volumetric_bundle::VolumetricBundle,
+    data::{
+        chunk_coord::ChunkCoord,
+        generation_graph::{GenerationGraph, GraphVolume},
+        voxel::Voxel,
+        voxel_material::VoxelMaterial,
+        voxel_world::VoxelWorldConfig,
+    },
+    invalid_data,
+    streaming::ChunkData,
+    CHUNK_SZ_3,
+};
+
+/// Loads [`GenerationGraph`]s from `.graph.ron` files, e.g.
+///
+/// ```ron
+/// (
+///     seed: 7,
+///     nodes: [
+///         Noise(offset: 16.0, amplitude: 24.0, frequency: 0.004, octaves: 5),
+///         DomainWarp(amplitude: 6.0, frequency: 0.02),
+///         Terrace(step: 6.0, sharpness: 3.0),
+///         CaveCarve(ridge_threshold: 0.92, min_depth: 6.0),
+///         MaterialAssign(layers: [(1.0, 0), (4.0, 1), (1e9, 2)]),
+///     ],
+/// )
+/// ```
+#[derive(Default)]
+pub struct GenerationGraphLoader;
+
+impl AssetLoader for GenerationGraphLoader {
+    type Asset = GenerationGraph;
+    type Settings = ();
+    type Error = io::Error;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> io::Result<GenerationGraph> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        GenerationGraph::from_ron(&bytes).map_err(invalid_data)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["graph.ron"]
+    }
+}
+
+/// A chunk whose voxels are generated from a [`GenerationGraph`] on the [`AsyncComputeTaskPool`],
+/// at its [`ChunkCoord`] placed by the [`VoxelWorldConfig`]. Once generated, the chunk gains a
+/// [`VolumetricBundle`] and its [`Transform`], and its [`VoxelMaterial`] is replaced every time the
+/// graph asset is modified.
+#[derive(Component)]
+pub struct GraphChunk {
+    pub graph: Handle<GenerationGraph>,
+    /// Whether the voxels are out of date with the graph.
+    stale: bool,
+    task: Option<Task<Vec<Voxel>>>,
+}
+
+impl GraphChunk {
+    pub fn new(graph: Handle<GenerationGraph>) -> Self {
+        Self {
+            graph,
+            stale: true,
+            task: None,
+        }
+    }
+
+    /// Whether the chunk waits for its graph to load or is being generated.
+    pub fn is_generating(&self) -> bool {
+        self.stale || self.task.is_some()
+    }
+
+    /// Starts generating the chunks whose graph is loaded and their voxels out of date, dropping
+    /// the generation of voxels that went out of date in the meantime, and inserts the voxels of
+    /// the chunks generated.
+    pub fn generate(
+        mut commands: Commands,
+        config: Res<VoxelWorldConfig>,
+        graphs: Res<Assets<GenerationGraph>>,
+        mut graph_events: EventReader<AssetEvent<GenerationGraph>>,
+        mut chunk_query: Query<(Entity, &mut GraphChunk, &ChunkCoord, Has<VoxelMaterial>)>,
+    ) {
+        let modified = graph_events
+            .read()
+            .filter_map(|event| match event {
+                AssetEvent::Modified { id } => Some(*id),
+                _ => None,
+            })
+            .collect::<HashSet<_>>();
+        let task_pool = AsyncComputeTaskPool::get();
+
+        for (entity, mut chunk, coord, has_voxels) in chunk_query.iter_mut() {
+            if modified.contains(&chunk.graph.id()) {
+                chunk.stale = true;
+            }
+
+            if chunk.stale {
+                if let Some(graph) = graphs.get(&chunk.graph) {
+                    let graph = graph.clone();
+                    let origin = config.chunk_to_voxel(*coord);
+                    chunk.task = Some(task_pool.spawn(async move { graph.generate_chunk(origin) }));
+                    chunk.stale = false;
+                }
+            }
+
+            let Some(task) = chunk.task.as_mut() else {
+                continue;
+            };
+            let Some(voxels) = block_on(poll_once(task)) else {
+                continue;
+            };
+            chunk.task = None;
+
+            let voxel_material = VoxelMaterial::from(ChunkData {
+                voxels,
+                chunk_size: CHUNK_SZ_3 as u32,
+            });
+            if has_voxels {
+                commands.entity(entity).insert(voxel_material);
+            } else {
+                commands.entity(entity).insert((
+                    VolumetricBundle::new(voxel_material),
+                    config.chunk_transform(*coord),
+                ));
+            }
+        }
+    }
+}
+
+/// Loads [`GenerationGraph`] assets and generates the [`GraphChunk`]s on the CPU and the
+/// [`GraphVolume`]s on the GPU from them, again whenever a graph is modified.
+pub struct GenerationGraphPlugin;
+
+impl GenerationGraphPlugin {
+    /// Generates the [`GraphVolume`]s of the modified graphs again.
+    pub fn regenerate_volumes(
+        mut graph_events: EventReader<AssetEvent<GenerationGraph>>,
+        mut volume_query: Query<&mut GraphVolume>,
+    ) {
+        let modified = graph_events
+            .read()
+            .filter_map(|event| match event {
+                AssetEvent::Modified { id } => Some(*id),
+                _ => None,
+            })
+            .collect::<HashSet<_>>();
+        if modified.is_empty() {
+            return;
+        }
+
+        for mut graph_volume in volume_query.iter_mut() {
+            if modified.contains(&graph_volume.graph.id()) {
+                graph_volume.set_changed();
+            }
+        }
+    }
+}
+
+impl Plugin for GenerationGraphPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<GenerationGraph>()
+            .init_asset_loader::<GenerationGraphLoader>()
+            .init_resource::<VoxelWorldConfig>()
+            .add_systems(Update, (GraphChunk::generate, Self::regenerate_volumes));
+    }
+}
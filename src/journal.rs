@@ -0,0 +1,308 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+};
+
+use bevy::{prelude::*, utils::HashSet};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    data::{
+        chunk_coord::ChunkCoord,
+        voxel::Voxel,
+        voxel_edit::{ChunkEdited, VoxelEdit},
+    },
+    snapshot::WorldSnapshot,
+};
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub(crate) enum JournalEdit {
+    Set {
+        position: [u32; 3],
+        flags: u32,
+        density: f32,
+        /// Missing from the journals written before voxels had an emissive color.
+        #[serde(default)]
+        emissive: u32,
+    },
+    Sphere {
+        center: [f32; 3],
+        radius: f32,
+        density: f32,
+    },
+}
+
+/// One line of the journal.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub(crate) struct JournalRecord {
+    chunk: [i32; 3],
+    edit: JournalEdit,
+}
+
+impl From<(ChunkCoord, VoxelEdit)> for JournalRecord {
+    fn from((coord, edit): (ChunkCoord, VoxelEdit)) -> Self {
+        let edit = match edit {
+            VoxelEdit::Set { position, voxel } => JournalEdit::Set {
+                position: position.to_array(),
+                flags: voxel.flags(),
+                density: voxel.density(),
+                emissive: voxel.emissive_bits(),
+            },
+            VoxelEdit::Sphere {
+                center,
+                radius,
+                density,
+            } => JournalEdit::Sphere {
+                center: center.to_array(),
+                radius,
+                density,
+            },
+        };
+
+        Self {
+            chunk: coord.0.to_array(),
+            edit,
+        }
+    }
+}
+
+impl From<JournalRecord> for (ChunkCoord, VoxelEdit) {
+    fn from(record: JournalRecord) -> Self {
+        let edit = match record.edit {
+            JournalEdit::Set {
+                position,
+                flags,
+                density,
+                emissive,
+            } => VoxelEdit::Set {
+                position: UVec3::from_array(position),
+                voxel: Voxel::new(flags, density).with_emissive_bits(emissive),
+            },
+            JournalEdit::Sphere {
+                center,
+                radius,
+                density,
+            } => VoxelEdit::Sphere {
+                center: Vec3::from_array(center),
+                radius,
+                density,
+            },
+        };
+
+        (ChunkCoord(IVec3::from_array(record.chunk)), edit)
+    }
+}
+
+/// Sent once chunks are durably saved, e.g. by [`WorldSnapshot::save`] or
+/// [`RegionFile::write`](crate::region::RegionFile::write), for
+/// [`EditJournal::compact_saved`] to drop the edits journaled for them.
+#[derive(Event, Clone, Debug, Default)]
+pub struct ChunksSaved {
+    pub coords: HashSet<ChunkCoord>,
+    /// The [`EditJournal::position`] when the voxels of the chunks were captured. The edits
+    /// recorded since, which the saved voxels miss, are kept.
+    pub journal_position: u64,
+}
+
+impl ChunksSaved {
+    /// The chunks of `snapshot`, once it is saved.
+    pub fn from_snapshot(snapshot: &WorldSnapshot) -> Self {
+        Self {
+            coords: snapshot.chunks.iter().map(|chunk| chunk.coord).collect(),
+            journal_position: snapshot.journal_position,
+        }
+    }
+}
+
+/// A write-ahead journal of the [`VoxelEdit`]s applied since the chunks were last saved. Each
+/// [`ChunkEdited`] is appended and synced to disk as it happens, so that replaying the journal over
+/// the last [`WorldSnapshot`] recovers every edit after a crash.
+#[derive(Resource)]
+pub struct EditJournal {
+    path: PathBuf,
+    file: File,
+    /// The position of each record of the file, in order.
+    positions: Vec<u64>,
+    next_position: u64,
+}
+
+impl EditJournal {
+    /// Opens the journal at `path` for appending, creating it if needed. A partially written last
+    /// record, left by a crash while recording it, is truncated so that the next record starts on
+    /// its own line.
+    pub fn open(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+
+        let contents = fs::read(&path)?;
+        let complete = contents
+            .iter()
+            .rposition(|byte| *byte == b'\n')
+            .map_or(0, |newline| newline + 1);
+        if complete < contents.len() {
+            file.set_len(complete as u64)?;
+            file.sync_data()?;
+        }
+        let records = contents[..complete]
+            .iter()
+            .filter(|byte| **byte == b'\n')
+            .count() as u64;
+
+        Ok(Self {
+            path,
+            file,
+            positions: (0..records).collect(),
+            next_position: records,
+        })
+    }
+
+    /// The path of the journal file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The position of the next recorded edit, to capture along with the voxels of the chunks
+    /// being saved so that [`EditJournal::compact`] only drops the edits applied to them.
+    pub fn position(&self) -> u64 {
+        self.next_position
+    }
+
+    /// Appends `edit` of the chunk at `coord` and flushes it to disk.
+    pub fn record(&mut self, coord: ChunkCoord, edit: VoxelEdit) -> io::Result<()> {
+        let mut line = ron::to_string(&JournalRecord::from((coord, edit)))
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        line.push('\n');
+
+        self.file.write_all(line.as_bytes())?;
+        self.file.sync_data()?;
+
+        self.positions.push(self.next_position);
+        self.next_position += 1;
+        Ok(())
+    }
+
+    /// Reads the edits of the journal at `path` in the order they were recorded. A partially
+    /// written last record, left by a crash while recording it, is ignored.
+    pub fn read(path: impl AsRef<Path>) -> io::Result<Vec<(ChunkCoord, VoxelEdit)>> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err),
+        };
+
+        let lines = BufReader::new(file)
+            .lines()
+            .collect::<io::Result<Vec<_>>>()?;
+        let mut edits = Vec::with_capacity(lines.len());
+
+        for (index, line) in lines.iter().enumerate() {
+            match ron::from_str::<JournalRecord>(line) {
+                Ok(record) => edits.push(record.into()),
+                Err(_) if index + 1 == lines.len() => break,
+                Err(err) => return Err(io::Error::new(io::ErrorKind::InvalidData, err)),
+            }
+        }
+
+        Ok(edits)
+    }
+
+    /// Applies the edits of the journal at `path` to the chunks of `snapshot` with the same
+    /// [`ChunkCoord`], returning the number of edits applied.
+    pub fn replay(path: impl AsRef<Path>, snapshot: &mut WorldSnapshot) -> io::Result<usize> {
+        let mut applied = 0;
+
+        for (coord, edit) in Self::read(path)? {
+            if let Some(chunk) = snapshot
+                .chunks
+                .iter_mut()
+                .find(|chunk| chunk.coord == coord)
+            {
+                edit.apply(&mut chunk.voxel_material);
+                applied += 1;
+            }
+        }
+
+        Ok(applied)
+    }
+
+    /// Drops the edits recorded before `position` of the chunks for which `is_saved` returns
+    /// true, e.g. every chunk after a [`WorldSnapshot::save`]. The journal is rewritten to a
+    /// temporary file that replaces it once synced, so a crash while compacting leaves the old
+    /// journal intact.
+    pub fn compact(
+        &mut self,
+        position: u64,
+        is_saved: impl Fn(ChunkCoord) -> bool,
+    ) -> io::Result<()> {
+        let compacted_path = self.path.with_extension("compact");
+        let mut positions = Vec::with_capacity(self.positions.len());
+
+        {
+            let mut compacted = File::create(&compacted_path)?;
+            for ((coord, edit), record_position) in
+                Self::read(&self.path)?.into_iter().zip(&self.positions)
+            {
+                if *record_position < position && is_saved(coord) {
+                    continue;
+                }
+                let record = ron::to_string(&JournalRecord::from((coord, edit)))
+                    .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+                writeln!(compacted, "{record}")?;
+                positions.push(*record_position);
+            }
+            compacted.sync_all()?;
+        }
+
+        fs::rename(&compacted_path, &self.path)?;
+        self.file = OpenOptions::new().append(true).open(&self.path)?;
+        self.positions = positions;
+        Ok(())
+    }
+
+    /// Compacts the [`EditJournal`], if there is one, for every [`ChunksSaved`].
+    pub fn compact_saved(journal: Option<ResMut<Self>>, mut saved: EventReader<ChunksSaved>) {
+        let Some(mut journal) = journal else {
+            saved.clear();
+            return;
+        };
+
+        for ChunksSaved {
+            coords,
+            journal_position,
+        } in saved.read()
+        {
+            if let Err(err) = journal.compact(*journal_position, |coord| coords.contains(&coord)) {
+                error!("Failed to compact the edit journal: {err}");
+            }
+        }
+    }
+
+    /// Records every [`ChunkEdited`] in the [`EditJournal`], if there is one. Edits of volumes
+    /// without a [`ChunkCoord`] are skipped, as they could not be told apart when replayed.
+    pub fn record_edits(
+        journal: Option<ResMut<Self>>,
+        mut edited: EventReader<ChunkEdited>,
+        chunk_query: Query<(), With<ChunkCoord>>,
+    ) {
+        let Some(mut journal) = journal else {
+            edited.clear();
+            return;
+        };
+
+        for ChunkEdited {
+            entity,
+            coord,
+            edit,
+            ..
+        } in edited.read()
+        {
+            if !chunk_query.contains(*entity) {
+                continue;
+            }
+            if let Err(err) = journal.record(*coord, *edit) {
+                error!("Failed to journal the edit of chunk {:?}: {err}", coord.0);
+            }
+        }
+    }
+}
@@ -0,0 +1,405 @@
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+use bevy::{
+    prelude::*,
+    render::{
+        extract_resource::{ExtractResource, ExtractResourcePlugin},
+        render_resource::{BindGroupLayoutEntry, BindingType, Buffer},
+        Extract, Render, RenderApp, RenderSet,
+    },
+    utils::HashMap,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bundles::volumetric_bundle::Volumetric,
+    data::{
+        chunk_coord::ChunkCoord, gpu_voxel_material::GpuVoxelMaterial,
+        voxel_material::VoxelMaterialComponents,
+    },
+    render::voxel_mesh_compute_pipeline::VoxelMeshComputePipeline,
+};
+
+/// Dumps the bind group layouts of the meshing pipeline and the buffers bound to them for each
+/// chunk into a [`LayoutReport`] whenever they change, and compares it with the expectations
+/// committed at [`LayoutSnapshotSettings::expected`], logging a warning for every difference.
+/// Only available with the `validate-gpu` feature, which adds it to the
+/// [`GpuReadbackPlugin`](crate::GpuReadbackPlugin).
+///
+/// Chunks are identified by their [`Name`], or else their [`ChunkCoord`], so that the reports of
+/// two runs of the same scene compare. Only the chunks of the expectations are compared, so that
+/// they may hold the layouts alone, as the committed `snapshots/layouts.ron` does, or along with
+/// the chunks of a reference scene.
+pub struct LayoutSnapshotPlugin;
+
+impl Plugin for LayoutSnapshotPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(ExtractResourcePlugin::<LayoutSnapshotSettings>::default())
+            .init_resource::<LayoutSnapshotSettings>();
+    }
+
+    fn finish(&self, app: &mut App) {
+        app.sub_app_mut(RenderApp)
+            .init_resource::<LayoutSnapshot>()
+            .add_systems(ExtractSchedule, LayoutSnapshot::extract)
+            .add_systems(
+                Render,
+                LayoutSnapshot::report.in_set(RenderSet::PrepareBindGroups),
+            );
+    }
+}
+
+#[derive(Resource, Clone, Debug, ExtractResource)]
+pub struct LayoutSnapshotSettings {
+    /// The committed report to compare with. A warning is logged while it does not exist.
+    pub expected: PathBuf,
+    /// Where each new report is written, e.g. to be copied over the expectations once a change
+    /// of layout is intended.
+    pub output: Option<PathBuf>,
+}
+
+impl Default for LayoutSnapshotSettings {
+    fn default() -> Self {
+        Self {
+            expected: PathBuf::from("snapshots/layouts.ron"),
+            output: Some(PathBuf::from("target/layouts.ron")),
+        }
+    }
+}
+
+/// The bind group layouts and per chunk buffers of the meshing pipeline, as written in RON.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct LayoutReport {
+    pub layouts: BTreeMap<String, Vec<LayoutEntryReport>>,
+    pub chunks: BTreeMap<String, Vec<BufferReport>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct LayoutEntryReport {
+    pub binding: u32,
+    pub visibility: String,
+    /// The binding type, with its minimum size for buffers.
+    pub ty: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BufferReport {
+    pub name: String,
+    /// Binding of the buffer in the bind group 1 layout, if it is bound to it.
+    pub binding: Option<u32>,
+    pub size: u64,
+    pub usage: String,
+}
+
+impl LayoutEntryReport {
+    fn new(entry: &BindGroupLayoutEntry) -> Self {
+        Self {
+            binding: entry.binding,
+            visibility: format!("{:?}", entry.visibility),
+            ty: format!("{:?}", entry.ty),
+        }
+    }
+}
+
+impl BufferReport {
+    fn new(name: &str, binding: Option<u32>, buffer: &Buffer) -> Self {
+        Self {
+            name: name.to_string(),
+            binding,
+            size: buffer.size(),
+            usage: format!("{:?}", buffer.usage()),
+        }
+    }
+}
+
+impl LayoutReport {
+    /// Reports the layouts of the meshing pipeline and the buffers of each chunk named in
+    /// `names`.
+    pub fn new(
+        gpu_voxel_materials: &VoxelMaterialComponents<GpuVoxelMaterial>,
+        names: &HashMap<Entity, String>,
+    ) -> Self {
+        let layout =
+            |entries: &[BindGroupLayoutEntry]| entries.iter().map(LayoutEntryReport::new).collect();
+        let layouts = BTreeMap::from([
+            (
+                "VoxelMeshComputePipeline::bind_group_1_layout".to_string(),
+                layout(&VoxelMeshComputePipeline::bind_group_1_layout_entries()),
+            ),
+            (
+                "VoxelMeshComputePipeline::page_table_layout".to_string(),
+                layout(&VoxelMeshComputePipeline::page_table_layout_entries()),
+            ),
+        ]);
+
+        let chunks = names
+            .iter()
+            .filter_map(|(entity, name)| {
+                let gpu_voxel_material = gpu_voxel_materials.get(entity)?;
+                Some((name.clone(), Self::buffers(gpu_voxel_material)))
+            })
+            .collect();
+
+        Self { layouts, chunks }
+    }
+
+    /// The buffers of a chunk uploaded so far, with their binding in the bind group 1 layout.
+    fn buffers(gpu_voxel_material: &GpuVoxelMaterial) -> Vec<BufferReport> {
+        [
+            (
+                "edge_table",
+                Some(0),
+                gpu_voxel_material.edge_table_buffer.buffer(),
+            ),
+            (
+                "tri_table",
+                Some(1),
+                gpu_voxel_material.tri_table_buffer.buffer(),
+            ),
+            ("voxels", Some(2), gpu_voxel_material.voxels_buffer.buffer()),
+            (
+                "atomics",
+                Some(3),
+                gpu_voxel_material.atomics_buffer.buffer(),
+            ),
+            (
+                "vertices",
+                Some(4),
+                gpu_voxel_material.vertices_buffer.buffer(),
+            ),
+            (
+                "normals",
+                Some(5),
+                gpu_voxel_material.normals_buffer.buffer(),
+            ),
+            (
+                "indices",
+                Some(6),
+                gpu_voxel_material.indices_buffer.buffer(),
+            ),
+            ("uvs", Some(7), gpu_voxel_material.uvs_buffer.buffer()),
+            (
+                "tangents",
+                Some(8),
+                gpu_voxel_material.tangents_buffer.buffer(),
+            ),
+            (
+                "dual_contouring_params",
+                Some(9),
+                gpu_voxel_material.dual_contouring_params_buffer.buffer(),
+            ),
+            (
+                "iso_surface_params",
+                Some(10),
+                gpu_voxel_material.iso_surface_params_buffer.buffer(),
+            ),
+            (
+                "clip_planes",
+                Some(11),
+                gpu_voxel_material.clip_planes_buffer.buffer(),
+            ),
+            (
+                "adaptive_resolution",
+                Some(12),
+                gpu_voxel_material.adaptive_resolution_buffer.buffer(),
+            ),
+            (
+                "emissives",
+                Some(13),
+                gpu_voxel_material.emissives_buffer.buffer(),
+            ),
+            (
+                "vertices_staging",
+                None,
+                Some(&gpu_voxel_material.vertices_staging_buffer),
+            ),
+            (
+                "atomics_staging",
+                None,
+                Some(&gpu_voxel_material.atomics_staging_buffer),
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(name, binding, buffer)| Some(BufferReport::new(name, binding, buffer?)))
+        .collect()
+    }
+
+    /// Describes how the report differs from `expected`, one line per layout entry or buffer.
+    /// Chunks missing from `expected` are not compared.
+    pub fn diff(&self, expected: &Self) -> Vec<String> {
+        let mut differences = Vec::new();
+        diff_maps(
+            &self.layouts,
+            &expected.layouts,
+            "layout",
+            true,
+            &mut differences,
+        );
+        diff_maps(
+            &self.chunks,
+            &expected.chunks,
+            "chunk",
+            false,
+            &mut differences,
+        );
+        differences
+    }
+
+    /// The buffers bound to a layout entry whose minimum size they don't reach, which would fail
+    /// validation when the bind group is created.
+    pub fn undersized_buffers(&self) -> Vec<String> {
+        let min_sizes = VoxelMeshComputePipeline::bind_group_1_layout_entries()
+            .iter()
+            .filter_map(|entry| match entry.ty {
+                BindingType::Buffer {
+                    min_binding_size: Some(min_size),
+                    ..
+                } => Some((entry.binding, min_size.get())),
+                _ => None,
+            })
+            .collect::<HashMap<_, _>>();
+
+        let mut undersized = Vec::new();
+        for (chunk, buffers) in &self.chunks {
+            for buffer in buffers {
+                let Some(binding) = buffer.binding else {
+                    continue;
+                };
+                let Some(min_size) = min_sizes.get(&binding) else {
+                    continue;
+                };
+                if buffer.size < *min_size {
+                    undersized.push(format!(
+                        "{chunk}: {} is {} bytes, binding {binding} needs at least {min_size}",
+                        buffer.name, buffer.size
+                    ));
+                }
+            }
+        }
+        undersized
+    }
+}
+
+/// Compares the entries of `reported` and `expected` by key, then element by element, along with
+/// the entries only reported if `unexpected` is set.
+fn diff_maps<T: PartialEq + std::fmt::Debug>(
+    reported: &BTreeMap<String, Vec<T>>,
+    expected: &BTreeMap<String, Vec<T>>,
+    kind: &str,
+    unexpected: bool,
+    differences: &mut Vec<String>,
+) {
+    for (key, expected_items) in expected {
+        let Some(items) = reported.get(key) else {
+            differences.push(format!("{kind} {key} is missing"));
+            continue;
+        };
+        for index in 0..items.len().max(expected_items.len()) {
+            match (items.get(index), expected_items.get(index)) {
+                (Some(item), Some(expected_item)) if item == expected_item => {}
+                (item, expected_item) => differences.push(format!(
+                    "{kind} {key} [{index}]: expected {expected_item:?}, found {item:?}"
+                )),
+            }
+        }
+    }
+    if !unexpected {
+        return;
+    }
+    for key in reported.keys().filter(|key| !expected.contains_key(*key)) {
+        differences.push(format!("{kind} {key} is not expected"));
+    }
+}
+
+/// The names of the chunks of the render world, and the last report written.
+#[derive(Resource, Default)]
+pub struct LayoutSnapshot {
+    names: HashMap<Entity, String>,
+    last: Option<LayoutReport>,
+}
+
+impl LayoutSnapshot {
+    /// Names the volumetric entities by their [`Name`], or else their [`ChunkCoord`], or else
+    /// their entity. Entities sharing a name are numbered in the order they were spawned.
+    #[allow(clippy::type_complexity)]
+    pub fn extract(
+        mut snapshot: ResMut<Self>,
+        chunk_query: Extract<Query<(Entity, Option<&Name>, Option<&ChunkCoord>), With<Volumetric>>>,
+    ) {
+        let mut chunks = chunk_query
+            .iter()
+            .map(|(entity, name, coord)| {
+                let name = match (name, coord) {
+                    (Some(name), _) => name.to_string(),
+                    (None, Some(coord)) => format!("chunk {}", coord.0),
+                    (None, None) => format!("volume {entity}"),
+                };
+                (entity, name)
+            })
+            .collect::<Vec<_>>();
+        chunks.sort_by_key(|(entity, _)| *entity);
+
+        let mut counts = HashMap::<String, usize>::default();
+        snapshot.names = chunks
+            .into_iter()
+            .map(|(entity, name)| {
+                let count = counts.entry(name.clone()).or_default();
+                *count += 1;
+                match *count {
+                    1 => (entity, name),
+                    count => (entity, format!("{name} #{count}")),
+                }
+            })
+            .collect();
+    }
+
+    /// Writes the report whenever it changes and compares it with the expected one.
+    pub fn report(
+        mut snapshot: ResMut<Self>,
+        settings: Res<LayoutSnapshotSettings>,
+        gpu_voxel_materials: Res<VoxelMaterialComponents<GpuVoxelMaterial>>,
+    ) {
+        let report = LayoutReport::new(&gpu_voxel_materials, &snapshot.names);
+        if snapshot.last.as_ref() == Some(&report) {
+            return;
+        }
+
+        for undersized in report.undersized_buffers() {
+            warn!("Layout snapshot: {undersized}");
+        }
+
+        if let Some(output) = &settings.output {
+            let written = ron::ser::to_string_pretty(&report, default())
+                .map_err(|err| err.to_string())
+                .and_then(|ron| {
+                    if let Some(parent) = output.parent() {
+                        fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+                    }
+                    fs::write(output, ron).map_err(|err| err.to_string())
+                });
+            if let Err(err) = written {
+                warn!("Failed to write the layout snapshot to {output:?}: {err}");
+            }
+        }
+
+        match fs::read_to_string(&settings.expected) {
+            Ok(expected) => match ron::from_str::<LayoutReport>(&expected) {
+                Ok(expected) => {
+                    for difference in report.diff(&expected) {
+                        warn!(
+                            "Layout snapshot differs from {:?}: {difference}",
+                            settings.expected
+                        );
+                    }
+                }
+                Err(err) => warn!("Failed to parse {:?}: {err}", settings.expected),
+            },
+            Err(err) => warn!(
+                "No layout snapshot compared, failed to read {:?}: {err}",
+                settings.expected
+            ),
+        }
+
+        snapshot.last = Some(report);
+    }
+}
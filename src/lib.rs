@@ -1,81 +1,496 @@
+pub mod ambient_occlusion;
+pub mod batching;
+pub mod border_normals;
 pub mod bundles;
 pub mod channels;
+#[cfg(feature = "readback")]
+pub mod chunk_analysis;
+#[cfg(feature = "persistence")]
+pub mod chunk_compression;
+#[cfg(feature = "gpu-driven")]
+pub mod chunk_culling;
+pub mod chunk_hash;
+pub mod chunk_hooks;
+#[cfg(feature = "persistence")]
+pub mod chunk_migration;
+#[cfg(feature = "editing")]
+pub mod chunk_splitting;
+#[cfg(feature = "gpu-driven")]
+pub mod cluster_bounds;
+pub mod compaction;
 pub mod data;
+#[cfg(feature = "gpu-driven")]
+pub mod depth_pyramid;
+pub mod diagnostics;
+pub mod distance_field;
+#[cfg(feature = "editing")]
+pub mod edit_locks;
+#[cfg(feature = "editor-gizmos")]
+pub mod editor_gizmos;
+pub mod emissive;
+pub mod erosion;
+pub mod feature_scattering;
+pub mod generation_graph;
+#[cfg(all(feature = "persistence", feature = "editing"))]
+pub mod journal;
+#[cfg(feature = "validate-gpu")]
+pub mod layout_snapshot;
+#[cfg(feature = "raymarch")]
+pub mod light_probes;
+#[cfg(feature = "persistence")]
+pub mod lod_pyramid;
+#[cfg(feature = "readback")]
+pub mod mesh_gizmos;
+pub mod minimap;
+pub mod occupancy_grid;
+pub mod particle_collision;
+pub mod pipeline_timeline;
+#[cfg(feature = "readback")]
+pub mod prop_candidates;
+#[cfg(feature = "readback")]
+pub mod readback_capture;
+#[cfg(feature = "persistence")]
+pub mod region;
 pub mod render;
+#[cfg(all(feature = "persistence", feature = "editing"))]
+pub mod replay;
+#[cfg(feature = "raymarch")]
+pub mod sdf_shadows;
+#[cfg(feature = "persistence")]
+pub mod snapshot;
+pub mod splat_map;
+pub mod streaming;
+pub mod terrain;
+#[cfg(feature = "validate-gpu")]
+pub mod validation;
+pub mod vertex_cache;
+pub mod volume_slice;
+pub mod volume_statistics;
+#[cfg(feature = "readback")]
+pub mod voxel_picking;
+pub mod voxel_scene;
+pub mod world_gen;
+pub mod world_statistics;
+pub use voxel_core as core;
+
 use bevy::{
-    ecs::{
-        query::ROQueryItem,
-        system::{lifetimeless::SRes, SystemParamItem},
-        world::Command,
-    },
     prelude::*,
     render::{
-        extract_component::{ExtractComponent, ExtractComponentPlugin},
-        render_graph::{self, NodeRunError, RenderGraph, RenderLabel},
-        render_phase::{PhaseItem, RenderCommand, RenderCommandResult, TrackedRenderPass},
-        render_resource::{binding_types::storage_buffer, *},
-        renderer::{RenderContext, RenderDevice, RenderQueue},
-        Extract, Render, RenderApp, RenderSet,
+        extract_component::ExtractComponentPlugin,
+        extract_resource::{ExtractResource, ExtractResourcePlugin},
+        render_graph::RenderGraph,
+        render_resource::*,
+        renderer::{RenderAdapterInfo, RenderDevice},
+        Render, RenderApp, RenderSet,
     },
-    utils::{info, HashMap},
 };
-use bundles::volumetric_bundle::Volumetric;
-use channels::{MainWorldReceiver, RenderWorldSender};
-use crossbeam_channel::{Receiver, Sender};
+use bundles::volumetric_bundle::{
+    MaterialSplit, MeshCapping, SeamlessNormals, Volumetric, VoxelComputeSuspended,
+};
+use channels::{
+    GpuChunkStateReceiver, GpuChunkStateSender, IsoSurfaceReceiver, IsoSurfaceSender,
+    MainWorldReceiver, PendingReadbacks, RawMeshReceiver, RawMeshSender, ReadbackFailed,
+    ReadbackFailedReceiver, ReadbackFailedSender, ReadbackPolicy, ReadbackRetries,
+    RenderWorldSender, VertexReadback, VoxelDataVersion,
+};
+#[cfg(feature = "persistence")]
+use chunk_migration::ChunkMigrations;
 use data::{
-    gpu_voxel_material::GpuVoxelMaterial,
+    adaptive_resolution::AdaptiveResolution,
+    boundary_mode::BoundaryMode,
+    chunk_coord::ChunkCoord,
+    chunk_priority::{ChunkPriority, ChunkPriorityFn},
+    chunk_retirement::{
+        ChunkRetirementExt, ChunkRetiring, RetiredChunkReceiver, RetiredChunkSender, RetiringChunks,
+    },
+    clip_planes::ClipPlanes,
+    compressed_voxels::CompressedUpload,
+    dual_contouring::DualContouringSettings,
+    edge_interpolation::EdgeInterpolation,
+    gpu_chunk_state::{GpuChunkState, GpuChunkStates},
+    gpu_compressed_voxels::GpuCompressedVoxels,
+    gpu_fixed_output_slots::{GpuFixedOutputSlots, GpuSlotCompaction},
+    gpu_iso_surface::GpuIsoSurfaces,
+    gpu_neighbor_voxels::GpuNeighborVoxels,
+    gpu_procedural_volume::{GenerationQueue, GpuProceduralVolume},
+    gpu_virtual_volume::GpuVirtualVolume,
+    gpu_voxel_material::{ExtractedVoxelMaterial, GpuVoxelMaterial},
     gpu_voxel_material_bind_group::GpuVoxelMaterialBindGroups,
+    iso_surface::IsoSurfaces,
+    material_split::MaterialIndexRanges,
+    mesh_buffer_sizing::MeshBufferSizing,
+    meshing_algorithm::MeshingAlgorithm,
+    procedural_volume::GenerationBudget,
+    raw_mesh_data::{GpuRawMeshData, RawMeshData},
+    shader_platform::ShaderPlatform,
+    upload_budget::{UploadBudget, UploadQueue},
+    virtual_volume::VirtualVolume,
+    voxel_layers::VoxelLayers,
     voxel_material::{VoxelMaterial, VoxelMaterialComponents},
+    voxel_world::{ChunkEntities, DirtyRegion, VoxelWorldConfig},
 };
-use render::voxel_mesh_compute_pipeline::{
-    VoxelMeshComputeNode, VoxelMeshComputeNodeLabel, VoxelMeshComputePipeline,
+#[cfg(feature = "editing")]
+use data::{
+    voxel_decal::{DecalProjected, ProjectDecal},
+    voxel_edit::{AppliedEditBatches, ChunkEdit, ChunkEditBatch, ChunkEdited},
+    voxel_layers::LayerEdit,
+    voxel_structure::{PlaceStructure, StructurePlaced, VoxelStructure},
+};
+#[cfg(feature = "editing")]
+use edit_locks::EditLocks;
+#[cfg(all(feature = "persistence", feature = "editing"))]
+use journal::{ChunksSaved, EditJournal};
+use render::{
+    node_ordering::VoxelNodeOrdering,
+    post_mesh_compute_pass::PostMeshComputePasses,
+    procedural_generation_compute_pipeline::{
+        ProceduralGenerationComputeNode, ProceduralGenerationComputeNodeLabel,
+        ProceduralGenerationComputePipeline,
+    },
+    shaders::load_shader_modules,
+    slot_compaction_compute_pipeline::SlotCompactionComputePipeline,
+    voxel_decompression_compute_pipeline::{
+        VoxelDecompressionComputeNode, VoxelDecompressionComputeNodeLabel,
+        VoxelDecompressionComputePipeline,
+    },
+    voxel_mesh_compute_pipeline::{
+        DirtyMeshes, VoxelMeshComputeNode, VoxelMeshComputeNodeLabel, VoxelMeshComputePipeline,
+        VoxelMeshPipelineId, VoxelMeshPrefixSumPipelineIds,
+    },
 };
 
-const CHUNK_SZ: usize = 32;
-const CHUNK_SZ_2: usize = CHUNK_SZ * CHUNK_SZ;
-const CHUNK_SZ_3: usize = CHUNK_SZ * CHUNK_SZ * CHUNK_SZ;
+use voxel_core::{CHUNK_SZ, CHUNK_SZ_2, CHUNK_SZ_3};
+
+/// Freezes the meshing of every volumetric entity while set.
+#[derive(Resource, Clone, Copy, Default, ExtractResource)]
+pub struct VoxelComputePaused(pub bool);
 
 pub struct GpuReadbackPlugin;
 
 impl Plugin for GpuReadbackPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins((ExtractComponentPlugin::<Volumetric>::default(),))
-            .add_systems(Startup, VoxelMaterial::generate_random)
-            .add_systems(Update, MainWorldReceiver::receive);
+        load_shader_modules(app);
+
+        let world = app.world_mut();
+        world
+            .register_component_hooks::<VoxelMaterial>()
+            .on_insert(VoxelDataVersion::on_insert);
+        world
+            .register_component_hooks::<VirtualVolume>()
+            .on_insert(VoxelDataVersion::on_insert);
+        world
+            .register_component_hooks::<ChunkCoord>()
+            .on_insert(ChunkEntities::on_insert)
+            .on_remove(ChunkEntities::on_remove);
+
+        #[cfg(feature = "validate-gpu")]
+        app.add_plugins((
+            validation::GpuValidationPlugin,
+            layout_snapshot::LayoutSnapshotPlugin,
+        ));
+
+        app.add_plugins((
+            ExtractComponentPlugin::<Volumetric>::default(),
+            (
+                ExtractComponentPlugin::<MeshingAlgorithm>::default(),
+                ExtractComponentPlugin::<BoundaryMode>::default(),
+                ExtractComponentPlugin::<EdgeInterpolation>::default(),
+            ),
+            (
+                ExtractComponentPlugin::<MeshCapping>::default(),
+                ExtractComponentPlugin::<MaterialSplit>::default(),
+                ExtractComponentPlugin::<SeamlessNormals>::default(),
+            ),
+            ExtractComponentPlugin::<RawMeshData>::default(),
+            ExtractComponentPlugin::<ChunkPriority>::default(),
+            ExtractComponentPlugin::<CompressedUpload>::default(),
+            ExtractComponentPlugin::<DualContouringSettings>::default(),
+            ExtractComponentPlugin::<IsoSurfaces>::default(),
+            ExtractComponentPlugin::<ClipPlanes>::default(),
+            ExtractComponentPlugin::<AdaptiveResolution>::default(),
+            ExtractComponentPlugin::<VoxelComputeSuspended>::default(),
+            ExtractComponentPlugin::<VoxelDataVersion>::default(),
+            ExtractComponentPlugin::<ChunkRetiring>::default(),
+            (
+                ExtractResourcePlugin::<VoxelComputePaused>::default(),
+                ExtractResourcePlugin::<ReadbackPolicy>::default(),
+                ExtractResourcePlugin::<GenerationBudget>::default(),
+                ExtractResourcePlugin::<MeshBufferSizing>::default(),
+                ExtractResourcePlugin::<UploadBudget>::default(),
+            ),
+        ))
+        .init_resource::<VoxelComputePaused>()
+        .init_resource::<ReadbackPolicy>()
+        .init_resource::<GenerationBudget>()
+        .init_resource::<MeshBufferSizing>()
+        .init_resource::<UploadBudget>()
+        .init_resource::<ChunkPriorityFn>()
+        .add_event::<ReadbackFailed>()
+        .register_type::<GpuChunkState>()
+        .init_resource::<VoxelWorldConfig>()
+        .init_resource::<ChunkEntities>()
+        .add_systems(Startup, VoxelMaterial::generate_random)
+        .add_systems(
+            First,
+            (
+                VirtualVolume::clear_changed_bricks,
+                DirtyRegion::clear_extracted,
+                VoxelMaterial::clear_unrecorded_writes,
+            ),
+        )
+        .add_systems(
+            PostUpdate,
+            (ChunkPriority::update, AdaptiveResolution::track_focus)
+                .after(TransformSystem::TransformPropagate),
+        )
+        .add_systems(PostUpdate, VoxelLayers::composite_changed)
+        .add_systems(
+            Update,
+            (
+                MainWorldReceiver::receive,
+                RawMeshReceiver::receive,
+                ReadbackFailedReceiver::receive,
+                GpuChunkStateReceiver::receive,
+                RetiredChunkReceiver::receive,
+                IsoSurfaces::spawn_meshes,
+                IsoSurfaceReceiver::receive.after(IsoSurfaces::spawn_meshes),
+            ),
+        );
+
+        #[cfg(feature = "persistence")]
+        app.init_resource::<ChunkMigrations>();
+
+        #[cfg(feature = "editing")]
+        app.init_resource::<AppliedEditBatches>()
+            .init_resource::<EditLocks>()
+            .add_event::<ChunkEdit>()
+            .add_event::<ChunkEditBatch>()
+            .add_event::<ChunkEdited>()
+            .add_event::<PlaceStructure>()
+            .add_event::<StructurePlaced>()
+            .init_asset::<VoxelStructure>()
+            .add_event::<ProjectDecal>()
+            .add_event::<DecalProjected>()
+            .add_event::<LayerEdit>()
+            .add_systems(First, AppliedEditBatches::clear_extracted)
+            .add_systems(
+                PostUpdate,
+                (
+                    ChunkEdit::apply,
+                    PlaceStructure::apply,
+                    ProjectDecal::apply,
+                    LayerEdit::apply.before(VoxelLayers::composite_changed),
+                    AdaptiveResolution::track_edits.after(ChunkEdit::apply),
+                ),
+            );
+
+        #[cfg(all(feature = "persistence", feature = "editing"))]
+        app.add_event::<ChunksSaved>().add_systems(
+            PostUpdate,
+            (
+                EditJournal::record_edits.after(ChunkEdit::apply),
+                EditJournal::compact_saved.after(EditJournal::record_edits),
+            ),
+        );
     }
 
     fn finish(&self, app: &mut App) {
         let (s, r) = crossbeam_channel::unbounded();
         app.insert_resource(MainWorldReceiver(r));
 
+        let (raw_mesh_s, raw_mesh_r) = crossbeam_channel::unbounded();
+        app.insert_resource(RawMeshReceiver(raw_mesh_r));
+
+        let (failed_s, failed_r) = crossbeam_channel::unbounded();
+        app.insert_resource(ReadbackFailedReceiver(failed_r));
+
+        let (iso_surface_s, iso_surface_r) = crossbeam_channel::unbounded();
+        app.insert_resource(IsoSurfaceReceiver(iso_surface_r));
+
+        let (state_s, state_r) = crossbeam_channel::unbounded();
+        app.insert_resource(GpuChunkStateReceiver(state_r));
+
+        let (retired_s, retired_r) = crossbeam_channel::unbounded();
+        app.insert_resource(RetiredChunkReceiver(retired_r));
+
+        let platform = match app.world().get_resource::<ShaderPlatform>() {
+            Some(platform) => *platform,
+            None => ShaderPlatform::detect(
+                app.world().resource::<RenderDevice>(),
+                app.world().resource::<RenderAdapterInfo>(),
+            ),
+        };
+        app.insert_resource(platform);
+
         let render_app = app.sub_app_mut(RenderApp);
 
         render_app
+            .insert_resource(platform)
             .init_resource::<VoxelMeshComputePipeline>()
+            .init_resource::<SpecializedComputePipelines<VoxelMeshComputePipeline>>()
+            .init_resource::<VoxelMaterialComponents<VoxelMeshPipelineId>>()
+            .free_on_retire::<VoxelMeshPipelineId>()
+            .init_resource::<VoxelMaterialComponents<VoxelMeshPrefixSumPipelineIds>>()
+            .free_on_retire::<VoxelMeshPrefixSumPipelineIds>()
             .insert_resource(RenderWorldSender(s))
+            .insert_resource(RawMeshSender(raw_mesh_s))
+            .insert_resource(ReadbackFailedSender(failed_s))
+            .insert_resource(IsoSurfaceSender(iso_surface_s))
+            .insert_resource(GpuChunkStateSender(state_s))
+            .insert_resource(RetiredChunkSender(retired_s))
+            .init_resource::<GpuChunkStates>()
+            .init_resource::<RetiringChunks>()
+            .init_resource::<VoxelMaterialComponents<GpuIsoSurfaces>>()
+            .free_on_retire::<GpuIsoSurfaces>()
+            .init_resource::<VoxelMaterialComponents<MaterialIndexRanges>>()
+            .free_on_retire::<MaterialIndexRanges>()
+            .init_resource::<ReadbackRetries>()
+            .init_resource::<VoxelMaterialComponents<GpuRawMeshData>>()
+            .free_on_retire::<GpuRawMeshData>()
+            .init_resource::<PendingReadbacks>()
+            .init_resource::<VoxelMaterialComponents<VertexReadback>>()
+            .free_on_retire::<VertexReadback>()
+            .init_resource::<PostMeshComputePasses>()
+            .init_resource::<VoxelNodeOrdering>()
+            .init_resource::<DirtyMeshes>()
+            .init_resource::<VoxelMaterialComponents<ExtractedVoxelMaterial>>()
+            .free_on_retire::<ExtractedVoxelMaterial>()
             .init_resource::<VoxelMaterialComponents<GpuVoxelMaterial>>()
+            .free_on_retire::<GpuVoxelMaterial>()
             .init_resource::<VoxelMaterialComponents<GpuVoxelMaterialBindGroups>>()
+            .free_on_retire::<GpuVoxelMaterialBindGroups>()
+            .init_resource::<VoxelMaterialComponents<GpuVirtualVolume>>()
+            .free_on_retire::<GpuVirtualVolume>()
+            .init_resource::<VoxelMaterialComponents<GpuNeighborVoxels>>()
+            .free_on_retire::<GpuNeighborVoxels>()
+            .init_resource::<VoxelDecompressionComputePipeline>()
+            .init_resource::<VoxelMaterialComponents<GpuCompressedVoxels>>()
+            .free_on_retire::<GpuCompressedVoxels>()
+            .init_resource::<ProceduralGenerationComputePipeline>()
+            .init_resource::<VoxelMaterialComponents<GpuProceduralVolume>>()
+            .free_on_retire::<GpuProceduralVolume>()
+            .init_resource::<GenerationQueue>()
+            .init_resource::<UploadQueue>()
+            .init_resource::<SlotCompactionComputePipeline>()
+            .init_resource::<GpuFixedOutputSlots>()
+            .init_resource::<VoxelMaterialComponents<GpuSlotCompaction>>()
+            .free_on_retire::<GpuSlotCompaction>()
             .add_systems(
                 ExtractSchedule,
                 (
-                    GpuVoxelMaterial::initialize,
-                    GpuVoxelMaterial::extract.after(GpuVoxelMaterial::initialize),
-                    GpuVoxelMaterialBindGroups::initialise.after(GpuVoxelMaterial::initialize),
+                    GpuVoxelMaterial::extract,
+                    GpuVirtualVolume::initialize,
+                    GpuVirtualVolume::extract.after(GpuVirtualVolume::initialize),
+                    GpuRawMeshData::initialize
+                        .after(GpuVoxelMaterial::extract)
+                        .after(GpuVirtualVolume::initialize)
+                        .after(GpuProceduralVolume::extract),
+                    MaterialIndexRanges::extract,
+                    GpuProceduralVolume::extract,
+                    GpuChunkStates::extract,
+                    RetiringChunks::extract,
+                    GpuNeighborVoxels::extract,
                 )
                     .in_set(RenderSet::ExtractCommands),
             )
             .add_systems(
                 Render,
                 (
+                    VoxelMeshComputePipeline::specialize.in_set(RenderSet::Prepare),
+                    GpuCompressedVoxels::prepare
+                        .in_set(RenderSet::PrepareResources)
+                        .before(GpuVoxelMaterial::prepare),
+                    GpuVoxelMaterial::prepare.in_set(RenderSet::PrepareResources),
+                    GpuProceduralVolume::prepare.in_set(RenderSet::PrepareResources),
+                    MeshBufferSizing::prepare
+                        .in_set(RenderSet::PrepareResources)
+                        .after(GpuVoxelMaterial::prepare)
+                        .after(GpuCompressedVoxels::prepare),
+                    // Prefix sum allocations are only known once the pipelines are specialized.
+                    GpuFixedOutputSlots::prepare
+                        .in_set(RenderSet::PrepareResources)
+                        .after(VoxelMeshComputePipeline::specialize),
+                    GpuSlotCompaction::prepare.in_set(RenderSet::PrepareBindGroups),
+                    DualContouringSettings::prepare
+                        .in_set(RenderSet::PrepareResources)
+                        .after(GpuVoxelMaterial::prepare)
+                        .after(GpuCompressedVoxels::prepare),
+                    ClipPlanes::prepare
+                        .in_set(RenderSet::PrepareResources)
+                        .after(GpuVoxelMaterial::prepare)
+                        .after(GpuCompressedVoxels::prepare),
+                    AdaptiveResolution::prepare
+                        .in_set(RenderSet::PrepareResources)
+                        .after(GpuVoxelMaterial::prepare)
+                        .after(GpuCompressedVoxels::prepare),
                     GpuVoxelMaterialBindGroups::prepare.in_set(RenderSet::PrepareBindGroups), // We don't need to recreate the bind group every frame
+                    GpuIsoSurfaces::prepare
+                        .in_set(RenderSet::PrepareBindGroups)
+                        .after(GpuSlotCompaction::prepare),
+                    GpuNeighborVoxels::prepare.in_set(RenderSet::PrepareBindGroups),
+                    GpuChunkStates::prepare
+                        .in_set(RenderSet::PrepareBindGroups)
+                        .after(GpuVoxelMaterialBindGroups::prepare)
+                        .after(GpuSlotCompaction::prepare)
+                        .before(DirtyMeshes::select),
+                    (
+                        DirtyMeshes::select,
+                        PendingReadbacks::select,
+                        GpuRawMeshData::select_changed_cells,
+                    )
+                        .chain()
+                        .in_set(RenderSet::PrepareBindGroups)
+                        .after(GpuVoxelMaterialBindGroups::prepare)
+                        .after(GpuIsoSurfaces::prepare),
+                    VoxelNodeOrdering::apply.in_set(RenderSet::Prepare),
                     RenderWorldSender::map_and_read_buffer.after(RenderSet::Render),
+                    RawMeshSender::map_and_read_buffers.after(RenderSet::Render),
+                    IsoSurfaceSender::map_and_read_buffers.after(RenderSet::Render),
                 ),
+            )
+            .add_systems(
+                Render,
+                (
+                    RetiringChunks::free_retired,
+                    RetiringChunks::track_submissions.after(RetiringChunks::free_retired),
+                )
+                    .after(RenderSet::Render),
             );
 
+        #[cfg(feature = "editing")]
+        render_app.add_systems(
+            ExtractSchedule,
+            AppliedEditBatches::extract.in_set(RenderSet::ExtractCommands),
+        );
+
+        let voxel_decompression_compute_node =
+            VoxelDecompressionComputeNode::from_world(render_app.world_mut());
         let voxel_mesh_compute_node = VoxelMeshComputeNode::from_world(render_app.world_mut());
 
         let mut render_graph = render_app.world_mut().resource_mut::<RenderGraph>();
 
+        render_graph.add_node(
+            VoxelDecompressionComputeNodeLabel,
+            voxel_decompression_compute_node,
+        );
         render_graph.add_node(VoxelMeshComputeNodeLabel, voxel_mesh_compute_node);
+        render_graph.add_node(
+            ProceduralGenerationComputeNodeLabel,
+            ProceduralGenerationComputeNode,
+        );
+        render_graph.add_node_edge(
+            ProceduralGenerationComputeNodeLabel,
+            VoxelMeshComputeNodeLabel,
+        );
+        render_graph.add_node_edge(
+            VoxelDecompressionComputeNodeLabel,
+            VoxelMeshComputeNodeLabel,
+        );
     }
 }
+
+pub(crate) fn invalid_data(
+    err: impl Into<Box<dyn std::error::Error + Send + Sync>>,
+) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, err)
+}
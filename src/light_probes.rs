@@ -0,0 +1,105 @@
+use bevy::{
+    pbr::{irradiance_volume::IrradianceVolume, LightProbe},
+    prelude::*,
+    render::{
+        extract_component::ExtractComponentPlugin, graph::CameraDriverLabel,
+        render_graph::RenderGraph, Render, RenderApp, RenderSet,
+    },
+};
+
+use crate::{
+    data::{
+        chunk_retirement::ChunkRetirementExt, gpu_light_probes::GpuLightProbes,
+        light_probes::VoxelLightProbes, voxel_material::VoxelMaterialComponents,
+    },
+    render::{
+        light_probes_compute_pipeline::{
+            LightProbesComputeNode, LightProbesComputeNodeLabel, LightProbesComputePipeline,
+        },
+        voxel_mesh_compute_pipeline::VoxelMeshComputeNodeLabel,
+    },
+};
+
+/// Bakes the irradiance volume of every [`VoxelLightProbes`] on the GPU after the volumes are
+/// meshed and before the cameras render, and lights the scene with it through a child
+/// [`LightProbe`] of the volumetric entity.
+///
+/// Must be added after the [`GpuReadbackPlugin`](crate::GpuReadbackPlugin).
+pub struct LightProbesPlugin;
+
+impl Plugin for LightProbesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(ExtractComponentPlugin::<VoxelLightProbes>::default())
+            .add_systems(
+                PostUpdate,
+                (
+                    spawn_light_probes,
+                    update_light_probes.after(spawn_light_probes),
+                )
+                    .before(TransformSystem::TransformPropagate),
+            );
+    }
+
+    fn finish(&self, app: &mut App) {
+        let render_app = app.sub_app_mut(RenderApp);
+
+        render_app
+            .init_resource::<LightProbesComputePipeline>()
+            .init_resource::<VoxelMaterialComponents<GpuLightProbes>>()
+            .free_on_retire::<GpuLightProbes>()
+            .add_systems(
+                ExtractSchedule,
+                (
+                    GpuLightProbes::initialize,
+                    GpuLightProbes::extract.after(GpuLightProbes::initialize),
+                ),
+            )
+            .add_systems(
+                Render,
+                GpuLightProbes::prepare.in_set(RenderSet::PrepareBindGroups),
+            );
+
+        let light_probes_compute_node = LightProbesComputeNode::from_world(render_app.world_mut());
+
+        let mut render_graph = render_app.world_mut().resource_mut::<RenderGraph>();
+
+        render_graph.add_node(LightProbesComputeNodeLabel, light_probes_compute_node);
+        render_graph.add_node_edge(VoxelMeshComputeNodeLabel, LightProbesComputeNodeLabel);
+        render_graph.add_node_edge(LightProbesComputeNodeLabel, CameraDriverLabel);
+    }
+}
+
+/// Spawns the light probe sampling the irradiance volume of newly added [`VoxelLightProbes`].
+fn spawn_light_probes(
+    mut commands: Commands,
+    light_probes_query: Query<(Entity, &VoxelLightProbes), Added<VoxelLightProbes>>,
+) {
+    for (entity, light_probes) in light_probes_query.iter() {
+        commands.entity(entity).with_children(|parent| {
+            parent.spawn((
+                LightProbe,
+                IrradianceVolume {
+                    voxels: light_probes.image.clone(),
+                    intensity: light_probes.intensity,
+                },
+                SpatialBundle::from_transform(light_probes.light_probe_transform()),
+            ));
+        });
+    }
+}
+
+/// Keeps the light probes of changed [`VoxelLightProbes`] in sync with their texture, intensity and
+/// probe spacing.
+fn update_light_probes(
+    light_probes_query: Query<(&VoxelLightProbes, &Children), Changed<VoxelLightProbes>>,
+    mut irradiance_volume_query: Query<(&mut IrradianceVolume, &mut Transform), With<LightProbe>>,
+) {
+    for (light_probes, children) in light_probes_query.iter() {
+        let mut iter = irradiance_volume_query.iter_many_mut(children);
+        while let Some((mut irradiance_volume, mut transform)) = iter.fetch_next() {
+            irradiance_volume.voxels = light_probes.image.clone();
+            irradiance_volume.intensity = light_probes.intensity;
+            *transform = light_probes.light_probe_transform();
+        }
+    }
+}
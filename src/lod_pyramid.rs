@@ -0,0 +1,295 @@
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, Read},
+    path::Path,
+    sync::Arc,
+};
+
+use bevy::{
+    prelude::*,
+    utils::{BoxedFuture, HashMap},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    chunk_hash::{fnv1a, FNV_OFFSET_BASIS},
+    core::serialization::{decode_voxels, encode_voxels},
+    data::{chunk_coord::ChunkCoord, voxel::Voxel, voxel_world::voxel_index},
+    invalid_data,
+    snapshot::append_file,
+    streaming::{ChunkData, ChunkProvider},
+    CHUNK_SZ, CHUNK_SZ_3,
+};
+
+const MANIFEST_PATH: &str = "manifest.ron";
+const PYRAMID_VERSION: u32 = 2;
+
+/// Levels past which a pyramid stops halving, whatever the extent of the volume.
+const MAX_LEVELS: usize = 16;
+
+/// Describes the contents of a [`LodPyramid`] archive.
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    version: u32,
+    source_hash: u64,
+    levels: Vec<Vec<LodChunkManifest>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct LodChunkManifest {
+    /// Path of the chunk's voxel data in the archive.
+    path: String,
+    coord: [i32; 3],
+}
+
+/// The chunks of an imported volume at every level of detail, precomputed once so that switching
+/// levels at runtime never resamples voxels. Level 0 holds the imported chunks, and the chunk at
+/// `coord` of each further level halves the resolution of the 2³ chunks from `coord * 2` of the
+/// level below, averaging the densities of each 2³ block and keeping the flags of its densest
+/// voxel. Chunks missing from a level are empty.
+///
+/// Importers build the pyramid with [`LodPyramid::load_or_build`], which keeps it in a cache
+/// archive rebuilt whenever the hash of the source data changes.
+pub struct LodPyramid {
+    /// The [`LodPyramid::hash_source`] of the data the pyramid was built from.
+    pub source_hash: u64,
+    levels: Vec<HashMap<ChunkCoord, Vec<Voxel>>>,
+}
+
+impl LodPyramid {
+    /// A hash of the source data of an import, e.g. the bytes of a `.vox` or NRRD file, that is
+    /// the same on every platform and build.
+    pub fn hash_source(data: &[u8]) -> u64 {
+        fnv1a(FNV_OFFSET_BASIS, data.iter().copied())
+    }
+
+    /// Downsamples the level 0 `chunks` until a single chunk covers the whole volume.
+    pub fn build(
+        chunks: impl IntoIterator<Item = (ChunkCoord, Vec<Voxel>)>,
+        source_hash: u64,
+    ) -> Self {
+        let mut levels = vec![chunks.into_iter().collect::<HashMap<_, _>>()];
+        while levels.len() < MAX_LEVELS {
+            let below = levels.last().expect("the pyramid should have a level 0");
+            if below.len() <= 1 {
+                break;
+            }
+            let level = downsample_level(below);
+            // Chunks on both sides of the origin never merge, stop once halving no longer shrinks.
+            if level.len() == below.len() && extent(&level) == extent(below) {
+                break;
+            }
+            levels.push(level);
+        }
+        Self {
+            source_hash,
+            levels,
+        }
+    }
+
+    /// Loads the pyramid cached at `cache_path` if it was built from the same source, or else
+    /// builds it from the chunks returned by `import` and caches it. A cache that fails to load is
+    /// rebuilt.
+    pub fn load_or_build<I>(
+        cache_path: impl AsRef<Path>,
+        source_hash: u64,
+        import: impl FnOnce() -> I,
+    ) -> io::Result<Self>
+    where
+        I: IntoIterator<Item = (ChunkCoord, Vec<Voxel>)>,
+    {
+        let cache_path = cache_path.as_ref();
+        match Self::load(cache_path) {
+            Ok(pyramid) if pyramid.source_hash == source_hash => return Ok(pyramid),
+            Ok(_) => info!("Rebuilding the LOD pyramid {cache_path:?}: its source changed"),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => warn!("Rebuilding the LOD pyramid {cache_path:?}: {err}"),
+        }
+
+        let pyramid = Self::build(import(), source_hash);
+        pyramid.save(cache_path)?;
+        Ok(pyramid)
+    }
+
+    /// Number of levels, level 0 included.
+    pub fn levels(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// The voxels of the chunk at `coord` of `level`, if it has any.
+    pub fn chunk(&self, level: usize, coord: ChunkCoord) -> Option<&[Voxel]> {
+        self.levels.get(level)?.get(&coord).map(Vec::as_slice)
+    }
+
+    /// The chunks of `level`.
+    pub fn level(&self, level: usize) -> impl Iterator<Item = (ChunkCoord, &[Voxel])> {
+        self.levels
+            .get(level)
+            .into_iter()
+            .flatten()
+            .map(|(coord, voxels)| (*coord, voxels.as_slice()))
+    }
+
+    /// Writes the pyramid to a tar archive at `path`, holding a `manifest.ron` and the voxel data
+    /// of every chunk of every level.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        if let Some(parent) = path.as_ref().parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut archive = tar::Builder::new(File::create(path)?);
+
+        let mut manifest = Manifest {
+            version: PYRAMID_VERSION,
+            source_hash: self.source_hash,
+            levels: Vec::with_capacity(self.levels.len()),
+        };
+
+        for (level, chunks) in self.levels.iter().enumerate() {
+            let mut level_manifest = Vec::with_capacity(chunks.len());
+            for (index, (coord, voxels)) in chunks.iter().enumerate() {
+                let path = format!("levels/{level}/{index}.bin");
+
+                append_file(&mut archive, &path, &encode_voxels(voxels))?;
+
+                level_manifest.push(LodChunkManifest {
+                    path,
+                    coord: coord.0.to_array(),
+                });
+            }
+            manifest.levels.push(level_manifest);
+        }
+
+        let manifest = ron::ser::to_string_pretty(&manifest, default()).map_err(invalid_data)?;
+        append_file(&mut archive, MANIFEST_PATH, manifest.as_bytes())?;
+
+        archive.into_inner()?.sync_all()
+    }
+
+    /// Reads a pyramid written by [`LodPyramid::save`].
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut files = BTreeMap::new();
+        for entry in tar::Archive::new(File::open(path)?).entries()? {
+            let mut entry = entry?;
+            let path = entry.path()?.to_string_lossy().into_owned();
+            let mut data = Vec::new();
+            entry.read_to_end(&mut data)?;
+            files.insert(path, data);
+        }
+
+        let manifest = files
+            .get(MANIFEST_PATH)
+            .ok_or_else(|| invalid_data("the pyramid has no manifest"))?;
+        let manifest: Manifest = ron::de::from_bytes(manifest).map_err(invalid_data)?;
+
+        if manifest.version != PYRAMID_VERSION {
+            return Err(invalid_data(format!(
+                "unsupported pyramid version {}",
+                manifest.version
+            )));
+        }
+
+        let levels = manifest
+            .levels
+            .into_iter()
+            .map(|chunks| {
+                chunks
+                    .into_iter()
+                    .map(|chunk| {
+                        let data = files
+                            .get(&chunk.path)
+                            .ok_or_else(|| invalid_data(format!("missing chunk {}", chunk.path)))?;
+                        let voxels = decode_voxels(data);
+                        Ok((ChunkCoord(IVec3::from_array(chunk.coord)), voxels))
+                    })
+                    .collect::<io::Result<_>>()
+            })
+            .collect::<io::Result<_>>()?;
+
+        Ok(Self {
+            source_hash: manifest.source_hash,
+            levels,
+        })
+    }
+}
+
+/// The level above `below`, each chunk halving the 2³ chunks it covers.
+fn downsample_level(below: &HashMap<ChunkCoord, Vec<Voxel>>) -> HashMap<ChunkCoord, Vec<Voxel>> {
+    let mut coords = below
+        .keys()
+        .map(|coord| ChunkCoord(coord.0.div_euclid(IVec3::splat(2))))
+        .collect::<Vec<_>>();
+    coords.sort_unstable_by_key(|coord| coord.0.to_array());
+    coords.dedup();
+
+    coords
+        .into_iter()
+        .map(|coord| {
+            let mut voxels = vec![Voxel::new(0, 0.0); CHUNK_SZ_3];
+            let half = CHUNK_SZ as u32 / 2;
+            for octant in 0..8 {
+                let offset = UVec3::new(octant & 1, (octant >> 1) & 1, octant >> 2);
+                let Some(child) = below.get(&ChunkCoord(coord.0 * 2 + offset.as_ivec3())) else {
+                    continue;
+                };
+                for z in 0..half {
+                    for y in 0..half {
+                        for x in 0..half {
+                            let pos = UVec3::new(x, y, z);
+                            voxels[voxel_index(offset * half + pos)] = block(child, pos * 2);
+                        }
+                    }
+                }
+            }
+            (coord, voxels)
+        })
+        .collect()
+}
+
+/// The chunks spanned by the coordinates of `level` along each axis.
+fn extent(level: &HashMap<ChunkCoord, Vec<Voxel>>) -> IVec3 {
+    let (min, max) = level
+        .keys()
+        .fold((IVec3::MAX, IVec3::MIN), |(min, max), coord| {
+            (min.min(coord.0), max.max(coord.0))
+        });
+    (max - min).max(IVec3::ZERO)
+}
+
+/// The voxel halving the 2³ voxels of `voxels` from `min`.
+fn block(voxels: &[Voxel], min: UVec3) -> Voxel {
+    let mut density = 0.0;
+    let mut densest = voxels[voxel_index(min)];
+    for offset in 0..8 {
+        let offset = UVec3::new(offset & 1, (offset >> 1) & 1, offset >> 2);
+        let voxel = voxels[voxel_index(min + offset)];
+        density += voxel.density();
+        if voxel.density() > densest.density() {
+            densest = voxel;
+        }
+    }
+    densest.with_density(density / 8.0)
+}
+
+/// Serves one level of a [`LodPyramid`] to the
+/// [`ChunkStreaming`](crate::streaming::ChunkStreaming), empty chunks past its extent.
+#[derive(Clone)]
+pub struct LodLevelProvider {
+    pub pyramid: Arc<LodPyramid>,
+    pub level: usize,
+}
+
+impl ChunkProvider for LodLevelProvider {
+    fn fetch(&self, coord: ChunkCoord) -> BoxedFuture<'_, io::Result<ChunkData>> {
+        Box::pin(async move {
+            let voxels = self
+                .pyramid
+                .chunk(self.level, coord)
+                .map_or_else(|| vec![Voxel::new(0, 0.0); CHUNK_SZ_3], <[Voxel]>::to_vec);
+            Ok(ChunkData {
+                voxels,
+                chunk_size: CHUNK_SZ_3 as u32,
+            })
+        })
+    }
+}
@@ -0,0 +1,149 @@
+use bevy::{
+    color::palettes::css::{BLUE, LIME, RED},
+    prelude::*,
+    utils::HashMap,
+};
+
+use crate::{bundles::volumetric_bundle::Volumetric, data::raw_mesh_data::RawMeshData};
+
+/// Triangles whose normal is shorter than this are drawn as degenerate.
+const DEGENERATE_EPSILON: f32 = 1e-6;
+
+/// Draws the meshes read back from the compute shader with gizmos, to diagnose inverted normals or
+/// degenerate triangles. While enabled, every volumetric entity is opted into [`RawMeshData`].
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct MeshDebugGizmos {
+    pub enabled: bool,
+    /// Draws the normal of each triangle from its centroid, as computed by the compute shader.
+    pub show_normals: bool,
+    pub normal_length: f32,
+    /// Outlines the triangles whose normal is zero in red.
+    pub show_degenerate: bool,
+    /// Draws each cell containing vertices, from blue for a single vertex to red for
+    /// `max_cell_vertices` and more.
+    pub show_cell_vertex_counts: bool,
+    pub max_cell_vertices: u32,
+    /// Triangles drawn per entity and frame, to keep the frame rate usable on large meshes.
+    pub max_triangles: usize,
+}
+
+impl Default for MeshDebugGizmos {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            show_normals: true,
+            normal_length: 0.5,
+            show_degenerate: true,
+            show_cell_vertex_counts: false,
+            max_cell_vertices: 12,
+            max_triangles: 65536,
+        }
+    }
+}
+
+/// Marks the entities opted into [`RawMeshData`] by [`MeshDebugGizmos`] rather than by the user, so
+/// that they are opted out again when it is disabled.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct MeshDebugReadback;
+
+impl MeshDebugGizmos {
+    /// Opts the volumetric entities into [`RawMeshData`] while enabled, and out when disabled.
+    #[allow(clippy::type_complexity)]
+    pub fn toggle_readback(
+        mut commands: Commands,
+        settings: Res<Self>,
+        missing_query: Query<Entity, (With<Volumetric>, Without<RawMeshData>)>,
+        debug_query: Query<Entity, With<MeshDebugReadback>>,
+    ) {
+        if settings.enabled {
+            for entity in missing_query.iter() {
+                commands
+                    .entity(entity)
+                    .insert((RawMeshData::default(), MeshDebugReadback));
+            }
+        } else {
+            for entity in debug_query.iter() {
+                commands
+                    .entity(entity)
+                    .remove::<(RawMeshData, MeshDebugReadback)>();
+            }
+        }
+    }
+
+    pub fn draw(
+        settings: Res<Self>,
+        mut gizmos: Gizmos,
+        raw_mesh_query: Query<(&RawMeshData, &GlobalTransform)>,
+    ) {
+        if !settings.enabled {
+            return;
+        }
+
+        for (raw_mesh, transform) in raw_mesh_query.iter() {
+            let vertices = &raw_mesh.vertices;
+            let to_world = |vertex: Vec3| transform.transform_point(vertex);
+
+            for triangle in raw_mesh
+                .indices
+                .chunks_exact(3)
+                .take(settings.max_triangles)
+            {
+                let [Some(&v0), Some(&v1), Some(&v2)] =
+                    [0, 1, 2].map(|corner| vertices.get(triangle[corner] as usize))
+                else {
+                    continue;
+                };
+
+                let normal = (v0 - v1).cross(v0 - v2);
+                if normal.length() < DEGENERATE_EPSILON {
+                    if settings.show_degenerate {
+                        let corners = [v0, v1, v2, v0].map(to_world);
+                        gizmos.linestrip(corners, RED);
+                    }
+                    continue;
+                }
+
+                if settings.show_normals {
+                    let centroid = (v0 + v1 + v2) / 3.0;
+                    let tip = centroid + normal.normalize() * settings.normal_length;
+                    gizmos.line(to_world(centroid), to_world(tip), LIME);
+                }
+            }
+
+            if settings.show_cell_vertex_counts {
+                let mut cell_vertex_counts = HashMap::<IVec3, u32>::new();
+                for vertex in vertices.iter() {
+                    *cell_vertex_counts
+                        .entry(vertex.floor().as_ivec3())
+                        .or_default() += 1;
+                }
+
+                let max = settings.max_cell_vertices.max(2);
+                for (cell, count) in cell_vertex_counts {
+                    let t = (count.min(max) - 1) as f32 / (max - 1) as f32;
+                    let cell = Transform::from_translation(cell.as_vec3() + 0.5);
+                    gizmos.cuboid(
+                        transform.mul_transform(cell),
+                        Color::from(BLUE).mix(&Color::from(RED), t),
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Draws the [`MeshDebugGizmos`] of the meshes read back from the compute shader. Must be added
+/// after the [`GpuReadbackPlugin`](crate::GpuReadbackPlugin).
+pub struct MeshDebugGizmosPlugin;
+
+impl Plugin for MeshDebugGizmosPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MeshDebugGizmos>().add_systems(
+            PostUpdate,
+            (
+                MeshDebugGizmos::toggle_readback,
+                MeshDebugGizmos::draw.after(TransformSystem::TransformPropagate),
+            ),
+        );
+    }
+}
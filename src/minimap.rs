@@ -0,0 +1,216 @@
+use bevy::{
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+    },
+    utils::{HashMap, HashSet},
+};
+
+use crate::{
+    bundles::volumetric_bundle::Volumetric,
+    data::{
+        chunk_coord::ChunkCoord,
+        iso_surface::DEFAULT_ISO_LEVEL,
+        material_split::MAX_MATERIALS,
+        voxel_material::VoxelMaterial,
+        voxel_world::{voxel_index, VoxelWorldConfig},
+    },
+    CHUNK_SZ,
+};
+
+#[cfg(feature = "editing")]
+use crate::data::voxel_edit::ChunkEdit;
+
+/// Top-down textures of the chunks of the voxel world for UI minimaps, one per square region of
+/// `region_chunks` by `region_chunks` chunk columns along x and z. Each texel holds the topmost
+/// solid voxel of its column of voxels: the colour of its material in the `color` image, and the
+/// world space height of its center in the `height` image. Columns without any solid voxel are
+/// transparent, at a height of `f32::NEG_INFINITY`.
+///
+/// With the [`VoxelMinimapPlugin`], the columns of the chunks whose voxels changed, e.g. edited,
+/// generated or streamed in, are drawn again every frame. Columns of unloaded chunks keep their
+/// last texels.
+#[derive(Resource, Clone, Debug)]
+pub struct VoxelMinimap {
+    /// Chunk columns along each side of a region, set before any chunk is drawn.
+    pub region_chunks: u32,
+    /// Density from which a voxel can be the top of its column.
+    pub threshold: f32,
+    /// The colour of the voxels whose flags are the index of the colour, the last one standing
+    /// for the flags past it.
+    pub material_colors: Vec<Color>,
+    regions: HashMap<IVec2, MinimapRegion>,
+}
+
+/// The textures of a region of the [`VoxelMinimap`], with one texel per column of voxels, x along
+/// their width and z along their height.
+#[derive(Clone, Debug)]
+pub struct MinimapRegion {
+    /// `Rgba8UnormSrgb` colours of the top of the columns.
+    pub color: Handle<Image>,
+    /// `R32Float` world space heights of the top of the columns.
+    pub height: Handle<Image>,
+}
+
+impl Default for VoxelMinimap {
+    fn default() -> Self {
+        Self {
+            region_chunks: 4,
+            threshold: DEFAULT_ISO_LEVEL,
+            material_colors: (0..MAX_MATERIALS)
+                .map(|material| {
+                    Color::hsl(material as f32 * 360.0 / MAX_MATERIALS as f32, 0.4, 0.5)
+                })
+                .collect(),
+            regions: HashMap::new(),
+        }
+    }
+}
+
+impl VoxelMinimap {
+    /// The textures of `region`, once any of its chunks was drawn.
+    pub fn region(&self, region: IVec2) -> Option<&MinimapRegion> {
+        self.regions.get(&region)
+    }
+
+    /// The regions drawn so far.
+    pub fn regions(&self) -> impl Iterator<Item = (IVec2, &MinimapRegion)> {
+        self.regions
+            .iter()
+            .map(|(region, textures)| (*region, textures))
+    }
+
+    /// The region covering the column of the chunk at `coord`.
+    pub fn region_of(&self, coord: ChunkCoord) -> IVec2 {
+        coord
+            .0
+            .xz()
+            .div_euclid(IVec2::splat(self.region_chunks.max(1) as i32))
+    }
+
+    /// Texels along each side of the textures of a region.
+    pub fn region_size(&self) -> u32 {
+        self.region_chunks.max(1) * CHUNK_SZ as u32
+    }
+
+    /// The textures of `region`, created empty if it was never drawn.
+    fn region_mut(&mut self, images: &mut Assets<Image>, region: IVec2) -> MinimapRegion {
+        let size = self.region_size();
+        self.regions
+            .entry(region)
+            .or_insert_with(|| {
+                let mut image = |pixel: &[u8], format| {
+                    images.add(Image::new_fill(
+                        Extent3d {
+                            width: size,
+                            height: size,
+                            depth_or_array_layers: 1,
+                        },
+                        TextureDimension::D2,
+                        pixel,
+                        format,
+                        RenderAssetUsages::default(),
+                    ))
+                };
+                MinimapRegion {
+                    color: image(&[0; 4], TextureFormat::Rgba8UnormSrgb),
+                    height: image(&f32::NEG_INFINITY.to_le_bytes(), TextureFormat::R32Float),
+                }
+            })
+            .clone()
+    }
+
+    /// Draws the columns of the chunks whose voxels changed, from the top of the highest chunk of
+    /// each column down.
+    pub fn update(
+        mut minimap: ResMut<Self>,
+        mut images: ResMut<Assets<Image>>,
+        config: Res<VoxelWorldConfig>,
+        changed_query: Query<&ChunkCoord, (With<Volumetric>, Changed<VoxelMaterial>)>,
+        chunk_query: Query<(&ChunkCoord, &VoxelMaterial), With<Volumetric>>,
+    ) {
+        let columns = changed_query
+            .iter()
+            .map(|coord| coord.0.xz())
+            .collect::<HashSet<_>>();
+        if columns.is_empty() {
+            return;
+        }
+
+        let mut column_chunks = HashMap::<IVec2, Vec<(ChunkCoord, &VoxelMaterial)>>::new();
+        for (coord, voxel_material) in chunk_query.iter() {
+            if columns.contains(&coord.0.xz()) {
+                column_chunks
+                    .entry(coord.0.xz())
+                    .or_default()
+                    .push((*coord, voxel_material));
+            }
+        }
+
+        let side = CHUNK_SZ as u32;
+        let region_size = minimap.region_size() as usize;
+        for (column, mut chunks) in column_chunks {
+            chunks.sort_by_key(|(coord, _)| std::cmp::Reverse(coord.0.y));
+
+            let region = minimap.region_of(chunks[0].0);
+            let textures = minimap.region_mut(&mut images, region);
+            let offset = (column - region * minimap.region_chunks.max(1) as i32).as_uvec2() * side;
+
+            let mut texels = Vec::with_capacity((side * side) as usize);
+            for z in 0..side {
+                for x in 0..side {
+                    let top = chunks.iter().find_map(|(coord, voxel_material)| {
+                        (0..side).rev().find_map(|y| {
+                            let position = UVec3::new(x, y, z);
+                            let voxel = voxel_material.voxels().get(voxel_index(position))?;
+                            (voxel.density() >= minimap.threshold).then(|| {
+                                let voxel_position =
+                                    config.chunk_to_voxel(*coord) + position.as_ivec3();
+                                (voxel.flags(), config.voxel_to_world(voxel_position).y)
+                            })
+                        })
+                    });
+
+                    let texel = (offset.y + z) as usize * region_size + (offset.x + x) as usize;
+                    texels.push(match top {
+                        Some((flags, height)) => {
+                            let color = minimap
+                                .material_colors
+                                .get(flags as usize)
+                                .or(minimap.material_colors.last())
+                                .map_or([0; 4], |color| color.to_srgba().to_u8_array());
+                            (texel, color, height)
+                        }
+                        None => (texel, [0; 4], f32::NEG_INFINITY),
+                    });
+                }
+            }
+
+            if let Some(image) = images.get_mut(&textures.color) {
+                for (texel, color, _) in &texels {
+                    image.data[texel * 4..texel * 4 + 4].copy_from_slice(color);
+                }
+            }
+            if let Some(image) = images.get_mut(&textures.height) {
+                for (texel, _, height) in &texels {
+                    image.data[texel * 4..texel * 4 + 4].copy_from_slice(&height.to_le_bytes());
+                }
+            }
+        }
+    }
+}
+
+/// Keeps the [`VoxelMinimap`] up to date with the voxels of the chunks of the world.
+pub struct VoxelMinimapPlugin;
+
+impl Plugin for VoxelMinimapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<VoxelMinimap>();
+
+        #[cfg(feature = "editing")]
+        app.add_systems(PostUpdate, VoxelMinimap::update.after(ChunkEdit::apply));
+        #[cfg(not(feature = "editing"))]
+        app.add_systems(PostUpdate, VoxelMinimap::update);
+    }
+}
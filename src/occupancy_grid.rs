@@ -0,0 +1,192 @@
+use bevy::prelude::*;
+
+use crate::{
+    bundles::volumetric_bundle::Volumetric,
+    data::{
+        voxel_material::VoxelMaterial,
+        voxel_world::{voxel_index, DirtyRegion},
+    },
+    CHUNK_SZ,
+};
+
+#[cfg(feature = "editing")]
+use crate::data::voxel_edit::ChunkEdit;
+
+const WORD_BITS: usize = u64::BITS as usize;
+
+/// The grid of a chunk along with its voxels and the region of them written this frame.
+type OccupancyGridItem = (
+    &'static mut OccupancyGrid,
+    Ref<'static, VoxelMaterial>,
+    Option<&'static DirtyRegion>,
+);
+
+/// Which voxels of a chunk are solid, one bit per voxel, for navigation or AI code that only needs
+/// to know where the terrain is. Bits are packed into `u64` words in the order of the voxels,
+/// x first, then y, then z, bit `i % 64` of word `i / 64` holding voxel `i`.
+///
+/// With the [`OccupancyGridPlugin`], the grid of a volumetric entity follows the edits of its
+/// [`VoxelMaterial`].
+#[derive(Component, Clone, Debug, PartialEq)]
+pub struct OccupancyGrid {
+    /// Density from which the bit of a voxel is set.
+    threshold: f32,
+    words: Vec<u64>,
+}
+
+impl OccupancyGrid {
+    /// Voxels along each axis of the grid.
+    pub const SIZE: UVec3 = UVec3::splat(CHUNK_SZ as u32);
+
+    /// The occupancy of the voxels of `voxel_material` whose density reaches `threshold`.
+    pub fn from_volume(voxel_material: &VoxelMaterial, threshold: f32) -> Self {
+        let mut grid = Self {
+            threshold,
+            words: vec![0; (Self::SIZE.element_product() as usize).div_ceil(WORD_BITS)],
+        };
+        grid.update_region(voxel_material, UVec3::ZERO, Self::SIZE);
+        grid
+    }
+
+    pub fn threshold(&self) -> f32 {
+        self.threshold
+    }
+
+    /// The packed bits of the grid.
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+
+    /// Whether the voxel at `position` is solid, `false` outside the grid.
+    pub fn is_solid(&self, position: UVec3) -> bool {
+        if position.cmpge(Self::SIZE).any() {
+            return false;
+        }
+        let index = voxel_index(position);
+        self.words[index / WORD_BITS] & (1 << (index % WORD_BITS)) != 0
+    }
+
+    /// Marks the voxel at `position` solid or empty, ignoring positions outside the grid.
+    pub fn set(&mut self, position: UVec3, solid: bool) {
+        if position.cmpge(Self::SIZE).any() {
+            return;
+        }
+        let index = voxel_index(position);
+        let bit = 1 << (index % WORD_BITS);
+        if solid {
+            self.words[index / WORD_BITS] |= bit;
+        } else {
+            self.words[index / WORD_BITS] &= !bit;
+        }
+    }
+
+    /// Number of solid voxels.
+    pub fn solid_count(&self) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    /// The positions of the solid voxels, in the order of the voxels.
+    pub fn iter_solid(&self) -> impl Iterator<Item = UVec3> + '_ {
+        self.iter_bits(false)
+    }
+
+    /// The positions of the empty voxels, in the order of the voxels.
+    pub fn iter_empty(&self) -> impl Iterator<Item = UVec3> + '_ {
+        self.iter_bits(true)
+    }
+
+    /// The positions of the solid voxels from `min` up to the voxel before `max`.
+    pub fn iter_solid_in(&self, min: UVec3, max: UVec3) -> impl Iterator<Item = UVec3> + '_ {
+        let max = max.min(Self::SIZE);
+        (min.z..max.z).flat_map(move |z| {
+            (min.y..max.y).flat_map(move |y| {
+                (min.x..max.x)
+                    .map(move |x| UVec3::new(x, y, z))
+                    .filter(|position| self.is_solid(*position))
+            })
+        })
+    }
+
+    /// Iterates the set bits of the words, or of their complement if `inverted`.
+    fn iter_bits(&self, inverted: bool) -> impl Iterator<Item = UVec3> + '_ {
+        let voxel_count = Self::SIZE.element_product() as usize;
+        self.words
+            .iter()
+            .enumerate()
+            .flat_map(move |(word_index, word)| {
+                let mut bits = if inverted { !word } else { *word };
+                std::iter::from_fn(move || {
+                    if bits == 0 {
+                        return None;
+                    }
+                    let bit = bits.trailing_zeros() as usize;
+                    bits &= bits - 1;
+                    Some(word_index * WORD_BITS + bit)
+                })
+            })
+            .take_while(move |index| *index < voxel_count)
+            .map(|index| position(index as u32))
+    }
+
+    /// Recomputes the occupancy of the voxels from `min` up to the voxel before `max`.
+    pub fn update_region(&mut self, voxel_material: &VoxelMaterial, min: UVec3, max: UVec3) {
+        let max = max.min(Self::SIZE);
+        for z in min.z..max.z {
+            for y in min.y..max.y {
+                for x in min.x..max.x {
+                    let position = UVec3::new(x, y, z);
+                    let solid = voxel_material
+                        .voxels()
+                        .get(voxel_index(position))
+                        .is_some_and(|voxel| voxel.density() >= self.threshold);
+                    self.set(position, solid);
+                }
+            }
+        }
+    }
+
+    /// Changes the threshold of the grid, recomputing all of it.
+    pub fn set_threshold(&mut self, voxel_material: &VoxelMaterial, threshold: f32) {
+        self.threshold = threshold;
+        self.update_region(voxel_material, UVec3::ZERO, Self::SIZE);
+    }
+
+    /// Updates the grids of the entities whose voxels changed this frame. Only the
+    /// [`DirtyRegion`] is recomputed when every write was recorded in it, e.g. by edits, and the
+    /// whole grid otherwise, e.g. when the voxels were replaced or streamed in, written through
+    /// [`VoxelMaterial::voxels_mut`] alongside edits, or edited without a dirty region.
+    pub fn update(mut grid_query: Query<OccupancyGridItem, With<Volumetric>>) {
+        for (mut grid, voxel_material, dirty_region) in grid_query.iter_mut() {
+            if !voxel_material.is_changed() || voxel_material.is_added() {
+                continue;
+            }
+            match dirty_region.and_then(DirtyRegion::bounds) {
+                Some((min, max)) if !voxel_material.has_unrecorded_writes() => {
+                    grid.update_region(&voxel_material, min, max);
+                }
+                _ => grid.update_region(&voxel_material, UVec3::ZERO, Self::SIZE),
+            }
+        }
+    }
+}
+
+/// The position of the voxel at `index` in the order of the voxels.
+fn position(index: u32) -> UVec3 {
+    let side = CHUNK_SZ as u32;
+    UVec3::new(index % side, index / side % side, index / (side * side))
+}
+
+/// Keeps the [`OccupancyGrid`] of each volumetric entity up to date with its voxels.
+pub struct OccupancyGridPlugin;
+
+impl Plugin for OccupancyGridPlugin {
+    fn build(&self, app: &mut App) {
+        #[cfg(feature = "editing")]
+        app.add_systems(PostUpdate, OccupancyGrid::update.after(ChunkEdit::apply));
+        #[cfg(not(feature = "editing"))]
+        app.add_systems(PostUpdate, OccupancyGrid::update);
+    }
+}
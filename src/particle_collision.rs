@@ -0,0 +1,200 @@
+use bevy::{
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+        texture::ImageSampler,
+    },
+};
+
+use crate::{
+    bundles::volumetric_bundle::Volumetric,
+    data::{
+        chunk_coord::ChunkCoord,
+        iso_surface::DEFAULT_ISO_LEVEL,
+        particle_collision::ParticleCollisionParams,
+        voxel_material::VoxelMaterial,
+        voxel_world::{voxel_index, VoxelWorldConfig},
+    },
+    CHUNK_SZ,
+};
+
+#[cfg(feature = "editing")]
+use crate::data::voxel_edit::ChunkEdit;
+
+/// The entity the [`ParticleCollisionVolume`] is centered on, e.g. the camera. Only the first one
+/// is followed.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct ParticleCollisionFocus;
+
+/// The densities of the chunks around the [`ParticleCollisionFocus`] as a 3D texture, for GPU
+/// particle systems to collide particles against the voxels without reading anything back, e.g.
+/// from a custom modifier. The texture holds one `R8Unorm` texel per voxel, its density clamped
+/// between `0.0` and `1.0`, and is sampled linearly; [`ParticleCollisionVolume::params`] places it
+/// in world space. The `bevy_volumetric::particle_collision` shader module samples it.
+///
+/// With the [`ParticleCollisionPlugin`], the window of chunks is written again whenever the focus
+/// moves to another chunk, and the chunks whose voxels changed every frame. Unloaded chunks are
+/// empty.
+#[derive(Resource, Clone, Debug)]
+pub struct ParticleCollisionVolume {
+    /// Chunks along each axis of the window, set before the texture is created.
+    pub extent_chunks: UVec3,
+    /// Density from which particles collide.
+    pub iso_level: f32,
+    texture: Option<Handle<Image>>,
+    min_chunk: Option<ChunkCoord>,
+}
+
+impl Default for ParticleCollisionVolume {
+    fn default() -> Self {
+        Self {
+            extent_chunks: UVec3::splat(3),
+            iso_level: DEFAULT_ISO_LEVEL,
+            texture: None,
+            min_chunk: None,
+        }
+    }
+}
+
+impl ParticleCollisionVolume {
+    /// The density texture, once the window was first written.
+    pub fn texture(&self) -> Option<&Handle<Image>> {
+        self.texture.as_ref()
+    }
+
+    /// The first chunk of the window, once it was first written.
+    pub fn min_chunk(&self) -> Option<ChunkCoord> {
+        self.min_chunk
+    }
+
+    /// Texels along each axis of the texture.
+    pub fn size(&self) -> UVec3 {
+        self.extent_chunks.max(UVec3::ONE) * CHUNK_SZ as u32
+    }
+
+    /// The params placing the texture in world space, to bind along with it.
+    pub fn params(&self, config: &VoxelWorldConfig) -> ParticleCollisionParams {
+        let min_voxel = config.chunk_to_voxel(self.min_chunk.unwrap_or_default());
+        ParticleCollisionParams::new(
+            config.voxel_to_world(min_voxel),
+            config.voxel_size,
+            self.size(),
+            self.iso_level,
+        )
+    }
+
+    /// Writes the window of chunks around the focus again when it moved to another chunk, or else
+    /// the chunks of the window whose voxels changed.
+    pub fn update(
+        mut volume: ResMut<Self>,
+        mut images: ResMut<Assets<Image>>,
+        config: Res<VoxelWorldConfig>,
+        focus_query: Query<&GlobalTransform, With<ParticleCollisionFocus>>,
+        chunk_query: Query<(&ChunkCoord, Ref<VoxelMaterial>), With<Volumetric>>,
+    ) {
+        let Some(focus) = focus_query.iter().next() else {
+            return;
+        };
+        let extent = volume.extent_chunks.max(UVec3::ONE);
+        let min_chunk =
+            ChunkCoord(config.world_to_chunk(focus.translation()).0 - (extent / 2).as_ivec3());
+        let size = volume.size();
+
+        let stale = volume
+            .texture
+            .as_ref()
+            .and_then(|texture| images.get(texture))
+            .is_none_or(|image| image.texture_descriptor.size != Self::extent(size));
+        if stale {
+            volume.texture = Some(images.add(Self::create_image(size)));
+            volume.min_chunk = None;
+        }
+        let moved = volume.min_chunk != Some(min_chunk);
+        let Some(image) = volume
+            .texture
+            .as_ref()
+            .and_then(|texture| images.get_mut(texture))
+        else {
+            return;
+        };
+        if moved {
+            image.data.fill(0);
+        }
+
+        for (coord, voxel_material) in chunk_query.iter() {
+            let offset = coord.0 - min_chunk.0;
+            if offset.cmplt(IVec3::ZERO).any() || offset.cmpge(extent.as_ivec3()).any() {
+                continue;
+            }
+            if !moved && !voxel_material.is_changed() {
+                continue;
+            }
+            Self::write_chunk(&mut image.data, size, offset.as_uvec3(), &voxel_material);
+        }
+        volume.min_chunk = Some(min_chunk);
+    }
+
+    fn extent(size: UVec3) -> Extent3d {
+        Extent3d {
+            width: size.x,
+            height: size.y,
+            depth_or_array_layers: size.z,
+        }
+    }
+
+    fn create_image(size: UVec3) -> Image {
+        let mut image = Image::new_fill(
+            Self::extent(size),
+            TextureDimension::D3,
+            &[0],
+            TextureFormat::R8Unorm,
+            RenderAssetUsages::default(),
+        );
+        image.sampler = ImageSampler::linear();
+        image
+    }
+
+    /// Writes the densities of `voxel_material` at the chunk `offset` of the window.
+    fn write_chunk(data: &mut [u8], size: UVec3, offset: UVec3, voxel_material: &VoxelMaterial) {
+        let side = CHUNK_SZ as u32;
+        let origin = offset * side;
+        for z in 0..side {
+            for y in 0..side {
+                for x in 0..side {
+                    let position = UVec3::new(x, y, z);
+                    let density = voxel_material
+                        .voxels()
+                        .get(voxel_index(position))
+                        .map_or(0.0, |voxel| voxel.density());
+                    let texel = origin + position;
+                    let index = (texel.x + (texel.y + texel.z * size.y) * size.x) as usize;
+                    data[index] = (density.clamp(0.0, 1.0) * 255.0).round() as u8;
+                }
+            }
+        }
+    }
+}
+
+/// Keeps the [`ParticleCollisionVolume`] up to date with the voxels around the
+/// [`ParticleCollisionFocus`].
+pub struct ParticleCollisionPlugin;
+
+impl Plugin for ParticleCollisionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ParticleCollisionVolume>();
+
+        #[cfg(feature = "editing")]
+        app.add_systems(
+            PostUpdate,
+            ParticleCollisionVolume::update
+                .after(ChunkEdit::apply)
+                .after(TransformSystem::TransformPropagate),
+        );
+        #[cfg(not(feature = "editing"))]
+        app.add_systems(
+            PostUpdate,
+            ParticleCollisionVolume::update.after(TransformSystem::TransformPropagate),
+        );
+    }
+}
@@ -0,0 +1,329 @@
+use std::collections::VecDeque;
+
+use bevy::{
+    core::FrameCount,
+    prelude::*,
+    render::{Render, RenderApp, RenderSet},
+    utils::{HashMap, HashSet},
+};
+use bevy_inspector_egui::{
+    bevy_egui::EguiContexts,
+    egui::{self, Color32, Sense},
+};
+use crossbeam_channel::{Receiver, Sender};
+
+use crate::{
+    channels::{PendingReadbacks, RenderWorldSender, VertexReadback},
+    data::{
+        gpu_chunk_state::GpuChunkStates, gpu_compressed_voxels::GpuCompressedVoxels,
+        gpu_voxel_material::GpuVoxelMaterial, upload_budget::UploadQueue,
+        voxel_material::VoxelMaterialComponents,
+    },
+    render::voxel_mesh_compute_pipeline::DirtyMeshes,
+};
+
+/// A stage of the voxel pipeline a chunk goes through from its voxels to its mesh.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PipelineStage {
+    /// Its voxels were uploaded to the GPU.
+    Uploaded,
+    /// Its meshing compute shader was dispatched.
+    Dispatched,
+    /// A page of its mesh was copied into its staging buffers.
+    Copied,
+    /// Its staging buffers were mapped and a page of its mesh read.
+    Mapped,
+    /// The last page of its mesh was read and sent to the main world.
+    Meshed,
+}
+
+impl PipelineStage {
+    pub const ALL: [Self; 5] = [
+        Self::Uploaded,
+        Self::Dispatched,
+        Self::Copied,
+        Self::Mapped,
+        Self::Meshed,
+    ];
+
+    /// The colour of the stage in the timeline.
+    pub fn color(self) -> Color32 {
+        match self {
+            PipelineStage::Uploaded => Color32::from_rgb(90, 140, 220),
+            PipelineStage::Dispatched => Color32::from_rgb(170, 110, 220),
+            PipelineStage::Copied => Color32::from_rgb(230, 170, 60),
+            PipelineStage::Mapped => Color32::from_rgb(230, 110, 80),
+            PipelineStage::Meshed => Color32::from_rgb(90, 200, 110),
+        }
+    }
+}
+
+/// The set of [`PipelineStage`]s a chunk went through in a frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PipelineStages(u8);
+
+impl PipelineStages {
+    pub fn insert(&mut self, stage: PipelineStage) {
+        self.0 |= 1 << stage as u8;
+    }
+
+    pub fn contains(&self, stage: PipelineStage) -> bool {
+        self.0 & (1 << stage as u8) != 0
+    }
+
+    /// The last stage of the pipeline reached in the frame.
+    pub fn last(&self) -> Option<PipelineStage> {
+        PipelineStage::ALL
+            .into_iter()
+            .rev()
+            .find(|stage| self.contains(*stage))
+    }
+}
+
+/// The stages each chunk went through in a frame of the render world. Chunks idle in the frame
+/// are left out.
+#[derive(Clone, Debug, Default)]
+pub struct TimelineFrame {
+    pub frame: u32,
+    pub stages: HashMap<Entity, PipelineStages>,
+}
+
+/// The stages of the voxel pipeline each chunk went through over the last `max_frames` frames of
+/// the render world, as recorded by the [`PipelineTimelinePlugin`]. Chunks that stay in a stage
+/// for several frames point at a stall, e.g. readbacks queued behind the
+/// [`ReadbackPolicy`](crate::channels::ReadbackPolicy) or entities whose pipelines are not ready.
+#[derive(Resource, Clone, Debug)]
+pub struct PipelineTimeline {
+    /// Whether the timeline window is shown.
+    pub open: bool,
+    pub max_frames: usize,
+    /// Chunks shown in the window at most, those busy the most recently first.
+    pub max_rows: usize,
+    frames: VecDeque<TimelineFrame>,
+}
+
+impl Default for PipelineTimeline {
+    fn default() -> Self {
+        Self {
+            open: true,
+            max_frames: 120,
+            max_rows: 32,
+            frames: VecDeque::new(),
+        }
+    }
+}
+
+impl PipelineTimeline {
+    /// The recorded frames, oldest first.
+    pub fn frames(&self) -> impl Iterator<Item = &TimelineFrame> {
+        self.frames.iter()
+    }
+
+    /// Draws the timeline window, one row per chunk and one column per frame coloured by the last
+    /// stage the chunk reached in the frame.
+    pub fn show(mut contexts: EguiContexts, mut timeline: ResMut<Self>) {
+        let mut open = timeline.open;
+        if !open {
+            return;
+        }
+
+        let mut rows = Vec::new();
+        let mut seen = HashSet::new();
+        for frame in timeline.frames.iter().rev() {
+            for entity in frame.stages.keys() {
+                if rows.len() < timeline.max_rows && seen.insert(*entity) {
+                    rows.push(*entity);
+                }
+            }
+        }
+        rows.sort();
+
+        egui::Window::new("Voxel pipeline")
+            .open(&mut open)
+            .show(contexts.ctx_mut(), |ui| {
+                ui.horizontal(|ui| {
+                    for stage in PipelineStage::ALL {
+                        let (rect, _) =
+                            ui.allocate_exact_size(egui::vec2(10.0, 10.0), Sense::hover());
+                        ui.painter().rect_filled(rect, 0.0, stage.color());
+                        ui.label(format!("{stage:?}"));
+                    }
+                });
+                if let (Some(first), Some(last)) = (timeline.frames.front(), timeline.frames.back())
+                {
+                    ui.label(format!("Frames {} to {}", first.frame, last.frame));
+                }
+                ui.separator();
+
+                let cell = egui::vec2(4.0, 12.0);
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for entity in &rows {
+                        ui.horizontal(|ui| {
+                            ui.add_sized([80.0, cell.y], egui::Label::new(format!("{entity}")));
+                            let (rect, _) = ui.allocate_exact_size(
+                                egui::vec2(cell.x * timeline.max_frames as f32, cell.y),
+                                Sense::hover(),
+                            );
+                            ui.painter().rect_filled(rect, 0.0, Color32::from_gray(30));
+                            for (column, frame) in timeline.frames.iter().enumerate() {
+                                let Some(stage) =
+                                    frame.stages.get(entity).and_then(PipelineStages::last)
+                                else {
+                                    continue;
+                                };
+                                let min = rect.min + egui::vec2(column as f32 * cell.x, 0.0);
+                                ui.painter().rect_filled(
+                                    egui::Rect::from_min_size(min, cell),
+                                    0.0,
+                                    stage.color(),
+                                );
+                            }
+                        });
+                    }
+                });
+            });
+
+        timeline.open = open;
+    }
+}
+
+/// Shows an egui window of the [`PipelineTimeline`] of the chunks, to tune the budgets of the
+/// pipeline and spot stalls. Requires the [`EguiPlugin`](bevy_inspector_egui::bevy_egui::EguiPlugin)
+/// and must be added after the [`GpuReadbackPlugin`](crate::GpuReadbackPlugin).
+pub struct PipelineTimelinePlugin;
+
+impl Plugin for PipelineTimelinePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PipelineTimeline>().add_systems(
+            Update,
+            (
+                PipelineTimelineReceiver::receive,
+                PipelineTimeline::show.after(PipelineTimelineReceiver::receive),
+            ),
+        );
+    }
+
+    fn finish(&self, app: &mut App) {
+        let (s, r) = crossbeam_channel::unbounded();
+        app.insert_resource(PipelineTimelineReceiver(r));
+
+        app.sub_app_mut(RenderApp)
+            .init_resource::<PipelineTimelineRecorder>()
+            .insert_resource(PipelineTimelineSender(s))
+            .add_systems(
+                Render,
+                (
+                    PipelineTimelineRecorder::record_uploads.after(GpuVoxelMaterial::prepare),
+                    PipelineTimelineRecorder::record_dispatches
+                        .after(RenderSet::Render)
+                        .before(RenderWorldSender::map_and_read_buffer),
+                    PipelineTimelineSender::send.after(RenderWorldSender::map_and_read_buffer),
+                ),
+            );
+    }
+}
+
+#[derive(Resource, Deref)]
+pub struct PipelineTimelineReceiver(pub Receiver<TimelineFrame>);
+
+impl PipelineTimelineReceiver {
+    /// Appends the frames recorded in the render world to the [`PipelineTimeline`], dropping the
+    /// frames past its `max_frames`.
+    pub fn receive(receiver: Res<Self>, mut timeline: ResMut<PipelineTimeline>) {
+        for frame in receiver.try_iter() {
+            timeline.frames.push_back(frame);
+        }
+        while timeline.frames.len() > timeline.max_frames {
+            timeline.frames.pop_front();
+        }
+    }
+}
+
+/// The stages of the frame being rendered, and the offset of the next page of the readbacks
+/// copied this frame before they are mapped.
+#[derive(Resource, Default)]
+pub struct PipelineTimelineRecorder {
+    stages: HashMap<Entity, PipelineStages>,
+    copied: HashMap<Entity, u64>,
+}
+
+impl PipelineTimelineRecorder {
+    fn insert(&mut self, entity: Entity, stage: PipelineStage) {
+        self.stages.entry(entity).or_default().insert(stage);
+    }
+
+    /// Records the voxels uploaded this frame, as is or compressed, leaving out those deferred by
+    /// the [`UploadBudget`](crate::data::upload_budget::UploadBudget).
+    pub fn record_uploads(
+        mut recorder: ResMut<Self>,
+        upload_queue: Res<UploadQueue>,
+        gpu_compressed_voxels: Res<VoxelMaterialComponents<GpuCompressedVoxels>>,
+    ) {
+        let compressed = gpu_compressed_voxels
+            .0
+            .iter()
+            .filter(|(_, gpu_compressed)| gpu_compressed.voxel_count > 0)
+            .map(|(entity, _)| entity);
+        for entity in upload_queue.uploaded().iter().chain(compressed) {
+            recorder.insert(*entity, PipelineStage::Uploaded);
+        }
+    }
+
+    /// Records the entities the meshing node dispatched and copied into their staging buffers,
+    /// before their readbacks are mapped.
+    pub fn record_dispatches(
+        mut recorder: ResMut<Self>,
+        dirty_meshes: Res<DirtyMeshes>,
+        pending_readbacks: Res<PendingReadbacks>,
+        gpu_chunk_states: Res<GpuChunkStates>,
+        vertex_readbacks: Res<VoxelMaterialComponents<VertexReadback>>,
+    ) {
+        for entity in dirty_meshes.meshed() {
+            if gpu_chunk_states.is_ready(entity) {
+                recorder.insert(*entity, PipelineStage::Dispatched);
+            }
+        }
+        // Only the readbacks selected within the `ReadbackPolicy` are copied.
+        for entity in pending_readbacks.selected() {
+            recorder.insert(*entity, PipelineStage::Copied);
+            let offset = vertex_readbacks
+                .get(entity)
+                .map_or(0, |vertex_readback| vertex_readback.offset);
+            recorder.copied.insert(*entity, offset);
+        }
+    }
+}
+
+#[derive(Resource, Deref)]
+pub struct PipelineTimelineSender(pub Sender<TimelineFrame>);
+
+impl PipelineTimelineSender {
+    /// Records the readbacks mapped this frame, those that left the queue or read a page, and
+    /// sends the stages of the frame to the main world.
+    pub fn send(
+        sender: Res<Self>,
+        mut recorder: ResMut<PipelineTimelineRecorder>,
+        pending_readbacks: Res<PendingReadbacks>,
+        vertex_readbacks: Res<VoxelMaterialComponents<VertexReadback>>,
+        frame_count: Res<FrameCount>,
+    ) {
+        for (entity, offset) in std::mem::take(&mut recorder.copied) {
+            // Cancelled readbacks are removed.
+            let Some(vertex_readback) = vertex_readbacks.get(&entity) else {
+                continue;
+            };
+            let pending = pending_readbacks.contains(&entity);
+            if !pending || vertex_readback.offset != offset {
+                recorder.insert(entity, PipelineStage::Mapped);
+            }
+            if !pending && !vertex_readback.in_progress() {
+                recorder.insert(entity, PipelineStage::Meshed);
+            }
+        }
+
+        let _ = sender.send(TimelineFrame {
+            frame: frame_count.0,
+            stages: std::mem::take(&mut recorder.stages),
+        });
+    }
+}
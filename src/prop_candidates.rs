@@ -0,0 +1,83 @@
+use bevy::{
+    prelude::*,
+    render::{
+        extract_component::ExtractComponentPlugin, render_graph::RenderGraph, Render, RenderApp,
+        RenderSet,
+    },
+};
+
+use crate::{
+    channels::{ReadbackAppExt, ReadbackChannel, ReadbackPlugin, ReadbackReceived},
+    data::{
+        chunk_retirement::ChunkRetirementExt,
+        gpu_prop_candidates::GpuPropCandidates,
+        prop_candidates::{PropCandidateData, PropCandidates},
+        voxel_material::VoxelMaterialComponents,
+    },
+    render::{
+        prop_candidates_compute_pipeline::{
+            PropCandidatesComputeNode, PropCandidatesComputeNodeLabel,
+            PropCandidatesComputePipeline,
+        },
+        voxel_mesh_compute_pipeline::{DirtyMeshes, VoxelMeshComputeNodeLabel},
+    },
+};
+
+/// Emits the [`PropCandidates`] of volumetric entities on the GPU whenever they are remeshed or
+/// their settings change, reading them back through a [`ReadbackChannel`] of
+/// [`PropCandidateData`].
+///
+/// Must be added after the [`GpuReadbackPlugin`](crate::GpuReadbackPlugin).
+pub struct PropCandidatesPlugin;
+
+impl PropCandidatesPlugin {
+    /// Replaces the [`PropCandidates`] of the entities whose points were read back.
+    pub fn receive(
+        mut readback_received: EventReader<ReadbackReceived<PropCandidateData>>,
+        mut candidates_query: Query<&mut PropCandidates>,
+    ) {
+        for received in readback_received.read() {
+            let Ok(mut prop_candidates) = candidates_query.get_mut(received.entity) else {
+                continue;
+            };
+            prop_candidates.apply(&received.data);
+        }
+    }
+}
+
+impl Plugin for PropCandidatesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            ExtractComponentPlugin::<PropCandidates>::default(),
+            ReadbackPlugin::<PropCandidateData>::default(),
+        ))
+        .add_readback_producer(GpuPropCandidates::map_and_read_buffers)
+        .add_systems(
+            Update,
+            Self::receive.after(ReadbackChannel::<PropCandidateData>::receive),
+        );
+    }
+
+    fn finish(&self, app: &mut App) {
+        let render_app = app.sub_app_mut(RenderApp);
+
+        render_app
+            .init_resource::<PropCandidatesComputePipeline>()
+            .init_resource::<VoxelMaterialComponents<GpuPropCandidates>>()
+            .free_on_retire::<GpuPropCandidates>()
+            .add_systems(ExtractSchedule, GpuPropCandidates::extract)
+            .add_systems(
+                Render,
+                GpuPropCandidates::prepare
+                    .in_set(RenderSet::PrepareBindGroups)
+                    .after(DirtyMeshes::select),
+            );
+
+        let candidates_compute_node = PropCandidatesComputeNode::from_world(render_app.world_mut());
+
+        let mut render_graph = render_app.world_mut().resource_mut::<RenderGraph>();
+
+        render_graph.add_node(PropCandidatesComputeNodeLabel, candidates_compute_node);
+        render_graph.add_node_edge(VoxelMeshComputeNodeLabel, PropCandidatesComputeNodeLabel);
+    }
+}
@@ -0,0 +1,364 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Read, Write},
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::{
+    prelude::*,
+    render::{
+        mesh::{Indices, PrimitiveTopology},
+        render_asset::RenderAssetUsages,
+    },
+};
+
+use crate::{
+    bundles::volumetric_bundle::Volumetric,
+    channels::VoxelDataVersion,
+    data::{
+        iso_surface::ATTRIBUTE_EMISSIVE,
+        material_split::{MaterialIndexRanges, MAX_MATERIALS},
+        raw_mesh_data::RawMeshData,
+        readback_header::MeshReadbackHeader,
+    },
+    invalid_data,
+};
+
+const DUMP_MAGIC: &[u8; 4] = b"VXRB";
+
+/// Dumps the mesh read back for every volumetric entity whenever it is remeshed to a timestamped
+/// file of `directory`, to reproduce GPU meshing bugs offline by loading the dump back with
+/// [`ReadbackDump::load`]. While enabled, every volumetric entity is opted into [`RawMeshData`].
+#[derive(Resource, Clone, Debug)]
+pub struct ReadbackCapture {
+    pub enabled: bool,
+    pub directory: PathBuf,
+}
+
+impl Default for ReadbackCapture {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            directory: PathBuf::from("readback_captures"),
+        }
+    }
+}
+
+/// Marks the entities opted into [`RawMeshData`] by [`ReadbackCapture`] rather than by the user, so
+/// that they are opted out again when it is disabled.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct CaptureReadback;
+
+impl ReadbackCapture {
+    /// Opts the volumetric entities into [`RawMeshData`] while enabled, and out when disabled.
+    #[allow(clippy::type_complexity)]
+    pub fn toggle_readback(
+        mut commands: Commands,
+        settings: Res<Self>,
+        missing_query: Query<Entity, (With<Volumetric>, Without<RawMeshData>)>,
+        capture_query: Query<Entity, With<CaptureReadback>>,
+    ) {
+        if settings.enabled {
+            for entity in missing_query.iter() {
+                commands
+                    .entity(entity)
+                    .insert((RawMeshData::default(), CaptureReadback));
+            }
+        } else {
+            for entity in capture_query.iter() {
+                commands
+                    .entity(entity)
+                    .remove::<(RawMeshData, CaptureReadback)>();
+            }
+        }
+    }
+
+    /// Dumps the [`RawMeshData`] read back since the last frame.
+    pub fn capture(
+        settings: Res<Self>,
+        raw_mesh_query: Query<(Entity, Ref<RawMeshData>, Option<&VoxelDataVersion>)>,
+    ) {
+        if !settings.enabled {
+            return;
+        }
+
+        for (entity, raw_mesh_data, version) in raw_mesh_query.iter() {
+            // Skip the empty mesh inserted to opt the entity in.
+            if !raw_mesh_data.is_changed() || raw_mesh_data.is_added() {
+                continue;
+            }
+
+            let dump = ReadbackDump {
+                entity,
+                version: version.map_or(0, |version| version.0),
+                mesh: raw_mesh_data.clone(),
+            };
+            let path = settings.directory.join(dump.file_name());
+            match fs::create_dir_all(&settings.directory).and_then(|_| dump.write(&path)) {
+                Ok(()) => info!("Captured the mesh readback of {entity:?} to {path:?}"),
+                Err(err) => warn!("Failed to capture the mesh readback of {entity:?}: {err}"),
+            }
+        }
+    }
+}
+
+/// The mesh read back for a volumetric entity and the voxels it was meshed from, as dumped by
+/// [`ReadbackCapture`].
+///
+/// The format is the magic `VXRB`, a [`MeshReadbackHeader`], the bits of the entity as a `u64`, its
+/// [`VoxelDataVersion`] and the number of material ranges as `u32`s, then the vertices as
+/// `vec3<f32>` with a 16 byte stride, the indices, the start and end of each material range, and
+/// the UV, tangent and emissive color of each vertex, all little endian. Dumps ending before the
+/// UVs and tangents, or before the emissive colors, load with none.
+#[derive(Clone, Debug)]
+pub struct ReadbackDump {
+    pub entity: Entity,
+    pub version: u32,
+    pub mesh: RawMeshData,
+}
+
+impl ReadbackDump {
+    /// The name of the file of the dump, unique to the entity, its version and the time.
+    pub fn file_name(&self) -> String {
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_millis());
+        format!(
+            "{millis}_{}v{}_{}.vxrb",
+            self.entity.index(),
+            self.entity.generation(),
+            self.version
+        )
+    }
+
+    /// The header the dump is written with.
+    pub fn header(&self) -> MeshReadbackHeader {
+        let flags = if self.mesh.material_ranges.is_some() {
+            MeshReadbackHeader::MATERIAL_SPLIT
+        } else {
+            0
+        };
+        MeshReadbackHeader::new(
+            self.mesh.vertices.len() as u32,
+            self.mesh.indices.len() as u32,
+            flags,
+        )
+    }
+
+    /// Writes the dump to a file at `path`.
+    pub fn write(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+
+        writer.write_all(DUMP_MAGIC)?;
+        writer.write_all(&self.header().to_bytes())?;
+        writer.write_all(&self.entity.to_bits().to_le_bytes())?;
+        writer.write_all(&self.version.to_le_bytes())?;
+        let ranges = self
+            .mesh
+            .material_ranges
+            .as_ref()
+            .map_or(&[][..], |ranges| &ranges.0);
+        writer.write_all(&(ranges.len() as u32).to_le_bytes())?;
+
+        for vertex in self.mesh.vertices.iter() {
+            for component in vertex.extend(0.0).to_array() {
+                writer.write_all(&component.to_le_bytes())?;
+            }
+        }
+        for index in self.mesh.indices.iter() {
+            writer.write_all(&index.to_le_bytes())?;
+        }
+        for range in ranges {
+            writer.write_all(&range.start.to_le_bytes())?;
+            writer.write_all(&range.end.to_le_bytes())?;
+        }
+        let uvs = self.mesh.uvs.iter().flat_map(|uv| uv.to_array());
+        let tangents = self
+            .mesh
+            .tangents
+            .iter()
+            .flat_map(|tangent| tangent.to_array());
+        let emissives = self
+            .mesh
+            .emissives
+            .iter()
+            .flat_map(|emissive| emissive.to_array());
+        for component in uvs.chain(tangents).chain(emissives) {
+            writer.write_all(&component.to_le_bytes())?;
+        }
+
+        writer.into_inner()?.sync_all()
+    }
+
+    /// Reads a dump written by [`ReadbackDump::write`], failing if it is truncated or its header
+    /// was written with another [`MESH_READBACK_VERSION`](crate::data::readback_header::MESH_READBACK_VERSION).
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut data = Vec::new();
+        File::open(path)?.read_to_end(&mut data)?;
+
+        let Some(data) = data.strip_prefix(DUMP_MAGIC) else {
+            return Err(invalid_data("not a mesh readback dump"));
+        };
+        let header = MeshReadbackHeader::from_bytes(data)?;
+        let mut words = data[MeshReadbackHeader::SIZE as usize..]
+            .chunks_exact(4)
+            .map(|word| u32::from_le_bytes(word.try_into().expect("should be a u32")));
+        let mut next_word = || {
+            words
+                .next()
+                .ok_or_else(|| invalid_data("truncated mesh readback dump"))
+        };
+
+        let entity =
+            Entity::try_from_bits(u64::from(next_word()?) | (u64::from(next_word()?) << 32))
+                .map_err(|err| invalid_data(err.to_string()))?;
+        let version = next_word()?;
+        let range_count = next_word()? as usize;
+        if range_count != 0 && range_count != MAX_MATERIALS {
+            return Err(invalid_data(format!(
+                "mesh readback dump of {range_count} material ranges instead of {MAX_MATERIALS}"
+            )));
+        }
+
+        let vertices = (0..header.vertex_count)
+            .map(|_| {
+                let [x, y, z, _] = [(); 4].map(|_| next_word().map(f32::from_bits));
+                Ok(Vec3::new(x?, y?, z?))
+            })
+            .collect::<io::Result<Arc<[Vec3]>>>()?;
+        let indices = (0..header.index_count)
+            .map(|_| next_word())
+            .collect::<io::Result<Arc<[u32]>>>()?;
+        let ranges = (0..range_count)
+            .map(|_| Ok::<Range<u32>, io::Error>(next_word()?..next_word()?))
+            .collect::<io::Result<Vec<_>>>()?;
+        let material_ranges = (range_count != 0)
+            .then(|| MaterialIndexRanges(std::array::from_fn(|material| ranges[material].clone())));
+
+        let next_f32 = |words: &mut dyn Iterator<Item = u32>| {
+            words
+                .next()
+                .map(f32::from_bits)
+                .ok_or_else(|| invalid_data("truncated mesh readback dump"))
+        };
+        let (uvs, tangents) = match words.len() {
+            0 => (Arc::from([]), Arc::from([])),
+            _ => {
+                let uvs = (0..header.vertex_count)
+                    .map(|_| Ok(Vec2::new(next_f32(&mut words)?, next_f32(&mut words)?)))
+                    .collect::<io::Result<Arc<[Vec2]>>>()?;
+                let tangents = (0..header.vertex_count)
+                    .map(|_| {
+                        let [x, y, z, w] = [(); 4].map(|_| next_f32(&mut words));
+                        Ok(Vec4::new(x?, y?, z?, w?))
+                    })
+                    .collect::<io::Result<Arc<[Vec4]>>>()?;
+                (uvs, tangents)
+            }
+        };
+        let emissives = match words.len() {
+            0 => Arc::from([]),
+            _ => (0..header.vertex_count)
+                .map(|_| {
+                    let [x, y, z] = [(); 3].map(|_| next_f32(&mut words));
+                    Ok(Vec3::new(x?, y?, z?))
+                })
+                .collect::<io::Result<Arc<[Vec3]>>>()?,
+        };
+
+        Ok(Self {
+            entity,
+            version,
+            mesh: RawMeshData {
+                vertices,
+                uvs,
+                tangents,
+                emissives,
+                indices,
+                material_ranges,
+            },
+        })
+    }
+
+    /// A static mesh of the dumped vertices, UVs, tangents, emissive colors and indices, with
+    /// smooth normals computed from its triangles. The indices of split materials are concatenated, skipping the
+    /// unused room between their ranges.
+    pub fn into_mesh(self) -> Mesh {
+        let indices = match &self.mesh.material_ranges {
+            Some(ranges) => ranges
+                .0
+                .iter()
+                .flat_map(|range| {
+                    self.mesh
+                        .indices
+                        .get(range.start as usize..range.end as usize)
+                        .unwrap_or_default()
+                })
+                .copied()
+                .collect(),
+            None => self.mesh.indices.to_vec(),
+        };
+
+        let mut mesh = Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::default(),
+        );
+        mesh.insert_attribute(
+            Mesh::ATTRIBUTE_POSITION,
+            self.mesh
+                .vertices
+                .iter()
+                .map(|vertex| vertex.to_array())
+                .collect::<Vec<_>>(),
+        );
+        if self.mesh.uvs.len() == self.mesh.vertices.len() {
+            mesh.insert_attribute(
+                Mesh::ATTRIBUTE_UV_0,
+                self.mesh
+                    .uvs
+                    .iter()
+                    .map(|uv| uv.to_array())
+                    .collect::<Vec<_>>(),
+            );
+        }
+        if self.mesh.tangents.len() == self.mesh.vertices.len() {
+            mesh.insert_attribute(
+                Mesh::ATTRIBUTE_TANGENT,
+                self.mesh
+                    .tangents
+                    .iter()
+                    .map(|tangent| tangent.to_array())
+                    .collect::<Vec<_>>(),
+            );
+        }
+        if self.mesh.emissives.len() == self.mesh.vertices.len() {
+            mesh.insert_attribute(
+                ATTRIBUTE_EMISSIVE,
+                self.mesh
+                    .emissives
+                    .iter()
+                    .map(|emissive| emissive.to_array())
+                    .collect::<Vec<_>>(),
+            );
+        }
+        mesh.insert_indices(Indices::U32(indices));
+        mesh.compute_smooth_normals();
+        mesh
+    }
+}
+
+/// Dumps the meshes read back from the compute shader while [`ReadbackCapture`] is enabled. Must be
+/// added after the [`GpuReadbackPlugin`](crate::GpuReadbackPlugin).
+pub struct ReadbackCapturePlugin;
+
+impl Plugin for ReadbackCapturePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ReadbackCapture>().add_systems(
+            PostUpdate,
+            (ReadbackCapture::toggle_readback, ReadbackCapture::capture),
+        );
+    }
+}
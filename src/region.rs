@@ -0,0 +1,222 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use bevy::{
+    prelude::*,
+    utils::{BoxedFuture, HashMap},
+};
+use memmap2::Mmap;
+
+use crate::{
+    data::{chunk_coord::ChunkCoord, voxel::Voxel, voxel_material::VoxelMaterial},
+    streaming::{ChunkData, ChunkProvider},
+    CHUNK_SZ_3,
+};
+
+const REGION_MAGIC: &[u8; 4] = b"VXRG";
+const REGION_VERSION: u32 = 2;
+const HEADER_SIZE: usize = 12;
+const ENTRY_SIZE: usize = 32;
+const VOXEL_SIZE: usize = 12;
+
+/// Where the voxels of a chunk are in a [`RegionFile`].
+#[derive(Clone, Copy)]
+struct RegionEntry {
+    chunk_size: u32,
+    offset: usize,
+    len: usize,
+}
+
+/// The voxels of the chunks of a huge volume, e.g. a scan, in a single file that is memory mapped
+/// rather than read, so that only the pages of the chunks actually accessed are loaded. The file
+/// starts with a table of its chunks followed by their voxels, as written by
+/// [`RegionFile::write`].
+///
+/// Chunks are decoded when fetched, which makes the region a [`ChunkProvider`] streaming only the
+/// visited chunks into memory, as long as the unloaded ones are also evicted from the
+/// [`ChunkStreaming`](crate::streaming::ChunkStreaming) cache.
+pub struct RegionFile {
+    mmap: Mmap,
+    entries: HashMap<ChunkCoord, RegionEntry>,
+}
+
+impl RegionFile {
+    /// Maps the region file at `path` and reads its table of chunks.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = File::open(path)?;
+        // Safety: the file must not be truncated while mapped, as with any memory mapped file.
+        let mmap = unsafe { Mmap::map(&file)? };
+
+        if mmap.len() < HEADER_SIZE || &mmap[0..4] != REGION_MAGIC {
+            return Err(invalid_data("not a region file"));
+        }
+        let version = read_u32(&mmap, 4);
+        if version != REGION_VERSION {
+            return Err(invalid_data(format!(
+                "unsupported region version {version}"
+            )));
+        }
+
+        let chunk_count = read_u32(&mmap, 8) as usize;
+        let table_end = HEADER_SIZE + chunk_count * ENTRY_SIZE;
+        if mmap.len() < table_end {
+            return Err(invalid_data("truncated region table"));
+        }
+
+        let mut entries = HashMap::with_capacity(chunk_count);
+        for index in 0..chunk_count {
+            let entry = HEADER_SIZE + index * ENTRY_SIZE;
+            let coord = ChunkCoord(IVec3::new(
+                read_u32(&mmap, entry) as i32,
+                read_u32(&mmap, entry + 4) as i32,
+                read_u32(&mmap, entry + 8) as i32,
+            ));
+            let chunk_size = read_u32(&mmap, entry + 12);
+            let offset = read_u64(&mmap, entry + 16) as usize;
+            let len = read_u64(&mmap, entry + 24) as usize;
+
+            if len
+                .checked_mul(VOXEL_SIZE)
+                .and_then(|size| offset.checked_add(size))
+                .is_none_or(|end| end > mmap.len())
+            {
+                return Err(invalid_data(format!(
+                    "chunk {} is outside of the region",
+                    coord.0
+                )));
+            }
+            entries.insert(
+                coord,
+                RegionEntry {
+                    chunk_size,
+                    offset,
+                    len,
+                },
+            );
+        }
+
+        Ok(Self { mmap, entries })
+    }
+
+    /// Writes `chunks` to a region file at `path`. Send a
+    /// [`ChunksSaved`](crate::journal::ChunksSaved) of their coordinates once it succeeds for the
+    /// edit journal to drop their edits.
+    pub fn write<'a>(
+        path: impl AsRef<Path>,
+        chunks: impl IntoIterator<Item = (ChunkCoord, &'a VoxelMaterial)>,
+    ) -> io::Result<()> {
+        let chunks = chunks.into_iter().collect::<Vec<_>>();
+        let mut writer = BufWriter::new(File::create(path)?);
+
+        writer.write_all(REGION_MAGIC)?;
+        writer.write_all(&REGION_VERSION.to_le_bytes())?;
+        writer.write_all(&(chunks.len() as u32).to_le_bytes())?;
+
+        let mut offset = (HEADER_SIZE + chunks.len() * ENTRY_SIZE) as u64;
+        for (coord, voxel_material) in &chunks {
+            for component in coord.0.to_array() {
+                writer.write_all(&component.to_le_bytes())?;
+            }
+            writer.write_all(&voxel_material.chunk_size.to_le_bytes())?;
+            writer.write_all(&offset.to_le_bytes())?;
+            writer.write_all(&(voxel_material.voxels().len() as u64).to_le_bytes())?;
+            offset += (voxel_material.voxels().len() * VOXEL_SIZE) as u64;
+        }
+
+        for (_, voxel_material) in &chunks {
+            for voxel in voxel_material.voxels() {
+                writer.write_all(&voxel.flags().to_le_bytes())?;
+                writer.write_all(&voxel.density().to_le_bytes())?;
+                writer.write_all(&voxel.emissive_bits().to_le_bytes())?;
+            }
+        }
+
+        writer.into_inner()?.sync_all()
+    }
+
+    pub fn contains(&self, coord: ChunkCoord) -> bool {
+        self.entries.contains_key(&coord)
+    }
+
+    pub fn coords(&self) -> impl Iterator<Item = ChunkCoord> + '_ {
+        self.entries.keys().copied()
+    }
+
+    /// A view of the voxels of the chunk at `coord`, decoding nothing until they are accessed.
+    pub fn view(&self, coord: ChunkCoord) -> Option<VoxelMaterialView<'_>> {
+        self.entries.get(&coord).map(|entry| VoxelMaterialView {
+            chunk_size: entry.chunk_size,
+            data: &self.mmap[entry.offset..entry.offset + entry.len * VOXEL_SIZE],
+        })
+    }
+}
+
+/// The voxels of a chunk of a [`RegionFile`], decoded from the mapped file on access.
+#[derive(Clone, Copy)]
+pub struct VoxelMaterialView<'a> {
+    chunk_size: u32,
+    data: &'a [u8],
+}
+
+impl VoxelMaterialView<'_> {
+    pub fn len(&self) -> usize {
+        self.data.len() / VOXEL_SIZE
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn voxel(&self, index: usize) -> Option<Voxel> {
+        (index < self.len()).then(|| {
+            let voxel = index * VOXEL_SIZE;
+            Voxel::new(
+                read_u32(self.data, voxel),
+                f32::from_bits(read_u32(self.data, voxel + 4)),
+            )
+            .with_emissive_bits(read_u32(self.data, voxel + 8))
+        })
+    }
+
+    pub fn voxels(&self) -> impl Iterator<Item = Voxel> + '_ {
+        (0..self.len()).filter_map(|index| self.voxel(index))
+    }
+
+    /// Decodes all the voxels of the chunk.
+    pub fn to_voxel_material(&self) -> VoxelMaterial {
+        VoxelMaterial::new(self.voxels().collect(), self.chunk_size)
+    }
+}
+
+impl ChunkProvider for RegionFile {
+    /// Decodes the chunk at `coord`, or an empty chunk if the region doesn't have it.
+    fn fetch(&self, coord: ChunkCoord) -> BoxedFuture<'_, io::Result<ChunkData>> {
+        Box::pin(async move {
+            Ok(match self.view(coord) {
+                Some(view) => ChunkData {
+                    voxels: view.voxels().collect(),
+                    chunk_size: view.chunk_size,
+                },
+                None => ChunkData {
+                    voxels: vec![Voxel::new(0, 0.0); CHUNK_SZ_3],
+                    chunk_size: CHUNK_SZ_3 as u32,
+                },
+            })
+        })
+    }
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+fn invalid_data(err: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
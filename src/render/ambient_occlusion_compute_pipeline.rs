@@ -0,0 +1,142 @@
+use bevy::{
+    prelude::*,
+    render::{
+        render_asset::RenderAssets,
+        render_graph::{self, RenderLabel},
+        render_resource::{
+            binding_types::{storage_buffer_read_only, uniform_buffer},
+            *,
+        },
+        renderer::{RenderContext, RenderDevice},
+        texture::GpuImage,
+    },
+};
+
+use crate::{
+    data::{
+        ambient_occlusion::{AmbientOcclusionParams, AmbientOcclusionVolume},
+        gpu_ambient_occlusion::GpuAmbientOcclusion,
+        voxel_material::VoxelMaterialComponents,
+    },
+    render::voxel_mesh_compute_pipeline::VoxelBuffer,
+};
+
+const SHADER_ASSET_PATH: &str = "shaders/ambient_occlusion.wgsl";
+
+#[derive(Resource)]
+pub struct AmbientOcclusionComputePipeline {
+    pub bind_group_layout: BindGroupLayout,
+    pub pipeline: CachedComputePipelineId,
+}
+
+impl FromWorld for AmbientOcclusionComputePipeline {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+
+        let bind_group_layout = render_device.create_bind_group_layout(
+            Some("AmbientOcclusionComputePipeline::bind_group_layout"),
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::COMPUTE,
+                (
+                    uniform_buffer::<AmbientOcclusionParams>(false),
+                    storage_buffer_read_only::<VoxelBuffer>(false),
+                    BindingType::StorageTexture {
+                        access: StorageTextureAccess::WriteOnly,
+                        format: TextureFormat::Rgba8Unorm,
+                        view_dimension: TextureViewDimension::D3,
+                    },
+                ),
+            ),
+        );
+
+        let shader = world.load_asset(SHADER_ASSET_PATH);
+
+        let pipeline_cache = world.resource::<PipelineCache>();
+
+        let pipeline = pipeline_cache.queue_compute_pipeline(ComputePipelineDescriptor {
+            label: Some("AmbientOcclusionComputePipeline shader".into()),
+            layout: vec![bind_group_layout.clone()],
+            push_constant_ranges: Vec::new(),
+            shader,
+            shader_defs: Vec::new(),
+            entry_point: "main".into(),
+        });
+
+        AmbientOcclusionComputePipeline {
+            bind_group_layout,
+            pipeline,
+        }
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+pub struct AmbientOcclusionComputeNodeLabel;
+
+/// Regenerates the occlusion texture of every [`AmbientOcclusionVolume`] from its voxels.
+pub struct AmbientOcclusionComputeNode {
+    ambient_occlusion_query: QueryState<Entity, With<AmbientOcclusionVolume>>,
+}
+
+impl FromWorld for AmbientOcclusionComputeNode {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            ambient_occlusion_query: world.query_filtered(),
+        }
+    }
+}
+
+impl render_graph::Node for AmbientOcclusionComputeNode {
+    fn update(&mut self, world: &mut World) {
+        self.ambient_occlusion_query.update_archetypes(world);
+    }
+
+    fn run(
+        &self,
+        _graph: &mut render_graph::RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), render_graph::NodeRunError> {
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let ambient_occlusion_pipeline = world.resource::<AmbientOcclusionComputePipeline>();
+        let gpu_images = world.resource::<RenderAssets<GpuImage>>();
+        let gpu_ambient_occlusions =
+            world.resource::<VoxelMaterialComponents<GpuAmbientOcclusion>>();
+
+        let Some(pipeline) =
+            pipeline_cache.get_compute_pipeline(ambient_occlusion_pipeline.pipeline)
+        else {
+            return Ok(()); // the pipeline is not loaded yet
+        };
+
+        let command_encoder = render_context.command_encoder();
+
+        for entity in self.ambient_occlusion_query.iter_manual(world) {
+            let Some(gpu_ambient_occlusion) = gpu_ambient_occlusions.get(&entity) else {
+                continue;
+            };
+            let (Some(bind_group), Some(gpu_image)) = (
+                gpu_ambient_occlusion.bind_group.as_ref(),
+                gpu_images.get(&gpu_ambient_occlusion.image),
+            ) else {
+                continue;
+            };
+
+            let size = UVec3::new(
+                gpu_image.texture.width(),
+                gpu_image.texture.height(),
+                gpu_image.texture.depth_or_array_layers(),
+            );
+            let workgroups = (size + 3) / 4;
+
+            let mut pass = command_encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some("ambient_occlusion"),
+                ..default()
+            });
+            pass.set_bind_group(0, bind_group, &[]);
+            pass.set_pipeline(pipeline);
+            pass.dispatch_workgroups(workgroups.x, workgroups.y, workgroups.z);
+        }
+
+        Ok(())
+    }
+}
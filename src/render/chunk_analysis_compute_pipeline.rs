@@ -0,0 +1,142 @@
+use bevy::{
+    prelude::*,
+    render::{
+        render_graph::{self, RenderLabel},
+        render_resource::{
+            binding_types::{storage_buffer, storage_buffer_read_only, uniform_buffer},
+            *,
+        },
+        renderer::{RenderContext, RenderDevice},
+    },
+};
+
+use crate::{
+    data::{
+        chunk_analysis::{ChunkAnalysisCounters, ChunkAnalysisParams, ChunkAnalysisReadback},
+        gpu_chunk_analysis::GpuChunkAnalysis,
+        voxel_material::VoxelMaterialComponents,
+    },
+    render::voxel_mesh_compute_pipeline::VoxelBuffer,
+};
+
+const SHADER_ASSET_PATH: &str = "shaders/chunk_analysis.wgsl";
+
+const WORKGROUP_SIZE: u32 = 256;
+
+#[derive(Resource)]
+pub struct ChunkAnalysisComputePipeline {
+    pub bind_group_layout: BindGroupLayout,
+    pub pipeline: CachedComputePipelineId,
+}
+
+impl FromWorld for ChunkAnalysisComputePipeline {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+
+        let bind_group_layout = render_device.create_bind_group_layout(
+            Some("ChunkAnalysisComputePipeline::bind_group_layout"),
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::COMPUTE,
+                (
+                    uniform_buffer::<ChunkAnalysisParams>(false),
+                    storage_buffer_read_only::<VoxelBuffer>(false),
+                    storage_buffer::<ChunkAnalysisCounters>(false),
+                ),
+            ),
+        );
+
+        let shader = world.load_asset(SHADER_ASSET_PATH);
+
+        let pipeline_cache = world.resource::<PipelineCache>();
+
+        let pipeline = pipeline_cache.queue_compute_pipeline(ComputePipelineDescriptor {
+            label: Some("ChunkAnalysisComputePipeline shader".into()),
+            layout: vec![bind_group_layout.clone()],
+            push_constant_ranges: Vec::new(),
+            shader,
+            shader_defs: Vec::new(),
+            entry_point: "main".into(),
+        });
+
+        ChunkAnalysisComputePipeline {
+            bind_group_layout,
+            pipeline,
+        }
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+pub struct ChunkAnalysisComputeNodeLabel;
+
+/// Recomputes the counters of every [`ChunkAnalysisReadback`] entity whose voxels changed and
+/// copies them to its staging buffer.
+pub struct ChunkAnalysisComputeNode {
+    analysis_query: QueryState<Entity, With<ChunkAnalysisReadback>>,
+}
+
+impl FromWorld for ChunkAnalysisComputeNode {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            analysis_query: world.query_filtered(),
+        }
+    }
+}
+
+impl render_graph::Node for ChunkAnalysisComputeNode {
+    fn update(&mut self, world: &mut World) {
+        self.analysis_query.update_archetypes(world);
+    }
+
+    fn run(
+        &self,
+        _graph: &mut render_graph::RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), render_graph::NodeRunError> {
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let analysis_pipeline = world.resource::<ChunkAnalysisComputePipeline>();
+        let gpu_chunk_analyses = world.resource::<VoxelMaterialComponents<GpuChunkAnalysis>>();
+
+        let Some(pipeline) = pipeline_cache.get_compute_pipeline(analysis_pipeline.pipeline) else {
+            return Ok(()); // the pipeline is not loaded yet
+        };
+
+        let command_encoder = render_context.command_encoder();
+
+        for entity in self.analysis_query.iter_manual(world) {
+            let Some(gpu_analysis) = gpu_chunk_analyses.get(&entity) else {
+                continue;
+            };
+            if !gpu_analysis.changed {
+                continue;
+            }
+            let Some(bind_group) = gpu_analysis.bind_group.as_ref() else {
+                continue;
+            };
+
+            let voxel_count = gpu_analysis.params_buffer.get().voxel_count;
+
+            command_encoder.clear_buffer(&gpu_analysis.counters_buffer, 0, None);
+
+            {
+                let mut pass = command_encoder.begin_compute_pass(&ComputePassDescriptor {
+                    label: Some("chunk_analysis"),
+                    ..default()
+                });
+                pass.set_bind_group(0, bind_group, &[]);
+                pass.set_pipeline(pipeline);
+                pass.dispatch_workgroups(voxel_count.div_ceil(WORKGROUP_SIZE), 1, 1);
+            }
+
+            command_encoder.copy_buffer_to_buffer(
+                &gpu_analysis.counters_buffer,
+                0,
+                &gpu_analysis.counters_staging_buffer,
+                0,
+                gpu_analysis.counters_buffer.size(),
+            );
+        }
+
+        Ok(())
+    }
+}
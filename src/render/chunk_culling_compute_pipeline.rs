@@ -0,0 +1,179 @@
+use bevy::{
+    prelude::*,
+    render::{
+        render_graph::{self, RenderLabel, ViewNode},
+        render_resource::{
+            binding_types::{storage_buffer, storage_buffer_read_only, texture_2d, uniform_buffer},
+            *,
+        },
+        renderer::{RenderContext, RenderDevice},
+    },
+};
+
+use crate::data::{
+    atomics::Atomics,
+    chunk_culling::{ChunkCullingParams, ChunkCullingView, ChunkDrawArgsBuffer},
+    cluster_bounds::ClusterBoundBuffer,
+    gpu_chunk_culling::GpuChunkCulling,
+    voxel_material::VoxelMaterialComponents,
+};
+
+const SHADER_ASSET_PATH: &str = "shaders/chunk_culling.wgsl";
+
+const WORKGROUP_SIZE: u32 = 64;
+
+#[derive(Resource)]
+pub struct ChunkCullingComputePipeline {
+    /// Binds the view and its depth pyramid.
+    pub view_bind_group_layout: BindGroupLayout,
+    /// Binds a chunk drawn one segment of its index buffer at a time and its args.
+    pub segment_bind_group_layout: BindGroupLayout,
+    /// Binds a chunk drawn one cluster at a time, its args and its cluster bounds.
+    pub cluster_bind_group_layout: BindGroupLayout,
+    pub segment_pipeline: CachedComputePipelineId,
+    pub cluster_pipeline: CachedComputePipelineId,
+}
+
+impl ChunkCullingComputePipeline {
+    /// The pipeline culling the draws of a chunk, its clusters or its segments.
+    pub fn pipeline(&self, clusters: bool) -> CachedComputePipelineId {
+        match clusters {
+            true => self.cluster_pipeline,
+            false => self.segment_pipeline,
+        }
+    }
+}
+
+impl FromWorld for ChunkCullingComputePipeline {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+
+        let view_bind_group_layout = render_device.create_bind_group_layout(
+            Some("ChunkCullingComputePipeline::view_bind_group_layout"),
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::COMPUTE,
+                (
+                    uniform_buffer::<ChunkCullingView>(false),
+                    texture_2d(TextureSampleType::Float { filterable: false }),
+                ),
+            ),
+        );
+        let segment_bind_group_layout = render_device.create_bind_group_layout(
+            Some("ChunkCullingComputePipeline::segment_bind_group_layout"),
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::COMPUTE,
+                (
+                    uniform_buffer::<ChunkCullingParams>(false),
+                    storage_buffer_read_only::<Atomics>(false),
+                    storage_buffer::<ChunkDrawArgsBuffer>(false),
+                ),
+            ),
+        );
+        let cluster_bind_group_layout = render_device.create_bind_group_layout(
+            Some("ChunkCullingComputePipeline::cluster_bind_group_layout"),
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::COMPUTE,
+                (
+                    uniform_buffer::<ChunkCullingParams>(false),
+                    storage_buffer_read_only::<Atomics>(false),
+                    storage_buffer::<ChunkDrawArgsBuffer>(false),
+                    storage_buffer_read_only::<ClusterBoundBuffer>(false),
+                ),
+            ),
+        );
+
+        let shader: Handle<Shader> = world.load_asset(SHADER_ASSET_PATH);
+
+        let pipeline_cache = world.resource::<PipelineCache>();
+
+        let queue = |label: &'static str, layout: &BindGroupLayout, shader_defs| {
+            pipeline_cache.queue_compute_pipeline(ComputePipelineDescriptor {
+                label: Some(label.into()),
+                layout: vec![view_bind_group_layout.clone(), layout.clone()],
+                push_constant_ranges: Vec::new(),
+                shader: shader.clone(),
+                shader_defs,
+                entry_point: "main".into(),
+            })
+        };
+
+        let segment_pipeline = queue(
+            "ChunkCullingComputePipeline segment shader",
+            &segment_bind_group_layout,
+            Vec::new(),
+        );
+        let cluster_pipeline = queue(
+            "ChunkCullingComputePipeline cluster shader",
+            &cluster_bind_group_layout,
+            vec!["CLUSTERS".into()],
+        );
+
+        ChunkCullingComputePipeline {
+            view_bind_group_layout,
+            segment_bind_group_layout,
+            cluster_bind_group_layout,
+            segment_pipeline,
+            cluster_pipeline,
+        }
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+pub struct ChunkCullingNodeLabel;
+
+/// Writes the culled draws of every chunk of the [`GpuChunkCulling`] of the view, one dispatch per
+/// chunk.
+#[derive(Default)]
+pub struct ChunkCullingNode;
+
+impl ViewNode for ChunkCullingNode {
+    type ViewQuery = Entity;
+
+    fn run<'w>(
+        &self,
+        _graph: &mut render_graph::RenderGraphContext,
+        render_context: &mut RenderContext<'w>,
+        view_entity: Entity,
+        world: &'w World,
+    ) -> Result<(), render_graph::NodeRunError> {
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let culling_pipeline = world.resource::<ChunkCullingComputePipeline>();
+        let gpu_chunk_cullings = world.resource::<VoxelMaterialComponents<GpuChunkCulling>>();
+
+        let Some(gpu_culling) = gpu_chunk_cullings.get(&view_entity) else {
+            return Ok(());
+        };
+        let Some(view_bind_group) = gpu_culling.view_bind_group.as_ref() else {
+            return Ok(());
+        };
+        let (Some(segment_pipeline), Some(cluster_pipeline)) = (
+            pipeline_cache.get_compute_pipeline(culling_pipeline.pipeline(false)),
+            pipeline_cache.get_compute_pipeline(culling_pipeline.pipeline(true)),
+        ) else {
+            return Ok(()); // the pipelines are not loaded yet
+        };
+
+        let mut pass =
+            render_context
+                .command_encoder()
+                .begin_compute_pass(&ComputePassDescriptor {
+                    label: Some("chunk_culling"),
+                    ..default()
+                });
+        pass.set_bind_group(0, view_bind_group, &[]);
+
+        for gpu_chunk in gpu_culling.chunks.values() {
+            let Some(bind_group) = gpu_chunk.bind_group.as_ref() else {
+                continue;
+            };
+            pass.set_pipeline(match gpu_chunk.clusters {
+                true => cluster_pipeline,
+                false => segment_pipeline,
+            });
+            pass.set_bind_group(1, bind_group, &[]);
+            pass.dispatch_workgroups(gpu_chunk.draw_count().div_ceil(WORKGROUP_SIZE), 1, 1);
+        }
+
+        Ok(())
+    }
+}
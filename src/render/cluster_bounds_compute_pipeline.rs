@@ -0,0 +1,108 @@
+use bevy::{
+    prelude::*,
+    render::{
+        render_resource::{
+            binding_types::{storage_buffer, storage_buffer_read_only, uniform_buffer},
+            *,
+        },
+        renderer::RenderDevice,
+    },
+};
+
+use crate::{
+    data::{
+        atomics::Atomics,
+        cluster_bounds::{ClusterBoundBuffer, ClusterBoundsParams},
+        gpu_cluster_bounds::GpuClusterBounds,
+        gpu_voxel_material::GpuVoxelMaterial,
+        voxel_material::VoxelMaterialComponents,
+    },
+    render::{
+        post_mesh_compute_pass::PostMeshComputePass,
+        voxel_mesh_compute_pipeline::{IndexBuffer, VertexBuffer},
+    },
+};
+
+const SHADER_ASSET_PATH: &str = "shaders/cluster_bounds.wgsl";
+
+const WORKGROUP_SIZE: u32 = 64;
+
+#[derive(Resource)]
+pub struct ClusterBoundsComputePipeline {
+    pub bind_group_layout: BindGroupLayout,
+    pub pipeline: CachedComputePipelineId,
+}
+
+impl FromWorld for ClusterBoundsComputePipeline {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+
+        let bind_group_layout = render_device.create_bind_group_layout(
+            Some("ClusterBoundsComputePipeline::bind_group_layout"),
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::COMPUTE,
+                (
+                    uniform_buffer::<ClusterBoundsParams>(false),
+                    storage_buffer_read_only::<VertexBuffer>(false),
+                    storage_buffer_read_only::<IndexBuffer>(false),
+                    storage_buffer_read_only::<Atomics>(false),
+                    storage_buffer::<ClusterBoundBuffer>(false),
+                ),
+            ),
+        );
+
+        let shader = world.load_asset(SHADER_ASSET_PATH);
+
+        let pipeline_cache = world.resource::<PipelineCache>();
+
+        let pipeline = pipeline_cache.queue_compute_pipeline(ComputePipelineDescriptor {
+            label: Some("ClusterBoundsComputePipeline shader".into()),
+            layout: vec![bind_group_layout.clone()],
+            push_constant_ranges: Vec::new(),
+            shader,
+            shader_defs: Vec::new(),
+            entry_point: "main".into(),
+        });
+
+        ClusterBoundsComputePipeline {
+            bind_group_layout,
+            pipeline,
+        }
+    }
+}
+
+/// Computes the cluster bounds of each remeshed entity with a [`GpuClusterBounds`], right after
+/// its meshing.
+pub struct ClusterBoundsComputePass;
+
+impl PostMeshComputePass for ClusterBoundsComputePass {
+    fn run(
+        &self,
+        world: &World,
+        entity: Entity,
+        _gpu_voxel_material: &GpuVoxelMaterial,
+        command_encoder: &mut CommandEncoder,
+    ) {
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let bounds_pipeline = world.resource::<ClusterBoundsComputePipeline>();
+        let gpu_cluster_bounds = world.resource::<VoxelMaterialComponents<GpuClusterBounds>>();
+
+        let Some(pipeline) = pipeline_cache.get_compute_pipeline(bounds_pipeline.pipeline) else {
+            return; // the pipeline is not loaded yet
+        };
+        let Some(gpu_bounds) = gpu_cluster_bounds.get(&entity) else {
+            return;
+        };
+        let Some(bind_group) = gpu_bounds.bind_group.as_ref() else {
+            return;
+        };
+
+        let mut pass = command_encoder.begin_compute_pass(&ComputePassDescriptor {
+            label: Some("cluster_bounds"),
+            ..default()
+        });
+        pass.set_bind_group(0, bind_group, &[]);
+        pass.set_pipeline(pipeline);
+        pass.dispatch_workgroups(gpu_bounds.cluster_count().div_ceil(WORKGROUP_SIZE), 1, 1);
+    }
+}
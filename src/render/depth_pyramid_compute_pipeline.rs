@@ -0,0 +1,185 @@
+use bevy::{
+    prelude::*,
+    render::{
+        render_graph::{self, RenderLabel, ViewNode},
+        render_resource::{
+            binding_types::{
+                texture_2d, texture_depth_2d, texture_depth_2d_multisampled, texture_storage_2d,
+            },
+            *,
+        },
+        renderer::{RenderContext, RenderDevice},
+    },
+};
+
+use crate::data::{gpu_depth_pyramid::GpuDepthPyramid, voxel_material::VoxelMaterialComponents};
+
+const SHADER_ASSET_PATH: &str = "shaders/depth_pyramid.wgsl";
+
+const WORKGROUP_SIZE: u32 = 8;
+
+/// The format of the mips of a [`GpuDepthPyramid`].
+pub const DEPTH_PYRAMID_FORMAT: TextureFormat = TextureFormat::R32Float;
+
+#[derive(Resource)]
+pub struct DepthPyramidComputePipeline {
+    /// Binds the depth texture of a view and the first mip of its pyramid.
+    pub depth_bind_group_layout: BindGroupLayout,
+    /// The same as `depth_bind_group_layout`, for a multisampled depth texture.
+    pub depth_multisampled_bind_group_layout: BindGroupLayout,
+    /// Binds a mip of a pyramid and the next one.
+    pub downsample_bind_group_layout: BindGroupLayout,
+    pub depth_pipeline: CachedComputePipelineId,
+    pub depth_multisampled_pipeline: CachedComputePipelineId,
+    pub downsample_pipeline: CachedComputePipelineId,
+}
+
+impl DepthPyramidComputePipeline {
+    /// The layout binding the depth texture of a view, multisampled or not.
+    pub fn depth_layout(&self, multisampled: bool) -> &BindGroupLayout {
+        match multisampled {
+            true => &self.depth_multisampled_bind_group_layout,
+            false => &self.depth_bind_group_layout,
+        }
+    }
+
+    /// The pipeline copying the depth texture of a view into the first mip of its pyramid.
+    pub fn depth_pipeline(&self, multisampled: bool) -> CachedComputePipelineId {
+        match multisampled {
+            true => self.depth_multisampled_pipeline,
+            false => self.depth_pipeline,
+        }
+    }
+}
+
+impl FromWorld for DepthPyramidComputePipeline {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+
+        let mip_entry = texture_storage_2d(DEPTH_PYRAMID_FORMAT, StorageTextureAccess::WriteOnly);
+
+        let depth_bind_group_layout = render_device.create_bind_group_layout(
+            Some("DepthPyramidComputePipeline::depth_bind_group_layout"),
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::COMPUTE,
+                (texture_depth_2d(), mip_entry),
+            ),
+        );
+        let depth_multisampled_bind_group_layout = render_device.create_bind_group_layout(
+            Some("DepthPyramidComputePipeline::depth_multisampled_bind_group_layout"),
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::COMPUTE,
+                (texture_depth_2d_multisampled(), mip_entry),
+            ),
+        );
+        let downsample_bind_group_layout = render_device.create_bind_group_layout(
+            Some("DepthPyramidComputePipeline::downsample_bind_group_layout"),
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::COMPUTE,
+                (
+                    texture_2d(TextureSampleType::Float { filterable: false }),
+                    mip_entry,
+                ),
+            ),
+        );
+
+        let shader: Handle<Shader> = world.load_asset(SHADER_ASSET_PATH);
+
+        let pipeline_cache = world.resource::<PipelineCache>();
+
+        let queue = |label: &'static str, layout: &BindGroupLayout, shader_defs| {
+            pipeline_cache.queue_compute_pipeline(ComputePipelineDescriptor {
+                label: Some(label.into()),
+                layout: vec![layout.clone()],
+                push_constant_ranges: Vec::new(),
+                shader: shader.clone(),
+                shader_defs,
+                entry_point: "main".into(),
+            })
+        };
+
+        let depth_pipeline = queue(
+            "DepthPyramidComputePipeline depth shader",
+            &depth_bind_group_layout,
+            vec!["FROM_DEPTH".into()],
+        );
+        let depth_multisampled_pipeline = queue(
+            "DepthPyramidComputePipeline depth multisampled shader",
+            &depth_multisampled_bind_group_layout,
+            vec!["FROM_DEPTH".into(), "MULTISAMPLED".into()],
+        );
+        let downsample_pipeline = queue(
+            "DepthPyramidComputePipeline downsample shader",
+            &downsample_bind_group_layout,
+            Vec::new(),
+        );
+
+        DepthPyramidComputePipeline {
+            depth_bind_group_layout,
+            depth_multisampled_bind_group_layout,
+            downsample_bind_group_layout,
+            depth_pipeline,
+            depth_multisampled_pipeline,
+            downsample_pipeline,
+        }
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+pub struct DepthPyramidNodeLabel;
+
+/// Builds the [`GpuDepthPyramid`] of the view from its depth texture, one mip after the other.
+#[derive(Default)]
+pub struct DepthPyramidNode;
+
+impl ViewNode for DepthPyramidNode {
+    type ViewQuery = Entity;
+
+    fn run<'w>(
+        &self,
+        _graph: &mut render_graph::RenderGraphContext,
+        render_context: &mut RenderContext<'w>,
+        view_entity: Entity,
+        world: &'w World,
+    ) -> Result<(), render_graph::NodeRunError> {
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let pyramid_pipeline = world.resource::<DepthPyramidComputePipeline>();
+        let gpu_depth_pyramids = world.resource::<VoxelMaterialComponents<GpuDepthPyramid>>();
+
+        let Some(gpu_depth_pyramid) = gpu_depth_pyramids.get(&view_entity) else {
+            return Ok(());
+        };
+        let (Some(depth_pipeline), Some(downsample_pipeline)) = (
+            pipeline_cache.get_compute_pipeline(
+                pyramid_pipeline.depth_pipeline(gpu_depth_pyramid.multisampled),
+            ),
+            pipeline_cache.get_compute_pipeline(pyramid_pipeline.downsample_pipeline),
+        ) else {
+            return Ok(()); // the pipelines are not loaded yet
+        };
+
+        let mut pass =
+            render_context
+                .command_encoder()
+                .begin_compute_pass(&ComputePassDescriptor {
+                    label: Some("depth_pyramid"),
+                    ..default()
+                });
+
+        for (mip, bind_group) in gpu_depth_pyramid.bind_groups.iter().enumerate() {
+            let size = gpu_depth_pyramid.mip_size(mip as u32);
+            pass.set_pipeline(match mip {
+                0 => depth_pipeline,
+                _ => downsample_pipeline,
+            });
+            pass.set_bind_group(0, bind_group, &[]);
+            pass.dispatch_workgroups(
+                size.x.div_ceil(WORKGROUP_SIZE),
+                size.y.div_ceil(WORKGROUP_SIZE),
+                1,
+            );
+        }
+
+        Ok(())
+    }
+}
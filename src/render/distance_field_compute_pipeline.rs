@@ -0,0 +1,158 @@
+use bevy::{
+    prelude::*,
+    render::{
+        render_graph::{self, RenderLabel},
+        render_resource::{
+            binding_types::{storage_buffer, storage_buffer_read_only, uniform_buffer},
+            *,
+        },
+        renderer::{RenderContext, RenderDevice},
+    },
+};
+
+use crate::{
+    data::{
+        distance_field::{
+            DistanceFieldParams, DistanceFieldVolume, SeedBuffer, DISTANCE_FIELD_FORMAT,
+        },
+        gpu_distance_field::GpuDistanceField,
+        voxel_material::VoxelMaterialComponents,
+    },
+    render::voxel_mesh_compute_pipeline::{DirtyMeshes, VoxelBuffer},
+    CHUNK_SZ,
+};
+
+const SHADER_ASSET_PATH: &str = "shaders/distance_field.wgsl";
+
+#[derive(Resource)]
+pub struct DistanceFieldComputePipeline {
+    pub bind_group_layout: BindGroupLayout,
+    /// Seeds the flood with the voxels on either side of the surface.
+    pub seed_pipeline: CachedComputePipelineId,
+    /// Propagates the closest seeds a step of voxels away.
+    pub jump_pipeline: CachedComputePipelineId,
+    /// Writes the signed distances to the closest seeds.
+    pub resolve_pipeline: CachedComputePipelineId,
+}
+
+impl FromWorld for DistanceFieldComputePipeline {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+
+        let bind_group_layout = render_device.create_bind_group_layout(
+            Some("DistanceFieldComputePipeline::bind_group_layout"),
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::COMPUTE,
+                (
+                    uniform_buffer::<DistanceFieldParams>(false),
+                    storage_buffer_read_only::<VoxelBuffer>(false),
+                    storage_buffer_read_only::<SeedBuffer>(false),
+                    storage_buffer::<SeedBuffer>(false),
+                    BindingType::StorageTexture {
+                        access: StorageTextureAccess::WriteOnly,
+                        format: DISTANCE_FIELD_FORMAT,
+                        view_dimension: TextureViewDimension::D3,
+                    },
+                ),
+            ),
+        );
+
+        let shader = world.load_asset(SHADER_ASSET_PATH);
+
+        let pipeline_cache = world.resource::<PipelineCache>();
+
+        let queue = |entry_point: &'static str| {
+            pipeline_cache.queue_compute_pipeline(ComputePipelineDescriptor {
+                label: Some(format!("DistanceFieldComputePipeline {entry_point} shader").into()),
+                layout: vec![bind_group_layout.clone()],
+                push_constant_ranges: Vec::new(),
+                shader: shader.clone(),
+                shader_defs: Vec::new(),
+                entry_point: entry_point.into(),
+            })
+        };
+
+        DistanceFieldComputePipeline {
+            seed_pipeline: queue("seed"),
+            jump_pipeline: queue("jump"),
+            resolve_pipeline: queue("resolve"),
+            bind_group_layout,
+        }
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+pub struct DistanceFieldComputeNodeLabel;
+
+/// Generates the distance field of every [`DistanceFieldVolume`] remeshed this frame, or whose
+/// [`GpuDistanceField`] asks for it, with one dispatch per pass of the jump flood.
+pub struct DistanceFieldComputeNode {
+    distance_field_query: QueryState<Entity, With<DistanceFieldVolume>>,
+}
+
+impl FromWorld for DistanceFieldComputeNode {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            distance_field_query: world.query_filtered(),
+        }
+    }
+}
+
+impl render_graph::Node for DistanceFieldComputeNode {
+    fn update(&mut self, world: &mut World) {
+        self.distance_field_query.update_archetypes(world);
+    }
+
+    fn run(
+        &self,
+        _graph: &mut render_graph::RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), render_graph::NodeRunError> {
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let distance_field_pipeline = world.resource::<DistanceFieldComputePipeline>();
+        let dirty_meshes = world.resource::<DirtyMeshes>();
+        let gpu_distance_fields = world.resource::<VoxelMaterialComponents<GpuDistanceField>>();
+
+        let (Some(seed_pipeline), Some(jump_pipeline), Some(resolve_pipeline)) = (
+            pipeline_cache.get_compute_pipeline(distance_field_pipeline.seed_pipeline),
+            pipeline_cache.get_compute_pipeline(distance_field_pipeline.jump_pipeline),
+            pipeline_cache.get_compute_pipeline(distance_field_pipeline.resolve_pipeline),
+        ) else {
+            return Ok(()); // the pipelines are not loaded yet
+        };
+
+        let command_encoder = render_context.command_encoder();
+        let workgroups = (CHUNK_SZ / 4) as u32;
+
+        for entity in self.distance_field_query.iter_manual(world) {
+            let Some(gpu_distance_field) = gpu_distance_fields.get(&entity) else {
+                continue;
+            };
+            if gpu_distance_field.bind_groups.is_empty()
+                || !(gpu_distance_field.regenerate || dirty_meshes.is_meshed(&entity))
+            {
+                continue;
+            }
+
+            let last = gpu_distance_field.bind_groups.len() - 1;
+            for (pass, bind_group) in gpu_distance_field.bind_groups.iter().enumerate() {
+                let pipeline = match pass {
+                    0 => seed_pipeline,
+                    pass if pass == last => resolve_pipeline,
+                    _ => jump_pipeline,
+                };
+
+                let mut pass = command_encoder.begin_compute_pass(&ComputePassDescriptor {
+                    label: Some("distance_field"),
+                    ..default()
+                });
+                pass.set_bind_group(0, bind_group, &[]);
+                pass.set_pipeline(pipeline);
+                pass.dispatch_workgroups(workgroups, workgroups, workgroups);
+            }
+        }
+
+        Ok(())
+    }
+}
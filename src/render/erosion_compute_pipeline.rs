@@ -0,0 +1,146 @@
+use bevy::{
+    prelude::*,
+    render::{
+        render_graph::{self, RenderLabel},
+        render_resource::{
+            binding_types::{storage_buffer, storage_buffer_read_only, uniform_buffer},
+            *,
+        },
+        renderer::{RenderContext, RenderDevice},
+    },
+};
+
+use crate::{
+    data::{
+        erosion::{Erosion, ErosionParams},
+        gpu_erosion::GpuErosion,
+        gpu_voxel_material::GpuVoxelMaterial,
+        voxel_material::VoxelMaterialComponents,
+    },
+    render::voxel_mesh_compute_pipeline::VoxelBuffer,
+    CHUNK_SZ,
+};
+
+const SHADER_ASSET_PATH: &str = "shaders/erosion.wgsl";
+
+#[derive(Resource)]
+pub struct ErosionComputePipeline {
+    pub bind_group_layout: BindGroupLayout,
+    pub pipeline: CachedComputePipelineId,
+}
+
+impl FromWorld for ErosionComputePipeline {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+
+        let bind_group_layout = render_device.create_bind_group_layout(
+            Some("ErosionComputePipeline::bind_group_layout"),
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::COMPUTE,
+                (
+                    uniform_buffer::<ErosionParams>(false),
+                    storage_buffer_read_only::<VoxelBuffer>(false),
+                    storage_buffer::<VoxelBuffer>(false),
+                ),
+            ),
+        );
+
+        let shader = world.load_asset(SHADER_ASSET_PATH);
+
+        let pipeline_cache = world.resource::<PipelineCache>();
+
+        let pipeline = pipeline_cache.queue_compute_pipeline(ComputePipelineDescriptor {
+            label: Some("ErosionComputePipeline shader".into()),
+            layout: vec![bind_group_layout.clone()],
+            push_constant_ranges: Vec::new(),
+            shader,
+            shader_defs: Vec::new(),
+            entry_point: "main".into(),
+        });
+
+        ErosionComputePipeline {
+            bind_group_layout,
+            pipeline,
+        }
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+pub struct ErosionComputeNodeLabel;
+
+/// Simulates the requested erosion steps of every [`Erosion`], one dispatch per step, with the
+/// eroded voxels copied back into the voxels buffer between steps.
+pub struct ErosionComputeNode {
+    erosion_query: QueryState<Entity, With<Erosion>>,
+}
+
+impl FromWorld for ErosionComputeNode {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            erosion_query: world.query_filtered(),
+        }
+    }
+}
+
+impl render_graph::Node for ErosionComputeNode {
+    fn update(&mut self, world: &mut World) {
+        self.erosion_query.update_archetypes(world);
+    }
+
+    fn run(
+        &self,
+        _graph: &mut render_graph::RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), render_graph::NodeRunError> {
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let erosion_pipeline = world.resource::<ErosionComputePipeline>();
+        let gpu_voxel_materials = world.resource::<VoxelMaterialComponents<GpuVoxelMaterial>>();
+        let gpu_erosions = world.resource::<VoxelMaterialComponents<GpuErosion>>();
+
+        let Some(pipeline) = pipeline_cache.get_compute_pipeline(erosion_pipeline.pipeline) else {
+            return Ok(()); // the pipeline is not loaded yet
+        };
+
+        let command_encoder = render_context.command_encoder();
+
+        for entity in self.erosion_query.iter_manual(world) {
+            let (Some(gpu_voxel_material), Some(gpu_erosion)) =
+                (gpu_voxel_materials.get(&entity), gpu_erosions.get(&entity))
+            else {
+                continue;
+            };
+            let (Some(bind_group), Some(voxels_buffer)) = (
+                gpu_erosion.bind_group.as_ref(),
+                gpu_voxel_material.voxels_buffer.buffer(),
+            ) else {
+                continue;
+            };
+
+            for _ in 0..gpu_erosion.steps {
+                let mut pass = command_encoder.begin_compute_pass(&ComputePassDescriptor {
+                    label: Some("erosion_step"),
+                    ..default()
+                });
+                pass.set_bind_group(0, bind_group, &[]);
+                pass.set_pipeline(pipeline);
+                pass.dispatch_workgroups(
+                    (CHUNK_SZ / 8) as u32,
+                    (CHUNK_SZ / 8) as u32,
+                    (CHUNK_SZ / 8) as u32,
+                );
+                drop(pass);
+
+                command_encoder.copy_buffer_to_buffer(
+                    &gpu_erosion.eroded_voxels_buffer,
+                    0,
+                    voxels_buffer,
+                    0,
+                    gpu_erosion.eroded_voxels_buffer.size(),
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,143 @@
+use bevy::{
+    prelude::*,
+    render::{
+        render_asset::RenderAssets,
+        render_graph::{self, RenderLabel},
+        render_resource::{
+            binding_types::{storage_buffer_read_only, uniform_buffer},
+            *,
+        },
+        renderer::{RenderContext, RenderDevice},
+        texture::GpuImage,
+    },
+};
+
+use crate::{
+    data::{
+        gpu_light_probes::GpuLightProbes,
+        iso_surface::IsoSurfaceParams,
+        light_probes::{LightProbeParams, VoxelLightProbes, LIGHT_PROBES_FORMAT},
+        voxel_material::VoxelMaterialComponents,
+    },
+    render::voxel_mesh_compute_pipeline::VoxelBuffer,
+};
+
+const SHADER_ASSET_PATH: &str = "shaders/light_probes.wgsl";
+
+#[derive(Resource)]
+pub struct LightProbesComputePipeline {
+    pub bind_group_layout: BindGroupLayout,
+    pub pipeline: CachedComputePipelineId,
+}
+
+impl FromWorld for LightProbesComputePipeline {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+
+        let bind_group_layout = render_device.create_bind_group_layout(
+            Some("LightProbesComputePipeline::bind_group_layout"),
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::COMPUTE,
+                (
+                    uniform_buffer::<LightProbeParams>(false),
+                    storage_buffer_read_only::<VoxelBuffer>(false),
+                    uniform_buffer::<IsoSurfaceParams>(false),
+                    BindingType::StorageTexture {
+                        access: StorageTextureAccess::WriteOnly,
+                        format: LIGHT_PROBES_FORMAT,
+                        view_dimension: TextureViewDimension::D3,
+                    },
+                ),
+            ),
+        );
+
+        let shader = world.load_asset(SHADER_ASSET_PATH);
+
+        let pipeline_cache = world.resource::<PipelineCache>();
+
+        let pipeline = pipeline_cache.queue_compute_pipeline(ComputePipelineDescriptor {
+            label: Some("LightProbesComputePipeline shader".into()),
+            layout: vec![bind_group_layout.clone()],
+            push_constant_ranges: Vec::new(),
+            shader,
+            shader_defs: Vec::new(),
+            entry_point: "main".into(),
+        });
+
+        LightProbesComputePipeline {
+            bind_group_layout,
+            pipeline,
+        }
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+pub struct LightProbesComputeNodeLabel;
+
+/// Bakes the irradiance volume texture of the [`VoxelLightProbes`] bound this frame.
+pub struct LightProbesComputeNode {
+    light_probes_query: QueryState<Entity, With<VoxelLightProbes>>,
+}
+
+impl FromWorld for LightProbesComputeNode {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            light_probes_query: world.query_filtered(),
+        }
+    }
+}
+
+impl render_graph::Node for LightProbesComputeNode {
+    fn update(&mut self, world: &mut World) {
+        self.light_probes_query.update_archetypes(world);
+    }
+
+    fn run(
+        &self,
+        _graph: &mut render_graph::RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), render_graph::NodeRunError> {
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let light_probes_pipeline = world.resource::<LightProbesComputePipeline>();
+        let gpu_images = world.resource::<RenderAssets<GpuImage>>();
+        let gpu_light_probes = world.resource::<VoxelMaterialComponents<GpuLightProbes>>();
+
+        let Some(pipeline) = pipeline_cache.get_compute_pipeline(light_probes_pipeline.pipeline)
+        else {
+            return Ok(()); // the pipeline is not loaded yet
+        };
+
+        let command_encoder = render_context.command_encoder();
+
+        for entity in self.light_probes_query.iter_manual(world) {
+            let Some(gpu_probes) = gpu_light_probes.get(&entity) else {
+                continue;
+            };
+            let (Some(bind_group), Some(gpu_image)) = (
+                gpu_probes.bind_group.as_ref(),
+                gpu_images.get(&gpu_probes.image),
+            ) else {
+                continue;
+            };
+
+            // One invocation per probe, the texture stacking the sides of the probes.
+            let probes = UVec3::new(
+                gpu_image.texture.width(),
+                gpu_image.texture.height() / 2,
+                gpu_image.texture.depth_or_array_layers() / 3,
+            );
+            let workgroups = (probes + 3) / 4;
+
+            let mut pass = command_encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some("light_probes"),
+                ..default()
+            });
+            pass.set_bind_group(0, bind_group, &[]);
+            pass.set_pipeline(pipeline);
+            pass.dispatch_workgroups(workgroups.x, workgroups.y, workgroups.z);
+        }
+
+        Ok(())
+    }
+}
@@ -1 +1,26 @@
+pub mod ambient_occlusion_compute_pipeline;
+#[cfg(feature = "readback")]
+pub mod chunk_analysis_compute_pipeline;
+#[cfg(feature = "gpu-driven")]
+pub mod chunk_culling_compute_pipeline;
+#[cfg(feature = "gpu-driven")]
+pub mod cluster_bounds_compute_pipeline;
+#[cfg(feature = "gpu-driven")]
+pub mod depth_pyramid_compute_pipeline;
+pub mod distance_field_compute_pipeline;
+pub mod erosion_compute_pipeline;
+#[cfg(feature = "raymarch")]
+pub mod light_probes_compute_pipeline;
+pub mod node_ordering;
+pub mod post_mesh_compute_pass;
+pub mod procedural_generation_compute_pipeline;
+#[cfg(feature = "readback")]
+pub mod prop_candidates_compute_pipeline;
+pub mod shaders;
+pub mod slot_compaction_compute_pipeline;
+pub mod splat_map_compute_pipeline;
+pub mod volume_statistics_compute_pipeline;
+pub mod voxel_decompression_compute_pipeline;
 pub mod voxel_mesh_compute_pipeline;
+#[cfg(feature = "readback")]
+pub mod voxel_picking_compute_pipeline;
@@ -0,0 +1,79 @@
+use bevy::{
+    prelude::*,
+    render::{
+        render_graph::{InternedRenderLabel, RenderGraph, RenderGraphError, RenderLabel},
+        RenderApp,
+    },
+};
+
+use super::voxel_mesh_compute_pipeline::VoxelMeshComputeNodeLabel;
+
+/// Where the [`VoxelMeshComputeNode`](super::voxel_mesh_compute_pipeline::VoxelMeshComputeNode)
+/// runs relative to another node of the main render graph.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VoxelNodeOrder {
+    Before(InternedRenderLabel),
+    After(InternedRenderLabel),
+}
+
+impl VoxelNodeOrder {
+    /// The output and input nodes of the edge enforcing the order.
+    fn edge(self) -> (InternedRenderLabel, InternedRenderLabel) {
+        match self {
+            VoxelNodeOrder::Before(label) => (VoxelMeshComputeNodeLabel.intern(), label),
+            VoxelNodeOrder::After(label) => (label, VoxelMeshComputeNodeLabel.intern()),
+        }
+    }
+}
+
+/// The [`VoxelNodeOrder`]s of the render world whose edges are not in the render graph yet,
+/// because one of their nodes has not been added.
+#[derive(Resource, Default)]
+pub struct VoxelNodeOrdering(pub Vec<VoxelNodeOrder>);
+
+impl VoxelNodeOrdering {
+    /// Adds the edges of the orders whose nodes are both in the render graph, keeping the others
+    /// until their nodes are added.
+    pub fn apply(mut ordering: ResMut<Self>, mut render_graph: ResMut<RenderGraph>) {
+        if ordering.0.is_empty() {
+            return;
+        }
+
+        ordering.0.retain(|order| {
+            let (output, input) = order.edge();
+            match render_graph.try_add_node_edge(output, input) {
+                Ok(()) | Err(RenderGraphError::EdgeAlreadyExists(_)) => false,
+                Err(_) => true,
+            }
+        });
+    }
+}
+
+pub trait VoxelNodeOrderingAppExt {
+    /// Runs the voxel meshing before the node `label` of the main render graph, e.g. a custom node
+    /// consuming the generated vertices on the GPU.
+    fn run_voxel_meshing_before(&mut self, label: impl RenderLabel) -> &mut Self;
+
+    /// Runs the voxel meshing after the node `label` of the main render graph, e.g. a custom node
+    /// writing voxels on the GPU.
+    fn run_voxel_meshing_after(&mut self, label: impl RenderLabel) -> &mut Self;
+}
+
+impl VoxelNodeOrderingAppExt for App {
+    fn run_voxel_meshing_before(&mut self, label: impl RenderLabel) -> &mut Self {
+        push_order(self, VoxelNodeOrder::Before(label.intern()))
+    }
+
+    fn run_voxel_meshing_after(&mut self, label: impl RenderLabel) -> &mut Self {
+        push_order(self, VoxelNodeOrder::After(label.intern()))
+    }
+}
+
+fn push_order(app: &mut App, order: VoxelNodeOrder) -> &mut App {
+    app.sub_app_mut(RenderApp)
+        .world_mut()
+        .get_resource_or_insert_with(VoxelNodeOrdering::default)
+        .0
+        .push(order);
+    app
+}
@@ -0,0 +1,43 @@
+use bevy::{
+    prelude::*,
+    render::{render_resource::CommandEncoder, RenderApp},
+};
+
+use crate::data::gpu_voxel_material::GpuVoxelMaterial;
+
+/// A compute pass run by the [`VoxelMeshComputeNode`](super::voxel_mesh_compute_pipeline::VoxelMeshComputeNode)
+/// after meshing each volumetric entity and before its vertices are copied for readback, with
+/// access to the generated buffers.
+pub trait PostMeshComputePass: Send + Sync + 'static {
+    /// Updates the pass before the node runs, e.g. to update its queries.
+    fn update(&mut self, _world: &mut World) {}
+
+    /// Records the pass for `entity` into `command_encoder`.
+    fn run(
+        &self,
+        world: &World,
+        entity: Entity,
+        gpu_voxel_material: &GpuVoxelMaterial,
+        command_encoder: &mut CommandEncoder,
+    );
+}
+
+/// The [`PostMeshComputePass`]es of the render world, run in registration order.
+#[derive(Resource, Default)]
+pub struct PostMeshComputePasses(pub Vec<Box<dyn PostMeshComputePass>>);
+
+pub trait PostMeshComputePassAppExt {
+    /// Registers a [`PostMeshComputePass`] in the render world.
+    fn add_post_mesh_compute_pass(&mut self, pass: impl PostMeshComputePass) -> &mut Self;
+}
+
+impl PostMeshComputePassAppExt for App {
+    fn add_post_mesh_compute_pass(&mut self, pass: impl PostMeshComputePass) -> &mut Self {
+        self.sub_app_mut(RenderApp)
+            .world_mut()
+            .get_resource_or_insert_with(PostMeshComputePasses::default)
+            .0
+            .push(Box::new(pass));
+        self
+    }
+}
@@ -0,0 +1,168 @@
+use bevy::{
+    prelude::*,
+    render::{
+        render_graph::{self, RenderLabel},
+        render_resource::{
+            binding_types::{storage_buffer, storage_buffer_read_only, uniform_buffer},
+            *,
+        },
+        renderer::{RenderContext, RenderDevice},
+    },
+};
+
+use crate::{
+    data::{
+        generation_graph::{GraphLayerBuffer, GraphNodeBuffer},
+        gpu_procedural_volume::GpuProceduralVolume,
+        procedural_volume::ProceduralParams,
+        voxel_material::VoxelMaterialComponents,
+    },
+    render::voxel_mesh_compute_pipeline::VoxelBuffer,
+    CHUNK_SZ_3,
+};
+
+const SHADER_ASSET_PATH: &str = "shaders/procedural_generation.wgsl";
+
+const WORKGROUP_SIZE: u32 = 256;
+
+#[derive(Resource)]
+pub struct ProceduralGenerationComputePipeline {
+    pub bind_group_layout: BindGroupLayout,
+    pub pipeline: CachedComputePipelineId,
+    /// Binds the nodes and layers of a
+    /// [`GenerationGraph`](crate::data::generation_graph::GenerationGraph) after the params and
+    /// voxels.
+    pub graph_bind_group_layout: BindGroupLayout,
+    /// Evaluates the graph of a [`GraphVolume`](crate::data::generation_graph::GraphVolume).
+    pub graph_pipeline: CachedComputePipelineId,
+}
+
+impl FromWorld for ProceduralGenerationComputePipeline {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+
+        let bind_group_layout = render_device.create_bind_group_layout(
+            Some("ProceduralGenerationComputePipeline::bind_group_layout"),
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::COMPUTE,
+                (
+                    uniform_buffer::<ProceduralParams>(false),
+                    storage_buffer::<VoxelBuffer>(false),
+                ),
+            ),
+        );
+
+        let graph_bind_group_layout = render_device.create_bind_group_layout(
+            Some("ProceduralGenerationComputePipeline::graph_bind_group_layout"),
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::COMPUTE,
+                (
+                    uniform_buffer::<ProceduralParams>(false),
+                    storage_buffer::<VoxelBuffer>(false),
+                    storage_buffer_read_only::<GraphNodeBuffer>(false),
+                    storage_buffer_read_only::<GraphLayerBuffer>(false),
+                ),
+            ),
+        );
+
+        let shader = world.load_asset(SHADER_ASSET_PATH);
+
+        let pipeline_cache = world.resource::<PipelineCache>();
+
+        let pipeline = pipeline_cache.queue_compute_pipeline(ComputePipelineDescriptor {
+            label: Some("ProceduralGenerationComputePipeline shader".into()),
+            layout: vec![bind_group_layout.clone()],
+            push_constant_ranges: Vec::new(),
+            shader: shader.clone(),
+            shader_defs: Vec::new(),
+            entry_point: "main".into(),
+        });
+
+        let graph_pipeline = pipeline_cache.queue_compute_pipeline(ComputePipelineDescriptor {
+            label: Some("ProceduralGenerationComputePipeline graph shader".into()),
+            layout: vec![graph_bind_group_layout.clone()],
+            push_constant_ranges: Vec::new(),
+            shader,
+            shader_defs: Vec::new(),
+            entry_point: "graph".into(),
+        });
+
+        ProceduralGenerationComputePipeline {
+            bind_group_layout,
+            pipeline,
+            graph_bind_group_layout,
+            graph_pipeline,
+        }
+    }
+}
+
+impl ProceduralGenerationComputePipeline {
+    /// Whether both generation pipelines are loaded.
+    pub fn is_loaded(&self, pipeline_cache: &PipelineCache) -> bool {
+        [self.pipeline, self.graph_pipeline]
+            .into_iter()
+            .all(|pipeline| pipeline_cache.get_compute_pipeline(pipeline).is_some())
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+pub struct ProceduralGenerationComputeNodeLabel;
+
+/// Generates the voxels of the [`ProceduralVolume`](crate::data::procedural_volume::ProceduralVolume)s
+/// and [`GraphVolume`](crate::data::generation_graph::GraphVolume)s picked from the generation
+/// queue this frame into their voxels buffer, before they are meshed.
+#[derive(Default)]
+pub struct ProceduralGenerationComputeNode;
+
+impl render_graph::Node for ProceduralGenerationComputeNode {
+    fn run(
+        &self,
+        _graph: &mut render_graph::RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), render_graph::NodeRunError> {
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let generation_pipeline = world.resource::<ProceduralGenerationComputePipeline>();
+        let gpu_procedural_volumes =
+            world.resource::<VoxelMaterialComponents<GpuProceduralVolume>>();
+
+        let (Some(pipeline), Some(graph_pipeline)) = (
+            pipeline_cache.get_compute_pipeline(generation_pipeline.pipeline),
+            pipeline_cache.get_compute_pipeline(generation_pipeline.graph_pipeline),
+        ) else {
+            return Ok(()); // the pipelines are not loaded yet
+        };
+
+        let mut jobs = gpu_procedural_volumes
+            .0
+            .values()
+            .filter(|gpu_procedural_volume| gpu_procedural_volume.generate)
+            .filter_map(|gpu_procedural_volume| {
+                let bind_group = gpu_procedural_volume.bind_group.as_ref()?;
+                match gpu_procedural_volume.graph {
+                    Some(_) => Some((graph_pipeline, bind_group)),
+                    None => Some((pipeline, bind_group)),
+                }
+            })
+            .peekable();
+        if jobs.peek().is_none() {
+            return Ok(());
+        }
+
+        let mut pass =
+            render_context
+                .command_encoder()
+                .begin_compute_pass(&ComputePassDescriptor {
+                    label: Some("procedural_generation"),
+                    ..default()
+                });
+
+        for (pipeline, bind_group) in jobs {
+            pass.set_pipeline(pipeline);
+            pass.set_bind_group(0, bind_group, &[]);
+            pass.dispatch_workgroups((CHUNK_SZ_3 as u32).div_ceil(WORKGROUP_SIZE), 1, 1);
+        }
+
+        Ok(())
+    }
+}
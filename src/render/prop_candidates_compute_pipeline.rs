@@ -0,0 +1,142 @@
+use bevy::{
+    prelude::*,
+    render::{
+        render_graph::{self, RenderLabel},
+        render_resource::{
+            binding_types::{storage_buffer, storage_buffer_read_only, uniform_buffer},
+            *,
+        },
+        renderer::{RenderContext, RenderDevice},
+    },
+};
+
+use crate::{
+    data::{
+        gpu_prop_candidates::GpuPropCandidates,
+        prop_candidates::{PropCandidateBuffer, PropCandidateParams, PropCandidatesReadback},
+        voxel_material::VoxelMaterialComponents,
+    },
+    render::voxel_mesh_compute_pipeline::VoxelBuffer,
+    CHUNK_SZ_3,
+};
+
+const SHADER_ASSET_PATH: &str = "shaders/prop_candidates.wgsl";
+
+const WORKGROUP_SIZE: u32 = 256;
+
+#[derive(Resource)]
+pub struct PropCandidatesComputePipeline {
+    pub bind_group_layout: BindGroupLayout,
+    pub pipeline: CachedComputePipelineId,
+}
+
+impl FromWorld for PropCandidatesComputePipeline {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+
+        let bind_group_layout = render_device.create_bind_group_layout(
+            Some("PropCandidatesComputePipeline::bind_group_layout"),
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::COMPUTE,
+                (
+                    uniform_buffer::<PropCandidateParams>(false),
+                    storage_buffer_read_only::<VoxelBuffer>(false),
+                    storage_buffer::<PropCandidateBuffer>(false),
+                ),
+            ),
+        );
+
+        let shader = world.load_asset(SHADER_ASSET_PATH);
+
+        let pipeline_cache = world.resource::<PipelineCache>();
+
+        let pipeline = pipeline_cache.queue_compute_pipeline(ComputePipelineDescriptor {
+            label: Some("PropCandidatesComputePipeline shader".into()),
+            layout: vec![bind_group_layout.clone()],
+            push_constant_ranges: Vec::new(),
+            shader,
+            shader_defs: Vec::new(),
+            entry_point: "main".into(),
+        });
+
+        PropCandidatesComputePipeline {
+            bind_group_layout,
+            pipeline,
+        }
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+pub struct PropCandidatesComputeNodeLabel;
+
+/// Emits the points of every [`PropCandidatesReadback`] entity whose voxels or settings changed
+/// and copies them to its staging buffer.
+pub struct PropCandidatesComputeNode {
+    candidates_query: QueryState<Entity, With<PropCandidatesReadback>>,
+}
+
+impl FromWorld for PropCandidatesComputeNode {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            candidates_query: world.query_filtered(),
+        }
+    }
+}
+
+impl render_graph::Node for PropCandidatesComputeNode {
+    fn update(&mut self, world: &mut World) {
+        self.candidates_query.update_archetypes(world);
+    }
+
+    fn run(
+        &self,
+        _graph: &mut render_graph::RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), render_graph::NodeRunError> {
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let candidates_pipeline = world.resource::<PropCandidatesComputePipeline>();
+        let gpu_prop_candidates = world.resource::<VoxelMaterialComponents<GpuPropCandidates>>();
+
+        let Some(pipeline) = pipeline_cache.get_compute_pipeline(candidates_pipeline.pipeline)
+        else {
+            return Ok(()); // the pipeline is not loaded yet
+        };
+
+        let command_encoder = render_context.command_encoder();
+
+        for entity in self.candidates_query.iter_manual(world) {
+            let Some(gpu_candidates) = gpu_prop_candidates.get(&entity) else {
+                continue;
+            };
+            if !gpu_candidates.changed {
+                continue;
+            }
+            let Some(bind_group) = gpu_candidates.bind_group.as_ref() else {
+                continue;
+            };
+
+            command_encoder.clear_buffer(&gpu_candidates.points_buffer, 0, None);
+
+            {
+                let mut pass = command_encoder.begin_compute_pass(&ComputePassDescriptor {
+                    label: Some("prop_candidates"),
+                    ..default()
+                });
+                pass.set_bind_group(0, bind_group, &[]);
+                pass.set_pipeline(pipeline);
+                pass.dispatch_workgroups((CHUNK_SZ_3 as u32).div_ceil(WORKGROUP_SIZE), 1, 1);
+            }
+
+            command_encoder.copy_buffer_to_buffer(
+                &gpu_candidates.points_buffer,
+                0,
+                &gpu_candidates.points_staging_buffer,
+                0,
+                gpu_candidates.points_buffer.size(),
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,43 @@
+#define_import_path bevy_volumetric::bindings
+
+#import bevy_volumetric::types::{VoxelBuffer, Atomics, CellSlotBuffer, VertexBuffer, NormalBuffer, IndexBuffer, UvBuffer, TangentBuffer, EmissiveBuffer, DualContouringParams, IsoSurfaceParams, ClipPlanesParams, AdaptiveResolutionParams}
+#import bevy_volumetric::tables::{EdgeTable, TriangleTable}
+
+// Bindings for the buffers and tables of `VoxelMeshComputePipeline::bind_group_1_layout`.
+@group(0) @binding(0) var<storage, read_write> uniform_edge_table: EdgeTable;
+@group(0) @binding(1) var<storage, read_write> uniform_tri_table: TriangleTable;
+@group(0) @binding(2) var<storage, read_write> in_voxels: VoxelBuffer;
+#ifdef CELL_SLOTS
+// Without storage atomics, cells write their primitive counts to `GpuFixedOutputSlots` instead,
+// or allocate their primitives from the prefix sum of their counts.
+@group(0) @binding(3) var<storage, read_write> cell_slots: CellSlotBuffer;
+#else
+@group(0) @binding(3) var<storage, read_write> global_atomics: Atomics;
+#endif
+@group(0) @binding(4) var<storage, read_write> out_vertices: VertexBuffer;
+@group(0) @binding(5) var<storage, read_write> out_normals: NormalBuffer;
+@group(0) @binding(6) var<storage, read_write> out_indices: IndexBuffer;
+@group(0) @binding(7) var<storage, read_write> out_uvs: UvBuffer;
+@group(0) @binding(8) var<storage, read_write> out_tangents: TangentBuffer;
+@group(0) @binding(9) var<uniform> dual_contouring: DualContouringParams;
+@group(0) @binding(10) var<uniform> iso_surface: IsoSurfaceParams;
+@group(0) @binding(11) var<uniform> clip_planes: ClipPlanesParams;
+@group(0) @binding(12) var<uniform> adaptive_resolution: AdaptiveResolutionParams;
+@group(0) @binding(13) var<storage, read_write> out_emissives: EmissiveBuffer;
+
+#ifdef VIRTUAL_VOLUME
+// Page table of `VoxelMeshComputePipeline::page_table_layout`, holding the pool slot + 1 of every
+// resident brick and 0 for the bricks that are not resident.
+@group(1) @binding(0) var page_table: texture_3d<u32>;
+#endif
+
+#ifdef SEAMLESS_NORMALS
+// Voxels of the chunks next to the faces of the chunk, of `VoxelMeshComputePipeline::neighbor_voxels_layout`.
+// Faces without a neighbour are bound to a buffer of a single voxel.
+@group(1) @binding(0) var<storage, read> neighbor_neg_x: VoxelBuffer;
+@group(1) @binding(1) var<storage, read> neighbor_pos_x: VoxelBuffer;
+@group(1) @binding(2) var<storage, read> neighbor_neg_y: VoxelBuffer;
+@group(1) @binding(3) var<storage, read> neighbor_pos_y: VoxelBuffer;
+@group(1) @binding(4) var<storage, read> neighbor_neg_z: VoxelBuffer;
+@group(1) @binding(5) var<storage, read> neighbor_pos_z: VoxelBuffer;
+#endif
@@ -0,0 +1,302 @@
+use std::sync::atomic::AtomicU32;
+
+use bevy::{asset::load_internal_asset, prelude::*};
+
+use crate::{
+    core::mesher::BISECTION_STEPS,
+    data::{
+        adaptive_resolution::{AdaptiveResolutionParams, MAX_DETAIL_REGIONS},
+        ambient_occlusion::AmbientOcclusionParams,
+        atomics::Atomics,
+        clip_planes::{ClipPlanesParams, MAX_CLIP_PLANES},
+        compressed_voxels::{DecompressionParams, VoxelRun, VoxelRunBuffer},
+        distance_field::{DistanceFieldParams, SeedBuffer},
+        dual_contouring::DualContouringParams,
+        erosion::ErosionParams,
+        generation_graph::{
+            GraphLayer, GraphLayerBuffer, GraphNode, GraphNodeBuffer, GRAPH_CAVE_CARVE,
+            GRAPH_DOMAIN_WARP, GRAPH_MATERIAL_ASSIGN, GRAPH_NOISE, GRAPH_TERRACE,
+        },
+        iso_surface::IsoSurfaceParams,
+        material_split::MAX_MATERIALS,
+        particle_collision::ParticleCollisionParams,
+        procedural_volume::ProceduralParams,
+        shader_platform::{
+            CellSlot, CellSlotBuffer, SlotCompactionParams, SlotStreamParams, SLOT_BLOCK_CELLS,
+        },
+        splat_map::SplatMapParams,
+        volume_statistics::{VolumeHistogram, VolumeStatisticsParams, HISTOGRAM_BINS},
+        voxel::Voxel,
+    },
+    render::voxel_mesh_compute_pipeline::{
+        EdgeTable, EmissiveBuffer, IndexBuffer, NormalBuffer, TangentBuffer, TriangleTable,
+        UvBuffer, VertexBuffer, VoxelBuffer,
+    },
+    CHUNK_SZ,
+};
+
+#[cfg(feature = "raymarch")]
+use crate::data::light_probes::LightProbeParams;
+#[cfg(feature = "readback")]
+use crate::data::{
+    chunk_analysis::{ChunkAnalysisCounters, ChunkAnalysisParams},
+    prop_candidates::{PropCandidateBuffer, PropCandidateParams, PropCandidatePoint},
+    voxel_picking::{VoxelPickParams, VoxelPickResult},
+};
+#[cfg(feature = "gpu-driven")]
+use crate::data::{
+    chunk_culling::{ChunkCullingParams, ChunkCullingView, ChunkDrawArgs, ChunkDrawArgsBuffer},
+    cluster_bounds::{ClusterBound, ClusterBoundBuffer, ClusterBoundsParams, CLUSTER_TRIANGLES},
+};
+
+pub const TYPES_SHADER_HANDLE: Handle<Shader> =
+    Handle::weak_from_u128(0x6a1c_93d0_57e4_4f0b_9d2e_1b7c_0a44_e301);
+pub const TABLES_SHADER_HANDLE: Handle<Shader> =
+    Handle::weak_from_u128(0x6a1c_93d0_57e4_4f0b_9d2e_1b7c_0a44_e302);
+pub const BINDINGS_SHADER_HANDLE: Handle<Shader> =
+    Handle::weak_from_u128(0x6a1c_93d0_57e4_4f0b_9d2e_1b7c_0a44_e303);
+pub const VOXEL_SHADER_HANDLE: Handle<Shader> =
+    Handle::weak_from_u128(0x6a1c_93d0_57e4_4f0b_9d2e_1b7c_0a44_e304);
+pub const NORMALS_SHADER_HANDLE: Handle<Shader> =
+    Handle::weak_from_u128(0x6a1c_93d0_57e4_4f0b_9d2e_1b7c_0a44_e305);
+pub const PARTICLE_COLLISION_SHADER_HANDLE: Handle<Shader> =
+    Handle::weak_from_u128(0x6a1c_93d0_57e4_4f0b_9d2e_1b7c_0a44_e306);
+pub const SPLAT_MAP_SHADER_HANDLE: Handle<Shader> =
+    Handle::weak_from_u128(0x6a1c_93d0_57e4_4f0b_9d2e_1b7c_0a44_e307);
+
+/// The WGSL spelling of a Rust type used inside a [`ShaderType`].
+pub trait WgslType {
+    fn wgsl_type() -> String;
+}
+
+macro_rules! impl_wgsl_type {
+    ($($ty:ty => $wgsl:literal),* $(,)?) => {
+        $(
+            impl WgslType for $ty {
+                fn wgsl_type() -> String {
+                    $wgsl.into()
+                }
+            }
+        )*
+    };
+}
+
+impl_wgsl_type! {
+    u32 => "u32",
+    i32 => "i32",
+    f32 => "f32",
+    Vec2 => "vec2<f32>",
+    Vec3 => "vec3<f32>",
+    Vec4 => "vec4<f32>",
+    UVec2 => "vec2<u32>",
+    UVec3 => "vec3<u32>",
+    UVec4 => "vec4<u32>",
+    IVec3 => "vec3<i32>",
+    Mat4 => "mat4x4<f32>",
+    AtomicU32 => "atomic<u32>",
+}
+
+impl<T: WgslType, const N: usize> WgslType for [T; N] {
+    fn wgsl_type() -> String {
+        format!("array<{}, {N}>", T::wgsl_type())
+    }
+}
+
+/// Runtime sized arrays, only valid as the last field of a storage buffer struct.
+impl<T: WgslType> WgslType for Vec<T> {
+    fn wgsl_type() -> String {
+        format!("array<{}>", T::wgsl_type())
+    }
+}
+
+/// A struct whose WGSL definition is generated from its Rust fields by [`shader_struct!`].
+pub trait WgslStruct {
+    fn wgsl_struct() -> String;
+}
+
+/// Declares a struct deriving [`ShaderType`] together with its [`WgslStruct`] definition, so the
+/// layout seen by the shader is always the one encoded on the Rust side.
+macro_rules! shader_struct {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident { $($body:tt)* }
+    ) => {
+        $(#[$meta])*
+        #[derive(bevy::render::render_resource::ShaderType)]
+        $vis struct $name { $($body)* }
+
+        impl $crate::render::shaders::WgslType for $name {
+            fn wgsl_type() -> String {
+                stringify!($name).into()
+            }
+        }
+
+        $crate::render::shaders::shader_struct!(@wgsl $name { $($body)* });
+    };
+    (
+        @wgsl $name:ident {
+            $($(#[$field_meta:meta])* $field_vis:vis $field:ident: $ty:ty),* $(,)?
+        }
+    ) => {
+        impl $crate::render::shaders::WgslStruct for $name {
+            fn wgsl_struct() -> String {
+                let mut source = format!("struct {} {{\n", stringify!($name));
+                $(
+                    source.push_str(&format!(
+                        "    {}: {},\n",
+                        stringify!($field),
+                        <$ty as $crate::render::shaders::WgslType>::wgsl_type()
+                    ));
+                )*
+                source.push_str("};\n");
+                source
+            }
+        }
+    };
+}
+
+pub(crate) use shader_struct;
+
+fn module(import_path: &str, items: &[String]) -> String {
+    let mut source = format!("#define_import_path {import_path}\n");
+    for item in items {
+        source.push('\n');
+        source.push_str(item);
+    }
+    source
+}
+
+/// Source of `bevy_volumetric::types`: the voxel, buffer, atomics and params structs plus chunk
+/// constants.
+pub fn types_module() -> String {
+    module(
+        "bevy_volumetric::types",
+        &[
+            format!("const CHUNK_SZ: i32 = {CHUNK_SZ};\n"),
+            format!("const HISTOGRAM_BINS: u32 = {HISTOGRAM_BINS}u;\n"),
+            format!("const MAX_CLIP_PLANES: u32 = {MAX_CLIP_PLANES}u;\n"),
+            format!("const MAX_MATERIALS: u32 = {MAX_MATERIALS}u;\n"),
+            format!("const MAX_DETAIL_REGIONS: u32 = {MAX_DETAIL_REGIONS}u;\n"),
+            format!("const SLOT_BLOCK_CELLS: u32 = {SLOT_BLOCK_CELLS}u;\n"),
+            format!("const GRAPH_NOISE: u32 = {GRAPH_NOISE}u;\n"),
+            format!("const GRAPH_DOMAIN_WARP: u32 = {GRAPH_DOMAIN_WARP}u;\n"),
+            format!("const GRAPH_TERRACE: u32 = {GRAPH_TERRACE}u;\n"),
+            format!("const GRAPH_CAVE_CARVE: u32 = {GRAPH_CAVE_CARVE}u;\n"),
+            format!("const GRAPH_MATERIAL_ASSIGN: u32 = {GRAPH_MATERIAL_ASSIGN}u;\n"),
+            #[cfg(feature = "gpu-driven")]
+            format!("const CLUSTER_TRIANGLES: u32 = {CLUSTER_TRIANGLES}u;\n"),
+            format!("const BISECTION_STEPS: u32 = {BISECTION_STEPS}u;\n"),
+            Voxel::wgsl_struct(),
+            VoxelBuffer::wgsl_struct(),
+            VertexBuffer::wgsl_struct(),
+            NormalBuffer::wgsl_struct(),
+            IndexBuffer::wgsl_struct(),
+            UvBuffer::wgsl_struct(),
+            TangentBuffer::wgsl_struct(),
+            EmissiveBuffer::wgsl_struct(),
+            Atomics::wgsl_struct(),
+            ErosionParams::wgsl_struct(),
+            AmbientOcclusionParams::wgsl_struct(),
+            VoxelRun::wgsl_struct(),
+            VoxelRunBuffer::wgsl_struct(),
+            DecompressionParams::wgsl_struct(),
+            DualContouringParams::wgsl_struct(),
+            IsoSurfaceParams::wgsl_struct(),
+            ClipPlanesParams::wgsl_struct(),
+            AdaptiveResolutionParams::wgsl_struct(),
+            VolumeStatisticsParams::wgsl_struct(),
+            VolumeHistogram::wgsl_struct(),
+            ProceduralParams::wgsl_struct(),
+            GraphNode::wgsl_struct(),
+            GraphNodeBuffer::wgsl_struct(),
+            GraphLayer::wgsl_struct(),
+            GraphLayerBuffer::wgsl_struct(),
+            ParticleCollisionParams::wgsl_struct(),
+            DistanceFieldParams::wgsl_struct(),
+            SeedBuffer::wgsl_struct(),
+            SplatMapParams::wgsl_struct(),
+            #[cfg(feature = "readback")]
+            ChunkAnalysisParams::wgsl_struct(),
+            #[cfg(feature = "readback")]
+            ChunkAnalysisCounters::wgsl_struct(),
+            CellSlot::wgsl_struct(),
+            CellSlotBuffer::wgsl_struct(),
+            SlotCompactionParams::wgsl_struct(),
+            SlotStreamParams::wgsl_struct(),
+            #[cfg(feature = "readback")]
+            VoxelPickParams::wgsl_struct(),
+            #[cfg(feature = "readback")]
+            VoxelPickResult::wgsl_struct(),
+            #[cfg(feature = "readback")]
+            PropCandidateParams::wgsl_struct(),
+            #[cfg(feature = "readback")]
+            PropCandidatePoint::wgsl_struct(),
+            #[cfg(feature = "readback")]
+            PropCandidateBuffer::wgsl_struct(),
+            #[cfg(feature = "gpu-driven")]
+            ClusterBoundsParams::wgsl_struct(),
+            #[cfg(feature = "gpu-driven")]
+            ClusterBound::wgsl_struct(),
+            #[cfg(feature = "gpu-driven")]
+            ClusterBoundBuffer::wgsl_struct(),
+            #[cfg(feature = "gpu-driven")]
+            ChunkCullingView::wgsl_struct(),
+            #[cfg(feature = "gpu-driven")]
+            ChunkCullingParams::wgsl_struct(),
+            #[cfg(feature = "gpu-driven")]
+            ChunkDrawArgs::wgsl_struct(),
+            #[cfg(feature = "gpu-driven")]
+            ChunkDrawArgsBuffer::wgsl_struct(),
+            #[cfg(feature = "raymarch")]
+            LightProbeParams::wgsl_struct(),
+        ],
+    )
+}
+
+/// Source of `bevy_volumetric::tables`: the marching cubes lookup tables.
+pub fn tables_module() -> String {
+    module(
+        "bevy_volumetric::tables",
+        &[EdgeTable::wgsl_struct(), TriangleTable::wgsl_struct()],
+    )
+}
+
+/// Registers the importable `bevy_volumetric::*` shader modules. The `types` and `tables` modules
+/// are generated from Rust, the rest are embedded WGSL files that import them.
+pub fn load_shader_modules(app: &mut App) {
+    let mut shaders = app.world_mut().resource_mut::<Assets<Shader>>();
+    shaders.insert(
+        TYPES_SHADER_HANDLE.id(),
+        Shader::from_wgsl(types_module(), "bevy_volumetric/types.wgsl"),
+    );
+    shaders.insert(
+        TABLES_SHADER_HANDLE.id(),
+        Shader::from_wgsl(tables_module(), "bevy_volumetric/tables.wgsl"),
+    );
+
+    load_internal_asset!(
+        app,
+        BINDINGS_SHADER_HANDLE,
+        "bindings.wgsl",
+        Shader::from_wgsl
+    );
+    load_internal_asset!(app, VOXEL_SHADER_HANDLE, "voxel.wgsl", Shader::from_wgsl);
+    load_internal_asset!(
+        app,
+        NORMALS_SHADER_HANDLE,
+        "normals.wgsl",
+        Shader::from_wgsl
+    );
+    load_internal_asset!(
+        app,
+        PARTICLE_COLLISION_SHADER_HANDLE,
+        "particle_collision.wgsl",
+        Shader::from_wgsl
+    );
+    load_internal_asset!(
+        app,
+        SPLAT_MAP_SHADER_HANDLE,
+        "splat_map.wgsl",
+        Shader::from_wgsl
+    );
+}
@@ -0,0 +1,40 @@
+#define_import_path bevy_volumetric::normals
+
+// Normals are stored octahedral-encoded, as two 16 bit snorms packed in a `u32`, unless the
+// `full-normals` feature sets FULL_NORMALS to store them as they are for debugging. Both
+// functions match the element type of `NormalBuffer`.
+
+#ifdef FULL_NORMALS
+fn encode_normal(normal: vec3<f32>) -> vec3<f32> {
+    return normal;
+}
+
+fn decode_normal(encoded: vec3<f32>) -> vec3<f32> {
+    return encoded;
+}
+#else
+// Function to encode a normal, which needs not be normalized, on an octahedron. Zero normals
+// decode to +z.
+fn encode_normal(normal: vec3<f32>) -> u32 {
+    let l1 = abs(normal.x) + abs(normal.y) + abs(normal.z);
+    if (l1 == 0.0) {
+        return pack2x16snorm(vec2<f32>(0.0));
+    }
+    var p = normal.xy / l1;
+    // The lower half of the octahedron is folded over the upper one.
+    if (normal.z < 0.0) {
+        p = (1.0 - abs(p.yx)) * select(vec2<f32>(-1.0), vec2<f32>(1.0), p >= vec2<f32>(0.0));
+    }
+    return pack2x16snorm(p);
+}
+
+// Function to decode a normal encoded with `encode_normal`, normalized.
+fn decode_normal(encoded: u32) -> vec3<f32> {
+    let p = unpack2x16snorm(encoded);
+    var normal = vec3<f32>(p, 1.0 - abs(p.x) - abs(p.y));
+    let t = max(-normal.z, 0.0);
+    normal.x += select(t, -t, normal.x >= 0.0);
+    normal.y += select(t, -t, normal.y >= 0.0);
+    return normalize(normal);
+}
+#endif
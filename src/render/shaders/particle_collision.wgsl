@@ -0,0 +1,60 @@
+#define_import_path bevy_volumetric::particle_collision
+
+#import bevy_volumetric::types::ParticleCollisionParams
+
+// Collision of GPU particles against the density texture of a `ParticleCollisionVolume`, bound by
+// the particle system along with its `ParticleCollisionParams` and a linear sampler. Positions
+// outside of the texture are empty.
+
+// The texture coordinates of `world_position`, texel centers sitting on the voxels.
+fn collision_uvw(params: ParticleCollisionParams, world_position: vec3<f32>) -> vec3<f32> {
+    let voxel = (world_position - params.world_min) / params.voxel_size;
+    return (voxel + 0.5) / vec3<f32>(params.size);
+}
+
+// The trilinearly interpolated density at `world_position`.
+fn collision_density(
+    densities: texture_3d<f32>,
+    density_sampler: sampler,
+    params: ParticleCollisionParams,
+    world_position: vec3<f32>,
+) -> f32 {
+    let uvw = collision_uvw(params, world_position);
+    if (any(uvw < vec3(0.0)) || any(uvw > vec3(1.0))) {
+        return 0.0;
+    }
+    return textureSampleLevel(densities, density_sampler, uvw, 0.0).r;
+}
+
+fn collision_is_solid(
+    densities: texture_3d<f32>,
+    density_sampler: sampler,
+    params: ParticleCollisionParams,
+    world_position: vec3<f32>,
+) -> bool {
+    return collision_density(densities, density_sampler, params, world_position) >= params.iso_level;
+}
+
+// The outward surface normal at `world_position`, the negated gradient of the densities from
+// central differences a voxel apart, or zero where they don't change.
+fn collision_normal(
+    densities: texture_3d<f32>,
+    density_sampler: sampler,
+    params: ParticleCollisionParams,
+    world_position: vec3<f32>,
+) -> vec3<f32> {
+    let step = params.voxel_size;
+    let gradient = vec3(
+        collision_density(densities, density_sampler, params, world_position + vec3(step, 0.0, 0.0))
+            - collision_density(densities, density_sampler, params, world_position - vec3(step, 0.0, 0.0)),
+        collision_density(densities, density_sampler, params, world_position + vec3(0.0, step, 0.0))
+            - collision_density(densities, density_sampler, params, world_position - vec3(0.0, step, 0.0)),
+        collision_density(densities, density_sampler, params, world_position + vec3(0.0, 0.0, step))
+            - collision_density(densities, density_sampler, params, world_position - vec3(0.0, 0.0, step)),
+    );
+    let length_squared = dot(gradient, gradient);
+    if (length_squared == 0.0) {
+        return vec3(0.0);
+    }
+    return -gradient * inverseSqrt(length_squared);
+}
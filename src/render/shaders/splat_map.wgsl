@@ -0,0 +1,34 @@
+#define_import_path bevy_volumetric::splat_map
+
+// Blending of detail textures by the weights of the `SplatMap` of a chunk, bound by its material
+// along with a linear sampler. Positions are in the voxel coordinates of the chunk, the local
+// space of its mesh.
+
+// The texture coordinates of `local_position`, texel centers sitting on the centers of their
+// cells of `cell_size` voxels.
+fn splat_uvw(splat_map: texture_3d<f32>, cell_size: f32, local_position: vec3<f32>) -> vec3<f32> {
+    let size = vec3<f32>(textureDimensions(splat_map));
+    return local_position / (size * cell_size);
+}
+
+// The weights of the four materials of the splat map at `local_position`, summing to 1 wherever
+// any of them is near.
+fn splat_weights(
+    splat_map: texture_3d<f32>,
+    splat_sampler: sampler,
+    cell_size: f32,
+    local_position: vec3<f32>,
+) -> vec4<f32> {
+    let uvw = splat_uvw(splat_map, cell_size, local_position);
+    let weights = textureSampleLevel(splat_map, splat_sampler, uvw, 0.0);
+    let total = dot(weights, vec4<f32>(1.0));
+    if (total <= 0.0) {
+        return vec4<f32>(0.0);
+    }
+    return weights / total;
+}
+
+// The blend of the colours sampled from the detail texture of each material by `weights`.
+fn blend_splat(weights: vec4<f32>, c0: vec4<f32>, c1: vec4<f32>, c2: vec4<f32>, c3: vec4<f32>) -> vec4<f32> {
+    return c0 * weights.x + c1 * weights.y + c2 * weights.z + c3 * weights.w;
+}
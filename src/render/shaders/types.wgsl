@@ -1,2 +0,0 @@
-#define_import_path bevy_volumetric::types
-
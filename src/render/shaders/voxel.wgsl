@@ -0,0 +1,272 @@
+#define_import_path bevy_volumetric::voxel
+
+#import bevy_volumetric::types::{CHUNK_SZ, MAX_CLIP_PLANES, BISECTION_STEPS, Voxel}
+#import bevy_volumetric::bindings::{in_voxels, iso_surface, clip_planes}
+
+#ifdef VIRTUAL_VOLUME
+#import bevy_volumetric::bindings::page_table
+#endif
+#ifdef SEAMLESS_NORMALS
+#import bevy_volumetric::bindings::{neighbor_neg_x, neighbor_pos_x, neighbor_neg_y, neighbor_pos_y, neighbor_neg_z, neighbor_pos_z}
+#endif
+
+// Function to get a flat index for a given position in the 3D grid.
+fn get_flat_index(pos: vec3<i32>) -> u32 {
+    return u32(pos.x + pos.y * CHUNK_SZ + pos.z * CHUNK_SZ * CHUNK_SZ);
+}
+
+// Function to get the size of the volume in voxels.
+fn get_volume_size() -> vec3<i32> {
+#ifdef VIRTUAL_VOLUME
+    // The page table has one texel per brick.
+    return vec3<i32>(textureDimensions(page_table)) * CHUNK_SZ;
+#else
+    return vec3<i32>(CHUNK_SZ);
+#endif
+}
+
+// Function to check whether the voxel at a given position is inside the volume and resident.
+fn is_resident(pos: vec3<i32>) -> bool {
+    if (any(pos < vec3<i32>(0)) || any(pos >= get_volume_size())) {
+        return false;
+    }
+#ifdef VIRTUAL_VOLUME
+    return textureLoad(page_table, pos / CHUNK_SZ, 0).r != 0u;
+#else
+    return true;
+#endif
+}
+
+// Function to get the index of a resident voxel in the voxels buffer.
+fn get_voxel_index(pos: vec3<i32>) -> u32 {
+#ifdef VIRTUAL_VOLUME
+    // Bricks are stored one after the other in the pool, at slot (page table entry - 1).
+    let slot = textureLoad(page_table, pos / CHUNK_SZ, 0).r - 1u;
+    return slot * u32(CHUNK_SZ * CHUNK_SZ * CHUNK_SZ) + get_flat_index(pos % CHUNK_SZ);
+#else
+    return get_flat_index(pos);
+#endif
+}
+
+// Function to get the signed distance of a position in front of the nearest clip plane, negative
+// if it is behind all of them.
+fn clip_distance(pos: vec3<f32>) -> f32 {
+    var distance = -1e30;
+    for (var i = 0u; i < min(clip_planes.count, MAX_CLIP_PLANES); i++) {
+        let plane = clip_planes.planes[i];
+        distance = max(distance, dot(plane.xyz, pos) - plane.w);
+    }
+    return distance;
+}
+
+// Function to cut a density away in front of the clip planes. The density drops below the
+// iso-level exactly at the planes, so the surface is closed along them.
+fn clip_density(pos: vec3<i32>, density: f32) -> f32 {
+    if (clip_planes.count == 0u) {
+        return density;
+    }
+    return min(density, iso_surface.iso_level - clip_distance(vec3<f32>(pos)));
+}
+
+// Function to get the voxel at a given position, or an empty voxel if it is not resident.
+// Positions outside of the volume are resolved according to the `BOUNDARY_*` shader def, and
+// densities in front of the clip planes are cut away.
+fn get_voxel(pos: vec3<i32>) -> Voxel {
+    let size = get_volume_size();
+    var voxel: Voxel;
+
+#ifdef BOUNDARY_CLAMP
+    let p = clamp(pos, vec3<i32>(0), size - 1); // Repeat the nearest edge voxel.
+#else ifdef BOUNDARY_WRAP
+    let p = ((pos % size) + size) % size; // Wrap to the opposite edge, also for negative positions.
+#else
+    let p = pos;
+#endif
+
+#ifdef BOUNDARY_SOLID
+    if (any(p < vec3<i32>(0)) || any(p >= size)) {
+        voxel.density = clip_density(pos, 1.0);
+        return voxel;
+    }
+#endif
+
+    if (is_resident(p)) {
+        voxel = in_voxels.data[get_voxel_index(p)];
+    }
+    voxel.density = clip_density(pos, voxel.density);
+    return voxel;
+}
+
+// Function to get the density of a voxel at a given position.
+fn get_voxel_density(pos: vec3<i32>) -> f32 {
+    return get_voxel(pos).density;
+}
+
+// Function to unpack the RGB9E5 emissive color of a voxel, see `Voxel::emissive_bits`.
+fn unpack_emissive(packed: u32) -> vec3<f32> {
+    let mantissas = vec3<u32>(packed, packed >> 9u, packed >> 18u) & vec3<u32>(0x1ffu);
+    return vec3<f32>(mantissas) * exp2(f32(i32(packed >> 27u) - 24));
+}
+
+// Function to pack an emissive color as RGB9E5, the same as `pack_rgb9e5` of voxel_core.
+fn pack_emissive(color: vec3<f32>) -> u32 {
+    let c = clamp(color, vec3<f32>(0.0), vec3<f32>(65408.0));
+    let m = max(c.x, max(c.y, c.z));
+    var exponent = max(i32(floor(log2(max(m, 1e-10)))), -16) + 16;
+    // Rounding the largest component up may overflow its mantissa.
+    if (u32(floor(m * exp2(f32(24 - exponent)) + 0.5)) == 512u) {
+        exponent += 1;
+    }
+    let mantissas = vec3<u32>(floor(c * exp2(f32(24 - exponent)) + 0.5));
+    return mantissas.x | (mantissas.y << 9u) | (mantissas.z << 18u) | (u32(exponent) << 27u);
+}
+
+// Function to get the emissive color at `v`, trilinearly interpolated between the solid voxels
+// of the 8 around it, so that a surface between glowing voxels and empty space glows as brightly
+// as the voxels. Interpolates between all 8 where none is solid.
+fn vertex_emissive(v: vec3<f32>) -> vec3<f32> {
+    let base = vec3<i32>(floor(v));
+    let t = v - floor(v);
+    var solid = vec4<f32>(0.0);
+    var all = vec3<f32>(0.0);
+    for (var i = 0u; i < 8u; i++) {
+        let corner = vec3<u32>(i & 1u, (i >> 1u) & 1u, (i >> 2u) & 1u);
+        let w = mix(1.0 - t, t, vec3<f32>(corner));
+        let weight = w.x * w.y * w.z;
+        let voxel = get_voxel(base + vec3<i32>(corner));
+        let emissive = unpack_emissive(voxel.emissive);
+        all += weight * emissive;
+        if (voxel.density >= iso_surface.iso_level) {
+            solid += weight * vec4<f32>(emissive, 1.0);
+        }
+    }
+    if (solid.w > 0.0) {
+        return solid.xyz / solid.w;
+    }
+    return all;
+}
+
+// Function to get the density at `t` between `v1` and `v2` of the Catmull-Rom spline through the
+// densities `v0` to `v3` of four consecutive voxels.
+fn catmull_rom(v0: f32, v1: f32, v2: f32, v3: f32, t: f32) -> f32 {
+    let a = -0.5 * v0 + 1.5 * v1 - 1.5 * v2 + 0.5 * v3;
+    let b = v0 - 2.5 * v1 + 2.0 * v2 - 0.5 * v3;
+    let c = 0.5 * (v2 - v0);
+    return ((a * t + b) * t + c) * t + v1;
+}
+
+// Function to find by bisection where the spline through the voxels before, along and after the
+// edge from `p1` to `p2` crosses the iso-level, as a fraction of the edge.
+fn bisect_edge(p1: vec3<i32>, p2: vec3<i32>, v1: f32, v2: f32) -> f32 {
+    let v0 = get_voxel_density(p1 - (p2 - p1));
+    let v3 = get_voxel_density(p2 + (p2 - p1));
+    var lo = 0.0;
+    var hi = 1.0;
+    for (var i = 0u; i < BISECTION_STEPS; i++) {
+        let mid = (lo + hi) * 0.5;
+        if ((catmull_rom(v0, v1, v2, v3, mid) < iso_surface.iso_level) == (v1 < iso_surface.iso_level)) {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    return (lo + hi) * 0.5;
+}
+
+// Function to interpolate between two vertices to where their densities cross the iso-level, as
+// selected by the `EDGE_INTERPOLATION_*` shader def.
+fn interp_vertex(p1: vec3<f32>, p2: vec3<f32>, v1: f32, v2: f32) -> vec3<f32> {
+    let mu = (iso_surface.iso_level - v1) / (v2 - v1);
+#ifdef EDGE_INTERPOLATION_SMOOTHSTEP
+    // Invert the smoothstep between the two densities.
+    return p1 + (0.5 - sin(asin(1.0 - 2.0 * clamp(mu, 0.0, 1.0)) / 3.0)) * (p2 - p1);
+#else ifdef EDGE_INTERPOLATION_BISECTION
+    // Only the edges between two neighbouring voxels can sample the voxels around them, not the
+    // edges of refined cells.
+    let edge = p2 - p1;
+    if (any(fract(p1) != vec3<f32>(0.0)) || dot(abs(edge), vec3<f32>(1.0)) != 1.0) {
+        return p1 + mu * edge;
+    }
+    return p1 + bisect_edge(vec3<i32>(p1), vec3<i32>(p2), v1, v2) * edge;
+#else
+    return p1 + mu * (p2 - p1);
+#endif
+}
+
+#ifdef SEAMLESS_NORMALS
+// Function to check whether there is a chunk next to `face`, in the order -x, +x, -y, +y, -z, +z.
+// Faces without one are bound to a buffer of a single voxel.
+fn has_neighbor(face: u32) -> bool {
+    var len = 0u;
+    switch face {
+        case 0u: { len = arrayLength(&neighbor_neg_x.data); }
+        case 1u: { len = arrayLength(&neighbor_pos_x.data); }
+        case 2u: { len = arrayLength(&neighbor_neg_y.data); }
+        case 3u: { len = arrayLength(&neighbor_pos_y.data); }
+        case 4u: { len = arrayLength(&neighbor_neg_z.data); }
+        default: { len = arrayLength(&neighbor_pos_z.data); }
+    }
+    return len >= u32(CHUNK_SZ * CHUNK_SZ * CHUNK_SZ);
+}
+
+// Function to get the density of the voxel at `index` in the chunk next to `face`.
+fn neighbor_density(face: u32, index: u32) -> f32 {
+    switch face {
+        case 0u: { return neighbor_neg_x.data[index].density; }
+        case 1u: { return neighbor_pos_x.data[index].density; }
+        case 2u: { return neighbor_neg_y.data[index].density; }
+        case 3u: { return neighbor_pos_y.data[index].density; }
+        case 4u: { return neighbor_neg_z.data[index].density; }
+        default: { return neighbor_pos_z.data[index].density; }
+    }
+}
+
+// Function to get the density of a voxel at a given position, read from the chunk next to the face
+// it is past. Positions past an edge or a corner, or past a face without a neighbour, are resolved
+// by `get_voxel_density`.
+fn seamless_density(pos: vec3<i32>) -> f32 {
+    let above = pos >= vec3<i32>(CHUNK_SZ);
+    let outside = select(vec3<i32>(0), vec3<i32>(1), (pos < vec3<i32>(0)) | above);
+    if (outside.x + outside.y + outside.z != 1) {
+        return get_voxel_density(pos);
+    }
+
+    let axis = select(select(2u, 1u, outside.y == 1), 0u, outside.x == 1);
+    let face = axis * 2u + select(0u, 1u, any(above));
+    if (!has_neighbor(face)) {
+        return get_voxel_density(pos);
+    }
+    let p = ((pos % CHUNK_SZ) + CHUNK_SZ) % CHUNK_SZ; // The position in the neighbour.
+    return clip_density(pos, neighbor_density(face, get_flat_index(p)));
+}
+
+// Function to get the density gradient at a lattice point by central differences of the
+// `seamless_density`.
+fn seamless_gradient(p: vec3<i32>) -> vec3<f32> {
+    return 0.5 * vec3<f32>(
+        seamless_density(p + vec3<i32>(1, 0, 0)) - seamless_density(p - vec3<i32>(1, 0, 0)),
+        seamless_density(p + vec3<i32>(0, 1, 0)) - seamless_density(p - vec3<i32>(0, 1, 0)),
+        seamless_density(p + vec3<i32>(0, 0, 1)) - seamless_density(p - vec3<i32>(0, 0, 1)),
+    );
+}
+
+// Function to get the normal at `v` against the density gradient, interpolated between the
+// gradients of the 8 lattice points around it, and oriented like the flat normal `fallback` of
+// its face so the winding of the mesh still decides which side is lit. Returns `fallback` where
+// the gradient vanishes.
+fn gradient_normal(v: vec3<f32>, fallback: vec3<f32>) -> vec3<f32> {
+    let base = vec3<i32>(floor(v));
+    let t = v - floor(v);
+    var gradient = vec3<f32>(0.0);
+    for (var i = 0u; i < 8u; i++) {
+        let corner = vec3<u32>(i & 1u, (i >> 1u) & 1u, (i >> 2u) & 1u);
+        let w = mix(1.0 - t, t, vec3<f32>(corner));
+        gradient += w.x * w.y * w.z * seamless_gradient(base + vec3<i32>(corner));
+    }
+    if (dot(gradient, gradient) < 1e-12) {
+        return fallback;
+    }
+    let normal = -normalize(gradient);
+    return select(normal, -normal, dot(normal, fallback) < 0.0);
+}
+#endif
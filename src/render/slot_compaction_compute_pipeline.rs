@@ -0,0 +1,217 @@
+use bevy::{
+    prelude::*,
+    render::{
+        render_resource::{
+            binding_types::{
+                storage_buffer, storage_buffer_read_only_sized, storage_buffer_sized,
+                uniform_buffer,
+            },
+            *,
+        },
+        renderer::{RenderDevice, RenderQueue},
+    },
+};
+
+use crate::data::{
+    atomics::Atomics,
+    gpu_fixed_output_slots::GpuSlotCompaction,
+    normal_encoding::NORMAL_WORDS,
+    shader_platform::{CellSlotBuffer, SlotCompactionParams, SlotStreamParams, SLOT_BLOCK_CELLS},
+};
+
+const SHADER_ASSET_PATH: &str = "shaders/slot_compaction.wgsl";
+
+const WORKGROUP_SIZE: u32 = 64;
+
+/// The `u32`s of an element of the vertices, normals, uvs, tangents, emissives and indices,
+/// compacted in that order. Normals are
+/// [`EncodedNormal`](crate::data::normal_encoding::EncodedNormal)s.
+const STREAM_WORDS: [u32; 6] = [4, NORMAL_WORDS, 2, 4, 1, 1];
+
+/// Compacts the fixed slots a volumetric entity was meshed into on a
+/// [`ShaderPlatform`](crate::data::shader_platform::ShaderPlatform) with `fixed_output_slots`:
+/// counts the primitives of each block of cells, scans the blocks, then scatters each output
+/// buffer. With `prefix_sum_allocation`, the same scan resolves where the primitives counted for
+/// each cell start instead, for the scatter pass of the meshing pipeline.
+#[derive(Resource)]
+pub struct SlotCompactionComputePipeline {
+    pub bind_group_layout: BindGroupLayout,
+    pub stream_layout: BindGroupLayout,
+    pub count_pipeline: CachedComputePipelineId,
+    pub scan_pipeline: CachedComputePipelineId,
+    pub scatter_pipeline: CachedComputePipelineId,
+    pub resolve_pipeline: CachedComputePipelineId,
+    /// The params of each stream, shared by every entity.
+    pub stream_params: Vec<UniformBuffer<SlotStreamParams>>,
+}
+
+impl FromWorld for SlotCompactionComputePipeline {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+        let render_queue = world.resource::<RenderQueue>();
+
+        let bind_group_layout = render_device.create_bind_group_layout(
+            Some("SlotCompactionComputePipeline::bind_group_layout"),
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::COMPUTE,
+                (
+                    uniform_buffer::<SlotCompactionParams>(false),
+                    storage_buffer::<CellSlotBuffer>(false),
+                    storage_buffer_sized(false, None),
+                    storage_buffer::<Atomics>(false),
+                ),
+            ),
+        );
+
+        let stream_layout = render_device.create_bind_group_layout(
+            Some("SlotCompactionComputePipeline::stream_layout"),
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::COMPUTE,
+                (
+                    uniform_buffer::<SlotStreamParams>(false),
+                    storage_buffer_read_only_sized(false, None),
+                    storage_buffer_sized(false, None),
+                ),
+            ),
+        );
+
+        let stream_params = STREAM_WORDS
+            .iter()
+            .enumerate()
+            .map(|(stream, words)| {
+                let mut params = UniformBuffer::from(SlotStreamParams {
+                    words: *words,
+                    indices: (stream == STREAM_WORDS.len() - 1) as u32,
+                });
+                params.write_buffer(render_device, render_queue);
+                params
+            })
+            .collect();
+
+        let shader = world.load_asset(SHADER_ASSET_PATH);
+
+        let pipeline_cache = world.resource::<PipelineCache>();
+
+        let queue = |entry_point: &'static str, layout: Vec<BindGroupLayout>| {
+            pipeline_cache.queue_compute_pipeline(ComputePipelineDescriptor {
+                label: Some(format!("SlotCompactionComputePipeline {entry_point}").into()),
+                layout,
+                push_constant_ranges: Vec::new(),
+                shader: shader.clone(),
+                shader_defs: Vec::new(),
+                entry_point: entry_point.into(),
+            })
+        };
+
+        let count_pipeline = queue("count_blocks", vec![bind_group_layout.clone()]);
+        let scan_pipeline = queue("scan_blocks", vec![bind_group_layout.clone()]);
+        let scatter_pipeline = queue(
+            "scatter",
+            vec![bind_group_layout.clone(), stream_layout.clone()],
+        );
+        let resolve_pipeline = queue("resolve_offsets", vec![bind_group_layout.clone()]);
+
+        SlotCompactionComputePipeline {
+            bind_group_layout,
+            stream_layout,
+            count_pipeline,
+            scan_pipeline,
+            scatter_pipeline,
+            resolve_pipeline,
+            stream_params,
+        }
+    }
+}
+
+impl SlotCompactionComputePipeline {
+    /// Whether all the compaction pipelines are loaded.
+    pub fn is_loaded(&self, pipeline_cache: &PipelineCache) -> bool {
+        [
+            self.count_pipeline,
+            self.scan_pipeline,
+            self.scatter_pipeline,
+            self.resolve_pipeline,
+        ]
+        .into_iter()
+        .all(|pipeline| pipeline_cache.get_compute_pipeline(pipeline).is_some())
+    }
+
+    /// Records the compaction of the fixed slots just meshed into the output buffers bound by
+    /// `gpu_slot_compaction`, along with their counters.
+    pub fn compact(
+        &self,
+        pipeline_cache: &PipelineCache,
+        gpu_slot_compaction: &GpuSlotCompaction,
+        command_encoder: &mut CommandEncoder,
+    ) {
+        let Some(scatter_pipeline) = pipeline_cache.get_compute_pipeline(self.scatter_pipeline)
+        else {
+            return;
+        };
+        let Some(mut pass) = self.scan(pipeline_cache, gpu_slot_compaction, command_encoder) else {
+            return;
+        };
+
+        let cells = gpu_slot_compaction.params_buffer.get().cells;
+        pass.set_pipeline(scatter_pipeline);
+        for stream_bind_group in &gpu_slot_compaction.stream_bind_groups {
+            pass.set_bind_group(1, stream_bind_group, &[]);
+            pass.dispatch_workgroups(cells.div_ceil(WORKGROUP_SIZE), 1, 1);
+        }
+    }
+
+    /// Records the prefix sum of the primitives just counted in the slots of each cell, resolving
+    /// where they start in the output buffers bound by `gpu_slot_compaction` and their counters.
+    pub fn prefix_sum(
+        &self,
+        pipeline_cache: &PipelineCache,
+        gpu_slot_compaction: &GpuSlotCompaction,
+        command_encoder: &mut CommandEncoder,
+    ) {
+        let Some(resolve_pipeline) = pipeline_cache.get_compute_pipeline(self.resolve_pipeline)
+        else {
+            return;
+        };
+        let Some(mut pass) = self.scan(pipeline_cache, gpu_slot_compaction, command_encoder) else {
+            return;
+        };
+
+        let cells = gpu_slot_compaction.params_buffer.get().cells;
+        pass.set_pipeline(resolve_pipeline);
+        pass.dispatch_workgroups(cells.div_ceil(WORKGROUP_SIZE), 1, 1);
+    }
+
+    /// Begins the pass counting the primitives of each block of cells and scanning the blocks.
+    fn scan<'a>(
+        &self,
+        pipeline_cache: &'a PipelineCache,
+        gpu_slot_compaction: &'a GpuSlotCompaction,
+        command_encoder: &'a mut CommandEncoder,
+    ) -> Option<ComputePass<'a>> {
+        let (Some(count_pipeline), Some(scan_pipeline)) = (
+            pipeline_cache.get_compute_pipeline(self.count_pipeline),
+            pipeline_cache.get_compute_pipeline(self.scan_pipeline),
+        ) else {
+            return None;
+        };
+
+        let blocks = gpu_slot_compaction
+            .params_buffer
+            .get()
+            .cells
+            .div_ceil(SLOT_BLOCK_CELLS);
+
+        let mut pass = command_encoder.begin_compute_pass(&ComputePassDescriptor {
+            label: Some("slot_compaction"),
+            ..default()
+        });
+        pass.set_bind_group(0, &gpu_slot_compaction.bind_group, &[]);
+
+        pass.set_pipeline(count_pipeline);
+        pass.dispatch_workgroups(blocks.div_ceil(WORKGROUP_SIZE), 1, 1);
+
+        pass.set_pipeline(scan_pipeline);
+        pass.dispatch_workgroups(1, 1, 1);
+        Some(pass)
+    }
+}
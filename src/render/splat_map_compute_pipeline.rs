@@ -0,0 +1,120 @@
+use bevy::{
+    prelude::*,
+    render::{
+        render_asset::RenderAssets,
+        render_resource::{
+            binding_types::{storage_buffer_read_only, uniform_buffer},
+            *,
+        },
+        renderer::RenderDevice,
+        texture::GpuImage,
+    },
+};
+
+use crate::{
+    data::{
+        gpu_splat_map::GpuSplatMap,
+        gpu_voxel_material::GpuVoxelMaterial,
+        splat_map::{SplatMapParams, SPLAT_MAP_FORMAT},
+        voxel_material::VoxelMaterialComponents,
+    },
+    render::{
+        post_mesh_compute_pass::PostMeshComputePass, voxel_mesh_compute_pipeline::VoxelBuffer,
+    },
+};
+
+const SHADER_ASSET_PATH: &str = "shaders/splat_map.wgsl";
+
+#[derive(Resource)]
+pub struct SplatMapComputePipeline {
+    pub bind_group_layout: BindGroupLayout,
+    pub pipeline: CachedComputePipelineId,
+}
+
+impl FromWorld for SplatMapComputePipeline {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+
+        let bind_group_layout = render_device.create_bind_group_layout(
+            Some("SplatMapComputePipeline::bind_group_layout"),
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::COMPUTE,
+                (
+                    uniform_buffer::<SplatMapParams>(false),
+                    storage_buffer_read_only::<VoxelBuffer>(false),
+                    BindingType::StorageTexture {
+                        access: StorageTextureAccess::WriteOnly,
+                        format: SPLAT_MAP_FORMAT,
+                        view_dimension: TextureViewDimension::D3,
+                    },
+                ),
+            ),
+        );
+
+        let shader = world.load_asset(SHADER_ASSET_PATH);
+
+        let pipeline_cache = world.resource::<PipelineCache>();
+
+        let pipeline = pipeline_cache.queue_compute_pipeline(ComputePipelineDescriptor {
+            label: Some("SplatMapComputePipeline shader".into()),
+            layout: vec![bind_group_layout.clone()],
+            push_constant_ranges: Vec::new(),
+            shader,
+            shader_defs: Vec::new(),
+            entry_point: "main".into(),
+        });
+
+        SplatMapComputePipeline {
+            bind_group_layout,
+            pipeline,
+        }
+    }
+}
+
+/// Generates the splat texture of each remeshed entity with a [`GpuSplatMap`], right after its
+/// meshing.
+pub struct SplatMapComputePass;
+
+impl PostMeshComputePass for SplatMapComputePass {
+    fn run(
+        &self,
+        world: &World,
+        entity: Entity,
+        _gpu_voxel_material: &GpuVoxelMaterial,
+        command_encoder: &mut CommandEncoder,
+    ) {
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let splat_map_pipeline = world.resource::<SplatMapComputePipeline>();
+        let gpu_images = world.resource::<RenderAssets<GpuImage>>();
+        let gpu_splat_maps = world.resource::<VoxelMaterialComponents<GpuSplatMap>>();
+
+        let Some(pipeline) = pipeline_cache.get_compute_pipeline(splat_map_pipeline.pipeline)
+        else {
+            return; // the pipeline is not loaded yet
+        };
+        let Some(gpu_splat_map) = gpu_splat_maps.get(&entity) else {
+            return;
+        };
+        let (Some(bind_group), Some(gpu_image)) = (
+            gpu_splat_map.bind_group.as_ref(),
+            gpu_images.get(&gpu_splat_map.image),
+        ) else {
+            return;
+        };
+
+        let size = UVec3::new(
+            gpu_image.texture.width(),
+            gpu_image.texture.height(),
+            gpu_image.texture.depth_or_array_layers(),
+        );
+        let workgroups = (size + 3) / 4;
+
+        let mut pass = command_encoder.begin_compute_pass(&ComputePassDescriptor {
+            label: Some("splat_map"),
+            ..default()
+        });
+        pass.set_bind_group(0, bind_group, &[]);
+        pass.set_pipeline(pipeline);
+        pass.dispatch_workgroups(workgroups.x, workgroups.y, workgroups.z);
+    }
+}
@@ -0,0 +1,169 @@
+use bevy::{
+    prelude::*,
+    render::{
+        render_graph::{self, RenderLabel},
+        render_resource::{
+            binding_types::{storage_buffer, storage_buffer_read_only, uniform_buffer},
+            *,
+        },
+        renderer::{RenderContext, RenderDevice},
+    },
+};
+
+use crate::{
+    data::{
+        gpu_volume_statistics::GpuVolumeStatistics,
+        volume_statistics::{
+            VolumeHistogram, VolumeStatisticsParams, VolumeStatisticsReadback, HISTOGRAM_BINS,
+        },
+        voxel_material::VoxelMaterialComponents,
+    },
+    render::voxel_mesh_compute_pipeline::VoxelBuffer,
+};
+
+const SHADER_ASSET_PATH: &str = "shaders/volume_statistics.wgsl";
+
+/// The histogram shader accumulates one workgroup-local bin per invocation.
+const WORKGROUP_SIZE: u32 = HISTOGRAM_BINS as u32;
+
+#[derive(Resource)]
+pub struct VolumeStatisticsComputePipeline {
+    pub bind_group_layout: BindGroupLayout,
+    /// Finds the density range of the voxels.
+    pub range_pipeline: CachedComputePipelineId,
+    /// Counts the voxels of each histogram bin over the density range.
+    pub histogram_pipeline: CachedComputePipelineId,
+}
+
+impl VolumeStatisticsComputePipeline {
+    pub fn is_ready(&self, pipeline_cache: &PipelineCache) -> bool {
+        pipeline_cache
+            .get_compute_pipeline(self.range_pipeline)
+            .is_some()
+            && pipeline_cache
+                .get_compute_pipeline(self.histogram_pipeline)
+                .is_some()
+    }
+}
+
+impl FromWorld for VolumeStatisticsComputePipeline {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+
+        let bind_group_layout = render_device.create_bind_group_layout(
+            Some("VolumeStatisticsComputePipeline::bind_group_layout"),
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::COMPUTE,
+                (
+                    uniform_buffer::<VolumeStatisticsParams>(false),
+                    storage_buffer_read_only::<VoxelBuffer>(false),
+                    storage_buffer::<VolumeHistogram>(false),
+                ),
+            ),
+        );
+
+        let shader = world.load_asset(SHADER_ASSET_PATH);
+
+        let pipeline_cache = world.resource::<PipelineCache>();
+
+        let pipeline = |entry_point: &'static str| {
+            pipeline_cache.queue_compute_pipeline(ComputePipelineDescriptor {
+                label: Some(format!("VolumeStatisticsComputePipeline {entry_point} shader").into()),
+                layout: vec![bind_group_layout.clone()],
+                push_constant_ranges: Vec::new(),
+                shader: shader.clone(),
+                shader_defs: Vec::new(),
+                entry_point: entry_point.into(),
+            })
+        };
+
+        VolumeStatisticsComputePipeline {
+            range_pipeline: pipeline("range"),
+            histogram_pipeline: pipeline("histogram"),
+            bind_group_layout,
+        }
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+pub struct VolumeStatisticsComputeNodeLabel;
+
+/// Recomputes the histogram of every [`VolumeStatisticsReadback`] entity whose voxels changed and
+/// copies it to its staging buffer.
+pub struct VolumeStatisticsComputeNode {
+    statistics_query: QueryState<Entity, With<VolumeStatisticsReadback>>,
+}
+
+impl FromWorld for VolumeStatisticsComputeNode {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            statistics_query: world.query_filtered(),
+        }
+    }
+}
+
+impl render_graph::Node for VolumeStatisticsComputeNode {
+    fn update(&mut self, world: &mut World) {
+        self.statistics_query.update_archetypes(world);
+    }
+
+    fn run(
+        &self,
+        _graph: &mut render_graph::RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), render_graph::NodeRunError> {
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let statistics_pipeline = world.resource::<VolumeStatisticsComputePipeline>();
+        let gpu_volume_statistics =
+            world.resource::<VoxelMaterialComponents<GpuVolumeStatistics>>();
+
+        let (Some(range_pipeline), Some(histogram_pipeline)) = (
+            pipeline_cache.get_compute_pipeline(statistics_pipeline.range_pipeline),
+            pipeline_cache.get_compute_pipeline(statistics_pipeline.histogram_pipeline),
+        ) else {
+            return Ok(()); // the pipelines are not loaded yet
+        };
+
+        let command_encoder = render_context.command_encoder();
+
+        for entity in self.statistics_query.iter_manual(world) {
+            let Some(gpu_statistics) = gpu_volume_statistics.get(&entity) else {
+                continue;
+            };
+            if !gpu_statistics.changed {
+                continue;
+            }
+            let Some(bind_group) = gpu_statistics.bind_group.as_ref() else {
+                continue;
+            };
+
+            let voxel_count = gpu_statistics.params_buffer.get().voxel_count;
+            let workgroups = voxel_count.div_ceil(WORKGROUP_SIZE);
+
+            command_encoder.clear_buffer(&gpu_statistics.histogram_buffer, 0, None);
+
+            {
+                let mut pass = command_encoder.begin_compute_pass(&ComputePassDescriptor {
+                    label: Some("volume_statistics"),
+                    ..default()
+                });
+                pass.set_bind_group(0, bind_group, &[]);
+                pass.set_pipeline(range_pipeline);
+                pass.dispatch_workgroups(workgroups, 1, 1);
+                pass.set_pipeline(histogram_pipeline);
+                pass.dispatch_workgroups(workgroups, 1, 1);
+            }
+
+            command_encoder.copy_buffer_to_buffer(
+                &gpu_statistics.histogram_buffer,
+                0,
+                &gpu_statistics.histogram_staging_buffer,
+                0,
+                gpu_statistics.histogram_buffer.size(),
+            );
+        }
+
+        Ok(())
+    }
+}
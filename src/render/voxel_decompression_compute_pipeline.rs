@@ -0,0 +1,133 @@
+use bevy::{
+    prelude::*,
+    render::{
+        render_graph::{self, RenderLabel},
+        render_resource::{
+            binding_types::{storage_buffer, storage_buffer_read_only, uniform_buffer},
+            *,
+        },
+        renderer::{RenderContext, RenderDevice},
+    },
+};
+
+use crate::{
+    data::{
+        compressed_voxels::{CompressedUpload, DecompressionParams, VoxelRunBuffer},
+        gpu_compressed_voxels::GpuCompressedVoxels,
+        voxel_material::VoxelMaterialComponents,
+    },
+    render::voxel_mesh_compute_pipeline::VoxelBuffer,
+};
+
+const SHADER_ASSET_PATH: &str = "shaders/voxel_decompression.wgsl";
+
+const WORKGROUP_SIZE: u32 = 256;
+
+#[derive(Resource)]
+pub struct VoxelDecompressionComputePipeline {
+    pub bind_group_layout: BindGroupLayout,
+    pub pipeline: CachedComputePipelineId,
+}
+
+impl FromWorld for VoxelDecompressionComputePipeline {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+
+        let bind_group_layout = render_device.create_bind_group_layout(
+            Some("VoxelDecompressionComputePipeline::bind_group_layout"),
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::COMPUTE,
+                (
+                    uniform_buffer::<DecompressionParams>(false),
+                    storage_buffer_read_only::<VoxelBuffer>(false),
+                    storage_buffer_read_only::<VoxelRunBuffer>(false),
+                    storage_buffer::<VoxelBuffer>(false),
+                ),
+            ),
+        );
+
+        let shader = world.load_asset(SHADER_ASSET_PATH);
+
+        let pipeline_cache = world.resource::<PipelineCache>();
+
+        let pipeline = pipeline_cache.queue_compute_pipeline(ComputePipelineDescriptor {
+            label: Some("VoxelDecompressionComputePipeline shader".into()),
+            layout: vec![bind_group_layout.clone()],
+            push_constant_ranges: Vec::new(),
+            shader,
+            shader_defs: Vec::new(),
+            entry_point: "main".into(),
+        });
+
+        VoxelDecompressionComputePipeline {
+            bind_group_layout,
+            pipeline,
+        }
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+pub struct VoxelDecompressionComputeNodeLabel;
+
+/// Expands the voxels uploaded this frame for every [`CompressedUpload`] entity into its voxels
+/// buffer, before anything reads them.
+pub struct VoxelDecompressionComputeNode {
+    compressed_query: QueryState<Entity, With<CompressedUpload>>,
+}
+
+impl FromWorld for VoxelDecompressionComputeNode {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            compressed_query: world.query_filtered(),
+        }
+    }
+}
+
+impl render_graph::Node for VoxelDecompressionComputeNode {
+    fn update(&mut self, world: &mut World) {
+        self.compressed_query.update_archetypes(world);
+    }
+
+    fn run(
+        &self,
+        _graph: &mut render_graph::RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), render_graph::NodeRunError> {
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let decompression_pipeline = world.resource::<VoxelDecompressionComputePipeline>();
+        let gpu_compressed_voxels =
+            world.resource::<VoxelMaterialComponents<GpuCompressedVoxels>>();
+
+        let Some(pipeline) = pipeline_cache.get_compute_pipeline(decompression_pipeline.pipeline)
+        else {
+            return Ok(()); // the pipeline is not loaded yet
+        };
+
+        let mut pass =
+            render_context
+                .command_encoder()
+                .begin_compute_pass(&ComputePassDescriptor {
+                    label: Some("voxel_decompression"),
+                    ..default()
+                });
+        pass.set_pipeline(pipeline);
+
+        for entity in self.compressed_query.iter_manual(world) {
+            let Some(gpu_compressed) = gpu_compressed_voxels.get(&entity) else {
+                continue;
+            };
+            let Some(bind_group) = gpu_compressed.bind_group.as_ref() else {
+                continue;
+            };
+            if gpu_compressed.voxel_count == 0 {
+                continue;
+            }
+
+            pass.set_bind_group(0, bind_group, &[]);
+            pass.dispatch_workgroups(gpu_compressed.voxel_count.div_ceil(WORKGROUP_SIZE), 1, 1);
+        }
+
+        Ok(())
+    }
+}
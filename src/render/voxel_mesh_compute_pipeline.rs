@@ -1,82 +1,482 @@
 use crate::{
-    bundles::volumetric_bundle::Volumetric,
-    data::{atomics::Atomics, voxel::Voxel},
-    CHUNK_SZ, CHUNK_SZ_3,
+    bundles::volumetric_bundle::{
+        MaterialSplit, MeshCapping, SeamlessNormals, Volumetric, VoxelComputeSuspended,
+    },
+    channels::{PendingReadbacks, ReadbackPolicy, VertexReadback},
+    data::{
+        adaptive_resolution::{AdaptiveResolution, AdaptiveResolutionParams},
+        atomics::Atomics,
+        boundary_mode::BoundaryMode,
+        clip_planes::ClipPlanesParams,
+        dual_contouring::DualContouringParams,
+        edge_interpolation::EdgeInterpolation,
+        gpu_chunk_state::GpuChunkStates,
+        gpu_iso_surface::GpuIsoSurfaces,
+        gpu_neighbor_voxels::GpuNeighborVoxels,
+        gpu_virtual_volume::GpuVirtualVolume,
+        iso_surface::IsoSurfaceParams,
+        meshing_algorithm::MeshingAlgorithm,
+        shader_platform::{CellSlot, ShaderPlatform},
+        voxel::Voxel,
+    },
+    VoxelComputePaused, CHUNK_SZ,
 };
 use bevy::{
     ecs::{
         query::ROQueryItem,
         system::{lifetimeless::SRes, SystemParamItem},
-        world::Command,
     },
     prelude::*,
     render::{
-        extract_component::{ExtractComponent, ExtractComponentPlugin},
-        render_graph::{self, NodeRunError, RenderGraph, RenderLabel},
+        render_graph::{self, RenderLabel},
         render_phase::{PhaseItem, RenderCommand, RenderCommandResult, TrackedRenderPass},
-        render_resource::{binding_types::storage_buffer, *},
+        render_resource::{
+            binding_types::{storage_buffer, storage_buffer_read_only, texture_3d, uniform_buffer},
+            *,
+        },
         renderer::{RenderContext, RenderDevice, RenderQueue},
-        Extract, Render, RenderApp, RenderSet,
     },
-    utils::{info, HashMap},
+    utils::{HashMap, HashSet},
 };
-use crossbeam_channel::{Receiver, Sender};
+use std::ops::Range;
 
 use crate::{
-    channels::RenderWorldSender,
     data::{
+        gpu_fixed_output_slots::{GpuFixedOutputSlots, GpuSlotCompaction},
         gpu_voxel_material::GpuVoxelMaterial,
         gpu_voxel_material_bind_group::GpuVoxelMaterialBindGroups,
+        material_split::MaterialIndexRanges,
+        raw_mesh_data::GpuRawMeshData,
+        readback_header::MeshReadbackHeader,
         voxel_material::VoxelMaterialComponents,
     },
+    render::{
+        post_mesh_compute_pass::PostMeshComputePasses, shaders::shader_struct,
+        slot_compaction_compute_pipeline::SlotCompactionComputePipeline,
+    },
 };
 
 const SHADER_ASSET_PATH: &str = "shaders/gpu_readback.wgsl";
 
-#[derive(ShaderType, Clone)]
-pub struct VoxelBuffer {
-    #[size(runtime)]
-    data: Vec<Voxel>,
+shader_struct! {
+    #[derive(Clone)]
+    pub struct VoxelBuffer {
+        #[size(runtime)]
+        data: Vec<Voxel>,
+    }
+}
+
+shader_struct! {
+    #[derive(Clone)]
+    pub struct VertexBuffer {
+        #[size(runtime)]
+        data: Vec<Vec3>,
+    }
+}
+
+#[cfg(not(feature = "full-normals"))]
+shader_struct! {
+    /// Per-vertex normals, octahedral encoded, see
+    /// [`EncodedNormal`](crate::data::normal_encoding::EncodedNormal).
+    #[derive(Clone)]
+    pub struct NormalBuffer {
+        #[size(runtime)]
+        data: Vec<u32>,
+    }
+}
+
+#[cfg(feature = "full-normals")]
+shader_struct! {
+    #[derive(Clone)]
+    pub struct NormalBuffer {
+        #[size(runtime)]
+        data: Vec<Vec3>,
+    }
 }
 
-#[derive(ShaderType, Clone)]
-pub struct VertexBuffer {
-    #[size(runtime)]
-    data: Vec<Vec3>,
+shader_struct! {
+    /// Per-vertex tangents along the triplanar UVs, with the handedness of the bitangent in `w`,
+    /// read back into the [`Mesh::ATTRIBUTE_TANGENT`] of iso-surfaces and into [`RawMeshData`].
+    #[derive(Clone)]
+    pub struct TangentBuffer {
+        #[size(runtime)]
+        data: Vec<Vec4>,
+    }
 }
 
-#[derive(ShaderType, Clone)]
-pub struct NormalBuffer {
-    #[size(runtime)]
-    data: Vec<Vec3>,
+shader_struct! {
+    /// Per-vertex emissive colors interpolated from the voxels, packed as RGB9E5 like
+    /// [`Voxel::emissive_bits`](crate::data::voxel::Voxel::emissive_bits), read back into the
+    /// [`ATTRIBUTE_EMISSIVE`](crate::data::iso_surface::ATTRIBUTE_EMISSIVE) of iso-surfaces and
+    /// into [`RawMeshData`].
+    #[derive(Clone)]
+    pub struct EmissiveBuffer {
+        #[size(runtime)]
+        data: Vec<u32>,
+    }
 }
 
-#[derive(ShaderType, Clone)]
-pub struct IndexBuffer {
-    #[size(runtime)]
-    data: Vec<UVec3>,
+shader_struct! {
+    #[derive(Clone)]
+    pub struct IndexBuffer {
+        #[size(runtime)]
+        data: Vec<u32>,
+    }
 }
 
-#[derive(ShaderType, Clone)]
-pub struct UvBuffer {
-    #[size(runtime)]
-    data: Vec<Vec2>,
+shader_struct! {
+    #[derive(Clone)]
+    pub struct UvBuffer {
+        #[size(runtime)]
+        data: Vec<Vec2>,
+    }
 }
 
-#[derive(ShaderType, Clone)]
-pub struct EdgeTable {
-    data: [u32; 256],
+shader_struct! {
+    #[derive(Clone)]
+    pub struct EdgeTable {
+        data: [u32; 256],
+    }
 }
 
-#[derive(ShaderType, Clone)]
-pub struct TriangleTable {
-    data: [[i32; 16]; 256],
+shader_struct! {
+    #[derive(Clone)]
+    pub struct TriangleTable {
+        data: [[i32; 16]; 256],
+    }
 }
 
 #[derive(Resource)]
 pub struct VoxelMeshComputePipeline {
     pub bind_group_1_layout: BindGroupLayout,
-    pub pipeline: CachedComputePipelineId,
+    /// Layout of the page table of a [`GpuVirtualVolume`], bound after `bind_group_1_layout`.
+    pub page_table_layout: BindGroupLayout,
+    /// Layout of the voxels of the neighbours of a [`GpuNeighborVoxels`], bound after
+    /// `bind_group_1_layout`.
+    pub neighbor_voxels_layout: BindGroupLayout,
+    /// A single voxel, bound for the faces of a [`GpuNeighborVoxels`] without a neighbour.
+    pub empty_neighbor_buffer: Buffer,
+    pub shader: Handle<Shader>,
+    pub platform: ShaderPlatform,
+}
+
+/// Cells meshed along each axis of a volumetric entity, including the capping layer of a
+/// [`MeshCapping`].
+pub fn meshed_cells(gpu_virtual_volume: Option<&GpuVirtualVolume>, capped: bool) -> UVec3 {
+    let size = gpu_virtual_volume.map_or(UVec3::splat(CHUNK_SZ as u32), |gpu_virtual_volume| {
+        gpu_virtual_volume.extent * CHUNK_SZ as u32
+    });
+    size + UVec3::splat(capped as u32)
+}
+
+/// The specialized meshing pipeline of a volumetric entity.
+#[derive(Clone, Copy)]
+pub struct VoxelMeshPipelineId(pub CachedComputePipelineId);
+
+/// The count and scatter pipelines meshing a volumetric entity with a prefix sum allocation, see
+/// [`ShaderPlatform::prefix_sum_allocation`]. Its further iso-surfaces are still meshed by its
+/// [`VoxelMeshPipelineId`], allocating from their atomics.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct VoxelMeshPrefixSumPipelineIds {
+    pub count: CachedComputePipelineId,
+    pub scatter: CachedComputePipelineId,
+}
+
+impl VoxelMeshPrefixSumPipelineIds {
+    /// Whether both pipelines are loaded.
+    pub fn is_loaded(&self, pipeline_cache: &PipelineCache) -> bool {
+        [self.count, self.scatter]
+            .into_iter()
+            .all(|pipeline| pipeline_cache.get_compute_pipeline(pipeline).is_some())
+    }
+}
+
+/// The passes of a prefix sum allocation: the count pass stores the primitives of each cell in
+/// its slot, which are scanned into where each cell starts before the scatter pass emits them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PrefixSumPass {
+    Count,
+    Scatter,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct VoxelMeshPipelineKey {
+    pub meshing_algorithm: MeshingAlgorithm,
+    pub boundary_mode: BoundaryMode,
+    pub edge_interpolation: EdgeInterpolation,
+    /// Whether voxels are looked up through the page table of a [`GpuVirtualVolume`].
+    pub virtual_volume: bool,
+    /// Whether the cells straddling the edges of the volume are meshed, see [`MeshCapping`].
+    pub capped: bool,
+    /// Whether the index buffer is split by material, see [`MaterialSplit`].
+    pub material_split: bool,
+    /// Whether cells in detail regions are refined, see [`AdaptiveResolution`].
+    pub adaptive_resolution: bool,
+    /// The pass of a prefix sum allocation, or `None` to allocate from the atomics.
+    pub prefix_sum_pass: Option<PrefixSumPass>,
+    /// Whether the voxels of the neighbours are bound for gradient normals, see [`SeamlessNormals`].
+    pub seamless_normals: bool,
+}
+
+impl VoxelMeshComputePipeline {
+    /// Queues the pipeline matching the [`MeshingAlgorithm`], [`BoundaryMode`],
+    /// [`EdgeInterpolation`] and storage of each volumetric entity.
+    #[allow(clippy::type_complexity, clippy::too_many_arguments)]
+    pub fn specialize(
+        pipeline_cache: Res<PipelineCache>,
+        voxel_mesh_pipeline: Res<VoxelMeshComputePipeline>,
+        mut pipelines: ResMut<SpecializedComputePipelines<VoxelMeshComputePipeline>>,
+        mut pipeline_ids: ResMut<VoxelMaterialComponents<VoxelMeshPipelineId>>,
+        mut prefix_sum_pipeline_ids: ResMut<VoxelMaterialComponents<VoxelMeshPrefixSumPipelineIds>>,
+        mut dirty_meshes: ResMut<DirtyMeshes>,
+        gpu_virtual_volumes: Res<VoxelMaterialComponents<GpuVirtualVolume>>,
+        volumetric_query: Query<
+            (
+                Entity,
+                Option<&MeshingAlgorithm>,
+                Option<&BoundaryMode>,
+                Option<&EdgeInterpolation>,
+                Has<MeshCapping>,
+                Has<MaterialSplit>,
+                Has<AdaptiveResolution>,
+                Has<SeamlessNormals>,
+            ),
+            With<Volumetric>,
+        >,
+    ) {
+        for (
+            entity,
+            meshing_algorithm,
+            boundary_mode,
+            edge_interpolation,
+            capped,
+            material_split,
+            adaptive_resolution,
+            seamless_normals,
+        ) in volumetric_query.iter()
+        {
+            let virtual_volume = gpu_virtual_volumes.get(&entity).is_some();
+            let key = VoxelMeshPipelineKey {
+                meshing_algorithm: meshing_algorithm.copied().unwrap_or_default(),
+                boundary_mode: boundary_mode.copied().unwrap_or_default(),
+                edge_interpolation: edge_interpolation.copied().unwrap_or_default(),
+                virtual_volume,
+                capped,
+                material_split,
+                adaptive_resolution,
+                prefix_sum_pass: None,
+                // Virtual volumes span more than a chunk and have no neighbours bound.
+                seamless_normals: seamless_normals && !virtual_volume,
+            };
+            let pipeline_id = pipelines.specialize(&pipeline_cache, &voxel_mesh_pipeline, key);
+
+            let previous = pipeline_ids
+                .0
+                .insert(entity, VoxelMeshPipelineId(pipeline_id));
+            if previous.is_none_or(|previous| previous.0 != pipeline_id) {
+                dirty_meshes.mark(entity);
+            }
+
+            let prefix_sum_ids = voxel_mesh_pipeline
+                .platform
+                .prefix_sum_passes(material_split)
+                .then(|| {
+                    let mut specialize = |pass| {
+                        pipelines.specialize(
+                            &pipeline_cache,
+                            &voxel_mesh_pipeline,
+                            VoxelMeshPipelineKey {
+                                prefix_sum_pass: Some(pass),
+                                ..key
+                            },
+                        )
+                    };
+                    VoxelMeshPrefixSumPipelineIds {
+                        count: specialize(PrefixSumPass::Count),
+                        scatter: specialize(PrefixSumPass::Scatter),
+                    }
+                });
+            let previous = match prefix_sum_ids {
+                Some(ids) => prefix_sum_pipeline_ids.0.insert(entity, ids),
+                None => prefix_sum_pipeline_ids.0.remove(&entity),
+            };
+            if previous != prefix_sum_ids {
+                dirty_meshes.mark(entity);
+            }
+        }
+    }
+}
+
+impl SpecializedComputePipeline for VoxelMeshComputePipeline {
+    type Key = VoxelMeshPipelineKey;
+
+    fn specialize(&self, key: Self::Key) -> ComputePipelineDescriptor {
+        let mut layout = vec![self.bind_group_1_layout.clone()];
+        // Caps are only closed if everything outside the volume is empty.
+        let boundary_mode = match key.capped {
+            true => BoundaryMode::Empty,
+            false => key.boundary_mode,
+        };
+
+        let mut shader_defs = vec![
+            key.meshing_algorithm.shader_def(),
+            boundary_mode.shader_def(),
+            key.edge_interpolation.shader_def(),
+        ];
+        shader_defs.extend(self.platform.shader_defs());
+
+        if self.platform.fixed_output_slots {
+            let (vertices, indices) = key.meshing_algorithm.max_cell_output();
+            shader_defs.push(ShaderDefVal::UInt(
+                "CELL_SLOT_VERTICES".into(),
+                vertices as u32,
+            ));
+            shader_defs.push(ShaderDefVal::UInt(
+                "CELL_SLOT_INDICES".into(),
+                indices as u32,
+            ));
+        }
+
+        // Only marching cubes reads the corners of its cells from the tile.
+        if self.platform.density_tile && key.meshing_algorithm == MeshingAlgorithm::MarchingCubes {
+            shader_defs.push("DENSITY_TILE".into());
+            shader_defs.push(ShaderDefVal::UInt(
+                "DENSITY_TILE_SIZE".into(),
+                self.platform.density_tile_size(),
+            ));
+        }
+
+        if key.capped {
+            shader_defs.push("MESH_CAPPING".into());
+        }
+
+        match key.prefix_sum_pass {
+            Some(PrefixSumPass::Count) => {
+                shader_defs.extend(["CELL_SLOTS".into(), "PREFIX_SUM_COUNT".into()]);
+            }
+            Some(PrefixSumPass::Scatter) => {
+                shader_defs.extend(["CELL_SLOTS".into(), "PREFIX_SUM_SCATTER".into()]);
+            }
+            None => {}
+        }
+
+        // Fixed slots and prefix sums are compacted into a single index buffer.
+        if key.material_split && !self.platform.fixed_output_slots && key.prefix_sum_pass.is_none()
+        {
+            shader_defs.push("MATERIAL_SPLIT".into());
+        }
+
+        if key.adaptive_resolution {
+            shader_defs.push("ADAPTIVE_RESOLUTION".into());
+        }
+
+        if key.virtual_volume {
+            layout.push(self.page_table_layout.clone());
+            shader_defs.push("VIRTUAL_VOLUME".into());
+        }
+
+        if key.seamless_normals {
+            layout.push(self.neighbor_voxels_layout.clone());
+            // Blocks keep the flat normals of their faces.
+            if key.meshing_algorithm != MeshingAlgorithm::Cubic {
+                shader_defs.push("SEAMLESS_NORMALS".into());
+            }
+        }
+
+        #[cfg(feature = "full-normals")]
+        shader_defs.push("FULL_NORMALS".into());
+
+        ComputePipelineDescriptor {
+            label: Some("VoxelMeshComputePipeline shader".into()),
+            layout,
+            push_constant_ranges: Vec::new(),
+            shader: self.shader.clone(),
+            shader_defs,
+            entry_point: "main".into(),
+        }
+    }
+}
+
+impl VoxelMeshComputePipeline {
+    /// The tables, voxels, output buffers and params bound for each volumetric entity.
+    pub fn bind_group_1_layout_entries() -> BindGroupLayoutEntries<14> {
+        BindGroupLayoutEntries::with_indices(
+            ShaderStages::COMPUTE,
+            (
+                (
+                    0,
+                    storage_buffer::<EdgeTable>(false).visibility(ShaderStages::COMPUTE),
+                ),
+                (
+                    1,
+                    storage_buffer::<TriangleTable>(false).visibility(ShaderStages::COMPUTE),
+                ),
+                (
+                    2,
+                    storage_buffer::<VoxelBuffer>(false).visibility(ShaderStages::COMPUTE),
+                ),
+                (
+                    3,
+                    storage_buffer::<Atomics>(false).visibility(ShaderStages::COMPUTE),
+                ),
+                (
+                    4,
+                    storage_buffer::<VertexBuffer>(false).visibility(ShaderStages::COMPUTE),
+                ),
+                (
+                    5,
+                    storage_buffer::<NormalBuffer>(false).visibility(ShaderStages::COMPUTE),
+                ),
+                (
+                    6,
+                    storage_buffer::<IndexBuffer>(false).visibility(ShaderStages::COMPUTE),
+                ),
+                (
+                    7,
+                    storage_buffer::<UvBuffer>(false).visibility(ShaderStages::COMPUTE),
+                ),
+                (
+                    8,
+                    storage_buffer::<TangentBuffer>(false).visibility(ShaderStages::COMPUTE),
+                ),
+                (
+                    9,
+                    uniform_buffer::<DualContouringParams>(false).visibility(ShaderStages::COMPUTE),
+                ),
+                (
+                    10,
+                    uniform_buffer::<IsoSurfaceParams>(false).visibility(ShaderStages::COMPUTE),
+                ),
+                (
+                    11,
+                    uniform_buffer::<ClipPlanesParams>(false).visibility(ShaderStages::COMPUTE),
+                ),
+                (
+                    12,
+                    uniform_buffer::<AdaptiveResolutionParams>(false)
+                        .visibility(ShaderStages::COMPUTE),
+                ),
+                (
+                    13,
+                    storage_buffer::<EmissiveBuffer>(false).visibility(ShaderStages::COMPUTE),
+                ),
+            ),
+        )
+    }
+
+    /// The page table of a virtual volume.
+    pub fn page_table_layout_entries() -> [BindGroupLayoutEntry; 1] {
+        BindGroupLayoutEntries::single(ShaderStages::COMPUTE, texture_3d(TextureSampleType::Uint))
+    }
+
+    /// The voxels of the neighbours of a chunk, in the order of [`NEIGHBOR_OFFSETS`](crate::data::gpu_neighbor_voxels::NEIGHBOR_OFFSETS).
+    pub fn neighbor_voxels_layout_entries() -> BindGroupLayoutEntries<6> {
+        let voxels = || storage_buffer_read_only::<VoxelBuffer>(false);
+        BindGroupLayoutEntries::sequential(
+            ShaderStages::COMPUTE,
+            (voxels(), voxels(), voxels(), voxels(), voxels(), voxels()),
+        )
+    }
 }
 
 impl FromWorld for VoxelMeshComputePipeline {
@@ -85,61 +485,171 @@ impl FromWorld for VoxelMeshComputePipeline {
 
         let bind_group_1_layout = render_device.create_bind_group_layout(
             Some("VoxelMeshComputePipeline::bind_group_1_layout"),
-            &BindGroupLayoutEntries::with_indices(
-                ShaderStages::COMPUTE,
-                (
-                    (
-                        0,
-                        storage_buffer::<EdgeTable>(false).visibility(ShaderStages::COMPUTE),
-                    ),
-                    (
-                        1,
-                        storage_buffer::<TriangleTable>(false).visibility(ShaderStages::COMPUTE),
-                    ),
-                    (
-                        2,
-                        storage_buffer::<VoxelBuffer>(false).visibility(ShaderStages::COMPUTE),
-                    ),
-                    (
-                        3,
-                        storage_buffer::<Atomics>(false).visibility(ShaderStages::COMPUTE),
-                    ),
-                    (
-                        4,
-                        storage_buffer::<VertexBuffer>(false).visibility(ShaderStages::COMPUTE),
-                    ),
-                    (
-                        5,
-                        storage_buffer::<NormalBuffer>(false).visibility(ShaderStages::COMPUTE),
-                    ),
-                    (
-                        6,
-                        storage_buffer::<IndexBuffer>(false).visibility(ShaderStages::COMPUTE),
-                    ),
-                    (
-                        7,
-                        storage_buffer::<UvBuffer>(false).visibility(ShaderStages::COMPUTE),
-                    ),
-                ),
-            ),
+            &Self::bind_group_1_layout_entries(),
         );
 
-        let shader = world.load_asset(SHADER_ASSET_PATH);
+        let page_table_layout = render_device.create_bind_group_layout(
+            Some("VoxelMeshComputePipeline::page_table_layout"),
+            &Self::page_table_layout_entries(),
+        );
 
-        let pipeline_cache = world.resource::<PipelineCache>();
+        let neighbor_voxels_layout = render_device.create_bind_group_layout(
+            Some("VoxelMeshComputePipeline::neighbor_voxels_layout"),
+            &Self::neighbor_voxels_layout_entries(),
+        );
 
-        let pipeline = pipeline_cache.queue_compute_pipeline(ComputePipelineDescriptor {
-            label: Some("VoxelMeshComputePipeline shader".into()),
-            layout: vec![bind_group_1_layout.clone()],
-            push_constant_ranges: Vec::new(),
-            shader: shader.clone(),
-            shader_defs: Vec::new(),
-            entry_point: "main".into(),
+        let empty_neighbor_buffer = render_device.create_buffer(&BufferDescriptor {
+            label: Some("VoxelMeshComputePipeline::empty_neighbor_buffer"),
+            size: std::mem::size_of::<Voxel>() as u64,
+            usage: BufferUsages::STORAGE,
+            mapped_at_creation: false,
         });
 
+        let shader = world.load_asset(SHADER_ASSET_PATH);
+
         VoxelMeshComputePipeline {
             bind_group_1_layout,
-            pipeline,
+            page_table_layout,
+            neighbor_voxels_layout,
+            empty_neighbor_buffer,
+            shader,
+            platform: *world.resource::<ShaderPlatform>(),
+        }
+    }
+}
+
+/// The volumetric entities whose mesh is out of date, e.g. because their voxels or meshing
+/// settings changed, and those selected to be remeshed this frame. The [`VoxelMeshComputeNode`]
+/// records nothing while no entity is remeshed or read back.
+#[derive(Resource, Default)]
+pub struct DirtyMeshes {
+    dirty: HashSet<Entity>,
+    meshed: HashSet<Entity>,
+    /// The voxels of dirty entities only remeshed because these voxels changed, see
+    /// [`DirtyMeshes::mark_region`].
+    regions: HashMap<Entity, Range<usize>>,
+    meshed_regions: HashMap<Entity, Range<usize>>,
+    /// Entities edited together, none of which is remeshed before the others.
+    groups: Vec<Vec<Entity>>,
+}
+
+impl DirtyMeshes {
+    /// Remeshes `entity` as soon as it can be meshed.
+    pub fn mark(&mut self, entity: Entity) {
+        self.dirty.insert(entity);
+        self.regions.remove(&entity);
+    }
+
+    /// Remeshes `entity` as soon as it can be meshed because the `voxels` range of its voxels
+    /// buffer changed, so that only the output of the cells around them has to be read back, see
+    /// [`GpuRawMeshData::changed_cells`]. Entities also marked for any other reason are read back
+    /// in full.
+    pub fn mark_region(&mut self, entity: Entity, voxels: Range<usize>) {
+        if self.dirty.insert(entity) {
+            self.regions.insert(entity, voxels);
+        } else if let Some(region) = self.regions.get_mut(&entity) {
+            *region = region.start.min(voxels.start)..region.end.max(voxels.end);
+        }
+    }
+
+    /// Remeshes the dirty `entities` in the same frame, once all of them can be meshed. Suspended
+    /// entities don't hold back the others.
+    pub fn group(&mut self, entities: impl IntoIterator<Item = Entity>) {
+        let group = entities.into_iter().collect::<Vec<_>>();
+        if group.len() > 1 {
+            self.groups.push(group);
+        }
+    }
+
+    pub fn is_dirty(&self, entity: &Entity) -> bool {
+        self.dirty.contains(entity)
+    }
+
+    /// Whether `entity` is remeshed this frame.
+    pub fn is_meshed(&self, entity: &Entity) -> bool {
+        self.meshed.contains(entity)
+    }
+
+    /// Number of entities waiting to be remeshed.
+    pub fn dirty_len(&self) -> usize {
+        self.dirty.len()
+    }
+
+    /// The entities remeshed this frame.
+    pub fn meshed(&self) -> impl Iterator<Item = &Entity> {
+        self.meshed.iter()
+    }
+
+    /// The voxels whose change alone `entity` is remeshed for this frame, if it is only remeshed
+    /// for changed voxels, see [`DirtyMeshes::mark_region`].
+    pub fn meshed_region(&self, entity: &Entity) -> Option<&Range<usize>> {
+        self.meshed_regions.get(entity)
+    }
+
+    /// Selects the dirty entities remeshed this frame: those whose [`GpuChunkState`] is ready and
+    /// whose previous mesh is not being read back in pages, unless another entity of their group
+    /// is still waiting.
+    ///
+    /// [`GpuChunkState`]: crate::data::gpu_chunk_state::GpuChunkState
+    #[allow(clippy::type_complexity)]
+    pub fn select(
+        mut dirty_meshes: ResMut<Self>,
+        paused: Res<VoxelComputePaused>,
+        gpu_chunk_states: Res<GpuChunkStates>,
+        vertex_readbacks: Res<VoxelMaterialComponents<VertexReadback>>,
+        volumetric_query: Query<(Entity, Has<VoxelComputeSuspended>), With<Volumetric>>,
+    ) {
+        let DirtyMeshes {
+            dirty,
+            meshed,
+            regions,
+            meshed_regions,
+            groups,
+        } = dirty_meshes.as_mut();
+        meshed.clear();
+        meshed_regions.clear();
+        dirty.retain(|entity| volumetric_query.contains(*entity));
+        regions.retain(|entity, _| dirty.contains(entity));
+
+        if paused.0 {
+            return;
+        }
+
+        let mut ready = HashSet::new();
+        let mut waiting = HashSet::new();
+        for (entity, suspended) in volumetric_query.iter() {
+            if !dirty.contains(&entity) || suspended {
+                continue;
+            }
+            let is_ready = gpu_chunk_states.is_ready(&entity)
+                && !vertex_readbacks
+                    .get(&entity)
+                    .is_some_and(VertexReadback::in_progress);
+
+            match is_ready {
+                true => ready.insert(entity),
+                false => waiting.insert(entity),
+            };
+        }
+
+        // A group is remeshed once none of its dirty entities is waiting, then forgotten.
+        groups.retain_mut(|group| {
+            group.retain(|entity| ready.contains(entity) || waiting.contains(entity));
+            let is_waiting = group.iter().any(|entity| waiting.contains(entity));
+            if is_waiting {
+                for entity in group.iter() {
+                    ready.remove(entity);
+                }
+            }
+            is_waiting
+        });
+
+        for entity in ready {
+            dirty.remove(&entity);
+            meshed.insert(entity);
+            if let Some(region) = regions.remove(&entity) {
+                meshed_regions.insert(entity, region);
+            }
         }
     }
 }
@@ -147,8 +657,10 @@ impl FromWorld for VoxelMeshComputePipeline {
 #[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
 pub struct VoxelMeshComputeNodeLabel;
 
+#[allow(clippy::type_complexity)]
 pub struct VoxelMeshComputeNode {
-    voxel_material_query: QueryState<Entity, With<Volumetric>>,
+    voxel_material_query:
+        QueryState<(Entity, Has<MeshCapping>), (With<Volumetric>, Without<VoxelComputeSuspended>)>,
 }
 
 impl FromWorld for VoxelMeshComputeNode {
@@ -162,6 +674,12 @@ impl FromWorld for VoxelMeshComputeNode {
 impl render_graph::Node for VoxelMeshComputeNode {
     fn update(&mut self, world: &mut World) {
         self.voxel_material_query.update_archetypes(world);
+
+        world.resource_scope(|world, mut post_mesh_passes: Mut<PostMeshComputePasses>| {
+            for post_mesh_pass in post_mesh_passes.0.iter_mut() {
+                post_mesh_pass.update(world);
+            }
+        });
     }
 
     fn run(
@@ -170,52 +688,340 @@ impl render_graph::Node for VoxelMeshComputeNode {
         render_context: &mut RenderContext,
         world: &World,
     ) -> Result<(), render_graph::NodeRunError> {
+        if world.resource::<VoxelComputePaused>().0 {
+            return Ok(());
+        }
+
+        let dirty_meshes = world.resource::<DirtyMeshes>();
+        let pending_readbacks = world.resource::<PendingReadbacks>();
+        if dirty_meshes.meshed.is_empty() && pending_readbacks.selected().next().is_none() {
+            return Ok(()); // nothing to mesh or copy for readback
+        }
+
         let pipeline_cache = world.resource::<PipelineCache>();
         let voxel_mesh_pipeline = world.resource::<VoxelMeshComputePipeline>();
+        let pipeline_ids = world.resource::<VoxelMaterialComponents<VoxelMeshPipelineId>>();
+        let prefix_sum_pipeline_ids =
+            world.resource::<VoxelMaterialComponents<VoxelMeshPrefixSumPipelineIds>>();
+        let fixed_output_slots = world.resource::<GpuFixedOutputSlots>();
+        let gpu_slot_compactions = world.resource::<VoxelMaterialComponents<GpuSlotCompaction>>();
+        let slot_compaction_pipeline = world.resource::<SlotCompactionComputePipeline>();
         let gpu_voxel_materials = world.resource::<VoxelMaterialComponents<GpuVoxelMaterial>>();
         let voxel_bind_groups =
             world.resource::<VoxelMaterialComponents<GpuVoxelMaterialBindGroups>>();
-
-        let pipeline = match pipeline_cache.get_compute_pipeline(voxel_mesh_pipeline.pipeline) {
-            None => return Ok(()), // some pipelines are not loaded yet
-            Some(pipeline) => pipeline,
-        };
+        let gpu_virtual_volumes = world.resource::<VoxelMaterialComponents<GpuVirtualVolume>>();
+        let gpu_neighbor_voxels = world.resource::<VoxelMaterialComponents<GpuNeighborVoxels>>();
+        let post_mesh_passes = world.resource::<PostMeshComputePasses>();
+        let gpu_raw_meshes = world.resource::<VoxelMaterialComponents<GpuRawMeshData>>();
+        let gpu_iso_surfaces = world.resource::<VoxelMaterialComponents<GpuIsoSurfaces>>();
+        let vertex_readbacks = world.resource::<VoxelMaterialComponents<VertexReadback>>();
+        let readback_policy = world.resource::<ReadbackPolicy>();
+        let gpu_chunk_states = world.resource::<GpuChunkStates>();
+        let material_index_ranges =
+            world.resource::<VoxelMaterialComponents<MaterialIndexRanges>>();
+        let render_queue = world.resource::<RenderQueue>();
+        let idle_readback = VertexReadback::default();
 
         let command_encoder = render_context.command_encoder();
 
-        for voxel_material_entity in self.voxel_material_query.iter_manual(world) {
+        for (voxel_material_entity, capped) in self.voxel_material_query.iter_manual(world) {
+            // Only the readbacks selected within the `ReadbackPolicy` are copied this frame.
+            let meshed = dirty_meshes.is_meshed(&voxel_material_entity);
+            let read_back = pending_readbacks.is_selected(&voxel_material_entity);
+            if !meshed && !read_back {
+                continue;
+            }
+            if !gpu_chunk_states.is_ready(&voxel_material_entity) {
+                continue; // GpuChunkStates warns about the entities in error
+            }
+
             let gpu_voxel_material = gpu_voxel_materials.get(&voxel_material_entity);
             let voxel_bind_groups = voxel_bind_groups.get(&voxel_material_entity);
-            match (gpu_voxel_material, voxel_bind_groups) {
-                (Some(gpu_voxel_material), Some(voxel_bind_group)) => {
-                    let mut pass =
-                        command_encoder.begin_compute_pass(&ComputePassDescriptor::default());
+            let pipeline = pipeline_ids
+                .get(&voxel_material_entity)
+                .and_then(|pipeline_id| pipeline_cache.get_compute_pipeline(pipeline_id.0));
+
+            // Ready entities have their buffers, bind groups and pipeline.
+            if let (Some(gpu_voxel_material), Some(voxel_bind_group), Some(pipeline)) =
+                (gpu_voxel_material, voxel_bind_groups, pipeline)
+            {
+                let vertex_readback = vertex_readbacks
+                    .get(&voxel_material_entity)
+                    .unwrap_or(&idle_readback);
 
-                    for (bind_group_id, bind_group) in voxel_bind_group.0.iter().enumerate() {
-                        pass.set_bind_group(bind_group_id as u32, &bind_group, &[]);
+                let gpu_virtual_volume = gpu_virtual_volumes.get(&voxel_material_entity);
+                // The page table of a virtual volume or the neighbours of a chunk, bound after
+                // the voxels for the main pass and the iso-surfaces alike.
+                let volume_bind_group =
+                    gpu_neighbor_voxels
+                        .get(&voxel_material_entity)
+                        .and_then(|gpu_neighbors| gpu_neighbors.bind_group.as_ref())
+                        .or(gpu_virtual_volume
+                            .map(|gpu_virtual_volume| &gpu_virtual_volume.bind_group));
+                // Capping meshes one extra layer of cells below the volume.
+                let workgroups = voxel_mesh_pipeline
+                    .platform
+                    .workgroups(meshed_cells(gpu_virtual_volume, capped));
+
+                // Entities are only remeshed once all the pages of their previous mesh are
+                // read, see `DirtyMeshes::select`.
+                if meshed {
+                    // Restart the vertex and index allocation from the start of the buffers.
+                    command_encoder.clear_buffer(
+                        gpu_voxel_material
+                            .atomics_buffer
+                            .buffer()
+                            .expect("Atomics Buffer should have already been uploaded to the gpu"),
+                        0,
+                        None,
+                    );
+                    let gpu_slot_compaction = gpu_slot_compactions.get(&voxel_material_entity);
+                    if let (Some(_), Some(slots)) =
+                        (gpu_slot_compaction, &fixed_output_slots.buffers)
+                    {
+                        // Cells outside the volume leave their slot empty.
+                        command_encoder.clear_buffer(&slots.cell_slots_buffer, 0, None);
                     }
 
-                    pass.set_pipeline(pipeline);
-                    pass.dispatch_workgroups(8, 8, 8);
+                    let dispatch = |command_encoder: &mut CommandEncoder,
+                                    pipeline: &ComputePipeline| {
+                        let mut pass =
+                            command_encoder.begin_compute_pass(&ComputePassDescriptor::default());
 
-                    drop(pass);
+                        for (bind_group_id, bind_group) in voxel_bind_group.0.iter().enumerate() {
+                            pass.set_bind_group(bind_group_id as u32, bind_group, &[]);
+                        }
+
+                        pass.set_pipeline(pipeline);
+
+                        if let Some(volume_bind_group) = volume_bind_group {
+                            pass.set_bind_group(1, volume_bind_group, &[]);
+                        }
+                        pass.dispatch_workgroups(workgroups.x, workgroups.y, workgroups.z);
+                    };
+
+                    // Entities meshed with a prefix sum are only ready once both of its
+                    // pipelines and its compaction are, see `GpuChunkStates::prepare`.
+                    let prefix_sum_pipelines = prefix_sum_pipeline_ids
+                        .get(&voxel_material_entity)
+                        .zip(gpu_slot_compaction)
+                        .and_then(|(ids, gpu_slot_compaction)| {
+                            Some((
+                                pipeline_cache.get_compute_pipeline(ids.count)?,
+                                pipeline_cache.get_compute_pipeline(ids.scatter)?,
+                                gpu_slot_compaction,
+                            ))
+                        });
 
+                    match prefix_sum_pipelines {
+                        Some((count_pipeline, scatter_pipeline, gpu_slot_compaction)) => {
+                            dispatch(command_encoder, count_pipeline);
+                            slot_compaction_pipeline.prefix_sum(
+                                pipeline_cache,
+                                gpu_slot_compaction,
+                                command_encoder,
+                            );
+                            dispatch(command_encoder, scatter_pipeline);
+                        }
+                        None => {
+                            dispatch(command_encoder, pipeline);
+                            if let Some(gpu_slot_compaction) = gpu_slot_compaction {
+                                slot_compaction_pipeline.compact(
+                                    pipeline_cache,
+                                    gpu_slot_compaction,
+                                    command_encoder,
+                                );
+                            }
+                        }
+                    }
+
+                    for post_mesh_pass in &post_mesh_passes.0 {
+                        post_mesh_pass.run(
+                            world,
+                            voxel_material_entity,
+                            gpu_voxel_material,
+                            command_encoder,
+                        );
+                    }
+                }
+
+                let page = match read_back {
+                    true => vertex_readback.page(gpu_voxel_material, readback_policy),
+                    false => 0..0,
+                };
+                if !page.is_empty() {
                     command_encoder.copy_buffer_to_buffer(
                         gpu_voxel_material
                             .vertices_buffer
                             .buffer()
                             .expect("Vertices Buffer should have already been uploaded to the gpu"),
+                        page.start,
+                        &gpu_voxel_material.vertices_staging_buffer,
+                        MeshReadbackHeader::SIZE,
+                        page.end - page.start,
+                    );
+                }
+                if read_back && page.start == 0 {
+                    // The version and flags are written before the commands run, which then
+                    // copy the vertex and index counts over the rest of the header.
+                    let flags = match material_index_ranges.get(&voxel_material_entity) {
+                        Some(_) => MeshReadbackHeader::MATERIAL_SPLIT,
+                        None => 0,
+                    };
+                    render_queue.write_buffer(
+                        &gpu_voxel_material.vertices_staging_buffer,
+                        0,
+                        &MeshReadbackHeader::new(0, 0, flags).to_bytes(),
+                    );
+                    command_encoder.copy_buffer_to_buffer(
+                        gpu_voxel_material
+                            .atomics_buffer
+                            .buffer()
+                            .expect("Atomics Buffer should have already been uploaded to the gpu"),
                         0,
                         &gpu_voxel_material.vertices_staging_buffer,
+                        std::mem::offset_of!(MeshReadbackHeader, vertex_count) as u64,
+                        2 * std::mem::size_of::<u32>() as u64,
+                    );
+                    command_encoder.copy_buffer_to_buffer(
+                        gpu_voxel_material
+                            .atomics_buffer
+                            .buffer()
+                            .expect("Atomics Buffer should have already been uploaded to the gpu"),
                         0,
-                        (CHUNK_SZ as usize * std::mem::size_of::<Vec3>()) as u64,
+                        &gpu_voxel_material.atomics_staging_buffer,
+                        0,
+                        gpu_voxel_material.atomics_staging_buffer.size(),
                     );
                 }
-                _ => {
-                    info!(
-                        "No gpu_voxel material or gpu_bind_group for {}",
-                        voxel_material_entity
+
+                if !meshed {
+                    continue;
+                }
+
+                // Only the counts and where the changed cells start and end are copied for a
+                // readback of changed cells, which copies their output once they are known.
+                let changed_cells = gpu_raw_meshes
+                    .get(&voxel_material_entity)
+                    .zip(fixed_output_slots.buffers.as_ref())
+                    .and_then(|(gpu_raw_mesh, slots)| {
+                        Some((gpu_raw_mesh, slots, gpu_raw_mesh.changed_cells.clone()?))
+                    });
+                if let Some((gpu_raw_mesh, slots, cells)) = changed_cells {
+                    let slot_size = CellSlot::min_size().get();
+                    for (cell, offset) in [(cells.start, 0), (cells.end - 1, slot_size)] {
+                        command_encoder.copy_buffer_to_buffer(
+                            &slots.cell_slots_buffer,
+                            cell as u64 * slot_size,
+                            &gpu_raw_mesh.cell_slots_staging_buffer,
+                            offset,
+                            slot_size,
+                        );
+                    }
+                    command_encoder.copy_buffer_to_buffer(
+                        gpu_voxel_material
+                            .atomics_buffer
+                            .buffer()
+                            .expect("Atomics Buffer should have already been uploaded to the gpu"),
+                        0,
+                        &gpu_raw_mesh.atomics_staging_buffer,
+                        0,
+                        gpu_raw_mesh.atomics_staging_buffer.size(),
                     );
+                } else if let Some(gpu_raw_mesh) = gpu_raw_meshes.get(&voxel_material_entity) {
+                    for (buffer, staging_buffer) in [
+                        (
+                            gpu_voxel_material.atomics_buffer.buffer(),
+                            &gpu_raw_mesh.atomics_staging_buffer,
+                        ),
+                        (
+                            gpu_voxel_material.vertices_buffer.buffer(),
+                            &gpu_raw_mesh.vertices_staging_buffer,
+                        ),
+                        (
+                            gpu_voxel_material.uvs_buffer.buffer(),
+                            &gpu_raw_mesh.uvs_staging_buffer,
+                        ),
+                        (
+                            gpu_voxel_material.tangents_buffer.buffer(),
+                            &gpu_raw_mesh.tangents_staging_buffer,
+                        ),
+                        (
+                            gpu_voxel_material.emissives_buffer.buffer(),
+                            &gpu_raw_mesh.emissives_staging_buffer,
+                        ),
+                        (
+                            gpu_voxel_material.indices_buffer.buffer(),
+                            &gpu_raw_mesh.indices_staging_buffer,
+                        ),
+                    ] {
+                        if let Some(buffer) = buffer {
+                            command_encoder.copy_buffer_to_buffer(
+                                buffer,
+                                0,
+                                staging_buffer,
+                                0,
+                                staging_buffer.size(),
+                            );
+                        }
+                    }
+                }
+
+                // Mesh the further iso-surfaces of the entity from the same voxels, each into
+                // its own buffers, and copy them for readback.
+                let iso_surfaces = gpu_iso_surfaces
+                    .get(&voxel_material_entity)
+                    .map_or(&[][..], |gpu_iso_surfaces| &gpu_iso_surfaces.surfaces);
+                for gpu_iso_surface in iso_surfaces {
+                    let (Some(bind_group), Some(atomics_buffer)) = (
+                        gpu_iso_surface.bind_group.as_ref(),
+                        gpu_iso_surface.atomics_buffer.buffer(),
+                    ) else {
+                        continue;
+                    };
+                    // Surfaces meshed into the fixed slots are compacted into their own buffers.
+                    let slot_compaction = gpu_iso_surface.slot_compaction.as_ref();
+                    if voxel_mesh_pipeline.platform.fixed_output_slots && slot_compaction.is_none()
+                    {
+                        continue;
+                    }
+
+                    command_encoder.clear_buffer(atomics_buffer, 0, None);
+                    if let (Some(_), Some(slots)) = (slot_compaction, &fixed_output_slots.buffers) {
+                        command_encoder.clear_buffer(&slots.cell_slots_buffer, 0, None);
+                    }
+
+                    let mut pass = command_encoder.begin_compute_pass(&ComputePassDescriptor {
+                        label: Some("iso_surface"),
+                        ..default()
+                    });
+                    pass.set_bind_group(0, bind_group, &[]);
+                    if let Some(volume_bind_group) = volume_bind_group {
+                        pass.set_bind_group(1, volume_bind_group, &[]);
+                    }
+                    pass.set_pipeline(pipeline);
+                    pass.dispatch_workgroups(workgroups.x, workgroups.y, workgroups.z);
+                    drop(pass);
+
+                    if let Some(slot_compaction) = slot_compaction {
+                        slot_compaction_pipeline.compact(
+                            pipeline_cache,
+                            slot_compaction,
+                            command_encoder,
+                        );
+                    }
+
+                    for (buffer, staging_buffer) in gpu_iso_surface.readback_buffers() {
+                        if let Some(buffer) = buffer {
+                            command_encoder.copy_buffer_to_buffer(
+                                buffer,
+                                0,
+                                staging_buffer,
+                                0,
+                                staging_buffer.size(),
+                            );
+                        }
+                    }
                 }
             }
         }
@@ -0,0 +1,153 @@
+use bevy::{
+    prelude::*,
+    render::{
+        render_graph::{self, RenderLabel},
+        render_resource::{
+            binding_types::{storage_buffer, storage_buffer_read_only, uniform_buffer},
+            *,
+        },
+        renderer::{RenderContext, RenderDevice},
+    },
+};
+
+use crate::{
+    data::{
+        atomics::Atomics,
+        gpu_voxel_picking::GpuVoxelPick,
+        voxel_material::VoxelMaterialComponents,
+        voxel_picking::{VoxelPickParams, VoxelPickResult},
+    },
+    render::voxel_mesh_compute_pipeline::{IndexBuffer, VertexBuffer},
+};
+
+const SHADER_ASSET_PATH: &str = "shaders/voxel_picking.wgsl";
+
+const WORKGROUP_SIZE: u32 = 64;
+
+/// Intersects a ray with the triangles of a volumetric entity: finds the nearest distance hit,
+/// then the first triangle hit at that distance.
+#[derive(Resource)]
+pub struct VoxelPickingComputePipeline {
+    pub bind_group_layout: BindGroupLayout,
+    pub nearest_pipeline: CachedComputePipelineId,
+    pub identify_pipeline: CachedComputePipelineId,
+}
+
+impl FromWorld for VoxelPickingComputePipeline {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+
+        let bind_group_layout = render_device.create_bind_group_layout(
+            Some("VoxelPickingComputePipeline::bind_group_layout"),
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::COMPUTE,
+                (
+                    uniform_buffer::<VoxelPickParams>(false),
+                    storage_buffer_read_only::<VertexBuffer>(false),
+                    storage_buffer_read_only::<IndexBuffer>(false),
+                    storage_buffer_read_only::<Atomics>(false),
+                    storage_buffer::<VoxelPickResult>(false),
+                ),
+            ),
+        );
+
+        let shader = world.load_asset(SHADER_ASSET_PATH);
+
+        let pipeline_cache = world.resource::<PipelineCache>();
+
+        let queue = |entry_point: &'static str| {
+            pipeline_cache.queue_compute_pipeline(ComputePipelineDescriptor {
+                label: Some(format!("VoxelPickingComputePipeline {entry_point}").into()),
+                layout: vec![bind_group_layout.clone()],
+                push_constant_ranges: Vec::new(),
+                shader: shader.clone(),
+                shader_defs: Vec::new(),
+                entry_point: entry_point.into(),
+            })
+        };
+
+        let nearest_pipeline = queue("nearest");
+        let identify_pipeline = queue("identify");
+
+        VoxelPickingComputePipeline {
+            bind_group_layout,
+            nearest_pipeline,
+            identify_pipeline,
+        }
+    }
+}
+
+impl VoxelPickingComputePipeline {
+    /// Whether both picking pipelines are loaded.
+    pub fn is_loaded(&self, pipeline_cache: &PipelineCache) -> bool {
+        [self.nearest_pipeline, self.identify_pipeline]
+            .into_iter()
+            .all(|pipeline| pipeline_cache.get_compute_pipeline(pipeline).is_some())
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+pub struct VoxelPickingComputeNodeLabel;
+
+/// Intersects every pending [`GpuVoxelPick`] with the triangles just meshed and copies its result
+/// to its staging buffer.
+pub struct VoxelPickingComputeNode;
+
+impl render_graph::Node for VoxelPickingComputeNode {
+    fn run(
+        &self,
+        _graph: &mut render_graph::RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), render_graph::NodeRunError> {
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let picking_pipeline = world.resource::<VoxelPickingComputePipeline>();
+        let gpu_voxel_picks = world.resource::<VoxelMaterialComponents<GpuVoxelPick>>();
+
+        let (Some(nearest_pipeline), Some(identify_pipeline)) = (
+            pipeline_cache.get_compute_pipeline(picking_pipeline.nearest_pipeline),
+            pipeline_cache.get_compute_pipeline(picking_pipeline.identify_pipeline),
+        ) else {
+            return Ok(()); // the pipelines are not loaded yet
+        };
+
+        let command_encoder = render_context.command_encoder();
+
+        for gpu_pick in gpu_voxel_picks.0.values() {
+            if !gpu_pick.pending {
+                continue;
+            }
+            let Some(bind_group) = gpu_pick.bind_group.as_ref() else {
+                continue;
+            };
+
+            let workgroups = gpu_pick
+                .params_buffer
+                .get()
+                .triangles
+                .div_ceil(WORKGROUP_SIZE);
+
+            {
+                let mut pass = command_encoder.begin_compute_pass(&ComputePassDescriptor {
+                    label: Some("voxel_picking"),
+                    ..default()
+                });
+                pass.set_bind_group(0, bind_group, &[]);
+                pass.set_pipeline(nearest_pipeline);
+                pass.dispatch_workgroups(workgroups, 1, 1);
+                pass.set_pipeline(identify_pipeline);
+                pass.dispatch_workgroups(workgroups, 1, 1);
+            }
+
+            command_encoder.copy_buffer_to_buffer(
+                &gpu_pick.result_buffer,
+                0,
+                &gpu_pick.result_staging_buffer,
+                0,
+                gpu_pick.result_buffer.size(),
+            );
+        }
+
+        Ok(())
+    }
+}
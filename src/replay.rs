@@ -0,0 +1,433 @@
+use std::{fs, io, path::Path, sync::Arc, time::Duration};
+
+use bevy::{prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bundles::volumetric_bundle::Volumetric,
+    data::{
+        chunk_coord::ChunkCoord,
+        voxel::Voxel,
+        voxel_edit::{ChunkEdit, ChunkEdited, VoxelEdit},
+        voxel_structure::{
+            PlaceStructure, StructureBlend, StructurePlaced, StructurePlacement, VoxelStructure,
+        },
+        voxel_transform::VoxelAxis,
+    },
+    journal::JournalRecord,
+    streaming::ChunkProvider,
+    world_gen::{WorldGenJob, WorldGenStarted},
+};
+
+const REPLAY_VERSION: u32 = 2;
+
+#[derive(Serialize, Deserialize)]
+enum ReplayEvent {
+    Edit(JournalRecord),
+    Stamp {
+        /// Index of the structure in [`ReplayFile::structures`].
+        structure: usize,
+        position: [i32; 3],
+        axis: VoxelAxis,
+        turns: u32,
+        blend: StructureBlend,
+    },
+    Generate {
+        origin: [i32; 3],
+        size: u32,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+struct ReplayRecord {
+    /// Seconds since the recording started.
+    time: f64,
+    event: ReplayEvent,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ReplayStructure {
+    size: [u32; 3],
+    anchor: [u32; 3],
+    /// The flags, density and RGB9E5 emissive color of each voxel.
+    voxels: Vec<(u32, f32, u32)>,
+}
+
+impl From<&VoxelStructure> for ReplayStructure {
+    fn from(structure: &VoxelStructure) -> Self {
+        Self {
+            size: structure.size.to_array(),
+            anchor: structure.anchor.to_array(),
+            voxels: structure
+                .voxels
+                .iter()
+                .map(|voxel| (voxel.flags(), voxel.density(), voxel.emissive_bits()))
+                .collect(),
+        }
+    }
+}
+
+impl From<ReplayStructure> for VoxelStructure {
+    fn from(structure: ReplayStructure) -> Self {
+        Self {
+            size: UVec3::from_array(structure.size),
+            anchor: UVec3::from_array(structure.anchor),
+            voxels: structure
+                .voxels
+                .into_iter()
+                .map(|(flags, density, emissive)| {
+                    Voxel::new(flags, density).with_emissive_bits(emissive)
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct ReplayFile {
+    version: u32,
+    structures: Vec<ReplayStructure>,
+    records: Vec<ReplayRecord>,
+}
+
+/// A [`ReplayFile`] borrowing the records to save.
+#[derive(Serialize)]
+struct ReplayFileRef<'a> {
+    version: u32,
+    structures: Vec<ReplayStructure>,
+    records: &'a [ReplayRecord],
+}
+
+#[derive(Default)]
+enum ReplayState {
+    #[default]
+    Idle,
+    Recording {
+        started: Option<Duration>,
+    },
+    Playing {
+        time: f64,
+        next: usize,
+        /// The world generation jobs spawned by the replay that are still running.
+        jobs: Vec<Entity>,
+    },
+}
+
+/// Records every voxel mutation of the world with its timestamp, i.e. the applied [`ChunkEdit`]s,
+/// [`PlaceStructure`]s and started [`WorldGenJob`]s, to replay them later in the same order, e.g. to
+/// reproduce a desync or as an automated regression scenario.
+///
+/// Structures are saved with the replay so that it doesn't depend on their assets. Generation jobs
+/// are replayed from the [`ChunkProvider`] given to [`VoxelReplay::with_provider`], which must be
+/// deterministic; the replay clock stops while they run, since their chunks generate in the
+/// background at a speed that differs from one run to the next.
+#[derive(Resource)]
+pub struct VoxelReplay {
+    /// Playback speed, 2.0 replaying the mutations twice as fast as they were recorded.
+    pub speed: f32,
+    provider: Option<Arc<dyn ChunkProvider>>,
+    structures: Vec<VoxelStructure>,
+    structure_indices: HashMap<AssetId<VoxelStructure>, usize>,
+    structure_handles: Vec<Handle<VoxelStructure>>,
+    records: Vec<ReplayRecord>,
+    state: ReplayState,
+}
+
+impl Default for VoxelReplay {
+    fn default() -> Self {
+        Self {
+            speed: 1.0,
+            provider: None,
+            structures: Vec::new(),
+            structure_indices: HashMap::new(),
+            structure_handles: Vec::new(),
+            records: Vec::new(),
+            state: ReplayState::default(),
+        }
+    }
+}
+
+impl VoxelReplay {
+    /// Generates the chunks of the replayed generation jobs with `provider`.
+    pub fn with_provider(mut self, provider: impl ChunkProvider) -> Self {
+        self.provider = Some(Arc::new(provider));
+        self
+    }
+
+    /// Discards the recorded mutations and records the next ones.
+    pub fn start_recording(&mut self) {
+        self.structures.clear();
+        self.structure_indices.clear();
+        self.structure_handles.clear();
+        self.records.clear();
+        self.state = ReplayState::Recording { started: None };
+    }
+
+    /// Replays the recorded mutations from the start.
+    pub fn play(&mut self) {
+        self.state = ReplayState::Playing {
+            time: 0.0,
+            next: 0,
+            jobs: Vec::new(),
+        };
+    }
+
+    /// Stops recording or playing.
+    pub fn stop(&mut self) {
+        self.state = ReplayState::Idle;
+    }
+
+    pub fn is_recording(&self) -> bool {
+        matches!(self.state, ReplayState::Recording { .. })
+    }
+
+    pub fn is_playing(&self) -> bool {
+        matches!(self.state, ReplayState::Playing { .. })
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Time from the start of the recording to its last mutation, at normal speed.
+    pub fn duration(&self) -> Duration {
+        self.records.last().map_or(Duration::ZERO, |record| {
+            Duration::from_secs_f64(record.time)
+        })
+    }
+
+    /// Writes the recorded mutations to `path`.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let file = ReplayFileRef {
+            version: REPLAY_VERSION,
+            structures: self.structures.iter().map(ReplayStructure::from).collect(),
+            records: &self.records,
+        };
+
+        let contents =
+            ron::to_string(&file).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        fs::write(path, contents)
+    }
+
+    /// Replaces the recorded mutations with those saved at `path`, keeping the provider and speed.
+    pub fn load(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+        let file = ron::from_str::<ReplayFile>(&fs::read_to_string(path)?)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        if file.version != REPLAY_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported replay version {}", file.version),
+            ));
+        }
+
+        self.structures = file.structures.into_iter().map(Into::into).collect();
+        self.structure_indices.clear();
+        self.structure_handles.clear();
+        self.records = file.records;
+        self.state = ReplayState::Idle;
+        Ok(())
+    }
+
+    /// Records the mutations applied this frame while recording. Edits of volumes without a
+    /// [`ChunkCoord`] are skipped, as they could not be told apart when replayed.
+    pub fn record(
+        mut replay: ResMut<Self>,
+        time: Res<Time<Real>>,
+        structures: Res<Assets<VoxelStructure>>,
+        chunk_query: Query<(), With<ChunkCoord>>,
+        mut edited: EventReader<ChunkEdited>,
+        mut placed: EventReader<StructurePlaced>,
+        mut generated: EventReader<WorldGenStarted>,
+    ) {
+        let replay = replay.as_mut();
+        let ReplayState::Recording { started } = &mut replay.state else {
+            edited.clear();
+            placed.clear();
+            generated.clear();
+            return;
+        };
+        let started = *started.get_or_insert(time.elapsed());
+        let now = time.elapsed().saturating_sub(started).as_secs_f64();
+
+        for WorldGenStarted { origin, size, .. } in generated.read() {
+            replay.records.push(ReplayRecord {
+                time: now,
+                event: ReplayEvent::Generate {
+                    origin: origin.0.to_array(),
+                    size: *size,
+                },
+            });
+        }
+
+        for ChunkEdited {
+            entity,
+            coord,
+            edit,
+            ..
+        } in edited.read()
+        {
+            if !chunk_query.contains(*entity) {
+                continue;
+            }
+            replay.records.push(ReplayRecord {
+                time: now,
+                event: ReplayEvent::Edit(JournalRecord::from((*coord, *edit))),
+            });
+        }
+
+        for StructurePlaced {
+            structure,
+            placement,
+            ..
+        } in placed.read()
+        {
+            let index = match replay.structure_indices.get(structure) {
+                Some(index) => *index,
+                None => {
+                    let Some(asset) = structures.get(*structure) else {
+                        continue;
+                    };
+                    replay.structures.push(asset.clone());
+                    replay
+                        .structure_indices
+                        .insert(*structure, replay.structures.len() - 1);
+                    replay.structures.len() - 1
+                }
+            };
+
+            replay.records.push(ReplayRecord {
+                time: now,
+                event: ReplayEvent::Stamp {
+                    structure: index,
+                    position: placement.position.to_array(),
+                    axis: placement.axis,
+                    turns: placement.turns,
+                    blend: placement.blend,
+                },
+            });
+        }
+    }
+
+    /// Sends the recorded mutations that are due while playing, stopping at the end of the replay.
+    #[allow(clippy::too_many_arguments)]
+    pub fn play_back(
+        mut commands: Commands,
+        mut replay: ResMut<Self>,
+        time: Res<Time<Real>>,
+        mut structures: ResMut<Assets<VoxelStructure>>,
+        chunk_query: Query<(Entity, &ChunkCoord), With<Volumetric>>,
+        job_query: Query<(), With<WorldGenJob>>,
+        mut edits: EventWriter<ChunkEdit>,
+        mut places: EventWriter<PlaceStructure>,
+    ) {
+        let replay = replay.as_mut();
+        if !replay.is_playing() {
+            return;
+        }
+
+        while replay.structure_handles.len() < replay.structures.len() {
+            let structure = replay.structures[replay.structure_handles.len()].clone();
+            replay.structure_handles.push(structures.add(structure));
+        }
+
+        let ReplayState::Playing {
+            time: replay_time,
+            next,
+            jobs,
+        } = &mut replay.state
+        else {
+            return;
+        };
+
+        jobs.retain(|job| job_query.contains(*job));
+        if !jobs.is_empty() {
+            return;
+        }
+        *replay_time += time.delta_seconds_f64() * replay.speed.max(0.0) as f64;
+
+        let chunks = chunk_query
+            .iter()
+            .map(|(entity, coord)| (*coord, entity))
+            .collect::<HashMap<_, _>>();
+
+        while let Some(record) = replay.records.get(*next) {
+            if record.time > *replay_time || !jobs.is_empty() {
+                break;
+            }
+            *next += 1;
+
+            match &record.event {
+                ReplayEvent::Edit(edit) => {
+                    let (coord, edit): (ChunkCoord, VoxelEdit) = (*edit).into();
+                    let Some(entity) = chunks.get(&coord) else {
+                        warn!("Replayed an edit of chunk {:?}, which is missing", coord.0);
+                        continue;
+                    };
+                    edits.send(ChunkEdit {
+                        entity: *entity,
+                        edit,
+                    });
+                }
+                ReplayEvent::Stamp {
+                    structure,
+                    position,
+                    axis,
+                    turns,
+                    blend,
+                } => {
+                    let Some(structure) = replay.structure_handles.get(*structure) else {
+                        continue;
+                    };
+                    places.send(PlaceStructure {
+                        structure: structure.clone(),
+                        placement: StructurePlacement {
+                            position: IVec3::from_array(*position),
+                            axis: *axis,
+                            turns: *turns,
+                            blend: *blend,
+                        },
+                    });
+                }
+                ReplayEvent::Generate { origin, size } => {
+                    let Some(provider) = &replay.provider else {
+                        warn!("Skipped a replayed world generation, the replay has no provider");
+                        continue;
+                    };
+                    let job = WorldGenJob::from_shared(
+                        provider.clone(),
+                        ChunkCoord(IVec3::from_array(*origin)),
+                        *size,
+                    );
+                    jobs.push(commands.spawn(job).id());
+                }
+            }
+        }
+
+        if *next >= replay.records.len() && jobs.is_empty() {
+            replay.state = ReplayState::Idle;
+        }
+    }
+}
+
+/// Records and plays back the [`VoxelReplay`] of the world. Must be added after the
+/// [`GpuReadbackPlugin`](crate::GpuReadbackPlugin); replaying generation jobs also needs the
+/// [`WorldGenPlugin`](crate::world_gen::WorldGenPlugin).
+pub struct VoxelReplayPlugin;
+
+impl Plugin for VoxelReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<VoxelReplay>()
+            .add_event::<WorldGenStarted>()
+            .add_systems(Update, VoxelReplay::play_back)
+            .add_systems(
+                PostUpdate,
+                VoxelReplay::record
+                    .after(ChunkEdit::apply)
+                    .after(PlaceStructure::apply),
+            );
+    }
+}
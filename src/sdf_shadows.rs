@@ -0,0 +1,172 @@
+use bevy::{
+    pbr::{ExtendedMaterial, MaterialExtension},
+    prelude::*,
+    render::render_resource::{AsBindGroup, ShaderRef, ShaderType},
+};
+
+use crate::{data::distance_field::DistanceFieldVolume, CHUNK_SZ};
+
+const SHADER_ASSET_PATH: &str = "shaders/sdf_shadows.wgsl";
+
+/// Shades the mesh of a volumetric entity with soft shadows cast by its own voxels, cone-traced
+/// through the distance field of its [`DistanceFieldVolume`] towards the first
+/// [`DirectionalLight`]. The shadows follow edits as the distance field is generated again when
+/// the entity is remeshed.
+///
+/// With the [`SdfShadowsPlugin`], the [`StandardMaterial`] of the entity is replaced by an
+/// [`SdfShadowMaterial`] extending it. Cones leaving the chunk are unoccluded, so voxels of other
+/// chunks cast no shadow.
+#[derive(Clone, Copy, Component, Debug)]
+pub struct SdfShadows {
+    /// Half angle of the cone in radians, the larger the softer the penumbrae.
+    pub cone_angle: f32,
+    /// Fraction of the light removed in full shadow.
+    pub strength: f32,
+    /// Distance in voxels the cones are traced over.
+    pub max_distance: f32,
+    /// Maximum number of distance field samples along a cone.
+    pub steps: u32,
+}
+
+impl Default for SdfShadows {
+    fn default() -> Self {
+        Self {
+            cone_angle: 0.1,
+            strength: 0.8,
+            max_distance: CHUNK_SZ as f32,
+            steps: 32,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, ShaderType)]
+pub struct SdfShadowParams {
+    pub volume_from_world: Mat4,
+    /// Direction towards the light, in voxels.
+    pub light_direction: Vec3,
+    pub cone_angle: f32,
+    pub strength: f32,
+    pub max_distance: f32,
+    pub steps: u32,
+}
+
+/// Traces the [`SdfShadows`] of a chunk through its distance field, see [`SdfShadowMaterial`].
+#[derive(Asset, AsBindGroup, Clone, Debug, TypePath)]
+pub struct SdfShadowExtension {
+    #[uniform(100)]
+    pub params: SdfShadowParams,
+    /// The `R32Float` distance texture of the [`DistanceFieldVolume`].
+    #[texture(101, dimension = "3d", sample_type = "float", filterable = false)]
+    pub distances: Handle<Image>,
+}
+
+impl MaterialExtension for SdfShadowExtension {
+    fn fragment_shader() -> ShaderRef {
+        SHADER_ASSET_PATH.into()
+    }
+
+    fn deferred_fragment_shader() -> ShaderRef {
+        SHADER_ASSET_PATH.into()
+    }
+}
+
+/// A [`StandardMaterial`] darkened by [`SdfShadows`].
+pub type SdfShadowMaterial = ExtendedMaterial<StandardMaterial, SdfShadowExtension>;
+
+impl SdfShadows {
+    fn params(&self, volume: &GlobalTransform, light_direction: Vec3) -> SdfShadowParams {
+        let volume_from_world = volume.compute_matrix().inverse();
+        SdfShadowParams {
+            volume_from_world,
+            light_direction: volume_from_world
+                .transform_vector3(light_direction)
+                .normalize_or_zero(),
+            cone_angle: self.cone_angle.clamp(1e-3, 1.5),
+            strength: self.strength.clamp(0.0, 1.0),
+            max_distance: self.max_distance.max(0.0),
+            steps: self.steps,
+        }
+    }
+
+    /// Replaces the [`StandardMaterial`] of the entities with [`SdfShadows`] and a
+    /// [`DistanceFieldVolume`] by an [`SdfShadowMaterial`] extending it.
+    #[allow(clippy::type_complexity)]
+    pub fn extend_materials(
+        mut commands: Commands,
+        standard_materials: Res<Assets<StandardMaterial>>,
+        mut materials: ResMut<Assets<SdfShadowMaterial>>,
+        shadows_query: Query<
+            (Entity, &Handle<StandardMaterial>, &DistanceFieldVolume),
+            With<SdfShadows>,
+        >,
+    ) {
+        for (entity, standard_material, distance_field) in shadows_query.iter() {
+            let Some(base) = standard_materials.get(standard_material) else {
+                continue;
+            };
+            let material = materials.add(ExtendedMaterial {
+                base: base.clone(),
+                extension: SdfShadowExtension {
+                    params: SdfShadowParams::default(),
+                    distances: distance_field.image.clone(),
+                },
+            });
+            commands
+                .entity(entity)
+                .remove::<Handle<StandardMaterial>>()
+                .insert(material);
+        }
+    }
+
+    /// Keeps the params and distance texture of the materials in sync with their [`SdfShadows`],
+    /// volume transform and the direction of the first [`DirectionalLight`].
+    pub fn update_materials(
+        mut materials: ResMut<Assets<SdfShadowMaterial>>,
+        light_query: Query<&GlobalTransform, With<DirectionalLight>>,
+        shadows_query: Query<(
+            &SdfShadows,
+            &DistanceFieldVolume,
+            &GlobalTransform,
+            &Handle<SdfShadowMaterial>,
+        )>,
+    ) {
+        // Without a directional light, shadows fall straight down.
+        let light_direction = light_query
+            .iter()
+            .next()
+            .map_or(Vec3::Y, |light| -*light.forward());
+
+        for (shadows, distance_field, volume, material) in shadows_query.iter() {
+            let params = shadows.params(volume, light_direction);
+            // Only touch materials that changed, as their bind groups are prepared again.
+            let is_current = materials.get(material).is_some_and(|material| {
+                material.extension.params == params
+                    && material.extension.distances == distance_field.image
+            });
+            if is_current {
+                continue;
+            }
+            if let Some(material) = materials.get_mut(material) {
+                material.extension.params = params;
+                material.extension.distances = distance_field.image.clone();
+            }
+        }
+    }
+}
+
+/// Draws the [`SdfShadows`] of volumetric entities.
+///
+/// Needs the [`DistanceFieldPlugin`](crate::distance_field::DistanceFieldPlugin).
+pub struct SdfShadowsPlugin;
+
+impl Plugin for SdfShadowsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(MaterialPlugin::<SdfShadowMaterial>::default())
+            .add_systems(
+                PostUpdate,
+                (SdfShadows::extend_materials, SdfShadows::update_materials)
+                    .chain()
+                    .after(TransformSystem::TransformPropagate),
+            );
+    }
+}
@@ -0,0 +1,284 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::{self, File},
+    io::{self, Read},
+    path::Path,
+};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bundles::volumetric_bundle::{Volumetric, VolumetricBundle},
+    chunk_compression::{compress_chunks, ChunkCodec, CompressionOptions},
+    chunk_migration::{ChunkMigrations, CHUNK_SCHEMA_VERSION},
+    core::serialization::{decode_voxels, encode_voxels},
+    data::{
+        chunk_coord::ChunkCoord,
+        generation_graph::{GenerationGraph, GraphVolume},
+        procedural_volume::ProceduralVolume,
+        voxel_material::VoxelMaterial,
+    },
+    invalid_data,
+};
+
+const MANIFEST_PATH: &str = "manifest.ron";
+const SNAPSHOT_VERSION: u32 = 1;
+
+/// Describes the contents of a [`WorldSnapshot`] archive.
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    version: u32,
+    /// The [`CHUNK_SCHEMA_VERSION`] the chunks were saved with, the first one for snapshots saved
+    /// before it was recorded.
+    #[serde(default = "first_chunk_schema")]
+    chunk_schema: u32,
+    /// The codec the chunks were compressed with, none for snapshots saved before chunks were
+    /// compressed.
+    #[serde(default)]
+    codec: ChunkCodec,
+    chunks: Vec<ChunkManifest>,
+    metadata: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ChunkManifest {
+    /// Path of the chunk's voxel data in the archive.
+    path: String,
+    #[serde(default)]
+    coord: [i32; 3],
+    chunk_size: u32,
+    translation: [f32; 3],
+}
+
+fn first_chunk_schema() -> u32 {
+    1
+}
+
+/// A chunk of a [`WorldSnapshot`].
+pub struct ChunkSnapshot {
+    pub coord: ChunkCoord,
+    pub voxel_material: VoxelMaterial,
+    pub translation: Vec3,
+}
+
+/// All the volumetric chunks of a world, saved to and loaded from a single tar archive holding a
+/// `manifest.ron` and the voxel data of every chunk.
+#[derive(Default)]
+pub struct WorldSnapshot {
+    pub chunks: Vec<ChunkSnapshot>,
+    /// Application data saved alongside the chunks, e.g. generation seeds or edit history.
+    pub metadata: BTreeMap<String, String>,
+    /// The [`EditJournal::position`](crate::journal::EditJournal::position) when the snapshot was
+    /// captured, 0 for a loaded snapshot.
+    pub journal_position: u64,
+}
+
+impl WorldSnapshot {
+    /// Captures the [`VoxelMaterial`], [`ChunkCoord`] and translation of every volumetric entity in
+    /// `world`, along with the seeds its chunks were generated with and the edit journal holding the
+    /// edits since, in the `seeds`, `edit_journal` and `edit_journal_position` metadata.
+    pub fn capture(world: &mut World) -> Self {
+        let chunks = world
+            .query_filtered::<(&VoxelMaterial, Option<&ChunkCoord>, Option<&Transform>), With<Volumetric>>()
+            .iter(world)
+            .map(|(voxel_material, coord, transform)| ChunkSnapshot {
+                coord: coord.copied().unwrap_or_default(),
+                voxel_material: VoxelMaterial::new(voxel_material.voxels().to_vec(), voxel_material.chunk_size),
+                translation: transform.map_or(Vec3::ZERO, |transform| transform.translation),
+            })
+            .collect();
+
+        let mut metadata = BTreeMap::new();
+        let seeds = generation_seeds(world);
+        if !seeds.is_empty() {
+            let seeds = seeds.iter().map(u32::to_string).collect::<Vec<_>>();
+            metadata.insert("seeds".to_string(), format!("[{}]", seeds.join(", ")));
+        }
+
+        #[cfg(feature = "editing")]
+        let journal_position = match world.get_resource::<crate::journal::EditJournal>() {
+            Some(journal) => {
+                metadata.insert(
+                    "edit_journal".to_string(),
+                    journal.path().display().to_string(),
+                );
+                metadata.insert(
+                    "edit_journal_position".to_string(),
+                    journal.position().to_string(),
+                );
+                journal.position()
+            }
+            None => 0,
+        };
+        #[cfg(not(feature = "editing"))]
+        let journal_position = 0;
+
+        Self {
+            chunks,
+            metadata,
+            journal_position,
+        }
+    }
+
+    /// Spawns a volumetric entity for every chunk of the snapshot.
+    pub fn spawn(self, commands: &mut Commands) {
+        for chunk in self.chunks {
+            commands.spawn((
+                VolumetricBundle::new(chunk.voxel_material),
+                chunk.coord,
+                Transform::from_translation(chunk.translation),
+            ));
+        }
+    }
+
+    /// Writes the snapshot to a tar archive at `path`, compressing its chunks with the default
+    /// [`CompressionOptions`]. Send a [`ChunksSaved::from_snapshot`](crate::journal::ChunksSaved::from_snapshot)
+    /// once it succeeds for the edit journal to drop the edits of its chunks.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.save_with(path, &CompressionOptions::default())
+    }
+
+    /// Writes the snapshot to a tar archive at `path`, compressing its chunks in parallel with the
+    /// codec of `options` while they are streamed to the archive. The archive is written to a
+    /// temporary file that replaces the one at `path` once synced, so a crash while saving leaves
+    /// the previous snapshot intact.
+    pub fn save_with(
+        &self,
+        path: impl AsRef<Path>,
+        options: &CompressionOptions,
+    ) -> io::Result<()> {
+        let path = path.as_ref();
+        let saving_path = path.with_extension("saving");
+        let mut archive = tar::Builder::new(File::create(&saving_path)?);
+
+        let manifest = Manifest {
+            version: SNAPSHOT_VERSION,
+            chunk_schema: CHUNK_SCHEMA_VERSION,
+            codec: options.codec,
+            chunks: self
+                .chunks
+                .iter()
+                .enumerate()
+                .map(|(index, chunk)| ChunkManifest {
+                    path: format!("chunks/{index}.bin"),
+                    coord: chunk.coord.0.to_array(),
+                    chunk_size: chunk.voxel_material.chunk_size,
+                    translation: chunk.translation.to_array(),
+                })
+                .collect(),
+            metadata: self.metadata.clone(),
+        };
+
+        compress_chunks(
+            self.chunks.len(),
+            options,
+            |index| encode_voxels(self.chunks[index].voxel_material.voxels()),
+            |index, data| append_file(&mut archive, &manifest.chunks[index].path, &data),
+        )?;
+
+        let manifest = ron::ser::to_string_pretty(&manifest, default())
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        append_file(&mut archive, MANIFEST_PATH, manifest.as_bytes())?;
+
+        archive.into_inner()?.sync_all()?;
+        fs::rename(&saving_path, path)
+    }
+
+    /// Reads a snapshot written by [`WorldSnapshot::save`] with the current
+    /// [`CHUNK_SCHEMA_VERSION`].
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::load_with_migrations(path, &ChunkMigrations::default())
+    }
+
+    /// Reads a snapshot written by [`WorldSnapshot::save`], migrating the chunks saved with an older
+    /// [`CHUNK_SCHEMA_VERSION`] through `migrations`.
+    pub fn load_with_migrations(
+        path: impl AsRef<Path>,
+        migrations: &ChunkMigrations,
+    ) -> io::Result<Self> {
+        let mut files = BTreeMap::new();
+        for entry in tar::Archive::new(File::open(path)?).entries()? {
+            let mut entry = entry?;
+            let path = entry.path()?.to_string_lossy().into_owned();
+            let mut data = Vec::new();
+            entry.read_to_end(&mut data)?;
+            files.insert(path, data);
+        }
+
+        let manifest = files
+            .get(MANIFEST_PATH)
+            .ok_or_else(|| invalid_data("the snapshot has no manifest"))?;
+        let manifest: Manifest = ron::de::from_bytes(manifest).map_err(invalid_data)?;
+
+        if manifest.version != SNAPSHOT_VERSION {
+            return Err(invalid_data(format!(
+                "unsupported snapshot version {}",
+                manifest.version
+            )));
+        }
+
+        let chunks = manifest
+            .chunks
+            .into_iter()
+            .map(|chunk| {
+                let data = files
+                    .get(&chunk.path)
+                    .ok_or_else(|| invalid_data(format!("missing chunk {}", chunk.path)))?;
+
+                let data = manifest.codec.decompress(data)?;
+                let data = migrations.migrate(&data, manifest.chunk_schema)?;
+                let voxels = decode_voxels(&data);
+
+                Ok(ChunkSnapshot {
+                    coord: ChunkCoord(IVec3::from_array(chunk.coord)),
+                    voxel_material: VoxelMaterial::new(voxels, chunk.chunk_size),
+                    translation: Vec3::from_array(chunk.translation),
+                })
+            })
+            .collect::<io::Result<_>>()?;
+
+        Ok(Self {
+            chunks,
+            metadata: manifest.metadata,
+            journal_position: 0,
+        })
+    }
+}
+
+/// The seeds of the [`ProceduralVolume`]s and [`GenerationGraph`]s of the chunks of `world`.
+fn generation_seeds(world: &mut World) -> BTreeSet<u32> {
+    let mut seeds = world
+        .query::<&ProceduralVolume>()
+        .iter(world)
+        .map(|procedural_volume| procedural_volume.seed)
+        .collect::<BTreeSet<_>>();
+
+    let graphs = world
+        .query::<&GraphVolume>()
+        .iter(world)
+        .map(|graph_volume| graph_volume.graph.id())
+        .collect::<Vec<_>>();
+    if let Some(assets) = world.get_resource::<Assets<GenerationGraph>>() {
+        seeds.extend(
+            graphs
+                .into_iter()
+                .filter_map(|id| assets.get(id))
+                .map(|graph| graph.seed),
+        );
+    }
+
+    seeds
+}
+
+pub(crate) fn append_file(
+    archive: &mut tar::Builder<File>,
+    path: &str,
+    data: &[u8],
+) -> io::Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    archive.append_data(&mut header, path, data)
+}
@@ -0,0 +1,49 @@
+use bevy::{
+    prelude::*,
+    render::{extract_component::ExtractComponentPlugin, Render, RenderApp, RenderSet},
+};
+
+use crate::{
+    data::{
+        chunk_retirement::ChunkRetirementExt, gpu_splat_map::GpuSplatMap, splat_map::SplatMap,
+        voxel_material::VoxelMaterialComponents,
+    },
+    render::{
+        post_mesh_compute_pass::PostMeshComputePassAppExt,
+        splat_map_compute_pipeline::{SplatMapComputePass, SplatMapComputePipeline},
+        voxel_mesh_compute_pipeline::DirtyMeshes,
+    },
+};
+
+/// Generates the splat texture of each volumetric entity with a [`SplatMap`] on the GPU whenever
+/// it is remeshed.
+pub struct SplatMapPlugin;
+
+impl Plugin for SplatMapPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(ExtractComponentPlugin::<SplatMap>::default())
+            .add_post_mesh_compute_pass(SplatMapComputePass);
+    }
+
+    fn finish(&self, app: &mut App) {
+        let render_app = app.sub_app_mut(RenderApp);
+
+        render_app
+            .init_resource::<SplatMapComputePipeline>()
+            .init_resource::<VoxelMaterialComponents<GpuSplatMap>>()
+            .free_on_retire::<GpuSplatMap>()
+            .add_systems(
+                ExtractSchedule,
+                (
+                    GpuSplatMap::initialize,
+                    GpuSplatMap::extract.after(GpuSplatMap::initialize),
+                ),
+            )
+            .add_systems(
+                Render,
+                GpuSplatMap::prepare
+                    .in_set(RenderSet::PrepareBindGroups)
+                    .after(DirtyMeshes::select),
+            );
+    }
+}
@@ -0,0 +1,332 @@
+use std::{io, sync::Arc};
+
+use bevy::{
+    prelude::*,
+    tasks::{block_on, poll_once, AsyncComputeTaskPool, Task},
+    utils::{BoxedFuture, HashMap, HashSet},
+};
+
+use crate::{
+    bundles::volumetric_bundle::{VolumetricBundle, VoxelComputeSuspended},
+    data::{
+        chunk_coord::ChunkCoord, chunk_retirement::ChunkRetiring, voxel::Voxel,
+        voxel_material::VoxelMaterial, voxel_world::VoxelWorldConfig,
+    },
+};
+
+/// The voxels of a chunk served by a [`ChunkProvider`].
+#[derive(Clone)]
+pub struct ChunkData {
+    pub voxels: Vec<Voxel>,
+    pub chunk_size: u32,
+}
+
+impl From<&VoxelMaterial> for ChunkData {
+    fn from(voxel_material: &VoxelMaterial) -> Self {
+        ChunkData {
+            voxels: voxel_material.voxels().to_vec(),
+            chunk_size: voxel_material.chunk_size,
+        }
+    }
+}
+
+impl From<ChunkData> for VoxelMaterial {
+    fn from(chunk: ChunkData) -> Self {
+        VoxelMaterial::new(chunk.voxels, chunk.chunk_size)
+    }
+}
+
+/// A source of chunk data, e.g. an HTTP server, a database or a procedural generator. Fetches run
+/// on the [`AsyncComputeTaskPool`], so they may block or await without stalling the frame.
+pub trait ChunkProvider: Send + Sync + 'static {
+    fn fetch(&self, coord: ChunkCoord) -> BoxedFuture<'_, io::Result<ChunkData>>;
+
+    /// Persists the voxels of a chunk edited since it was fetched, once it is unloaded. Chunks
+    /// are only kept in the cache by default.
+    fn store(&self, _coord: ChunkCoord, _chunk: ChunkData) -> BoxedFuture<'_, io::Result<()>> {
+        Box::pin(async { Ok(()) })
+    }
+}
+
+/// Times a failed [`ChunkProvider::fetch`] of a chunk is attempted before it is given up on.
+pub const MAX_FETCH_ATTEMPTS: u32 = 3;
+
+/// A chunk in the cache of the [`ChunkStreaming`], along with the last update it was used in.
+struct CachedChunk {
+    chunk: ChunkData,
+    last_used: u64,
+}
+
+/// Streams chunks in and out of the world. Requested chunks are fetched from the [`ChunkProvider`]
+/// once and cached, so unloading and requesting a chunk again doesn't fetch it twice. The cache
+/// holds up to [`ChunkStreaming::cache_capacity`] chunks, evicting the least recently used ones
+/// first. Failed fetches are retried up to [`MAX_FETCH_ATTEMPTS`] times before the request is
+/// dropped.
+///
+/// Unloaded chunks are suspended and hidden right away but only despawned once they are
+/// [`ChunkRetiring`], when the GPU is done with the frames still using their buffers. The voxels
+/// of the chunks edited since they were spawned are written back to the cache and
+/// [`ChunkProvider::store`]d first. Chunks are kept in the cache while they are being stored, and
+/// for good once storing them failed, so that their edits aren't lost.
+#[derive(Resource)]
+pub struct ChunkStreaming {
+    provider: Arc<dyn ChunkProvider>,
+    /// Maximum number of chunks kept in the cache.
+    pub cache_capacity: usize,
+    cache: HashMap<ChunkCoord, CachedChunk>,
+    frame: u64,
+    fetches: HashMap<ChunkCoord, Task<io::Result<ChunkData>>>,
+    failed_fetches: HashMap<ChunkCoord, u32>,
+    stores: HashMap<ChunkCoord, Task<io::Result<()>>>,
+    unstored: HashSet<ChunkCoord>,
+    loaded: HashMap<ChunkCoord, Entity>,
+    edited: HashSet<ChunkCoord>,
+    requested: Vec<ChunkCoord>,
+    unloaded: Vec<ChunkCoord>,
+}
+
+impl ChunkStreaming {
+    pub fn new(provider: impl ChunkProvider) -> Self {
+        Self::from_provider(Arc::new(provider))
+    }
+
+    fn from_provider(provider: Arc<dyn ChunkProvider>) -> Self {
+        Self {
+            provider,
+            cache_capacity: 1024,
+            cache: HashMap::default(),
+            frame: 0,
+            fetches: HashMap::default(),
+            failed_fetches: HashMap::default(),
+            stores: HashMap::default(),
+            unstored: HashSet::default(),
+            loaded: HashMap::default(),
+            edited: HashSet::default(),
+            requested: Vec::new(),
+            unloaded: Vec::new(),
+        }
+    }
+
+    /// Spawns the chunk at `coord`, fetching it first if it isn't cached.
+    pub fn request(&mut self, coord: ChunkCoord) {
+        self.unloaded.retain(|unloaded| *unloaded != coord);
+        self.requested.push(coord);
+    }
+
+    /// Retires the chunk at `coord`, keeping its data cached.
+    pub fn unload(&mut self, coord: ChunkCoord) {
+        self.requested.retain(|requested| *requested != coord);
+        self.unloaded.push(coord);
+    }
+
+    /// Keeps up to `cache_capacity` chunks in the cache.
+    pub fn with_cache_capacity(mut self, cache_capacity: usize) -> Self {
+        self.cache_capacity = cache_capacity;
+        self
+    }
+
+    /// Drops the cached data of the chunk at `coord`, so that it is fetched again when requested.
+    /// Edits of the chunk not yet written back by unloading it, or not yet stored, are lost.
+    pub fn evict(&mut self, coord: ChunkCoord) {
+        self.cache.remove(&coord);
+        self.unstored.remove(&coord);
+    }
+
+    /// Whether storing the edits of the chunk at `coord` failed, so that they are only cached.
+    pub fn is_unstored(&self, coord: ChunkCoord) -> bool {
+        self.unstored.contains(&coord)
+    }
+
+    /// Evicts the least recently used chunks past the capacity of the cache, but those being
+    /// stored or whose edits could not be stored.
+    fn evict_over_capacity(&mut self) {
+        let excess = self.cache.len().saturating_sub(self.cache_capacity);
+        if excess == 0 {
+            return;
+        }
+
+        let mut evictable = self
+            .cache
+            .iter()
+            .filter(|(coord, _)| {
+                !self.stores.contains_key(*coord) && !self.unstored.contains(*coord)
+            })
+            .map(|(coord, cached)| (cached.last_used, *coord))
+            .collect::<Vec<_>>();
+        evictable.sort_unstable_by_key(|(last_used, _)| *last_used);
+        for (_, coord) in evictable.into_iter().take(excess) {
+            self.cache.remove(&coord);
+        }
+    }
+
+    pub fn is_loaded(&self, coord: ChunkCoord) -> bool {
+        self.loaded.contains_key(&coord)
+    }
+
+    pub fn is_cached(&self, coord: ChunkCoord) -> bool {
+        self.cache.contains_key(&coord)
+    }
+
+    /// Starts fetching the requested chunks, spawns the ones that are ready where the
+    /// [`VoxelWorldConfig`] places them and retires the unloaded ones.
+    pub fn update(
+        mut commands: Commands,
+        mut streaming: ResMut<Self>,
+        config: Res<VoxelWorldConfig>,
+        chunk_query: Query<Ref<VoxelMaterial>>,
+    ) {
+        let streaming = streaming.as_mut();
+        let task_pool = AsyncComputeTaskPool::get();
+        streaming.frame += 1;
+
+        for (coord, entity) in streaming.loaded.iter() {
+            if chunk_query.get(*entity).is_ok_and(|voxel_material| {
+                voxel_material.is_changed() && !voxel_material.is_added()
+            }) {
+                streaming.edited.insert(*coord);
+            }
+        }
+
+        for coord in streaming.unloaded.drain(..) {
+            streaming.fetches.remove(&coord);
+            let Some(entity) = streaming.loaded.remove(&coord) else {
+                continue;
+            };
+
+            if streaming.edited.remove(&coord) {
+                if let Ok(voxel_material) = chunk_query.get(entity) {
+                    let chunk = ChunkData::from(voxel_material.as_ref());
+                    let cached = CachedChunk {
+                        chunk: chunk.clone(),
+                        last_used: streaming.frame,
+                    };
+                    streaming.cache.insert(coord, cached);
+                    let provider = streaming.provider.clone();
+                    let store = task_pool.spawn(async move { provider.store(coord, chunk).await });
+                    // A store still running for older voxels of the chunk is left to finish.
+                    if let Some(previous) = streaming.stores.insert(coord, store) {
+                        previous.detach();
+                    }
+                }
+            }
+            commands.entity(entity).insert((
+                ChunkRetiring,
+                VoxelComputeSuspended,
+                Visibility::Hidden,
+            ));
+        }
+
+        for coord in &streaming.requested {
+            if streaming.loaded.contains_key(coord)
+                || streaming.fetches.contains_key(coord)
+                || streaming.cache.contains_key(coord)
+            {
+                continue;
+            }
+
+            let provider = streaming.provider.clone();
+            let coord = *coord;
+            let fetch = task_pool.spawn(async move { provider.fetch(coord).await });
+            streaming.fetches.insert(coord, fetch);
+        }
+
+        let mut fetched = Vec::new();
+        streaming.fetches.retain(|coord, fetch| {
+            let Some(result) = block_on(poll_once(fetch)) else {
+                return true;
+            };
+            fetched.push((*coord, result));
+            false
+        });
+        for (coord, result) in fetched {
+            match result {
+                Ok(chunk) => {
+                    streaming.failed_fetches.remove(&coord);
+                    let last_used = streaming.frame;
+                    streaming
+                        .cache
+                        .insert(coord, CachedChunk { chunk, last_used });
+                }
+                Err(err) => {
+                    let attempts = streaming.failed_fetches.entry(coord).or_default();
+                    *attempts += 1;
+                    if *attempts < MAX_FETCH_ATTEMPTS {
+                        // Still requested, so fetched again below on the next update.
+                        warn!("Failed to fetch chunk {coord:?}, retrying: {err}");
+                    } else {
+                        error!(
+                            "Failed to fetch chunk {coord:?} {attempts} times, giving up: {err}"
+                        );
+                        streaming.failed_fetches.remove(&coord);
+                        streaming.requested.retain(|requested| *requested != coord);
+                    }
+                }
+            }
+        }
+
+        let mut stored = Vec::new();
+        streaming.stores.retain(|coord, store| {
+            let Some(result) = block_on(poll_once(store)) else {
+                return true;
+            };
+            stored.push((*coord, result));
+            false
+        });
+        for (coord, result) in stored {
+            match result {
+                Ok(()) => {
+                    streaming.unstored.remove(&coord);
+                }
+                Err(err) => {
+                    error!("Failed to store chunk {coord:?}, keeping its edits cached: {err}");
+                    streaming.unstored.insert(coord);
+                }
+            }
+        }
+
+        streaming.requested.retain(|coord| {
+            if streaming.loaded.contains_key(coord) {
+                return false;
+            }
+            let Some(cached) = streaming.cache.get_mut(coord) else {
+                // Still fetching, or fetched again after a failure.
+                return true;
+            };
+            cached.last_used = streaming.frame;
+            let chunk = &cached.chunk;
+
+            let entity = commands
+                .spawn((
+                    VolumetricBundle::new(chunk.clone().into()),
+                    *coord,
+                    config.chunk_transform(*coord),
+                ))
+                .id();
+            streaming.loaded.insert(*coord, entity);
+            false
+        });
+
+        streaming.evict_over_capacity();
+    }
+}
+
+/// Streams chunks from a [`ChunkProvider`] through the [`ChunkStreaming`] resource.
+pub struct ChunkStreamingPlugin {
+    provider: Arc<dyn ChunkProvider>,
+}
+
+impl ChunkStreamingPlugin {
+    pub fn new(provider: impl ChunkProvider) -> Self {
+        Self {
+            provider: Arc::new(provider),
+        }
+    }
+}
+
+impl Plugin for ChunkStreamingPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ChunkStreaming::from_provider(self.provider.clone()))
+            .init_resource::<VoxelWorldConfig>()
+            .add_systems(PreUpdate, ChunkStreaming::update);
+    }
+}
@@ -0,0 +1,463 @@
+use std::{io, sync::Arc};
+
+use bevy::{prelude::*, utils::BoxedFuture};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    data::{
+        chunk_coord::ChunkCoord,
+        voxel::Voxel,
+        voxel_world::{voxel_index, VoxelWorldConfig},
+    },
+    streaming::{ChunkData, ChunkProvider},
+    CHUNK_SZ, CHUNK_SZ_3,
+};
+
+/// A step of a [`TerrainPipeline`], transforming the voxels generated by the previous stages.
+pub trait TerrainStage: Send + Sync + 'static {
+    /// Writes the chunk at `coord`, whose first voxel is at `origin` in voxel coordinates.
+    fn generate(&self, coord: ChunkCoord, origin: IVec3, voxels: &mut [Voxel]);
+}
+
+/// Generates chunks by running its [`TerrainStage`]s in order over empty voxels, e.g. a
+/// [`HeightmapTerrain`] followed by [`CaveCarving`]. Stages sample their noise in world voxel
+/// coordinates, so neighbouring chunks line up without seams.
+pub struct TerrainPipeline {
+    pub config: VoxelWorldConfig,
+    stages: Vec<Box<dyn TerrainStage>>,
+}
+
+impl Default for TerrainPipeline {
+    fn default() -> Self {
+        Self::new(VoxelWorldConfig::default())
+    }
+}
+
+impl TerrainPipeline {
+    pub fn new(config: VoxelWorldConfig) -> Self {
+        Self {
+            config,
+            stages: Vec::new(),
+        }
+    }
+
+    /// A [`HeightmapTerrain`] of `biomes` carved by [`CaveCarving`].
+    pub fn with_caves(biomes: BiomeMap) -> Self {
+        let seed = biomes.seed.wrapping_add(0x000c_47e5);
+        let biomes = Arc::new(biomes);
+        Self::default()
+            .with_stage(HeightmapTerrain {
+                biomes: biomes.clone(),
+            })
+            .with_stage(CaveCarving { seed, biomes })
+    }
+
+    /// Runs `stage` after the stages already added.
+    pub fn with_stage(mut self, stage: impl TerrainStage) -> Self {
+        self.stages.push(Box::new(stage));
+        self
+    }
+
+    /// Generates the voxels of the chunk at `coord`.
+    pub fn generate(&self, coord: ChunkCoord) -> ChunkData {
+        let mut voxels = vec![Voxel::new(0, 0.0); CHUNK_SZ_3];
+        let origin = self.config.chunk_to_voxel(coord);
+        for stage in &self.stages {
+            stage.generate(coord, origin, &mut voxels);
+        }
+        ChunkData {
+            voxels,
+            chunk_size: CHUNK_SZ_3 as u32,
+        }
+    }
+}
+
+impl ChunkProvider for TerrainPipeline {
+    fn fetch(&self, coord: ChunkCoord) -> BoxedFuture<'_, io::Result<ChunkData>> {
+        Box::pin(async move { Ok(self.generate(coord)) })
+    }
+}
+
+/// Caves carved out of a [`Biome`] by [`CaveCarving`]. Frequencies are in cycles per voxel.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub struct CaveSettings {
+    /// Frequency of the ridged noise whose ridges are hollowed into caverns.
+    pub ridge_frequency: f32,
+    /// Ridged noise above which voxels are carved, from 0 to 1; higher makes fewer, thinner caves.
+    pub ridge_threshold: f32,
+    /// Frequency of the two noise fields whose shared zero crossings form the worm tunnels.
+    pub worm_frequency: f32,
+    /// Half width of the worm tunnels, in noise units; 0 disables them.
+    pub worm_radius: f32,
+    /// Voxels below the surface of the terrain above which nothing is carved, so that caves don't
+    /// riddle the surface.
+    pub min_depth: f32,
+}
+
+impl Default for CaveSettings {
+    fn default() -> Self {
+        Self {
+            ridge_frequency: 0.03,
+            ridge_threshold: 0.9,
+            worm_frequency: 0.02,
+            worm_radius: 0.06,
+            min_depth: 4.0,
+        }
+    }
+}
+
+/// Identifies a [`Biome`] in a [`BiomeRegistry`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct BiomeId(pub u16);
+
+/// The terrain of the columns of the world whose climate is closest to the biome's, queried by
+/// the [`HeightmapTerrain`] and [`CaveCarving`] stages. Positions are in world voxel coordinates,
+/// columns are their `x` and `z`.
+pub trait Biome: Send + Sync + 'static {
+    /// The temperature and humidity the biome is picked for, each from 0 to 1.
+    fn climate(&self) -> Vec2;
+
+    /// Height of the surface of `column`, in voxels. Heights are blended across the borders of
+    /// biomes.
+    fn height(&self, column: Vec2, seed: u32) -> f32;
+
+    /// Flags of a voxel `depth` voxels below the surface, i.e. its material in the palette of
+    /// materials of a [`MaterialSplit`](crate::bundles::volumetric_bundle::MaterialSplit).
+    fn material(&self, depth: f32) -> u32;
+
+    /// Density of the voxel at `pos` under a surface at `height`, where features such as
+    /// overhangs or spires can be added. Defaults to solid below the surface, with a gradient of
+    /// one voxel at the surface.
+    fn density(&self, pos: Vec3, height: f32, seed: u32) -> f32 {
+        let _ = seed;
+        (height - pos.y).clamp(0.0, 1.0)
+    }
+
+    /// The caves carved into the biome by [`CaveCarving`].
+    fn caves(&self) -> CaveSettings {
+        CaveSettings::default()
+    }
+}
+
+/// A [`Biome`] of fractal hills whose voxels are layered by depth, e.g. grass over dirt over
+/// stone.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LayeredBiome {
+    /// Temperature and humidity, each from 0 to 1.
+    pub climate: Vec2,
+    /// Height of the surface in voxels, before the noise is added.
+    pub base_height: f32,
+    /// Height of the hills above and below `base_height`, in voxels.
+    pub height_amplitude: f32,
+    /// Frequency of the hills, in cycles per voxel.
+    pub height_frequency: f32,
+    /// The material of each layer along with the depth it reaches down to, from the surface down.
+    /// Voxels below the last layer are of its material.
+    pub layers: Vec<(f32, u32)>,
+    pub caves: CaveSettings,
+}
+
+impl Default for LayeredBiome {
+    fn default() -> Self {
+        Self {
+            climate: Vec2::splat(0.5),
+            base_height: 16.0,
+            height_amplitude: 8.0,
+            height_frequency: 0.01,
+            layers: vec![(1.0, 0)],
+            caves: CaveSettings::default(),
+        }
+    }
+}
+
+impl Biome for LayeredBiome {
+    fn climate(&self) -> Vec2 {
+        self.climate
+    }
+
+    fn height(&self, column: Vec2, seed: u32) -> f32 {
+        let p = column.extend(0.0).xzy() * self.height_frequency;
+        self.base_height + fbm(p, seed, 4) * self.height_amplitude
+    }
+
+    fn material(&self, depth: f32) -> u32 {
+        layer_material(&self.layers, depth)
+    }
+
+    fn caves(&self) -> CaveSettings {
+        self.caves
+    }
+}
+
+/// The biomes a [`BiomeMap`] picks from.
+#[derive(Clone, Default)]
+pub struct BiomeRegistry {
+    biomes: Vec<Arc<dyn Biome>>,
+}
+
+impl BiomeRegistry {
+    /// Adds `biome`, returning its id.
+    pub fn register(&mut self, biome: impl Biome) -> BiomeId {
+        self.biomes.push(Arc::new(biome));
+        BiomeId(self.biomes.len() as u16 - 1)
+    }
+
+    pub fn get(&self, id: BiomeId) -> Option<&dyn Biome> {
+        self.biomes.get(id.0 as usize).map(|biome| biome.as_ref())
+    }
+
+    pub fn len(&self) -> usize {
+        self.biomes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.biomes.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (BiomeId, &dyn Biome)> {
+        self.biomes
+            .iter()
+            .enumerate()
+            .map(|(index, biome)| (BiomeId(index as u16), biome.as_ref()))
+    }
+}
+
+/// Assigns each column of the world the [`Biome`] of its [`BiomeRegistry`] closest to its climate,
+/// a temperature and a humidity sampled from low frequency noise. Heights are blended between the
+/// biomes of similar climates so that their borders are smooth.
+#[derive(Clone)]
+pub struct BiomeMap {
+    pub seed: u32,
+    /// Frequency of the climate changes, in cycles per voxel.
+    pub climate_frequency: f32,
+    /// Distance in climate within which the heights of biomes are blended.
+    pub blend_distance: f32,
+    pub registry: BiomeRegistry,
+}
+
+impl Default for BiomeMap {
+    fn default() -> Self {
+        let mut registry = BiomeRegistry::default();
+        registry.register(LayeredBiome::default());
+        Self::new(registry)
+    }
+}
+
+impl BiomeMap {
+    pub fn new(registry: BiomeRegistry) -> Self {
+        Self {
+            seed: 0,
+            climate_frequency: 0.002,
+            blend_distance: 0.1,
+            registry,
+        }
+    }
+
+    /// The temperature and humidity of `column`, each from 0 to 1.
+    pub fn climate_at(&self, column: Vec2) -> Vec2 {
+        let p = column.extend(0.0).xzy() * self.climate_frequency;
+        let temperature = fbm(p, self.seed, 3);
+        let humidity = fbm(p, self.seed.wrapping_add(0x5eed), 3);
+        (Vec2::new(temperature, humidity) * 0.5 + 0.5).clamp(Vec2::ZERO, Vec2::ONE)
+    }
+
+    /// The biome whose climate is closest to that of `column`.
+    pub fn biome_at(&self, column: Vec2) -> Option<BiomeId> {
+        let climate = self.climate_at(column);
+        self.registry
+            .iter()
+            .min_by(|(_, a), (_, b)| {
+                let a = a.climate().distance_squared(climate);
+                let b = b.climate().distance_squared(climate);
+                a.total_cmp(&b)
+            })
+            .map(|(id, _)| id)
+    }
+
+    /// Height of the surface of `column`, in voxels.
+    pub fn height_at(&self, column: Vec2) -> f32 {
+        let climate = self.climate_at(column);
+        let Some(closest) = self
+            .registry
+            .iter()
+            .map(|(_, biome)| biome.climate().distance(climate))
+            .min_by(f32::total_cmp)
+        else {
+            return 0.0;
+        };
+
+        // Biomes hardly further from the climate than the closest one share the column.
+        let blend_distance = self.blend_distance.max(f32::EPSILON);
+        let (mut height, mut total) = (0.0, 0.0);
+        for (_, biome) in self.registry.iter() {
+            let excess = biome.climate().distance(climate) - closest;
+            let weight = 1.0 - smoothstep(0.0, blend_distance, excess);
+            if weight > 0.0 {
+                height += biome.height(column, self.seed.wrapping_add(1)) * weight;
+                total += weight;
+            }
+        }
+        height / total
+    }
+}
+
+/// The first stage of a terrain: the density and materials of the [`Biome`] of each column of
+/// the [`BiomeMap`].
+pub struct HeightmapTerrain {
+    pub biomes: Arc<BiomeMap>,
+}
+
+impl TerrainStage for HeightmapTerrain {
+    fn generate(&self, _coord: ChunkCoord, origin: IVec3, voxels: &mut [Voxel]) {
+        let seed = self.biomes.seed.wrapping_add(2);
+        for z in 0..CHUNK_SZ as u32 {
+            for x in 0..CHUNK_SZ as u32 {
+                let column = Vec2::new((origin.x + x as i32) as f32, (origin.z + z as i32) as f32);
+                let Some(biome) = self
+                    .biomes
+                    .biome_at(column)
+                    .and_then(|id| self.biomes.registry.get(id))
+                else {
+                    continue;
+                };
+                let height = self.biomes.height_at(column);
+
+                for y in 0..CHUNK_SZ as u32 {
+                    let pos = Vec3::new(column.x, (origin.y + y as i32) as f32, column.y);
+                    let density = biome.density(pos, height, seed);
+                    let material = biome.material(height - pos.y);
+                    voxels[voxel_index(UVec3::new(x, y, z))] = Voxel::new(material, density);
+                }
+            }
+        }
+    }
+}
+
+/// Carves caverns along the ridges of 3D noise and worm tunnels where two noise fields cross
+/// zero together out of the voxels of the previous stages, with the [`CaveSettings`] of the
+/// biome of each column.
+pub struct CaveCarving {
+    pub seed: u32,
+    pub biomes: Arc<BiomeMap>,
+}
+
+impl TerrainStage for CaveCarving {
+    fn generate(&self, _coord: ChunkCoord, origin: IVec3, voxels: &mut [Voxel]) {
+        for z in 0..CHUNK_SZ as u32 {
+            for x in 0..CHUNK_SZ as u32 {
+                let column = Vec2::new((origin.x + x as i32) as f32, (origin.z + z as i32) as f32);
+                let Some(biome) = self
+                    .biomes
+                    .biome_at(column)
+                    .and_then(|id| self.biomes.registry.get(id))
+                else {
+                    continue;
+                };
+                let caves = biome.caves();
+                let ceiling = self.biomes.height_at(column) - caves.min_depth;
+
+                for y in 0..CHUNK_SZ as u32 {
+                    let pos = Vec3::new(column.x, (origin.y + y as i32) as f32, column.y);
+                    if pos.y > ceiling {
+                        break;
+                    }
+                    let voxel = &mut voxels[voxel_index(UVec3::new(x, y, z))];
+                    if voxel.density() <= 0.0 {
+                        continue;
+                    }
+                    let carved = voxel.density() * (1.0 - carve(&caves, pos, self.seed));
+                    *voxel = voxel.with_density(carved);
+                }
+            }
+        }
+    }
+}
+
+/// How much of the voxel at `pos`, in world voxel coordinates, the `caves` carve out, from 0 to 1.
+pub(crate) fn carve(caves: &CaveSettings, pos: Vec3, seed: u32) -> f32 {
+    let ridge = 1.0 - gradient_noise(pos * caves.ridge_frequency, seed).abs();
+    let cavern = smoothstep(caves.ridge_threshold, 1.0, ridge);
+
+    let worm = match caves.worm_radius > 0.0 {
+        true => {
+            let p = pos * caves.worm_frequency;
+            let a = gradient_noise(p, seed.wrapping_add(1));
+            let b = gradient_noise(p, seed.wrapping_add(2));
+            1.0 - smoothstep(0.0, caves.worm_radius, a.hypot(b))
+        }
+        false => 0.0,
+    };
+
+    cavern.max(worm)
+}
+
+/// The material of the first of `layers` reaching below `depth`, or else of the last one.
+pub(crate) fn layer_material(layers: &[(f32, u32)], depth: f32) -> u32 {
+    layers
+        .iter()
+        .find(|(layer_depth, _)| depth < *layer_depth)
+        .or(layers.last())
+        .map_or(0, |(_, material)| *material)
+}
+
+pub(crate) fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0).max(f32::EPSILON)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+pub(crate) fn hash(cell: IVec3, seed: u32) -> u32 {
+    let mut h = seed
+        ^ (cell.x as u32).wrapping_mul(0x8da6_b343)
+        ^ (cell.y as u32).wrapping_mul(0xd816_3841)
+        ^ (cell.z as u32).wrapping_mul(0xcb1a_b31f);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x2c1b_3c6d);
+    h ^= h >> 12;
+    h = h.wrapping_mul(0x297a_2d39);
+    h ^ (h >> 15)
+}
+
+const GRADIENTS: [Vec3; 12] = [
+    Vec3::new(1.0, 1.0, 0.0),
+    Vec3::new(-1.0, 1.0, 0.0),
+    Vec3::new(1.0, -1.0, 0.0),
+    Vec3::new(-1.0, -1.0, 0.0),
+    Vec3::new(1.0, 0.0, 1.0),
+    Vec3::new(-1.0, 0.0, 1.0),
+    Vec3::new(1.0, 0.0, -1.0),
+    Vec3::new(-1.0, 0.0, -1.0),
+    Vec3::new(0.0, 1.0, 1.0),
+    Vec3::new(0.0, -1.0, 1.0),
+    Vec3::new(0.0, 1.0, -1.0),
+    Vec3::new(0.0, -1.0, -1.0),
+];
+
+/// Perlin gradient noise at `p`, roughly from -1 to 1.
+pub fn gradient_noise(p: Vec3, seed: u32) -> f32 {
+    let cell = p.floor();
+    let f = p - cell;
+    let cell = cell.as_ivec3();
+    let fade = f * f * f * (f * (f * 6.0 - 15.0) + 10.0);
+
+    let mut noise = 0.0;
+    for corner in 0..8 {
+        let offset = IVec3::new(corner & 1, (corner >> 1) & 1, (corner >> 2) & 1);
+        let gradient = GRADIENTS[hash(cell + offset, seed) as usize % GRADIENTS.len()];
+        let weight = Vec3::select(offset.cmpeq(IVec3::ONE), fade, Vec3::ONE - fade);
+        noise += weight.x * weight.y * weight.z * gradient.dot(f - offset.as_vec3());
+    }
+    noise
+}
+
+/// Sum of `octaves` of [`gradient_noise`], each of twice the frequency and half the amplitude.
+pub fn fbm(p: Vec3, seed: u32, octaves: u32) -> f32 {
+    let (mut noise, mut amplitude, mut frequency, mut total) = (0.0, 1.0, 1.0, 0.0);
+    for octave in 0..octaves {
+        noise += gradient_noise(p * frequency, seed.wrapping_add(octave)) * amplitude;
+        total += amplitude;
+        amplitude *= 0.5;
+        frequency *= 2.0;
+    }
+    noise / total.max(f32::EPSILON)
+}
@@ -0,0 +1,294 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
+use bevy::{
+    core::FrameCount,
+    prelude::*,
+    render::{
+        extract_resource::{ExtractResource, ExtractResourcePlugin},
+        Render, RenderApp, RenderSet,
+    },
+    utils::HashMap,
+};
+
+use crate::{
+    bundles::volumetric_bundle::MeshCapping,
+    core::mesher::{CpuMesh, CpuMesher},
+    data::{
+        boundary_mode::BoundaryMode,
+        edge_interpolation::EdgeInterpolation,
+        gpu_erosion::GpuErosion,
+        gpu_virtual_volume::GpuVirtualVolume,
+        gpu_voxel_material::{ExtractedVoxelMaterial, GpuVoxelMaterial},
+        meshing_algorithm::MeshingAlgorithm,
+        voxel_material::VoxelMaterialComponents,
+    },
+    render::voxel_mesh_compute_pipeline::DirtyMeshes,
+};
+
+/// Compares the meshes read back from the GPU with the [`CpuMesher`], logging a warning for every
+/// sampled chunk whose vertex or triangle counts, or vertex positions, diverge. Only available
+/// with the `validate-gpu` feature, which adds it to the [`GpuReadbackPlugin`](crate::GpuReadbackPlugin).
+///
+/// Virtual volumes and eroded entities are never sampled, as their voxels on the GPU are not the
+/// ones of their [`VoxelMaterial`](crate::data::voxel_material::VoxelMaterial).
+pub struct GpuValidationPlugin;
+
+impl Plugin for GpuValidationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(ExtractResourcePlugin::<GpuValidationSettings>::default())
+            .init_resource::<GpuValidationSettings>();
+    }
+
+    fn finish(&self, app: &mut App) {
+        app.sub_app_mut(RenderApp)
+            .init_resource::<GpuValidation>()
+            .add_systems(
+                Render,
+                GpuValidation::sample
+                    .in_set(RenderSet::PrepareBindGroups)
+                    .after(DirtyMeshes::select),
+            );
+    }
+}
+
+#[derive(Resource, Clone, Copy, Debug, ExtractResource)]
+pub struct GpuValidationSettings {
+    /// Minimum number of frames between two sampled chunks.
+    pub interval: u32,
+    /// Distance within which a GPU vertex matches a CPU one.
+    pub epsilon: f32,
+}
+
+impl Default for GpuValidationSettings {
+    fn default() -> Self {
+        Self {
+            interval: 60,
+            epsilon: 1e-3,
+        }
+    }
+}
+
+/// The CPU mesh of a sampled chunk, waiting for the readback of the GPU mesh of the same voxels.
+struct PendingValidation {
+    mesh: CpuMesh,
+    /// Dual contouring vertices are not placed by the [`CpuMesher`], only their counts match.
+    compare_positions: bool,
+    epsilon: f32,
+    /// Read along with the first page of the vertices.
+    gpu_index_count: Option<u32>,
+}
+
+/// The meshing settings of a chunk, for the CPU mesher to match the GPU one.
+type MeshingSettingsItem = (
+    Option<&'static MeshingAlgorithm>,
+    Option<&'static BoundaryMode>,
+    Option<&'static EdgeInterpolation>,
+    Has<MeshCapping>,
+);
+
+/// The sampled chunks of the render world.
+#[derive(Resource, Default)]
+pub struct GpuValidation {
+    pending: HashMap<Entity, PendingValidation>,
+    last_sample: Option<(u32, Entity)>,
+}
+
+impl GpuValidation {
+    /// Meshes one of the chunks remeshed this frame on the CPU, at most once per
+    /// [`GpuValidationSettings::interval`] frames and taking turns between chunks.
+    #[allow(clippy::too_many_arguments)]
+    pub fn sample(
+        mut validation: ResMut<Self>,
+        settings: Res<GpuValidationSettings>,
+        frame_count: Res<FrameCount>,
+        dirty_meshes: Res<DirtyMeshes>,
+        extracted_voxel_materials: Res<VoxelMaterialComponents<ExtractedVoxelMaterial>>,
+        gpu_voxel_materials: Res<VoxelMaterialComponents<GpuVoxelMaterial>>,
+        gpu_virtual_volumes: Res<VoxelMaterialComponents<GpuVirtualVolume>>,
+        gpu_erosions: Option<Res<VoxelMaterialComponents<GpuErosion>>>,
+        meshing_query: Query<MeshingSettingsItem>,
+    ) {
+        let validation = validation.as_mut();
+        validation
+            .pending
+            .retain(|entity, _| gpu_voxel_materials.get(entity).is_some());
+
+        if validation
+            .last_sample
+            .is_some_and(|(frame, _)| frame_count.0.wrapping_sub(frame) < settings.interval)
+        {
+            return;
+        }
+
+        let mut candidates = dirty_meshes
+            .meshed()
+            .filter(|entity| {
+                // The CPU mesher doesn't refine detail regions.
+                gpu_voxel_materials
+                    .get(entity)
+                    .is_some_and(|gpu_voxel_material| {
+                        !gpu_voxel_material
+                            .adaptive_resolution_buffer
+                            .get()
+                            .is_refined()
+                    })
+                    && gpu_virtual_volumes.get(entity).is_none()
+                    && gpu_erosions
+                        .as_ref()
+                        .is_none_or(|gpu_erosions| gpu_erosions.get(entity).is_none())
+                    && !validation.pending.contains_key(*entity)
+            })
+            .copied()
+            .collect::<Vec<_>>();
+        candidates.sort();
+
+        let last_entity = validation.last_sample.map(|(_, entity)| entity);
+        let Some(entity) = candidates
+            .iter()
+            .find(|entity| last_entity.is_none_or(|last_entity| **entity > last_entity))
+            .or(candidates.first())
+            .copied()
+        else {
+            return;
+        };
+
+        let (
+            Some(extracted),
+            Some(gpu_voxel_material),
+            Ok((meshing_algorithm, boundary_mode, edge_interpolation, capped)),
+        ) = (
+            extracted_voxel_materials.get(&entity),
+            gpu_voxel_materials.get(&entity),
+            meshing_query.get(entity),
+        )
+        else {
+            return;
+        };
+
+        let meshing_algorithm = meshing_algorithm.copied().unwrap_or_default();
+        let mesh = CpuMesher {
+            voxels: &extracted.voxels,
+            meshing_algorithm: meshing_algorithm.into(),
+            boundary_mode: boundary_mode.copied().unwrap_or_default().into(),
+            capped,
+            clip_planes: gpu_voxel_material.clip_planes_buffer.get().planes(),
+            iso_level: gpu_voxel_material.iso_surface_params_buffer.get().iso_level,
+            edge_interpolation: edge_interpolation.copied().unwrap_or_default().into(),
+        }
+        .mesh();
+
+        validation.pending.insert(
+            entity,
+            PendingValidation {
+                mesh,
+                compare_positions: meshing_algorithm != MeshingAlgorithm::DualContouring,
+                epsilon: settings.epsilon,
+                gpu_index_count: None,
+            },
+        );
+        validation.last_sample = Some((frame_count.0, entity));
+    }
+
+    /// Records the number of indices written by the GPU for `entity`.
+    pub fn record_index_count(&mut self, entity: Entity, index_count: u32) {
+        if let Some(pending) = self.pending.get_mut(&entity) {
+            pending.gpu_index_count = Some(index_count);
+        }
+    }
+
+    /// Compares the vertices read back for `entity`, as `vec3<f32>`s with a 16 byte stride, with
+    /// its pending CPU mesh.
+    pub fn compare(&mut self, entity: Entity, vertices: &[u32]) {
+        let Some(pending) = self.pending.remove(&entity) else {
+            return;
+        };
+
+        let gpu_positions = vertices
+            .chunks_exact(4)
+            .map(|vertex| {
+                Vec3::new(
+                    f32::from_bits(vertex[0]),
+                    f32::from_bits(vertex[1]),
+                    f32::from_bits(vertex[2]),
+                )
+            })
+            .collect::<Vec<_>>();
+        let cpu_triangles = pending.mesh.index_count / 3;
+        let gpu_triangles = pending.gpu_index_count.unwrap_or(0) as usize / 3;
+
+        if gpu_positions.len() != pending.mesh.positions.len() || gpu_triangles != cpu_triangles {
+            warn!(
+                "GPU mesh of {entity} diverges from the CPU mesher: {} vertices and {gpu_triangles} triangles, expected {} vertices and {cpu_triangles} triangles",
+                gpu_positions.len(),
+                pending.mesh.positions.len(),
+            );
+            return;
+        }
+
+        if !pending.compare_positions
+            || position_hash(&gpu_positions, pending.epsilon)
+                == position_hash(&pending.mesh.positions, pending.epsilon)
+        {
+            debug!("GPU mesh of {entity} matches the CPU mesher");
+            return;
+        }
+
+        // Positions close to a rounding boundary may hash differently, match them exactly.
+        let unmatched =
+            unmatched_positions(&pending.mesh.positions, &gpu_positions, pending.epsilon);
+        match unmatched.first() {
+            Some(position) => warn!(
+                "GPU mesh of {entity} diverges from the CPU mesher: {} of {} vertices are further than {} from any GPU vertex, e.g. {position}",
+                unmatched.len(),
+                pending.mesh.positions.len(),
+                pending.epsilon,
+            ),
+            None => debug!("GPU mesh of {entity} matches the CPU mesher"),
+        }
+    }
+}
+
+fn quantize(position: Vec3, epsilon: f32) -> IVec3 {
+    (position / epsilon).round().as_ivec3()
+}
+
+/// A hash of `positions` rounded to `epsilon`, independent of their order.
+fn position_hash(positions: &[Vec3], epsilon: f32) -> u64 {
+    positions.iter().fold(0, |hash, position| {
+        let mut hasher = DefaultHasher::new();
+        quantize(*position, epsilon).hash(&mut hasher);
+        hash.wrapping_add(hasher.finish())
+    })
+}
+
+/// The `expected` positions without a distinct position of `actual` within `epsilon`.
+fn unmatched_positions(expected: &[Vec3], actual: &[Vec3], epsilon: f32) -> Vec<Vec3> {
+    let mut grid = HashMap::<IVec3, Vec<Vec3>>::default();
+    for position in actual {
+        grid.entry(quantize(*position, epsilon))
+            .or_default()
+            .push(*position);
+    }
+
+    let mut unmatched = Vec::new();
+    'expected: for position in expected {
+        let cell = quantize(*position, epsilon);
+        for offset in (0..27).map(|i| IVec3::new(i % 3, (i / 3) % 3, i / 9) - 1) {
+            let Some(candidates) = grid.get_mut(&(cell + offset)) else {
+                continue;
+            };
+            if let Some(index) = candidates
+                .iter()
+                .position(|candidate| candidate.distance(*position) <= epsilon)
+            {
+                candidates.swap_remove(index);
+                continue 'expected;
+            }
+        }
+        unmatched.push(*position);
+    }
+    unmatched
+}
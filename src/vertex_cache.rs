@@ -0,0 +1,282 @@
+use bevy::{
+    core::FrameCount,
+    prelude::*,
+    render::mesh::{Indices, PrimitiveTopology},
+    utils::HashSet,
+};
+
+/// Entries of the simulated post-transform vertex cache.
+const CACHE_SIZE: usize = 32;
+const CACHE_DECAY_POWER: f32 = 1.5;
+/// Score of the vertices of the last triangle, kept lower than the next entries so that strips are
+/// preferred over fans.
+const LAST_TRIANGLE_SCORE: f32 = 0.75;
+const VALENCE_BOOST_SCALE: f32 = 2.0;
+const VALENCE_BOOST_POWER: f32 = 0.5;
+
+/// Score of a vertex at `cache_position` with `remaining` triangles not yet emitted.
+fn vertex_score(cache_position: Option<usize>, remaining: u32) -> f32 {
+    if remaining == 0 {
+        return -1.0;
+    }
+    let cache_score = match cache_position {
+        None => 0.0,
+        Some(position) if position < 3 => LAST_TRIANGLE_SCORE,
+        Some(position) => {
+            let scale = 1.0 / (CACHE_SIZE - 3) as f32;
+            (1.0 - (position - 3) as f32 * scale).powf(CACHE_DECAY_POWER)
+        }
+    };
+    // Vertices left with few triangles are boosted so that they are finished and leave the cache.
+    cache_score + VALENCE_BOOST_SCALE * (remaining as f32).powf(-VALENCE_BOOST_POWER)
+}
+
+/// Reorders the triangles of a triangle list so that consecutive triangles share vertices,
+/// following Tom Forsyth's linear-speed vertex cache optimisation. The triangles and their winding
+/// are kept, a trailing incomplete triangle is left in place.
+pub fn reorder_for_vertex_cache(indices: &mut [u32]) {
+    let triangle_count = indices.len() / 3;
+    if triangle_count < 2 {
+        return;
+    }
+    let vertex_count = indices[..triangle_count * 3]
+        .iter()
+        .max()
+        .map_or(0, |max| *max as usize + 1);
+
+    // The triangles of each vertex, packed by vertex, the first `remaining` still to be emitted.
+    let mut remaining = vec![0u32; vertex_count];
+    for index in &indices[..triangle_count * 3] {
+        remaining[*index as usize] += 1;
+    }
+    let mut offsets = Vec::with_capacity(vertex_count + 1);
+    offsets.push(0usize);
+    for count in &remaining {
+        offsets.push(offsets.last().unwrap() + *count as usize);
+    }
+    let mut vertex_triangles = vec![0u32; triangle_count * 3];
+    let mut filled = vec![0usize; vertex_count];
+    for (triangle, vertices) in indices.chunks_exact(3).enumerate() {
+        for vertex in vertices {
+            let vertex = *vertex as usize;
+            vertex_triangles[offsets[vertex] + filled[vertex]] = triangle as u32;
+            filled[vertex] += 1;
+        }
+    }
+
+    let mut cache_positions = vec![None; vertex_count];
+    let mut scores = remaining
+        .iter()
+        .map(|remaining| vertex_score(None, *remaining))
+        .collect::<Vec<_>>();
+    let triangle_score = |scores: &[f32], triangle: usize| -> f32 {
+        indices[triangle * 3..triangle * 3 + 3]
+            .iter()
+            .map(|vertex| scores[*vertex as usize])
+            .sum()
+    };
+    let mut emitted = vec![false; triangle_count];
+
+    let mut order = Vec::with_capacity(triangle_count);
+    let mut cache = Vec::<u32>::with_capacity(CACHE_SIZE + 3);
+    let mut best = None;
+    let mut cursor = 0;
+
+    while order.len() < triangle_count {
+        // Without a candidate around the cache, restart from the next triangle not yet emitted.
+        let triangle = match best {
+            Some(triangle) => triangle,
+            None => {
+                while emitted[cursor] {
+                    cursor += 1;
+                }
+                cursor
+            }
+        };
+        emitted[triangle] = true;
+        order.push(triangle);
+
+        let vertices = [
+            indices[triangle * 3],
+            indices[triangle * 3 + 1],
+            indices[triangle * 3 + 2],
+        ];
+        for vertex in vertices {
+            let vertex_index = vertex as usize;
+            let start = offsets[vertex_index];
+            let active = &mut vertex_triangles[start..start + remaining[vertex_index] as usize];
+            if let Some(position) = active.iter().position(|other| *other as usize == triangle) {
+                let last = active.len() - 1;
+                active.swap(position, last);
+                remaining[vertex_index] -= 1;
+            }
+        }
+
+        let previous = std::mem::take(&mut cache);
+        cache.extend(vertices);
+        cache.extend(previous.iter().filter(|vertex| !vertices.contains(vertex)));
+        let evicted = cache.split_off(cache.len().min(CACHE_SIZE));
+
+        for vertex in &evicted {
+            cache_positions[*vertex as usize] = None;
+        }
+        for (position, vertex) in cache.iter().enumerate() {
+            cache_positions[*vertex as usize] = Some(position);
+        }
+
+        let mut touched = HashSet::<usize>::default();
+        for vertex in cache.iter().chain(&evicted) {
+            let vertex = *vertex as usize;
+            scores[vertex] = vertex_score(cache_positions[vertex], remaining[vertex]);
+            let start = offsets[vertex];
+            touched.extend(
+                vertex_triangles[start..start + remaining[vertex] as usize]
+                    .iter()
+                    .map(|triangle| *triangle as usize),
+            );
+        }
+
+        best = None;
+        let mut best_score = f32::MIN;
+        for triangle in touched {
+            let score = triangle_score(&scores, triangle);
+            if score > best_score {
+                best_score = score;
+                best = Some(triangle);
+            }
+        }
+    }
+
+    let reordered = order
+        .iter()
+        .flat_map(|triangle| indices[triangle * 3..triangle * 3 + 3].iter().copied())
+        .collect::<Vec<_>>();
+    indices[..triangle_count * 3].copy_from_slice(&reordered);
+}
+
+/// The average number of vertices transformed per triangle by a FIFO post-transform cache of
+/// `cache_size` entries, from 0.5 for a perfect ordering of a large grid to 3.
+pub fn average_cache_miss_ratio(indices: &[u32], cache_size: usize) -> f32 {
+    let triangle_count = indices.len() / 3;
+    if triangle_count == 0 {
+        return 0.0;
+    }
+    let mut cache = std::collections::VecDeque::with_capacity(cache_size);
+    let mut misses = 0;
+    for index in &indices[..triangle_count * 3] {
+        if cache.contains(index) {
+            continue;
+        }
+        misses += 1;
+        if cache.len() == cache_size {
+            cache.pop_front();
+        }
+        cache.push_back(*index);
+    }
+    misses as f32 / triangle_count as f32
+}
+
+/// Reorders the indices of the entity's [`Mesh`] for the post-transform vertex cache once the mesh
+/// has stayed unchanged for `settle_frames`, so that chunks being edited aren't reordered after
+/// every remesh while static chunks draw faster. Only indexed triangle lists are reordered.
+#[derive(Clone, Copy, Component, Debug)]
+pub struct OptimizeVertexCache {
+    pub settle_frames: u32,
+    changed_at: Option<u32>,
+    /// Hash of the indices last written, to tell the reordering apart from a new mesh.
+    optimized: Option<u64>,
+}
+
+impl Default for OptimizeVertexCache {
+    fn default() -> Self {
+        Self {
+            settle_frames: 30,
+            changed_at: None,
+            optimized: None,
+        }
+    }
+}
+
+/// A hash of the indices of `mesh`.
+fn indices_hash(mesh: &Mesh) -> Option<u64> {
+    use std::hash::{Hash, Hasher};
+
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    match mesh.indices()? {
+        Indices::U16(indices) => indices.hash(&mut hasher),
+        Indices::U32(indices) => indices.hash(&mut hasher),
+    }
+    Some(hasher.finish())
+}
+
+impl OptimizeVertexCache {
+    /// Tracks the meshes changed by anything but the reordering and reorders the settled ones.
+    pub fn optimize(
+        frame_count: Res<FrameCount>,
+        mut meshes: ResMut<Assets<Mesh>>,
+        mut mesh_events: EventReader<AssetEvent<Mesh>>,
+        mut optimize_query: Query<(Ref<Handle<Mesh>>, &mut OptimizeVertexCache)>,
+    ) {
+        let modified_meshes = mesh_events
+            .read()
+            .filter_map(|event| match event {
+                AssetEvent::Added { id }
+                | AssetEvent::Modified { id }
+                | AssetEvent::LoadedWithDependencies { id } => Some(*id),
+                _ => None,
+            })
+            .collect::<HashSet<_>>();
+
+        for (mesh, mut optimize_vertex_cache) in optimize_query.iter_mut() {
+            if mesh.is_changed()
+                || optimize_vertex_cache.is_added()
+                || modified_meshes.contains(&mesh.id())
+            {
+                let hash = meshes.get(mesh.id()).and_then(indices_hash);
+                if hash.is_none() || hash != optimize_vertex_cache.optimized {
+                    optimize_vertex_cache.changed_at = Some(frame_count.0);
+                    optimize_vertex_cache.optimized = None;
+                }
+            }
+
+            let Some(changed_at) = optimize_vertex_cache.changed_at else {
+                continue;
+            };
+            if frame_count.0.wrapping_sub(changed_at) < optimize_vertex_cache.settle_frames {
+                continue;
+            }
+            optimize_vertex_cache.changed_at = None;
+
+            let Some(mesh) = meshes.get_mut(mesh.id()) else {
+                continue;
+            };
+            if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+                continue;
+            }
+            match mesh.indices_mut() {
+                Some(Indices::U32(indices)) => reorder_for_vertex_cache(indices),
+                Some(Indices::U16(indices)) => {
+                    let mut wide = indices
+                        .iter()
+                        .map(|index| *index as u32)
+                        .collect::<Vec<_>>();
+                    reorder_for_vertex_cache(&mut wide);
+                    for (index, reordered) in indices.iter_mut().zip(wide) {
+                        *index = reordered as u16;
+                    }
+                }
+                None => continue,
+            }
+            optimize_vertex_cache.optimized = indices_hash(mesh);
+        }
+    }
+}
+
+/// Reorders the indices of the meshes of entities with [`OptimizeVertexCache`].
+pub struct VertexCachePlugin;
+
+impl Plugin for VertexCachePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostUpdate, OptimizeVertexCache::optimize);
+    }
+}
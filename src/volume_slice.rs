@@ -0,0 +1,234 @@
+use bevy::{
+    pbr::{MaterialPipeline, MaterialPipelineKey},
+    prelude::*,
+    render::{
+        mesh::MeshVertexBufferLayoutRef,
+        render_asset::RenderAssetUsages,
+        render_resource::{
+            AsBindGroup, Extent3d, RenderPipelineDescriptor, ShaderRef, ShaderType,
+            SpecializedMeshPipelineError, TextureDimension, TextureFormat,
+        },
+    },
+};
+
+use crate::{data::voxel_material::VoxelMaterial, CHUNK_SZ, CHUNK_SZ_3};
+
+const SHADER_ASSET_PATH: &str = "shaders/volume_slice.wgsl";
+
+/// Maps the densities of a [`VolumeSlice`] to colours.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorMap {
+    #[default]
+    Grayscale,
+    /// Perceptually uniform dark blue to yellow.
+    Viridis,
+    /// Black to red to yellow to white.
+    Hot,
+}
+
+impl ColorMap {
+    fn index(self) -> u32 {
+        match self {
+            Self::Grayscale => 0,
+            Self::Viridis => 1,
+            Self::Hot => 2,
+        }
+    }
+}
+
+/// Draws a plane through the densities of a volumetric entity, to inspect the raw voxels alongside
+/// the extracted surface. The plane is drawn by a [`VolumeSliceQuad`] child entity.
+#[derive(Clone, Copy, Component, Debug)]
+pub struct VolumeSlice {
+    /// A point of the plane, in voxels from the origin of the volume.
+    pub origin: Vec3,
+    pub normal: Dir3,
+    pub color_map: ColorMap,
+    /// The densities mapped to the two ends of the colour map.
+    pub density_range: Vec2,
+}
+
+impl Default for VolumeSlice {
+    fn default() -> Self {
+        Self {
+            origin: Vec3::splat(CHUNK_SZ as f32 * 0.5),
+            normal: Dir3::Z,
+            color_map: ColorMap::default(),
+            density_range: Vec2::new(0.0, 1.0),
+        }
+    }
+}
+
+/// The quad drawing the [`VolumeSlice`] of its parent.
+#[derive(Clone, Copy, Component, Debug)]
+pub struct VolumeSliceQuad;
+
+#[derive(Clone, Copy, Debug, Default, ShaderType)]
+pub struct VolumeSliceParams {
+    pub volume_from_world: Mat4,
+    pub density_range: Vec2,
+    pub color_map: u32,
+}
+
+/// Samples the nearest voxel of a 3D density texture at each fragment of the slice.
+#[derive(Asset, AsBindGroup, Clone, Debug, TypePath)]
+pub struct VolumeSliceMaterial {
+    #[uniform(0)]
+    pub params: VolumeSliceParams,
+    /// One `R32Float` texel per voxel.
+    #[texture(1, dimension = "3d", sample_type = "float", filterable = false)]
+    pub densities: Handle<Image>,
+}
+
+impl Material for VolumeSliceMaterial {
+    fn fragment_shader() -> ShaderRef {
+        SHADER_ASSET_PATH.into()
+    }
+
+    fn specialize(
+        _pipeline: &MaterialPipeline<Self>,
+        descriptor: &mut RenderPipelineDescriptor,
+        _layout: &MeshVertexBufferLayoutRef,
+        _key: MaterialPipelineKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        // The slice is seen from both sides.
+        descriptor.primitive.cull_mode = None;
+        Ok(())
+    }
+}
+
+/// Creates a 3D texture holding the density of every voxel of `voxel_material`. Voxels past the
+/// 32³ of a chunk are left out and missing ones are empty, e.g. for streamed chunks of another
+/// size.
+pub fn density_image(voxel_material: &VoxelMaterial) -> Image {
+    let size = CHUNK_SZ as u32;
+    let data = voxel_material
+        .voxels()
+        .iter()
+        .map(|voxel| voxel.density())
+        .chain(std::iter::repeat(0.0))
+        .take(CHUNK_SZ_3)
+        .flat_map(f32::to_le_bytes)
+        .collect();
+
+    Image::new(
+        Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: size,
+        },
+        TextureDimension::D3,
+        data,
+        TextureFormat::R32Float,
+        RenderAssetUsages::RENDER_WORLD,
+    )
+}
+
+impl VolumeSlice {
+    fn params(&self, volume: &GlobalTransform) -> VolumeSliceParams {
+        VolumeSliceParams {
+            volume_from_world: volume.compute_matrix().inverse(),
+            density_range: self.density_range,
+            color_map: self.color_map.index(),
+        }
+    }
+
+    /// The transform of the quad of the slice within the volume.
+    fn quad_transform(&self) -> Transform {
+        Transform::from_translation(self.origin)
+            .with_rotation(Quat::from_rotation_arc(Vec3::Y, *self.normal))
+    }
+
+    /// Spawns the [`VolumeSliceQuad`] of newly added [`VolumeSlice`]s.
+    pub fn spawn_quads(
+        mut commands: Commands,
+        mut meshes: ResMut<Assets<Mesh>>,
+        mut images: ResMut<Assets<Image>>,
+        mut materials: ResMut<Assets<VolumeSliceMaterial>>,
+        slice_query: Query<
+            (Entity, &VolumeSlice, &VoxelMaterial, &GlobalTransform),
+            Added<VolumeSlice>,
+        >,
+    ) {
+        // Large enough to cut through the whole volume at any angle.
+        let extent = CHUNK_SZ as f32 * 3f32.sqrt();
+
+        for (entity, slice, voxel_material, volume) in slice_query.iter() {
+            let material = materials.add(VolumeSliceMaterial {
+                params: slice.params(volume),
+                densities: images.add(density_image(voxel_material)),
+            });
+
+            commands.entity(entity).with_children(|parent| {
+                parent.spawn((
+                    MaterialMeshBundle {
+                        mesh: meshes.add(Plane3d::default().mesh().size(extent, extent)),
+                        material,
+                        transform: slice.quad_transform(),
+                        ..default()
+                    },
+                    VolumeSliceQuad,
+                ));
+            });
+        }
+    }
+
+    /// Keeps the quads and materials of the slices in sync with their [`VolumeSlice`], volume
+    /// transform and voxels.
+    #[allow(clippy::type_complexity)]
+    pub fn update_quads(
+        mut images: ResMut<Assets<Image>>,
+        mut materials: ResMut<Assets<VolumeSliceMaterial>>,
+        slice_query: Query<(
+            Ref<VolumeSlice>,
+            Ref<VoxelMaterial>,
+            Ref<GlobalTransform>,
+            &Children,
+        )>,
+        mut quad_query: Query<
+            (&mut Transform, &Handle<VolumeSliceMaterial>),
+            With<VolumeSliceQuad>,
+        >,
+    ) {
+        for (slice, voxel_material, volume, children) in slice_query.iter() {
+            let slice_changed = slice.is_changed() || volume.is_changed();
+            if !slice_changed && !voxel_material.is_changed() {
+                continue;
+            }
+
+            let mut quads = quad_query.iter_many_mut(children);
+            while let Some((mut transform, material)) = quads.fetch_next() {
+                let Some(material) = materials.get_mut(material) else {
+                    continue;
+                };
+
+                if slice_changed {
+                    *transform = slice.quad_transform();
+                    material.params = slice.params(&volume);
+                }
+                if voxel_material.is_changed() {
+                    if let Some(image) = images.get_mut(&material.densities) {
+                        *image = density_image(&voxel_material);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Draws the [`VolumeSlice`] of volumetric entities.
+pub struct VolumeSlicePlugin;
+
+impl Plugin for VolumeSlicePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(MaterialPlugin::<VolumeSliceMaterial>::default())
+            .add_systems(
+                PostUpdate,
+                (
+                    VolumeSlice::spawn_quads,
+                    VolumeSlice::update_quads.after(VolumeSlice::spawn_quads),
+                )
+                    .after(TransformSystem::TransformPropagate),
+            );
+    }
+}
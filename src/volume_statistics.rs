@@ -0,0 +1,85 @@
+use bevy::{
+    prelude::*,
+    render::{
+        extract_component::ExtractComponentPlugin, render_graph::RenderGraph, Render, RenderApp,
+        RenderSet,
+    },
+};
+
+use crate::{
+    channels::{ReadbackAppExt, ReadbackChannel, ReadbackPlugin, ReadbackReceived},
+    data::{
+        chunk_retirement::ChunkRetirementExt,
+        gpu_volume_statistics::GpuVolumeStatistics,
+        volume_statistics::{VolumeHistogramData, VolumeStatistics},
+        voxel_material::VoxelMaterialComponents,
+    },
+    render::{
+        volume_statistics_compute_pipeline::{
+            VolumeStatisticsComputeNode, VolumeStatisticsComputeNodeLabel,
+            VolumeStatisticsComputePipeline,
+        },
+        voxel_mesh_compute_pipeline::VoxelMeshComputeNodeLabel,
+    },
+};
+
+/// Computes the [`VolumeStatistics`] of volumetric entities on the GPU whenever their voxels
+/// change, after any simulation has run on them, reading them back through a [`ReadbackChannel`]
+/// of [`VolumeHistogramData`].
+///
+/// Must be added after the [`GpuReadbackPlugin`](crate::GpuReadbackPlugin).
+pub struct VolumeStatisticsPlugin;
+
+impl VolumeStatisticsPlugin {
+    /// Replaces the [`VolumeStatistics`] of the entities whose histogram was read back.
+    pub fn receive(
+        mut readback_received: EventReader<ReadbackReceived<VolumeHistogramData>>,
+        mut statistics_query: Query<&mut VolumeStatistics>,
+    ) {
+        for received in readback_received.read() {
+            let (Some(data), Ok(mut volume_statistics)) = (
+                received.data.first(),
+                statistics_query.get_mut(received.entity),
+            ) else {
+                continue;
+            };
+            *volume_statistics = VolumeStatistics::from_data(data);
+        }
+    }
+}
+
+impl Plugin for VolumeStatisticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            ExtractComponentPlugin::<VolumeStatistics>::default(),
+            ReadbackPlugin::<VolumeHistogramData>::default(),
+        ))
+        .add_readback_producer(GpuVolumeStatistics::map_and_read_buffers)
+        .add_systems(
+            Update,
+            Self::receive.after(ReadbackChannel::<VolumeHistogramData>::receive),
+        );
+    }
+
+    fn finish(&self, app: &mut App) {
+        let render_app = app.sub_app_mut(RenderApp);
+
+        render_app
+            .init_resource::<VolumeStatisticsComputePipeline>()
+            .init_resource::<VoxelMaterialComponents<GpuVolumeStatistics>>()
+            .free_on_retire::<GpuVolumeStatistics>()
+            .add_systems(ExtractSchedule, GpuVolumeStatistics::extract)
+            .add_systems(
+                Render,
+                GpuVolumeStatistics::prepare.in_set(RenderSet::PrepareBindGroups),
+            );
+
+        let statistics_compute_node =
+            VolumeStatisticsComputeNode::from_world(render_app.world_mut());
+
+        let mut render_graph = render_app.world_mut().resource_mut::<RenderGraph>();
+
+        render_graph.add_node(VolumeStatisticsComputeNodeLabel, statistics_compute_node);
+        render_graph.add_node_edge(VoxelMeshComputeNodeLabel, VolumeStatisticsComputeNodeLabel);
+    }
+}
@@ -0,0 +1,71 @@
+use bevy::{
+    prelude::*,
+    render::{render_graph::RenderGraph, Render, RenderApp, RenderSet},
+};
+
+use crate::{
+    channels::{ReadbackAppExt, ReadbackChannel, ReadbackPlugin, ReadbackReceived},
+    data::{
+        chunk_retirement::ChunkRetirementExt,
+        gpu_voxel_picking::GpuVoxelPick,
+        voxel_material::VoxelMaterialComponents,
+        voxel_picking::{VoxelPickData, VoxelPicking},
+    },
+    render::{
+        voxel_mesh_compute_pipeline::VoxelMeshComputeNodeLabel,
+        voxel_picking_compute_pipeline::{
+            VoxelPickingComputeNode, VoxelPickingComputeNodeLabel, VoxelPickingComputePipeline,
+        },
+    },
+};
+
+/// Picks the triangles generated on the GPU with the rays cast by the [`VoxelPicking`], reading
+/// the nearest hit of each entity back through a [`ReadbackChannel`] of [`VoxelPickData`].
+///
+/// Must be added after the [`GpuReadbackPlugin`](crate::GpuReadbackPlugin).
+pub struct VoxelPickingPlugin;
+
+impl VoxelPickingPlugin {
+    /// Keeps the nearest of the hits read back for the latest cast.
+    pub fn receive(
+        mut readback_received: EventReader<ReadbackReceived<VoxelPickData>>,
+        mut picking: ResMut<VoxelPicking>,
+    ) {
+        for received in readback_received.read() {
+            if let Some(data) = received.data.first() {
+                picking.apply(received.entity, data);
+            }
+        }
+    }
+}
+
+impl Plugin for VoxelPickingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(ReadbackPlugin::<VoxelPickData>::default())
+            .init_resource::<VoxelPicking>()
+            .add_readback_producer(GpuVoxelPick::map_and_read_buffers)
+            .add_systems(
+                Update,
+                Self::receive.after(ReadbackChannel::<VoxelPickData>::receive),
+            );
+    }
+
+    fn finish(&self, app: &mut App) {
+        let render_app = app.sub_app_mut(RenderApp);
+
+        render_app
+            .init_resource::<VoxelPickingComputePipeline>()
+            .init_resource::<VoxelMaterialComponents<GpuVoxelPick>>()
+            .free_on_retire::<GpuVoxelPick>()
+            .add_systems(ExtractSchedule, GpuVoxelPick::extract)
+            .add_systems(
+                Render,
+                GpuVoxelPick::prepare.in_set(RenderSet::PrepareBindGroups),
+            );
+
+        let mut render_graph = render_app.world_mut().resource_mut::<RenderGraph>();
+
+        render_graph.add_node(VoxelPickingComputeNodeLabel, VoxelPickingComputeNode);
+        render_graph.add_node_edge(VoxelMeshComputeNodeLabel, VoxelPickingComputeNodeLabel);
+    }
+}
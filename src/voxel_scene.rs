@@ -0,0 +1,187 @@
+use std::{fs, io, path::Path};
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::HashSet,
+};
+
+use crate::{
+    bundles::volumetric_bundle::{Volumetric, VolumetricBundle},
+    core::serialization::{decode_voxels, encode_voxels},
+    data::{
+        chunk_coord::ChunkCoord, voxel::Voxel, voxel_material::VoxelMaterial,
+        voxel_world::VoxelWorldConfig,
+    },
+};
+
+/// The voxels of a chunk as an asset, referenced by the [`SceneChunk`]s of scenes.
+#[derive(Asset, Clone, TypePath)]
+pub struct ChunkVoxels {
+    pub voxels: Vec<Voxel>,
+    pub chunk_size: u32,
+}
+
+impl From<&VoxelMaterial> for ChunkVoxels {
+    fn from(voxel_material: &VoxelMaterial) -> Self {
+        Self {
+            voxels: voxel_material.voxels().to_vec(),
+            chunk_size: voxel_material.chunk_size,
+        }
+    }
+}
+
+impl From<&ChunkVoxels> for VoxelMaterial {
+    fn from(chunk_voxels: &ChunkVoxels) -> Self {
+        Self::new(chunk_voxels.voxels.clone(), chunk_voxels.chunk_size)
+    }
+}
+
+impl ChunkVoxels {
+    /// Writes the voxels to a `.voxels` file read back by the [`ChunkVoxelsLoader`], so that scene
+    /// files reference them by path and the chunks are reloaded whenever the file changes.
+    pub fn write(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, encode_voxels(&self.voxels))
+    }
+}
+
+/// Loads [`ChunkVoxels`] from `.voxels` files of voxels encoded by
+/// [`encode_voxels`](crate::core::serialization::encode_voxels).
+#[derive(Default)]
+pub struct ChunkVoxelsLoader;
+
+impl AssetLoader for ChunkVoxelsLoader {
+    type Asset = ChunkVoxels;
+    type Settings = ();
+    type Error = io::Error;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> io::Result<ChunkVoxels> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let voxels = decode_voxels(&bytes);
+        Ok(ChunkVoxels {
+            chunk_size: voxels.len() as u32,
+            voxels,
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["voxels"]
+    }
+}
+
+/// A chunk of a scene whose voxels are the [`ChunkVoxels`] asset it references. Once the asset is
+/// loaded, the chunk gains a [`VolumetricBundle`], and its [`Transform`] if the scene has none,
+/// and its [`VoxelMaterial`] is replaced every time the asset is modified.
+#[derive(Component, Reflect, Clone, Debug, Default)]
+#[reflect(Component)]
+pub struct SceneChunk {
+    pub voxels: Handle<ChunkVoxels>,
+}
+
+impl SceneChunk {
+    /// A [`DynamicScene`] of the chunks from `min` up to and including `max`, with their reflected
+    /// components, e.g. their [`ChunkCoord`] and [`Transform`], and a [`SceneChunk`] referencing a
+    /// copy of their voxels added to the [`ChunkVoxels`] assets. Spawning it with the
+    /// [`SceneSpawner`] spawns the chunks back once the [`VoxelScenePlugin`] is added.
+    ///
+    /// The copies have no asset path; write them with [`ChunkVoxels::write`] and load them back
+    /// with the [`AssetServer`] for the scene to be serialized.
+    pub fn export_region(world: &mut World, min: ChunkCoord, max: ChunkCoord) -> DynamicScene {
+        let mut chunk_query =
+            world.query_filtered::<(Entity, &ChunkCoord, &VoxelMaterial), With<Volumetric>>();
+        let chunks = chunk_query
+            .iter(world)
+            .filter(|(_, coord, _)| coord.0.cmpge(min.0).all() && coord.0.cmple(max.0).all())
+            .map(|(entity, _, voxel_material)| (entity, ChunkVoxels::from(voxel_material)))
+            .collect::<Vec<_>>();
+
+        let mut chunk_voxels = world.resource_mut::<Assets<ChunkVoxels>>();
+        let chunks = chunks
+            .into_iter()
+            .map(|(entity, voxels)| (entity, chunk_voxels.add(voxels)))
+            .collect::<Vec<_>>();
+
+        let mut scene = DynamicSceneBuilder::from_world(world)
+            .deny::<SceneChunk>()
+            .extract_entities(chunks.iter().map(|(entity, _)| *entity))
+            .build();
+        for dynamic_entity in &mut scene.entities {
+            if let Some((_, voxels)) = chunks
+                .iter()
+                .find(|(entity, _)| *entity == dynamic_entity.entity)
+            {
+                dynamic_entity.components.push(Box::new(SceneChunk {
+                    voxels: voxels.clone(),
+                }));
+            }
+        }
+        scene
+    }
+
+    /// Inserts the voxels of the scene chunks whose asset loaded, and replaces those of the chunks
+    /// whose asset was modified.
+    #[allow(clippy::type_complexity)]
+    pub fn spawn_voxels(
+        mut commands: Commands,
+        config: Res<VoxelWorldConfig>,
+        chunk_voxels: Res<Assets<ChunkVoxels>>,
+        mut chunk_voxels_events: EventReader<AssetEvent<ChunkVoxels>>,
+        chunk_query: Query<(
+            Entity,
+            &SceneChunk,
+            Option<&ChunkCoord>,
+            Has<VoxelMaterial>,
+            Has<Transform>,
+        )>,
+    ) {
+        let modified = chunk_voxels_events
+            .read()
+            .filter_map(|event| match event {
+                AssetEvent::Modified { id } => Some(*id),
+                _ => None,
+            })
+            .collect::<HashSet<_>>();
+
+        for (entity, chunk, coord, has_voxels, has_transform) in chunk_query.iter() {
+            if has_voxels && !modified.contains(&chunk.voxels.id()) {
+                continue;
+            }
+            let Some(voxels) = chunk_voxels.get(&chunk.voxels) else {
+                continue;
+            };
+
+            let voxel_material = VoxelMaterial::from(voxels);
+            if has_voxels {
+                commands.entity(entity).insert(voxel_material);
+                continue;
+            }
+            let mut entity = commands.entity(entity);
+            entity.insert(VolumetricBundle::new(voxel_material));
+            if !has_transform {
+                entity.insert(config.chunk_transform(coord.copied().unwrap_or_default()));
+            }
+        }
+    }
+}
+
+/// Spawns the chunks of scenes exported with [`SceneChunk::export_region`], or authored with
+/// [`SceneChunk`] and [`ChunkCoord`] components, so that voxel content takes part in scene
+/// workflows, editors and hot reloading.
+pub struct VoxelScenePlugin;
+
+impl Plugin for VoxelScenePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<ChunkVoxels>()
+            .init_asset_loader::<ChunkVoxelsLoader>()
+            .init_resource::<VoxelWorldConfig>()
+            .register_type::<SceneChunk>()
+            .register_type::<ChunkCoord>()
+            .add_systems(Update, SceneChunk::spawn_voxels);
+    }
+}
@@ -0,0 +1,225 @@
+use std::{collections::VecDeque, io, sync::Arc, time::Duration};
+
+use bevy::{
+    prelude::*,
+    tasks::{block_on, poll_once, AsyncComputeTaskPool, Task},
+    utils::HashMap,
+};
+
+use crate::{
+    bundles::volumetric_bundle::VolumetricBundle,
+    data::{chunk_coord::ChunkCoord, voxel_world::VoxelWorldConfig},
+    streaming::{ChunkData, ChunkProvider, MAX_FETCH_ATTEMPTS},
+};
+
+/// Sent when a [`WorldGenJob`] starts generating its chunks.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct WorldGenStarted {
+    pub job: Entity,
+    pub origin: ChunkCoord,
+    pub size: u32,
+}
+
+/// Sent every frame a [`WorldGenJob`] spawns chunks.
+#[derive(Event, Clone, Debug)]
+pub struct WorldGenProgress {
+    pub job: Entity,
+    pub completed: usize,
+    pub total: usize,
+    /// Percentage of the chunks spawned, from 0 to 100.
+    pub percent: f32,
+    /// Average number of chunks spawned per second since the job started.
+    pub chunks_per_second: f32,
+}
+
+/// Sent once all the chunks of a [`WorldGenJob`] are spawned or given up on, after which the job
+/// entity is despawned.
+#[derive(Event, Clone, Debug)]
+pub struct WorldGenCompleted {
+    pub job: Entity,
+    pub chunks: usize,
+    /// Chunks given up on after failing to generate [`MAX_FETCH_ATTEMPTS`] times.
+    pub failed: usize,
+    pub elapsed: Duration,
+}
+
+/// Generates the chunks of a cubic region from a [`ChunkProvider`] in the background, spawning a
+/// bounded number of them per frame so that a loading screen keeps running smoothly. Spawn it on
+/// its own entity to start the job and listen for [`WorldGenProgress`] and [`WorldGenCompleted`];
+/// despawning the entity cancels the chunks that are still generating. Chunks that fail to
+/// generate are queued again, up to [`MAX_FETCH_ATTEMPTS`] times.
+#[derive(Component)]
+pub struct WorldGenJob {
+    provider: Arc<dyn ChunkProvider>,
+    /// Maximum number of chunks generated at the same time.
+    pub max_in_flight: usize,
+    /// Maximum number of chunks spawned per frame.
+    pub max_spawns_per_frame: usize,
+    origin: ChunkCoord,
+    size: u32,
+    queued: VecDeque<ChunkCoord>,
+    generating: Vec<(ChunkCoord, Task<io::Result<ChunkData>>)>,
+    generated: VecDeque<(ChunkCoord, ChunkData)>,
+    attempts: HashMap<ChunkCoord, u32>,
+    total: usize,
+    completed: usize,
+    failed: usize,
+    started: Option<Duration>,
+}
+
+impl WorldGenJob {
+    /// Generates the `size`×`size`×`size` chunks starting at `origin`.
+    pub fn new(provider: impl ChunkProvider, origin: ChunkCoord, size: u32) -> Self {
+        Self::from_shared(Arc::new(provider), origin, size)
+    }
+
+    /// Like [`WorldGenJob::new`], with a provider shared with other jobs.
+    pub fn from_shared(provider: Arc<dyn ChunkProvider>, origin: ChunkCoord, size: u32) -> Self {
+        let extent = size as i32;
+        let mut queued = VecDeque::new();
+        for z in 0..extent {
+            for y in 0..extent {
+                for x in 0..extent {
+                    queued.push_back(ChunkCoord(origin.0 + IVec3::new(x, y, z)));
+                }
+            }
+        }
+
+        Self {
+            provider,
+            max_in_flight: 16,
+            max_spawns_per_frame: 4,
+            origin,
+            size,
+            total: queued.len(),
+            queued,
+            generating: Vec::new(),
+            generated: VecDeque::new(),
+            attempts: HashMap::default(),
+            completed: 0,
+            failed: 0,
+            started: None,
+        }
+    }
+
+    /// Fraction of the chunks spawned or given up on, from 0 to 1.
+    pub fn progress(&self) -> f32 {
+        match self.total {
+            0 => 1.0,
+            total => (self.completed + self.failed) as f32 / total as f32,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.completed + self.failed == self.total
+    }
+
+    /// Starts generating queued chunks, spawns those that are ready where the [`VoxelWorldConfig`]
+    /// places them and reports the progress of every job.
+    pub fn update(
+        mut commands: Commands,
+        time: Res<Time<Real>>,
+        config: Res<VoxelWorldConfig>,
+        mut job_query: Query<(Entity, &mut WorldGenJob)>,
+        mut started_events: EventWriter<WorldGenStarted>,
+        mut progress_events: EventWriter<WorldGenProgress>,
+        mut completed_events: EventWriter<WorldGenCompleted>,
+    ) {
+        let task_pool = AsyncComputeTaskPool::get();
+
+        for (job_entity, mut job) in job_query.iter_mut() {
+            let job = job.as_mut();
+            let started = match job.started {
+                Some(started) => started,
+                None => {
+                    started_events.send(WorldGenStarted {
+                        job: job_entity,
+                        origin: job.origin,
+                        size: job.size,
+                    });
+                    *job.started.insert(time.elapsed())
+                }
+            };
+
+            while job.generating.len() < job.max_in_flight.max(1) {
+                let Some(coord) = job.queued.pop_front() else {
+                    break;
+                };
+                let provider = job.provider.clone();
+                let generate = task_pool.spawn(async move { provider.fetch(coord).await });
+                job.generating.push((coord, generate));
+            }
+
+            let (generated, queued) = (&mut job.generated, &mut job.queued);
+            let (attempts, failed) = (&mut job.attempts, &mut job.failed);
+            job.generating.retain_mut(|(coord, generate)| {
+                let Some(result) = block_on(poll_once(generate)) else {
+                    return true;
+                };
+                match result {
+                    Ok(chunk) => generated.push_back((*coord, chunk)),
+                    Err(err) => {
+                        let attempt = attempts.entry(*coord).or_default();
+                        *attempt += 1;
+                        if *attempt < MAX_FETCH_ATTEMPTS {
+                            warn!("Failed to generate chunk {coord:?}, retrying: {err}");
+                            queued.push_back(*coord);
+                        } else {
+                            error!("Failed to generate chunk {coord:?}, giving up: {err}");
+                            *failed += 1;
+                        }
+                    }
+                }
+                false
+            });
+
+            let mut spawned = 0;
+            while spawned < job.max_spawns_per_frame.max(1) {
+                let Some((coord, chunk)) = job.generated.pop_front() else {
+                    break;
+                };
+                commands.spawn((
+                    VolumetricBundle::new(chunk.into()),
+                    coord,
+                    config.chunk_transform(coord),
+                ));
+                spawned += 1;
+            }
+            job.completed += spawned;
+
+            let elapsed = time.elapsed().saturating_sub(started);
+            if spawned > 0 {
+                progress_events.send(WorldGenProgress {
+                    job: job_entity,
+                    completed: job.completed,
+                    total: job.total,
+                    percent: job.progress() * 100.0,
+                    chunks_per_second: job.completed as f32 / elapsed.as_secs_f32().max(1e-3),
+                });
+            }
+
+            if job.is_finished() {
+                completed_events.send(WorldGenCompleted {
+                    job: job_entity,
+                    chunks: job.completed,
+                    failed: job.failed,
+                    elapsed,
+                });
+                commands.entity(job_entity).despawn();
+            }
+        }
+    }
+}
+
+/// Runs the [`WorldGenJob`]s of the world.
+pub struct WorldGenPlugin;
+
+impl Plugin for WorldGenPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<WorldGenStarted>()
+            .add_event::<WorldGenProgress>()
+            .add_event::<WorldGenCompleted>()
+            .init_resource::<VoxelWorldConfig>()
+            .add_systems(PreUpdate, WorldGenJob::update);
+    }
+}
@@ -0,0 +1,353 @@
+use std::{
+    fmt::Write as _,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use bevy::{
+    ecs::system::SystemParam,
+    prelude::*,
+    render::{render_resource::Buffer, Render, RenderApp},
+    utils::HashMap,
+};
+use crossbeam_channel::{Receiver, Sender};
+use serde::Serialize;
+
+use crate::{
+    bundles::volumetric_bundle::Volumetric,
+    channels::RenderWorldSender,
+    data::{
+        chunk_retirement::ChunkRetiring,
+        gpu_chunk_state::GpuChunkState,
+        gpu_voxel_material::GpuVoxelMaterial,
+        iso_surface::DEFAULT_ISO_LEVEL,
+        voxel_material::{VoxelMaterial, VoxelMaterialComponents},
+    },
+};
+
+/// Volumetric entities by their [`GpuChunkState`]. Entities whose state was not mirrored yet are
+/// uninitialized.
+#[derive(Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ChunkStateCounts {
+    pub uninitialized: usize,
+    pub uploading: usize,
+    pub ready: usize,
+    pub error: usize,
+    /// Entities being unloaded, whatever their state, see [`ChunkRetiring`].
+    pub retiring: usize,
+}
+
+/// Bytes of memory held for the volumetric entities, by category.
+#[derive(Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MemoryStatistics {
+    /// The voxels of the [`VoxelMaterial`]s in the main world.
+    pub cpu_voxels: u64,
+    /// The voxel buffers on the GPU.
+    pub gpu_voxels: u64,
+    /// The vertex, attribute and index buffers the meshes are generated into.
+    pub gpu_mesh_outputs: u64,
+    /// The staging buffers the meshes are read back through.
+    pub gpu_staging: u64,
+    /// The lookup tables, counters and parameters of the meshing.
+    pub gpu_other: u64,
+}
+
+/// The solid voxels of one material, the flags of the voxels.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MaterialVoxels {
+    pub material: u32,
+    pub voxels: u64,
+}
+
+/// Aggregate statistics of the volumetric entities of the world, for capacity planning and
+/// tracking performance regressions, exported as JSON or CSV with [`ExportWorldStatistics`].
+/// The GPU memory and mesh counts are those the render world last sent.
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct WorldStatistics {
+    pub chunks: usize,
+    pub chunks_by_state: ChunkStateCounts,
+    pub memory: MemoryStatistics,
+    /// Vertices of the meshes last read back.
+    pub vertices: u64,
+    /// Triangles of the meshes last read back.
+    pub triangles: u64,
+    /// The solid voxels of each material, by ascending material.
+    pub materials: Vec<MaterialVoxels>,
+}
+
+impl WorldStatistics {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("World statistics should serialize to JSON")
+    }
+
+    /// One `statistic,value` row per statistic, with a row per material named after it.
+    pub fn to_csv(&self) -> String {
+        let ChunkStateCounts {
+            uninitialized,
+            uploading,
+            ready,
+            error,
+            retiring,
+        } = self.chunks_by_state;
+        let MemoryStatistics {
+            cpu_voxels,
+            gpu_voxels,
+            gpu_mesh_outputs,
+            gpu_staging,
+            gpu_other,
+        } = self.memory;
+
+        let mut csv = String::from("statistic,value\n");
+        for (statistic, value) in [
+            ("chunks", self.chunks as u64),
+            ("chunks_by_state.uninitialized", uninitialized as u64),
+            ("chunks_by_state.uploading", uploading as u64),
+            ("chunks_by_state.ready", ready as u64),
+            ("chunks_by_state.error", error as u64),
+            ("chunks_by_state.retiring", retiring as u64),
+            ("memory.cpu_voxels", cpu_voxels),
+            ("memory.gpu_voxels", gpu_voxels),
+            ("memory.gpu_mesh_outputs", gpu_mesh_outputs),
+            ("memory.gpu_staging", gpu_staging),
+            ("memory.gpu_other", gpu_other),
+            ("vertices", self.vertices),
+            ("triangles", self.triangles),
+        ] {
+            let _ = writeln!(csv, "{statistic},{value}");
+        }
+        for MaterialVoxels { material, voxels } in &self.materials {
+            let _ = writeln!(csv, "materials.{material},{voxels}");
+        }
+        csv
+    }
+
+    /// Writes the statistics to `path` in `format`.
+    pub fn write(&self, path: impl AsRef<Path>, format: StatisticsFormat) -> io::Result<()> {
+        let contents = match format {
+            StatisticsFormat::Json => self.to_json(),
+            StatisticsFormat::Csv => self.to_csv(),
+        };
+        fs::write(path, contents)
+    }
+}
+
+/// The voxels and state of a chunk, along with whether it is retiring.
+type ChunkStatisticsItem = (
+    Option<&'static VoxelMaterial>,
+    Option<&'static GpuChunkState>,
+    Has<ChunkRetiring>,
+);
+
+/// Gathers the [`WorldStatistics`] on demand. The material distribution goes through every voxel
+/// of the world, so it is best not gathered every frame.
+#[derive(SystemParam)]
+pub struct WorldStatisticsCollector<'w, 's> {
+    gpu_statistics: Res<'w, GpuWorldStatistics>,
+    chunks: Query<'w, 's, ChunkStatisticsItem, With<Volumetric>>,
+}
+
+impl<'w, 's> WorldStatisticsCollector<'w, 's> {
+    /// The statistics of the world, counting the voxels from `iso_level` as solid.
+    pub fn collect(&self, iso_level: f32) -> WorldStatistics {
+        let mut chunks = 0;
+        let mut chunks_by_state = ChunkStateCounts::default();
+        let mut cpu_voxels = 0;
+        let mut materials = HashMap::<u32, u64>::new();
+
+        for (voxel_material, state, retiring) in self.chunks.iter() {
+            chunks += 1;
+            match state {
+                None | Some(GpuChunkState::Uninitialized) => chunks_by_state.uninitialized += 1,
+                Some(GpuChunkState::Uploading) => chunks_by_state.uploading += 1,
+                Some(GpuChunkState::Ready) => chunks_by_state.ready += 1,
+                Some(GpuChunkState::Error(_)) => chunks_by_state.error += 1,
+            }
+            if retiring {
+                chunks_by_state.retiring += 1;
+            }
+
+            let Some(voxel_material) = voxel_material else {
+                continue;
+            };
+            cpu_voxels += std::mem::size_of_val(voxel_material.voxels()) as u64;
+            for voxel in voxel_material.voxels().iter() {
+                if voxel.density() >= iso_level {
+                    *materials.entry(voxel.flags()).or_default() += 1;
+                }
+            }
+        }
+
+        let mut materials: Vec<_> = materials
+            .into_iter()
+            .map(|(material, voxels)| MaterialVoxels { material, voxels })
+            .collect();
+        materials.sort_unstable_by_key(|material| material.material);
+
+        let gpu = &self.gpu_statistics;
+        WorldStatistics {
+            chunks,
+            chunks_by_state,
+            memory: MemoryStatistics {
+                cpu_voxels,
+                gpu_voxels: gpu.voxel_bytes,
+                gpu_mesh_outputs: gpu.mesh_output_bytes,
+                gpu_staging: gpu.staging_bytes,
+                gpu_other: gpu.other_bytes,
+            },
+            vertices: gpu.vertices,
+            triangles: gpu.triangles,
+            materials,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StatisticsFormat {
+    #[default]
+    Json,
+    Csv,
+}
+
+/// Requests writing the [`WorldStatistics`] to `path`.
+#[derive(Event, Clone, Debug)]
+pub struct ExportWorldStatistics {
+    pub path: PathBuf,
+    pub format: StatisticsFormat,
+}
+
+impl ExportWorldStatistics {
+    pub fn json(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            format: StatisticsFormat::Json,
+        }
+    }
+
+    pub fn csv(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            format: StatisticsFormat::Csv,
+        }
+    }
+
+    /// Gathers the statistics once for the requested exports and writes them.
+    pub fn export(mut exports: EventReader<Self>, collector: WorldStatisticsCollector) {
+        if exports.is_empty() {
+            return;
+        }
+        let statistics = collector.collect(DEFAULT_ISO_LEVEL);
+        for export in exports.read() {
+            if let Err(err) = statistics.write(&export.path, export.format) {
+                warn!(
+                    "Failed to export the world statistics to {}: {err}",
+                    export.path.display()
+                );
+            }
+        }
+    }
+}
+
+/// The statistics of the volumetric entities measured in the render world, last sent to the main
+/// world.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GpuWorldStatistics {
+    pub voxel_bytes: u64,
+    pub mesh_output_bytes: u64,
+    pub staging_bytes: u64,
+    pub other_bytes: u64,
+    pub vertices: u64,
+    pub triangles: u64,
+}
+
+impl GpuWorldStatistics {
+    /// Adds the buffers and the mesh counts of `gpu_voxel_material`.
+    fn add(&mut self, gpu_voxel_material: &GpuVoxelMaterial) {
+        let size = |buffer: Option<&Buffer>| buffer.map_or(0, |buffer| buffer.size());
+        let total = gpu_voxel_material.gpu_bytes();
+
+        let voxel_bytes = size(gpu_voxel_material.voxels_buffer.buffer());
+        let mesh_output_bytes = [
+            gpu_voxel_material.vertices_buffer.buffer(),
+            gpu_voxel_material.normals_buffer.buffer(),
+            gpu_voxel_material.uvs_buffer.buffer(),
+            gpu_voxel_material.tangents_buffer.buffer(),
+            gpu_voxel_material.emissives_buffer.buffer(),
+            gpu_voxel_material.indices_buffer.buffer(),
+        ]
+        .into_iter()
+        .map(size)
+        .sum::<u64>();
+        let staging_bytes = gpu_voxel_material.vertices_staging_buffer.size()
+            + gpu_voxel_material.atomics_staging_buffer.size();
+
+        self.voxel_bytes += voxel_bytes;
+        self.mesh_output_bytes += mesh_output_bytes;
+        self.staging_bytes += staging_bytes;
+        self.other_bytes += total.saturating_sub(voxel_bytes + mesh_output_bytes + staging_bytes);
+
+        if let Some(mesh_counts) = gpu_voxel_material.mesh_counts {
+            self.vertices += mesh_counts.vertices as u64;
+            self.triangles += mesh_counts.indices as u64 / 3;
+        }
+    }
+}
+
+#[derive(Resource, Deref)]
+pub struct GpuWorldStatisticsReceiver(pub Receiver<GpuWorldStatistics>);
+
+impl GpuWorldStatisticsReceiver {
+    /// Keeps the statistics the render world last sent.
+    pub fn receive(receiver: Res<Self>, mut gpu_statistics: ResMut<GpuWorldStatistics>) {
+        if let Some(statistics) = receiver.try_iter().last() {
+            *gpu_statistics = statistics;
+        }
+    }
+}
+
+#[derive(Resource, Deref)]
+pub struct GpuWorldStatisticsSender(pub Sender<GpuWorldStatistics>);
+
+impl GpuWorldStatisticsSender {
+    /// Measures the GPU memory and the meshes of every volumetric entity, once their readbacks for
+    /// the frame are done.
+    pub fn measure(
+        sender: Res<Self>,
+        gpu_voxel_materials: Res<VoxelMaterialComponents<GpuVoxelMaterial>>,
+    ) {
+        let mut statistics = GpuWorldStatistics::default();
+        for gpu_voxel_material in gpu_voxel_materials.0.values() {
+            statistics.add(gpu_voxel_material);
+        }
+        let _ = sender.send(statistics);
+    }
+}
+
+/// Exports the [`WorldStatistics`] on [`ExportWorldStatistics`] requests. Must be added after the
+/// [`GpuReadbackPlugin`](crate::GpuReadbackPlugin).
+pub struct WorldStatisticsPlugin;
+
+impl Plugin for WorldStatisticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GpuWorldStatistics>()
+            .add_event::<ExportWorldStatistics>()
+            .add_systems(
+                Update,
+                (
+                    GpuWorldStatisticsReceiver::receive,
+                    ExportWorldStatistics::export.after(GpuWorldStatisticsReceiver::receive),
+                ),
+            );
+    }
+
+    fn finish(&self, app: &mut App) {
+        let (s, r) = crossbeam_channel::unbounded();
+        app.insert_resource(GpuWorldStatisticsReceiver(r));
+
+        app.sub_app_mut(RenderApp)
+            .insert_resource(GpuWorldStatisticsSender(s))
+            .add_systems(
+                Render,
+                GpuWorldStatisticsSender::measure.after(RenderWorldSender::map_and_read_buffer),
+            );
+    }
+}